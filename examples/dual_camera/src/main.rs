@@ -0,0 +1,122 @@
+use std::error::Error;
+use std::sync::Arc;
+
+use minifb::{Window, WindowOptions};
+use tokio::sync::mpsc;
+use win_video::devices::{Camera, Cameras, DeviceConfig, camera::Output};
+use win_video::i_capture::ICapture;
+
+/// Shows two cameras at the same time (a face cam and a document cam for example), each in its own window.
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let cameras = unsafe { Cameras::new()? };
+
+    if cameras.len() < 2 {
+        eprintln!("Two cameras are needed, found {}", cameras.len());
+        return Ok(());
+    }
+
+    for info in cameras.infos() {
+        println!("{}: {}", info.index, info.friendly_name);
+    }
+
+    let config = DeviceConfig::new(Output::RGB32, 1280, 720, 30);
+
+    let first = cameras.activate(0, config)?;
+    let second = cameras.activate(1, config)?;
+
+    //the cameras keep their own activation, the enumerated devices are released here
+    drop(cameras);
+
+    //every camera has its own receiver and capture loop, the sessions stop them when they are dropped
+    let first_frames = display_frames(&first);
+    let second_frames = display_frames(&second);
+
+    let first_session = first.clone().start_session();
+    let second_session = second.clone().start_session();
+
+    let mut first_window = CameraWindow::new("Camera 0", &first, first_frames);
+    let mut second_window = CameraWindow::new("Camera 1", &second, second_frames);
+
+    while first_window.is_open() && second_window.is_open() {
+        first_window.update()?;
+        second_window.update()?;
+    }
+
+    first_session.stop().await?;
+    second_session.stop().await?;
+
+    Ok(())
+}
+
+/// Converts the BGRA frames of the camera to the 0RGB pixels minifb shows, in a task of its own.
+fn display_frames(camera: &Arc<Camera>) -> mpsc::Receiver<Vec<u32>> {
+    let (tx, rx) = mpsc::channel::<Vec<u32>>(2);
+    let receiver = camera.clone_receiver();
+
+    tokio::spawn(async move {
+        let mut receiver = receiver.lock().await;
+
+        while let Some(frame) = receiver.recv().await {
+            let pixels = frame
+                .data
+                .chunks_exact(4)
+                .map(|bgra| ((bgra[2] as u32) << 16) | ((bgra[1] as u32) << 8) | bgra[0] as u32)
+                .collect();
+
+            //a window that is behind only needs the newest frame
+            let _ = tx.try_send(pixels);
+        }
+    });
+
+    rx
+}
+
+/// A window showing the newest frame of one camera.
+struct CameraWindow {
+    window: Window,
+    frames: mpsc::Receiver<Vec<u32>>,
+    current_frame: Vec<u32>,
+    width: usize,
+    height: usize,
+}
+
+impl CameraWindow {
+    fn new(title: &str, camera: &Camera, frames: mpsc::Receiver<Vec<u32>>) -> Self {
+        let dimensions = camera.dimensions();
+        let (width, height) = (dimensions.width as usize, dimensions.height as usize);
+
+        let mut opts = WindowOptions::default();
+        opts.resize = true;
+        opts.scale_mode = minifb::ScaleMode::AspectRatioStretch;
+
+        let mut window = Window::new(title, width, height, opts).expect("Could not open the window!");
+        window.set_target_fps(30);
+
+        Self {
+            window,
+            frames,
+            current_frame: vec![0u32; width * height],
+            width,
+            height,
+        }
+    }
+
+    fn is_open(&self) -> bool {
+        self.window.is_open() && !self.window.is_key_down(minifb::Key::Escape)
+    }
+
+    fn update(&mut self) -> Result<(), Box<dyn Error>> {
+        while let Ok(frame) = self.frames.try_recv() {
+            //frames of another size (after a reconnect) are skipped
+            if frame.len() == self.width * self.height {
+                self.current_frame = frame;
+            }
+        }
+
+        self.window
+            .update_with_buffer(&self.current_frame, self.width, self.height)?;
+
+        Ok(())
+    }
+}
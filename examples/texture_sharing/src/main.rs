@@ -0,0 +1,137 @@
+use std::error::Error;
+
+use minifb::{Window, WindowOptions};
+use win_video::devices::{Monitor, PixelFormat};
+use win_video::i_gpu_capture::IGpuCapture;
+use windows::Win32::Foundation::{HMODULE, HWND};
+use windows::Win32::Graphics::Direct3D::D3D_DRIVER_TYPE_HARDWARE;
+use windows::Win32::Graphics::Direct3D11::{
+    D3D11_CREATE_DEVICE_BGRA_SUPPORT, D3D11_SDK_VERSION, D3D11CreateDeviceAndSwapChain,
+    ID3D11Device, ID3D11DeviceContext, ID3D11Texture2D,
+};
+use windows::Win32::Graphics::Dxgi::Common::{
+    DXGI_FORMAT_B8G8R8A8_UNORM, DXGI_MODE_DESC, DXGI_SAMPLE_DESC,
+};
+use windows::Win32::Graphics::Dxgi::{
+    DXGI_PRESENT, DXGI_SWAP_CHAIN_DESC, DXGI_SWAP_EFFECT_FLIP_DISCARD,
+    DXGI_USAGE_RENDER_TARGET_OUTPUT, IDXGIKeyedMutex, IDXGISwapChain,
+};
+use windows::core::Interface;
+
+/// Shows a monitor in a window without the frames ever leaving the GPU.
+///
+/// The monitor textures are opened on a second D3D11 device (the one owning the swap chain) through their shared handle
+/// and copied straight into the back buffer.
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let monitor = unsafe { Monitor::from_monitor(0)? };
+
+    //the swap chain is 8 bit, HDR surfaces are delivered raw on the GPU
+    if monitor.surface_format() != PixelFormat::Bgra8 {
+        eprintln!("This example only supports SDR monitors.");
+        return Ok(());
+    }
+
+    let dimensions = monitor.get_texture_dimensions();
+    let (width, height) = (dimensions.width as usize, dimensions.height as usize);
+
+    let mut opts = WindowOptions::default();
+    opts.resize = true;
+    let mut window = Window::new("Texture Sharing", width / 2, height / 2, opts)?;
+    window.set_target_fps(60);
+
+    let hwnd = HWND(window.get_window_handle());
+    let (device, context, swap_chain) =
+        unsafe { create_swap_chain(hwnd, dimensions.width, dimensions.height)? };
+
+    // start capturing textures on a different future
+    let capture = monitor.clone();
+    tokio::spawn(async move {
+        capture
+            .start_gpu_capturing()
+            .await
+            .expect("Could not capture, failed.");
+    });
+
+    let gpu_receiver = monitor.clone_gpu_receiver();
+
+    while window.is_open() && !window.is_key_pressed(minifb::Key::Escape, minifb::KeyRepeat::Yes) {
+        //pump the window messages, the swap chain does the drawing
+        window.update();
+
+        let Ok(frame) = gpu_receiver.lock().await.try_recv() else {
+            tokio::task::yield_now().await;
+            continue;
+        };
+
+        unsafe {
+            //open the texture of the capture device on our device
+            let mut opened: Option<ID3D11Texture2D> = None;
+            device.OpenSharedResource(frame.shared_handle()?, &mut opened)?;
+            let opened = opened.unwrap();
+
+            //the keyed mutex is shared with the texture of the capture device
+            let keyed_mutex: IDXGIKeyedMutex = opened.cast()?;
+            let back_buffer: ID3D11Texture2D = swap_chain.GetBuffer(0)?;
+
+            keyed_mutex.AcquireSync(0, u32::MAX)?;
+            context.CopyResource(&back_buffer, &opened);
+            keyed_mutex.ReleaseSync(0)?;
+
+            swap_chain.Present(1, DXGI_PRESENT(0)).ok()?;
+        }
+    }
+
+    //stop capturing the screen
+    monitor.stop_gpu_capturing().await?;
+
+    Ok(())
+}
+
+// creates a device with a swap chain the size of the monitor that presents to the window
+unsafe fn create_swap_chain(
+    hwnd: HWND,
+    width: u32,
+    height: u32,
+) -> Result<(ID3D11Device, ID3D11DeviceContext, IDXGISwapChain), Box<dyn Error>> {
+    let desc = DXGI_SWAP_CHAIN_DESC {
+        BufferDesc: DXGI_MODE_DESC {
+            Width: width,
+            Height: height,
+            Format: DXGI_FORMAT_B8G8R8A8_UNORM,
+            ..Default::default()
+        },
+        SampleDesc: DXGI_SAMPLE_DESC {
+            Count: 1,
+            Quality: 0,
+        },
+        BufferUsage: DXGI_USAGE_RENDER_TARGET_OUTPUT,
+        BufferCount: 2,
+        OutputWindow: hwnd,
+        Windowed: true.into(),
+        SwapEffect: DXGI_SWAP_EFFECT_FLIP_DISCARD,
+        Flags: 0,
+    };
+
+    let mut swap_chain = None;
+    let mut device = None;
+    let mut context = None;
+
+    unsafe {
+        D3D11CreateDeviceAndSwapChain(
+            None,
+            D3D_DRIVER_TYPE_HARDWARE,
+            HMODULE::default(),
+            D3D11_CREATE_DEVICE_BGRA_SUPPORT,
+            None,
+            D3D11_SDK_VERSION,
+            Some(&desc),
+            Some(&mut swap_chain),
+            Some(&mut device),
+            None,
+            Some(&mut context),
+        )?;
+    }
+
+    Ok((device.unwrap(), context.unwrap(), swap_chain.unwrap()))
+}
@@ -1,13 +1,13 @@
 use std::error::Error;
 use std::sync::Arc;
 
-use enc_video::devices::ActivatedDevice;
-use enc_video::devices::{VideoDevices, activated_device::Output};
-use enc_video::i_capture::ICapture;
-use enc_video::monitor::Monitor;
+use win_video::capture_error::CaptureError;
+use win_video::convert;
+use win_video::devices::{Backpressure, Cameras, DeviceConfig, Monitor, camera::Output};
+use win_video::dyn_capture::DynCapture;
+use win_video::i_capture::ICapture;
 use minifb::{Window, WindowOptions};
 use tokio::sync::mpsc;
-use windows::Win32::System::Com::{COINIT_MULTITHREADED, CoInitializeEx};
 
 /// Determines if the camera or monior will run
 pub enum CaptureType {
@@ -21,51 +21,69 @@ async fn main() -> Result<(), Box<dyn Error>> {
     //this could easily be changed to camera.
     let capture_type = CaptureType::Monitor(0);
 
-    let capture = Arc::new(get_capture(capture_type));
+    let capture = match get_capture(capture_type) {
+        Ok(capture) => capture,
+        Err(e) => {
+            //another process may be holding the duplication, let the user know instead of panicking
+            if let Some(CaptureError::DuplicationUnavailable) = e.downcast_ref::<CaptureError>() {
+                eprintln!("The monitor cannot be captured right now: {e}");
+                return Ok(());
+            }
+
+            return Err(e);
+        }
+    };
     let dimensions = capture.get_dimensions()?;
     let (width, height) = (dimensions.width as usize, dimensions.height as usize);
 
+    //cameras activated with NV12 or YUY2 deliver YUV, which is converted to BGRA before it is displayed
+    let format = capture.get_output_format();
+
     //create channel to send converted frame data to the main thread.
     let (tx, mut rx) = mpsc::channel::<Vec<u32>>(2);
 
     // task to capture and convert raw data from the camera or window
-    let recv = capture.clone_receiver().clone();
+    //the task is the only consumer, so it owns the receiver instead of locking the shared one for every frame
+    let mut recv = capture.take_receiver()?;
     tokio::spawn(async move {
-        loop {
-            //get the data before the conversion and drop right after
-            let data = {
-                let mut guard = recv.lock().await;
-                guard.recv().await
-            };
+        while let Some(frame) = recv.recv().await {
+            if frame.is_empty() {
+                continue;
+            }
 
-            if let Some(raw_data) = data {
-                if raw_data.is_empty() {
-                    continue;
+            //the converted frames are tightly packed, the frames of the capture may pad their rows
+            let bgra;
+            let (raw_data, stride) = match format {
+                Output::NV12 => {
+                    bgra = match convert::nv12_to_bgra(&frame.data, dimensions.width, dimensions.height) {
+                        Ok(bgra) => bgra,
+                        Err(_) => continue,
+                    };
+                    (&bgra, 0)
                 }
-
-                //convert to u32
-                let mut frame_u32 = vec![0u32; width * height];
-                for i in 0..width * height {
-                    let b = raw_data[i * 4] as u32;
-                    let g = raw_data[i * 4 + 1] as u32;
-                    let r = raw_data[i * 4 + 2] as u32;
-                    frame_u32[i] = (r << 16) | (g << 8) | b;
+                Output::YUY2 => {
+                    bgra = match convert::yuy2_to_bgra(&frame.data, dimensions.width, dimensions.height) {
+                        Ok(bgra) => bgra,
+                        Err(_) => continue,
+                    };
+                    (&bgra, 0)
                 }
+                _ => (&frame.data, frame.stride),
+            };
 
-                //try sending, we do not need to send the data, as we can afford to lose frames
-                let _ = tx.try_send(frame_u32);
-            } else {
-                break;
-            }
+            //convert to u32
+            let Ok(frame_u32) = convert::bgra_to_0rgb(raw_data, dimensions.width, dimensions.height, stride) else {
+                continue;
+            };
+
+            //the window only shows the latest frame, a frame it has not picked up yet can be lost
+            let _ = tx.try_send(frame_u32);
         }
     });
 
     // start capturing data on a different future
     let capture_clone = capture.clone();
     tokio::spawn(async move {
-        //deref get capture ref to Arc<dynIcapture> and clone it
-        let capture_clone = (*capture_clone).as_ref().clone();
-
         capture_clone
             .start_capturing()
             .await
@@ -87,42 +105,35 @@ async fn main() -> Result<(), Box<dyn Error>> {
     }
 
     //stop capturing the screen
-    (*capture).as_ref().clone().stop_capturing().await?;
+    capture.stop_capturing().await?;
 
     Ok(())
 }
 
-/// This function is not really used within this, but shows how you can return an ICapture which is capable of being interchangeable with the Monitor and or VideoDevice.
+/// This function is not really used within this, but shows how you can return a DynCapture which is capable of being interchangeable with the Monitor and or VideoDevice.
 /// This allows you to use the same code in the main whether you use a Monitor or Camera.
-fn get_capture(cap_type: CaptureType) -> Box<Arc<dyn ICapture<CaptureOutput = Vec<u8>>>> {
+fn get_capture(cap_type: CaptureType) -> Result<Arc<dyn DynCapture>, Box<dyn Error>> {
     match cap_type {
         CaptureType::Monitor(id) => {
             let monitor: Arc<Monitor>;
 
             unsafe {
-                monitor = Monitor::from_monitor(id).expect("Could not get monitor {id}");
+                monitor = Monitor::from_monitor(id)?;
             }
 
-            Box::new(monitor)
+            //a live preview wants the newest frame, the monitor drops the old ones instead of waiting for the window
+            monitor.set_backpressure(Backpressure::DropOldest);
+
+            Ok(monitor)
         }
         CaptureType::Camera => {
-            let device: Arc<ActivatedDevice>;
-
-            unsafe {
-                let hr = CoInitializeEx(None, COINIT_MULTITHREADED);
+            let video_devices = unsafe { Cameras::new()? };
 
-                if hr != windows::Win32::Foundation::S_OK {
-                    panic!("Could not initialize CoInit with error {hr}");
-                }
-
-                let video_devices = VideoDevices::new().expect("Could not aggregate video devices");
-
-                device = video_devices
-                    .activate_device(video_devices.devices[0], Some(Output::RGB32))
-                    .expect("Could not activate device.");
-            }
+            //an index without a camera is a DeviceIndexOutOfRange error, not a panic
+            let device = video_devices.activate(0, DeviceConfig::new(Output::RGB32, 1280, 720, 30))?;
+            device.set_backpressure(Backpressure::DropOldest);
 
-            Box::new(device)
+            Ok(device)
         }
     }
 }
@@ -0,0 +1,116 @@
+pub mod histogram;
+pub mod motion_detector;
+
+pub use crate::analysis::histogram::{HistogramChannel, HistogramStream, compute_histogram};
+pub use crate::analysis::motion_detector::{MotionDetector, MotionEvent};
+
+use crate::devices::Rect;
+
+/// # Frame Delta
+///
+/// What changed between two BGRA frames, returned by frame_delta.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FrameDelta {
+    /// The number of pixels with a different color (any of the blue, green or red channels, by more than the tolerance).
+    pub changed_pixels: u32,
+
+    /// The bounding box of the changed pixels, None when no pixel changed.
+    pub dirty_region: Option<Rect>,
+
+    /// The absolute difference of every channel as a BGRA image of the frame size (alpha is 255), only filled by frame_delta_with_image.
+    pub diff_image: Option<Vec<u8>>,
+}
+
+impl FrameDelta {
+    /// # Is Unchanged
+    ///
+    /// If no pixel changed.
+    pub fn is_unchanged(&self) -> bool {
+        self.changed_pixels == 0
+    }
+}
+
+/// # Frame Delta
+///
+/// Compares two tightly packed BGRA frames of the same size pixel by pixel, for motion analysis or sending only what changed.
+///
+/// Pixels past the end of the shorter frame are not compared. Monitors already report what changed in Frame::dirty_rects (from Desktop Duplication),
+/// which is much cheaper than comparing every pixel, this is for captures without change information such as cameras.
+pub fn frame_delta(prev: &[u8], curr: &[u8], width: u32, height: u32) -> FrameDelta {
+    compare(prev, curr, width, height, 0, false)
+}
+
+/// # Frame Delta With Tolerance
+///
+/// Same as frame_delta, a pixel only counts as changed when a channel differs by more than tolerance.
+///
+/// Camera frames are never identical because of sensor noise, a tolerance of a few levels (such as 16) ignores the noise.
+pub fn frame_delta_with_tolerance(
+    prev: &[u8],
+    curr: &[u8],
+    width: u32,
+    height: u32,
+    tolerance: u8,
+) -> FrameDelta {
+    compare(prev, curr, width, height, tolerance, false)
+}
+
+/// # Frame Delta With Image
+///
+/// Same as frame_delta, also filling diff_image with the absolute difference of the frames.
+pub fn frame_delta_with_image(prev: &[u8], curr: &[u8], width: u32, height: u32) -> FrameDelta {
+    compare(prev, curr, width, height, 0, true)
+}
+
+// compares the pixels row by row, growing the bounding box of the changed pixels
+fn compare(
+    prev: &[u8],
+    curr: &[u8],
+    width: u32,
+    height: u32,
+    tolerance: u8,
+    with_image: bool,
+) -> FrameDelta {
+    let (width, height) = (width as usize, height as usize);
+    let pixels = (width * height).min(prev.len() / 4).min(curr.len() / 4);
+
+    let mut diff_image = with_image.then(|| vec![0; width * height * 4]);
+    let mut changed_pixels = 0;
+
+    //left, top, right, bottom of the changed pixels, right and bottom are exclusive
+    let mut bounds: Option<(usize, usize, usize, usize)> = None;
+
+    for index in 0..pixels {
+        let offset = index * 4;
+        let (a, b) = (&prev[offset..offset + 4], &curr[offset..offset + 4]);
+
+        if let Some(image) = diff_image.as_mut() {
+            for channel in 0..3 {
+                image[offset + channel] = a[channel].abs_diff(b[channel]);
+            }
+
+            image[offset + 3] = 255;
+        }
+
+        if (0..3).all(|channel| a[channel].abs_diff(b[channel]) <= tolerance) {
+            continue;
+        }
+
+        changed_pixels += 1;
+
+        let (x, y) = (index % width, index / width);
+
+        bounds = Some(match bounds {
+            Some((left, top, right, bottom)) => {
+                (left.min(x), top.min(y), right.max(x + 1), bottom.max(y + 1))
+            }
+            None => (x, y, x + 1, y + 1),
+        });
+    }
+
+    FrameDelta {
+        changed_pixels,
+        dirty_region: bounds.map(Rect::from_tuple),
+        diff_image,
+    }
+}
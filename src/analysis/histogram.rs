@@ -0,0 +1,167 @@
+use std::{
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, ready},
+};
+
+use futures_core::Stream;
+
+use crate::{
+    capture_error::CaptureError,
+    devices::{Frame, camera::Output},
+    dyn_capture::DynCapture,
+    frame_stream::FrameStream,
+    i_capture::ICapture,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// # Histogram Channel
+///
+/// Which values compute_histogram counts.
+pub enum HistogramChannel {
+    Red,
+    Green,
+    Blue,
+
+    /// The brightness, BT.601 weighted red, green and blue for RGB frames and the Y plane as is for NV12 and YUY2.
+    Luma,
+
+    /// Red, green and blue one after another, 768 entries.
+    All,
+}
+
+/// # Compute Histogram
+///
+/// Counts how many pixels of a frame have each value (0 to 255) of the channel, for exposure correction or scene change detection.
+///
+/// The histogram has 256 entries, 768 for HistogramChannel::All (the red, then the green, then the blue counts).
+/// RGB32 frames support every channel, NV12 and YUY2 only Luma and RGB10A2 counts the upper 8 bits of each channel.
+/// The frame must be tightly packed.
+///
+/// Returns CaptureError::UnsupportedFormat for the other formats and channels.
+pub fn compute_histogram(
+    frame: &[u8],
+    format: Output,
+    channel: HistogramChannel,
+) -> Result<Vec<u32>, CaptureError> {
+    let entries = match channel {
+        HistogramChannel::All => 768,
+        _ => 256,
+    };
+
+    let mut histogram = vec![0u32; entries];
+
+    match (format, channel) {
+        (Output::RGB32, _) => {
+            for pixel in frame.chunks_exact(4) {
+                count(&mut histogram, channel, [pixel[2], pixel[1], pixel[0]]);
+            }
+        }
+        (Output::RGB10A2, _) => {
+            for pixel in frame.chunks_exact(4) {
+                let packed = u32::from_le_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]);
+
+                //red is in the lowest 10 bits
+                let rgb = [0, 10, 20].map(|shift| ((packed >> (shift + 2)) & 0xFF) as u8);
+                count(&mut histogram, channel, rgb);
+            }
+        }
+        (Output::NV12, HistogramChannel::Luma) => {
+            //the Y plane is two thirds of the frame
+            for y in &frame[..frame.len() / 3 * 2] {
+                histogram[*y as usize] += 1;
+            }
+        }
+        (Output::YUY2, HistogramChannel::Luma) => {
+            for y in frame.iter().step_by(2) {
+                histogram[*y as usize] += 1;
+            }
+        }
+        _ => {
+            return Err(CaptureError::UnsupportedFormat(format!(
+                "a {channel:?} histogram of {format:?} frames"
+            )));
+        }
+    }
+
+    Ok(histogram)
+}
+
+// counts a single red, green, blue pixel
+fn count(histogram: &mut [u32], channel: HistogramChannel, [r, g, b]: [u8; 3]) {
+    match channel {
+        HistogramChannel::Red => histogram[r as usize] += 1,
+        HistogramChannel::Green => histogram[g as usize] += 1,
+        HistogramChannel::Blue => histogram[b as usize] += 1,
+        HistogramChannel::Luma => {
+            let luma = (77 * r as u32 + 150 * g as u32 + 29 * b as u32) >> 8;
+            histogram[luma as usize] += 1;
+        }
+        HistogramChannel::All => {
+            histogram[r as usize] += 1;
+            histogram[256 + g as usize] += 1;
+            histogram[512 + b as usize] += 1;
+        }
+    }
+}
+
+/// # Histogram Stream
+///
+/// The frames of a capture as a Stream of their histogram (see compute_histogram) and the frame itself.
+///
+/// Like FrameStream it keeps the receiver of the capture locked until it is dropped and ends when the capture stops sending data.
+///
+/// ```rs
+/// use tokio_stream::StreamExt;
+///
+/// let mut histograms = HistogramStream::new(monitor.clone(), HistogramChannel::Luma)?;
+///
+/// while let Some((histogram, frame)) = histograms.next().await {
+///     let dark = histogram[..64].iter().sum::<u32>();
+///     println!("{dark} dark pixels at {:?}", frame.timestamp);
+/// }
+/// ```
+pub struct HistogramStream {
+    frames: FrameStream<Frame>,
+    format: Output,
+    channel: HistogramChannel,
+}
+
+impl HistogramStream {
+    /// # New
+    ///
+    /// A stream over the receiver of the source, computing the channel in the current output format of the source.
+    ///
+    /// Returns CaptureError::UnsupportedFormat when the channel cannot be computed for the format.
+    pub fn new(
+        source: Arc<dyn DynCapture>,
+        channel: HistogramChannel,
+    ) -> Result<Self, CaptureError> {
+        let format = source.get_output_format();
+
+        //an empty frame only checks the format and channel
+        compute_histogram(&[], format, channel)?;
+
+        Ok(Self {
+            frames: FrameStream::new(source.clone_receiver()),
+            format,
+            channel,
+        })
+    }
+}
+
+impl Stream for HistogramStream {
+    type Item = (Vec<u32>, Frame);
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let Some(frame) = ready!(Pin::new(&mut self.frames).poll_next(cx)) else {
+            return Poll::Ready(None);
+        };
+
+        //the format and channel were checked when the stream was created
+        let histogram = compute_histogram(&frame.data, self.format, self.channel)
+            .unwrap_or_else(|_| vec![0; 256]);
+
+        Poll::Ready(Some((histogram, frame)))
+    }
+}
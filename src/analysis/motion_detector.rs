@@ -0,0 +1,308 @@
+use std::{
+    error::Error,
+    pin::Pin,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use tokio::sync::{
+    Mutex,
+    mpsc::{self, Receiver, Sender},
+};
+
+use crate::{
+    analysis::frame_delta_with_tolerance,
+    capture_state::StateCell,
+    devices::{
+        DEFAULT_CHANNEL_CAPACITY, Dimensions, EVENT_CHANNEL_CAPACITY, Frame, Rect, camera::Output,
+    },
+    frame_callback::FrameCallback,
+    i_capture::ICapture,
+};
+
+// the per channel difference camera sensor noise stays below
+const DEFAULT_PIXEL_TOLERANCE: u8 = 16;
+
+// a failure passing frames on, kept until the source has stopped
+type Failure = std::sync::Mutex<Option<Box<dyn Error + Send + Sync>>>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// # Motion Event
+///
+/// Sent by a MotionDetector when a frame changed more than the threshold compared to the previous frame.
+pub struct MotionEvent {
+    /// The timestamp of the frame that moved, see Frame::timestamp.
+    pub timestamp: Duration,
+
+    /// The percentage of pixels that changed, from 0 to 100.
+    pub intensity: f32,
+
+    /// The bounding box of the changed pixels.
+    pub region: Rect,
+}
+
+/// # Motion Detector
+///
+/// Wraps any capture that delivers RGB32 (BGRA) frames, compares every frame with the previous one and sends a MotionEvent
+/// to the motion receiver when more than threshold percent of the pixels changed.
+///
+/// Every frame is passed on unchanged to the receiver of the detector, so the raw stream can still be recorded or shown.
+/// Motion events are dropped when the motion receiver is full, they never hold back the frames.
+///
+/// ```rs
+/// let detector = MotionDetector::new(camera, 2.0);
+/// let motion = detector.clone_motion_receiver();
+///
+/// tokio::spawn(detector.clone().start_capturing());
+///
+/// while let Some(event) = motion.lock().await.recv().await {
+///     println!("{:.1}% moved in {:?} at {:?}", event.intensity, event.region, event.timestamp);
+/// }
+/// ```
+///
+/// Every pixel of every frame is compared on the CPU, frames flagged with Frame::is_duplicate are not compared.
+pub struct MotionDetector<S: ICapture<CaptureOutput = Frame> + ?Sized> {
+    // the capture frames are taken from
+    source: Arc<S>,
+
+    // the percentage of changed pixels above which a frame moved
+    threshold: f32,
+
+    // the per channel difference up to which a pixel did not change
+    pixel_tolerance: RwLock<u8>,
+
+    /// The receiver, can be used to grab the frames of the source.
+    pub receiver: Arc<Mutex<Receiver<Frame>>>,
+
+    // to pass frames on
+    sender: Sender<Frame>,
+
+    // to send motion events
+    motion_sender: Sender<MotionEvent>,
+    motion_receiver: Arc<Mutex<Receiver<MotionEvent>>>,
+
+    // how long recv_frame waits for a frame
+    frame_timeout: RwLock<Option<Duration>>,
+
+    // the callback registered with on_frame
+    frame_callback: FrameCallback<Frame>,
+
+    // if the wrapper captures, see ICapture::state
+    state: StateCell,
+}
+
+impl<S: ICapture<CaptureOutput = Frame> + ?Sized + 'static> MotionDetector<S> {
+    /// # New
+    ///
+    /// Wraps the source, a frame moved when more than threshold percent (0 to 100) of its pixels changed.
+    ///
+    /// Capturing is started and stopped through the detector, do not start the source yourself.
+    pub fn new(source: Arc<S>, threshold: f32) -> Arc<Self> {
+        let (tx, rx) = mpsc::channel(DEFAULT_CHANNEL_CAPACITY);
+        let (motion_tx, motion_rx) = mpsc::channel(EVENT_CHANNEL_CAPACITY);
+
+        Arc::new(Self {
+            source,
+            threshold,
+            pixel_tolerance: RwLock::new(DEFAULT_PIXEL_TOLERANCE),
+            receiver: Arc::new(Mutex::new(rx)),
+            sender: tx,
+            motion_sender: motion_tx,
+            motion_receiver: Arc::new(Mutex::new(motion_rx)),
+            frame_timeout: RwLock::new(None),
+            frame_callback: FrameCallback::default(),
+            state: StateCell::default(),
+        })
+    }
+
+    /// # Threshold
+    ///
+    /// The percentage of changed pixels above which a frame moved.
+    pub fn threshold(&self) -> f32 {
+        self.threshold
+    }
+
+    /// # Set Pixel Tolerance
+    ///
+    /// Sets how much a channel of a pixel may differ before the pixel counts as changed, 16 by default to ignore camera sensor noise.
+    ///
+    /// 0 counts every difference, which suits monitors.
+    pub fn set_pixel_tolerance(&self, tolerance: u8) {
+        *self.pixel_tolerance.write().unwrap() = tolerance;
+    }
+
+    /// # Pixel Tolerance
+    ///
+    /// How much a channel of a pixel may differ before the pixel counts as changed.
+    pub fn pixel_tolerance(&self) -> u8 {
+        *self.pixel_tolerance.read().unwrap()
+    }
+
+    /// # Clone Motion Receiver
+    ///
+    /// The receiver of the motion events.
+    pub fn clone_motion_receiver(&self) -> Arc<Mutex<Receiver<MotionEvent>>> {
+        self.motion_receiver.clone()
+    }
+
+    /// # Source
+    ///
+    /// The capture the frames are taken from.
+    pub fn source(&self) -> Arc<S> {
+        self.source.clone()
+    }
+
+    // passes the frames of the source on, comparing each with the previous one, until the source channel closes.
+    //
+    // the source is always drained, even after a failure, so it never blocks on a full channel while it stops.
+    async fn detect_motion(&self, failure: &Failure) {
+        let source_receiver = self.source.clone_receiver();
+        let mut source_receiver = source_receiver.lock().await;
+
+        let mut previous: Option<Vec<u8>> = None;
+
+        while let Some(frame) = source_receiver.recv().await {
+            if !self.is_capturing() || failure.lock().unwrap().is_some() {
+                continue;
+            }
+
+            if !frame.is_duplicate {
+                if let Some(event) = self.compare(previous.as_deref(), &frame) {
+                    trace_event!(debug, intensity = event.intensity, "motion detected");
+
+                    let _ = self.motion_sender.try_send(event);
+                }
+
+                previous = Some(frame.data.clone());
+            }
+
+            if self.sender.send(frame).await.is_err() {
+                trace_event!(warn, "receiver dropped, stopping motion detector");
+
+                *failure.lock().unwrap() = Some("MotionDetector receiver was dropped".into());
+                let _ = self.source.clone().stop_capturing().await;
+            }
+        }
+    }
+
+    // the motion between the previous and the frame, None when it stayed below the threshold or the size changed
+    fn compare(&self, previous: Option<&[u8]>, frame: &Frame) -> Option<MotionEvent> {
+        let previous = previous.filter(|previous| previous.len() == frame.data.len())?;
+        let Dimensions { width, height } = self.source.get_dimensions().ok()?;
+
+        let pixels = width as u64 * height as u64;
+
+        if pixels == 0 {
+            return None;
+        }
+
+        let delta = frame_delta_with_tolerance(
+            previous,
+            &frame.data,
+            width,
+            height,
+            self.pixel_tolerance(),
+        );
+
+        let intensity = (delta.changed_pixels as f64 * 100.0 / pixels as f64) as f32;
+
+        if intensity <= self.threshold {
+            return None;
+        }
+
+        Some(MotionEvent {
+            timestamp: frame.timestamp,
+            intensity,
+            region: delta.dirty_region?,
+        })
+    }
+}
+
+impl<S: ICapture<CaptureOutput = Frame> + ?Sized + 'static> ICapture for MotionDetector<S> {
+    type CaptureOutput = Frame;
+
+    /// # Get Dimensions
+    ///
+    /// The dimensions of the source.
+    fn get_dimensions(&self) -> Result<Dimensions, Box<dyn std::error::Error>> {
+        self.source.get_dimensions()
+    }
+
+    /// # Get Output Format
+    ///
+    /// The output of the source, always RGB32 while capturing.
+    fn get_output_format(&self) -> Output {
+        self.source.get_output_format()
+    }
+
+    /// # Stop Capturing
+    ///
+    /// Stops passing frames on and stops the source.
+    fn stop_capturing(
+        self: Arc<Self>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>> + Send>> {
+        Box::pin(async move {
+            if !self.state.stop() {
+                return Err("Not capturing any data".into());
+            }
+
+            self.source.clone().stop_capturing().await
+        })
+    }
+
+    /// # Start Capturing
+    ///
+    /// Starts the source and compares each of its frames, the future completes once the source stops.
+    ///
+    /// The source must deliver tightly packed RGB32 frames.
+    fn start_capturing(
+        self: Arc<Self>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>> + Send>> {
+        Box::pin(async move {
+            if self.source.get_output_format() != Output::RGB32 {
+                return Err("MotionDetector requires a source that delivers RGB32 frames".into());
+            }
+
+            //the state is stopped again when the source stops, also with an error
+            let _running = self.state.start().await?;
+
+            let failure = Failure::default();
+
+            //the detect loop only ends with the source channel, so the source finishing ends the capture
+            let source_result = tokio::select! {
+                result = self.source.clone().start_capturing() => result,
+                _ = self.detect_motion(&failure) => Ok(()),
+            };
+
+            if let Some(e) = failure.into_inner().unwrap() {
+                return Err(e as Box<dyn Error>);
+            }
+
+            source_result
+        })
+    }
+
+    fn clone_receiver(&self) -> Arc<Mutex<Receiver<Self::CaptureOutput>>> {
+        self.receiver.clone()
+    }
+
+    fn set_frame_timeout(&self, duration: Duration) {
+        *self.frame_timeout.write().unwrap() = Some(duration);
+    }
+
+    fn clear_frame_timeout(&self) {
+        *self.frame_timeout.write().unwrap() = None;
+    }
+
+    fn get_frame_timeout(&self) -> Option<Duration> {
+        *self.frame_timeout.read().unwrap()
+    }
+
+    fn frame_callback(&self) -> &FrameCallback<Frame> {
+        &self.frame_callback
+    }
+
+    fn state_cell(&self) -> &StateCell {
+        &self.state
+    }
+}
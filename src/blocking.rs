@@ -0,0 +1,240 @@
+use std::{
+    sync::{Arc, mpsc},
+    thread,
+    time::{Duration, Instant},
+};
+
+use tokio::sync::oneshot;
+
+use crate::{
+    capture_error::CaptureError,
+    devices::{DEFAULT_CHANNEL_CAPACITY, Frame},
+    i_capture::ICapture,
+};
+
+// the runtime of the capture thread, the capture loop and the forwarding of frames run on it
+const WORKER_THREADS: usize = 2;
+
+// how long stopping waits for the capture loop, and then for the runtime to shut down, before giving up on them
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+// how long stopping waits for the capture thread, past the timeouts of the loop and the runtime
+const JOIN_TIMEOUT: Duration = Duration::from_secs(15);
+
+// how often a stop checks if the capture thread has ended
+const JOIN_POLL: Duration = Duration::from_millis(10);
+
+/// # Blocking Capture
+///
+/// Runs a capture on a dedicated thread and delivers its frames through a std channel, for programs without an async runtime.
+///
+/// The thread runs its own Tokio runtime, the caller never needs one, the crate still depends on Tokio. Frames are read by iterating the capture, with recv_timeout or with for_each.
+///
+/// The capture stops when stop is called or the BlockingCapture is dropped.
+pub struct BlockingCapture {
+    frames: Option<mpsc::Receiver<Frame>>,
+
+    // ends the forwarding loop, which stops the capture
+    stop: Option<oneshot::Sender<()>>,
+
+    thread: Option<thread::JoinHandle<Result<(), CaptureError>>>,
+}
+
+impl BlockingCapture {
+    /// # Start
+    ///
+    /// Starts the capture on its own thread, do not start the source yourself.
+    pub fn start<S: ICapture<CaptureOutput = Frame> + 'static>(
+        source: Arc<S>,
+    ) -> Result<Self, CaptureError> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(WORKER_THREADS)
+            .thread_name("win-video-capture")
+            .enable_all()
+            .build()?;
+
+        let (frame_tx, frame_rx) = mpsc::sync_channel(DEFAULT_CHANNEL_CAPACITY);
+        let (stop_tx, stop_rx) = oneshot::channel();
+
+        let thread = thread::Builder::new()
+            .name("win-video-blocking".into())
+            .spawn(move || {
+                let ended = runtime.block_on(forward(source, frame_tx, stop_rx));
+
+                //a blocking read of a device that hangs must not keep the thread forever
+                runtime.shutdown_timeout(STOP_TIMEOUT);
+
+                ended
+            })?;
+
+        Ok(Self {
+            frames: Some(frame_rx),
+            stop: Some(stop_tx),
+            thread: Some(thread),
+        })
+    }
+
+    /// # For Each
+    ///
+    /// Starts the capture and calls the callback with every frame on the calling thread, until the callback returns false or the capture ends.
+    ///
+    /// Returns the error that ended the capture, if it failed on its own.
+    pub fn for_each<S: ICapture<CaptureOutput = Frame> + 'static>(
+        source: Arc<S>,
+        mut callback: impl FnMut(Frame) -> bool,
+    ) -> Result<(), CaptureError> {
+        let mut capture = Self::start(source)?;
+
+        for frame in &mut capture {
+            if !callback(frame) {
+                break;
+            }
+        }
+
+        capture.stop()
+    }
+
+    /// # Recv Timeout
+    ///
+    /// Waits up to the timeout for the next frame, None once the capture has ended.
+    ///
+    /// Returns CaptureError::Timeout when no frame arrived in time.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Option<Frame>, CaptureError> {
+        let Some(frames) = &self.frames else {
+            return Ok(None);
+        };
+
+        match frames.recv_timeout(timeout) {
+            Ok(frame) => Ok(Some(frame)),
+            Err(mpsc::RecvTimeoutError::Timeout) => Err(CaptureError::Timeout(timeout)),
+            Err(mpsc::RecvTimeoutError::Disconnected) => Ok(None),
+        }
+    }
+
+    /// # Stop
+    ///
+    /// Stops the capture and waits for its thread to end, returning the error that ended the capture if it failed on its own.
+    ///
+    /// A capture loop that does not end within 5 seconds is aborted and CaptureError::Timeout is returned.
+    pub fn stop(mut self) -> Result<(), CaptureError> {
+        self.shutdown()
+    }
+
+    // moves the frame channel out, whoever takes it must drop it after signal_stop so a pending send ends
+    pub(crate) fn take_frames(&mut self) -> Option<mpsc::Receiver<Frame>> {
+        self.frames.take()
+    }
+
+    // ends the forwarding loop without waiting for the thread
+    pub(crate) fn signal_stop(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+    }
+
+    // signals the thread, unblocks a pending send by closing the channel and joins the thread.
+    //
+    // a thread that has not ended within JOIN_TIMEOUT is left running, so dropping a capture never hangs.
+    fn shutdown(&mut self) -> Result<(), CaptureError> {
+        self.signal_stop();
+
+        self.frames = None;
+
+        let Some(thread) = self.thread.take() else {
+            return Ok(());
+        };
+
+        let deadline = Instant::now() + JOIN_TIMEOUT;
+
+        while !thread.is_finished() {
+            if Instant::now() >= deadline {
+                trace_event!(warn, "the capture thread did not end in time, leaving it");
+
+                return Err(CaptureError::Timeout(JOIN_TIMEOUT));
+            }
+
+            thread::sleep(JOIN_POLL);
+        }
+
+        thread
+            .join()
+            .map_err(|_| CaptureError::Other("the capture thread panicked".into()))?
+    }
+}
+
+impl Iterator for BlockingCapture {
+    type Item = Frame;
+
+    /// Blocks until the next frame, None once the capture has ended.
+    fn next(&mut self) -> Option<Frame> {
+        self.frames.as_ref()?.recv().ok()
+    }
+}
+
+impl Drop for BlockingCapture {
+    fn drop(&mut self) {
+        let _ = self.shutdown();
+    }
+}
+
+// runs the capture and moves its frames to the std channel until stopped, the consumer is gone or the capture ends
+async fn forward<S: ICapture<CaptureOutput = Frame> + 'static>(
+    source: Arc<S>,
+    frames: mpsc::SyncSender<Frame>,
+    mut stop: oneshot::Receiver<()>,
+) -> Result<(), CaptureError> {
+    let capturing = source.clone();
+
+    let mut task = tokio::spawn(async move {
+        capturing
+            .start_capturing()
+            .await
+            .map_err(CaptureError::from)
+    });
+
+    let receiver = source.clone_receiver();
+    let mut receiver = receiver.lock().await;
+
+    let ended = loop {
+        tokio::select! {
+            _ = &mut stop => break None,
+            ended = &mut task => break Some(ended),
+            frame = receiver.recv() => match frame {
+                //blocks this thread, not the workers running the capture
+                Some(frame) if frames.send(frame).is_ok() => {}
+                _ => break None,
+            },
+        }
+    };
+
+    let ended = match ended {
+        Some(ended) => ended,
+        None => {
+            let stopped = tokio::time::timeout(STOP_TIMEOUT, async {
+                //an error here only means the capture already stopped on its own
+                let _ = source.clone().stop_capturing().await;
+
+                //the capture loop may be waiting on a full channel, frames are dropped until it has ended
+                loop {
+                    tokio::select! {
+                        ended = &mut task => break ended,
+                        Some(_) = receiver.recv() => {}
+                    }
+                }
+            })
+            .await;
+
+            match stopped {
+                Ok(ended) => ended,
+                Err(_) => {
+                    trace_event!(warn, "the capture loop did not stop in time, aborting it");
+
+                    task.abort();
+                    return Err(CaptureError::Timeout(STOP_TIMEOUT));
+                }
+            }
+        }
+    };
+
+    ended.map_err(|e| CaptureError::Other(e.to_string()))?
+}
@@ -0,0 +1,162 @@
+use std::{fmt::Display, time::Duration};
+
+use windows::Win32::Foundation::E_ACCESSDENIED;
+
+use crate::devices::CameraProperty;
+
+/// # Capture Error
+///
+/// Errors that can be raised while creating or running a capture.
+#[derive(Debug)]
+pub enum CaptureError {
+    /// Desktop Duplication could not be created because the max number of duplications has already been reached (for example another process holds the duplication).
+    DuplicationUnavailable,
+
+    /// The camera or audio endpoint was lost (unplugged, invalidated) and could not be reconnected.
+    DeviceLost,
+
+    /// No display contains the requested point or window.
+    MonitorNotFound,
+
+    /// No camera has the requested friendly name or symbolic link, available lists the friendly names of the connected cameras.
+    DeviceNotFound {
+        requested: String,
+        available: Vec<String>,
+    },
+
+    /// No device has the index, len is the number of devices (cameras or audio endpoints).
+    DeviceIndexOutOfRange { index: usize, len: usize },
+
+    /// start_capturing was called while the capture is running, see ICapture::state.
+    AlreadyCapturing,
+
+    /// No frame arrived on the receiver within the frame timeout, or no photo within the timeout of Camera::take_photo.
+    Timeout(Duration),
+
+    /// A photo is already being taken or another application holds the camera while a photo is taken, see Camera::take_photo.
+    ///
+    /// A camera another application holds when it is activated or starts capturing is reported as DeviceInUse.
+    CameraBusy,
+
+    /// Another application holds the camera (or the privacy settings block access to cameras), so it could not be activated or started.
+    ///
+    /// name is the friendly name of the device, error the windows error it was reported with. A preview may still read it with AccessMode::Shared.
+    DeviceInUse {
+        name: String,
+        error: windows::core::Error,
+    },
+
+    /// An error returned directly from a windows call.
+    Windows(windows::core::Error),
+
+    /// Reading or writing a file failed.
+    Io(std::io::Error),
+
+    /// A frame could not be encoded.
+    Encoding(String),
+
+    /// The requested format or codec is not supported on this system, described by the message.
+    UnsupportedFormat(String),
+
+    /// The camera does not support the property (or its auto or manual mode), see CameraControls::range.
+    UnsupportedControl(CameraProperty),
+
+    /// The value is outside of the range the camera accepts for the property.
+    ControlOutOfRange {
+        property: CameraProperty,
+        value: i32,
+        min: i32,
+        max: i32,
+    },
+
+    /// Any other error raised by a capture, described by the message.
+    Other(String),
+}
+
+impl Display for CaptureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CaptureError::DuplicationUnavailable => write!(
+                f,
+                "desktop duplication is unavailable, the max number of duplications may already be in use by another process"
+            ),
+            CaptureError::DeviceLost => {
+                write!(f, "the device was lost and could not be reconnected")
+            }
+            CaptureError::MonitorNotFound => write!(f, "no monitor contains the point or window"),
+            CaptureError::DeviceNotFound {
+                requested,
+                available,
+            } => write!(
+                f,
+                "no camera matches \"{requested}\", available cameras: {}",
+                available.join(", ")
+            ),
+            CaptureError::DeviceIndexOutOfRange { index, len } => {
+                write!(f, "there is no device {index}, {len} devices were found")
+            }
+            CaptureError::AlreadyCapturing => write!(f, "the capture is already running"),
+            CaptureError::Timeout(duration) => {
+                write!(f, "no frame was received within {duration:?}")
+            }
+            CaptureError::CameraBusy => write!(
+                f,
+                "the camera is busy, a photo is already being taken or another application holds the camera"
+            ),
+            CaptureError::DeviceInUse { name, error } if error.code() == E_ACCESSDENIED => write!(
+                f,
+                "access to {name} was denied, another application may be using it or camera access is turned off in the privacy settings"
+            ),
+            CaptureError::DeviceInUse { name, error } => write!(
+                f,
+                "{name} is in use by another application, close it and try again ({error})"
+            ),
+            CaptureError::Windows(e) => write!(f, "windows error: {e}"),
+            CaptureError::Io(e) => write!(f, "io error: {e}"),
+            CaptureError::Encoding(message) => write!(f, "encoding error: {message}"),
+            CaptureError::UnsupportedFormat(message) => write!(f, "unsupported format: {message}"),
+            CaptureError::UnsupportedControl(property) => {
+                write!(f, "the camera does not support {property:?}")
+            }
+            CaptureError::ControlOutOfRange {
+                property,
+                value,
+                min,
+                max,
+            } => write!(
+                f,
+                "{value} is outside of the range of {property:?}, which is {min} to {max}"
+            ),
+            CaptureError::Other(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
+
+impl From<windows::core::Error> for CaptureError {
+    fn from(value: windows::core::Error) -> Self {
+        CaptureError::Windows(value)
+    }
+}
+
+impl From<std::io::Error> for CaptureError {
+    fn from(value: std::io::Error) -> Self {
+        CaptureError::Io(value)
+    }
+}
+
+impl From<Box<dyn std::error::Error>> for CaptureError {
+    /// Recovers the capture or windows error inside of a boxed error, other errors become CaptureError::Other.
+    fn from(value: Box<dyn std::error::Error>) -> Self {
+        let value = match value.downcast::<CaptureError>() {
+            Ok(e) => return *e,
+            Err(value) => value,
+        };
+
+        match value.downcast::<windows::core::Error>() {
+            Ok(e) => CaptureError::Windows(*e),
+            Err(value) => CaptureError::Other(value.to_string()),
+        }
+    }
+}
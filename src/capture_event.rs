@@ -0,0 +1,45 @@
+use std::time::Duration;
+
+use crate::devices::CursorInfo;
+
+/// # Capture Event
+///
+/// Something that happened to a capture without stopping it, sent on the event receiver of the capture next to the frames.
+///
+/// Events are dropped when nobody reads them.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CaptureEvent {
+    /// The capture lost its source (for example desktop duplication while the secure desktop is shown) and is trying to get it back, no frames are sent until it is recovered.
+    Recovering,
+
+    /// The capture got its source back and sends frames again.
+    Recovered {
+        /// How long no frames could be captured.
+        downtime: Duration,
+    },
+
+    /// The device of a camera was lost (for example unplugged) and the camera waits for it to come back, see Camera::set_reconnect_policy.
+    ///
+    /// The frame channel stays open, no frames are sent until the camera is reconnected.
+    Disconnected,
+
+    /// The device of a camera came back and was activated with the same format, frames are sent again.
+    Reconnected {
+        /// How long the device was gone.
+        downtime: Duration,
+    },
+
+    /// The size of the frames changed, for example a camera that reconnected with another resolution or a monitor that was rotated.
+    ///
+    /// Frames sent after the event have the new size, get_dimensions already reports it.
+    Resized {
+        /// The new width of the frames.
+        width: u32,
+
+        /// The new height of the frames.
+        height: u32,
+    },
+
+    /// The mouse cursor of a monitor moved or changed its shape, only sent after Monitor::set_cursor_events(true).
+    CursorChanged(CursorInfo),
+}
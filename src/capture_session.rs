@@ -0,0 +1,193 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::task::JoinHandle;
+
+use crate::{capture_error::CaptureError, com::MtaUsage, i_capture::ICapture};
+
+// how long stopping waits for the capture loop to end before the task is aborted
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// # Capture Session
+///
+/// A running capture that is stopped when the session is dropped, so an early return or a panic never leaves a device capturing.
+///
+/// The capture loop runs in its own task, frames are read from the receiver of the capture as usual.
+///
+/// Dropping the session stops the capture in the background, use stop to wait for the capture to end and get its result.
+/// A loop that has not ended 5 seconds after it was told to stop is aborted, also when the capture refuses to stop.
+/// The device (and its COM and DXGI objects) is released once the session and every other clone of it are dropped.
+///
+/// While it runs the session keeps the COM multithreaded apartment alive, which the capture loop uses on the Tokio worker threads, so COM is left as it was found once every session ended.
+pub struct CaptureSession<S: ICapture + ?Sized + 'static> {
+    capture: Arc<S>,
+
+    // the task running the capture loop, taken when the session is stopped
+    task: Option<JoinHandle<Result<(), CaptureError>>>,
+
+    // keeps the tokio workers running the loop in the multithreaded apartment, released with the session
+    _mta: MtaUsage,
+}
+
+impl<S: ICapture + ?Sized + 'static> CaptureSession<S> {
+    /// # Start
+    ///
+    /// Spawns a task running start_capturing of the capture, this must be called from within a Tokio runtime.
+    pub fn start(capture: Arc<S>) -> Self {
+        let capturing = capture.clone();
+
+        let task = tokio::spawn(async move {
+            capturing
+                .start_capturing()
+                .await
+                .map_err(CaptureError::from)
+        });
+
+        Self {
+            capture,
+            task: Some(task),
+            _mta: MtaUsage::acquire(),
+        }
+    }
+
+    /// # Capture
+    ///
+    /// The capture the session runs.
+    pub fn capture(&self) -> &Arc<S> {
+        &self.capture
+    }
+
+    /// # Is Finished
+    ///
+    /// If the capture loop has ended, either because it was stopped or because it failed.
+    pub fn is_finished(&self) -> bool {
+        self.task.as_ref().is_none_or(JoinHandle::is_finished)
+    }
+
+    /// # Stop
+    ///
+    /// Stops the capture and waits for the capture loop to end, returning the error that ended it if it failed on its own.
+    ///
+    /// A loop that does not end within 5 seconds is aborted and CaptureError::Timeout is returned.
+    pub async fn stop(mut self) -> Result<(), CaptureError> {
+        let Some(task) = self.task.take() else {
+            return Ok(());
+        };
+
+        stop_task(self.capture.clone(), task).await
+    }
+
+    // waits for the capture loop to end on its own, the task stays in the session until it has ended so this can be cancelled
+    async fn finished(&mut self) -> Result<(), CaptureError> {
+        let Some(task) = self.task.as_mut() else {
+            return Ok(());
+        };
+
+        let ended = task.await.map_err(|e| CaptureError::Other(e.to_string()));
+        self.task = None;
+
+        ended?
+    }
+
+    // stops like stop, taking the frames the loop sends meanwhile so it never waits on a full channel
+    async fn stop_draining(self) -> Result<(), CaptureError> {
+        let receiver = self.capture.clone_receiver();
+        let mut receiver = receiver.lock().await;
+
+        let stopping = self.stop();
+        tokio::pin!(stopping);
+
+        loop {
+            tokio::select! {
+                stopped = &mut stopping => return stopped,
+                Some(_) = receiver.recv() => {}
+            }
+        }
+    }
+}
+
+// stops the capture and waits for its task, a task that is still running after STOP_TIMEOUT is aborted
+async fn stop_task<S: ICapture + ?Sized + 'static>(
+    capture: Arc<S>,
+    mut task: JoinHandle<Result<(), CaptureError>>,
+) -> Result<(), CaptureError> {
+    let ended = tokio::time::timeout(STOP_TIMEOUT, async {
+        //an error here only means the capture already stopped on its own (or will not stop), the loop result tells why
+        let _ = capture.clone().stop_capturing().await;
+
+        (&mut task).await
+    })
+    .await;
+
+    match ended {
+        Ok(ended) => ended.map_err(|e| CaptureError::Other(e.to_string()))?,
+        Err(_) => {
+            trace_event!(warn, "the capture loop did not stop in time, aborting it");
+
+            task.abort();
+            Err(CaptureError::Timeout(STOP_TIMEOUT))
+        }
+    }
+}
+
+// starts the capture, receives n frames and stops it again, see ICapture::capture_n_frames
+pub(crate) async fn capture_n_frames<S: ICapture + 'static>(
+    capture: Arc<S>,
+    n: usize,
+) -> Result<Vec<S::CaptureOutput>, CaptureError> {
+    if n == 0 {
+        return Ok(Vec::new());
+    }
+
+    let mut session = CaptureSession::start(capture.clone());
+    let mut frames = Vec::with_capacity(n);
+
+    let received = loop {
+        if frames.len() == n {
+            break Ok(());
+        }
+
+        tokio::select! {
+            frame = capture.recv_frame() => match frame {
+                Ok(Some(frame)) => frames.push(frame),
+                Ok(None) => break Ok(()),
+                Err(e) => break Err(e),
+            },
+            //a capture that fails to start or stops on its own would leave the receiver waiting forever
+            ended = session.finished() => break ended,
+        }
+    };
+
+    let stopped = session.stop_draining().await;
+
+    received?;
+    stopped?;
+
+    if frames.len() < n {
+        return Err(CaptureError::Other(format!(
+            "the capture ended after {} of {n} frames",
+            frames.len()
+        )));
+    }
+
+    Ok(frames)
+}
+
+impl<S: ICapture + ?Sized + 'static> Drop for CaptureSession<S> {
+    fn drop(&mut self) {
+        let Some(task) = self.task.take() else {
+            return;
+        };
+
+        //stopping is asynchronous, without a runtime (such as while it shuts down) the loop is aborted instead
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                let capture = self.capture.clone();
+
+                runtime.spawn(async move {
+                    let _ = stop_task(capture, task).await;
+                });
+            }
+            Err(_) => task.abort(),
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use tokio::sync::Notify;
+
+use crate::capture_error::CaptureError;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+/// # Capture State
+///
+/// Whether a capture is running, returned by ICapture::state.
+pub enum CaptureState {
+    /// No capture loop is running, start_capturing can be called.
+    #[default]
+    Stopped,
+
+    /// The capture loop is running and sends frames to the receiver.
+    Capturing,
+
+    /// stop_capturing was called and the capture loop has not ended yet, which takes until its current frame is sent.
+    ///
+    /// start_capturing waits for the loop to end and starts again.
+    Stopping,
+}
+
+/// # State Cell
+///
+/// The CaptureState of a capture, every capture keeps one of these and hands it out with ICapture::state_cell.
+///
+/// Only a capture changes the state, users read it with ICapture::state. The capture loops run while the state is capturing, so
+/// stop_capturing only has to move it to stopping.
+#[derive(Debug, Default)]
+pub struct StateCell {
+    state: AtomicU8,
+
+    // wakes the starts waiting for a stopping loop to end
+    stopped: Notify,
+}
+
+impl StateCell {
+    /// # New
+    ///
+    /// A stopped state.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// # Get
+    ///
+    /// The current state.
+    pub fn get(&self) -> CaptureState {
+        match self.state.load(Ordering::Acquire) {
+            1 => CaptureState::Capturing,
+            2 => CaptureState::Stopping,
+            _ => CaptureState::Stopped,
+        }
+    }
+
+    // moves a stopped capture to capturing, the state is stopped again once the returned guard drops at the end of the capture loop.
+    //
+    // a stopping capture is waited for, so stop followed by start never runs two loops at once.
+    pub(crate) async fn start(&self) -> Result<Running<'_>, CaptureError> {
+        loop {
+            //registered before the state is read, so the end of the loop can not be missed in between
+            let stopped = self.stopped.notified();
+            tokio::pin!(stopped);
+            stopped.as_mut().enable();
+
+            let started = self.state.compare_exchange(
+                CaptureState::Stopped as u8,
+                CaptureState::Capturing as u8,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            );
+
+            match started {
+                Ok(_) => return Ok(Running(self)),
+                Err(state) if state == CaptureState::Stopping as u8 => stopped.await,
+                Err(_) => return Err(CaptureError::AlreadyCapturing),
+            }
+        }
+    }
+
+    // moves a running capture to stopping, false if it was not capturing
+    pub(crate) fn stop(&self) -> bool {
+        self.state
+            .compare_exchange(
+                CaptureState::Capturing as u8,
+                CaptureState::Stopping as u8,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            )
+            .is_ok()
+    }
+}
+
+// stops the state when the capture loop ends, also when it ends with an error
+pub(crate) struct Running<'a>(&'a StateCell);
+
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        self.0
+            .state
+            .store(CaptureState::Stopped as u8, Ordering::Release);
+        self.0.stopped.notify_waiters();
+    }
+}
@@ -0,0 +1,38 @@
+use std::sync::OnceLock;
+
+use windows::Win32::System::Performance::{QueryPerformanceCounter, QueryPerformanceFrequency};
+
+// the clock is in 100 ns units, the unit of Media Foundation sample times
+const UNITS_PER_SECOND: i128 = 10_000_000;
+
+/// # Clock
+///
+/// The current time of the shared capture clock, the performance counter (QPC) in 100 ns units.
+///
+/// This is the clock of Media Foundation (MFGetSystemTime) and of the present times of Desktop Duplication,
+/// the presentation_time of frames and the QPC positions of WASAPI audio can be compared against it directly.
+pub fn clock() -> i64 {
+    let mut ticks = 0;
+
+    //QueryPerformanceCounter cannot fail on Windows XP or later
+    let _ = unsafe { QueryPerformanceCounter(&mut ticks) };
+
+    qpc_to_clock(ticks)
+}
+
+// converts raw performance counter ticks (such as DXGI_OUTDUPL_FRAME_INFO::LastPresentTime) to the 100 ns units of the clock
+pub(crate) fn qpc_to_clock(ticks: i64) -> i64 {
+    (ticks as i128 * UNITS_PER_SECOND / qpc_frequency() as i128) as i64
+}
+
+// ticks per second of the performance counter, fixed at boot
+fn qpc_frequency() -> i64 {
+    static FREQUENCY: OnceLock<i64> = OnceLock::new();
+
+    *FREQUENCY.get_or_init(|| {
+        let mut frequency = 0;
+        let _ = unsafe { QueryPerformanceFrequency(&mut frequency) };
+
+        frequency.max(1)
+    })
+}
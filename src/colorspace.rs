@@ -0,0 +1,508 @@
+use crate::capture_error::CaptureError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// # Color Matrix
+///
+/// The coefficients YUV and RGB are converted with, both in limited (video) range where Y spans 16 to 235.
+pub enum ColorMatrix {
+    /// BT.601, the matrix of SD video.
+    Bt601,
+
+    /// BT.709, the matrix of HD video.
+    Bt709,
+}
+
+impl ColorMatrix {
+    /// # For Height
+    ///
+    /// The matrix video of the height is usually encoded with, BT.709 from 720 rows up and BT.601 below.
+    pub fn for_height(height: u32) -> Self {
+        if height >= 720 {
+            ColorMatrix::Bt709
+        } else {
+            ColorMatrix::Bt601
+        }
+    }
+
+    // the fixed point weights (8 fractional bits) of red, green and blue in Y, U and V
+    fn yuv_weights(&self) -> ToYuv {
+        match self {
+            ColorMatrix::Bt601 => ToYuv {
+                y: [66, 129, 25],
+                u: [-38, -74, 112],
+                v: [112, -94, -18],
+            },
+            ColorMatrix::Bt709 => ToYuv {
+                y: [47, 157, 16],
+                u: [-26, -86, 112],
+                v: [112, -102, -10],
+            },
+        }
+    }
+
+    // the fixed point weights (8 fractional bits) of Y, U and V in blue, green and red
+    fn rgb_weights(&self) -> ToRgb {
+        match self {
+            ColorMatrix::Bt601 => ToRgb {
+                y: 298,
+                u_b: 516,
+                u_g: 100,
+                v_g: 208,
+                v_r: 409,
+            },
+            ColorMatrix::Bt709 => ToRgb {
+                y: 298,
+                u_b: 541,
+                u_g: 55,
+                v_g: 136,
+                v_r: 459,
+            },
+        }
+    }
+}
+
+// the weights of red, green and blue
+struct ToYuv {
+    y: [i32; 3],
+    u: [i32; 3],
+    v: [i32; 3],
+}
+
+// blue is y + u_b * u, green y - u_g * u - v_g * v and red y + v_r * v, with u and v centered on 0
+struct ToRgb {
+    y: i32,
+    u_b: i32,
+    u_g: i32,
+    v_g: i32,
+    v_r: i32,
+}
+
+/// # BGRA To RGB32
+///
+/// Packs BGRA pixels into one u32 per pixel as 0x00RRGGBB, the layout of minifb and most software framebuffers.
+///
+/// A pixel is converted for every u32 of dst, src needs 4 bytes for each. Returns CaptureError::Other when src is too short.
+#[inline]
+pub fn bgra_to_rgb32(src: &[u8], dst: &mut [u32]) -> Result<(), CaptureError> {
+    if src.len() < dst.len() * 4 {
+        return Err(CaptureError::Other(format!(
+            "{} pixels need {} bytes of BGRA, got {}",
+            dst.len(),
+            dst.len() * 4,
+            src.len()
+        )));
+    }
+
+    bgra_row_to_rgb32(&src[..dst.len() * 4], dst);
+
+    Ok(())
+}
+
+/// # BGRA To YUV420
+///
+/// Converts a tightly packed BGRA frame to planar 4:2:0 YUV (I420), with BT.709 for HD frames and BT.601 below (see ColorMatrix::for_height).
+///
+/// y holds width * height bytes, u and v a sample for every 2x2 block, (width + 1) / 2 * (height + 1) / 2 bytes each, averaged from the block.
+/// Odd sizes are allowed, the last chroma column and row then cover a single pixel.
+///
+/// Returns CaptureError::Other when a buffer is too short for the size.
+#[inline]
+pub fn bgra_to_yuv420(
+    src: &[u8],
+    y: &mut [u8],
+    u: &mut [u8],
+    v: &mut [u8],
+    width: u32,
+    height: u32,
+) -> Result<(), CaptureError> {
+    bgra_to_yuv420_with(src, y, u, v, width, height, ColorMatrix::for_height(height))
+}
+
+/// # BGRA To YUV420 With
+///
+/// bgra_to_yuv420 with the matrix given, for video that does not follow the usual matrix of its size.
+#[inline]
+pub fn bgra_to_yuv420_with(
+    src: &[u8],
+    y: &mut [u8],
+    u: &mut [u8],
+    v: &mut [u8],
+    width: u32,
+    height: u32,
+    matrix: ColorMatrix,
+) -> Result<(), CaptureError> {
+    let (width, height) = (width as usize, height as usize);
+    let (chroma_width, chroma_height) = (width.div_ceil(2), height.div_ceil(2));
+
+    check_len("BGRA", src.len(), width * height * 4)?;
+    check_len("Y", y.len(), width * height)?;
+    check_len("U", u.len(), chroma_width * chroma_height)?;
+    check_len("V", v.len(), chroma_width * chroma_height)?;
+
+    if width == 0 || height == 0 {
+        return Ok(());
+    }
+
+    bgra_to_yuv420_rows(
+        src,
+        width * 4,
+        width,
+        height,
+        y,
+        matrix,
+        |index, sample_u, sample_v| {
+            u[index] = sample_u;
+            v[index] = sample_v;
+        },
+    );
+
+    Ok(())
+}
+
+// converts height rows of stride bytes of BGRA, cropped to width, to a tightly packed Y plane. the U and V samples of every 2x2 block
+// are handed to chroma with the index of the block, the rows of blocks one after the other.
+//
+// width and height are not 0 and the buffers hold the frame, the public functions check this
+pub(crate) fn bgra_to_yuv420_rows(
+    src: &[u8],
+    stride: usize,
+    width: usize,
+    height: usize,
+    y: &mut [u8],
+    matrix: ColorMatrix,
+    mut chroma: impl FnMut(usize, u8, u8),
+) {
+    let (chroma_width, chroma_height) = (width.div_ceil(2), height.div_ceil(2));
+    let weights = matrix.yuv_weights();
+
+    for (row, y_row) in y.chunks_exact_mut(width).take(height).enumerate() {
+        bgra_row_to_luma(
+            &src[row * stride..row * stride + width * 4],
+            y_row,
+            &weights.y,
+        );
+    }
+
+    //the edge pixels are repeated for the blocks of odd sizes
+    let pixel = |x: usize, y: usize| {
+        let offset = y.min(height - 1) * stride + x.min(width - 1) * 4;
+        [
+            src[offset + 2] as i32,
+            src[offset + 1] as i32,
+            src[offset] as i32,
+        ]
+    };
+
+    let sample = |weights: &[i32; 3], rgb: [i32; 3]| {
+        (((weights[0] * rgb[0] + weights[1] * rgb[1] + weights[2] * rgb[2] + 128) >> 8) + 128) as u8
+    };
+
+    for cy in 0..chroma_height {
+        for cx in 0..chroma_width {
+            let mut sum = [0; 3];
+
+            for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                let rgb = pixel(cx * 2 + dx, cy * 2 + dy);
+
+                for c in 0..3 {
+                    sum[c] += rgb[c];
+                }
+            }
+
+            let average = sum.map(|channel| channel / 4);
+
+            chroma(
+                cy * chroma_width + cx,
+                sample(&weights.u, average),
+                sample(&weights.v, average),
+            );
+        }
+    }
+}
+
+/// # YUV420 To BGRA
+///
+/// Converts planar 4:2:0 YUV (I420) to tightly packed opaque BGRA, with BT.709 for HD frames and BT.601 below (see ColorMatrix::for_height).
+///
+/// The planes are laid out like the ones of bgra_to_yuv420, dst holds width * height * 4 bytes.
+///
+/// Returns CaptureError::Other when a buffer is too short for the size.
+#[inline]
+pub fn yuv420_to_bgra(
+    y: &[u8],
+    u: &[u8],
+    v: &[u8],
+    dst: &mut [u8],
+    width: u32,
+    height: u32,
+) -> Result<(), CaptureError> {
+    yuv420_to_bgra_with(y, u, v, dst, width, height, ColorMatrix::for_height(height))
+}
+
+/// # YUV420 To BGRA With
+///
+/// yuv420_to_bgra with the matrix given, for video that does not follow the usual matrix of its size.
+#[inline]
+pub fn yuv420_to_bgra_with(
+    y: &[u8],
+    u: &[u8],
+    v: &[u8],
+    dst: &mut [u8],
+    width: u32,
+    height: u32,
+    matrix: ColorMatrix,
+) -> Result<(), CaptureError> {
+    let (width, height) = (width as usize, height as usize);
+    let (chroma_width, chroma_height) = (width.div_ceil(2), height.div_ceil(2));
+
+    check_len("Y", y.len(), width * height)?;
+    check_len("U", u.len(), chroma_width * chroma_height)?;
+    check_len("V", v.len(), chroma_width * chroma_height)?;
+    check_len("BGRA", dst.len(), width * height * 4)?;
+
+    if width == 0 || height == 0 {
+        return Ok(());
+    }
+
+    for (row, (y_row, dst_row)) in y
+        .chunks_exact(width)
+        .zip(dst.chunks_exact_mut(width * 4))
+        .take(height)
+        .enumerate()
+    {
+        let chroma = (row / 2) * chroma_width;
+
+        yuv_row_to_bgra(
+            y_row,
+            &u[chroma..chroma + chroma_width],
+            &v[chroma..chroma + chroma_width],
+            dst_row,
+            matrix,
+        );
+    }
+
+    Ok(())
+}
+
+// errors when a buffer is shorter than the frame needs
+fn check_len(plane: &str, len: usize, needed: usize) -> Result<(), CaptureError> {
+    if len < needed {
+        return Err(CaptureError::Other(format!(
+            "the {plane} buffer needs {needed} bytes, got {len}"
+        )));
+    }
+
+    Ok(())
+}
+
+// rounds a fixed point channel back to a byte
+#[inline]
+fn to_byte(value: i32) -> u8 {
+    ((value + 128) >> 8).clamp(0, 255) as u8
+}
+
+#[inline]
+pub(crate) fn bgra_row_to_rgb32(src: &[u8], dst: &mut [u32]) {
+    #[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+    let done = unsafe { sse2::bgra_to_rgb32(src, dst) };
+
+    #[cfg(not(all(target_arch = "x86_64", target_feature = "sse2")))]
+    let done = 0;
+
+    for (pixel, bgra) in dst[done..].iter_mut().zip(src[done * 4..].chunks_exact(4)) {
+        *pixel = u32::from_le_bytes([bgra[0], bgra[1], bgra[2], 0]);
+    }
+}
+
+#[inline]
+fn bgra_row_to_luma(src: &[u8], y: &mut [u8], weights: &[i32; 3]) {
+    #[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+    let done = unsafe { sse2::bgra_to_luma(src, y, weights) };
+
+    #[cfg(not(all(target_arch = "x86_64", target_feature = "sse2")))]
+    let done = 0;
+
+    for (luma, bgra) in y[done..].iter_mut().zip(src[done * 4..].chunks_exact(4)) {
+        let (r, g, b) = (bgra[2] as i32, bgra[1] as i32, bgra[0] as i32);
+
+        *luma = (((weights[0] * r + weights[1] * g + weights[2] * b + 128) >> 8) + 16) as u8;
+    }
+}
+
+// every two pixels of the row share a U and V sample
+#[inline]
+pub(crate) fn yuv_row_to_bgra(y: &[u8], u: &[u8], v: &[u8], dst: &mut [u8], matrix: ColorMatrix) {
+    let weights = &matrix.rgb_weights();
+
+    #[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+    let done = unsafe { sse2::yuv_to_bgra(y, u, v, dst, weights) };
+
+    #[cfg(not(all(target_arch = "x86_64", target_feature = "sse2")))]
+    let done = 0;
+
+    for (x, pixel) in dst.chunks_exact_mut(4).enumerate().skip(done) {
+        let luma = weights.y * (y[x] as i32 - 16);
+        let d = u[x / 2] as i32 - 128;
+        let e = v[x / 2] as i32 - 128;
+
+        pixel.copy_from_slice(&[
+            to_byte(luma + weights.u_b * d),
+            to_byte(luma - weights.u_g * d - weights.v_g * e),
+            to_byte(luma + weights.v_r * e),
+            255,
+        ]);
+    }
+}
+
+// the rows in blocks of pixels with SSE2, which every x86_64 cpu has. each function returns the pixels it converted,
+// the rest of the row is left to the scalar loop, the results are the same to the bit.
+#[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+mod sse2 {
+    use std::arch::x86_64::*;
+
+    use super::ToRgb;
+
+    // four pixels at a time, the alpha byte is cleared
+    #[inline]
+    pub(super) unsafe fn bgra_to_rgb32(src: &[u8], dst: &mut [u32]) -> usize {
+        let blocks = dst.len().min(src.len() / 4) / 4;
+
+        unsafe {
+            let mask = _mm_set1_epi32(0x00FF_FFFF);
+
+            for block in 0..blocks {
+                let pixels = _mm_loadu_si128(src.as_ptr().add(block * 16) as *const __m128i);
+
+                _mm_storeu_si128(
+                    dst.as_mut_ptr().add(block * 4) as *mut __m128i,
+                    _mm_and_si128(pixels, mask),
+                );
+            }
+        }
+
+        blocks * 4
+    }
+
+    // four pixels at a time, each pixel is the sum of two products of madd for (b, g) and (r, a)
+    #[inline]
+    pub(super) unsafe fn bgra_to_luma(src: &[u8], y: &mut [u8], weights: &[i32; 3]) -> usize {
+        let blocks = y.len().min(src.len() / 4) / 4;
+
+        unsafe {
+            let zero = _mm_setzero_si128();
+            let [r, g, b] = weights.map(|weight| weight as i16);
+            let weights = _mm_setr_epi16(b, g, r, 0, b, g, r, 0);
+
+            for block in 0..blocks {
+                let pixels = _mm_loadu_si128(src.as_ptr().add(block * 16) as *const __m128i);
+
+                let sums = |half: __m128i| {
+                    let products = _mm_madd_epi16(half, weights);
+
+                    //lanes 0 and 2 hold the sums of the two pixels
+                    let summed = _mm_add_epi32(products, _mm_shuffle_epi32(products, 0xB1));
+                    _mm_shuffle_epi32(summed, 0x08)
+                };
+
+                let luma = _mm_unpacklo_epi64(
+                    sums(_mm_unpacklo_epi8(pixels, zero)),
+                    sums(_mm_unpackhi_epi8(pixels, zero)),
+                );
+
+                let luma = _mm_add_epi32(
+                    _mm_srai_epi32(_mm_add_epi32(luma, _mm_set1_epi32(128)), 8),
+                    _mm_set1_epi32(16),
+                );
+
+                let luma = _mm_packus_epi16(_mm_packs_epi32(luma, luma), zero);
+
+                y.as_mut_ptr()
+                    .add(block * 4)
+                    .cast::<i32>()
+                    .write_unaligned(_mm_cvtsi128_si32(luma));
+            }
+        }
+
+        blocks * 4
+    }
+
+    // eight pixels at a time with four U and V samples, the channels are computed in 32 bits with madd
+    #[inline]
+    pub(super) unsafe fn yuv_to_bgra(
+        y: &[u8],
+        u: &[u8],
+        v: &[u8],
+        dst: &mut [u8],
+        weights: &ToRgb,
+    ) -> usize {
+        let blocks = (dst.len() / 4)
+            .min(y.len())
+            .min(u.len() * 2)
+            .min(v.len() * 2)
+            / 8;
+
+        unsafe {
+            let zero = _mm_setzero_si128();
+
+            //the pair of weights madd multiplies two 16 bit lanes with
+            let pair = |low: i32, high: i32| _mm_set1_epi32((high << 16) | (low & 0xFFFF));
+
+            let b_weights = pair(weights.y, weights.u_b);
+            let g_weights = pair(weights.y, -weights.u_g);
+            let g_v_weights = pair(-weights.v_g, 0);
+            let r_weights = pair(weights.y, weights.v_r);
+
+            //the chroma samples are doubled, one for each pixel of the pair
+            let chroma = |samples: &[u8]| {
+                let samples = _mm_cvtsi32_si128(i32::from_le_bytes([
+                    samples[0], samples[1], samples[2], samples[3],
+                ]));
+                let doubled = _mm_unpacklo_epi8(samples, samples);
+
+                _mm_sub_epi16(_mm_unpacklo_epi8(doubled, zero), _mm_set1_epi16(128))
+            };
+
+            let channel = |a: __m128i, b: __m128i, weights: __m128i| {
+                (
+                    _mm_madd_epi16(_mm_unpacklo_epi16(a, b), weights),
+                    _mm_madd_epi16(_mm_unpackhi_epi16(a, b), weights),
+                )
+            };
+
+            let to_bytes = |(low, high): (__m128i, __m128i)| {
+                let round = _mm_set1_epi32(128);
+                let low = _mm_srai_epi32(_mm_add_epi32(low, round), 8);
+                let high = _mm_srai_epi32(_mm_add_epi32(high, round), 8);
+
+                _mm_packus_epi16(_mm_packs_epi32(low, high), zero)
+            };
+
+            for block in 0..blocks {
+                let luma = _mm_loadl_epi64(y.as_ptr().add(block * 8) as *const __m128i);
+                let luma = _mm_sub_epi16(_mm_unpacklo_epi8(luma, zero), _mm_set1_epi16(16));
+
+                let d = chroma(&u[block * 4..block * 4 + 4]);
+                let e = chroma(&v[block * 4..block * 4 + 4]);
+
+                let b = to_bytes(channel(luma, d, b_weights));
+                let r = to_bytes(channel(luma, e, r_weights));
+
+                let (g_low, g_high) = channel(luma, d, g_weights);
+                let (g_v_low, g_v_high) = channel(e, zero, g_v_weights);
+                let g = to_bytes((
+                    _mm_add_epi32(g_low, g_v_low),
+                    _mm_add_epi32(g_high, g_v_high),
+                ));
+
+                let bg = _mm_unpacklo_epi8(b, g);
+                let ra = _mm_unpacklo_epi8(r, _mm_set1_epi8(-1));
+
+                let out = dst.as_mut_ptr().add(block * 32) as *mut __m128i;
+                _mm_storeu_si128(out, _mm_unpacklo_epi16(bg, ra));
+                _mm_storeu_si128(out.add(1), _mm_unpackhi_epi16(bg, ra));
+            }
+        }
+
+        blocks * 8
+    }
+}
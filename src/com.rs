@@ -0,0 +1,152 @@
+use std::marker::PhantomData;
+
+use windows::Win32::{
+    Foundation::{CO_E_NOTINITIALIZED, RPC_E_CHANGED_MODE},
+    System::Com::{
+        APTTYPE, APTTYPE_MAINSTA, APTTYPE_MTA, APTTYPE_NA, APTTYPE_STA, APTTYPEQUALIFIER,
+        CO_MTA_USAGE_COOKIE, COINIT_MULTITHREADED, CoDecrementMTAUsage, CoGetApartmentType,
+        CoIncrementMTAUsage, CoInitializeEx, CoUninitialize,
+    },
+};
+
+use crate::capture_error::CaptureError;
+
+/// # Apartment
+///
+/// The COM apartment of a thread.
+///
+/// Cameras (Media Foundation) need COM on the thread that enumerates and activates them, in either apartment. Their capture loops run on Tokio worker threads
+/// in the multithreaded apartment, which every camera and capture session keeps alive, so the workers never need COM themselves.
+/// Monitors (DXGI) do not use COM, encoders and the device watcher initialize it on their own threads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Apartment {
+    /// The multithreaded apartment, joined with CoInitializeEx(COINIT_MULTITHREADED) or implicitly while another thread keeps it alive.
+    Multithreaded,
+
+    /// A single threaded apartment, such as the thread of a window.
+    SingleThreaded,
+
+    /// The neutral apartment.
+    Neutral,
+
+    /// COM is not initialized on the thread.
+    Uninitialized,
+}
+
+/// # Current Apartment
+///
+/// The COM apartment of the calling thread.
+pub fn current_apartment() -> Apartment {
+    let mut apartment = APTTYPE::default();
+    let mut qualifier = APTTYPEQUALIFIER::default();
+
+    match unsafe { CoGetApartmentType(&mut apartment, &mut qualifier) } {
+        Err(_) => Apartment::Uninitialized,
+        Ok(()) => match apartment {
+            APTTYPE_MTA => Apartment::Multithreaded,
+            APTTYPE_STA | APTTYPE_MAINSTA => Apartment::SingleThreaded,
+            APTTYPE_NA => Apartment::Neutral,
+            _ => Apartment::Uninitialized,
+        },
+    }
+}
+
+// fails with CO_E_NOTINITIALIZED naming the fix when the thread has no apartment, rather than a bare HRESULT from deep inside media foundation
+pub(crate) fn require_com(what: &str) -> Result<(), windows::core::Error> {
+    if current_apartment() == Apartment::Uninitialized {
+        return Err(windows::core::Error::new(
+            CO_E_NOTINITIALIZED,
+            format!(
+                "COM is not initialized on this thread, create a ComGuard (or call CoInitializeEx) before using {what}"
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+/// # Com Guard
+///
+/// Initializes COM in the multithreaded apartment on the current thread and uninitializes it when dropped, leaving the thread as it was found.
+///
+/// Threads that already are in a single threaded apartment are left in it (COM cannot change the apartment of a thread), see apartment.
+/// The guard must be dropped on the thread that created it, so it is not Send.
+pub struct ComGuard {
+    // if CoInitializeEx succeeded (S_OK or S_FALSE), which must be balanced by CoUninitialize
+    initialized: bool,
+
+    apartment: Apartment,
+
+    _thread: PhantomData<*const ()>,
+}
+
+impl ComGuard {
+    /// # New
+    ///
+    /// Initializes COM on the current thread, accepting threads where it already is initialized (in either apartment).
+    pub fn new() -> Result<Self, CaptureError> {
+        let result = unsafe { CoInitializeEx(None, COINIT_MULTITHREADED) };
+
+        let initialized = match result.ok() {
+            Ok(()) => true,
+            Err(e) if e.code() == RPC_E_CHANGED_MODE => false,
+            Err(e) => return Err(e.into()),
+        };
+
+        let apartment = current_apartment();
+
+        trace_event!(debug, apartment = ?apartment, initialized, "com initialized");
+
+        Ok(Self {
+            initialized,
+            apartment,
+            _thread: PhantomData,
+        })
+    }
+
+    /// # Apartment
+    ///
+    /// The apartment of the thread, SingleThreaded when the thread was already in one.
+    pub fn apartment(&self) -> Apartment {
+        self.apartment
+    }
+}
+
+impl Drop for ComGuard {
+    fn drop(&mut self) {
+        if self.initialized {
+            unsafe { CoUninitialize() };
+        }
+    }
+}
+
+// keeps the multithreaded apartment alive, so threads without COM (such as tokio workers) are in it implicitly.
+//
+// COM counts the usages itself, the apartment is torn down once the last one is dropped (unless a thread joined it explicitly).
+pub(crate) struct MtaUsage(Option<CO_MTA_USAGE_COOKIE>);
+
+impl MtaUsage {
+    // a failure only means the workers rely on the apartment of the app, as before
+    pub(crate) fn acquire() -> Self {
+        let cookie = unsafe { CoIncrementMTAUsage() }
+            .inspect_err(|_e| {
+                trace_event!(warn, hresult = %_e.code(), "CoIncrementMTAUsage failed");
+            })
+            .ok();
+
+        Self(cookie)
+    }
+}
+
+impl Drop for MtaUsage {
+    fn drop(&mut self) {
+        if let Some(cookie) = self.0.take() {
+            let _ = unsafe { CoDecrementMTAUsage(cookie) };
+        }
+    }
+}
+
+//the cookie is only a token for the process wide apartment, it can be released from any thread
+unsafe impl Send for MtaUsage {}
+
+unsafe impl Sync for MtaUsage {}
@@ -0,0 +1,6 @@
+#[cfg(feature = "gstreamer")]
+pub mod gstreamer;
+#[cfg(feature = "image")]
+pub mod image;
+#[cfg(feature = "wgpu")]
+pub mod wgpu;
@@ -0,0 +1,131 @@
+use ::gstreamer as gst;
+use ::gstreamer_app::AppSrc;
+
+use crate::{
+    capture_error::CaptureError,
+    devices::{Dimensions, Frame, camera::Output},
+    i_capture::ICapture,
+};
+
+/// # Caps String
+///
+/// The GStreamer caps of frames of the format and size, such as `video/x-raw,format=BGRA,width=1920,height=1080,framerate=60/1`.
+///
+/// fps is the frame rate as numerator and denominator, None for captures without a fixed rate (framerate=0/1, such as monitors that only deliver on change).
+/// None is returned for formats GStreamer has no matching caps for (16 bit float HDR frames and PCM audio).
+pub fn caps_string(
+    format: Output,
+    width: u32,
+    height: u32,
+    fps: Option<(u32, u32)>,
+) -> Option<String> {
+    let media = match format {
+        Output::RGB32 => "video/x-raw,format=BGRA",
+        Output::NV12 => "video/x-raw,format=NV12",
+        Output::YUY2 => "video/x-raw,format=YUY2",
+        //red is in the lowest 10 bits, as in DXGI_FORMAT_R10G10B10A2_UNORM
+        Output::RGB10A2 => "video/x-raw,format=RGB10A2_LE",
+        Output::MJPEG => "image/jpeg",
+        Output::RGBA16F | Output::PCM => return None,
+    };
+
+    let (numerator, denominator) = fps.unwrap_or((0, 1));
+
+    Some(format!(
+        "{media},width={width},height={height},framerate={numerator}/{}",
+        denominator.max(1)
+    ))
+}
+
+/// # Caps
+///
+/// The caps of caps_string parsed for an AppSrc.
+///
+/// Returns CaptureError::UnsupportedFormat when GStreamer has no caps for the format.
+pub fn caps(
+    format: Output,
+    width: u32,
+    height: u32,
+    fps: Option<(u32, u32)>,
+) -> Result<gst::Caps, CaptureError> {
+    let caps = caps_string(format, width, height, fps).ok_or_else(|| {
+        CaptureError::UnsupportedFormat(format!("GStreamer has no caps for {format:?} frames"))
+    })?;
+
+    caps.parse::<gst::Caps>()
+        .map_err(|e| CaptureError::Other(format!("the caps {caps} could not be parsed: {e}")))
+}
+
+/// # Frame To Buffer
+///
+/// Moves the data of a frame into a GStreamer buffer without copying it, the presentation timestamp is the timestamp of the frame.
+///
+/// Raw frames must be tightly packed (the default of every capture), GStreamer expects rows without padding for these caps.
+pub fn frame_to_buffer(frame: Frame) -> gst::Buffer {
+    let timestamp = gst::ClockTime::from_nseconds(frame.timestamp.as_nanos() as u64);
+
+    let mut buffer = gst::Buffer::from_mut_slice(frame.data);
+
+    //the buffer was just created, so it is writable
+    if let Some(buffer) = buffer.get_mut() {
+        buffer.set_pts(timestamp);
+    }
+
+    buffer
+}
+
+/// # Feed App Src
+///
+/// Sets the caps of the app source from the output format and dimensions of the capture and pushes every frame of its receiver
+/// into it, until the capture stops sending frames. The end of stream is signaled afterwards and the number of pushed frames returned.
+///
+/// Start the capture and set the pipeline to playing yourself (GStreamer must be initialized with gstreamer::init), the receiver is locked while feeding.
+///
+/// ```rs
+/// let pipeline = gstreamer::parse::launch("appsrc name=src ! videoconvert ! autovideosink")?;
+/// let src = pipeline.downcast_ref::<gstreamer::Bin>().unwrap().by_name("src").unwrap();
+/// let src = src.downcast::<gstreamer_app::AppSrc>().unwrap();
+///
+/// pipeline.set_state(gstreamer::State::Playing)?;
+/// tokio::spawn(monitor.clone().start_capturing());
+///
+/// let frames = feed_app_src(monitor.as_ref(), &src, Some((60, 1))).await?;
+/// ```
+///
+/// Returns CaptureError::Other when the pipeline refuses a buffer, such as after it was stopped.
+pub async fn feed_app_src<S: ICapture<CaptureOutput = Frame> + ?Sized>(
+    capture: &S,
+    app_src: &AppSrc,
+    fps: Option<(u32, u32)>,
+) -> Result<u64, CaptureError> {
+    let Dimensions { width, height } = capture.get_dimensions()?;
+
+    app_src.set_caps(Some(&caps(
+        capture.get_output_format(),
+        width,
+        height,
+        fps,
+    )?));
+    app_src.set_format(gst::Format::Time);
+    app_src.set_is_live(true);
+
+    let receiver = capture.clone_receiver();
+    let mut receiver = receiver.lock().await;
+
+    let mut pushed = 0;
+
+    while let Some(frame) = receiver.recv().await {
+        app_src
+            .push_buffer(frame_to_buffer(frame))
+            .map_err(|e| CaptureError::Other(format!("the app source refused a frame: {e:?}")))?;
+
+        pushed += 1;
+    }
+
+    trace_event!(debug, pushed, "capture ended, signaling the end of stream");
+
+    //a pipeline that already stopped does not need the end of stream
+    let _ = app_src.end_of_stream();
+
+    Ok(pushed)
+}
@@ -0,0 +1,97 @@
+use ::image::{DynamicImage, ImageFormat, RgbImage, Rgba16Image, Rgba32FImage};
+
+use crate::{
+    capture_error::CaptureError,
+    devices::{camera::Output, tone_map::half_to_f32},
+    encoding::yuv::{nv12_to_bgra, yuy2_to_bgra},
+};
+
+/// # Frame To Image
+///
+/// Converts the data of a frame into an image of the image crate, for resizing, filtering or saving it.
+///
+/// Uncompressed frames must be tightly packed, which is the default of every capture (see Frame::stride).
+///
+/// - RGB32, NV12 and YUY2 become an ImageRgb8, the unused fourth byte of RGB32 is dropped and the YUV formats are converted with BT.601.
+/// - RGB10A2 becomes an ImageRgba16 holding the raw (PQ encoded) values scaled to 16 bits.
+/// - RGBA16F becomes an ImageRgba32F holding the linear scRGB values.
+/// - MJPEG is decoded.
+///
+/// Returns CaptureError::Other when the data is too short for the size or the JPEG cannot be decoded.
+pub fn frame_to_image(
+    frame: &[u8],
+    width: u32,
+    height: u32,
+    format: Output,
+) -> Result<DynamicImage, CaptureError> {
+    if format == Output::PCM {
+        return Err(CaptureError::Other(
+            "PCM audio can not be made into an image".into(),
+        ));
+    }
+
+    if format == Output::MJPEG {
+        return ::image::load_from_memory_with_format(frame, ImageFormat::Jpeg)
+            .map_err(|e| CaptureError::Other(e.to_string()));
+    }
+
+    let (w, h) = (width as usize, height as usize);
+    let size = format.frame_size(width, height);
+
+    if frame.len() < size {
+        return Err(CaptureError::Other(format!(
+            "a {width}x{height} {format:?} frame needs {size} bytes, got {}",
+            frame.len()
+        )));
+    }
+
+    let frame = &frame[..size];
+
+    let image = match format {
+        Output::RGB32 => rgb8(width, height, frame),
+        Output::NV12 => rgb8(width, height, &nv12_to_bgra(frame, w, h)),
+        Output::YUY2 => rgb8(width, height, &yuy2_to_bgra(frame, w, h)),
+        Output::RGB10A2 => {
+            let pixels = frame
+                .chunks_exact(4)
+                .flat_map(|pixel| {
+                    let packed = u32::from_le_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]);
+
+                    let [r, g, b] = [0, 10, 20].map(|shift| {
+                        let value = ((packed >> shift) & 0x3FF) as u16;
+                        value << 6 | value >> 4
+                    });
+
+                    [r, g, b, (packed >> 30) as u16 * 0x5555]
+                })
+                .collect();
+
+            Rgba16Image::from_raw(width, height, pixels).map(DynamicImage::ImageRgba16)
+        }
+        Output::RGBA16F => {
+            let pixels = frame
+                .chunks_exact(2)
+                .map(|half| half_to_f32(u16::from_le_bytes([half[0], half[1]])))
+                .collect();
+
+            Rgba32FImage::from_raw(width, height, pixels).map(DynamicImage::ImageRgba32F)
+        }
+        Output::MJPEG | Output::PCM => unreachable!("MJPEG and PCM frames are handled above"),
+    };
+
+    image.ok_or_else(|| {
+        CaptureError::Other(format!(
+            "a {width}x{height} {format:?} frame could not be made into an image"
+        ))
+    })
+}
+
+// drops the fourth byte of BGRA(X) pixels
+fn rgb8(width: u32, height: u32, bgra: &[u8]) -> Option<DynamicImage> {
+    let pixels = bgra
+        .chunks_exact(4)
+        .flat_map(|pixel| [pixel[2], pixel[1], pixel[0]])
+        .collect();
+
+    RgbImage::from_raw(width, height, pixels).map(DynamicImage::ImageRgb8)
+}
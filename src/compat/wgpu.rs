@@ -0,0 +1,101 @@
+use std::borrow::Cow;
+
+use ::wgpu::{
+    Device, Extent3d, Origin3d, Queue, TexelCopyBufferLayout, TexelCopyTextureInfo, Texture,
+    TextureAspect, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+};
+
+use crate::{
+    capture_error::CaptureError,
+    convert::check_size,
+    devices::camera::Output,
+    encoding::yuv::{nv12_to_bgra, yuy2_to_bgra},
+};
+
+/// # Texture Format
+///
+/// The wgpu format upload_frame creates the texture of a frame with, None for MJPEG which must be decoded first and for PCM audio.
+///
+/// NV12 and YUY2 frames are converted to BGRA before they are uploaded.
+pub fn texture_format(format: Output) -> Option<TextureFormat> {
+    match format {
+        Output::RGB32 | Output::NV12 | Output::YUY2 => Some(TextureFormat::Bgra8Unorm),
+        Output::RGB10A2 => Some(TextureFormat::Rgb10a2Unorm),
+        Output::RGBA16F => Some(TextureFormat::Rgba16Float),
+        Output::MJPEG | Output::PCM => None,
+    }
+}
+
+/// # Upload Frame
+///
+/// Creates a 2D texture for a tightly packed frame and writes the frame into it with the queue, the texture can be sampled and copied to right away.
+///
+/// The label shows up in wgpu validation errors and graphics debuggers. See texture_format for the format of the texture.
+///
+/// Returns CaptureError::UnsupportedFormat for MJPEG frames and CaptureError::Other when the data is too short for the size.
+pub fn upload_frame(
+    device: &Device,
+    queue: &Queue,
+    frame: &[u8],
+    width: u32,
+    height: u32,
+    format: Output,
+    label: Option<&str>,
+) -> Result<Texture, CaptureError> {
+    let Some(texture_format) = texture_format(format) else {
+        return Err(CaptureError::UnsupportedFormat(format!(
+            "{format:?} frames must be decoded before they can be uploaded"
+        )));
+    };
+
+    check_size(format, frame, width, height)?;
+
+    let (w, h) = (width as usize, height as usize);
+
+    let data = match format {
+        Output::NV12 => Cow::Owned(nv12_to_bgra(frame, w, h)),
+        Output::YUY2 => Cow::Owned(yuy2_to_bgra(frame, w, h)),
+        _ => Cow::Borrowed(&frame[..format.frame_size(width, height)]),
+    };
+
+    let size = Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+    };
+
+    let texture = device.create_texture(&TextureDescriptor {
+        label,
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: texture_format,
+        usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST | TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+
+    //NV12 and YUY2 are BGRA now
+    let bytes_per_row = match format {
+        Output::NV12 | Output::YUY2 => width * 4,
+        _ => format.stride(width) as u32,
+    };
+
+    queue.write_texture(
+        TexelCopyTextureInfo {
+            texture: &texture,
+            mip_level: 0,
+            origin: Origin3d::ZERO,
+            aspect: TextureAspect::All,
+        },
+        &data,
+        TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(bytes_per_row),
+            rows_per_image: Some(height),
+        },
+        size,
+    );
+
+    Ok(texture)
+}
@@ -0,0 +1,221 @@
+use crate::{
+    capture_error::CaptureError,
+    colorspace::bgra_row_to_rgb32,
+    devices::{PixelFormat, camera::Output, tone_map::tone_map_to_bgra},
+    encoding::yuv,
+};
+
+/// # NV12 To BGRA
+///
+/// Converts a tightly packed NV12 frame (a Y plane followed by an interleaved UV plane, width * height * 3 / 2 bytes) to tightly packed BGRA for display.
+///
+/// The colors are converted with BT.601 limited range, which cameras use. Every 2x2 block of pixels shares the chroma of the block.
+///
+/// Returns CaptureError::Other when the data is too short for the size.
+pub fn nv12_to_bgra(data: &[u8], width: u32, height: u32) -> Result<Vec<u8>, CaptureError> {
+    check_size(Output::NV12, data, width, height)?;
+
+    Ok(yuv::nv12_to_bgra(data, width as usize, height as usize))
+}
+
+/// # YUY2 To BGRA
+///
+/// Converts a tightly packed YUY2 frame (packed 4:2:2, width * height * 2 bytes) to tightly packed BGRA, for previewing frames of a camera delivering YUY2.
+///
+/// The colors are converted with BT.601 limited range, every two pixels of a row share their chroma.
+///
+/// Returns CaptureError::Other when the data is too short for the size.
+pub fn yuy2_to_bgra(data: &[u8], width: u32, height: u32) -> Result<Vec<u8>, CaptureError> {
+    check_size(Output::YUY2, data, width, height)?;
+
+    Ok(yuv::yuy2_to_bgra(data, width as usize, height as usize))
+}
+
+/// # BGRA To 0RGB
+///
+/// Packs a BGRA frame into one u32 per pixel as 0x00RRGGBB, the layout of minifb and most software framebuffers, the alpha channel is dropped.
+///
+/// stride is the bytes of a row (Frame::stride), 0 for tightly packed rows, the padding of the rows is skipped. The result is tightly packed, width * height values.
+///
+/// Returns CaptureError::Other when the stride is shorter than a row or the data is too short for the size.
+pub fn bgra_to_0rgb(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    stride: usize,
+) -> Result<Vec<u32>, CaptureError> {
+    let mut packed = vec![0u32; width as usize * height as usize];
+
+    bgra_to_0rgb_into(data, &mut packed, width, height, stride)?;
+
+    Ok(packed)
+}
+
+/// # BGRA To 0RGB Into
+///
+/// bgra_to_0rgb into a buffer of at least width * height values, so the same framebuffer can be filled with every frame.
+/// The values past the frame are left as they are.
+///
+/// Returns CaptureError::Other when the stride is shorter than a row or a buffer is too short for the size.
+pub fn bgra_to_0rgb_into(
+    data: &[u8],
+    dst: &mut [u32],
+    width: u32,
+    height: u32,
+    stride: usize,
+) -> Result<(), CaptureError> {
+    let row_len = width as usize * 4;
+    let stride = if stride > 0 { stride } else { row_len };
+
+    //the last row does not need the padding of the stride
+    let size = match height as usize {
+        0 => 0,
+        rows => stride * (rows - 1) + row_len,
+    };
+
+    if stride < row_len || data.len() < size {
+        return Err(CaptureError::Other(format!(
+            "a {width}x{height} BGRA frame with a stride of {stride} needs {size} bytes, got {}",
+            data.len()
+        )));
+    }
+
+    let pixels = width as usize * height as usize;
+
+    if dst.len() < pixels {
+        return Err(CaptureError::Other(format!(
+            "a {width}x{height} frame needs {pixels} values, got {}",
+            dst.len()
+        )));
+    }
+
+    if pixels == 0 {
+        return Ok(());
+    }
+
+    for (row, packed) in dst[..pixels].chunks_exact_mut(width as usize).enumerate() {
+        bgra_row_to_rgb32(&data[row * stride..row * stride + row_len], packed);
+    }
+
+    Ok(())
+}
+
+/// # HDR To BGRA
+///
+/// Tone maps a raw HDR frame (see HdrMode::Raw) to tightly packed 8 bit BGRA, for an SDR preview of frames that are kept in their full range.
+///
+/// format is the layout of the frame (Monitor::pixel_format when it was captured), stride the bytes of a row (Frame::stride), 0 for tightly packed rows.
+/// sdr_white_nits is the brightness that becomes pure white, normally Monitor::sdr_white_level. Bgra8 frames are copied unchanged.
+///
+/// This is the same conversion a Monitor does with HdrMode::ToneMap. Returns CaptureError::Other when the data is too short for the size.
+pub fn hdr_to_bgra(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    stride: usize,
+    format: PixelFormat,
+    sdr_white_nits: f32,
+) -> Result<Vec<u8>, CaptureError> {
+    let row_len = width as usize * format.bytes_per_pixel();
+    let stride = if stride > 0 { stride } else { row_len };
+
+    //the last row does not need the padding of the stride
+    let size = match height as usize {
+        0 => 0,
+        rows => stride * (rows - 1) + row_len,
+    };
+
+    if stride < row_len || data.len() < size {
+        return Err(CaptureError::Other(format!(
+            "a {width}x{height} {format:?} frame with a stride of {stride} needs {size} bytes, got {}",
+            data.len()
+        )));
+    }
+
+    Ok(tone_map_to_bgra(
+        data,
+        stride,
+        width as usize,
+        height as usize,
+        format,
+        sdr_white_nits.max(1.0),
+    ))
+}
+
+/// # Decode MJPEG Frame
+///
+/// Decodes a frame of a camera delivering MJPEG (a complete JPEG image) into a frame of tightly packed BGRA, with libjpeg-turbo (the jpeg feature).
+///
+/// The stride of the decoded frame is width * 4, the capture information (timestamps, dirty rects) is kept.
+///
+/// Returns CaptureError::Other when the data is not a valid JPEG image, cameras sometimes deliver a corrupt frame after they start.
+#[cfg(feature = "jpeg")]
+pub fn decode_mjpeg_frame(
+    frame: crate::devices::Frame,
+) -> Result<crate::devices::Frame, CaptureError> {
+    let image = turbojpeg::decompress(&frame.data, turbojpeg::PixelFormat::BGRA)
+        .map_err(|e| CaptureError::Other(format!("the MJPEG frame could not be decoded: {e}")))?;
+
+    Ok(crate::devices::Frame {
+        data: image.pixels,
+        stride: image.pitch,
+        ..frame
+    })
+}
+
+// errors when the data is shorter than a tightly packed frame of the format
+pub(crate) fn check_size(
+    format: Output,
+    data: &[u8],
+    width: u32,
+    height: u32,
+) -> Result<(), CaptureError> {
+    let size = format.frame_size(width, height);
+
+    if data.len() < size {
+        return Err(CaptureError::Other(format!(
+            "a {width}x{height} {format:?} frame needs {size} bytes, got {}",
+            data.len()
+        )));
+    }
+
+    Ok(())
+}
+
+// downscales tightly packed BGRA by averaging the box of source pixels under every pixel, larger sizes repeat pixels
+pub(crate) fn scale_bgra(
+    data: &[u8],
+    width: usize,
+    height: usize,
+    new_width: usize,
+    new_height: usize,
+) -> Vec<u8> {
+    let mut scaled = Vec::with_capacity(new_width * new_height * 4);
+
+    for y in 0..new_height {
+        let top = y * height / new_height;
+        let bottom = ((y + 1) * height / new_height).max(top + 1);
+
+        for x in 0..new_width {
+            let left = x * width / new_width;
+            let right = ((x + 1) * width / new_width).max(left + 1);
+
+            let mut sum = [0u32; 4];
+
+            for row in top..bottom {
+                let start = (row * width + left) * 4;
+
+                for pixel in data[start..start + (right - left) * 4].chunks_exact(4) {
+                    for c in 0..4 {
+                        sum[c] += pixel[c] as u32;
+                    }
+                }
+            }
+
+            let count = ((bottom - top) * (right - left)) as u32;
+            scaled.extend(sum.map(|channel| (channel / count) as u8));
+        }
+    }
+
+    scaled
+}
@@ -1,42 +1,235 @@
+pub mod adapter_info;
+pub mod aspect_mode;
+pub mod audio_device;
+pub mod audio_devices;
+pub mod audio_frame;
+pub mod backpressure;
 pub mod camera;
+pub mod camera_control;
 pub mod cameras;
+pub mod change_detection;
+pub mod click_overlay;
+pub mod cursor_info;
+pub mod device_config;
+pub mod device_info;
+pub mod device_watcher;
 pub mod dimensions;
+pub mod frame;
+pub mod frame_rate;
+pub mod frame_transform;
+pub mod gdi_monitor;
+pub mod gpu_frame;
+pub(crate) mod gpu_scaler;
+pub mod hdr_mode;
+pub mod media_type_info;
 pub mod monitor;
 pub mod monitor_frame;
 pub mod monitor_info;
+pub mod multi_monitor_stitch;
+pub mod negotiated_format;
+pub mod photo;
+pub mod pixel_format;
+pub mod rect;
+pub mod retry_policy;
+pub mod rotation;
+#[cfg(feature = "serde")]
+pub mod saved_frame;
+pub mod shared_capture;
+pub mod shared_frame;
+pub(crate) mod tone_map;
 
+pub use crate::devices::adapter_info::AdapterInfo;
+pub use crate::devices::aspect_mode::AspectMode;
+pub use crate::devices::audio_device::AudioDevice;
+pub use crate::devices::audio_devices::{AudioDeviceInfo, AudioDevices};
+pub use crate::devices::audio_frame::{AudioFormat, AudioFrame, SampleFormat};
+pub use crate::devices::backpressure::Backpressure;
 pub use crate::devices::camera::Camera;
-pub use crate::devices::cameras::Cameras;
+pub use crate::devices::camera_control::{
+    CameraControls, CameraProperty, PowerLineFrequency, PropertyRange, PropertyValue,
+};
+pub use crate::devices::cameras::{Cameras, DeviceHandle, DeviceSelector};
+pub use crate::devices::change_detection::ChangeDetection;
+pub use crate::devices::click_overlay::ClickOverlay;
+pub use crate::devices::cursor_info::{CursorInfo, CursorShapeKind};
+pub use crate::devices::device_config::{AccessMode, DeviceConfig};
+pub use crate::devices::device_info::{DeviceChanges, DeviceInfo};
+pub use crate::devices::device_watcher::{DeviceEvent, DeviceWatcher};
 pub use crate::devices::dimensions::Dimensions;
+pub use crate::devices::frame::Frame;
+pub use crate::devices::frame_rate::FrameRate;
+pub use crate::devices::frame_transform::FrameTransform;
+pub use crate::devices::gdi_monitor::GdiMonitor;
+pub use crate::devices::gpu_frame::{GpuFrame, TextureLock};
+pub use crate::devices::hdr_mode::HdrMode;
+pub use crate::devices::media_type_info::MediaTypeInfo;
 pub use crate::devices::monitor::Monitor;
 pub use crate::devices::monitor_frame::MonitorFrame;
+pub use crate::devices::multi_monitor_stitch::MultiMonitorStitch;
+pub use crate::devices::negotiated_format::NegotiatedFormat;
+pub use crate::devices::photo::{Photo, PhotoSource};
+pub use crate::devices::pixel_format::PixelFormat;
+pub use crate::devices::rect::{MoveRect, Rect};
+pub use crate::devices::retry_policy::RetryPolicy;
+pub use crate::devices::rotation::Rotation;
+#[cfg(feature = "serde")]
+pub use crate::devices::saved_frame::SavedFrame;
+pub use crate::devices::shared_capture::{ShareFrames, SharedCapture};
+pub use crate::devices::shared_frame::SharedFrame;
+use crate::capture_error::CaptureError;
+use crate::capture_state::{CaptureState, StateCell};
+use crate::devices::adapter_info::enum_adapters;
 use crate::devices::monitor_info::MonitorInfo;
 
+use std::sync::RwLock;
+
+use tokio::sync::{Mutex, mpsc::{self, Receiver, Sender, error::{SendError, TrySendError}}};
+
 use windows::Win32::{
-    Graphics::Gdi::{DISPLAY_DEVICEW, EnumDisplayDevicesW},
+    Devices::Display::{
+        DISPLAYCONFIG_DEVICE_INFO_GET_SDR_WHITE_LEVEL, DISPLAYCONFIG_DEVICE_INFO_GET_SOURCE_NAME,
+        DISPLAYCONFIG_DEVICE_INFO_HEADER, DISPLAYCONFIG_MODE_INFO, DISPLAYCONFIG_PATH_INFO,
+        DISPLAYCONFIG_SDR_WHITE_LEVEL, DISPLAYCONFIG_SOURCE_DEVICE_NAME,
+        DisplayConfigGetDeviceInfo, GetDisplayConfigBufferSizes, QDC_ONLY_ACTIVE_PATHS,
+        QueryDisplayConfig,
+    },
+    Foundation::{ERROR_SUCCESS, POINT},
+    Graphics::Gdi::{DEVMODEW, DISPLAY_DEVICE_ATTACHED_TO_DESKTOP, DISPLAY_DEVICEW, ENUM_CURRENT_SETTINGS, EnumDisplayDevicesW, EnumDisplaySettingsW, MONITOR_DEFAULTTONULL, MonitorFromPoint},
     Media::MediaFoundation::{IMFActivate, MF_DEVSOURCE_ATTRIBUTE_FRIENDLY_NAME},
+    System::Com::CoTaskMemFree,
     UI::WindowsAndMessaging::{EDD_GET_DEVICE_INTERFACE_NAME, GetSystemMetrics, SM_CMONITORS},
 };
 
+// frames a capture buffers for its receiver unless set_channel_capacity is used, the lowest latency
+pub(crate) const DEFAULT_CHANNEL_CAPACITY: usize = 1;
+
+// how many events can wait for the consumer before new ones are dropped
+pub(crate) const EVENT_CHANNEL_CAPACITY: usize = 16;
+
+// replaces the channel of a capture with one of the capacity, frames still in the old channel are dropped.
+//
+// fails while the capture is running or a task is waiting on the receiver, either would keep using the old channel.
+pub(crate) fn replace_channel<T>(sender: &RwLock<Sender<T>>, receiver: &Mutex<Receiver<T>>, state: &StateCell, capacity: usize) -> Result<(), CaptureError> {
+    //a stopping loop still sends with the old sender
+    if state.get() != CaptureState::Stopped {
+        return Err(CaptureError::Other("the channel capacity cannot be changed while capturing".into()));
+    }
+
+    let Ok(mut receiver) = receiver.try_lock() else {
+        return Err(CaptureError::Other("the channel capacity cannot be changed while the receiver is locked".into()));
+    };
+
+    let (tx, rx) = mpsc::channel(capacity.max(1));
+
+    *sender.write().unwrap() = tx;
+    *receiver = rx;
+
+    Ok(())
+}
+
+// sends a frame to the receiver of a capture as the backpressure says, false when the frame was dropped because the channel is full.
+//
+// no change is lost with a dropped frame: the changes of a frame taken out of the channel are merged into the frame after it,
+// a new frame that is dropped is kept in dropped (without its data) and merged into the next frame that is sent.
+pub(crate) async fn send_with_backpressure(sender: &Sender<Frame>, receiver: &Mutex<Receiver<Frame>>, backpressure: Backpressure, dropped: &mut Option<Frame>, mut frame: Frame) -> Result<bool, SendError<Frame>> {
+    if let Some(dropped) = dropped.take() {
+        frame.merge_dropped(&dropped);
+    }
+
+    if backpressure == Backpressure::Block {
+        return sender.send(frame).await.map(|_| true);
+    }
+
+    let full = match sender.try_send(frame) {
+        Ok(()) => return Ok(true),
+        Err(TrySendError::Closed(frame)) => return Err(SendError(frame)),
+        Err(TrySendError::Full(full)) => full,
+    };
+
+    let mut frame = full;
+
+    //a consumer holding the receiver keeps the buffered frames, the new one is dropped then
+    let buffered = match backpressure {
+        Backpressure::DropOldest | Backpressure::Coalesce => receiver.try_lock().ok().map(|mut receiver| {
+            let mut buffered = vec![];
+
+            while let Ok(frame) = receiver.try_recv() {
+                buffered.push(frame);
+            }
+
+            buffered
+        }),
+        _ => None,
+    };
+
+    let Some(mut buffered) = buffered.filter(|buffered| !buffered.is_empty()) else {
+        frame.data = Vec::new();
+        *dropped = Some(frame);
+
+        return Ok(false);
+    };
+
+    if backpressure == Backpressure::Coalesce {
+        for older in &buffered {
+            frame.merge_dropped(older);
+        }
+
+        buffered.clear();
+    } else {
+        let oldest = buffered.remove(0);
+
+        match buffered.first_mut() {
+            Some(next) => next.merge_dropped(&oldest),
+            None => frame.merge_dropped(&oldest),
+        }
+    }
+
+    //only the capture loop sends, so the frames that are kept fit into the emptied channel again
+    let mut kept = buffered.into_iter().chain(std::iter::once(frame));
+
+    while let Some(next) = kept.next() {
+        match sender.try_send(next) {
+            Ok(()) => {}
+            Err(TrySendError::Closed(frame)) => return Err(SendError(frame)),
+            Err(TrySendError::Full(frame)) => {
+                let mut frame = kept.fold(frame, |older, mut newer| {
+                    newer.merge_dropped(&older);
+                    newer
+                });
+
+                frame.data = Vec::new();
+                *dropped = Some(frame);
+
+                return Ok(false);
+            }
+        }
+    }
+
+    Ok(true)
+}
+
 /// # Get Device Name
 ///
 /// From an activated device retrieves the name of the device that is friendly (meaning readible)
 ///
 /// This can be used to sort and find device names.
 pub unsafe fn get_device_name(device: &IMFActivate) -> Result<String, windows::core::Error> {
+    unsafe { get_device_string(device, &MF_DEVSOURCE_ATTRIBUTE_FRIENDLY_NAME) }
+}
+
+// reads a string attribute of a device, freeing the copy media foundation allocates
+pub(crate) unsafe fn get_device_string(device: &IMFActivate, key: &windows::core::GUID) -> Result<String, windows::core::Error> {
     unsafe {
-        let mut name_len: u32 = 0;
-        let mut pw_name: windows::core::PWSTR = windows::core::PWSTR::null();
+        let mut len: u32 = 0;
+        let mut pw_value: windows::core::PWSTR = windows::core::PWSTR::null();
+
+        device.GetAllocatedString(key, &mut pw_value, &mut len)?;
 
-        device.GetAllocatedString(
-            &MF_DEVSOURCE_ATTRIBUTE_FRIENDLY_NAME,
-            &mut pw_name,
-            &mut name_len,
-        )?;
+        let value = String::from_utf16_lossy(pw_value.as_wide());
 
-        let name = String::from_utf16_lossy(pw_name.as_wide());
+        CoTaskMemFree(Some(pw_value.0 as *const std::ffi::c_void));
 
-        Ok(name)
+        Ok(value)
     }
 }
 
@@ -63,7 +256,8 @@ pub unsafe fn get_all_monitor_info() -> Vec<MonitorInfo> {
         loop {
 
             //generate device info
-            let mut device_info = DISPLAY_DEVICEW::default();
+            //the size must be set, else windows rejects the call and no monitor is listed
+            let mut device_info = DISPLAY_DEVICEW { cb: size_of::<DISPLAY_DEVICEW>() as u32, ..Default::default() };
 
             //gets information about the display, returns false if the device index was out of bounds.
             let exist = EnumDisplayDevicesW(
@@ -82,7 +276,31 @@ pub unsafe fn get_all_monitor_info() -> Vec<MonitorInfo> {
             let device_name = String::from_utf16_lossy(&device_info.DeviceName);
             let device_desc = String::from_utf16_lossy(&device_info.DeviceString);
 
-            let info = MonitorInfo::new(device_name, device_desc, device_index);
+            let mut info = MonitorInfo::new(device_name, device_desc, device_index);
+
+            //the position and mode of the display on the virtual desktop, only displays on the desktop have a current mode
+            let mut mode = DEVMODEW { dmSize: size_of::<DEVMODEW>() as u16, ..Default::default() };
+
+            if device_info.StateFlags.contains(DISPLAY_DEVICE_ATTACHED_TO_DESKTOP)
+                && EnumDisplaySettingsW(windows::core::PCWSTR(device_info.DeviceName.as_ptr()), ENUM_CURRENT_SETTINGS, &mut mode).as_bool()
+            {
+                let position = mode.Anonymous1.Anonymous2.dmPosition;
+
+                info.left = position.x;
+                info.top = position.y;
+                info.width = mode.dmPelsWidth;
+                info.height = mode.dmPelsHeight;
+                info.refresh_rate = mode.dmDisplayFrequency;
+                info.attached = true;
+
+                //the top left pixel of a display belongs to it
+                let monitor = MonitorFromPoint(POINT { x: position.x, y: position.y }, MONITOR_DEFAULTTONULL);
+
+                if !monitor.is_invalid() {
+                    info.dpi = Monitor::query_dpi(monitor);
+                    info.scale_factor = info.dpi as f32 / 96.0;
+                }
+            }
 
             //push
             monitors.push(info);
@@ -93,3 +311,95 @@ pub unsafe fn get_all_monitor_info() -> Vec<MonitorInfo> {
 
     monitors
 }
+
+/// # Get All Adapter Info
+///
+/// Retrieves every graphics adapter of your system and how many monitors each one drives.
+///
+/// The first adapter is the default adapter, a Monitor of a specific adapter can be created with Monitor::from_adapter_output.
+pub unsafe fn get_all_adapter_info() -> Result<Vec<AdapterInfo>, windows::core::Error> {
+    unsafe {
+        enum_adapters()?
+            .iter()
+            .enumerate()
+            .map(|(index, adapter)| AdapterInfo::from_adapter(index as u32, adapter))
+            .collect()
+    }
+}
+
+/// # Get SDR White Level
+///
+/// The brightness (in nits) SDR white is displayed at for a GDI device name such as \\.\DISPLAY1 (see Monitor::name).
+///
+/// This is the "SDR content brightness" slider from the display settings. Returns None if the display could not be found.
+pub unsafe fn get_sdr_white_level(gdi_device_name: &str) -> Option<f32> {
+    let gdi_device_name = gdi_device_name.trim_end_matches('\0');
+
+    unsafe {
+        let mut path_count: u32 = 0;
+        let mut mode_count: u32 = 0;
+
+        if GetDisplayConfigBufferSizes(QDC_ONLY_ACTIVE_PATHS, &mut path_count, &mut mode_count)
+            != ERROR_SUCCESS
+        {
+            return None;
+        }
+
+        let mut paths = vec![DISPLAYCONFIG_PATH_INFO::default(); path_count as usize];
+        let mut modes = vec![DISPLAYCONFIG_MODE_INFO::default(); mode_count as usize];
+
+        if QueryDisplayConfig(
+            QDC_ONLY_ACTIVE_PATHS,
+            &mut path_count,
+            paths.as_mut_ptr(),
+            &mut mode_count,
+            modes.as_mut_ptr(),
+            None,
+        ) != ERROR_SUCCESS
+        {
+            return None;
+        }
+
+        for path in paths.iter().take(path_count as usize) {
+            //find the path that belongs to the gdi device
+            let mut source_name = DISPLAYCONFIG_SOURCE_DEVICE_NAME {
+                header: DISPLAYCONFIG_DEVICE_INFO_HEADER {
+                    r#type: DISPLAYCONFIG_DEVICE_INFO_GET_SOURCE_NAME,
+                    size: std::mem::size_of::<DISPLAYCONFIG_SOURCE_DEVICE_NAME>() as u32,
+                    adapterId: path.sourceInfo.adapterId,
+                    id: path.sourceInfo.id,
+                },
+                ..Default::default()
+            };
+
+            if DisplayConfigGetDeviceInfo(&mut source_name.header) != 0 {
+                continue;
+            }
+
+            let name = String::from_utf16_lossy(&source_name.viewGdiDeviceName);
+
+            if name.trim_end_matches('\0') != gdi_device_name {
+                continue;
+            }
+
+            let mut white_level = DISPLAYCONFIG_SDR_WHITE_LEVEL {
+                header: DISPLAYCONFIG_DEVICE_INFO_HEADER {
+                    r#type: DISPLAYCONFIG_DEVICE_INFO_GET_SDR_WHITE_LEVEL,
+                    size: std::mem::size_of::<DISPLAYCONFIG_SDR_WHITE_LEVEL>() as u32,
+                    adapterId: path.targetInfo.adapterId,
+                    id: path.targetInfo.id,
+                },
+                ..Default::default()
+            };
+
+            if DisplayConfigGetDeviceInfo(&mut white_level.header) != 0 {
+                return None;
+            }
+
+            //the white level is a multiplier of 80 nits, stored * 1000
+            return Some(white_level.SDRWhiteLevel as f32 / 1000.0 * 80.0);
+        }
+
+        None
+    }
+}
@@ -0,0 +1,82 @@
+use windows::Win32::{
+    Foundation::LUID,
+    Graphics::Dxgi::{
+        CreateDXGIFactory1, DXGI_ADAPTER_FLAG_SOFTWARE, IDXGIAdapter1, IDXGIFactory1,
+    },
+};
+
+/// # Adapter Info
+///
+/// A graphics adapter (GPU) of the system and the number of monitors it drives.
+///
+/// On hybrid graphics laptops the internal display is usually driven by the integrated GPU and external displays by either GPU, a Monitor has to be created on the adapter driving its output.
+#[derive(Clone, Debug)]
+pub struct AdapterInfo {
+    /// The index of the adapter, used with Monitor::from_adapter_output.
+    pub index: u32,
+
+    /// The description of the adapter, for example "Intel(R) UHD Graphics".
+    pub name: String,
+
+    /// The locally unique identifier of the adapter, it stays the same until the system restarts.
+    pub luid: LUID,
+
+    /// The video memory that is not shared with the CPU in bytes, small or 0 for integrated GPUs.
+    pub dedicated_video_memory: usize,
+
+    /// If this is a software adapter (such as the Microsoft Basic Render Driver).
+    pub software: bool,
+
+    /// The number of outputs (monitors) driven by the adapter.
+    pub outputs: u32,
+}
+
+impl AdapterInfo {
+    pub(crate) unsafe fn from_adapter(
+        index: u32,
+        adapter: &IDXGIAdapter1,
+    ) -> Result<Self, windows::core::Error> {
+        let desc = unsafe { adapter.GetDesc1()? };
+
+        let name_len = desc
+            .Description
+            .iter()
+            .position(|&c| c == 0)
+            .unwrap_or(desc.Description.len());
+
+        Ok(Self {
+            index,
+            name: String::from_utf16_lossy(&desc.Description[..name_len]),
+            luid: desc.AdapterLuid,
+            dedicated_video_memory: desc.DedicatedVideoMemory,
+            software: desc.Flags & DXGI_ADAPTER_FLAG_SOFTWARE.0 as u32 != 0,
+            outputs: unsafe { output_count(adapter) },
+        })
+    }
+}
+
+// every adapter of the system in the order DXGI enumerates them, the first is the default adapter
+pub(crate) unsafe fn enum_adapters() -> Result<Vec<IDXGIAdapter1>, windows::core::Error> {
+    let mut adapters = vec![];
+
+    unsafe {
+        let factory: IDXGIFactory1 = CreateDXGIFactory1()?;
+
+        while let Ok(adapter) = factory.EnumAdapters1(adapters.len() as u32) {
+            adapters.push(adapter);
+        }
+    }
+
+    Ok(adapters)
+}
+
+// the number of outputs connected to an adapter
+pub(crate) unsafe fn output_count(adapter: &IDXGIAdapter1) -> u32 {
+    let mut count = 0;
+
+    while unsafe { adapter.EnumOutputs(count) }.is_ok() {
+        count += 1;
+    }
+
+    count
+}
@@ -0,0 +1,15 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+/// # Aspect Mode
+///
+/// How a Monitor fits its frames into an output size with a different aspect ratio.
+pub enum AspectMode {
+    /// Scale the width and height independently so the frame fills the output size, the image may be distorted.
+    #[default]
+    Stretch,
+
+    /// Scale the frame to fit inside the output size, the unused area is filled with black bars.
+    Letterbox,
+
+    /// Scale the frame to cover the output size, the edges that do not fit are cut off.
+    Crop,
+}
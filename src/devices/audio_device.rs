@@ -0,0 +1,382 @@
+use std::{
+    pin::Pin,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use tokio::sync::{
+    Mutex,
+    mpsc::{self, Receiver, Sender},
+};
+use windows::Win32::{
+    Media::{
+        Audio::{
+            AUDCLNT_BUFFERFLAGS_DATA_DISCONTINUITY, AUDCLNT_BUFFERFLAGS_SILENT,
+            AUDCLNT_E_DEVICE_INVALIDATED, AUDCLNT_SHAREMODE_SHARED, AUDCLNT_STREAMFLAGS_LOOPBACK,
+            IAudioCaptureClient, IAudioClient, IMMDevice, WAVE_FORMAT_PCM, WAVEFORMATEX,
+            WAVEFORMATEXTENSIBLE,
+        },
+        KernelStreaming::{KSDATAFORMAT_SUBTYPE_PCM, WAVE_FORMAT_EXTENSIBLE},
+        Multimedia::{KSDATAFORMAT_SUBTYPE_IEEE_FLOAT, WAVE_FORMAT_IEEE_FLOAT},
+    },
+    System::Com::{CLSCTX_ALL, CoTaskMemFree},
+};
+
+use crate::{
+    capture_error::CaptureError,
+    capture_state::StateCell,
+    com::MtaUsage,
+    devices::{AudioFormat, AudioFrame, Dimensions, SampleFormat, camera::Output},
+    frame_callback::FrameCallback,
+    i_capture::ICapture,
+};
+
+// how much audio windows buffers for the device between two reads, in 100 ns units
+const BUFFER_DURATION: i64 = 2_000_000;
+
+// how often the capture loop reads the packets windows buffered, well within the buffer duration
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+// packets buffered for the receiver, windows delivers a packet about every 10 ms
+const AUDIO_CHANNEL_CAPACITY: usize = 64;
+
+/// # Audio Device
+///
+/// A microphone or the loopback of an output endpoint, opened by AudioDevices and captured through WASAPI in shared mode.
+///
+/// Capturing sends an AudioFrame of PCM samples in the mix format of windows (see format) to the receiver for every packet of the device,
+/// timestamped on the same clock as monitor and camera frames so audio and video can be muxed together.
+pub struct AudioDevice {
+    client: IAudioClient,
+    capture_client: IAudioCaptureClient,
+
+    // the format of the samples, the mix format of the endpoint
+    format: AudioFormat,
+
+    // the friendly name of the endpoint
+    name: String,
+
+    // if the render endpoint is recorded instead of a capture endpoint
+    loopback: bool,
+
+    /// The receiver, can be used to grab the audio packets directly.
+    pub receiver: Arc<Mutex<Receiver<AudioFrame>>>,
+
+    sender: Sender<AudioFrame>,
+
+    // how long recv_frame waits for a packet
+    frame_timeout: RwLock<Option<Duration>>,
+
+    // the callback registered with on_frame
+    frame_callback: FrameCallback<AudioFrame>,
+
+    // if the capture loop runs, see ICapture::state
+    state: StateCell,
+
+    // the capture loop runs on tokio workers, which are in the multithreaded apartment while it is alive
+    _mta: MtaUsage,
+}
+
+impl AudioDevice {
+    // opens the endpoint in shared mode with its mix format
+    pub(crate) unsafe fn from_endpoint(
+        device: &IMMDevice,
+        name: String,
+        loopback: bool,
+    ) -> Result<Arc<Self>, CaptureError> {
+        unsafe {
+            let client: IAudioClient = device.Activate(CLSCTX_ALL, None)?;
+
+            let flags = if loopback {
+                AUDCLNT_STREAMFLAGS_LOOPBACK
+            } else {
+                0
+            };
+
+            //the stream is opened with the mix format, so windows never has to convert the samples
+            let mix_format = client.GetMixFormat()?;
+
+            let format = audio_format(mix_format).and_then(|format| {
+                client.Initialize(
+                    AUDCLNT_SHAREMODE_SHARED,
+                    flags,
+                    BUFFER_DURATION,
+                    0,
+                    mix_format,
+                    None,
+                )?;
+
+                Ok(format)
+            });
+
+            CoTaskMemFree(Some(mix_format as *const std::ffi::c_void));
+
+            let format = format?;
+
+            let capture_client: IAudioCaptureClient = client.GetService()?;
+
+            trace_event!(
+                debug,
+                sample_rate = format.sample_rate,
+                channels = format.channels,
+                loopback,
+                "audio device activated"
+            );
+
+            let (tx, rx) = mpsc::channel(AUDIO_CHANNEL_CAPACITY);
+
+            Ok(Arc::new(Self {
+                client,
+                capture_client,
+                format,
+                name,
+                loopback,
+                receiver: Arc::new(Mutex::new(rx)),
+                sender: tx,
+                frame_timeout: RwLock::new(None),
+                frame_callback: FrameCallback::default(),
+                state: StateCell::default(),
+                _mta: MtaUsage::acquire(),
+            }))
+        }
+    }
+
+    /// # Format
+    ///
+    /// The sample rate, channels and sample format of every AudioFrame, usually 32 bit float at 48000 Hz.
+    pub fn format(&self) -> AudioFormat {
+        self.format
+    }
+
+    /// # Name
+    ///
+    /// The friendly name of the endpoint.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// # Is Loopback
+    ///
+    /// If the device records what an output endpoint plays.
+    pub fn is_loopback(&self) -> bool {
+        self.loopback
+    }
+
+    // reads the packets windows buffered and sends them
+    async fn send_packets(&self) -> Result<(), CaptureError> {
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+
+        while self.is_capturing() {
+            interval.tick().await;
+
+            loop {
+                let packet = unsafe { self.read_packet() }.map_err(|e| {
+                    trace_event!(error, hresult = %e.code(), "reading an audio packet failed");
+
+                    if e.code() == AUDCLNT_E_DEVICE_INVALIDATED {
+                        CaptureError::DeviceLost
+                    } else {
+                        e.into()
+                    }
+                })?;
+
+                let Some(packet) = packet else {
+                    break;
+                };
+
+                self.sender.send(packet).await.map_err(|e| {
+                    trace_event!(warn, "receiver dropped, stopping audio capture");
+                    CaptureError::Other(format!("Failed to send audio: {}", e))
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // copies the next packet out of the buffer of windows, None when no packet is waiting
+    unsafe fn read_packet(&self) -> Result<Option<AudioFrame>, windows::core::Error> {
+        unsafe {
+            if self.capture_client.GetNextPacketSize()? == 0 {
+                return Ok(None);
+            }
+
+            let mut data = std::ptr::null_mut();
+            let mut frames = 0;
+            let mut flags = 0;
+            let mut position = 0;
+            let mut qpc_position = 0;
+
+            self.capture_client.GetBuffer(
+                &mut data,
+                &mut frames,
+                &mut flags,
+                Some(&mut position),
+                Some(&mut qpc_position),
+            )?;
+
+            let len = frames as usize * self.format.block_align();
+            let is_silent = flags & AUDCLNT_BUFFERFLAGS_SILENT.0 as u32 != 0;
+
+            let samples = if is_silent || data.is_null() {
+                vec![0; len]
+            } else {
+                std::slice::from_raw_parts(data, len).to_vec()
+            };
+
+            self.capture_client.ReleaseBuffer(frames)?;
+
+            Ok(Some(AudioFrame {
+                data: samples,
+                frames: frames as usize,
+                format: self.format,
+                timestamp: self.format.duration_of(position),
+                //the performance counter position of WASAPI is already in 100 ns units
+                presentation_time: qpc_position as i64,
+                is_silent,
+                discontinuity: flags & AUDCLNT_BUFFERFLAGS_DATA_DISCONTINUITY.0 as u32 != 0,
+            }))
+        }
+    }
+}
+
+// the sample format of a wave format, floats and integers of 16, 24 or 32 bits are supported
+unsafe fn audio_format(format: *const WAVEFORMATEX) -> Result<AudioFormat, CaptureError> {
+    let wave = unsafe { std::ptr::read_unaligned(format) };
+
+    let is_float = match wave.wFormatTag as u32 {
+        WAVE_FORMAT_IEEE_FLOAT => true,
+        WAVE_FORMAT_PCM => false,
+        WAVE_FORMAT_EXTENSIBLE => {
+            let extensible =
+                unsafe { std::ptr::read_unaligned(format as *const WAVEFORMATEXTENSIBLE) };
+            let sub_format = extensible.SubFormat;
+
+            if sub_format == KSDATAFORMAT_SUBTYPE_IEEE_FLOAT {
+                true
+            } else if sub_format == KSDATAFORMAT_SUBTYPE_PCM {
+                false
+            } else {
+                return Err(CaptureError::UnsupportedFormat(format!(
+                    "the audio sub format {sub_format:?} is not PCM or float"
+                )));
+            }
+        }
+        tag => {
+            return Err(CaptureError::UnsupportedFormat(format!(
+                "the audio format tag {tag} is not PCM or float"
+            )));
+        }
+    };
+
+    let sample_format = match (is_float, wave.wBitsPerSample) {
+        (true, 32) => SampleFormat::F32,
+        (false, 16) => SampleFormat::I16,
+        (false, 24) => SampleFormat::I24,
+        (false, 32) => SampleFormat::I32,
+        (_, bits) => {
+            return Err(CaptureError::UnsupportedFormat(format!(
+                "audio samples of {bits} bits are not supported"
+            )));
+        }
+    };
+
+    Ok(AudioFormat {
+        sample_rate: wave.nSamplesPerSec,
+        channels: wave.nChannels,
+        sample_format,
+    })
+}
+
+impl ICapture for AudioDevice {
+    type CaptureOutput = AudioFrame;
+
+    /// # Get Dimensions
+    ///
+    /// Audio has no dimensions, this always returns an error.
+    fn get_dimensions(&self) -> Result<Dimensions, Box<dyn std::error::Error>> {
+        Err("an audio device has no dimensions, see AudioDevice::format".into())
+    }
+
+    /// # Get Output Format
+    ///
+    /// Always PCM, the sample format is described by AudioDevice::format.
+    fn get_output_format(&self) -> Output {
+        Output::PCM
+    }
+
+    /// # Stop Capturing
+    ///
+    /// Stops reading packets, the capture loop ends within a poll interval.
+    fn stop_capturing(
+        self: Arc<Self>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>> + Send>> {
+        Box::pin(async move {
+            //the loop ends once it sees the state is not capturing anymore
+            if !self.state.stop() {
+                return Err("already stopped.".into());
+            }
+
+            Ok(())
+        })
+    }
+
+    /// # Start Capturing
+    ///
+    /// Starts the stream of the endpoint and sends its packets to the receiver until stop_capturing is called.
+    ///
+    /// Ends with CaptureError::DeviceLost when the endpoint is unplugged or disabled.
+    fn start_capturing(
+        self: Arc<Self>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>> + Send>> {
+        Box::pin(async move {
+            //the state is stopped again when the loop ends, also with an error
+            let _running = self.state.start().await?;
+
+            if let Err(e) = unsafe { self.client.Start() } {
+                return Err(e.into());
+            }
+
+            let sent = self.send_packets().await;
+
+            //packets left in the buffer of windows are dropped, the next start begins with new audio
+            unsafe {
+                let _ = self.client.Stop();
+                let _ = self.client.Reset();
+            }
+
+            sent?;
+
+            Ok(())
+        })
+    }
+
+    fn clone_receiver(&self) -> Arc<Mutex<Receiver<Self::CaptureOutput>>> {
+        self.receiver.clone()
+    }
+
+    fn set_frame_timeout(&self, duration: Duration) {
+        *self.frame_timeout.write().unwrap() = Some(duration);
+    }
+
+    fn clear_frame_timeout(&self) {
+        *self.frame_timeout.write().unwrap() = None;
+    }
+
+    fn get_frame_timeout(&self) -> Option<Duration> {
+        *self.frame_timeout.read().unwrap()
+    }
+
+    fn frame_callback(&self) -> &FrameCallback<AudioFrame> {
+        &self.frame_callback
+    }
+
+    fn state_cell(&self) -> &StateCell {
+        &self.state
+    }
+}
+
+//the audio client is free threaded, the capture loop uses it from the workers in the multithreaded apartment
+unsafe impl Send for AudioDevice {}
+
+unsafe impl Sync for AudioDevice {}
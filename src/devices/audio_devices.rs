@@ -0,0 +1,175 @@
+use std::sync::Arc;
+
+use windows::Win32::{
+    Devices::FunctionDiscovery::PKEY_Device_FriendlyName,
+    Media::Audio::{
+        DEVICE_STATE_ACTIVE, EDataFlow, IMMDevice, IMMDeviceEnumerator, MMDeviceEnumerator,
+        eCapture, eConsole, eRender,
+    },
+    System::Com::{CLSCTX_ALL, CoCreateInstance, CoTaskMemFree, STGM_READ},
+};
+
+use crate::capture_error::CaptureError;
+use crate::devices::AudioDevice;
+use crate::init::ensure_com;
+
+/// # Audio Device Info
+///
+/// Describes an audio endpoint found by AudioDevices, for showing it to a user and finding it again later.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct AudioDeviceInfo {
+    /// The readable name of the endpoint, for example "Microphone (USB Audio Device)".
+    pub friendly_name: String,
+
+    /// The endpoint id of windows, this stays the same across reboots, unlike the index.
+    pub id: String,
+
+    /// The index of the endpoint in AudioDevices::infos.
+    pub index: usize,
+}
+
+/// # Audio Devices
+///
+/// The active audio capture endpoints (microphones, line inputs) of the system, enumerated with WASAPI.
+///
+/// The system audio output can be recorded as well with activate_loopback, for screen recordings with sound.
+pub struct AudioDevices {
+    enumerator: IMMDeviceEnumerator,
+
+    // the capture endpoints, in the same order as infos
+    devices: Vec<IMMDevice>,
+
+    infos: Vec<AudioDeviceInfo>,
+}
+
+impl AudioDevices {
+    /// # New
+    ///
+    /// Enumerates the active capture endpoints, an empty list when there are none.
+    ///
+    /// COM is initialized on a thread without it like Cameras::new does.
+    pub fn new() -> Result<Self, CaptureError> {
+        ensure_com("AudioDevices")?;
+
+        unsafe {
+            let enumerator: IMMDeviceEnumerator =
+                CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)?;
+            let collection = enumerator.EnumAudioEndpoints(eCapture, DEVICE_STATE_ACTIVE)?;
+
+            let mut devices = Vec::new();
+            let mut infos = Vec::new();
+
+            for index in 0..collection.GetCount()? {
+                let device = collection.Item(index)?;
+
+                infos.push(AudioDeviceInfo {
+                    friendly_name: endpoint_name(&device).unwrap_or_default(),
+                    id: endpoint_id(&device)?,
+                    index: devices.len(),
+                });
+                devices.push(device);
+            }
+
+            Ok(Self {
+                enumerator,
+                devices,
+                infos,
+            })
+        }
+    }
+
+    /// # Infos
+    ///
+    /// The friendly name, id and index of every capture endpoint.
+    pub fn infos(&self) -> &[AudioDeviceInfo] {
+        &self.infos
+    }
+
+    /// # Len
+    ///
+    /// The number of capture endpoints, every index below it can be given to activate.
+    pub fn len(&self) -> usize {
+        self.devices.len()
+    }
+
+    /// # Is Empty
+    ///
+    /// If no capture endpoint was found.
+    pub fn is_empty(&self) -> bool {
+        self.devices.is_empty()
+    }
+
+    /// # Activate
+    ///
+    /// Opens the capture endpoint at the index in shared mode, delivering the samples in the mix format of windows (see AudioDevice::format).
+    ///
+    /// Returns CaptureError::DeviceIndexOutOfRange when the index is not below len.
+    pub fn activate(&self, index: usize) -> Result<Arc<AudioDevice>, CaptureError> {
+        let device = self
+            .devices
+            .get(index)
+            .ok_or(CaptureError::DeviceIndexOutOfRange {
+                index,
+                len: self.len(),
+            })?;
+
+        unsafe {
+            AudioDevice::from_endpoint(device, self.infos[index].friendly_name.clone(), false)
+        }
+    }
+
+    /// # Activate Default
+    ///
+    /// Opens the default capture endpoint (the microphone chosen in the sound settings).
+    pub fn activate_default(&self) -> Result<Arc<AudioDevice>, CaptureError> {
+        unsafe { self.activate_default_endpoint(eCapture, false) }
+    }
+
+    /// # Activate Loopback
+    ///
+    /// Records what the default output endpoint plays (the system audio), for screen recordings with sound.
+    ///
+    /// No packets are delivered while nothing plays, AudioFrame::presentation_time tells where the next packet belongs.
+    pub fn activate_loopback(&self) -> Result<Arc<AudioDevice>, CaptureError> {
+        unsafe { self.activate_default_endpoint(eRender, true) }
+    }
+
+    unsafe fn activate_default_endpoint(
+        &self,
+        flow: EDataFlow,
+        loopback: bool,
+    ) -> Result<Arc<AudioDevice>, CaptureError> {
+        unsafe {
+            let device = self.enumerator.GetDefaultAudioEndpoint(flow, eConsole)?;
+            let name = endpoint_name(&device).unwrap_or_default();
+
+            AudioDevice::from_endpoint(&device, name, loopback)
+        }
+    }
+}
+
+// the friendly name of an endpoint from its property store
+unsafe fn endpoint_name(device: &IMMDevice) -> Result<String, windows::core::Error> {
+    unsafe {
+        let properties = device.OpenPropertyStore(STGM_READ)?;
+
+        Ok(properties.GetValue(&PKEY_Device_FriendlyName)?.to_string())
+    }
+}
+
+// the id of an endpoint, freeing the copy windows allocates
+unsafe fn endpoint_id(device: &IMMDevice) -> Result<String, windows::core::Error> {
+    unsafe {
+        let id = device.GetId()?;
+        let value = String::from_utf16_lossy(id.as_wide());
+
+        CoTaskMemFree(Some(id.0 as *const std::ffi::c_void));
+
+        Ok(value)
+    }
+}
+
+//endpoints are free threaded, they can be used from any thread in the multithreaded apartment
+unsafe impl Send for AudioDevices {}
+
+unsafe impl Sync for AudioDevices {}
@@ -0,0 +1,113 @@
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+/// # Sample Format
+///
+/// How a single sample of an AudioFrame is stored, every sample is little endian.
+pub enum SampleFormat {
+    /// 32 bit float from -1.0 to 1.0, the format the shared mode mixer of windows uses.
+    #[default]
+    F32,
+    /// 16 bit signed integer.
+    I16,
+    /// 24 bit signed integer packed into 3 bytes.
+    I24,
+    /// 32 bit signed integer.
+    I32,
+}
+
+impl SampleFormat {
+    /// # Bytes
+    ///
+    /// The number of bytes of one sample.
+    pub fn bytes(&self) -> usize {
+        match self {
+            SampleFormat::I16 => 2,
+            SampleFormat::I24 => 3,
+            SampleFormat::F32 | SampleFormat::I32 => 4,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+/// # Audio Format
+///
+/// The sample rate, channel count and sample format of the PCM data of an AudioDevice.
+pub struct AudioFormat {
+    /// Samples per second of every channel, for example 48000.
+    pub sample_rate: u32,
+
+    /// The number of interleaved channels, 2 for stereo.
+    pub channels: u16,
+
+    /// How every sample is stored.
+    pub sample_format: SampleFormat,
+}
+
+impl AudioFormat {
+    /// # Block Align
+    ///
+    /// The bytes of one sample of every channel, the size of a single audio frame.
+    pub fn block_align(&self) -> usize {
+        self.sample_format.bytes() * self.channels as usize
+    }
+
+    /// # Duration Of
+    ///
+    /// How long the given number of audio frames (samples per channel) plays.
+    pub fn duration_of(&self, frames: u64) -> Duration {
+        Duration::from_nanos(frames * 1_000_000_000 / self.sample_rate.max(1) as u64)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+/// # Audio Frame
+///
+/// A packet of PCM audio sent to the receiver of an AudioDevice, the interleaved samples and when they were captured.
+pub struct AudioFrame {
+    /// The interleaved samples, frames * format.block_align() bytes.
+    pub data: Vec<u8>,
+
+    /// The number of audio frames (samples per channel) in data.
+    pub frames: usize,
+
+    /// The format of the samples.
+    pub format: AudioFormat,
+
+    /// When the first sample was captured relative to when capturing started, derived from the sample position of the device.
+    pub timestamp: Duration,
+
+    /// When the first sample was captured on the shared clock, in 100 ns units of the performance counter (see crate::clock).
+    ///
+    /// Comparable with the presentation time of monitor and camera frames, for synchronizing audio and video.
+    pub presentation_time: i64,
+
+    /// If the device reported the packet as silent, data then holds zeros.
+    pub is_silent: bool,
+
+    /// If samples were lost between the previous packet and this one, usually because the receiver was read too slowly.
+    pub discontinuity: bool,
+}
+
+impl AudioFrame {
+    /// # Len
+    ///
+    /// The number of bytes of data in the frame.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// # Is Empty
+    ///
+    /// If the frame holds no samples.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// # Duration
+    ///
+    /// How long the samples of the frame play.
+    pub fn duration(&self) -> Duration {
+        self.format.duration_of(self.frames as u64)
+    }
+}
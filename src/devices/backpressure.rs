@@ -0,0 +1,29 @@
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+/// # Backpressure
+///
+/// What the capture loop of a monitor or camera does with a new frame when the channel of the receiver is full.
+///
+/// No change is lost with a dropped frame, the next frame the consumer gets counts it in skipped_since_last and lists its dirty rects.
+/// That frame has no move rects, they would be relative to a frame the consumer never saw.
+pub enum Backpressure {
+    /// Wait until the consumer takes a frame (default), no frame is ever dropped, which suits a recorder.
+    ///
+    /// The capture loop stalls for as long as the consumer does: a monitor counts the desktop updates meanwhile in skipped_since_last,
+    /// a camera loses the frames media foundation delivers meanwhile. A consumer that stops reading without stopping the capture stalls it forever.
+    #[default]
+    Block,
+
+    /// Drop the new frame and keep the buffered ones, the consumer sees older frames first.
+    DropNewest,
+
+    /// Drop the oldest buffered frame to make room for the new one, the consumer always gets the latest frames, which suits a live preview.
+    ///
+    /// While a consumer holds the lock of the receiver (for example waiting on it in a loop) the buffered frames can not be taken out,
+    /// the new frame is dropped instead as with DropNewest.
+    DropOldest,
+
+    /// Merge the buffered frames and the new one into the new frame, the consumer gets a single frame with the latest image.
+    ///
+    /// Like DropOldest the new frame is dropped while a consumer holds the lock of the receiver.
+    Coalesce,
+}
@@ -1,29 +1,214 @@
-use std::{pin::Pin, sync::Arc};
+use std::{
+    pin::Pin,
+    sync::{
+        Arc, RwLock, Weak,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
 
 use tokio::sync::{
     Mutex,
     mpsc::{self, Receiver, Sender},
+    oneshot,
 };
 use windows::Win32::{
-    Foundation::E_ABORT,
-    Media::MediaFoundation::{
-        IMFAttributes, IMFMediaBuffer, IMFMediaSource, IMFSample, IMFSourceReader,
-        MF_MT_FRAME_SIZE, MF_MT_MAJOR_TYPE, MF_MT_SUBTYPE, MF_READWRITE_ENABLE_HARDWARE_TRANSFORMS,
-        MF_SOURCE_READER_ALL_STREAMS, MF_SOURCE_READER_ENABLE_VIDEO_PROCESSING,
-        MF_SOURCE_READER_FIRST_VIDEO_STREAM, MFCreateAttributes, MFCreateMediaType,
-        MFCreateSourceReaderFromMediaSource, MFMediaType_Video, MFVideoFormat_NV12,
-        MFVideoFormat_RGB32,
+    Foundation::{
+        E_ABORT, E_ACCESSDENIED, ERROR_BUSY, ERROR_DEVICE_NOT_CONNECTED, ERROR_DEVICE_REMOVED,
+        ERROR_SHARING_VIOLATION,
+    },
+    Media::{
+        KernelStreaming::{
+            IKsControl, KS_VideoControlFlag_Trigger, KSIDENTIFIER, KSIDENTIFIER_0,
+            KSIDENTIFIER_0_0, KSPROPERTY_TYPE_SET, KSPROPERTY_VIDEOCONTROL_MODE,
+            KSPROPERTY_VIDEOCONTROL_MODE_S, PINNAME_IMAGE, PINNAME_VIDEO_STILL,
+            PROPSETID_VIDCAP_VIDEOCONTROL,
+        },
+        MediaFoundation::{
+            IMFActivate, IMFAttributes, IMFMediaBuffer, IMFMediaSource, IMFMediaType, IMFSample,
+            IMFSourceReader, IMFSourceReaderEx, IMFStreamDescriptor, IMFTransform,
+            MF_DEVICESTREAM_STREAM_CATEGORY, MF_DEVSOURCE_ATTRIBUTE_FRIENDLY_NAME,
+            MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE_VIDCAP_SYMBOLIC_LINK,
+            MF_E_HW_MFT_FAILED_START_STREAMING, MF_E_NO_MORE_TYPES,
+            MF_E_VIDEO_RECORDING_DEVICE_INVALIDATED, MF_E_VIDEO_RECORDING_DEVICE_PREEMPTED,
+            MF_MT_DEFAULT_STRIDE, MF_MT_FRAME_RATE, MF_MT_FRAME_SIZE, MF_MT_MAJOR_TYPE,
+            MF_MT_SUBTYPE, MF_READWRITE_ENABLE_HARDWARE_TRANSFORMS, MF_SOURCE_READER_ALL_STREAMS,
+            MF_SOURCE_READER_ENABLE_VIDEO_PROCESSING, MF_SOURCE_READER_FIRST_VIDEO_STREAM,
+            MF_SOURCE_READER_MEDIASOURCE, MF_SOURCE_READERF_ENDOFSTREAM, MF_SOURCE_READERF_ERROR,
+            MFAudioFormat_PCM, MFCreateAttributes, MFCreateMediaType,
+            MFCreateSourceReaderFromMediaSource, MFGetStrideForBitmapInfoHeader, MFMediaType_Video,
+            MFVideoFormat_A2R10G10B10, MFVideoFormat_A16B16G16R16F, MFVideoFormat_MJPG,
+            MFVideoFormat_NV12, MFVideoFormat_RGB32, MFVideoFormat_YUY2,
+        },
+    },
+};
+
+use windows::core::{BOOL, GUID, Interface};
+
+use crate::{
+    capture_error::CaptureError,
+    capture_event::CaptureEvent,
+    capture_state::{CaptureState, StateCell},
+    clock::clock,
+    com::MtaUsage,
+    convert::{check_size, scale_bgra},
+    devices::{
+        AccessMode, Backpressure, CameraControls, DEFAULT_CHANNEL_CAPACITY, DeviceConfig,
+        Dimensions, EVENT_CHANNEL_CAPACITY, Frame, FrameRate, FrameTransform, MediaTypeInfo,
+        NegotiatedFormat, Photo, PhotoSource, RetryPolicy,
+        cameras::activate_symbolic_link,
+        change_detection::{ChangeDetection, ChangeDetector},
+        device_watcher::{is_tracked, track_removal},
+        frame_rate::{FpsMeter, FrameLimiter},
+        get_device_string, replace_channel, send_with_backpressure,
     },
+    frame_callback::FrameCallback,
+    i_capture::ICapture,
+    i_sync_capture::{ISyncCapture, SyncChannel},
+    init::MediaFoundation,
 };
 
-use crate::{devices::Dimensions, i_capture::ICapture};
+// the stream of the photo pin, the index for the source reader and the identifier for the driver
+#[derive(Clone, Copy)]
+struct PhotoStream {
+    index: u32,
+    identifier: u32,
+}
+
+// the size and frame interval of a preview, frames are downscaled to the size and dropped down to the interval
+#[derive(Clone, Copy)]
+struct Preview {
+    width: u32,
+    height: u32,
+    interval: Duration,
+}
+
+// how far (in 100 ns units) a sample time may be from the clock before the source is taken to use another clock
+const MAX_SAMPLE_CLOCK_DRIFT: i64 = 10_000_000;
+
+// the longest wait between two attempts at reconnecting a lost device, so capturing resumes soon after it is plugged in again
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(1);
 
 /// Output Control
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Output {
     /// Raw unprocesses data directly from the device
     NV12,
     /// Processes data as RGB32
     RGB32,
+    /// 10 bits per channel RGB with 2 bits of alpha, delivered by HDR monitors using HdrMode::Raw
+    RGB10A2,
+    /// 16 bit float per channel RGBA, delivered by HDR monitors using HdrMode::Raw
+    RGBA16F,
+    /// Packed 4:2:2 YUV, 2 bytes per pixel
+    YUY2,
+    /// Compressed motion JPEG, every frame is a complete JPEG image of variable length
+    MJPEG,
+    /// Interleaved PCM audio samples, delivered by AudioDevice, the sample format is described by every AudioFrame
+    PCM,
+}
+
+impl Output {
+    /// # Subtype
+    ///
+    /// The media foundation video subtype of the output, the audio subtype for PCM.
+    pub fn subtype(&self) -> GUID {
+        match self {
+            Output::NV12 => MFVideoFormat_NV12,
+            Output::RGB32 => MFVideoFormat_RGB32,
+            Output::RGB10A2 => MFVideoFormat_A2R10G10B10,
+            Output::RGBA16F => MFVideoFormat_A16B16G16R16F,
+            Output::YUY2 => MFVideoFormat_YUY2,
+            Output::MJPEG => MFVideoFormat_MJPG,
+            Output::PCM => MFAudioFormat_PCM,
+        }
+    }
+
+    /// # Can Convert From
+    ///
+    /// If a camera delivering the native format can produce this output.
+    ///
+    /// RGB32 and NV12 are decoded (MJPEG) or converted (YUY2) by media foundation, other outputs are passed through untouched so they need the same native format.
+    pub fn can_convert_from(&self, native: Output) -> bool {
+        match self {
+            Output::RGB32 | Output::NV12 => matches!(
+                native,
+                Output::NV12 | Output::RGB32 | Output::YUY2 | Output::MJPEG
+            ),
+            _ => *self == native,
+        }
+    }
+
+    /// # Stride
+    ///
+    /// The bytes per row of a tightly packed frame of the given width (of the luma plane for NV12), 0 for MJPEG and PCM.
+    pub fn stride(&self, width: u32) -> usize {
+        let width = width as usize;
+
+        match self {
+            Output::NV12 => width,
+            Output::YUY2 => width * 2,
+            Output::RGB32 | Output::RGB10A2 => width * 4,
+            Output::RGBA16F => width * 8,
+            Output::MJPEG | Output::PCM => 0,
+        }
+    }
+
+    // the outputs that one of the native types can be delivered as, in the order of the enum
+    pub(crate) fn deliverable_from(native_types: &[MediaTypeInfo]) -> Vec<Output> {
+        [
+            Output::NV12,
+            Output::RGB32,
+            Output::RGB10A2,
+            Output::RGBA16F,
+            Output::YUY2,
+            Output::MJPEG,
+        ]
+        .into_iter()
+        .filter(|output| {
+            native_types
+                .iter()
+                .any(|native| output.can_convert_from(native.format))
+        })
+        .collect()
+    }
+
+    /// # Frame Size
+    ///
+    /// The bytes of a tightly packed frame of the given size, 0 for MJPEG and PCM.
+    ///
+    /// NV12 holds a chroma sample for every 2x2 block and YUY2 for every two pixels of a row, odd sizes round up.
+    pub fn frame_size(&self, width: u32, height: u32) -> usize {
+        let (width, height) = (width as usize, height as usize);
+
+        match self {
+            Output::NV12 => width * height + width.div_ceil(2) * height.div_ceil(2) * 2,
+            Output::YUY2 => width.div_ceil(2) * 4 * height,
+            _ => self.stride(width as u32) * height,
+        }
+    }
+
+    /// # From Subtype
+    ///
+    /// Maps a media foundation video subtype to an output, None if there is no matching output.
+    pub fn from_subtype(subtype: &GUID) -> Option<Self> {
+        if *subtype == MFVideoFormat_NV12 {
+            Some(Output::NV12)
+        } else if *subtype == MFVideoFormat_RGB32 {
+            Some(Output::RGB32)
+        } else if *subtype == MFVideoFormat_A2R10G10B10 {
+            Some(Output::RGB10A2)
+        } else if *subtype == MFVideoFormat_A16B16G16R16F {
+            Some(Output::RGBA16F)
+        } else if *subtype == MFVideoFormat_YUY2 {
+            Some(Output::YUY2)
+        } else if *subtype == MFVideoFormat_MJPG {
+            Some(Output::MJPEG)
+        } else {
+            None
+        }
+    }
 }
 
 /// # Activated Device
@@ -36,20 +221,93 @@ pub enum Output {
 ///
 /// This could be a webcam or some other type of video device. This data can then be pushed through a pipeline like OpenCV for data capturing or other sorts of projects.
 pub struct Camera {
-    // source reader that allows to get the bytes from the device
-    media_reader: IMFSourceReader,
+    // source reader that allows to get the bytes from the device, replaced when the device is reconnected
+    media_reader: RwLock<IMFSourceReader>,
 
-    /// The receiver, can be used to grab data directly from the device.
-    pub receiver: Arc<Mutex<Receiver<Vec<u8>>>>,
+    // the activation object the device was created from, needed to activate the device again, replaced when the device is reconnected
+    activate: RwLock<Option<IMFActivate>>,
+
+    // the device is found again by its symbolic link after it was plugged in again
+    symbolic_link: Option<String>,
+
+    // the name of the device for errors shown to users, None for cameras made from a media source
+    friendly_name: Option<String>,
 
-    // to send data
-    sender: Sender<Vec<u8>>,
+    // if the native type is set or kept, used again when the device is reconnected
+    access: AccessMode,
 
-    // determines if the camera is capturing and sending data
-    is_capturing: Arc<Mutex<bool>>,
+    // the native mode that was negotiated, selected again when the device is reconnected
+    mode: Option<MediaTypeInfo>,
+
+    // set for previews, which own their activation object
+    preview: Option<Preview>,
+
+    // the size of the frames read from the reader before the transform, read once and again when the device is reconnected
+    dimensions: RwLock<Dimensions>,
+
+    // how frames are mirrored, flipped and rotated before they are sent
+    transform: RwLock<FrameTransform>,
+
+    // attempts and delay used to reconnect a lost device, None if disabled
+    auto_reconnect: RwLock<Option<RetryPolicy>>,
+
+    // set when a device watcher sees the device removed
+    removed: Arc<AtomicBool>,
+
+    // events such as the loss and reconnection of the device
+    event_sender: Sender<CaptureEvent>,
+    event_receiver: Arc<Mutex<Receiver<CaptureEvent>>>,
+
+    /// The receiver, can be used to grab data directly from the device.
+    pub receiver: Arc<Mutex<Receiver<Frame>>>,
+
+    // to send data, replaced by set_channel_capacity
+    sender: RwLock<Sender<Frame>>,
 
     /// The type of output the camera will give back to the user
     pub output: Output,
+
+    // how long recv_frame waits for a frame
+    frame_timeout: RwLock<Option<Duration>>,
+
+    // the callback registered with on_frame
+    frame_callback: FrameCallback<Frame>,
+
+    // how frames are compared with the previous frame, None when they are not
+    change_detection: RwLock<Option<ChangeDetection>>,
+
+    // what happens to a frame when the buffer of the receiver is full
+    backpressure: RwLock<Backpressure>,
+
+    // the delivery times of the latest frames, see actual_fps
+    frame_rate: FpsMeter,
+
+    // the highest rate frames are sent at, None to send every frame of the camera
+    max_fps: RwLock<Option<u32>>,
+
+    // the frames discarded after capturing starts, see set_warmup_frames
+    warmup_frames: RwLock<u32>,
+
+    // if the capture loop runs, see ICapture::state
+    state: StateCell,
+
+    // held while a photo is taken, a read of the photo stream that timed out keeps it until the device delivers
+    taking_photo: Arc<Mutex<()>>,
+
+    // the photo waiting for the next frame of the running capture loop
+    photo_waiter: RwLock<Option<oneshot::Sender<Frame>>>,
+
+    // the capture loop runs on tokio workers, which are in the multithreaded apartment while it is alive
+    _mta: MtaUsage,
+
+    // the source reader needs media foundation started for as long as it is used
+    _media_foundation: MediaFoundation,
+
+    // the camera itself, started on a thread of its own by ISyncCapture
+    this: Weak<Camera>,
+
+    // the frames of ISyncCapture
+    sync: SyncChannel,
 }
 
 impl Camera {
@@ -61,26 +319,882 @@ impl Camera {
     pub unsafe fn new(
         source: IMFMediaSource,
         output: Option<Output>,
+    ) -> Result<Arc<Self>, windows::core::Error> {
+        unsafe { Self::create(source, output, None) }
+    }
+
+    // activates the device and keeps the activation object so the device can be reconnected
+    pub(crate) unsafe fn from_activate(
+        device: &IMFActivate,
+        output: Option<Output>,
+    ) -> Result<Arc<Self>, windows::core::Error> {
+        unsafe {
+            let device = Self::own_activate(device)?;
+            let source = device.ActivateObject::<IMFMediaSource>()?;
+
+            Self::create(source, output, Some(device))
+        }
+    }
+
+    // the activation object for a new camera of the device.
+    //
+    // activating an object again returns the media source it activated before, so a second camera of a device that is already activated gets a new object.
+    // cameras never share a source, reader or reconnect of another camera.
+    unsafe fn own_activate(device: &IMFActivate) -> Result<IMFActivate, windows::core::Error> {
+        let symbolic_link = unsafe {
+            get_device_string(
+                device,
+                &MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE_VIDCAP_SYMBOLIC_LINK,
+            )
+        };
+
+        match symbolic_link {
+            Ok(symbolic_link) if is_tracked(&symbolic_link) => {
+                trace_event!(
+                    debug,
+                    "device already activated, using a new activation object"
+                );
+
+                unsafe { activate_symbolic_link(&symbolic_link) }
+            }
+            _ => Ok(device.clone()),
+        }
+    }
+
+    unsafe fn create(
+        source: IMFMediaSource,
+        output: Option<Output>,
+        activate: Option<IMFActivate>,
+    ) -> Result<Arc<Self>, windows::core::Error> {
+        unsafe {
+            let media_reader = Self::create_reader(&source)?;
+
+            Self::from_reader(
+                media_reader,
+                output,
+                activate,
+                None,
+                None,
+                AccessMode::Exclusive,
+            )
+        }
+    }
+
+    // activates the device with the first output of the preferences that one of its native types can be delivered as
+    pub(crate) unsafe fn from_activate_preferred(
+        device: &IMFActivate,
+        preferences: &[Output],
+    ) -> Result<(Arc<Self>, Output), CaptureError> {
+        unsafe {
+            let device = &Self::own_activate(device)?;
+            let source = device.ActivateObject::<IMFMediaSource>()?;
+            //the device is freed again when no output fits or the reader can not be set up
+            let shutdown = SourceShutdown::new(device);
+
+            let media_reader = Self::create_reader(&source)?;
+            let native_types = Self::native_media_types(&media_reader)?;
+
+            let Some(output) = preferences.iter().copied().find(|output| {
+                native_types
+                    .iter()
+                    .any(|native| output.can_convert_from(native.format))
+            }) else {
+                let mut natives: Vec<Output> =
+                    native_types.iter().map(|info| info.format).collect();
+                natives.sort_by_key(|native| *native as u8);
+                natives.dedup();
+
+                return Err(CaptureError::UnsupportedFormat(format!(
+                    "none of {preferences:?} can be delivered by the camera, it natively delivers {natives:?}"
+                )));
+            };
+
+            let camera = Self::from_reader(
+                media_reader,
+                Some(output),
+                Some(device.clone()),
+                None,
+                None,
+                AccessMode::Exclusive,
+            )?;
+
+            shutdown.keep();
+
+            Ok((camera, output))
+        }
+    }
+
+    // activates the device with the native mode closest to the config, or exactly the config when it is exact
+    pub(crate) unsafe fn from_activate_config(
+        device: &IMFActivate,
+        config: &DeviceConfig,
+    ) -> Result<Arc<Self>, CaptureError> {
+        unsafe {
+            let device = &Self::own_activate(device)?;
+            let source = device.ActivateObject::<IMFMediaSource>()?;
+            //the device is freed again when no mode fits or the reader can not be set up
+            let shutdown = SourceShutdown::new(device);
+
+            let media_reader = Self::create_reader(&source)?;
+            //a shared device can only be read in the type it delivers
+            let native_types = match config.access {
+                AccessMode::Exclusive => Self::native_media_types(&media_reader)?,
+                AccessMode::Shared => {
+                    Self::select_native_format(&media_reader, &config.format, None, config.access)?
+                        .into_iter()
+                        .collect()
+                }
+            };
+
+            let Some(mode) = config.select_mode(&native_types) else {
+                let (width, height, fps) = (config.width, config.height, config.fps);
+
+                return Err(CaptureError::UnsupportedFormat(if config.exact {
+                    format!(
+                        "the camera has no {width}x{height} mode at {fps} fps that can be delivered as {:?}",
+                        config.format
+                    )
+                } else {
+                    format!(
+                        "the camera has no mode that can be delivered as {:?}",
+                        config.format
+                    )
+                }));
+            };
+
+            trace_event!(
+                debug,
+                width = mode.width,
+                height = mode.height,
+                fps = mode.fps(),
+                access = ?config.access,
+                "native mode selected"
+            );
+
+            let camera = Self::from_reader(
+                media_reader,
+                Some(config.format),
+                Some(device.clone()),
+                Some(mode),
+                None,
+                config.access,
+            )?;
+
+            shutdown.keep();
+
+            camera.set_warmup_frames(config.warmup_frames);
+
+            Ok(camera)
+        }
+    }
+
+    // activates the smallest native mode for a preview of at most max_width x max_height, keeping the aspect ratio of the mode
+    pub(crate) unsafe fn from_activate_preview(
+        device: IMFActivate,
+        max_width: u32,
+        max_height: u32,
+        fps: u32,
+    ) -> Result<Arc<Self>, CaptureError> {
+        let config = DeviceConfig::new(Output::RGB32, max_width, max_height, fps);
+
+        unsafe {
+            let source = device.ActivateObject::<IMFMediaSource>()?;
+            let media_reader = Self::create_reader(&source)?;
+            let native_types = Self::native_media_types(&media_reader)?;
+
+            let Some(mode) = config.select_mode(&native_types) else {
+                let _ = device.ShutdownObject();
+
+                return Err(CaptureError::UnsupportedFormat(
+                    "the camera has no mode that can be delivered as RGB32".into(),
+                ));
+            };
+
+            let scale = (max_width as f64 / mode.width as f64)
+                .min(max_height as f64 / mode.height as f64)
+                .min(1.0);
+
+            let preview = Preview {
+                width: ((mode.width as f64 * scale).round() as u32).max(1),
+                height: ((mode.height as f64 * scale).round() as u32).max(1),
+                interval: Duration::from_secs_f64(1.0 / fps.max(1) as f64),
+            };
+
+            trace_event!(
+                debug,
+                width = preview.width,
+                height = preview.height,
+                native_width = mode.width,
+                native_height = mode.height,
+                "preview mode selected"
+            );
+
+            Ok(Self::from_reader(
+                media_reader,
+                Some(Output::RGB32),
+                Some(device),
+                Some(mode),
+                Some(preview),
+                AccessMode::Exclusive,
+            )?)
+        }
+    }
+
+    unsafe fn from_reader(
+        media_reader: IMFSourceReader,
+        output: Option<Output>,
+        activate: Option<IMFActivate>,
+        mode: Option<MediaTypeInfo>,
+        preview: Option<Preview>,
+        access: AccessMode,
     ) -> Result<Arc<Self>, windows::core::Error> {
         let output = output.unwrap_or(Output::NV12); //unwraps to NV12 by default
-        let (tx, rx) = mpsc::channel(1);
+        let (tx, rx) = mpsc::channel(DEFAULT_CHANNEL_CAPACITY);
+        let (event_tx, event_rx) = mpsc::channel(EVENT_CHANNEL_CAPACITY);
+        let media_foundation = MediaFoundation::start()?;
+
+        unsafe {
+            Self::set_stream_selection(&media_reader)?;
+            let negotiated =
+                Self::select_native_format(&media_reader, &output, mode.as_ref(), access)?;
+            Self::set_output_format(&media_reader, &output)?;
+
+            let symbolic_link = activate.as_ref().and_then(|activate| {
+                get_device_string(
+                    activate,
+                    &MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE_VIDCAP_SYMBOLIC_LINK,
+                )
+                .ok()
+            });
+
+            let friendly_name = activate.as_ref().and_then(|activate| {
+                get_device_string(activate, &MF_DEVSOURCE_ATTRIBUTE_FRIENDLY_NAME).ok()
+            });
+
+            let dimensions = match preview {
+                Some(preview) => Dimensions {
+                    width: preview.width,
+                    height: preview.height,
+                },
+                None => Self::current_dimensions(&media_reader)?,
+            };
+
+            //cameras without a symbolic link cannot be matched to removal events
+            let removed = symbolic_link
+                .clone()
+                .map_or_else(|| Arc::new(AtomicBool::new(false)), track_removal);
+
+            let activated = Arc::new_cyclic(|this| Camera {
+                media_reader: RwLock::new(media_reader),
+                activate: RwLock::new(activate),
+                symbolic_link,
+                friendly_name,
+                access,
+                mode: negotiated.or(mode),
+                preview,
+                dimensions: RwLock::new(dimensions),
+                transform: RwLock::new(FrameTransform::default()),
+                auto_reconnect: RwLock::new(None),
+                removed,
+                event_sender: event_tx,
+                event_receiver: Arc::new(Mutex::new(event_rx)),
+                _mta: MtaUsage::acquire(),
+                _media_foundation: media_foundation,
+                frame_timeout: RwLock::new(None),
+                frame_callback: FrameCallback::default(),
+                change_detection: RwLock::new(None),
+                backpressure: RwLock::new(Backpressure::default()),
+                frame_rate: FpsMeter::default(),
+                max_fps: RwLock::new(None),
+                warmup_frames: RwLock::new(0),
+                state: StateCell::default(),
+                taking_photo: Arc::new(Mutex::new(())),
+                photo_waiter: RwLock::new(None),
+                receiver: Arc::new(Mutex::new(rx)),
+                sender: RwLock::new(tx),
+                output,
+                this: this.clone(),
+                sync: SyncChannel::default(),
+            });
+
+            return Ok(activated);
+        }
+    }
+
+    /// # Supported Formats
+    ///
+    /// The formats, resolutions and frame rates the camera delivers natively, the largest resolution first and then the highest frame rate, without duplicates.
+    ///
+    /// RGB32 and NV12 can be requested for any of them, see Output::can_convert_from.
+    pub fn supported_formats(&self) -> Result<Vec<MediaTypeInfo>, windows::core::Error> {
+        unsafe { Self::native_media_types(&self.media_reader()) }
+    }
+
+    /// # Friendly Name
+    ///
+    /// The readable name of the device, None for a camera made from a media source with Camera::new.
+    pub fn friendly_name(&self) -> Option<&str> {
+        self.friendly_name.as_deref()
+    }
+
+    /// # Access Mode
+    ///
+    /// If the camera set its native mode or reads the type the device already delivers, see AccessMode.
+    pub fn access_mode(&self) -> AccessMode {
+        self.access
+    }
+
+    /// # Get Frame Rate
+    ///
+    /// The frame rate the camera was negotiated to deliver, as the numerator and denominator (30000 / 1001 is 29.97 fps).
+    pub fn get_frame_rate(&self) -> Result<(u32, u32), windows::core::Error> {
+        let first_video_stream = MF_SOURCE_READER_FIRST_VIDEO_STREAM.0 as u32;
+
+        let rate = unsafe {
+            self.media_reader()
+                .GetCurrentMediaType(first_video_stream)?
+                .GetUINT64(&MF_MT_FRAME_RATE)?
+        };
+
+        Ok(((rate >> 32) as u32, (rate & 0xFFFFFFFF) as u32))
+    }
+
+    /// # Negotiated Format
+    ///
+    /// The media type the source reader really delivers: the subtype, frame size, frame rate and stride, and if a converter produces it.
+    ///
+    /// Media foundation may insert converters or pick another frame size than requested, the stride tells if the frames are bottom-up (vertically flipped).
+    /// Previews deliver frames scaled down from this format, see dimensions.
+    pub fn negotiated_format(&self) -> Result<NegotiatedFormat, windows::core::Error> {
+        let first_video_stream = MF_SOURCE_READER_FIRST_VIDEO_STREAM.0 as u32;
+        let reader = self.media_reader();
+
+        unsafe {
+            let media_type = reader.GetCurrentMediaType(first_video_stream)?;
+
+            let subtype = media_type.GetGUID(&MF_MT_SUBTYPE)?;
+            let output = Output::from_subtype(&subtype);
+
+            //both the size and rate are packed as two u32 into a u64
+            let size = media_type.GetUINT64(&MF_MT_FRAME_SIZE)?;
+            let rate = media_type.GetUINT64(&MF_MT_FRAME_RATE).unwrap_or(0);
+            let width = (size >> 32) as u32;
+
+            //the default stride is stored as the bits of an i32, without it the stride of the uncompressed subtype is computed (top-down)
+            let stride = match media_type.GetUINT32(&MF_MT_DEFAULT_STRIDE) {
+                Ok(stride) => stride as i32,
+                Err(_) if output == Some(Output::MJPEG) => 0,
+                Err(_) => MFGetStrideForBitmapInfoHeader(subtype.data1, width)
+                    .unwrap_or_else(|_| output.map_or(0, |output| output.stride(width) as i32)),
+            };
+
+            //the first transform of the chain exists only when media foundation converts the native type
+            let mut transform: Option<IMFTransform> = None;
+            let has_converter = reader
+                .cast::<IMFSourceReaderEx>()
+                .and_then(|reader| {
+                    reader.GetTransformForStream(first_video_stream, 0, None, &mut transform)
+                })
+                .is_ok()
+                && transform.is_some();
+
+            Ok(NegotiatedFormat {
+                subtype,
+                output,
+                width,
+                height: (size & 0xFFFFFFFF) as u32,
+                fps_numerator: (rate >> 32) as u32,
+                fps_denominator: (rate & 0xFFFFFFFF) as u32,
+                stride,
+                has_converter,
+            })
+        }
+    }
+
+    /// # Controls
+    ///
+    /// The brightness, exposure, white balance and other properties of the camera, see CameraControls.
+    ///
+    /// The controls belong to the current media source, get them again after the camera was reconnected.
+    pub fn controls(&self) -> Result<CameraControls, windows::core::Error> {
+        Ok(CameraControls::new(&self.media_source()?))
+    }
+
+    // the media source the reader reads from
+    fn media_source(&self) -> Result<IMFMediaSource, windows::core::Error> {
+        let mut source = std::ptr::null_mut();
+
+        unsafe {
+            self.media_reader().GetServiceForStream(
+                MF_SOURCE_READER_MEDIASOURCE.0 as u32,
+                &GUID::zeroed(),
+                &IMFMediaSource::IID,
+                &mut source,
+            )?;
+
+            Ok(IMFMediaSource::from_raw(source))
+        }
+    }
+
+    /// # Take Photo
+    ///
+    /// Takes a single image with the photo stream of the camera (an image or still pin, often of a much higher resolution than the video),
+    /// without stopping a running capture. The photo stream is selected only while the image is read.
+    ///
+    /// Cameras without a photo stream return the next frame of the video stream instead, Photo::source tells which one was used.
+    /// While capturing the frame is also sent to the receiver as usual.
+    ///
+    /// Returns CaptureError::Timeout when no image arrives within the timeout and CaptureError::CameraBusy when a photo is already being taken
+    /// (also after a timeout, until the device delivers the image it was asked for) or another application holds the camera.
+    pub async fn take_photo(&self, timeout: Duration) -> Result<Photo, CaptureError> {
+        let Ok(taking) = self.taking_photo.clone().try_lock_owned() else {
+            return Err(CaptureError::CameraBusy);
+        };
+
+        let Some(stream) = (unsafe { self.photo_stream() })? else {
+            trace_event!(
+                debug,
+                "the camera has no photo stream, taking the next video frame"
+            );
+
+            return self.next_video_frame(timeout).await;
+        };
+
+        let camera = self
+            .this
+            .upgrade()
+            .ok_or_else(|| CaptureError::Other("the camera was dropped".into()))?;
+
+        //reading blocks until the device delivers, the lock is kept until then
+        let read = tokio::task::spawn_blocking(move || {
+            let _taking = taking;
+            unsafe { camera.read_photo(stream) }
+        });
+
+        match tokio::time::timeout(timeout, read).await {
+            Ok(Ok(photo)) => photo.map_err(Self::photo_error),
+            Ok(Err(e)) => Err(CaptureError::Other(format!(
+                "reading the photo failed: {e}"
+            ))),
+            Err(_) => {
+                trace_event!(warn, ?timeout, "the photo stream did not deliver an image");
+                Err(CaptureError::Timeout(timeout))
+            }
+        }
+    }
+
+    // a device held by another application is busy, not lost
+    fn photo_error(e: windows::core::Error) -> CaptureError {
+        let code = e.code();
+
+        if code == MF_E_VIDEO_RECORDING_DEVICE_PREEMPTED || code == E_ACCESSDENIED {
+            CaptureError::CameraBusy
+        } else {
+            e.into()
+        }
+    }
+
+    // the stream of the image or still pin, None if the device only has video streams
+    unsafe fn photo_stream(&self) -> Result<Option<PhotoStream>, windows::core::Error> {
+        unsafe {
+            let descriptor = self.media_source()?.CreatePresentationDescriptor()?;
+
+            for index in 0..descriptor.GetStreamDescriptorCount()? {
+                let mut selected = BOOL::default();
+                let mut stream: Option<IMFStreamDescriptor> = None;
+                descriptor.GetStreamDescriptorByIndex(index, &mut selected, &mut stream)?;
+
+                let Some(stream) = stream else {
+                    continue;
+                };
+
+                //streams without a category are video streams
+                let Ok(category) = stream.GetGUID(&MF_DEVICESTREAM_STREAM_CATEGORY) else {
+                    continue;
+                };
+
+                if category == PINNAME_IMAGE || category == PINNAME_VIDEO_STILL {
+                    return Ok(Some(PhotoStream {
+                        index,
+                        identifier: stream.GetStreamIdentifier()?,
+                    }));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    // selects the photo stream at its largest size, triggers the pin and reads a single image, the stream is deselected again afterwards
+    unsafe fn read_photo(&self, stream: PhotoStream) -> Result<Photo, windows::core::Error> {
+        let reader = self.media_reader();
+
+        unsafe {
+            Self::select_photo_type(&reader, stream.index)?;
+            reader.SetStreamSelection(stream.index, true)?;
+
+            self.trigger_photo(stream.identifier);
+
+            let read = Self::read_photo_sample(&reader, stream.index);
+
+            //the video keeps streaming, only the photo stream is deselected
+            let _ = reader.SetStreamSelection(stream.index, false);
+
+            let (data, presentation_time) = read?;
+            let media_type = reader.GetCurrentMediaType(stream.index)?;
+            let subtype = media_type.GetGUID(&MF_MT_SUBTYPE)?;
+            let size = media_type.GetUINT64(&MF_MT_FRAME_SIZE)?;
+
+            let (width, height) = ((size >> 32) as u32, (size & 0xFFFFFFFF) as u32);
+            let output = Output::from_subtype(&subtype);
+
+            let mut frame = Frame::new(data, Duration::ZERO);
+            frame.stride = output.map_or(0, |output| output.stride(width));
+            frame.presentation_time = presentation_time;
+
+            trace_event!(
+                debug,
+                width,
+                height,
+                bytes = frame.data.len(),
+                "took a photo"
+            );
+
+            Ok(Photo {
+                frame,
+                width,
+                height,
+                subtype,
+                output,
+                source: PhotoSource::PhotoStream,
+            })
+        }
+    }
+
+    // the largest native type of the photo stream, converted to RGB32 when media foundation can, else delivered as is (often JPEG)
+    unsafe fn select_photo_type(
+        reader: &IMFSourceReader,
+        stream: u32,
+    ) -> Result<(), windows::core::Error> {
+        let mut largest: Option<(u64, IMFMediaType)> = None;
+        let mut index = 0;
+
+        unsafe {
+            loop {
+                let media_type = match reader.GetNativeMediaType(stream, index) {
+                    Ok(media_type) => media_type,
+                    //all types have been read
+                    Err(e) if e.code() == MF_E_NO_MORE_TYPES => break,
+                    Err(e) => return Err(e),
+                };
+
+                index += 1;
+
+                let Ok(size) = media_type.GetUINT64(&MF_MT_FRAME_SIZE) else {
+                    continue;
+                };
+
+                let area = (size >> 32) * (size & 0xFFFFFFFF);
+
+                if largest.as_ref().is_none_or(|(best, _)| area > *best) {
+                    largest = Some((area, media_type));
+                }
+            }
+
+            let Some((_, native)) = largest else {
+                return Ok(());
+            };
+
+            let size = native.GetUINT64(&MF_MT_FRAME_SIZE)?;
+
+            let rgb = MFCreateMediaType()?;
+            rgb.SetGUID(&MF_MT_MAJOR_TYPE, &MFMediaType_Video)?;
+            rgb.SetGUID(&MF_MT_SUBTYPE, &MFVideoFormat_RGB32)?;
+            rgb.SetUINT64(&MF_MT_FRAME_SIZE, size)?;
+
+            if reader.SetCurrentMediaType(stream, None, &rgb).is_err() {
+                reader.SetCurrentMediaType(stream, None, &native)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // image pins only deliver when triggered, drivers of still pins that stream continuously may reject the trigger and are read anyway
+    fn trigger_photo(&self, identifier: u32) {
+        let Ok(control) = self
+            .media_source()
+            .and_then(|source| source.cast::<IKsControl>())
+        else {
+            return;
+        };
+
+        let mut mode = KSPROPERTY_VIDEOCONTROL_MODE_S {
+            Property: KSIDENTIFIER {
+                Anonymous: KSIDENTIFIER_0 {
+                    Anonymous: KSIDENTIFIER_0_0 {
+                        Set: PROPSETID_VIDCAP_VIDEOCONTROL,
+                        Id: KSPROPERTY_VIDEOCONTROL_MODE.0 as u32,
+                        Flags: KSPROPERTY_TYPE_SET,
+                    },
+                },
+            },
+            StreamIndex: identifier,
+            Mode: KS_VideoControlFlag_Trigger.0,
+        };
+
+        let size = std::mem::size_of::<KSPROPERTY_VIDEOCONTROL_MODE_S>() as u32;
+        let mut returned = 0;
+
+        let triggered = unsafe {
+            control.KsProperty(
+                &mode.Property,
+                size,
+                &mut mode as *mut _ as *mut _,
+                size,
+                &mut returned,
+            )
+        };
+
+        if let Err(_e) = triggered {
+            trace_event!(debug, hresult = %_e.code(), "the photo pin rejected the trigger");
+        }
+    }
+
+    // reads samples of the photo stream until one holds an image
+    unsafe fn read_photo_sample(
+        reader: &IMFSourceReader,
+        stream: u32,
+    ) -> Result<(Vec<u8>, i64), windows::core::Error> {
+        loop {
+            let mut sample: Option<IMFSample> = None;
+            let mut stream_flags: u32 = 0;
+            let mut time_stamp: i64 = 0;
+
+            unsafe {
+                reader.ReadSample(
+                    stream,
+                    0,
+                    None,
+                    Some(&mut stream_flags),
+                    Some(&mut time_stamp),
+                    Some(&mut sample),
+                )?;
+            }
+
+            let flags = stream_flags as i32;
+
+            if flags & (MF_SOURCE_READERF_ERROR.0 | MF_SOURCE_READERF_ENDOFSTREAM.0) != 0 {
+                return Err(windows::Win32::Foundation::E_FAIL.into());
+            }
+
+            //stream ticks carry no sample
+            let Some(sample) = sample else {
+                continue;
+            };
+
+            let buffer = unsafe { sample.ConvertToContiguousBuffer()? };
+
+            return Ok((
+                Self::get_frame_data(&buffer)?,
+                Self::presentation_time(time_stamp),
+            ));
+        }
+    }
+
+    // the next frame of the video stream, taken from the capture loop while it runs so the receiver does not miss it
+    async fn next_video_frame(&self, timeout: Duration) -> Result<Photo, CaptureError> {
+        let output = self.get_output_format();
+
+        let (frame, size) = if self.state.get() == CaptureState::Capturing {
+            let (tx, rx) = oneshot::channel();
+            *self.photo_waiter.write().unwrap() = Some(tx);
+
+            let frame = tokio::time::timeout(timeout, rx).await;
+
+            //the loop may not have run since, a later frame is not wanted anymore
+            self.photo_waiter.write().unwrap().take();
+
+            match frame {
+                Ok(Ok(frame)) => (frame, self.dimensions()),
+                Ok(Err(_)) => return Err(CaptureError::Other("the capture stopped".into())),
+                Err(_) => return Err(CaptureError::Timeout(timeout)),
+            }
+        } else {
+            let camera = self
+                .this
+                .upgrade()
+                .ok_or_else(|| CaptureError::Other("the camera was dropped".into()))?;
+
+            let warmup = self.warmup_frames();
+
+            //the frames before the first settled one are read within the timeout too
+            let read = tokio::task::spawn_blocking(move || {
+                for _ in 0..warmup {
+                    camera.read_timed_sample(None)?;
+                }
+
+                camera.read_timed_sample(None)
+            });
+
+            let (data, presentation_time) = match tokio::time::timeout(timeout, read).await {
+                Ok(Ok(sample)) => sample.map_err(Self::photo_error)?,
+                Ok(Err(e)) => {
+                    return Err(CaptureError::Other(format!(
+                        "reading the frame failed: {e}"
+                    )));
+                }
+                Err(_) => return Err(CaptureError::Timeout(timeout)),
+            };
+
+            //the sample is read as the reader delivers it, previews are not scaled here and keep the size of the native mode
+            let (data, size) = match self.preview {
+                Some(_) => (data, self.reader_dimensions()?),
+                None => {
+                    let size = self.dimensions.read().unwrap().clone();
+                    self.transform_frame(data, &size)
+                }
+            };
+
+            let mut frame = Frame::new(data, Duration::ZERO);
+            frame.stride = output.stride(size.width);
+            frame.presentation_time = presentation_time;
+            (frame, size)
+        };
+
+        Ok(Photo {
+            frame,
+            width: size.width,
+            height: size.height,
+            subtype: output.subtype(),
+            output: Some(output),
+            source: PhotoSource::VideoFrame,
+        })
+    }
+
+    /// # Set Change Detection
+    ///
+    /// Compares the samples of every frame with the previous frame, flagging frames that did not change with Frame::is_duplicate or skipping them (see ChangeDetection).
+    ///
+    /// Camera frames are never identical because of sensor noise, use a tolerance of a few levels to detect a still scene.
+    pub fn set_change_detection(&self, detection: ChangeDetection) {
+        *self.change_detection.write().unwrap() = Some(detection);
+    }
+
+    /// # Disable Change Detection
+    ///
+    /// Frames are not compared and never flagged as duplicates (default).
+    pub fn disable_change_detection(&self) {
+        *self.change_detection.write().unwrap() = None;
+    }
+
+    /// # Change Detection
+    ///
+    /// How frames are compared with the previous frame, None if they are not.
+    pub fn change_detection(&self) -> Option<ChangeDetection> {
+        *self.change_detection.read().unwrap()
+    }
+
+    /// # Set Channel Capacity
+    ///
+    /// How many frames are buffered for the receiver, 1 by default (0 is taken as 1).
+    ///
+    /// A small buffer has the lowest latency, which suits a live preview. The capture waits for the consumer when the buffer is full and media foundation drops the frames the camera delivers meanwhile.
+    /// A recorder can use a larger buffer, so a slow write does not lose frames, at the cost of that many frames of latency.
+    ///
+    /// Must be called before capturing starts and while no task waits on the receiver, else CaptureError::Other is returned. Frames still in the buffer are dropped.
+    pub fn set_channel_capacity(&self, capacity: usize) -> Result<(), CaptureError> {
+        replace_channel(&self.sender, &self.receiver, &self.state, capacity)
+    }
+
+    /// # Channel Capacity
+    ///
+    /// How many frames are buffered for the receiver.
+    pub fn channel_capacity(&self) -> usize {
+        self.sender.read().unwrap().max_capacity()
+    }
+
+    /// # Set Backpressure
+    ///
+    /// What happens to a new frame when the buffer of the receiver is full, Backpressure::Block by default.
+    ///
+    /// With Block the capture waits for the consumer and media foundation drops the frames the camera delivers meanwhile, a stalled consumer stalls the capture loop
+    /// (and stop_capturing takes effect only once a frame is taken). DropNewest and DropOldest never wait, so the camera keeps being read.
+    pub fn set_backpressure(&self, backpressure: Backpressure) {
+        *self.backpressure.write().unwrap() = backpressure;
+    }
+
+    /// # Backpressure
+    ///
+    /// What happens to a new frame when the buffer of the receiver is full.
+    pub fn backpressure(&self) -> Backpressure {
+        *self.backpressure.read().unwrap()
+    }
+
+    /// # Actual FPS
+    ///
+    /// The rate frames were really delivered at, measured over the latest frames (see set_fps_window) and reset when capturing starts.
+    ///
+    /// Cameras in low light often deliver far less than the frame rate of their mode, because auto exposure extends the shutter time. Frames dropped by backpressure are counted, frames skipped by change detection are not.
+    pub fn actual_fps(&self) -> FrameRate {
+        self.frame_rate.get()
+    }
+
+    /// # Set Max FPS
+    ///
+    /// Caps the rate frames are sent at, for apps that need fewer frames (say 15 fps) than the camera delivers (say 60 fps). None (the default) or 0 sends every frame.
+    ///
+    /// Frames over the rate are dropped by their timestamp right after they are read, before they are scaled, transformed or compared, so the dropped frames cost no conversion work.
+    /// A cap at or above the negotiated frame rate (see get_frame_rate) does nothing. Media foundation still reads every frame of the device, pick a slower mode to save USB bandwidth.
+    ///
+    /// Unlike the frame rate of a preview, it can be changed while capturing.
+    pub fn set_max_fps(&self, fps: Option<u32>) {
+        *self.max_fps.write().unwrap() = fps.filter(|fps| *fps > 0);
+    }
+
+    /// # Max FPS
+    ///
+    /// The highest rate frames are sent at, None when every frame is sent.
+    pub fn max_fps(&self) -> Option<u32> {
+        *self.max_fps.read().unwrap()
+    }
+
+    /// # Set Warmup Frames
+    ///
+    /// How many frames are read and discarded every time capturing starts (and after the device reconnects), before the first frame is sent.
+    /// The first frames of most webcams are black or badly exposed while auto exposure converges, a handful of frames (say 5 to 30) skips them.
+    ///
+    /// take_photo on a stopped camera without a photo stream and capture_n_frames only return settled frames too. 0 (the default) sends every frame.
+    pub fn set_warmup_frames(&self, frames: u32) {
+        *self.warmup_frames.write().unwrap() = frames;
+    }
+
+    /// # Warmup Frames
+    ///
+    /// The frames discarded after capturing starts.
+    pub fn warmup_frames(&self) -> u32 {
+        *self.warmup_frames.read().unwrap()
+    }
 
-        unsafe {
-            let media_reader = Self::create_reader(&source)?;
+    // the negotiated frame rate in frames per second, None when the reader does not know it
+    fn native_fps(&self) -> Option<f64> {
+        let (numerator, denominator) = self.get_frame_rate().ok()?;
 
-            Self::set_stream_selection(&media_reader)?;
-            Self::set_output_format(&media_reader, &output)?;
+        (numerator > 0 && denominator > 0).then(|| numerator as f64 / denominator as f64)
+    }
 
-            let activated = Camera {
-                media_reader,
-                receiver: Arc::new(Mutex::new(rx)),
-                sender: tx,
-                is_capturing: Arc::new(Mutex::new(false)),
-                output,
-            };
+    /// # Set FPS Window
+    ///
+    /// Sets the number of latest frames actual_fps is measured over, 60 by default (at least 2). A short window shows brief dips, a long one smooths them out.
+    pub fn set_fps_window(&self, frames: usize) {
+        self.frame_rate.set_window(frames);
+    }
 
-            return Ok(Arc::new(activated));
-        }
+    /// # FPS Window
+    ///
+    /// The number of latest frames actual_fps is measured over.
+    pub fn fps_window(&self) -> usize {
+        self.frame_rate.window()
     }
 
     /// # Read Sample
@@ -90,6 +1204,14 @@ impl Camera {
     /// Reads a sample of the stream, converts to a buffer and retrieves the underlying data returned as Vec<u8>
     ///
     pub fn read_sample(&self, video_stream: Option<u32>) -> Result<Vec<u8>, windows::core::Error> {
+        self.read_timed_sample(video_stream).map(|(data, _)| data)
+    }
+
+    // reads a sample with its presentation time on the clock
+    fn read_timed_sample(
+        &self,
+        video_stream: Option<u32>,
+    ) -> Result<(Vec<u8>, i64), windows::core::Error> {
         //initialize values for loading into the readsample func
         let video_stream = video_stream.unwrap_or(MF_SOURCE_READER_FIRST_VIDEO_STREAM.0 as u32);
         let mut sample: Option<IMFSample> = None;
@@ -99,7 +1221,7 @@ impl Camera {
         let mut time_stamp: i64 = 0;
 
         unsafe {
-            self.media_reader.ReadSample(
+            self.media_reader().ReadSample(
                 video_stream,
                 0,
                 Some(&mut stream_index),
@@ -109,7 +1231,7 @@ impl Camera {
             )?;
 
             if sample.is_none() {
-                return Ok(vec![]);
+                return Ok((vec![], clock()));
             }
 
             buffer = Some(sample.unwrap().ConvertToContiguousBuffer()?);
@@ -122,7 +1244,309 @@ impl Camera {
 
         let buffer = buffer.unwrap();
 
-        Ok(Self::get_frame_data(&buffer)?)
+        Ok((
+            Self::get_frame_data(&buffer)?,
+            Self::presentation_time(time_stamp),
+        ))
+    }
+
+    // capture sources stamp their samples with the clock, a sample time far from it is from a source that does not and is replaced by the read time
+    fn presentation_time(sample_time: i64) -> i64 {
+        let now = clock();
+
+        if sample_time > 0 && (now - sample_time).abs() < MAX_SAMPLE_CLOCK_DRIFT {
+            sample_time
+        } else {
+            now
+        }
+    }
+
+    /// # Set Auto Reconnect
+    ///
+    /// When the device is lost while capturing (for example briefly unplugged) try to activate it again up to max_attempts times, waiting delay between each attempt (doubling up to a second).
+    ///
+    /// CaptureError::DeviceLost is only returned from start_capturing once all attempts fail.
+    ///
+    /// Only cameras activated through Cameras can be reconnected.
+    pub fn set_auto_reconnect(&self, max_attempts: u32, delay: Duration) {
+        self.set_reconnect_policy(RetryPolicy::new(max_attempts, delay));
+    }
+
+    /// # Set Reconnect Policy
+    ///
+    /// Same as set_auto_reconnect, with RetryPolicy::max_duration as the longest wait for the device to come back, for example 30 seconds for a cable that is plugged in again.
+    ///
+    /// While it waits the frame channel stays open, the device is looked up again by its symbolic link and activated with the format negotiated before.
+    /// CaptureEvent::Disconnected and CaptureEvent::Reconnected are sent on clone_event_receiver, so the app can show the state.
+    pub fn set_reconnect_policy(&self, policy: RetryPolicy) {
+        *self.auto_reconnect.write().unwrap() = Some(policy);
+    }
+
+    /// # Reconnect Policy
+    ///
+    /// How a lost device is reconnected, None if it is not.
+    pub fn reconnect_policy(&self) -> Option<RetryPolicy> {
+        *self.auto_reconnect.read().unwrap()
+    }
+
+    /// # Clone Event Receiver
+    ///
+    /// The receiver of the events of the camera, CaptureEvent::Disconnected and CaptureEvent::Reconnected while a lost device is reconnected.
+    ///
+    /// Events are dropped once 16 are waiting to be read.
+    pub fn clone_event_receiver(&self) -> Arc<Mutex<Receiver<CaptureEvent>>> {
+        self.event_receiver.clone()
+    }
+
+    // sends an event without waiting, dropping it when the receiver is full
+    fn send_event(&self, event: CaptureEvent) {
+        let _ = self.event_sender.try_send(event);
+    }
+
+    /// # Disable Auto Reconnect
+    ///
+    /// Lost devices end the capture with the error from the device (default).
+    pub fn disable_auto_reconnect(&self) {
+        *self.auto_reconnect.write().unwrap() = None;
+    }
+
+    // clones the current source reader out of the lock
+    fn media_reader(&self) -> IMFSourceReader {
+        self.media_reader.read().unwrap().clone()
+    }
+
+    // the frame size of the current media type, the size of the frames read from the device
+    fn reader_dimensions(&self) -> Result<Dimensions, windows::core::Error> {
+        Self::current_dimensions(&self.media_reader())
+    }
+
+    fn current_dimensions(reader: &IMFSourceReader) -> Result<Dimensions, windows::core::Error> {
+        let first_video_stream = MF_SOURCE_READER_FIRST_VIDEO_STREAM.0 as u32;
+
+        //the dimensions are stored as a u64
+        let size = unsafe {
+            reader
+                .GetCurrentMediaType(first_video_stream)?
+                .GetUINT64(&MF_MT_FRAME_SIZE)?
+        };
+
+        let width = (size >> 32) as u32;
+        let height = (size & 0xFFFFFFFF) as u32;
+
+        Ok(Dimensions { width, height })
+    }
+
+    /// # Dimensions
+    ///
+    /// The size of the delivered frames, the downscaled size for previews.
+    ///
+    /// The size is read from the device when the camera is activated and again when it is reconnected, so this is a cheap read that can be called every frame.
+    ///
+    /// The width and height are swapped when the transform rotates frames by 90 or 270 degrees.
+    pub fn dimensions(&self) -> Dimensions {
+        let size = self.dimensions.read().unwrap().clone();
+
+        self.applied_transform().transformed_size(&size)
+    }
+
+    /// # Set Transform
+    ///
+    /// Sets how frames are mirrored, flipped and rotated before they are sent, for front facing cameras that are shown mirrored
+    /// or cameras mounted upside down. Takes effect with the next frame.
+    ///
+    /// Only RGB32, RGB10A2 and RGBA16F frames are transformed (see FrameTransform::supports), other outputs are sent as they are.
+    pub fn set_transform(&self, transform: FrameTransform) {
+        *self.transform.write().unwrap() = transform;
+    }
+
+    /// # Transform
+    ///
+    /// How frames are mirrored, flipped and rotated before they are sent.
+    pub fn transform(&self) -> FrameTransform {
+        *self.transform.read().unwrap()
+    }
+
+    // the transform frames of the output really get, nothing for outputs that cannot be transformed
+    fn applied_transform(&self) -> FrameTransform {
+        if FrameTransform::supports(self.output) {
+            self.transform()
+        } else {
+            FrameTransform::default()
+        }
+    }
+
+    /// # Refresh Dimensions
+    ///
+    /// Reads the size of the frames from the device again and stores it for dimensions, sending CaptureEvent::Resized when it changed.
+    ///
+    /// Previews always keep their downscaled size.
+    pub fn refresh_dimensions(&self) -> Result<Dimensions, CaptureError> {
+        if self.preview.is_some() {
+            return Ok(self.dimensions());
+        }
+
+        let dimensions = self.reader_dimensions()?;
+
+        let previous =
+            std::mem::replace(&mut *self.dimensions.write().unwrap(), dimensions.clone());
+
+        if previous != dimensions {
+            let dimensions = self.dimensions();
+
+            trace_event!(
+                info,
+                width = dimensions.width,
+                height = dimensions.height,
+                "camera resized"
+            );
+
+            self.send_event(CaptureEvent::Resized {
+                width: dimensions.width,
+                height: dimensions.height,
+            });
+        }
+
+        Ok(self.dimensions())
+    }
+
+    // the errors of a device another application holds, or that the privacy settings keep from being opened
+    fn is_in_use(e: &windows::core::Error) -> bool {
+        let code = e.code();
+
+        code == MF_E_HW_MFT_FAILED_START_STREAMING
+            || code == MF_E_VIDEO_RECORDING_DEVICE_PREEMPTED
+            || code == E_ACCESSDENIED
+            || code == ERROR_SHARING_VIOLATION.to_hresult()
+            || code == ERROR_BUSY.to_hresult()
+    }
+
+    // reports an activation error of a device another application holds as CaptureError::DeviceInUse, with the name of the device
+    pub(crate) fn in_use_error(device: &IMFActivate, error: CaptureError) -> CaptureError {
+        Self::in_use_named(error, || {
+            unsafe { get_device_string(device, &MF_DEVSOURCE_ATTRIBUTE_FRIENDLY_NAME) }
+                .unwrap_or_else(|_| "the camera".into())
+        })
+    }
+
+    // the error as CaptureError::DeviceInUse when it is one of a device another application holds, the name is only read then
+    pub(crate) fn in_use_named(error: CaptureError, name: impl FnOnce() -> String) -> CaptureError {
+        match error {
+            CaptureError::Windows(error) if Self::is_in_use(&error) => CaptureError::DeviceInUse {
+                name: name(),
+                error,
+            },
+            error => error,
+        }
+    }
+
+    // a read error of a device another application holds as CaptureError::DeviceInUse
+    fn in_use(&self, error: windows::core::Error) -> CaptureError {
+        CaptureError::DeviceInUse {
+            name: self
+                .friendly_name
+                .clone()
+                .unwrap_or_else(|| "the camera".into()),
+            error,
+        }
+    }
+
+    // if the error means the device went away
+    fn is_device_lost(e: &windows::core::Error) -> bool {
+        let code = e.code();
+
+        code == MF_E_HW_MFT_FAILED_START_STREAMING
+            || code == MF_E_VIDEO_RECORDING_DEVICE_INVALIDATED
+            || code == MF_E_VIDEO_RECORDING_DEVICE_PREEMPTED
+            || code == ERROR_DEVICE_NOT_CONNECTED.to_hresult()
+            || code == ERROR_DEVICE_REMOVED.to_hresult()
+    }
+
+    // shuts down the lost media source and activates the device again with the same output and mode
+    unsafe fn reactivate(&self) -> Result<(), windows::core::Error> {
+        let Some(lost) = self.activate.read().unwrap().clone() else {
+            return Err(E_ABORT.into());
+        };
+
+        unsafe {
+            //the old source is dead, it must be shutdown before the device can be activated again.
+            let _ = lost.ShutdownObject();
+
+            //a device that was plugged in again is a new device object, found by its symbolic link
+            let activate = match &self.symbolic_link {
+                Some(symbolic_link) => activate_symbolic_link(symbolic_link)?,
+                None => lost,
+            };
+
+            let source = activate.ActivateObject::<IMFMediaSource>()?;
+            let media_reader = Self::create_reader(&source)?;
+
+            Self::set_stream_selection(&media_reader)?;
+            Self::select_native_format(
+                &media_reader,
+                &self.output,
+                self.mode.as_ref(),
+                self.access,
+            )?;
+            Self::set_output_format(&media_reader, &self.output)?;
+
+            *self.media_reader.write().unwrap() = media_reader;
+            *self.activate.write().unwrap() = Some(activate);
+        }
+
+        //removals seen before the device came back belong to the old source
+        self.removed.store(false, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    // tries to reconnect the device for the max duration of the policy (or its attempts), sending Disconnected and Reconnected events
+    async fn reconnect(&self, policy: RetryPolicy) -> Result<(), CaptureError> {
+        let started = Instant::now();
+        let mut attempt = 0;
+
+        self.send_event(CaptureEvent::Disconnected);
+
+        loop {
+            let gave_up = match policy.max_duration {
+                Some(max_duration) => started.elapsed() >= max_duration,
+                None => attempt >= policy.attempts,
+            };
+
+            if gave_up {
+                trace_event!(error, "device did not come back");
+                return Err(CaptureError::DeviceLost);
+            }
+
+            let mut delay = policy.delay_for(attempt).min(MAX_RECONNECT_DELAY);
+
+            //the last attempt is made right when the max duration runs out
+            if let Some(max_duration) = policy.max_duration {
+                delay = delay.min(max_duration.saturating_sub(started.elapsed()));
+            }
+
+            tokio::time::sleep(delay).await;
+
+            let reconnected = unsafe { self.reactivate() }
+                .inspect_err(|_e| {
+                    trace_event!(warn, hresult = %_e.code(), attempt, "reconnect failed");
+                })
+                .is_ok();
+
+            if reconnected {
+                trace_event!(info, "device reconnected");
+
+                //the device may have come back with another size
+                let _ = self.refresh_dimensions();
+
+                self.send_event(CaptureEvent::Reconnected {
+                    downtime: started.elapsed(),
+                });
+
+                return Ok(());
+            }
+
+            attempt += 1;
+        }
     }
 
     pub fn get_frame_data(buffer: &IMFMediaBuffer) -> Result<Vec<u8>, windows::core::Error> {
@@ -149,6 +1573,23 @@ impl Camera {
         }
     }
 
+    // transforms a tightly packed frame of the size, returning it with the size it has now.
+    //
+    // the size follows the transform that was really applied, a frame that could not be transformed keeps the size it was read at.
+    fn transform_frame(&self, data: Vec<u8>, size: &Dimensions) -> (Vec<u8>, Dimensions) {
+        let transform = self.applied_transform();
+
+        match transform.apply(
+            &data,
+            size.width as usize,
+            size.height as usize,
+            self.output,
+        ) {
+            Some(transformed) => (transformed, transform.transformed_size(size)),
+            None => (data, size.clone()),
+        }
+    }
+
     // sets the output format for the receiver.
     unsafe fn set_output_format(
         reader: &IMFSourceReader,
@@ -159,12 +1600,7 @@ impl Camera {
 
             media_type.SetGUID(&MF_MT_MAJOR_TYPE, &MFMediaType_Video)?;
 
-            let guid_value = match output {
-                Output::NV12 => &MFVideoFormat_NV12,
-                Output::RGB32 => &MFVideoFormat_RGB32,
-            };
-
-            media_type.SetGUID(&MF_MT_SUBTYPE, guid_value)?;
+            media_type.SetGUID(&MF_MT_SUBTYPE, &output.subtype())?;
 
             let first_video_stream = MF_SOURCE_READER_FIRST_VIDEO_STREAM.0 as u32;
             reader.SetCurrentMediaType(first_video_stream, None, &media_type)?;
@@ -173,6 +1609,71 @@ impl Camera {
         Ok(())
     }
 
+    // picks the native type of the mode, else the one with the largest frame size, then the highest frame rate, that the output can be produced from.
+    //
+    // many cameras only offer their high resolutions and frame rates as MJPEG or YUY2, which media foundation decodes into the output.
+    // the current native type is kept if no native type matches, the selected mode is returned.
+    unsafe fn select_native_format(
+        reader: &IMFSourceReader,
+        output: &Output,
+        mode: Option<&MediaTypeInfo>,
+        access: AccessMode,
+    ) -> Result<Option<MediaTypeInfo>, windows::core::Error> {
+        let first_video_stream = MF_SOURCE_READER_FIRST_VIDEO_STREAM.0 as u32;
+        let mut best = None;
+        let mut index = 0;
+
+        unsafe {
+            //a shared device keeps the type it delivers, another application may be streaming it
+            if access == AccessMode::Shared {
+                let current = reader.GetCurrentMediaType(first_video_stream)?;
+                return MediaTypeInfo::from_media_type(&current);
+            }
+
+            loop {
+                let media_type = match reader.GetNativeMediaType(first_video_stream, index) {
+                    Ok(media_type) => media_type,
+                    //all types have been read
+                    Err(e) if e.code() == MF_E_NO_MORE_TYPES => break,
+                    Err(e) => return Err(e),
+                };
+
+                index += 1;
+
+                let Some(info) = MediaTypeInfo::from_media_type(&media_type)? else {
+                    continue;
+                };
+
+                //the mode is one of the native types, a reconnected device that lost it falls back to the largest
+                if mode == Some(&info) {
+                    reader.SetCurrentMediaType(first_video_stream, None, &media_type)?;
+                    return Ok(Some(info));
+                }
+
+                if !output.can_convert_from(info.format) {
+                    continue;
+                }
+
+                let rank = ((info.width as u64) * (info.height as u64), info.fps());
+
+                if best
+                    .as_ref()
+                    .is_none_or(|(best_rank, _, _)| rank > *best_rank)
+                {
+                    best = Some((rank, media_type, info));
+                }
+            }
+
+            let Some((_, media_type, info)) = best else {
+                return Ok(None);
+            };
+
+            reader.SetCurrentMediaType(first_video_stream, None, &media_type)?;
+
+            Ok(Some(info))
+        }
+    }
+
     // set the stream selection, this is by default the first video stream from all rendering streams.
     unsafe fn set_stream_selection(reader: &IMFSourceReader) -> Result<(), windows::core::Error> {
         unsafe {
@@ -186,8 +1687,38 @@ impl Camera {
         Ok(())
     }
 
+    // the native types of the first video stream with a known output, sorted and without duplicates
+    pub(crate) unsafe fn native_media_types(
+        reader: &IMFSourceReader,
+    ) -> Result<Vec<MediaTypeInfo>, windows::core::Error> {
+        let first_video_stream = MF_SOURCE_READER_FIRST_VIDEO_STREAM.0 as u32;
+        let mut infos = vec![];
+        let mut index = 0;
+
+        unsafe {
+            loop {
+                let media_type = match reader.GetNativeMediaType(first_video_stream, index) {
+                    Ok(media_type) => media_type,
+                    //all types have been read
+                    Err(e) if e.code() == MF_E_NO_MORE_TYPES => break,
+                    Err(e) => return Err(e),
+                };
+
+                if let Some(info) = MediaTypeInfo::from_media_type(&media_type)? {
+                    infos.push(info);
+                }
+
+                index += 1;
+            }
+        }
+
+        MediaTypeInfo::sort_and_dedup(&mut infos);
+
+        Ok(infos)
+    }
+
     // creates the IMFSource reader for video processing and enables hardware transforms
-    unsafe fn create_reader(
+    pub(crate) unsafe fn create_reader(
         source: &IMFMediaSource,
     ) -> Result<IMFSourceReader, windows::core::Error> {
         unsafe {
@@ -211,32 +1742,35 @@ impl Camera {
 }
 
 impl ICapture for Camera {
-    type CaptureOutput = Vec<u8>;
+    type CaptureOutput = Frame;
 
     /// # Get Dimensions
     ///
-    /// Get the device size of the video camera.
+    /// Get the device size of the video camera, the downscaled size for previews.
+    ///
+    /// This is the cached size of dimensions and never fails, use refresh_dimensions to read it from the device again.
     fn get_dimensions(&self) -> Result<Dimensions, Box<dyn std::error::Error>> {
-        let first_video_stream = MF_SOURCE_READER_FIRST_VIDEO_STREAM.0 as u32;
-        let size: Option<u64>;
-
-        //create unsafe calls to get the media type and the dimensions store as a u64
-        unsafe {
-            let media_type = self.media_reader.GetCurrentMediaType(first_video_stream)?;
-
-            size = Some(media_type.GetUINT64(&MF_MT_FRAME_SIZE)?);
-        }
-
-        if size.is_none() {
-            return Err("Could not resolve size of device".into());
-        }
+        Ok(self.dimensions())
+    }
 
-        let size = size.unwrap();
+    /// # Get Output Format
+    ///
+    /// The format the source reader actually delivers, read from the current media type.
+    ///
+    /// Falls back to the requested output if the current media type cannot be read.
+    fn get_output_format(&self) -> Output {
+        let first_video_stream = MF_SOURCE_READER_FIRST_VIDEO_STREAM.0 as u32;
 
-        let width = (size >> 32) as u32;
-        let height = (size & 0xFFFFFFFF) as u32;
+        let subtype = unsafe {
+            self.media_reader()
+                .GetCurrentMediaType(first_video_stream)
+                .and_then(|media_type| media_type.GetGUID(&MF_MT_SUBTYPE))
+        };
 
-        Ok(Dimensions { width, height })
+        subtype
+            .ok()
+            .and_then(|subtype| Output::from_subtype(&subtype))
+            .unwrap_or(self.output)
     }
 
     /// ## Stop Captruing
@@ -246,14 +1780,11 @@ impl ICapture for Camera {
         self: Arc<Self>,
     ) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>> + Send>> {
         Box::pin(async move {
-            let mut cap_guard = self.is_capturing.lock().await;
-
-            if !*cap_guard {
+            //the loop ends once it sees the state is not capturing anymore
+            if !self.state.stop() {
                 return Err("already stopped.".into());
             }
 
-            *cap_guard = false;
-
             Ok(())
         })
     }
@@ -273,35 +1804,185 @@ impl ICapture for Camera {
         self: Arc<Self>,
     ) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>> + Send>> {
         Box::pin(async move {
-            // lock the capguard, check if already capturing, if not set as true and continue
-            {
-                let mut cap_guard = self.is_capturing.lock().await;
-
-                if *cap_guard {
-                    return Err("already capturing".into());
-                }
+            // the state is stopped again when the loop ends, also with an error
+            let _running = self.state.start().await?;
 
-                *cap_guard = true;
-            }
+            self.frame_rate.reset();
 
             //clone all resources that need to be moved
-            let is_capturing_ref = self.is_capturing.clone();
-            let sender = self.sender.clone();
+            let sender = self.sender.read().unwrap().clone();
+            let started = Instant::now();
+
+            //previews scale the frames of the native mode, which may change when the device reconnects
+            let mut native = self.reader_dimensions().ok();
+            let mut next_preview = Duration::ZERO;
+
+            //frames over the max fps are dropped against the rate of the mode, which may change when the device reconnects
+            let mut native_fps = self.native_fps();
+            let mut limiter = FrameLimiter::default();
+
+            let mut detector = ChangeDetector::default();
+
+            //the last frame the backpressure dropped, counted in the next frame that is sent
+            let mut dropped = None;
+
+            //a device that never delivered was held by another application, not lost
+            let mut delivered = false;
+
+            //the frames left to discard while auto exposure settles, again after a reconnect
+            let mut warmup = self.warmup_frames();
+
             loop {
-                //check if capturing, drop immediately
+                //stop_capturing moves the state to stopping
+                if !self.is_capturing() {
+                    break;
+                }
+
+                //a device watcher saw the device unplugged, report it as lost instead of with the error of the next read
+                if self.removed.swap(false, Ordering::Relaxed) {
+                    trace_event!(warn, "the device was removed");
+
+                    let policy = *self.auto_reconnect.read().unwrap();
+
+                    let Some(policy) = policy else {
+                        return Err(CaptureError::DeviceLost.into());
+                    };
+
+                    self.reconnect(policy).await?;
+
+                    native = self.reader_dimensions().ok();
+                    native_fps = self.native_fps();
+                    limiter.reset();
+                    warmup = self.warmup_frames();
+                    continue;
+                }
+
+                let first_video_stream = MF_SOURCE_READER_FIRST_VIDEO_STREAM.0 as u32;
+
+                let (data, presentation_time) =
+                    match self.read_timed_sample(Some(first_video_stream)) {
+                        Ok(sample) => sample,
+                        Err(e) => {
+                            trace_event!(error, hresult = %e.code(), "ReadSample failed");
+
+                            let policy = *self.auto_reconnect.read().unwrap();
+
+                            let removed = self.removed.swap(false, Ordering::Relaxed);
+
+                            if !delivered && !removed && Self::is_in_use(&e) {
+                                return Err(self.in_use(e).into());
+                            }
+
+                            match policy {
+                                Some(policy) if removed || Self::is_device_lost(&e) => {
+                                    self.reconnect(policy).await?;
+
+                                    //the reconnected device may have picked another size
+                                    native = self.reader_dimensions().ok();
+                                    native_fps = self.native_fps();
+                                    limiter.reset();
+                                    warmup = self.warmup_frames();
+                                    continue;
+                                }
+                                None if removed => return Err(CaptureError::DeviceLost.into()),
+                                _ => return Err(e.into()),
+                            }
+                        }
+                    };
+
+                delivered = true;
+
+                if warmup > 0 {
+                    warmup -= 1;
+                    trace_event!(trace, warmup, "discarding a camera warmup frame");
+                    continue;
+                }
+
+                //timestamps are in 100 nanosecond units of the clock
+                if let Some(max_fps) = self.max_fps()
+                    && !limiter.admit(
+                        Duration::from_nanos(presentation_time.max(0) as u64 * 100),
+                        max_fps,
+                        native_fps,
+                    )
                 {
-                    let is_capturing = is_capturing_ref.lock().await;
+                    trace_event!(trace, max_fps, "dropping a camera frame over the max fps");
+                    continue;
+                }
 
-                    if !*is_capturing {
-                        break;
+                let data = match (self.preview, &native) {
+                    (Some(preview), Some(native)) => {
+                        let elapsed = started.elapsed();
+
+                        //frames faster than the frame rate of the preview are dropped
+                        if elapsed < next_preview
+                            || check_size(Output::RGB32, &data, native.width, native.height)
+                                .is_err()
+                        {
+                            continue;
+                        }
+
+                        next_preview = (next_preview + preview.interval).max(elapsed);
+
+                        scale_bgra(
+                            &data,
+                            native.width as usize,
+                            native.height as usize,
+                            preview.width as usize,
+                            preview.height as usize,
+                        )
                     }
+                    _ => data,
+                };
+
+                //frames are transformed at the size they were read or scaled to
+                let size = self.dimensions.read().unwrap().clone();
+                let (data, size) = self.transform_frame(data, &size);
+
+                trace_event!(trace, bytes = data.len(), "sending camera frame");
+
+                let mut frame = Frame::new(data, started.elapsed());
+                frame.stride = self.output.stride(size.width);
+                frame.presentation_time = presentation_time;
+
+                //a photo of a camera without a photo stream is the next frame
+                if let Some(waiter) = self.photo_waiter.write().unwrap().take() {
+                    let _ = waiter.send(frame.clone());
                 }
 
-                let first_video_stream = MF_SOURCE_READER_FIRST_VIDEO_STREAM.0 as u32;
+                if let Some(detection) = self.change_detection() {
+                    frame.is_duplicate = detector.is_duplicate(&detection, &frame.data);
 
-                let data = self.read_sample(Some(first_video_stream))?;
+                    //a still frame after a dropped one is the first the consumer sees of the change
+                    if frame.is_duplicate && detection.skip_duplicates && dropped.is_none() {
+                        trace_event!(trace, "skipping duplicate camera frame");
+                        continue;
+                    }
+                }
 
-                sender.send(data).await?;
+                let backpressure = self.backpressure();
+
+                self.frame_rate.record(Instant::now());
+
+                let sent = send_with_backpressure(
+                    &sender,
+                    &self.receiver,
+                    backpressure,
+                    &mut dropped,
+                    frame,
+                )
+                .await
+                .inspect_err(|_| {
+                    trace_event!(warn, "receiver dropped, stopping camera capture");
+                })?;
+
+                if !sent {
+                    trace_event!(
+                        debug,
+                        ?backpressure,
+                        "receiver full, dropped a camera frame"
+                    );
+                }
             }
 
             Ok(())
@@ -314,6 +1995,84 @@ impl ICapture for Camera {
     fn clone_receiver(&self) -> Arc<Mutex<tokio::sync::mpsc::Receiver<Self::CaptureOutput>>> {
         self.receiver.clone()
     }
+
+    fn set_frame_timeout(&self, duration: Duration) {
+        *self.frame_timeout.write().unwrap() = Some(duration);
+    }
+
+    fn clear_frame_timeout(&self) {
+        *self.frame_timeout.write().unwrap() = None;
+    }
+
+    fn get_frame_timeout(&self) -> Option<Duration> {
+        *self.frame_timeout.read().unwrap()
+    }
+
+    fn frame_callback(&self) -> &FrameCallback<Frame> {
+        &self.frame_callback
+    }
+
+    fn state_cell(&self) -> &StateCell {
+        &self.state
+    }
+}
+
+impl ISyncCapture for Camera {
+    fn start_capturing(&self) -> Result<(), CaptureError> {
+        self.sync.start(&self.this)
+    }
+
+    fn stop_capturing(&self) -> Result<(), CaptureError> {
+        self.sync.stop()
+    }
+
+    fn recv_frame(&self, timeout: Option<Duration>) -> Result<Option<Vec<u8>>, CaptureError> {
+        self.sync.recv(timeout)
+    }
+
+    /// The cached size of dimensions.
+    fn get_dimensions(&self) -> Result<Dimensions, CaptureError> {
+        Ok(self.dimensions())
+    }
+}
+
+// shuts the media source of an activation down when dropped, unless it was kept.
+//
+// an activated source holds the device until it is shut down, releasing the activation is not enough.
+pub(crate) struct SourceShutdown<'a> {
+    activate: &'a IMFActivate,
+    keep: bool,
+}
+
+impl<'a> SourceShutdown<'a> {
+    pub(crate) fn new(activate: &'a IMFActivate) -> Self {
+        Self {
+            activate,
+            keep: false,
+        }
+    }
+
+    // the source is used by a camera, which shuts it down itself
+    pub(crate) fn keep(mut self) {
+        self.keep = true;
+    }
+}
+
+impl Drop for SourceShutdown<'_> {
+    fn drop(&mut self) {
+        if !self.keep {
+            let _ = unsafe { self.activate.ShutdownObject() };
+        }
+    }
+}
+
+impl Drop for Camera {
+    // every activated camera has its own media source, shutting it down frees the device for other activations
+    fn drop(&mut self) {
+        if let Some(activate) = self.activate.get_mut().unwrap() {
+            let _ = unsafe { activate.ShutdownObject() };
+        }
+    }
 }
 
 unsafe impl Send for Camera {}
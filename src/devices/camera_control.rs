@@ -0,0 +1,466 @@
+use windows::{
+    Win32::Media::{
+        DirectShow::{
+            CameraControl_Exposure, CameraControl_Focus, CameraControl_Iris, CameraControl_Pan,
+            CameraControl_Roll, CameraControl_Tilt, CameraControl_Zoom, E_PROP_ID_UNSUPPORTED,
+            IAMCameraControl, IAMVideoProcAmp, VideoProcAmp_BacklightCompensation,
+            VideoProcAmp_Brightness, VideoProcAmp_ColorEnable, VideoProcAmp_Contrast,
+            VideoProcAmp_Flags_Auto, VideoProcAmp_Flags_Manual, VideoProcAmp_Gain,
+            VideoProcAmp_Gamma, VideoProcAmp_Hue, VideoProcAmp_Saturation, VideoProcAmp_Sharpness,
+            VideoProcAmp_WhiteBalance,
+        },
+        KernelStreaming::KSPROPERTY_VIDEOPROCAMP_POWERLINE_FREQUENCY,
+        MediaFoundation::IMFMediaSource,
+    },
+    core::Interface,
+};
+
+use crate::capture_error::CaptureError;
+
+/// # Camera Property
+///
+/// A setting of a camera that can be read and changed through CameraControls.
+///
+/// The units are defined by the driver, the documented units are the ones of the DirectShow properties.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CameraProperty {
+    /// The black level of the image.
+    Brightness,
+
+    /// The difference between the darkest and brightest parts of the image.
+    Contrast,
+
+    /// The hue in degrees times 100.
+    Hue,
+
+    /// The amount of color.
+    Saturation,
+
+    /// How strongly edges are enhanced.
+    Sharpness,
+
+    /// The gamma times 100.
+    Gamma,
+
+    /// 0 for black and white, 1 for color.
+    ColorEnable,
+
+    /// The color temperature in kelvin.
+    WhiteBalance,
+
+    /// 0 when off, 1 when on.
+    BacklightCompensation,
+
+    /// The amplification of the sensor signal.
+    Gain,
+
+    /// The exposure time as log2 of seconds, -5 is 1/32 s.
+    Exposure,
+
+    /// The aperture as the f-stop times 10.
+    Iris,
+
+    /// The optical zoom as the focal length in millimeters.
+    Zoom,
+
+    /// The distance the lens is focused at in millimeters, auto is auto focus.
+    Focus,
+
+    /// The horizontal angle of the camera in degrees, positive turns right.
+    Pan,
+
+    /// The vertical angle of the camera in degrees, positive turns up.
+    Tilt,
+
+    /// The rotation of the image in degrees, positive turns clockwise.
+    Roll,
+
+    /// The frequency of the mains the exposure avoids flicker at, see PowerLineFrequency for the values.
+    PowerLineFrequency,
+}
+
+impl CameraProperty {
+    /// # All
+    ///
+    /// Every property, in the order CameraControls::supported lists them.
+    pub const ALL: [CameraProperty; 18] = [
+        CameraProperty::Brightness,
+        CameraProperty::Contrast,
+        CameraProperty::Hue,
+        CameraProperty::Saturation,
+        CameraProperty::Sharpness,
+        CameraProperty::Gamma,
+        CameraProperty::ColorEnable,
+        CameraProperty::WhiteBalance,
+        CameraProperty::BacklightCompensation,
+        CameraProperty::Gain,
+        CameraProperty::Exposure,
+        CameraProperty::Iris,
+        CameraProperty::Zoom,
+        CameraProperty::Focus,
+        CameraProperty::Pan,
+        CameraProperty::Tilt,
+        CameraProperty::Roll,
+        CameraProperty::PowerLineFrequency,
+    ];
+
+    // the interface of the property and its id on that interface
+    fn id(&self) -> PropertyId {
+        match self {
+            CameraProperty::Brightness => PropertyId::ProcAmp(VideoProcAmp_Brightness.0),
+            CameraProperty::Contrast => PropertyId::ProcAmp(VideoProcAmp_Contrast.0),
+            CameraProperty::Hue => PropertyId::ProcAmp(VideoProcAmp_Hue.0),
+            CameraProperty::Saturation => PropertyId::ProcAmp(VideoProcAmp_Saturation.0),
+            CameraProperty::Sharpness => PropertyId::ProcAmp(VideoProcAmp_Sharpness.0),
+            CameraProperty::Gamma => PropertyId::ProcAmp(VideoProcAmp_Gamma.0),
+            CameraProperty::ColorEnable => PropertyId::ProcAmp(VideoProcAmp_ColorEnable.0),
+            CameraProperty::WhiteBalance => PropertyId::ProcAmp(VideoProcAmp_WhiteBalance.0),
+            CameraProperty::BacklightCompensation => {
+                PropertyId::ProcAmp(VideoProcAmp_BacklightCompensation.0)
+            }
+            CameraProperty::Gain => PropertyId::ProcAmp(VideoProcAmp_Gain.0),
+            CameraProperty::Exposure => PropertyId::CameraControl(CameraControl_Exposure.0),
+            CameraProperty::Iris => PropertyId::CameraControl(CameraControl_Iris.0),
+            CameraProperty::Zoom => PropertyId::CameraControl(CameraControl_Zoom.0),
+            CameraProperty::Focus => PropertyId::CameraControl(CameraControl_Focus.0),
+            CameraProperty::Pan => PropertyId::CameraControl(CameraControl_Pan.0),
+            CameraProperty::Tilt => PropertyId::CameraControl(CameraControl_Tilt.0),
+            CameraProperty::Roll => PropertyId::CameraControl(CameraControl_Roll.0),
+            //the proc amp ids are the ids of the kernel streaming property set, which has no DirectShow name for this one
+            CameraProperty::PowerLineFrequency => {
+                PropertyId::ProcAmp(KSPROPERTY_VIDEOPROCAMP_POWERLINE_FREQUENCY.0)
+            }
+        }
+    }
+}
+
+/// # Power Line Frequency
+///
+/// The anti flicker setting of a camera, the exposure is matched to the frequency of the mains so lights do not flicker in the image.
+///
+/// Lights flicker at twice the frequency of the mains, which is 50 Hz in most of Europe, Asia and Africa and 60 Hz in the Americas.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PowerLineFrequency {
+    /// No flicker compensation.
+    Disabled,
+
+    Hz50,
+
+    Hz60,
+
+    /// The camera detects the frequency on its own, only some (UVC 1.5) cameras support it.
+    Auto,
+}
+
+impl PowerLineFrequency {
+    // the value of the property, as defined by UVC
+    fn value(&self) -> i32 {
+        match self {
+            PowerLineFrequency::Disabled => 0,
+            PowerLineFrequency::Hz50 => 1,
+            PowerLineFrequency::Hz60 => 2,
+            PowerLineFrequency::Auto => 3,
+        }
+    }
+
+    fn from_value(value: i32) -> Option<Self> {
+        match value {
+            0 => Some(PowerLineFrequency::Disabled),
+            1 => Some(PowerLineFrequency::Hz50),
+            2 => Some(PowerLineFrequency::Hz60),
+            3 => Some(PowerLineFrequency::Auto),
+            _ => None,
+        }
+    }
+}
+
+// properties are split over two interfaces with the same methods
+enum PropertyId {
+    ProcAmp(i32),
+    CameraControl(i32),
+}
+
+/// # Property Range
+///
+/// The values a property of a camera accepts, for example the bounds of a slider.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PropertyRange {
+    /// The smallest value.
+    pub min: i32,
+
+    /// The largest value.
+    pub max: i32,
+
+    /// The distance between two values the property can have.
+    pub step: i32,
+
+    /// The value of the property when the camera is reset.
+    pub default: i32,
+
+    /// If the camera can adjust the property on its own, see CameraControls::set_auto.
+    pub supports_auto: bool,
+
+    /// If the property can be set to a value.
+    pub supports_manual: bool,
+}
+
+impl PropertyRange {
+    /// # Contains
+    ///
+    /// If the value is within min and max.
+    pub fn contains(&self, value: i32) -> bool {
+        (self.min..=self.max).contains(&value)
+    }
+}
+
+/// # Property Value
+///
+/// The current value of a property and if the camera adjusts it on its own.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PropertyValue {
+    /// The current value, while auto is set this is the value the camera chose (or the last manual value on some drivers).
+    pub value: i32,
+
+    /// If the camera adjusts the property on its own.
+    pub auto: bool,
+}
+
+/// # Camera Controls
+///
+/// Reads and changes the brightness, exposure, white balance, zoom, focus, pan, tilt and other properties of an activated camera, obtained through Camera::controls.
+///
+/// Cameras only support some of the properties, range returns None and get, set and set_auto return CaptureError::UnsupportedControl for the others.
+///
+/// Changes are applied to the device right away and usually persist until the device is unplugged, even after the camera is dropped.
+pub struct CameraControls {
+    proc_amp: Option<IAMVideoProcAmp>,
+
+    camera_control: Option<IAMCameraControl>,
+}
+
+impl CameraControls {
+    // the control interfaces of the media source, a source without them supports no properties
+    pub(crate) fn new(source: &IMFMediaSource) -> Self {
+        CameraControls {
+            proc_amp: source.cast().ok(),
+            camera_control: source.cast().ok(),
+        }
+    }
+
+    /// # Supported
+    ///
+    /// Every property the camera supports with its range.
+    pub fn supported(&self) -> Vec<(CameraProperty, PropertyRange)> {
+        CameraProperty::ALL
+            .into_iter()
+            .filter_map(|property| Some((property, self.range(property)?)))
+            .collect()
+    }
+
+    /// # Range
+    ///
+    /// The values the property accepts, None when the camera does not support it.
+    pub fn range(&self, property: CameraProperty) -> Option<PropertyRange> {
+        let (mut min, mut max, mut step, mut default, mut caps) = (0, 0, 0, 0, 0);
+
+        unsafe {
+            match property.id() {
+                PropertyId::ProcAmp(id) => self.proc_amp.as_ref()?.GetRange(
+                    id,
+                    &mut min,
+                    &mut max,
+                    &mut step,
+                    &mut default,
+                    &mut caps,
+                ),
+                PropertyId::CameraControl(id) => self.camera_control.as_ref()?.GetRange(
+                    id,
+                    &mut min,
+                    &mut max,
+                    &mut step,
+                    &mut default,
+                    &mut caps,
+                ),
+            }
+            .ok()?;
+        }
+
+        //both interfaces use the same flag values
+        Some(PropertyRange {
+            min,
+            max,
+            step,
+            default,
+            supports_auto: caps & VideoProcAmp_Flags_Auto.0 != 0,
+            supports_manual: caps & VideoProcAmp_Flags_Manual.0 != 0,
+        })
+    }
+
+    /// # Get
+    ///
+    /// The current value of the property.
+    pub fn get(&self, property: CameraProperty) -> Result<PropertyValue, CaptureError> {
+        let (mut value, mut flags) = (0, 0);
+
+        unsafe {
+            match property.id() {
+                PropertyId::ProcAmp(id) => self.proc_amp(property)?.Get(id, &mut value, &mut flags),
+                PropertyId::CameraControl(id) => self
+                    .camera_control(property)?
+                    .Get(id, &mut value, &mut flags),
+            }
+            .map_err(|e| unsupported_or(property, e))?;
+        }
+
+        Ok(PropertyValue {
+            value,
+            auto: flags & VideoProcAmp_Flags_Auto.0 != 0,
+        })
+    }
+
+    /// # Set
+    ///
+    /// Sets the property to the value and turns off its auto mode.
+    ///
+    /// Returns CaptureError::ControlOutOfRange when the value is outside of the range, values between two steps are rounded by the driver.
+    pub fn set(&self, property: CameraProperty, value: i32) -> Result<(), CaptureError> {
+        let range = self
+            .range(property)
+            .ok_or(CaptureError::UnsupportedControl(property))?;
+
+        if !range.supports_manual {
+            return Err(CaptureError::UnsupportedControl(property));
+        }
+
+        if !range.contains(value) {
+            return Err(CaptureError::ControlOutOfRange {
+                property,
+                value,
+                min: range.min,
+                max: range.max,
+            });
+        }
+
+        self.write(property, value, false)
+    }
+
+    /// # Move By
+    ///
+    /// Moves the property by a number of steps of its range from the current value, for example panning one step to the right with move_by(CameraProperty::Pan, 1).
+    ///
+    /// Moves past the end of the range stop at min or max, the new value is returned. Turns off the auto mode of the property.
+    pub fn move_by(&self, property: CameraProperty, steps: i32) -> Result<i32, CaptureError> {
+        let range = self
+            .range(property)
+            .ok_or(CaptureError::UnsupportedControl(property))?;
+
+        let current = self.get(property)?;
+
+        let value = (current.value as i64 + steps as i64 * range.step.max(1) as i64)
+            .clamp(range.min as i64, range.max as i64) as i32;
+
+        self.set(property, value)?;
+
+        Ok(value)
+    }
+
+    /// # Set Auto
+    ///
+    /// Lets the camera adjust the property on its own, or keeps the current value when auto is false.
+    ///
+    /// Returns CaptureError::UnsupportedControl when the camera cannot adjust the property on its own.
+    pub fn set_auto(&self, property: CameraProperty, auto: bool) -> Result<(), CaptureError> {
+        let range = self
+            .range(property)
+            .ok_or(CaptureError::UnsupportedControl(property))?;
+
+        if (auto && !range.supports_auto) || (!auto && !range.supports_manual) {
+            return Err(CaptureError::UnsupportedControl(property));
+        }
+
+        let current = self.get(property)?;
+
+        self.write(property, current.value, auto)
+    }
+
+    /// # Set Power Line Frequency
+    ///
+    /// Sets the anti flicker frequency of the camera, for recording under lights of another mains frequency than the camera was set up for.
+    ///
+    /// Returns CaptureError::UnsupportedControl when the camera has no power line frequency property
+    /// and CaptureError::ControlOutOfRange when it does not support the frequency, such as Auto on older cameras.
+    pub fn set_power_line_frequency(
+        &self,
+        frequency: PowerLineFrequency,
+    ) -> Result<(), CaptureError> {
+        self.set(CameraProperty::PowerLineFrequency, frequency.value())
+    }
+
+    /// # Power Line Frequency
+    ///
+    /// The current anti flicker frequency of the camera.
+    ///
+    /// Returns CaptureError::UnsupportedControl when the camera has no power line frequency property, see supports_power_line_frequency.
+    pub fn power_line_frequency(&self) -> Result<PowerLineFrequency, CaptureError> {
+        let current = self.get(CameraProperty::PowerLineFrequency)?;
+
+        PowerLineFrequency::from_value(current.value).ok_or_else(|| {
+            CaptureError::Other(format!(
+                "the camera reported an unknown power line frequency of {}",
+                current.value
+            ))
+        })
+    }
+
+    /// # Supports Power Line Frequency
+    ///
+    /// If the camera has a power line frequency property, range(CameraProperty::PowerLineFrequency) tells if it supports Auto (a max of 3).
+    pub fn supports_power_line_frequency(&self) -> bool {
+        self.range(CameraProperty::PowerLineFrequency).is_some()
+    }
+
+    // sets the value with the auto or manual flag
+    fn write(&self, property: CameraProperty, value: i32, auto: bool) -> Result<(), CaptureError> {
+        let flags = if auto {
+            VideoProcAmp_Flags_Auto.0
+        } else {
+            VideoProcAmp_Flags_Manual.0
+        };
+
+        unsafe {
+            match property.id() {
+                PropertyId::ProcAmp(id) => self.proc_amp(property)?.Set(id, value, flags),
+                PropertyId::CameraControl(id) => {
+                    self.camera_control(property)?.Set(id, value, flags)
+                }
+            }
+            .map_err(|e| unsupported_or(property, e))
+        }
+    }
+
+    fn proc_amp(&self, property: CameraProperty) -> Result<&IAMVideoProcAmp, CaptureError> {
+        self.proc_amp
+            .as_ref()
+            .ok_or(CaptureError::UnsupportedControl(property))
+    }
+
+    fn camera_control(&self, property: CameraProperty) -> Result<&IAMCameraControl, CaptureError> {
+        self.camera_control
+            .as_ref()
+            .ok_or(CaptureError::UnsupportedControl(property))
+    }
+}
+
+// drivers answer properties they lack with E_PROP_ID_UNSUPPORTED
+fn unsupported_or(property: CameraProperty, e: windows::core::Error) -> CaptureError {
+    if e.code() == E_PROP_ID_UNSUPPORTED {
+        CaptureError::UnsupportedControl(property)
+    } else {
+        CaptureError::Windows(e)
+    }
+}
+
+// the interfaces are only called through their methods, which drivers implement free threaded
+unsafe impl Send for CameraControls {}
+
+unsafe impl Sync for CameraControls {}
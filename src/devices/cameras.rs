@@ -2,15 +2,166 @@ use std::{ffi::c_void, sync::Arc};
 
 use windows::Win32::{
     Media::MediaFoundation::{
-        IMFActivate, IMFAttributes, MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE,
-        MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE_VIDCAP_GUID, MFCreateAttributes, MFEnumDeviceSources,
+        IMFActivate, IMFAttributes, IMFMediaSource, MF_DEVSOURCE_ATTRIBUTE_FRIENDLY_NAME,
+        MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE, MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE_VIDCAP_GUID,
+        MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE_VIDCAP_SYMBOLIC_LINK, MFCreateAttributes,
+        MFCreateDeviceSourceActivate, MFEnumDeviceSources,
     },
     System::Com::CoTaskMemFree,
 };
 
-use windows::Win32::Foundation::E_FAIL;
+use windows::Win32::Foundation::{E_FAIL, E_INVALIDARG};
+use windows::core::HSTRING;
 
-use crate::devices::{Camera, camera::Output};
+use crate::capture_error::CaptureError;
+use crate::devices::{
+    Camera, DeviceChanges, DeviceConfig, DeviceInfo, DeviceWatcher, MediaTypeInfo,
+    camera::{Output, SourceShutdown},
+    get_device_string,
+};
+use crate::init::{MediaFoundation, ensure_com};
+
+// creates a new activation object for the video capture device with the symbolic link
+pub(crate) unsafe fn activate_symbolic_link(
+    symbolic_link: &str,
+) -> Result<IMFActivate, windows::core::Error> {
+    unsafe {
+        let mut attributes: Option<IMFAttributes> = None;
+        MFCreateAttributes(&mut attributes, 2)?;
+
+        let attributes = attributes.ok_or(windows::core::Error::from(E_FAIL))?;
+
+        attributes.SetGUID(
+            &MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE,
+            &MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE_VIDCAP_GUID,
+        )?;
+        attributes.SetString(
+            &MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE_VIDCAP_SYMBOLIC_LINK,
+            &HSTRING::from(symbolic_link),
+        )?;
+
+        MFCreateDeviceSourceActivate(&attributes)
+    }
+}
+
+// compares the symbolic links of a refresh with the known ones, returning the positions of the known links that are gone
+// and of the found links that are new (a link found twice is only new once).
+pub(crate) fn diff_symbolic_links(known: &[&str], found: &[&str]) -> (Vec<usize>, Vec<usize>) {
+    let removed = known
+        .iter()
+        .enumerate()
+        .filter(|(_, link)| !found.contains(link))
+        .map(|(position, _)| position)
+        .collect();
+
+    let added = found
+        .iter()
+        .enumerate()
+        .filter(|(position, link)| !known.contains(link) && !found[..*position].contains(link))
+        .map(|(position, _)| position)
+        .collect();
+
+    (removed, added)
+}
+
+/// # Device Selector
+///
+/// Picks one of the devices of a Cameras, implemented for a device index, a DeviceInfo and a device from Cameras::devices.
+pub trait DeviceSelector {
+    /// # Select
+    ///
+    /// The device that is picked, None when the cameras have no such device.
+    fn select<'s>(&'s self, cameras: &'s Cameras) -> Option<&'s IMFActivate>;
+}
+
+impl DeviceSelector for usize {
+    fn select<'s>(&'s self, cameras: &'s Cameras) -> Option<&'s IMFActivate> {
+        cameras.devices.get(*self)
+    }
+}
+
+impl DeviceSelector for &DeviceInfo {
+    /// Finds the device by its symbolic link, so the info of an earlier enumeration still finds the same device.
+    fn select<'s>(&'s self, cameras: &'s Cameras) -> Option<&'s IMFActivate> {
+        cameras
+            .infos
+            .iter()
+            .find(|info| info.symbolic_link == self.symbolic_link)
+            .and_then(|info| cameras.devices.get(info.index))
+    }
+}
+
+impl DeviceSelector for &IMFActivate {
+    fn select<'s>(&'s self, _cameras: &'s Cameras) -> Option<&'s IMFActivate> {
+        Some(*self)
+    }
+}
+
+impl DeviceSelector for DeviceHandle<'_> {
+    fn select<'s>(&'s self, cameras: &'s Cameras) -> Option<&'s IMFActivate> {
+        cameras.devices.get(self.index)
+    }
+}
+
+/// # Device Handle
+///
+/// One of the devices of a Cameras, returned by Cameras::get for a valid index.
+///
+/// The handle borrows the cameras, so it can not outlive the enumerated devices and activating it is safe.
+#[derive(Clone, Copy)]
+pub struct DeviceHandle<'c> {
+    cameras: &'c Cameras,
+    index: usize,
+}
+
+impl<'c> DeviceHandle<'c> {
+    /// # Index
+    ///
+    /// The index of the device in the cameras.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// # Info
+    ///
+    /// The friendly name, symbolic link and index of the device.
+    pub fn info(&self) -> &'c DeviceInfo {
+        &self.cameras.infos[self.index]
+    }
+
+    /// # Friendly Name
+    ///
+    /// The readable name of the device, for example "Logitech C920".
+    pub fn friendly_name(&self) -> &'c str {
+        &self.info().friendly_name
+    }
+
+    /// # Activate
+    ///
+    /// Activates the device with the native mode closest to the config, see Cameras::activate_device_with.
+    pub fn activate(&self, config: DeviceConfig) -> Result<Arc<Camera>, CaptureError> {
+        unsafe { self.cameras.activate_device_with(*self, config) }
+    }
+
+    /// # Capabilities
+    ///
+    /// The formats, resolutions and frame rates of the device, see Cameras::device_capabilities.
+    pub fn capabilities(&self) -> Result<Vec<MediaTypeInfo>, CaptureError> {
+        unsafe { self.cameras.device_capabilities(*self) }
+    }
+
+    /// # Supports
+    ///
+    /// If the device can deliver the output at the width and height, see Cameras::supports.
+    pub fn supports(
+        &self,
+        output: Output,
+        width: Option<u32>,
+        height: Option<u32>,
+    ) -> Result<bool, CaptureError> {
+        unsafe { self.cameras.supports(*self, output, width, height) }
+    }
+}
 
 /// # Device
 ///
@@ -19,18 +170,55 @@ use crate::devices::{Camera, camera::Output};
 /// # Examples
 ///
 /// Examples to come!
-pub struct Cameras<'a> {
-    pub devices: Vec<&'a IMFActivate>,
-    pp_devices: *mut Option<IMFActivate>,
+///
+/// The devices are released when the cameras are dropped, cameras activated from them keep their own activation.
+pub struct Cameras {
+    pub devices: Vec<IMFActivate>,
+
+    // the info of every device, in the same order
+    infos: Vec<DeviceInfo>,
+
+    // media foundation is started while the devices are enumerated and activated, declared last so it shuts down after the devices are released
+    _media_foundation: MediaFoundation,
 }
 
-impl<'a> Cameras<'a> {
+impl Cameras {
     /// # New
     ///
     /// Creates a new video devices struct.
     ///
     /// Aggregates all connected video devices on your window sytem and creates a struct containing them.
+    ///
+    /// COM and media foundation are started by the crate, a thread without COM joins the multithreaded apartment until it exits.
+    /// After init::assume_com_initialized COM must be initialized on the calling thread (see ComGuard), else CO_E_NOTINITIALIZED is returned.
     pub unsafe fn new() -> Result<Self, windows::core::Error> {
+        ensure_com("Cameras")?;
+
+        let media_foundation = MediaFoundation::start()?;
+
+        unsafe {
+            let valid_devices = Self::enumerate()?;
+
+            if valid_devices.is_empty() {
+                return Err(E_FAIL.into());
+            }
+
+            let infos = valid_devices
+                .iter()
+                .enumerate()
+                .map(|(index, device)| Self::device_info(device, index))
+                .collect();
+
+            Ok(Self {
+                devices: valid_devices,
+                infos,
+                _media_foundation: media_foundation,
+            })
+        }
+    }
+
+    // lists the video capture devices, the entries are moved out of the array before it is freed
+    unsafe fn enumerate() -> Result<Vec<IMFActivate>, windows::core::Error> {
         unsafe {
             let mut ppmfattributes: Option<IMFAttributes> = None;
 
@@ -48,20 +236,334 @@ impl<'a> Cameras<'a> {
 
             MFEnumDeviceSources(&ppmfattributes, &mut pp_devices, &mut count)?;
 
-            if count == 0 {
-                return Err(E_FAIL.into());
+            if pp_devices.is_null() {
+                return Ok(vec![]);
             }
 
-            let valid_devices_iter = std::slice::from_raw_parts(pp_devices, count as usize)
-                .iter()
-                .flatten();
+            //taking the entries leaves nothing in the array to release, so freeing it is all that is left
+            let valid_devices = std::slice::from_raw_parts_mut(pp_devices, count as usize)
+                .iter_mut()
+                .filter_map(Option::take)
+                .collect();
 
-            let valid_devices: Vec<&IMFActivate> = valid_devices_iter.collect();
+            CoTaskMemFree(Some(pp_devices as *const c_void));
 
-            Ok(Self {
-                devices: valid_devices,
-                pp_devices,
-            })
+            Ok(valid_devices)
+        }
+    }
+
+    fn device_info(device: &IMFActivate, index: usize) -> DeviceInfo {
+        DeviceInfo {
+            friendly_name: get_device_string(device, &MF_DEVSOURCE_ATTRIBUTE_FRIENDLY_NAME)
+                .unwrap_or_default(),
+            symbolic_link: get_device_string(
+                device,
+                &MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE_VIDCAP_SYMBOLIC_LINK,
+            )
+            .unwrap_or_default(),
+            index,
+        }
+    }
+
+    /// # Refresh
+    ///
+    /// Enumerates the devices again and compares them with the current list by symbolic link, returning the devices that were added and removed.
+    ///
+    /// Devices that are still connected keep their entry in devices and infos, in the same order, so their index only moves down when a device before them is removed.
+    /// New devices are appended at the end. Unlike new, a refresh that finds no device succeeds and leaves the list empty.
+    ///
+    /// Cameras keep their own activation, so a camera activated before the refresh stays valid even when its device is missing from the new list for a moment.
+    pub fn refresh(&mut self) -> Result<DeviceChanges, windows::core::Error> {
+        trace_span!(DEBUG, "refresh");
+
+        let found = unsafe { Self::enumerate()? };
+
+        let found: Vec<(DeviceInfo, IMFActivate)> = found
+            .into_iter()
+            .map(|device| (Self::device_info(&device, 0), device))
+            .collect();
+
+        let known_links: Vec<&str> = self
+            .infos
+            .iter()
+            .map(|info| info.symbolic_link.as_str())
+            .collect();
+        let found_links: Vec<&str> = found
+            .iter()
+            .map(|(info, _)| info.symbolic_link.as_str())
+            .collect();
+
+        let (removed, added) = diff_symbolic_links(&known_links, &found_links);
+
+        let mut changes = DeviceChanges::default();
+        let mut devices = Vec::with_capacity(found.len());
+        let mut infos = Vec::with_capacity(found.len());
+
+        //the kept devices are the same objects as before, only their index is renumbered
+        let known = std::mem::take(&mut self.infos)
+            .into_iter()
+            .zip(std::mem::take(&mut self.devices));
+
+        for (position, (info, device)) in known.enumerate() {
+            if removed.contains(&position) {
+                trace_event!(info, device = %info.friendly_name, "device removed");
+                changes.removed.push(info);
+            } else {
+                infos.push(DeviceInfo {
+                    index: infos.len(),
+                    ..info
+                });
+                devices.push(device);
+            }
+        }
+
+        //the activations found again for kept devices are released here
+        for (position, (info, device)) in found.into_iter().enumerate() {
+            if added.contains(&position) {
+                let info = DeviceInfo {
+                    index: infos.len(),
+                    ..info
+                };
+
+                trace_event!(info, device = %info.friendly_name, "device added");
+
+                changes.added.push(info.clone());
+                infos.push(info);
+                devices.push(device);
+            }
+        }
+
+        self.devices = devices;
+        self.infos = infos;
+
+        Ok(changes)
+    }
+
+    /// # Watch
+    ///
+    /// Reports cameras that are plugged in or unplugged as DeviceEvent::Arrived and DeviceEvent::Removed, until the watcher is dropped.
+    ///
+    /// While a watcher runs, a capturing camera whose device is removed ends with CaptureError::DeviceLost (or reconnects with auto reconnect) after the frame it is reading, whatever error media foundation reports for the device.
+    pub fn watch() -> Result<DeviceWatcher, CaptureError> {
+        DeviceWatcher::start()
+    }
+
+    /// # Infos
+    ///
+    /// The friendly name, symbolic link and index of every device, in the same order as devices.
+    pub fn infos(&self) -> &[DeviceInfo] {
+        &self.infos
+    }
+
+    /// # Len
+    ///
+    /// The number of devices, every index below it can be given to get and activate.
+    pub fn len(&self) -> usize {
+        self.devices.len()
+    }
+
+    /// # Is Empty
+    ///
+    /// If no device was found.
+    pub fn is_empty(&self) -> bool {
+        self.devices.is_empty()
+    }
+
+    /// # Get
+    ///
+    /// The device at the index, None when the index is not below len.
+    pub fn get(&self, index: usize) -> Option<DeviceHandle<'_>> {
+        (index < self.len()).then_some(DeviceHandle {
+            cameras: self,
+            index,
+        })
+    }
+
+    /// # Activate
+    ///
+    /// Activates the device at the index with the native mode closest to the config, the safe form of activate_device_with.
+    ///
+    /// Returns CaptureError::DeviceIndexOutOfRange when the index is not below len.
+    pub fn activate(
+        &self,
+        index: usize,
+        config: DeviceConfig,
+    ) -> Result<Arc<Camera>, CaptureError> {
+        self.get(index)
+            .ok_or(CaptureError::DeviceIndexOutOfRange {
+                index,
+                len: self.len(),
+            })?
+            .activate(config)
+    }
+
+    /// # Activate Device Preferred
+    ///
+    /// Same as activate_device, with the first output of the preferences that the device can deliver, for example RGB32, else NV12, else YUY2.
+    ///
+    /// An output is picked when one of the native types of the device can be delivered as it (see Output::can_convert_from), the chosen output is returned with the camera.
+    ///
+    /// Returns CaptureError::UnsupportedFormat listing the native formats when no preference can be delivered.
+    pub unsafe fn activate_device_preferred(
+        &self,
+        device: impl DeviceSelector,
+        preferences: &[Output],
+    ) -> Result<(Arc<Camera>, Output), CaptureError> {
+        trace_span!(DEBUG, "activate_device_preferred");
+
+        let device = self.select(&device)?;
+
+        unsafe {
+            let (camera, output) = Camera::from_activate_preferred(device, preferences)
+                .map_err(|e| Camera::in_use_error(device, e))
+                .inspect_err(|_e| {
+                    trace_event!(error, error = %_e, "could not activate the device");
+                })?;
+
+            trace_event!(debug, output = ?output, "device activated");
+
+            Ok((camera, output))
+        }
+    }
+
+    /// # Activate Device With
+    ///
+    /// Same as activate_device, with the native mode closest to the size and frame rate of the config (the nearest size first, then the nearest frame rate).
+    ///
+    /// With config.exact the mode must match the size and frame rate, else CaptureError::UnsupportedFormat is returned.
+    /// The negotiated mode can be read with Camera::get_dimensions and Camera::get_frame_rate, it is kept when the camera reconnects.
+    ///
+    /// A device another application holds fails with CaptureError::DeviceInUse naming the device, here or when capturing starts.
+    /// With DeviceConfig::shared the camera reads the type the device already delivers, see AccessMode::Shared.
+    pub unsafe fn activate_device_with(
+        &self,
+        device: impl DeviceSelector,
+        config: DeviceConfig,
+    ) -> Result<Arc<Camera>, CaptureError> {
+        trace_span!(DEBUG, "activate_device_with");
+
+        let device = self.select(&device)?;
+
+        unsafe {
+            let camera = Camera::from_activate_config(device, &config)
+                .map_err(|e| Camera::in_use_error(device, e))
+                .inspect_err(|_e| {
+                    trace_event!(error, error = %_e, "could not activate the device");
+                })?;
+
+            trace_event!(debug, "device activated");
+
+            Ok(camera)
+        }
+    }
+
+    /// # Preview
+    ///
+    /// Activates a cheap live thumbnail of the device for a device picker, RGB32 frames of at most max_width x max_height at up to fps frames per second (for example 160x90 at 5 fps).
+    ///
+    /// The smallest native mode is used and downscaled keeping its aspect ratio, previewing several cameras at once does not saturate the USB bandwidth.
+    /// get_dimensions reports the downscaled size.
+    ///
+    /// The preview has its own media source, so the device can still be activated with activate_device while the preview runs.
+    /// Cameras that only stream to one client at a time fail that activation until the preview is dropped, which shuts its source down.
+    pub unsafe fn preview(
+        &self,
+        device: impl DeviceSelector,
+        max_width: u32,
+        max_height: u32,
+        fps: u32,
+    ) -> Result<Arc<Camera>, CaptureError> {
+        trace_span!(DEBUG, "preview");
+
+        let device = self.select(&device)?;
+
+        unsafe {
+            let symbolic_link = get_device_string(
+                device,
+                &MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE_VIDCAP_SYMBOLIC_LINK,
+            )?;
+
+            //a new activation object of the same device, activating the enumerated one again would share its media source
+            let activate = activate_symbolic_link(&symbolic_link)?;
+
+            let camera = Camera::from_activate_preview(activate, max_width, max_height, fps)
+                .map_err(|e| Camera::in_use_error(device, e))
+                .inspect_err(|_e| {
+                    trace_event!(error, error = %_e, "could not activate the preview");
+                })?;
+
+            trace_event!(debug, "preview activated");
+
+            Ok(camera)
+        }
+    }
+
+    /// # Find By Name
+    ///
+    /// The first device whose friendly name matches, ignoring case (also outside of ASCII). Identical cameras share a friendly name, use find_by_symbolic_link to tell them apart.
+    pub fn find_by_name(&self, name: &str) -> Option<&DeviceInfo> {
+        self.infos
+            .iter()
+            .find(|info| info.friendly_name.to_lowercase() == name.to_lowercase())
+    }
+
+    /// # Find By Symbolic Link
+    ///
+    /// The device with exactly this symbolic link, which stays the same when other devices are plugged in or unplugged.
+    pub fn find_by_symbolic_link(&self, symbolic_link: &str) -> Option<&DeviceInfo> {
+        self.infos
+            .iter()
+            .find(|info| info.symbolic_link == symbolic_link)
+    }
+
+    /// # Activate By Name
+    ///
+    /// Same as activate_device, for the first device whose friendly name matches ignoring case.
+    ///
+    /// Returns CaptureError::DeviceNotFound listing the available names when no device matches.
+    pub unsafe fn activate_by_name(
+        &self,
+        name: &str,
+        output_type: Option<Output>,
+    ) -> Result<Arc<Camera>, CaptureError> {
+        let info = self
+            .find_by_name(name)
+            .ok_or_else(|| self.not_found(name))?;
+
+        let device = self.select(&info)?;
+
+        unsafe { self.activate_device(device, output_type) }
+            .map_err(|e| Camera::in_use_error(device, e.into()))
+    }
+
+    /// # Activate By Symbolic Link
+    ///
+    /// Same as activate_device, for the device with exactly this symbolic link, such as one stored in a config file.
+    ///
+    /// Returns CaptureError::DeviceNotFound listing the available names when no device matches.
+    pub unsafe fn activate_by_symbolic_link(
+        &self,
+        symbolic_link: &str,
+        output_type: Option<Output>,
+    ) -> Result<Arc<Camera>, CaptureError> {
+        let info = self
+            .find_by_symbolic_link(symbolic_link)
+            .ok_or_else(|| self.not_found(symbolic_link))?;
+
+        let device = self.select(&info)?;
+
+        unsafe { self.activate_device(device, output_type) }
+            .map_err(|e| Camera::in_use_error(device, e.into()))
+    }
+
+    fn not_found(&self, requested: &str) -> CaptureError {
+        CaptureError::DeviceNotFound {
+            requested: requested.to_string(),
+            available: self
+                .infos
+                .iter()
+                .map(|info| info.friendly_name.clone())
+                .collect(),
         }
     }
 
@@ -71,28 +573,107 @@ impl<'a> Cameras<'a> {
     ///
     /// You may choose an Output type or None (for NV12) but this will set the type of output you will receive from the receiver.
     ///
-    /// After activating any devices or after completing all operations with this struct you should call free_devices.
+    /// The largest native mode the output can be produced from is used, MJPEG and YUY2 modes are decoded by media foundation when RGB32 or NV12 is requested.
+    ///
+    /// The device can be an index, a DeviceInfo from infos or one of devices.
+    ///
+    /// Every camera has its own source reader, channel and capture loop, so several devices can capture at the same time (see examples/dual_camera).
+    /// Activating a device again while a camera of it is alive gives the new camera its own media source, which cameras that only stream to one client at a time refuse.
     pub unsafe fn activate_device(
         &self,
-        device: &IMFActivate,
+        device: impl DeviceSelector,
         output_type: Option<Output>,
     ) -> Result<Arc<Camera>, windows::core::Error> {
+        trace_span!(DEBUG, "activate_device");
+
+        let device = self.select(&device)?;
+
         unsafe {
-            let media_src = device
-                .ActivateObject::<windows::Win32::Media::MediaFoundation::IMFMediaSource>()?;
+            let camera = Camera::from_activate(device, output_type).inspect_err(|_e| {
+                trace_event!(error, hresult = %_e.code(), "could not activate the device");
+            })?;
 
-            Ok(Camera::new(media_src, output_type)?)
+            trace_event!(debug, "device activated");
+
+            Ok(camera)
         }
     }
 
-    /// # Free Devices
+    /// # Device Capabilities
+    ///
+    /// Lists the formats, resolutions and frame rates a device supports without activating a Camera.
     ///
-    /// Uses CoTaskMemFree to free all devices that have been collected, this is essential for memory.
-    pub unsafe fn free_devices(&self) {
+    /// Internally a temporary source reader is created to walk all of the native media types of the first video stream,
+    /// its media source is shut down again before this returns, also on an error.
+    ///
+    /// Native types with a format that has no matching Output are skipped, the list is ordered like Camera::supported_formats.
+    pub unsafe fn device_capabilities(
+        &self,
+        device: impl DeviceSelector,
+    ) -> Result<Vec<MediaTypeInfo>, CaptureError> {
+        let device = self.select(&device)?;
+
         unsafe {
-            if !self.pp_devices.is_null() {
-                CoTaskMemFree(Some(self.pp_devices as *const c_void));
-            }
+            let symbolic_link = get_device_string(
+                device,
+                &MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE_VIDCAP_SYMBOLIC_LINK,
+            )?;
+
+            //a new activation object, shutting down the source of the enumerated one would stop the cameras activated from it
+            let activate = activate_symbolic_link(&symbolic_link)?;
+            let source = activate.ActivateObject::<IMFMediaSource>()?;
+            let _shutdown = SourceShutdown::new(&activate);
+
+            let reader = Camera::create_reader(&source)?;
+
+            Ok(Camera::native_media_types(&reader)?)
         }
     }
+
+    /// # Supported Outputs
+    ///
+    /// The outputs the device can be activated with, without activating a Camera, for offering only valid choices.
+    ///
+    /// Derived from the native types (see device_capabilities), RGB32 and NV12 are listed when a native type can be converted to them.
+    /// The temporary media source is shut down before this returns, so the device can be activated right after.
+    pub unsafe fn supported_outputs(
+        &self,
+        device: impl DeviceSelector,
+    ) -> Result<Vec<Output>, CaptureError> {
+        let native_types = unsafe { self.device_capabilities(device)? };
+
+        Ok(Output::deliverable_from(&native_types))
+    }
+
+    /// # Supports
+    ///
+    /// If the device can deliver the output at the width and height without activating a Camera, for greying out the options a device can not do.
+    ///
+    /// A native type must have the size (None matches any width or height) and a format the output is delivered from, natively or converted like supported_outputs.
+    /// Checked on the native types (see device_capabilities), the device is opened with a temporary source reader for them and shut down again before this returns.
+    /// That still opens the device, check the options of a device once with device_capabilities instead of calling this for every option.
+    pub unsafe fn supports(
+        &self,
+        device: impl DeviceSelector,
+        output: Output,
+        width: Option<u32>,
+        height: Option<u32>,
+    ) -> Result<bool, CaptureError> {
+        let native_types = unsafe { self.device_capabilities(device)? };
+
+        Ok(native_types.iter().any(|native| {
+            output.can_convert_from(native.format)
+                && width.is_none_or(|width| native.width == width)
+                && height.is_none_or(|height| native.height == height)
+        }))
+    }
+
+    fn select<'s>(
+        &'s self,
+        device: &'s impl DeviceSelector,
+    ) -> Result<&'s IMFActivate, windows::core::Error> {
+        device.select(self).ok_or_else(|| {
+            windows::core::Error::new(E_INVALIDARG, "the cameras have no such device")
+        })
+    }
 }
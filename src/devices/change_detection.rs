@@ -0,0 +1,84 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// # Change Detection
+///
+/// Compares every frame of a capture with the previous frame before it is delivered, flagging frames that did not change
+/// with Frame::is_duplicate or not delivering them at all.
+///
+/// Only a sample of the bytes is compared (every sample_step-th byte), so this is cheap but can miss a change smaller than the step,
+/// such as a blinking text cursor. It still costs CPU on every frame and is off unless set on the capture.
+pub struct ChangeDetection {
+    /// The distance in bytes between two sampled bytes, 1 compares every byte.
+    ///
+    /// A step that is not a multiple of the pixel size samples every channel.
+    pub sample_step: usize,
+
+    /// The mean difference of the sampled bytes up to which a frame counts as unchanged, 0 requires the samples to be identical.
+    ///
+    /// Cameras never deliver identical frames because of sensor noise, a tolerance of a few levels lets a motion only recorder skip a still scene.
+    pub tolerance: u8,
+
+    /// If frames that did not change are not delivered at all instead of being flagged.
+    pub skip_duplicates: bool,
+}
+
+impl ChangeDetection {
+    pub fn new(sample_step: usize, tolerance: u8) -> Self {
+        ChangeDetection {
+            sample_step: sample_step.max(1),
+            tolerance,
+            skip_duplicates: false,
+        }
+    }
+
+    /// # Skipping Duplicates
+    ///
+    /// Frames that did not change are not delivered, see skip_duplicates.
+    pub fn skipping_duplicates(mut self) -> Self {
+        self.skip_duplicates = true;
+        self
+    }
+}
+
+impl Default for ChangeDetection {
+    /// Every 67th byte is sampled and has to be identical, duplicates are flagged.
+    fn default() -> Self {
+        Self::new(67, 0)
+    }
+}
+
+// the samples of the previous frame, compared with the samples of the next one
+#[derive(Default)]
+pub(crate) struct ChangeDetector {
+    samples: Vec<u8>,
+}
+
+impl ChangeDetector {
+    // samples the data and compares it with the previous data, frames of another size always changed
+    pub(crate) fn is_duplicate(&mut self, detection: &ChangeDetection, data: &[u8]) -> bool {
+        let samples: Vec<u8> = data
+            .iter()
+            .step_by(detection.sample_step.max(1))
+            .copied()
+            .collect();
+
+        let duplicate = !samples.is_empty()
+            && samples.len() == self.samples.len()
+            && mean_difference(&samples, &self.samples) <= detection.tolerance as u64;
+
+        self.samples = samples;
+
+        duplicate
+    }
+
+    // forgets the previous frame, the next frame is never a duplicate
+    pub(crate) fn reset(&mut self) {
+        self.samples.clear();
+    }
+}
+
+// the mean absolute difference of two sample lists of the same length, rounded down
+fn mean_difference(a: &[u8], b: &[u8]) -> u64 {
+    let total: u64 = a.iter().zip(b).map(|(a, b)| a.abs_diff(*b) as u64).sum();
+
+    total / a.len().max(1) as u64
+}
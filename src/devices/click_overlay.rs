@@ -0,0 +1,212 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use windows::Win32::{
+    Foundation::{LPARAM, LRESULT, POINT, WPARAM},
+    System::Threading::GetCurrentThreadId,
+    UI::WindowsAndMessaging::{
+        CallNextHookEx, GetMessageW, MSG, MSLLHOOKSTRUCT, PM_NOREMOVE, PeekMessageW,
+        PostThreadMessageW, SetWindowsHookExW, UnhookWindowsHookEx, WH_MOUSE_LL, WM_LBUTTONDOWN,
+        WM_MBUTTONDOWN, WM_QUIT, WM_RBUTTONDOWN,
+    },
+};
+
+use crate::capture_error::CaptureError;
+
+// clicks older than the longest overlay are not needed, but only this many are kept at once
+const MAX_CLICKS: usize = 64;
+
+/// # Click Overlay
+///
+/// A ring drawn around the cursor in monitor frames every time a mouse button is pressed, fading out over the duration.
+///
+/// Used with Monitor::set_click_overlay, the ring is baked into the frames so every consumer sees it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClickOverlay {
+    /// The BGRA color of the ring, the alpha is the opacity of a new ring.
+    pub color: [u8; 4],
+
+    /// The outer radius of the ring in frame pixels.
+    pub radius: u32,
+
+    /// The width of the ring in frame pixels.
+    pub thickness: u32,
+
+    /// How long the ring stays visible after the click.
+    pub duration: Duration,
+}
+
+impl Default for ClickOverlay {
+    /// An opaque yellow ring of 24 pixels that fades out over half a second.
+    fn default() -> Self {
+        Self {
+            color: [0, 215, 255, 255],
+            radius: 24,
+            thickness: 4,
+            duration: Duration::from_millis(500),
+        }
+    }
+}
+
+impl ClickOverlay {
+    // draws a ring centered on (x, y) into a (possibly padded) BGRA frame, stronger rings are more opaque.
+    pub(crate) fn draw_ring(
+        &self,
+        data: &mut [u8],
+        width: usize,
+        height: usize,
+        (x, y): (f32, f32),
+        strength: f32,
+    ) {
+        if height == 0 || self.radius == 0 {
+            return;
+        }
+
+        let stride = data.len() / height;
+        let outer = self.radius as f32;
+        let inner = (outer - self.thickness.max(1) as f32).max(0.0);
+        let opacity = (self.color[3] as f32 / 255.0) * strength.clamp(0.0, 1.0);
+
+        let left = (x - outer).floor().max(0.0) as usize;
+        let top = (y - outer).floor().max(0.0) as usize;
+        let right = ((x + outer).ceil().max(0.0) as usize).min(width);
+        let bottom = ((y + outer).ceil().max(0.0) as usize).min(height);
+
+        for py in top..bottom {
+            for px in left..right {
+                let distance =
+                    ((px as f32 + 0.5 - x).powi(2) + (py as f32 + 0.5 - y).powi(2)).sqrt();
+
+                if distance < inner || distance > outer {
+                    continue;
+                }
+
+                let offset = py * stride + px * 4;
+                let pixel = &mut data[offset..offset + 3];
+
+                for (channel, color) in pixel.iter_mut().zip(self.color) {
+                    *channel = (*channel as f32 * (1.0 - opacity) + color as f32 * opacity) as u8;
+                }
+            }
+        }
+    }
+}
+
+// a mouse button press, in desktop coordinates
+#[derive(Clone, Copy)]
+pub(crate) struct Click {
+    pub(crate) position: POINT,
+    pub(crate) at: Instant,
+}
+
+// clicks seen by the low level mouse hook, shared by every monitor
+static CLICKS: Mutex<Vec<Click>> = Mutex::new(Vec::new());
+
+// the thread running the mouse hook and how many overlays use it
+static HOOK_THREAD: Mutex<Option<(u32, usize)>> = Mutex::new(None);
+
+// the clicks that happened within the last duration
+pub(crate) fn recent_clicks(duration: Duration) -> Vec<Click> {
+    CLICKS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|click| click.at.elapsed() <= duration)
+        .copied()
+        .collect()
+}
+
+unsafe extern "system" fn mouse_proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code >= 0
+        && matches!(
+            wparam.0 as u32,
+            WM_LBUTTONDOWN | WM_RBUTTONDOWN | WM_MBUTTONDOWN
+        )
+    {
+        let info = unsafe { &*(lparam.0 as *const MSLLHOOKSTRUCT) };
+        let mut clicks = CLICKS.lock().unwrap();
+
+        if clicks.len() >= MAX_CLICKS {
+            clicks.remove(0);
+        }
+
+        clicks.push(Click {
+            position: info.pt,
+            at: Instant::now(),
+        });
+    }
+
+    unsafe { CallNextHookEx(None, code, wparam, lparam) }
+}
+
+// keeps the low level mouse hook installed while it is alive.
+//
+// the hook runs on its own thread with a message loop, the thread is shared between every overlay
+// and stops once the last one is dropped.
+pub(crate) struct ClickHook;
+
+impl ClickHook {
+    pub(crate) fn install() -> Result<Self, CaptureError> {
+        let mut hook_thread = HOOK_THREAD.lock().unwrap();
+
+        match hook_thread.as_mut() {
+            Some((_, users)) => *users += 1,
+            None => *hook_thread = Some((Self::spawn_thread()?, 1)),
+        }
+
+        Ok(ClickHook)
+    }
+
+    // starts the hook thread and returns its id once the hook is installed
+    fn spawn_thread() -> Result<u32, CaptureError> {
+        let (tx, rx) = std::sync::mpsc::channel();
+
+        std::thread::spawn(move || unsafe {
+            let hook = match SetWindowsHookExW(WH_MOUSE_LL, Some(mouse_proc), None, 0) {
+                Ok(hook) => hook,
+                Err(e) => {
+                    let _ = tx.send(Err(e));
+                    return;
+                }
+            };
+
+            //make the message queue so WM_QUIT can be posted to the thread
+            let mut msg = MSG::default();
+            let _ = PeekMessageW(&mut msg, None, 0, 0, PM_NOREMOVE);
+
+            let _ = tx.send(Ok(GetCurrentThreadId()));
+
+            //the hook is called from this loop, it ends with WM_QUIT (0) or an error (-1)
+            while GetMessageW(&mut msg, None, 0, 0).0 > 0 {}
+
+            let _ = UnhookWindowsHookEx(hook);
+        });
+
+        rx.recv()
+            .map_err(|_| CaptureError::Other("the mouse hook thread stopped".into()))?
+            .map_err(CaptureError::from)
+    }
+}
+
+impl Drop for ClickHook {
+    fn drop(&mut self) {
+        let mut hook_thread = HOOK_THREAD.lock().unwrap();
+
+        let Some((thread_id, users)) = hook_thread.as_mut() else {
+            return;
+        };
+
+        *users -= 1;
+
+        if *users == 0 {
+            unsafe {
+                let _ = PostThreadMessageW(*thread_id, WM_QUIT, WPARAM(0), LPARAM(0));
+            }
+
+            *hook_thread = None;
+            CLICKS.lock().unwrap().clear();
+        }
+    }
+}
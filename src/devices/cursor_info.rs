@@ -0,0 +1,78 @@
+use windows::Win32::Graphics::Dxgi::{
+    DXGI_OUTDUPL_POINTER_SHAPE_INFO, DXGI_OUTDUPL_POINTER_SHAPE_TYPE_MASKED_COLOR,
+    DXGI_OUTDUPL_POINTER_SHAPE_TYPE_MONOCHROME,
+};
+
+/// # Cursor Shape Kind
+///
+/// How the shape of a CursorInfo is laid out, as reported by Desktop Duplication.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum CursorShapeKind {
+    /// 1 bit per pixel, an AND mask followed by an XOR mask of the same size, so the shape has twice the height of the cursor.
+    Monochrome,
+
+    /// 32 bit BGRA with straight alpha, drawn by alpha blending.
+    #[default]
+    Color,
+
+    /// 32 bit BGRX where the fourth byte is a mask, 0x00 replaces the desktop pixel with the color and 0xFF XORs it with the color.
+    MaskedColor,
+}
+
+impl CursorShapeKind {
+    pub(crate) fn from_dxgi(shape_type: u32) -> Self {
+        match shape_type as i32 {
+            t if t == DXGI_OUTDUPL_POINTER_SHAPE_TYPE_MONOCHROME.0 => CursorShapeKind::Monochrome,
+            t if t == DXGI_OUTDUPL_POINTER_SHAPE_TYPE_MASKED_COLOR.0 => {
+                CursorShapeKind::MaskedColor
+            }
+            _ => CursorShapeKind::Color,
+        }
+    }
+}
+
+/// # Cursor Info
+///
+/// The mouse cursor of a monitor, delivered separately from the frames (which never contain the cursor) so it can be drawn as an overlay.
+///
+/// Read with Monitor::cursor_info or received as CaptureEvent::CursorChanged, it is updated whenever the duplication reports a mouse update, even when the desktop image did not change.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CursorInfo {
+    /// The top left of the cursor shape relative to the top left of the monitor, in desktop coordinates.
+    ///
+    /// Not meaningful while the cursor is not visible.
+    pub position: (i32, i32),
+
+    /// If the cursor is shown on this monitor.
+    pub visible: bool,
+
+    /// The point of the shape that is at the mouse position, relative to the top left of the shape.
+    pub hotspot: (i32, i32),
+
+    /// The pixels of the cursor, laid out as described by shape_kind, None until the duplication reported the first shape.
+    pub shape: Option<Vec<u8>>,
+
+    /// How the pixels of the shape are laid out.
+    pub shape_kind: CursorShapeKind,
+
+    /// The width of the shape in pixels.
+    pub shape_width: u32,
+
+    /// The height of the shape in pixels, twice the height of the cursor for monochrome shapes.
+    pub shape_height: u32,
+
+    /// The number of bytes between the start of two rows of the shape.
+    pub shape_pitch: u32,
+}
+
+impl CursorInfo {
+    // replaces the shape with the one read from the duplication
+    pub(crate) fn set_shape(&mut self, shape: Vec<u8>, info: &DXGI_OUTDUPL_POINTER_SHAPE_INFO) {
+        self.shape = Some(shape);
+        self.shape_kind = CursorShapeKind::from_dxgi(info.Type);
+        self.shape_width = info.Width;
+        self.shape_height = info.Height;
+        self.shape_pitch = info.Pitch;
+        self.hotspot = (info.HotSpot.x, info.HotSpot.y);
+    }
+}
@@ -0,0 +1,116 @@
+use crate::devices::{MediaTypeInfo, camera::Output};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// # Device Config
+///
+/// The output, resolution and frame rate a camera is activated with through Cameras::activate_device_with.
+///
+/// The closest native mode of the camera is used unless exact is set, Camera::get_dimensions and Camera::get_frame_rate report the mode that was negotiated.
+pub struct DeviceConfig {
+    /// The format of the frames sent to the receiver.
+    pub format: Output,
+
+    /// The requested width of the frames in pixels.
+    pub width: u32,
+
+    /// The requested height of the frames in pixels.
+    pub height: u32,
+
+    /// The requested frames per second, a native mode of 29.97 fps counts as 30.
+    pub fps: u32,
+
+    /// Fail the activation with CaptureError::UnsupportedFormat when the camera has no mode of exactly this size and frame rate.
+    pub exact: bool,
+
+    /// If the camera is taken over or shared with the applications already streaming it, AccessMode::Exclusive by default.
+    pub access: AccessMode,
+
+    /// The frames read and discarded after capturing starts, while auto exposure settles, 0 by default. See Camera::set_warmup_frames.
+    pub warmup_frames: u32,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+/// # Access Mode
+///
+/// How a camera is activated through Cameras::activate_device_with, see DeviceConfig::shared.
+pub enum AccessMode {
+    /// The native mode closest to the config is set on the device, which fails with CaptureError::DeviceInUse while another application streams it.
+    #[default]
+    Exclusive,
+
+    /// The device keeps the native type it delivers and the frames are only converted to the output, the size and frame rate of the config are ignored
+    /// (with exact the type must match them).
+    ///
+    /// Leaving the type alone lets a preview read a camera another application streams, where the Windows camera frame server shares the device
+    /// (Windows 10 and later, for cameras and applications it supports). Elsewhere the activation still fails with CaptureError::DeviceInUse.
+    Shared,
+}
+
+impl DeviceConfig {
+    pub fn new(format: Output, width: u32, height: u32, fps: u32) -> Self {
+        DeviceConfig {
+            format,
+            width,
+            height,
+            fps,
+            exact: false,
+            access: AccessMode::Exclusive,
+            warmup_frames: 0,
+        }
+    }
+
+    /// # Exact
+    ///
+    /// Requires a native mode of exactly this size and frame rate, see exact.
+    pub fn exact(mut self) -> Self {
+        self.exact = true;
+        self
+    }
+
+    /// # Shared
+    ///
+    /// Reads the camera in the type it already delivers instead of setting a mode, see AccessMode::Shared.
+    pub fn shared(mut self) -> Self {
+        self.access = AccessMode::Shared;
+        self
+    }
+
+    /// # Warmup Frames
+    ///
+    /// Discards the first frames of the camera after capturing starts, see warmup_frames.
+    pub fn warmup_frames(mut self, frames: u32) -> Self {
+        self.warmup_frames = frames;
+        self
+    }
+
+    // the native mode that produces the config, the nearest size then the nearest frame rate,
+    // modes that deliver the format without converting win ties.
+    pub(crate) fn select_mode(&self, natives: &[MediaTypeInfo]) -> Option<MediaTypeInfo> {
+        natives
+            .iter()
+            .filter(|native| self.format.can_convert_from(native.format))
+            .filter(|native| !self.exact || self.matches(native))
+            .min_by_key(|native| {
+                (
+                    native.width.abs_diff(self.width) as u64
+                        + native.height.abs_diff(self.height) as u64,
+                    (native.fps().round() as u32).abs_diff(self.fps),
+                    native.format != self.format,
+                )
+            })
+            .copied()
+    }
+
+    fn matches(&self, native: &MediaTypeInfo) -> bool {
+        native.width == self.width
+            && native.height == self.height
+            && native.fps().round() as u32 == self.fps
+    }
+}
+
+impl Default for DeviceConfig {
+    /// NV12 at 1280x720 and 30 fps, the closest mode is used.
+    fn default() -> Self {
+        DeviceConfig::new(Output::NV12, 1280, 720, 30)
+    }
+}
@@ -0,0 +1,37 @@
+/// # Device Info
+///
+/// Describes a camera found by Cameras, for showing it to a user and finding it again later.
+///
+/// A DeviceInfo can be given to Cameras::activate_device in place of the device.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DeviceInfo {
+    /// The readable name of the device, for example "Logitech C920".
+    pub friendly_name: String,
+
+    /// The symbolic link of the device, this stays the same across reboots and reconnects, unlike the index.
+    pub symbolic_link: String,
+
+    /// The index of the device in Cameras::devices.
+    pub index: usize,
+}
+
+/// # Device Changes
+///
+/// The devices that were plugged in and unplugged since the cameras were enumerated, returned by Cameras::refresh.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DeviceChanges {
+    /// The devices that are new, with their index after the refresh.
+    pub added: Vec<DeviceInfo>,
+
+    /// The devices that are gone, with the index they had before the refresh.
+    pub removed: Vec<DeviceInfo>,
+}
+
+impl DeviceChanges {
+    /// # Is Empty
+    ///
+    /// If no device was added or removed.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
@@ -0,0 +1,304 @@
+use std::sync::{
+    Arc, Weak,
+    atomic::{AtomicBool, Ordering},
+};
+
+use tokio::sync::{
+    Mutex,
+    mpsc::{self, Receiver, Sender},
+};
+use windows::{
+    Win32::{
+        Foundation::{HANDLE, HWND, LPARAM, LRESULT, WPARAM},
+        Media::KernelStreaming::KSCATEGORY_CAPTURE,
+        System::{LibraryLoader::GetModuleHandleW, Threading::GetCurrentThreadId},
+        UI::WindowsAndMessaging::{
+            CreateWindowExW, DBT_DEVICEARRIVAL, DBT_DEVICEREMOVECOMPLETE,
+            DBT_DEVTYP_DEVICEINTERFACE, DEV_BROADCAST_DEVICEINTERFACE_W,
+            DEVICE_NOTIFY_WINDOW_HANDLE, DefWindowProcW, DestroyWindow, DispatchMessageW,
+            GetMessageW, HDEVNOTIFY, HWND_MESSAGE, MSG, PostMessageW, PostThreadMessageW,
+            RegisterClassW, RegisterDeviceNotificationW, UnregisterDeviceNotification,
+            WINDOW_EX_STYLE, WINDOW_STYLE, WM_APP, WM_DEVICECHANGE, WM_QUIT, WNDCLASSW,
+        },
+    },
+    core::w,
+};
+
+use crate::{
+    capture_error::CaptureError,
+    com::ComGuard,
+    devices::{Cameras, DeviceInfo},
+};
+
+// events buffered for the receiver, the watcher thread waits once they are not read
+const EVENT_CHANNEL_CAPACITY: usize = 16;
+
+// posted to the watcher thread when a capture device interface arrived or was removed
+const WM_DEVICES_CHANGED: u32 = WM_APP + 1;
+
+// the cameras that are activated, flagged when a watcher sees their device removed
+static ACTIVE_CAMERAS: std::sync::Mutex<Vec<(String, Weak<AtomicBool>)>> =
+    std::sync::Mutex::new(Vec::new());
+
+// the flag a camera of the device checks in its capture loop, set once a watcher sees the device removed
+pub(crate) fn track_removal(symbolic_link: String) -> Arc<AtomicBool> {
+    let removed = Arc::new(AtomicBool::new(false));
+
+    let mut cameras = ACTIVE_CAMERAS.lock().unwrap();
+    cameras.retain(|(_, camera)| camera.strong_count() > 0);
+    cameras.push((symbolic_link, Arc::downgrade(&removed)));
+
+    removed
+}
+
+// if a camera of the device is alive, symbolic links are compared ignoring case like windows does
+pub(crate) fn is_tracked(symbolic_link: &str) -> bool {
+    ACTIVE_CAMERAS
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|(link, camera)| link.eq_ignore_ascii_case(symbolic_link) && camera.strong_count() > 0)
+}
+
+// flags every activated camera of the device, symbolic links are compared ignoring case like windows does
+fn mark_removed(symbolic_link: &str) {
+    let mut cameras = ACTIVE_CAMERAS.lock().unwrap();
+    cameras.retain(|(_, camera)| camera.strong_count() > 0);
+
+    for (link, camera) in cameras.iter() {
+        if link.eq_ignore_ascii_case(symbolic_link)
+            && let Some(removed) = camera.upgrade()
+        {
+            removed.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+/// # Device Event
+///
+/// A camera that was plugged in or unplugged while a DeviceWatcher runs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DeviceEvent {
+    /// The device was plugged in, its index is the one in a Cameras created now.
+    Arrived(DeviceInfo),
+
+    /// The device was unplugged, its index is the one it had before.
+    Removed(DeviceInfo),
+}
+
+/// # Device Watcher
+///
+/// Reports cameras that are plugged in or unplugged, created with Cameras::watch.
+///
+/// The devices are watched on a background thread until the watcher is dropped.
+pub struct DeviceWatcher {
+    /// The receiver of the events, can be used to wait for events directly.
+    pub receiver: Arc<Mutex<Receiver<DeviceEvent>>>,
+
+    // the devices that are currently connected, updated before the events are sent
+    devices: Arc<std::sync::Mutex<Vec<DeviceInfo>>>,
+
+    // the watcher thread, quit when the watcher is dropped
+    thread_id: u32,
+}
+
+impl DeviceWatcher {
+    // starts the watcher thread and returns once the notifications are registered
+    pub(crate) fn start() -> Result<Self, CaptureError> {
+        let (tx, rx) = mpsc::channel(EVENT_CHANNEL_CAPACITY);
+        let (started_tx, started_rx) = std::sync::mpsc::channel();
+
+        let devices = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let watched = devices.clone();
+
+        std::thread::spawn(move || unsafe {
+            //media foundation enumerates the devices through COM
+            let _com = ComGuard::new();
+
+            *watched.lock().unwrap() = connected_devices();
+
+            match register_window() {
+                Ok((window, notification)) => {
+                    let _ = started_tx.send(Ok(GetCurrentThreadId()));
+
+                    run(tx, &watched);
+
+                    let _ = UnregisterDeviceNotification(notification);
+                    let _ = DestroyWindow(window);
+                }
+                Err(e) => {
+                    let _ = started_tx.send(Err(e));
+                }
+            }
+        });
+
+        let thread_id = started_rx
+            .recv()
+            .map_err(|_| CaptureError::Other("the device watcher thread stopped".into()))?
+            .map_err(CaptureError::from)?;
+
+        trace_event!(debug, "device watcher started");
+
+        Ok(Self {
+            receiver: Arc::new(Mutex::new(rx)),
+            devices,
+            thread_id,
+        })
+    }
+
+    /// # Recv
+    ///
+    /// Waits for the next device that is plugged in or unplugged.
+    pub async fn recv(&self) -> Option<DeviceEvent> {
+        self.receiver.lock().await.recv().await
+    }
+
+    /// # Clone Receiver
+    ///
+    /// Clones the receiver the events are sent to.
+    pub fn clone_receiver(&self) -> Arc<Mutex<Receiver<DeviceEvent>>> {
+        self.receiver.clone()
+    }
+
+    /// # Devices
+    ///
+    /// The cameras that are connected now, up to date with the events that were sent.
+    pub fn devices(&self) -> Vec<DeviceInfo> {
+        self.devices.lock().unwrap().clone()
+    }
+}
+
+impl Drop for DeviceWatcher {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = PostThreadMessageW(self.thread_id, WM_QUIT, WPARAM(0), LPARAM(0));
+        }
+    }
+}
+
+// the cameras that are connected, none when they cannot be enumerated
+unsafe fn connected_devices() -> Vec<DeviceInfo> {
+    unsafe {
+        match Cameras::new() {
+            Ok(cameras) => cameras.infos().to_vec(),
+            Err(_) => vec![],
+        }
+    }
+}
+
+// window messages are sent to the window procedure, it posts a message the loop of the thread handles
+unsafe extern "system" fn window_proc(
+    window: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    if message == WM_DEVICECHANGE
+        && matches!(
+            wparam.0 as u32,
+            DBT_DEVICEARRIVAL | DBT_DEVICEREMOVECOMPLETE
+        )
+    {
+        unsafe {
+            let _ = PostMessageW(Some(window), WM_DEVICES_CHANGED, WPARAM(0), LPARAM(0));
+        }
+
+        return LRESULT(1);
+    }
+
+    unsafe { DefWindowProcW(window, message, wparam, lparam) }
+}
+
+// creates a message only window that receives the arrival and removal of capture devices
+unsafe fn register_window() -> Result<(HWND, HDEVNOTIFY), windows::core::Error> {
+    unsafe {
+        let instance = GetModuleHandleW(None)?.into();
+        let class_name = w!("win-video-device-watcher");
+
+        //fails once the class is registered, which every later watcher can use
+        let _ = RegisterClassW(&WNDCLASSW {
+            lpfnWndProc: Some(window_proc),
+            hInstance: instance,
+            lpszClassName: class_name,
+            ..Default::default()
+        });
+
+        let window = CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            class_name,
+            None,
+            WINDOW_STYLE::default(),
+            0,
+            0,
+            0,
+            0,
+            Some(HWND_MESSAGE),
+            None,
+            Some(instance),
+            None,
+        )?;
+
+        let filter = DEV_BROADCAST_DEVICEINTERFACE_W {
+            dbcc_size: size_of::<DEV_BROADCAST_DEVICEINTERFACE_W>() as u32,
+            dbcc_devicetype: DBT_DEVTYP_DEVICEINTERFACE.0,
+            dbcc_classguid: KSCATEGORY_CAPTURE,
+            ..Default::default()
+        };
+
+        let notification = RegisterDeviceNotificationW(
+            HANDLE(window.0),
+            &filter as *const _ as *const std::ffi::c_void,
+            DEVICE_NOTIFY_WINDOW_HANDLE,
+        )
+        .inspect_err(|_| {
+            let _ = DestroyWindow(window);
+        })?;
+
+        Ok((window, notification))
+    }
+}
+
+// dispatches messages until WM_QUIT, comparing the connected devices whenever a device interface changed
+unsafe fn run(events: Sender<DeviceEvent>, devices: &std::sync::Mutex<Vec<DeviceInfo>>) {
+    let mut msg = MSG::default();
+
+    unsafe {
+        //ends with WM_QUIT (0) or an error (-1)
+        while GetMessageW(&mut msg, None, 0, 0).0 > 0 {
+            if msg.message != WM_DEVICES_CHANGED {
+                DispatchMessageW(&msg);
+                continue;
+            }
+
+            //one device registers several interfaces, comparing the lists sends a single event for it
+            let connected = connected_devices();
+            let previous = std::mem::replace(&mut *devices.lock().unwrap(), connected.clone());
+
+            let removed = previous.iter().filter(|info| {
+                !connected
+                    .iter()
+                    .any(|device| device.symbolic_link == info.symbolic_link)
+            });
+
+            for info in removed {
+                trace_event!(info, device = %info.friendly_name, "device removed");
+
+                mark_removed(&info.symbolic_link);
+
+                let _ = events.blocking_send(DeviceEvent::Removed(info.clone()));
+            }
+
+            let arrived = connected.iter().filter(|info| {
+                !previous
+                    .iter()
+                    .any(|device| device.symbolic_link == info.symbolic_link)
+            });
+
+            for info in arrived {
+                trace_event!(info, device = %info.friendly_name, "device arrived");
+
+                let _ = events.blocking_send(DeviceEvent::Arrived(info.clone()));
+            }
+        }
+    }
+}
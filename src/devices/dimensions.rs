@@ -1,5 +1,6 @@
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// # Dimensions
 /// 
 /// Simply a container that has a width and height
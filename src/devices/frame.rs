@@ -0,0 +1,144 @@
+use std::time::Duration;
+
+use crate::{
+    clock::clock,
+    devices::{MoveRect, Rect, SharedFrame},
+};
+
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// # Frame
+///
+/// A single frame sent to the receiver of a capture, the data and information about how the frame was captured.
+pub struct Frame {
+    /// The frame data, laid out as described by get_output_format of the capture.
+    ///
+    /// Uncompressed frames are tightly packed unless the capture was asked for padded frames, see stride.
+    pub data: Vec<u8>,
+
+    /// The number of bytes between the start of two rows of data (of the luma plane for NV12).
+    ///
+    /// This is width * bytes per pixel for tightly packed frames and can be larger for padded frames, 0 for compressed frames such as MJPEG.
+    pub stride: usize,
+
+    /// How many times the source updated since the previous delivered frame without those updates being delivered.
+    ///
+    /// For monitors this is derived from the accumulated frames of Desktop Duplication, a recorder with a fixed frame rate can repeat the previous frame this many times to keep its timeline in sync.
+    ///
+    /// Frames a full receiver dropped (see Backpressure) are counted too, for cameras this is all it counts.
+    pub skipped_since_last: u64,
+
+    /// When the frame was captured, relative to when capturing started.
+    ///
+    /// In constant frame rate mode this is exactly the frame index divided by the frame rate.
+    pub timestamp: Duration,
+
+    /// When the frame was captured on the shared clock, in 100 ns units of the performance counter (see crate::clock).
+    ///
+    /// Unlike timestamp this is comparable between captures, such as a monitor, a camera and audio captured at the same time.
+    /// Monitors use the present time of Desktop Duplication, cameras the time of their samples.
+    pub presentation_time: i64,
+
+    /// The regions of the frame that changed since the previous frame, for sending only what changed (such as remote desktop streaming).
+    ///
+    /// Empty when the capture has no change information, the whole frame should then be treated as changed.
+    /// Cameras always deliver an empty list, monitors report the dirty rects of Desktop Duplication mapped onto the delivered frame.
+    ///
+    /// The destination of every move in move_rects is also listed, so the moves can be ignored.
+    pub dirty_rects: Vec<Rect>,
+
+    /// The regions that were moved from elsewhere in the previous frame, apply them before the dirty rects are copied.
+    ///
+    /// Only monitors that are not scaled with an output size report moves, scaled monitors report the moved regions as dirty rects.
+    pub move_rects: Vec<MoveRect>,
+
+    /// If the frame shows the same image as the previous frame.
+    ///
+    /// Monitors flag frames Desktop Duplication delivered for a cursor change only and the frames repeated in constant frame rate mode,
+    /// captures with a ChangeDetection set also flag frames whose samples did not change. Always false otherwise.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub is_duplicate: bool,
+}
+
+impl Frame {
+    /// # New
+    ///
+    /// Creates a frame from data with no skipped updates or change information (not a duplicate), captured at the given time.
+    ///
+    /// The presentation time is the current time of the clock.
+    ///
+    /// The stride is 0, set it for uncompressed data.
+    pub fn new(data: Vec<u8>, timestamp: Duration) -> Self {
+        Self {
+            data,
+            stride: 0,
+            skipped_since_last: 0,
+            timestamp,
+            presentation_time: clock(),
+            dirty_rects: Vec::new(),
+            move_rects: Vec::new(),
+            is_duplicate: false,
+        }
+    }
+
+    /// # Len
+    ///
+    /// The number of bytes of data in the frame, for compressed frames such as MJPEG this is the compressed size.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// # Is Empty
+    ///
+    /// If the frame holds no data.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// # Into Shared
+    ///
+    /// Moves the frame into a SharedFrame, whose clones share the data instead of copying it.
+    pub fn into_shared(self) -> SharedFrame {
+        self.into()
+    }
+
+    /// # Dirty Region
+    ///
+    /// The bounding box of the dirty rects, the same as FrameDelta::dirty_region of crate::analysis without comparing any pixels.
+    ///
+    /// None when the capture has no change information (the whole frame should then be treated as changed).
+    pub fn dirty_region(&self) -> Option<Rect> {
+        self.dirty_rects.iter().copied().reduce(|a, b| Rect {
+            left: a.left.min(b.left),
+            top: a.top.min(b.top),
+            right: a.right.max(b.right),
+            bottom: a.bottom.max(b.bottom),
+        })
+    }
+
+    // takes over the changes of an older frame that was dropped before the consumer saw it, so this frame lists everything that changed
+    // since the frame delivered before. the moves of either frame do not apply to the delivered frame, their destinations are dirty rects already.
+    pub(crate) fn merge_dropped(&mut self, dropped: &Frame) {
+        self.skipped_since_last += dropped.skipped_since_last + 1;
+
+        if dropped.is_duplicate {
+            return;
+        }
+
+        //a frame without change information changed as a whole
+        let whole = |frame: &Frame| !frame.is_duplicate && frame.dirty_rects.is_empty() && frame.move_rects.is_empty();
+
+        if whole(self) || whole(dropped) {
+            self.dirty_rects.clear();
+        } else {
+            for rect in &dropped.dirty_rects {
+                if !self.dirty_rects.contains(rect) {
+                    self.dirty_rects.push(*rect);
+                }
+            }
+        }
+
+        self.move_rects.clear();
+        self.is_duplicate = false;
+    }
+}
@@ -0,0 +1,142 @@
+use std::{
+    collections::VecDeque,
+    sync::{Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+// the number of frames the rate is measured over by default, two seconds of 30 fps video
+pub(crate) const DEFAULT_FPS_WINDOW: usize = 60;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+/// # Frame Rate
+///
+/// The rate frames were really delivered at, returned by actual_fps of Camera and Monitor.
+///
+/// Both values are 0 until two frames were delivered since capturing started.
+pub struct FrameRate {
+    /// The rate of the last two frames, one over the time between them.
+    pub instantaneous: f32,
+
+    /// The mean rate over the frames of the window.
+    pub average: f32,
+
+    /// The number of frames the average was measured over, up to the window length.
+    pub frames: usize,
+}
+
+// drops the frames that come faster than a maximum rate, see Camera::set_max_fps
+#[derive(Debug, Default)]
+pub(crate) struct FrameLimiter {
+    // the earliest time of the next kept frame
+    next: Option<Duration>,
+}
+
+impl FrameLimiter {
+    // if the frame at the time is kept, every frame is when max_fps is 0 or at or above the native rate
+    pub(crate) fn admit(&mut self, time: Duration, max_fps: u32, native_fps: Option<f64>) -> bool {
+        if max_fps == 0 || native_fps.is_some_and(|native| max_fps as f64 >= native) {
+            self.next = None;
+            return true;
+        }
+
+        let interval = Duration::from_secs_f64(1.0 / max_fps as f64);
+
+        //timestamps jitter, a frame slightly early is still kept
+        let tolerance = interval / 8;
+
+        if let Some(next) = self.next
+            && time + tolerance < next
+            && time + interval >= next
+        {
+            return false;
+        }
+
+        //kept frames stay on the grid of the first one, a gap or a timestamp going back starts a new grid
+        self.next = Some(match self.next {
+            Some(next) if time + interval >= next && time < next + interval => next + interval,
+            _ => time + interval,
+        });
+
+        true
+    }
+
+    // forgets the frames of a previous capture
+    pub(crate) fn reset(&mut self) {
+        self.next = None;
+    }
+}
+
+// the delivery times of the latest frames of a capture loop
+#[derive(Debug)]
+pub(crate) struct FpsMeter {
+    times: Mutex<VecDeque<Instant>>,
+    window: RwLock<usize>,
+}
+
+impl Default for FpsMeter {
+    fn default() -> Self {
+        Self {
+            times: Mutex::new(VecDeque::with_capacity(DEFAULT_FPS_WINDOW)),
+            window: RwLock::new(DEFAULT_FPS_WINDOW),
+        }
+    }
+}
+
+impl FpsMeter {
+    // records a delivered frame, dropping the oldest time once the window is full
+    pub(crate) fn record(&self, at: Instant) {
+        let window = self.window();
+        let mut times = self.times.lock().unwrap();
+
+        while times.len() >= window {
+            times.pop_front();
+        }
+
+        times.push_back(at);
+    }
+
+    // forgets the frames of a previous capture
+    pub(crate) fn reset(&self) {
+        self.times.lock().unwrap().clear();
+    }
+
+    // the number of frames measured over, at least two
+    pub(crate) fn set_window(&self, frames: usize) {
+        *self.window.write().unwrap() = frames.max(2);
+        self.times.lock().unwrap().clear();
+    }
+
+    pub(crate) fn window(&self) -> usize {
+        *self.window.read().unwrap()
+    }
+
+    pub(crate) fn get(&self) -> FrameRate {
+        let times = self.times.lock().unwrap();
+
+        let (Some(first), Some(last)) = (times.front(), times.back()) else {
+            return FrameRate::default();
+        };
+
+        let rate = |frames: usize, seconds: f32| {
+            if seconds > 0.0 {
+                frames as f32 / seconds
+            } else {
+                0.0
+            }
+        };
+
+        let instantaneous = match times.len() {
+            0 | 1 => 0.0,
+            len => rate(1, (*last - times[len - 2]).as_secs_f32()),
+        };
+
+        FrameRate {
+            instantaneous,
+            average: rate(
+                times.len().saturating_sub(1),
+                (*last - *first).as_secs_f32(),
+            ),
+            frames: times.len(),
+        }
+    }
+}
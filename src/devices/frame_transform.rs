@@ -0,0 +1,97 @@
+use crate::{
+    devices::{Dimensions, Rotation, camera::Output},
+    transform::rotate::reflect,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// # Frame Transform
+///
+/// Mirrors, flips and rotates the frames of a camera before they are sent, see Camera::set_transform.
+///
+/// The frame is first mirrored and flipped, then rotated clockwise.
+///
+/// ```rs
+/// //a front facing camera mounted upside down
+/// camera.set_transform(FrameTransform {
+///     mirror: true,
+///     rotation: Rotation::Rotate180,
+///     ..Default::default()
+/// });
+/// ```
+pub struct FrameTransform {
+    /// Mirrors the frame horizontally, as front facing cameras are usually shown.
+    pub mirror: bool,
+
+    /// Flips the frame vertically.
+    pub flip: bool,
+
+    /// Rotates the frame clockwise, 90 and 270 degrees swap the width and height.
+    pub rotation: Rotation,
+}
+
+impl FrameTransform {
+    /// # Is Identity
+    ///
+    /// If the transform leaves frames as they are.
+    pub fn is_identity(&self) -> bool {
+        !self.mirror && !self.flip && self.rotation == Rotation::Identity
+    }
+
+    /// # Transformed Size
+    ///
+    /// The size of a frame once the transform is applied.
+    pub fn transformed_size(&self, size: &Dimensions) -> Dimensions {
+        self.rotation.rotated_size(size)
+    }
+
+    /// # Supports
+    ///
+    /// If frames of the output can be transformed, only formats with whole pixels (RGB32, RGB10A2 and RGBA16F) can.
+    ///
+    /// NV12 and YUY2 share chroma samples between pixels and MJPEG is compressed, these frames are sent as they are.
+    pub fn supports(output: Output) -> bool {
+        bytes_per_pixel(output).is_some()
+    }
+
+    // transforms a tightly packed frame, the result is tightly packed too.
+    //
+    // returns None when there is nothing to do or the format cannot be transformed, so the buffer is sent as is.
+    pub(crate) fn apply(
+        &self,
+        data: &[u8],
+        width: usize,
+        height: usize,
+        output: Output,
+    ) -> Option<Vec<u8>> {
+        let bytes_per_pixel = bytes_per_pixel(output)?;
+        let row_len = width * bytes_per_pixel;
+
+        if self.is_identity() || data.len() < row_len * height {
+            return None;
+        }
+
+        let data = &data[..row_len * height];
+
+        let reflected = (self.mirror || self.flip)
+            .then(|| reflect(data, width, height, bytes_per_pixel, self.mirror, self.flip));
+
+        let source = reflected.as_deref().unwrap_or(data);
+
+        match self
+            .rotation
+            .rotate_pixels(source, width, height, bytes_per_pixel)
+        {
+            Some(rotated) => Some(rotated),
+            None => reflected,
+        }
+    }
+}
+
+// the bytes of a single pixel, None for formats without whole pixels
+fn bytes_per_pixel(output: Output) -> Option<usize> {
+    match output {
+        Output::RGB32 | Output::RGB10A2 => Some(4),
+        Output::RGBA16F => Some(8),
+        Output::NV12 | Output::YUY2 | Output::MJPEG | Output::PCM => None,
+    }
+}
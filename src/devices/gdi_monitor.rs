@@ -0,0 +1,425 @@
+use std::{
+    pin::Pin,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use tokio::sync::{
+    Mutex,
+    mpsc::{self, Receiver, Sender},
+};
+
+use windows::Win32::Graphics::Gdi::{
+    BI_RGB, BITMAPINFO, BITMAPINFOHEADER, BitBlt, CAPTUREBLT, CreateCompatibleBitmap,
+    CreateCompatibleDC, DIB_RGB_COLORS, DeleteDC, DeleteObject, GetDC, GetDIBits, ReleaseDC,
+    SRCCOPY, SelectObject,
+};
+use windows::Win32::UI::HiDpi::{
+    DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2, SetThreadDpiAwarenessContext,
+};
+
+use crate::{
+    capture_error::CaptureError,
+    capture_state::StateCell,
+    devices::{
+        DEFAULT_CHANNEL_CAPACITY, Dimensions, Frame,
+        adapter_info::{enum_adapters, output_count},
+        camera::Output,
+        get_all_monitor_info,
+        monitor_info::MonitorInfo,
+    },
+    frame_callback::FrameCallback,
+    i_capture::ICapture,
+};
+
+// the frame rate of a GDI capture unless set_fps is used
+const DEFAULT_GDI_FPS: u32 = 30;
+
+/// # GDI Monitor
+///
+/// Captures a monitor with GDI (BitBlt of the screen), a last resort for environments without Desktop Duplication
+/// such as some remote desktop sessions, virtual machines and very old GPUs.
+///
+/// Every frame is a copy of the whole display made on the CPU, which is a lot slower than a Monitor and has none of its change information
+/// (dirty rects, duplicate detection), the cursor is not part of the frames. Frames are tightly packed BGRA (RGB32) with an opaque alpha channel.
+///
+/// Monitor::from_monitor_or_gdi returns a GdiMonitor on its own when the system does not support Desktop Duplication.
+pub struct GdiMonitor {
+    // the captured area on the virtual desktop
+    left: i32,
+    top: i32,
+    size: Dimensions,
+
+    // the frames captured per second
+    fps: RwLock<u32>,
+
+    /// The receiver, can be used to grab the frames of the display.
+    pub receiver: Arc<Mutex<Receiver<Frame>>>,
+
+    // to send frames
+    sender: Sender<Frame>,
+
+    // how long recv_frame waits for a frame
+    frame_timeout: RwLock<Option<Duration>>,
+
+    // the callback registered with on_frame
+    frame_callback: FrameCallback<Frame>,
+
+    // if the capture loop runs, see ICapture::state
+    state: StateCell,
+}
+
+impl GdiMonitor {
+    /// # From Monitor
+    ///
+    /// The GDI capture of the monitor with the index of Monitor::from_monitor, which counts the outputs of every adapter.
+    ///
+    /// When DXGI cannot list the outputs at all the index counts the displays of get_all_monitor_info that are part of the desktop.
+    pub unsafe fn from_monitor(monitor: u32) -> Result<Arc<Self>, Box<dyn std::error::Error>> {
+        let (left, top, width, height) = match unsafe { Self::output_area(monitor) } {
+            Some(area) => area,
+            None => {
+                let displays: Vec<MonitorInfo> = unsafe { get_all_monitor_info() }
+                    .into_iter()
+                    .filter(|info| info.attached)
+                    .collect();
+
+                let Some(info) = displays.get(monitor as usize) else {
+                    return Err(CaptureError::DeviceIndexOutOfRange {
+                        index: monitor as usize,
+                        len: displays.len(),
+                    }
+                    .into());
+                };
+
+                (info.left, info.top, info.width, info.height)
+            }
+        };
+
+        Ok(Self::from_area(left, top, width, height)?)
+    }
+
+    /// # From Info
+    ///
+    /// The GDI capture of a display of get_all_monitor_info, which must be part of the desktop.
+    pub fn from_info(info: &MonitorInfo) -> Result<Arc<Self>, CaptureError> {
+        if !info.attached {
+            return Err(CaptureError::Other(format!(
+                "{} is not part of the desktop",
+                info.name.trim_end_matches('\0')
+            )));
+        }
+
+        Self::from_area(info.left, info.top, info.width, info.height)
+    }
+
+    // captures an area of the virtual desktop
+    fn from_area(left: i32, top: i32, width: u32, height: u32) -> Result<Arc<Self>, CaptureError> {
+        if width == 0 || height == 0 {
+            return Err(CaptureError::Other(
+                "a GDI capture needs a display with a width and height above 0".into(),
+            ));
+        }
+
+        trace_event!(
+            info,
+            left,
+            top,
+            width,
+            height,
+            "capturing a display with GDI"
+        );
+
+        let (tx, rx) = mpsc::channel(DEFAULT_CHANNEL_CAPACITY);
+
+        Ok(Arc::new(Self {
+            left,
+            top,
+            size: Dimensions { width, height },
+            fps: RwLock::new(DEFAULT_GDI_FPS),
+            receiver: Arc::new(Mutex::new(rx)),
+            sender: tx,
+            frame_timeout: RwLock::new(None),
+            frame_callback: FrameCallback::default(),
+            state: StateCell::default(),
+        }))
+    }
+
+    // the desktop coordinates of an output counted over every adapter, None when DXGI cannot list the outputs
+    unsafe fn output_area(monitor: u32) -> Option<(i32, i32, u32, u32)> {
+        let mut output = monitor;
+
+        for adapter in unsafe { enum_adapters() }.ok()? {
+            let outputs = unsafe { output_count(&adapter) };
+
+            if output < outputs {
+                let desc = unsafe { adapter.EnumOutputs(output).and_then(|o| o.GetDesc()) }.ok()?;
+                let rect = desc.DesktopCoordinates;
+
+                return Some((
+                    rect.left,
+                    rect.top,
+                    (rect.right - rect.left).max(0) as u32,
+                    (rect.bottom - rect.top).max(0) as u32,
+                ));
+            }
+
+            output -= outputs;
+        }
+
+        None
+    }
+
+    /// # Set FPS
+    ///
+    /// Sets how many frames are captured per second, 30 by default. Each frame copies the whole display, so high rates cost a lot of CPU time.
+    pub fn set_fps(&self, fps: u32) {
+        *self.fps.write().unwrap() = fps.max(1);
+    }
+
+    /// # FPS
+    ///
+    /// How many frames are captured per second.
+    pub fn fps(&self) -> u32 {
+        *self.fps.read().unwrap()
+    }
+
+    /// # Position
+    ///
+    /// The top left corner of the captured display on the virtual desktop.
+    pub fn position(&self) -> (i32, i32) {
+        (self.left, self.top)
+    }
+
+    // copies the display as a per monitor aware thread, the desktop coordinates of the display are in physical pixels.
+    //
+    // runs on the blocking thread pool, a copy of the whole display blocks for a long time.
+    unsafe fn grab(
+        left: i32,
+        top: i32,
+        size: &Dimensions,
+    ) -> Result<Vec<u8>, windows::core::Error> {
+        unsafe {
+            let previous = SetThreadDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2);
+            let grabbed = Self::grab_screen(left, top, size);
+
+            if !previous.is_invalid() {
+                SetThreadDpiAwarenessContext(previous);
+            }
+
+            grabbed
+        }
+    }
+
+    // copies the display into a top down BGRA buffer, the GDI objects are made for every frame since they cannot be sent between threads
+    unsafe fn grab_screen(
+        left: i32,
+        top: i32,
+        size: &Dimensions,
+    ) -> Result<Vec<u8>, windows::core::Error> {
+        let (width, height) = (size.width as i32, size.height as i32);
+        let mut data = vec![0u8; size.width as usize * size.height as usize * 4];
+
+        //GetDIBits writes the height the rows were returned in back into the header
+        let dib_height = unsafe {
+            let screen = GetDC(None);
+
+            if screen.is_invalid() {
+                return Err(windows::core::Error::from_thread());
+            }
+
+            let memory = CreateCompatibleDC(Some(screen));
+            let bitmap = CreateCompatibleBitmap(screen, width, height);
+            let previous = SelectObject(memory, bitmap.into());
+
+            //CAPTUREBLT includes layered windows, which are left out otherwise
+            let copied = BitBlt(
+                memory,
+                0,
+                0,
+                width,
+                height,
+                Some(screen),
+                left,
+                top,
+                SRCCOPY | CAPTUREBLT,
+            );
+
+            //a negative height asks for the rows from the top down
+            let mut info = BITMAPINFO {
+                bmiHeader: BITMAPINFOHEADER {
+                    biSize: size_of::<BITMAPINFOHEADER>() as u32,
+                    biWidth: width,
+                    biHeight: -height,
+                    biPlanes: 1,
+                    biBitCount: 32,
+                    biCompression: BI_RGB.0,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+
+            let rows = if copied.is_ok() {
+                GetDIBits(
+                    memory,
+                    bitmap,
+                    0,
+                    height as u32,
+                    Some(data.as_mut_ptr() as *mut _),
+                    &mut info,
+                    DIB_RGB_COLORS,
+                )
+            } else {
+                0
+            };
+
+            SelectObject(memory, previous);
+            let _ = DeleteObject(bitmap.into());
+            let _ = DeleteDC(memory);
+            ReleaseDC(None, screen);
+
+            copied?;
+
+            if rows == 0 {
+                return Err(windows::core::Error::from_thread());
+            }
+
+            info.bmiHeader.biHeight
+        };
+
+        Ok(dib_to_frame(data, size.width, dib_height))
+    }
+}
+
+// turns the 32 bit rows of GetDIBits into a top down BGRA frame, a positive height means the rows are from the bottom up
+pub(crate) fn dib_to_frame(mut data: Vec<u8>, width: u32, height: i32) -> Vec<u8> {
+    let row_len = width as usize * 4;
+
+    if height > 0 && row_len > 0 {
+        let rows = data.len() / row_len;
+
+        for row in 0..rows / 2 {
+            let (upper, lower) = data.split_at_mut((rows - 1 - row) * row_len);
+            upper[row * row_len..(row + 1) * row_len].swap_with_slice(&mut lower[..row_len]);
+        }
+    }
+
+    //GDI leaves the alpha channel at 0
+    for pixel in data.chunks_exact_mut(4) {
+        pixel[3] = 255;
+    }
+
+    data
+}
+
+impl ICapture for GdiMonitor {
+    type CaptureOutput = Frame;
+
+    /// # Get Dimensions
+    ///
+    /// The size of the display when the capture was made.
+    fn get_dimensions(&self) -> Result<Dimensions, Box<dyn std::error::Error>> {
+        Ok(self.size.clone())
+    }
+
+    /// # Get Output Format
+    ///
+    /// Always RGB32.
+    fn get_output_format(&self) -> Output {
+        Output::RGB32
+    }
+
+    /// # Stop Capturing
+    ///
+    /// Stops the capture loop after the current frame.
+    fn stop_capturing(
+        self: Arc<Self>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>> + Send>> {
+        Box::pin(async move {
+            //the loop ends once it sees the state is not capturing anymore
+            if !self.state.stop() {
+                return Err("Not capturing any data".into());
+            }
+
+            Ok(())
+        })
+    }
+
+    /// # Start Capturing
+    ///
+    /// Copies the display at the frame rate of set_fps until stopped, a copy that takes longer than a frame delays the next one.
+    fn start_capturing(
+        self: Arc<Self>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>> + Send>> {
+        Box::pin(async move {
+            //the state is stopped again when the loop ends, also with an error
+            let _running = self.state.start().await?;
+
+            let started = Instant::now();
+            let mut next = started;
+
+            loop {
+                if !self.is_capturing() {
+                    break;
+                }
+
+                let (left, top, size) = (self.left, self.top, self.size.clone());
+
+                let data = tokio::task::spawn_blocking(move || unsafe {
+                    Self::grab(left, top, &size)
+                })
+                .await
+                .map_err(|_| CaptureError::Other("the GDI capture thread panicked".into()))?
+                .inspect_err(|_e| {
+                    trace_event!(error, hresult = %_e.code(), "BitBlt of the display failed");
+                })?;
+
+                let mut frame = Frame::new(data, started.elapsed());
+                frame.stride = Output::RGB32.stride(self.size.width);
+
+                if self.sender.send(frame).await.is_err() {
+                    trace_event!(warn, "receiver dropped, stopping GDI capture");
+                    return Err(
+                        CaptureError::Other("GdiMonitor receiver was dropped".into()).into(),
+                    );
+                }
+
+                next += Duration::from_secs_f64(1.0 / self.fps() as f64);
+
+                let now = Instant::now();
+
+                if next > now {
+                    tokio::time::sleep_until(next.into()).await;
+                } else {
+                    next = now;
+                }
+            }
+
+            Ok(())
+        })
+    }
+
+    fn clone_receiver(&self) -> Arc<Mutex<Receiver<Self::CaptureOutput>>> {
+        self.receiver.clone()
+    }
+
+    fn set_frame_timeout(&self, duration: Duration) {
+        *self.frame_timeout.write().unwrap() = Some(duration);
+    }
+
+    fn clear_frame_timeout(&self) {
+        *self.frame_timeout.write().unwrap() = None;
+    }
+
+    fn get_frame_timeout(&self) -> Option<Duration> {
+        *self.frame_timeout.read().unwrap()
+    }
+
+    fn frame_callback(&self) -> &FrameCallback<Frame> {
+        &self.frame_callback
+    }
+
+    fn state_cell(&self) -> &StateCell {
+        &self.state
+    }
+}
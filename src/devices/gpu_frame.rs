@@ -0,0 +1,248 @@
+use std::sync::Arc;
+
+use windows::Win32::Foundation::HANDLE;
+use windows::Win32::Graphics::Direct3D11::{
+    D3D11_BIND_SHADER_RESOURCE, D3D11_CPU_ACCESS_READ, D3D11_MAP_READ, D3D11_MAPPED_SUBRESOURCE,
+    D3D11_RESOURCE_MISC_SHARED_KEYEDMUTEX, D3D11_TEXTURE2D_DESC, D3D11_USAGE_DEFAULT,
+    D3D11_USAGE_STAGING, ID3D11Device, ID3D11DeviceContext, ID3D11Texture2D,
+};
+use windows::Win32::Graphics::Dxgi::Common::DXGI_SAMPLE_DESC;
+use windows::Win32::Graphics::Dxgi::{IDXGIKeyedMutex, IDXGIResource};
+use windows::core::Interface;
+
+use crate::capture_error::CaptureError;
+use crate::devices::{Dimensions, PixelFormat, Rotation};
+
+// key used by every user of the keyed mutex, it is only used for mutual exclusion
+const KEY: u64 = 0;
+
+// wait forever when acquiring the keyed mutex
+const INFINITE: u32 = u32::MAX;
+
+// a texture that can be opened by other devices, synchronized with a keyed mutex.
+pub(crate) struct SharedTexture {
+    texture: ID3D11Texture2D,
+    keyed_mutex: IDXGIKeyedMutex,
+    format: PixelFormat,
+}
+
+impl SharedTexture {
+    // creates a shareable texture the size and format of the duplicated surface
+    pub(crate) fn new(
+        device: &ID3D11Device,
+        size: &Dimensions,
+        format: PixelFormat,
+    ) -> Result<Self, windows::core::Error> {
+        let desc = D3D11_TEXTURE2D_DESC {
+            Width: size.width,
+            Height: size.height,
+            MipLevels: 1,
+            ArraySize: 1,
+            Format: format.to_dxgi(),
+            SampleDesc: DXGI_SAMPLE_DESC {
+                Count: 1,
+                Quality: 0,
+            },
+            Usage: D3D11_USAGE_DEFAULT,
+            BindFlags: D3D11_BIND_SHADER_RESOURCE.0 as u32,
+            CPUAccessFlags: 0,
+            MiscFlags: D3D11_RESOURCE_MISC_SHARED_KEYEDMUTEX.0 as u32,
+        };
+
+        let mut texture = None;
+
+        unsafe {
+            device.CreateTexture2D(&desc, None, Some(&mut texture))?;
+        }
+
+        let texture = texture.unwrap();
+        let keyed_mutex = texture.cast()?;
+
+        Ok(Self {
+            texture,
+            keyed_mutex,
+            format,
+        })
+    }
+
+    pub(crate) fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    // copies the source into the texture while holding the keyed mutex
+    pub(crate) unsafe fn copy_from(
+        &self,
+        context: &ID3D11DeviceContext,
+        source: &ID3D11Texture2D,
+    ) -> Result<(), windows::core::Error> {
+        unsafe {
+            self.keyed_mutex.AcquireSync(KEY, INFINITE)?;
+            context.CopyResource(&self.texture, source);
+            self.keyed_mutex.ReleaseSync(KEY)
+        }
+    }
+}
+
+/// # GPU Frame
+///
+/// A frame that stays on the GPU, delivered by IGpuCapture instead of copying the data to system memory.
+///
+/// The texture is owned by the crate and is not written to again until every clone of the frame is dropped.
+///
+/// Access to the texture is synchronized with a keyed mutex, hold a TextureLock (from lock) while reading the texture from another device or thread.
+#[derive(Clone)]
+pub struct GpuFrame {
+    shared: Arc<SharedTexture>,
+
+    // the device that owns the texture and its immediate context, used to download the texture
+    device: ID3D11Device,
+    context: ID3D11DeviceContext,
+
+    /// The size of the texture, this is the unrotated size of the duplicated surface.
+    pub dimensions: Dimensions,
+
+    /// The rotation of the display, textures are never rotated so a consumer has to rotate them by this to get an upright image.
+    pub rotation: Rotation,
+
+    /// How many times the source updated since the previous delivered frame without those updates being delivered.
+    pub skipped_since_last: u64,
+
+    /// When the frame was presented on the shared clock, in 100 ns units of the performance counter (see crate::clock).
+    pub presentation_time: i64,
+}
+
+impl GpuFrame {
+    pub(crate) fn new(
+        shared: Arc<SharedTexture>,
+        device: ID3D11Device,
+        context: ID3D11DeviceContext,
+        dimensions: Dimensions,
+        rotation: Rotation,
+        skipped_since_last: u64,
+        presentation_time: i64,
+    ) -> Self {
+        Self {
+            shared,
+            device,
+            context,
+            dimensions,
+            rotation,
+            skipped_since_last,
+            presentation_time,
+        }
+    }
+
+    /// # Texture
+    ///
+    /// The texture holding the frame, it can be used directly with the device from IGpuCapture::gpu_device.
+    pub fn texture(&self) -> &ID3D11Texture2D {
+        &self.shared.texture
+    }
+
+    /// # Format
+    ///
+    /// The pixel format of the texture, HDR surfaces are not tone mapped on the GPU.
+    pub fn format(&self) -> PixelFormat {
+        self.shared.format()
+    }
+
+    /// # Shared Handle
+    ///
+    /// A handle that another D3D11 device can open with ID3D11Device::OpenSharedResource.
+    ///
+    /// The handle belongs to the texture, it must not be closed.
+    pub fn shared_handle(&self) -> Result<HANDLE, windows::core::Error> {
+        unsafe {
+            self.shared
+                .texture
+                .cast::<IDXGIResource>()?
+                .GetSharedHandle()
+        }
+    }
+
+    /// # Lock
+    ///
+    /// Acquires the keyed mutex of the texture, blocking until the crate is no longer writing to it.
+    ///
+    /// The mutex is released when the returned lock is dropped.
+    pub fn lock(&self) -> Result<TextureLock<'_>, CaptureError> {
+        unsafe {
+            self.shared.keyed_mutex.AcquireSync(KEY, INFINITE)?;
+        }
+
+        Ok(TextureLock {
+            keyed_mutex: &self.shared.keyed_mutex,
+        })
+    }
+
+    /// # Download
+    ///
+    /// Copies the texture to system memory for consumers that need the bytes.
+    ///
+    /// The data is tightly packed (width * bytes per pixel per row) in the format of the texture.
+    pub fn download(&self) -> Result<Vec<u8>, CaptureError> {
+        let format = self.format();
+        let desc = D3D11_TEXTURE2D_DESC {
+            Width: self.dimensions.width,
+            Height: self.dimensions.height,
+            MipLevels: 1,
+            ArraySize: 1,
+            Format: format.to_dxgi(),
+            SampleDesc: DXGI_SAMPLE_DESC {
+                Count: 1,
+                Quality: 0,
+            },
+            Usage: D3D11_USAGE_STAGING,
+            BindFlags: 0,
+            CPUAccessFlags: D3D11_CPU_ACCESS_READ.0 as u32,
+            MiscFlags: 0,
+        };
+
+        let width_bytes = self.dimensions.width as usize * format.bytes_per_pixel();
+        let height = self.dimensions.height as usize;
+        let mut data = vec![0u8; width_bytes * height];
+
+        unsafe {
+            let mut staging = None;
+            self.device
+                .CreateTexture2D(&desc, None, Some(&mut staging))?;
+            let staging = staging.unwrap();
+
+            {
+                let _lock = self.lock()?;
+                self.context.CopyResource(&staging, &self.shared.texture);
+            }
+
+            let mut mapped = D3D11_MAPPED_SUBRESOURCE::default();
+            self.context
+                .Map(&staging, 0, D3D11_MAP_READ, 0, Some(&mut mapped))?;
+
+            let row_pitch = mapped.RowPitch as usize;
+            let raw = std::slice::from_raw_parts(mapped.pData as *const u8, row_pitch * height);
+
+            //strip the row padding
+            for (y, row) in data.chunks_exact_mut(width_bytes).enumerate() {
+                row.copy_from_slice(&raw[y * row_pitch..y * row_pitch + width_bytes]);
+            }
+
+            self.context.Unmap(&staging, 0);
+        }
+
+        Ok(data)
+    }
+}
+
+/// # Texture Lock
+///
+/// Holds the keyed mutex of a GpuFrame texture, released on drop.
+pub struct TextureLock<'a> {
+    keyed_mutex: &'a IDXGIKeyedMutex,
+}
+
+impl Drop for TextureLock<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = self.keyed_mutex.ReleaseSync(KEY);
+        }
+    }
+}
@@ -0,0 +1,427 @@
+use std::mem::ManuallyDrop;
+
+use windows::Win32::Foundation::{E_NOTIMPL, RECT};
+use windows::Win32::Graphics::Direct3D11::{
+    D3D11_BIND_RENDER_TARGET, D3D11_TEX2D_VPIV, D3D11_TEX2D_VPOV, D3D11_TEXTURE2D_DESC,
+    D3D11_USAGE_DEFAULT, D3D11_VIDEO_COLOR, D3D11_VIDEO_COLOR_0, D3D11_VIDEO_COLOR_RGBA,
+    D3D11_VIDEO_FRAME_FORMAT_PROGRESSIVE, D3D11_VIDEO_PROCESSOR_CONTENT_DESC,
+    D3D11_VIDEO_PROCESSOR_FORMAT_SUPPORT_INPUT, D3D11_VIDEO_PROCESSOR_FORMAT_SUPPORT_OUTPUT,
+    D3D11_VIDEO_PROCESSOR_INPUT_VIEW_DESC, D3D11_VIDEO_PROCESSOR_INPUT_VIEW_DESC_0,
+    D3D11_VIDEO_PROCESSOR_OUTPUT_VIEW_DESC, D3D11_VIDEO_PROCESSOR_OUTPUT_VIEW_DESC_0,
+    D3D11_VIDEO_PROCESSOR_ROTATION, D3D11_VIDEO_PROCESSOR_ROTATION_90,
+    D3D11_VIDEO_PROCESSOR_ROTATION_180, D3D11_VIDEO_PROCESSOR_ROTATION_270,
+    D3D11_VIDEO_PROCESSOR_ROTATION_IDENTITY, D3D11_VIDEO_PROCESSOR_STREAM,
+    D3D11_VIDEO_USAGE_OPTIMAL_SPEED, D3D11_VPIV_DIMENSION_TEXTURE2D,
+    D3D11_VPOV_DIMENSION_TEXTURE2D, ID3D11Device, ID3D11DeviceContext, ID3D11Texture2D,
+    ID3D11VideoContext, ID3D11VideoDevice, ID3D11VideoProcessor, ID3D11VideoProcessorEnumerator,
+    ID3D11VideoProcessorInputView, ID3D11VideoProcessorOutputView,
+};
+use windows::Win32::Graphics::Dxgi::Common::{DXGI_FORMAT, DXGI_RATIONAL, DXGI_SAMPLE_DESC};
+use windows::core::Interface;
+
+use crate::devices::{AspectMode, Dimensions, PixelFormat, Rotation};
+
+// a rect as (left, top, right, bottom)
+type Rect = (usize, usize, usize, usize);
+
+// where a scaled frame is drawn in the output
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Placement {
+    // the rotation applied while scaling
+    pub(crate) rotation: Rotation,
+
+    // the part of the rotated surface that is scaled
+    source: Rect,
+
+    // where the source is drawn in the output
+    dest: Rect,
+
+    // the size of the output
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+}
+
+impl Placement {
+    // fits a (rotated) surface into the output following the aspect mode
+    fn new(
+        rotation: Rotation,
+        source: &Dimensions,
+        output: &Dimensions,
+        aspect_mode: AspectMode,
+    ) -> Self {
+        let source_width = source.width as usize;
+        let source_height = source.height as usize;
+        let width = output.width as usize;
+        let height = output.height as usize;
+
+        let full_source = (0, 0, source_width, source_height);
+        let full_output = (0, 0, width, height);
+
+        let scale_x = width as f64 / source_width.max(1) as f64;
+        let scale_y = height as f64 / source_height.max(1) as f64;
+
+        let (source, dest) = match aspect_mode {
+            AspectMode::Stretch => (full_source, full_output),
+            AspectMode::Letterbox => {
+                let scale = scale_x.min(scale_y);
+
+                let dest = centered(
+                    (source_width as f64 * scale).round() as usize,
+                    (source_height as f64 * scale).round() as usize,
+                    width,
+                    height,
+                );
+
+                (full_source, dest)
+            }
+            AspectMode::Crop => {
+                let scale = scale_x.max(scale_y);
+
+                let source = centered(
+                    (width as f64 / scale).round() as usize,
+                    (height as f64 / scale).round() as usize,
+                    source_width,
+                    source_height,
+                );
+
+                (source, full_output)
+            }
+        };
+
+        Self {
+            rotation,
+            source,
+            dest,
+            width,
+            height,
+        }
+    }
+
+    // maps a rect of the rotated surface onto the output, parts outside of the source are clipped
+    pub(crate) fn map_rect(&self, (left, top, right, bottom): Rect) -> Rect {
+        let (source_left, source_top, source_right, source_bottom) = self.source;
+        let (dest_left, dest_top, dest_right, dest_bottom) = self.dest;
+
+        let map = |value: usize, from: (usize, usize), to: (usize, usize)| {
+            let value = value.clamp(from.0, from.1);
+            let scale = (to.1 - to.0) as f64 / (from.1 - from.0).max(1) as f64;
+
+            to.0 + ((value - from.0) as f64 * scale).round() as usize
+        };
+
+        let horizontal = ((source_left, source_right), (dest_left, dest_right));
+        let vertical = ((source_top, source_bottom), (dest_top, dest_bottom));
+
+        (
+            map(left, horizontal.0, horizontal.1),
+            map(top, vertical.0, vertical.1),
+            map(right, horizontal.0, horizontal.1),
+            map(bottom, vertical.0, vertical.1),
+        )
+    }
+
+    // maps a point of the rotated surface onto the output, points outside of the source land outside of the dest
+    pub(crate) fn map_point(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        let (source_left, source_top, source_right, source_bottom) = self.source;
+        let (dest_left, dest_top, dest_right, dest_bottom) = self.dest;
+
+        let scale_x = (dest_right - dest_left) as f32 / (source_right - source_left).max(1) as f32;
+        let scale_y = (dest_bottom - dest_top) as f32 / (source_bottom - source_top).max(1) as f32;
+
+        (
+            dest_left as f32 + (x - source_left as f32) * scale_x,
+            dest_top as f32 + (y - source_top as f32) * scale_y,
+        )
+    }
+}
+
+// a rect of the given size in the middle of the outer size
+fn centered(width: usize, height: usize, outer_width: usize, outer_height: usize) -> Rect {
+    let width = width.min(outer_width);
+    let height = height.min(outer_height);
+
+    let left = (outer_width - width) / 2;
+    let top = (outer_height - height) / 2;
+
+    (left, top, left + width, top + height)
+}
+
+fn to_rect((left, top, right, bottom): Rect) -> RECT {
+    RECT {
+        left: left as i32,
+        top: top as i32,
+        right: right as i32,
+        bottom: bottom as i32,
+    }
+}
+
+fn to_video_rotation(rotation: Rotation) -> D3D11_VIDEO_PROCESSOR_ROTATION {
+    match rotation {
+        Rotation::Identity => D3D11_VIDEO_PROCESSOR_ROTATION_IDENTITY,
+        Rotation::Rotate90 => D3D11_VIDEO_PROCESSOR_ROTATION_90,
+        Rotation::Rotate180 => D3D11_VIDEO_PROCESSOR_ROTATION_180,
+        Rotation::Rotate270 => D3D11_VIDEO_PROCESSOR_ROTATION_270,
+    }
+}
+
+// scales (and rotates) the duplicated surface into a smaller texture with the D3D11 video processor,
+// so only the scaled frame has to be copied to the CPU.
+pub(crate) struct GpuScaler {
+    video_device: ID3D11VideoDevice,
+    video_context: ID3D11VideoContext,
+    enumerator: ID3D11VideoProcessorEnumerator,
+    processor: ID3D11VideoProcessor,
+
+    // the texture the scaled frame is drawn into
+    output_texture: ID3D11Texture2D,
+    output_view: ID3D11VideoProcessorOutputView,
+
+    // the last source texture and its view, the duplication normally hands out the same texture every frame
+    input: Option<(ID3D11Texture2D, ID3D11VideoProcessorInputView)>,
+
+    // what the scaler was made for, it has to be made again when any of these change
+    surface_size: Dimensions,
+    format: PixelFormat,
+    output_size: Dimensions,
+    aspect_mode: AspectMode,
+
+    placement: Placement,
+}
+
+impl GpuScaler {
+    // makes a video processor that scales a surface of the given size and format into the output size and format,
+    // the rotation is applied at the same time.
+    pub(crate) fn new(
+        device: &ID3D11Device,
+        context: &ID3D11DeviceContext,
+        surface_size: &Dimensions,
+        format: PixelFormat,
+        output_format: DXGI_FORMAT,
+        rotation: Rotation,
+        output_size: &Dimensions,
+        aspect_mode: AspectMode,
+    ) -> Result<Self, windows::core::Error> {
+        let video_device: ID3D11VideoDevice = device.cast()?;
+        let video_context: ID3D11VideoContext = context.cast()?;
+
+        //the frame rate is only a hint for the driver
+        let frame_rate = DXGI_RATIONAL {
+            Numerator: 60,
+            Denominator: 1,
+        };
+
+        let content_desc = D3D11_VIDEO_PROCESSOR_CONTENT_DESC {
+            InputFrameFormat: D3D11_VIDEO_FRAME_FORMAT_PROGRESSIVE,
+            InputFrameRate: frame_rate,
+            InputWidth: surface_size.width,
+            InputHeight: surface_size.height,
+            OutputFrameRate: frame_rate,
+            OutputWidth: output_size.width,
+            OutputHeight: output_size.height,
+            Usage: D3D11_VIDEO_USAGE_OPTIMAL_SPEED,
+        };
+
+        let texture_desc = D3D11_TEXTURE2D_DESC {
+            Width: output_size.width,
+            Height: output_size.height,
+            MipLevels: 1,
+            ArraySize: 1,
+            Format: output_format,
+            SampleDesc: DXGI_SAMPLE_DESC {
+                Count: 1,
+                Quality: 0,
+            },
+            Usage: D3D11_USAGE_DEFAULT,
+            BindFlags: D3D11_BIND_RENDER_TARGET.0 as u32,
+            CPUAccessFlags: 0,
+            MiscFlags: 0,
+        };
+
+        let output_view_desc = D3D11_VIDEO_PROCESSOR_OUTPUT_VIEW_DESC {
+            ViewDimension: D3D11_VPOV_DIMENSION_TEXTURE2D,
+            Anonymous: D3D11_VIDEO_PROCESSOR_OUTPUT_VIEW_DESC_0 {
+                Texture2D: D3D11_TEX2D_VPOV { MipSlice: 0 },
+            },
+        };
+
+        let placement = Placement::new(
+            rotation,
+            &rotation.rotated_size(surface_size),
+            output_size,
+            aspect_mode,
+        );
+
+        //the source rect is in the coordinates of the unrotated surface
+        let source_rect = to_rect(rotation.unrotate_rect(
+            placement.source,
+            surface_size.width as usize,
+            surface_size.height as usize,
+        ));
+        let dest_rect = to_rect(placement.dest);
+
+        //letterbox bars
+        let black = D3D11_VIDEO_COLOR {
+            Anonymous: D3D11_VIDEO_COLOR_0 {
+                RGBA: D3D11_VIDEO_COLOR_RGBA {
+                    R: 0.0,
+                    G: 0.0,
+                    B: 0.0,
+                    A: 1.0,
+                },
+            },
+        };
+
+        unsafe {
+            let enumerator = video_device.CreateVideoProcessorEnumerator(&content_desc)?;
+
+            let input_support = enumerator.CheckVideoProcessorFormat(format.to_dxgi())?;
+            let output_support = enumerator.CheckVideoProcessorFormat(output_format)?;
+
+            if input_support & D3D11_VIDEO_PROCESSOR_FORMAT_SUPPORT_INPUT.0 as u32 == 0
+                || output_support & D3D11_VIDEO_PROCESSOR_FORMAT_SUPPORT_OUTPUT.0 as u32 == 0
+            {
+                return Err(windows::core::Error::new(
+                    E_NOTIMPL,
+                    format!(
+                        "the video processor cannot scale {format:?} surfaces into {output_format:?}"
+                    ),
+                ));
+            }
+
+            let processor = video_device.CreateVideoProcessor(&enumerator, 0)?;
+
+            let mut output_texture = None;
+            device.CreateTexture2D(&texture_desc, None, Some(&mut output_texture))?;
+            let output_texture = output_texture.unwrap();
+
+            let mut output_view = None;
+            video_device.CreateVideoProcessorOutputView(
+                &output_texture,
+                &enumerator,
+                &output_view_desc,
+                Some(&mut output_view),
+            )?;
+
+            video_context.VideoProcessorSetStreamFrameFormat(
+                &processor,
+                0,
+                D3D11_VIDEO_FRAME_FORMAT_PROGRESSIVE,
+            );
+            video_context.VideoProcessorSetStreamSourceRect(
+                &processor,
+                0,
+                true,
+                Some(&source_rect),
+            );
+            video_context.VideoProcessorSetStreamDestRect(&processor, 0, true, Some(&dest_rect));
+            video_context.VideoProcessorSetStreamRotation(
+                &processor,
+                0,
+                rotation != Rotation::Identity,
+                to_video_rotation(rotation),
+            );
+            video_context.VideoProcessorSetOutputBackgroundColor(&processor, false, &black);
+
+            Ok(Self {
+                video_device,
+                video_context,
+                enumerator,
+                processor,
+                output_texture,
+                output_view: output_view.unwrap(),
+                input: None,
+                surface_size: surface_size.clone(),
+                format,
+                output_size: output_size.clone(),
+                aspect_mode,
+                placement,
+            })
+        }
+    }
+
+    // if the scaler was made for these settings
+    pub(crate) fn matches(
+        &self,
+        surface_size: &Dimensions,
+        format: PixelFormat,
+        rotation: Rotation,
+        output_size: &Dimensions,
+        aspect_mode: AspectMode,
+    ) -> bool {
+        self.surface_size == *surface_size
+            && self.format == format
+            && self.placement.rotation == rotation
+            && self.output_size == *output_size
+            && self.aspect_mode == aspect_mode
+    }
+
+    pub(crate) fn placement(&self) -> Placement {
+        self.placement
+    }
+
+    // draws the source into the output texture, which is returned
+    pub(crate) unsafe fn scale(
+        &mut self,
+        source: &ID3D11Texture2D,
+    ) -> Result<&ID3D11Texture2D, windows::core::Error> {
+        let input_view = match &self.input {
+            Some((texture, view)) if texture == source => view.clone(),
+            _ => {
+                let view = unsafe { self.create_input_view(source)? };
+                self.input = Some((source.clone(), view.clone()));
+                view
+            }
+        };
+
+        let stream = D3D11_VIDEO_PROCESSOR_STREAM {
+            Enable: true.into(),
+            pInputSurface: ManuallyDrop::new(Some(input_view)),
+            ..Default::default()
+        };
+
+        let blt = unsafe {
+            self.video_context.VideoProcessorBlt(
+                &self.processor,
+                &self.output_view,
+                0,
+                std::slice::from_ref(&stream),
+            )
+        };
+
+        //the stream holds a reference to the view that is not released on its own
+        drop(ManuallyDrop::into_inner(stream.pInputSurface));
+
+        blt?;
+
+        Ok(&self.output_texture)
+    }
+
+    unsafe fn create_input_view(
+        &self,
+        source: &ID3D11Texture2D,
+    ) -> Result<ID3D11VideoProcessorInputView, windows::core::Error> {
+        let desc = D3D11_VIDEO_PROCESSOR_INPUT_VIEW_DESC {
+            FourCC: 0,
+            ViewDimension: D3D11_VPIV_DIMENSION_TEXTURE2D,
+            Anonymous: D3D11_VIDEO_PROCESSOR_INPUT_VIEW_DESC_0 {
+                Texture2D: D3D11_TEX2D_VPIV {
+                    MipSlice: 0,
+                    ArraySlice: 0,
+                },
+            },
+        };
+
+        let mut view = None;
+
+        unsafe {
+            self.video_device.CreateVideoProcessorInputView(
+                source,
+                &self.enumerator,
+                &desc,
+                Some(&mut view),
+            )?;
+        }
+
+        Ok(view.unwrap())
+    }
+}
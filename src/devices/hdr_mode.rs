@@ -0,0 +1,18 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+/// # HDR Mode
+///
+/// Controls what a Monitor does with frames when the desktop surface is HDR (10 or 16 bit).
+///
+/// Has no effect on SDR displays, which always deliver 8 bit BGRA.
+pub enum HdrMode {
+    /// Tone map the HDR surface to 8 bit BGRA using the SDR white level from the display settings.
+    ///
+    /// This is the default so existing consumers keep working on HDR displays.
+    #[default]
+    ToneMap,
+
+    /// Deliver the raw 10/16 bit surface data, see Monitor::pixel_format for the layout.
+    ///
+    /// Monitor::tone_map (or convert::hdr_to_bgra) converts single frames to BGRA, for an SDR preview of the raw capture.
+    Raw,
+}
@@ -0,0 +1,101 @@
+use std::cmp::{Ordering, Reverse};
+
+use windows::Win32::Media::MediaFoundation::{
+    IMFMediaType, MF_MT_FRAME_RATE, MF_MT_FRAME_SIZE, MF_MT_SUBTYPE,
+};
+
+use crate::devices::camera::Output;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// # Media Type Info
+///
+/// A format, resolution and frame rate a camera supports.
+///
+/// These can be retrieved before activation with Cameras::device_capabilities, or from an activated camera with Camera::supported_formats.
+pub struct MediaTypeInfo {
+    /// The format of the frames.
+    pub format: Output,
+
+    /// Width of the frames in pixels.
+    pub width: u32,
+
+    /// Height of the frames in pixels.
+    pub height: u32,
+
+    /// Numerator of the frame rate, frames per second is fps_numerator / fps_denominator.
+    pub fps_numerator: u32,
+
+    /// Denominator of the frame rate.
+    pub fps_denominator: u32,
+}
+
+impl MediaTypeInfo {
+    /// # Frames Per Second
+    ///
+    /// The frame rate as a float, for example 29.97.
+    pub fn fps(&self) -> f32 {
+        if self.fps_denominator == 0 {
+            return 0.0;
+        }
+
+        self.fps_numerator as f32 / self.fps_denominator as f32
+    }
+
+    // compares the frame rates without rounding, 60/2 is the same rate as 30/1
+    fn cmp_fps(&self, other: &Self) -> Ordering {
+        let rate = self.fps_numerator as u64 * other.fps_denominator.max(1) as u64;
+        let other_rate = other.fps_numerator as u64 * self.fps_denominator.max(1) as u64;
+
+        rate.cmp(&other_rate)
+    }
+
+    // orders the infos for a list, the largest resolution first then the highest frame rate,
+    // and removes the duplicates of native types that only differ in attributes the info does not hold.
+    pub(crate) fn sort_and_dedup(infos: &mut Vec<Self>) {
+        infos.sort_by(|a, b| {
+            let key = |info: &Self| {
+                (
+                    Reverse(info.width as u64 * info.height as u64),
+                    Reverse(info.width),
+                )
+            };
+
+            key(a)
+                .cmp(&key(b))
+                .then_with(|| b.cmp_fps(a))
+                .then_with(|| (a.format as u8).cmp(&(b.format as u8)))
+        });
+
+        infos.dedup_by(|a, b| {
+            a.format == b.format
+                && a.width == b.width
+                && a.height == b.height
+                && a.cmp_fps(b) == Ordering::Equal
+        });
+    }
+
+    // reads the info from a media type, None if the subtype is not a known output.
+    pub(crate) unsafe fn from_media_type(
+        media_type: &IMFMediaType,
+    ) -> Result<Option<Self>, windows::core::Error> {
+        unsafe {
+            let subtype = media_type.GetGUID(&MF_MT_SUBTYPE)?;
+
+            let Some(format) = Output::from_subtype(&subtype) else {
+                return Ok(None);
+            };
+
+            //both the size and rate are packed as two u32 into a u64
+            let size = media_type.GetUINT64(&MF_MT_FRAME_SIZE)?;
+            let rate = media_type.GetUINT64(&MF_MT_FRAME_RATE).unwrap_or(0);
+
+            Ok(Some(MediaTypeInfo {
+                format,
+                width: (size >> 32) as u32,
+                height: (size & 0xFFFFFFFF) as u32,
+                fps_numerator: (rate >> 32) as u32,
+                fps_denominator: (rate & 0xFFFFFFFF) as u32,
+            }))
+        }
+    }
+}
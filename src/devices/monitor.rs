@@ -1,170 +1,1556 @@
+use crate::capture_error::CaptureError;
+use crate::capture_event::CaptureEvent;
+use crate::capture_state::StateCell;
+use crate::clock::{clock, qpc_to_clock};
 use crate::devices::monitor_info::MonitorInfo;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock, Weak};
+use std::time::{Duration, Instant};
 
 use tokio::sync::mpsc::{Receiver, Sender};
 use tokio::sync::{Mutex, mpsc};
-use windows::Win32::Foundation::RECT;
+use windows::Win32::Foundation::{E_ACCESSDENIED, E_INVALIDARG, HWND, LUID, POINT, RECT};
 use windows::Win32::Graphics::Direct3D11::{
     D3D11_BIND_FLAG, D3D11_CPU_ACCESS_READ, D3D11_MAP_READ, D3D11_MAPPED_SUBRESOURCE,
     D3D11_RESOURCE_MISC_FLAG, D3D11_TEXTURE2D_DESC, D3D11_USAGE_STAGING, ID3D11DeviceContext,
-    ID3D11Texture2D,
+    ID3D11Multithread, ID3D11Texture2D,
+};
+use windows::Win32::Graphics::Dxgi::Common::{
+    DXGI_FORMAT_B8G8R8A8_UNORM, DXGI_FORMAT_R10G10B10A2_UNORM, DXGI_FORMAT_R16G16B16A16_FLOAT,
+    DXGI_SAMPLE_DESC,
+};
+use windows::Win32::Graphics::Dxgi::{DXGI_OUTDUPL_MOVE_RECT, IDXGIOutput1};
+use windows::Win32::Graphics::Gdi::{
+    HMONITOR, MONITOR_DEFAULTTONULL, MonitorFromPoint, MonitorFromWindow,
+};
+use windows::Win32::System::Threading::GetCurrentProcessId;
+use windows::Win32::UI::HiDpi::{
+    DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2, GetDpiForMonitor, MDT_EFFECTIVE_DPI,
+    SetThreadDpiAwarenessContext,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetWindowRect, GetWindowThreadProcessId, IsIconic, IsWindow, SetWindowDisplayAffinity,
+    WDA_EXCLUDEFROMCAPTURE, WDA_NONE,
 };
-use windows::Win32::Graphics::Dxgi::Common::{DXGI_FORMAT_B8G8R8A8_UNORM, DXGI_SAMPLE_DESC};
-use windows::Win32::Graphics::Dxgi::{DXGI_OUTDUPL_MOVE_RECT, IDXGIDevice, IDXGIOutput1};
 use windows::Win32::{
     Foundation::HMODULE,
     Graphics::{
-        Direct3D::D3D_DRIVER_TYPE_HARDWARE,
+        Direct3D::D3D_DRIVER_TYPE_UNKNOWN,
         Direct3D11::{
-            D3D11_CREATE_DEVICE_BGRA_SUPPORT, D3D11_SDK_VERSION, D3D11CreateDevice, ID3D11Device,
+            D3D11_CREATE_DEVICE_BGRA_SUPPORT, D3D11_CREATE_DEVICE_VIDEO_SUPPORT, D3D11_SDK_VERSION,
+            D3D11CreateDevice, ID3D11Device,
         },
         Dxgi::*,
     },
 };
 use windows::core::Interface;
 
-use crate::devices::{Dimensions, get_monitor_count};
+use crate::devices::adapter_info::{enum_adapters, output_count};
+use crate::devices::camera::Output;
+use crate::devices::change_detection::ChangeDetector;
+use crate::devices::click_overlay::{ClickHook, recent_clicks};
+use crate::devices::frame_rate::FpsMeter;
+use crate::devices::gpu_frame::SharedTexture;
+use crate::devices::gpu_scaler::{GpuScaler, Placement};
 use crate::devices::monitor_frame::MonitorFrame;
+use crate::devices::tone_map::tone_map_to_bgra;
+use crate::devices::{
+    AspectMode, Backpressure, ChangeDetection, ClickOverlay, CursorInfo, DEFAULT_CHANNEL_CAPACITY,
+    Dimensions, EVENT_CHANNEL_CAPACITY, Frame, FrameRate, GdiMonitor, GpuFrame, HdrMode, MoveRect,
+    PixelFormat, Rect, RetryPolicy, Rotation, get_sdr_white_level, replace_channel,
+    send_with_backpressure,
+};
+use crate::dyn_capture::DynCapture;
+use crate::frame_callback::FrameCallback;
 use crate::i_capture::ICapture;
+use crate::i_gpu_capture::IGpuCapture;
+use crate::i_sync_capture::{ISyncCapture, SyncChannel};
+
+// brightness of SDR white when the display settings cannot be read
+const DEFAULT_SDR_WHITE_NITS: f32 = 80.0;
+
+// how long to wait for the desktop to change before checking if capturing was stopped
+const ACQUIRE_TIMEOUT_MS: u32 = 500;
+
+// the longest wait between two attempts at recovering a lost duplication, so capturing resumes soon after the desktop returns
+const MAX_RECOVERY_DELAY: Duration = Duration::from_secs(1);
 
 /// # Monitor
 ///
 /// Reprents a monitor on your device, you can simply create one by using the from_monitor function
 pub struct Monitor {
     /// The IDXGIOutputDuplication interface accesses and manipulates the duplicated desktop image.
-    duplication_output: IDXGIOutputDuplication,
+    ///
+    /// None while the output is being duplicated again after the duplication was lost.
+    duplication_output: Mutex<Option<IDXGIOutputDuplication>>,
+
+    //the output and device the duplication was created from, kept so the output can be duplicated again.
+    output: IDXGIOutput1,
+    device: ID3D11Device,
+
+    //how transient duplication failures are retried
+    retry_policy: RwLock<RetryPolicy>,
+
+    pub receiver: Arc<Mutex<Receiver<Frame>>>,
+    //replaced by set_channel_capacity
+    sender: RwLock<Sender<Frame>>,
 
-    pub receiver: Arc<Mutex<Receiver<Vec<u8>>>>,
-    sender: Sender<Vec<u8>>,
+    /// The receiver of textures when capturing with start_gpu_capturing.
+    pub gpu_receiver: Arc<Mutex<Receiver<GpuFrame>>>,
+    gpu_sender: Sender<GpuFrame>,
 
-    is_sending: Arc<Mutex<bool>>,
+    //textures handed out to the GPU receiver, reused once no delivered frame holds them
+    gpu_textures: RwLock<Vec<Arc<SharedTexture>>>,
 
     frame: Arc<Mutex<MonitorFrame>>,
 
-    device_context: ID3D11DeviceContext,
+    device_context: ID3D11DeviceContext,
+
+    //texture that is used to copy from the GPU to CPU, expensive, so made on init and when the size or format of the copied frame changes
+    staging_texture: RwLock<ID3D11Texture2D>,
+
+    //the format of the duplicated desktop surface
+    surface_format: RwLock<PixelFormat>,
+
+    //what should happen with HDR surfaces
+    hdr_mode: RwLock<HdrMode>,
+
+    //the brightness in nits of SDR white, used for tone mapping
+    sdr_white_level: RwLock<f32>,
+
+    /// The size of the duplicated surface in physical pixels, this is the unrotated size of the monitor.
+    ///
+    /// Use physical_size or get_dimensions for the size of the delivered frames.
+    pub desktop_size: Dimensions,
+
+    //how the display is rotated, the duplicated surface is always unrotated
+    rotation: RwLock<Rotation>,
+
+    //if frames are rotated to match the display orientation before they are sent
+    auto_rotate: RwLock<bool>,
+
+    //the DPI scaling of the monitor, 1.5 at 150%
+    scale_factor: f32,
+
+    //the position of the monitor on the virtual desktop
+    desktop_coordinates: RECT,
+
+    //the size frames are scaled to on the GPU and how the aspect ratio is kept, None to send the full size
+    output_size: RwLock<Option<(Dimensions, AspectMode)>>,
+
+    //the video processor used to scale frames, made again when the output size, rotation or surface changes
+    scaler: RwLock<Option<GpuScaler>>,
+
+    //frames per second of constant frame rate mode, None to send on change
+    constant_fps: RwLock<Option<u32>>,
+
+    //how long recv_frame waits for a frame
+    frame_timeout: RwLock<Option<Duration>>,
+
+    //the callback registered with on_frame
+    frame_callback: FrameCallback<Frame>,
+
+    //how frames are compared with the previous frame, None to only flag what Desktop Duplication reports
+    change_detection: RwLock<Option<ChangeDetection>>,
+
+    //the samples of the previous delivered frame
+    change_detector: Mutex<ChangeDetector>,
+
+    //what happens to a frame when the buffer of the receiver is full
+    backpressure: RwLock<Backpressure>,
+
+    //the delivery times of the latest frames, see actual_fps
+    frame_rate: FpsMeter,
+
+    //if the capture loop (of frames or textures) runs, see ICapture::state
+    state: StateCell,
+
+    //windows that are painted over in delivered frames
+    excluded_windows: RwLock<Vec<HWND>>,
+
+    //the BGRA color excluded windows are painted with
+    exclusion_color: RwLock<[u8; 4]>,
+
+    //if frames keep the row padding of the mapped texture
+    padded_frames: RwLock<bool>,
+
+    //rings drawn on mouse clicks and the mouse hook detecting them, None when off
+    click_overlay: RwLock<Option<(ClickOverlay, ClickHook)>>,
+
+    //the latest cursor reported by the duplication, None until the first mouse update
+    cursor: RwLock<Option<CursorInfo>>,
+
+    //if cursor updates are sent as events
+    cursor_events: RwLock<bool>,
+
+    //events such as the recovery of a lost duplication
+    event_sender: Sender<CaptureEvent>,
+    event_receiver: Arc<Mutex<Receiver<CaptureEvent>>>,
+
+    //the monitor itself, started on a thread of its own by ISyncCapture
+    this: Weak<Monitor>,
+
+    //the frames of ISyncCapture
+    sync: SyncChannel,
+
+    pub name: String,
+}
+
+impl Monitor {
+    /// # From Monitor Info
+    ///
+    /// Create device information for a given monitor of your system.
+    ///
+    /// Provides a Monitor struct that has the ability to duplicate the data and do other manipulation.
+    pub unsafe fn from_monitor_info(
+        monitor_info: MonitorInfo,
+    ) -> Result<Arc<Self>, Box<dyn std::error::Error>> {
+        let index = monitor_info.index;
+
+        unsafe { Self::from_monitor(index) }
+    }
+
+    /// ## From Monitor
+    ///
+    /// Create device information for a given monitor of your system.
+    ///
+    /// Provides a Monitor struct that has the ability to duplicate the data and do other manipulation.
+    pub unsafe fn from_monitor(monitor: u32) -> Result<Arc<Self>, Box<dyn std::error::Error>> {
+        unsafe { Self::from_monitor_with_retry(monitor, RetryPolicy::default()) }
+    }
+
+    /// ## From Monitor Or GDI
+    ///
+    /// Same as from_monitor, but falls back to a GdiMonitor of the same display when the system does not support Desktop Duplication
+    /// (DXGI_ERROR_UNSUPPORTED, as over some remote desktop sessions and in virtual machines).
+    ///
+    /// Other errors are returned as they are, a GdiMonitor is a lot slower and is only worth it when there is nothing else.
+    pub unsafe fn from_monitor_or_gdi(
+        monitor: u32,
+    ) -> Result<Arc<dyn DynCapture>, Box<dyn std::error::Error>> {
+        match unsafe { Self::from_monitor(monitor) } {
+            Ok(monitor) => Ok(monitor),
+            Err(e) if Self::is_unsupported(e.as_ref()) => {
+                trace_event!(warn, error = %e, "desktop duplication is not supported, falling back to GDI");
+
+                Ok(unsafe { GdiMonitor::from_monitor(monitor) }?)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    // if the error says desktop duplication (or direct3d 11) is not supported by the system
+    fn is_unsupported(e: &(dyn std::error::Error + 'static)) -> bool {
+        let code = match (
+            e.downcast_ref::<CaptureError>(),
+            e.downcast_ref::<windows::core::Error>(),
+        ) {
+            (Some(CaptureError::Windows(e)), _) | (_, Some(e)) => e.code(),
+            _ => return false,
+        };
+
+        code == DXGI_ERROR_UNSUPPORTED
+    }
+
+    /// ## From Monitor With Retry
+    ///
+    /// Same as from_monitor, but transient duplication failures (such as UAC secure desktop transitions) are retried using the given policy.
+    ///
+    /// The policy is kept and used again if the duplication is lost while capturing, it can be changed later with set_retry_policy.
+    ///
+    /// Returns CaptureError::DuplicationUnavailable if the max number of duplications has been reached.
+    pub unsafe fn from_monitor_with_retry(
+        monitor: u32,
+        retry_policy: RetryPolicy,
+    ) -> Result<Arc<Self>, Box<dyn std::error::Error>> {
+        trace_span!(DEBUG, "from_monitor", monitor);
+
+        unsafe {
+            //the index counts the outputs of every adapter, so the device is made on the adapter driving the output
+            let mut output = monitor;
+
+            for adapter in enum_adapters()? {
+                let outputs = output_count(&adapter);
+
+                if output < outputs {
+                    return Self::from_output(&adapter, output, retry_policy);
+                }
+
+                output -= outputs;
+            }
+
+            let max_monitors = monitor - output;
+
+            Err(format!(
+                "monitor index ({monitor}) fell outside of the max range of {max_monitors}"
+            )
+            .into())
+        }
+    }
+
+    /// ## From Adapter Output
+    ///
+    /// Create the Monitor of an output of a specific adapter (see get_all_adapter_info), the output index is local to the adapter.
+    ///
+    /// from_monitor finds the adapter on its own, this is for choosing between adapters explicitly.
+    pub unsafe fn from_adapter_output(
+        adapter: u32,
+        output: u32,
+    ) -> Result<Arc<Self>, Box<dyn std::error::Error>> {
+        unsafe {
+            let Some(adapter) = enum_adapters()?.into_iter().nth(adapter as usize) else {
+                return Err(format!("there is no adapter with the index {adapter}").into());
+            };
+
+            Self::from_output(&adapter, output, RetryPolicy::default())
+        }
+    }
+
+    /// ## From Adapter Luid
+    ///
+    /// Create the Monitor of an output of the adapter with the given LUID (see AdapterInfo::luid), the output index is local to the adapter.
+    pub unsafe fn from_adapter_luid(
+        luid: LUID,
+        output: u32,
+    ) -> Result<Arc<Self>, Box<dyn std::error::Error>> {
+        unsafe {
+            for adapter in enum_adapters()? {
+                let desc = adapter.GetDesc1()?;
+
+                if desc.AdapterLuid.LowPart == luid.LowPart
+                    && desc.AdapterLuid.HighPart == luid.HighPart
+                {
+                    return Self::from_output(&adapter, output, RetryPolicy::default());
+                }
+            }
+        }
+
+        Err("there is no adapter with the given luid".into())
+    }
+
+    // creates the monitor of an output of the adapter, the device is created on the same adapter
+    unsafe fn from_output(
+        adapter: &IDXGIAdapter1,
+        output: u32,
+        retry_policy: RetryPolicy,
+    ) -> Result<Arc<Self>, Box<dyn std::error::Error>> {
+        unsafe {
+            let adapter: IDXGIAdapter = adapter.cast()?;
+
+            //the driver type must be unknown when an adapter is given, the device is still a hardware device
+            let driver_type = D3D_DRIVER_TYPE_UNKNOWN;
+
+            //add support for duplication, and for the hardware encoders taking the textures when the driver has it
+            let flags = D3D11_CREATE_DEVICE_BGRA_SUPPORT | D3D11_CREATE_DEVICE_VIDEO_SUPPORT;
+
+            //use default
+            let p_feature_levels = None;
+
+            //device should be unwrappable after the following function call:
+            let mut device = None;
+
+            //this is okay to be null since we are using a NON-Software type for the driver_type
+            //A handle to a DLL that implements a software rasterizer. If DriverType is D3D_DRIVER_TYPE_SOFTWARE, Software must not be NULL.
+            let module_handle = HMODULE(std::ptr::null_mut());
+
+            let mut device_context: Option<ID3D11DeviceContext> = None;
+
+            let created = D3D11CreateDevice(
+                &adapter,
+                driver_type,
+                module_handle,
+                flags,
+                p_feature_levels,
+                D3D11_SDK_VERSION,
+                Some(&mut device),
+                None, //we do not need to determine the feature level at this time
+                Some(&mut device_context), //we do no tneed the device context at this time
+            );
+
+            //software adapters do not support video, duplication still works without it
+            if created.is_err() {
+                D3D11CreateDevice(
+                    &adapter,
+                    driver_type,
+                    module_handle,
+                    D3D11_CREATE_DEVICE_BGRA_SUPPORT,
+                    p_feature_levels,
+                    D3D11_SDK_VERSION,
+                    Some(&mut device),
+                    None,
+                    Some(&mut device_context),
+                )?;
+            }
+
+            let device: ID3D11Device = device.unwrap();
+            let device_context = device_context.unwrap();
+
+            //GPU frames can be downloaded from other threads while capturing, so the immediate context must be thread safe
+            if let Ok(multithread) = device_context.cast::<ID3D11Multithread>() {
+                let _ = multithread.SetMultithreadProtected(true);
+            }
+
+            let monitor_output: windows::Win32::Graphics::Dxgi::IDXGIOutput =
+                adapter.EnumOutputs(output)?;
+
+            let monitor_output1: IDXGIOutput1 = monitor_output.cast()?;
+
+            let desc = monitor_output1.GetDesc()?;
+
+            let dup_output = Self::duplicate_output(&monitor_output1, &device, &retry_policy)?;
+
+            //get the size of the monitor from the duplicated surface, this is always in physical pixels.
+            //the desktop coordinates are scaled down for DPI unaware processes so they cannot be used.
+            let dup_desc = dup_output.GetDesc();
+            let mode = dup_desc.ModeDesc;
+            let device_size = Dimensions {
+                width: mode.Width,
+                height: mode.Height,
+            };
+
+            let scale_factor = Self::query_scale_factor(desc.Monitor);
+
+            let (tx, rx) = mpsc::channel(DEFAULT_CHANNEL_CAPACITY);
+            let (gpu_tx, gpu_rx) = mpsc::channel(1);
+            let (event_tx, event_rx) = mpsc::channel(EVENT_CHANNEL_CAPACITY);
+
+            let surface_format = Self::surface_format_of(&dup_output);
+
+            let staging_texture =
+                Self::create_staging_texture(&device, &device_size, surface_format)?;
+
+            let name = String::from_utf16_lossy(&desc.DeviceName);
+
+            Ok(Arc::new_cyclic(|this| Self {
+                duplication_output: Mutex::new(Some(dup_output)),
+                output: monitor_output1,
+                device,
+                retry_policy: RwLock::new(retry_policy),
+                sender: RwLock::new(tx),
+                receiver: Arc::new(Mutex::new(rx)),
+                gpu_sender: gpu_tx,
+                gpu_receiver: Arc::new(Mutex::new(gpu_rx)),
+                gpu_textures: RwLock::new(vec![]),
+                frame: Arc::new(Mutex::new(MonitorFrame::default())),
+                device_context,
+                staging_texture: RwLock::new(staging_texture),
+                surface_format: RwLock::new(surface_format),
+                hdr_mode: RwLock::new(HdrMode::default()),
+                sdr_white_level: RwLock::new(
+                    get_sdr_white_level(&name).unwrap_or(DEFAULT_SDR_WHITE_NITS),
+                ),
+                desktop_size: device_size,
+                rotation: RwLock::new(Rotation::from_dxgi(dup_desc.Rotation)),
+                auto_rotate: RwLock::new(true),
+                scale_factor,
+                desktop_coordinates: desc.DesktopCoordinates,
+                output_size: RwLock::new(None),
+                scaler: RwLock::new(None),
+                constant_fps: RwLock::new(None),
+                frame_timeout: RwLock::new(None),
+                frame_callback: FrameCallback::default(),
+                change_detection: RwLock::new(None),
+                change_detector: Mutex::new(ChangeDetector::default()),
+                backpressure: RwLock::new(Backpressure::default()),
+                frame_rate: FpsMeter::default(),
+                state: StateCell::default(),
+                excluded_windows: RwLock::new(vec![]),
+                exclusion_color: RwLock::new([0, 0, 0, 255]),
+                padded_frames: RwLock::new(false),
+                click_overlay: RwLock::new(None),
+                cursor: RwLock::new(None),
+                cursor_events: RwLock::new(false),
+                event_sender: event_tx,
+                event_receiver: Arc::new(Mutex::new(event_rx)),
+                this: this.clone(),
+                sync: SyncChannel::default(),
+                name,
+            }))
+        }
+    }
+
+    /// ## From Point
+    ///
+    /// Create the Monitor of the display that contains a point of the virtual desktop, in desktop coordinates (the top left of the primary display is 0, 0).
+    ///
+    /// Returns CaptureError::MonitorNotFound if the point is not on any display.
+    pub unsafe fn from_point(x: i32, y: i32) -> Result<Arc<Self>, Box<dyn std::error::Error>> {
+        let monitor = unsafe { MonitorFromPoint(POINT { x, y }, MONITOR_DEFAULTTONULL) };
+
+        unsafe { Self::from_hmonitor(monitor) }
+    }
+
+    /// ## Containing Window
+    ///
+    /// Create the Monitor of the display a window is on, a window spanning several displays resolves to the display with the largest part of the window.
+    ///
+    /// Returns CaptureError::MonitorNotFound if the window is not on any display.
+    pub unsafe fn containing_window(hwnd: HWND) -> Result<Arc<Self>, Box<dyn std::error::Error>> {
+        let monitor = unsafe { MonitorFromWindow(hwnd, MONITOR_DEFAULTTONULL) };
+
+        unsafe { Self::from_hmonitor(monitor) }
+    }
+
+    // creates the monitor of a display handle from the adapter output showing the display
+    unsafe fn from_hmonitor(monitor: HMONITOR) -> Result<Arc<Self>, Box<dyn std::error::Error>> {
+        if monitor.is_invalid() {
+            return Err(CaptureError::MonitorNotFound.into());
+        }
+
+        unsafe {
+            for adapter in enum_adapters()? {
+                let mut index = 0;
+
+                while let Ok(output) = adapter.EnumOutputs(index) {
+                    if output.GetDesc()?.Monitor == monitor {
+                        return Self::from_output(&adapter, index, RetryPolicy::default());
+                    }
+
+                    index += 1;
+                }
+            }
+        }
+
+        Err(CaptureError::MonitorNotFound.into())
+    }
+
+    /// # Physical Size
+    ///
+    /// The size of the monitor in physical pixels, regardless of the DPI awareness of the process.
+    ///
+    /// The frames sent to the receiver are this size unless an output size is set, the width and height are swapped for portrait displays unless auto rotate is off.
+    pub fn physical_size(&self) -> Dimensions {
+        if self.auto_rotate() {
+            self.rotation().rotated_size(&self.desktop_size)
+        } else {
+            self.desktop_size.clone()
+        }
+    }
+
+    /// # Logical Size
+    ///
+    /// The size of the monitor in logical (scaled) pixels, for example 1707x1067 for a 2560x1600 display at 150%.
+    ///
+    /// This is the size a DPI unaware application sees for the monitor.
+    pub fn logical_size(&self) -> Dimensions {
+        let physical = self.physical_size();
+
+        Dimensions {
+            width: (physical.width as f32 / self.scale_factor).round() as u32,
+            height: (physical.height as f32 / self.scale_factor).round() as u32,
+        }
+    }
+
+    /// # Set Output Size
+    ///
+    /// Scales frames to the given size on the GPU before they are copied to system memory, so frames arrive on the receiver at this size and get_dimensions reflects it.
+    ///
+    /// The aspect mode decides what happens when the aspect ratio of the output differs from the monitor: stretch, letterbox (black bars) or crop.
+    ///
+    /// The size is of the delivered frames, after rotation. A width or height of 0 turns scaling off.
+    pub fn set_output_size(&self, output_size: Dimensions, aspect_mode: AspectMode) {
+        *self.output_size.write().unwrap() =
+            (output_size.width > 0 && output_size.height > 0).then_some((output_size, aspect_mode));
+    }
+
+    /// # Clear Output Size
+    ///
+    /// Sends frames at the full size of the monitor (default).
+    pub fn clear_output_size(&self) {
+        *self.output_size.write().unwrap() = None;
+    }
+
+    /// # Output Size
+    ///
+    /// The size frames are scaled to, None if frames are sent at the full size of the monitor.
+    pub fn output_size(&self) -> Option<Dimensions> {
+        self.output_size
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|(size, _)| size.clone())
+    }
+
+    /// # Aspect Mode
+    ///
+    /// How frames are fit into the output size, Stretch when no output size is set.
+    pub fn aspect_mode(&self) -> AspectMode {
+        self.output_size
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|(_, aspect_mode)| *aspect_mode)
+            .unwrap_or_default()
+    }
+
+    // scales the source into the output size on the GPU, None when no output size is set.
+    //
+    // the scaled texture is already rotated (unless auto rotate is off) and is reused for the next frame.
+    unsafe fn scale(
+        &self,
+        source: &ID3D11Texture2D,
+    ) -> Result<Option<(ID3D11Texture2D, Placement)>, windows::core::Error> {
+        let mut scaler = self.scaler.write().unwrap();
+
+        let Some((output_size, aspect_mode)) = self.output_size.read().unwrap().clone() else {
+            *scaler = None;
+            return Ok(None);
+        };
+
+        let rotation = if self.auto_rotate() {
+            self.rotation()
+        } else {
+            Rotation::Identity
+        };
+        let format = self.surface_format();
+
+        let outdated = !scaler.as_ref().is_some_and(|scaler| {
+            scaler.matches(
+                &self.desktop_size,
+                format,
+                rotation,
+                &output_size,
+                aspect_mode,
+            )
+        });
+
+        if outdated {
+            *scaler = Some(GpuScaler::new(
+                &self.device,
+                &self.device_context,
+                &self.desktop_size,
+                format,
+                format.to_dxgi(),
+                rotation,
+                &output_size,
+                aspect_mode,
+            )?);
+        }
+
+        let scaler = scaler.as_mut().unwrap();
+        let scaled = unsafe { scaler.scale(source)? }.clone();
+
+        Ok(Some((scaled, scaler.placement())))
+    }
+
+    /// # Rotation
+    ///
+    /// How the display is rotated in the display settings.
+    pub fn rotation(&self) -> Rotation {
+        *self.rotation.read().unwrap()
+    }
+
+    /// # Set Auto Rotate
+    ///
+    /// Sets if frames are rotated to match what is seen on the display (default) or delivered as the raw unrotated surface.
+    ///
+    /// Turning this off is useful when the rotation is handled elsewhere, for example on the GPU.
+    pub fn set_auto_rotate(&self, auto_rotate: bool) {
+        *self.auto_rotate.write().unwrap() = auto_rotate;
+    }
+
+    /// # Auto Rotate
+    ///
+    /// If frames are rotated to match the display orientation.
+    pub fn auto_rotate(&self) -> bool {
+        *self.auto_rotate.read().unwrap()
+    }
+
+    // rotates a frame to the display orientation, returned as is when there is nothing to do
+    fn rotate_frame(&self, data: Vec<u8>) -> Vec<u8> {
+        if !self.auto_rotate() {
+            return data;
+        }
+
+        self.rotation()
+            .rotate_pixels(
+                &data,
+                self.desktop_size.width as usize,
+                self.desktop_size.height as usize,
+                self.pixel_format().bytes_per_pixel(),
+            )
+            .unwrap_or(data)
+    }
+
+    /// # Scale Factor
+    ///
+    /// The DPI scaling of the monitor from the display settings, 1.0 at 100% and 1.5 at 150%.
+    pub fn scale_factor(&self) -> f32 {
+        self.scale_factor
+    }
+
+    // the scale factor of the effective DPI, 96 DPI is 100%
+    unsafe fn query_scale_factor(monitor: HMONITOR) -> f32 {
+        unsafe { Self::query_dpi(monitor) as f32 / 96.0 }
+    }
+
+    // queries the effective DPI of the monitor as a per monitor aware thread so the real value is returned
+    // regardless of the DPI awareness of the process, 96 when it cannot be queried.
+    pub(crate) unsafe fn query_dpi(monitor: HMONITOR) -> u32 {
+        let mut dpi_x: u32 = 0;
+        let mut dpi_y: u32 = 0;
+
+        let queried = unsafe {
+            let previous = SetThreadDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2);
+            let queried = GetDpiForMonitor(monitor, MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y);
+
+            if !previous.is_invalid() {
+                SetThreadDpiAwarenessContext(previous);
+            }
+
+            queried
+        };
+
+        if queried.is_err() || dpi_x == 0 {
+            return 96;
+        }
+
+        dpi_x
+    }
+
+    /// # Exclude Window
+    ///
+    /// Removes a window from the delivered frames.
+    ///
+    /// Windows owned by this process are excluded with display affinity (WDA_EXCLUDEFROMCAPTURE) so the content behind them is still captured.
+    ///
+    /// Any other window (or when display affinity is not supported) has its rectangle painted with the exclusion color in every frame, following the window as it moves.
+    ///
+    /// Excluded windows that are closed are silently dropped from the exclusion list.
+    pub fn exclude_window(&self, hwnd: HWND) -> Result<(), CaptureError> {
+        unsafe {
+            if !IsWindow(Some(hwnd)).as_bool() {
+                return Err(CaptureError::Windows(E_INVALIDARG.into()));
+            }
+
+            if Self::is_own_window(hwnd)
+                && SetWindowDisplayAffinity(hwnd, WDA_EXCLUDEFROMCAPTURE).is_ok()
+            {
+                return Ok(());
+            }
+        }
+
+        let mut excluded = self.excluded_windows.write().unwrap();
+
+        if !excluded.contains(&hwnd) {
+            excluded.push(hwnd);
+        }
+
+        Ok(())
+    }
+
+    /// # Include Window
+    ///
+    /// Reverts exclude_window, the window is shown in delivered frames again.
+    pub fn include_window(&self, hwnd: HWND) {
+        self.excluded_windows
+            .write()
+            .unwrap()
+            .retain(|w| *w != hwnd);
+
+        unsafe {
+            if Self::is_own_window(hwnd) {
+                let _ = SetWindowDisplayAffinity(hwnd, WDA_NONE);
+            }
+        }
+    }
+
+    /// # Set Exclusion Color
+    ///
+    /// The BGRA color that excluded windows are painted with, black by default.
+    ///
+    /// Raw HDR frames are always painted black.
+    pub fn set_exclusion_color(&self, bgra: [u8; 4]) {
+        *self.exclusion_color.write().unwrap() = bgra;
+    }
+
+    // if the window belongs to this process
+    unsafe fn is_own_window(hwnd: HWND) -> bool {
+        let mut process_id: u32 = 0;
+
+        unsafe {
+            GetWindowThreadProcessId(hwnd, Some(&mut process_id));
+
+            process_id == GetCurrentProcessId()
+        }
+    }
+
+    // maps the dirty and move rects of the surface onto the delivered frame, the destination of every move is added to the dirty rects.
+    //
+    // a move is not pixel exact once scaled, so scaled frames only report the dirty rects.
+    fn map_changed_rects(
+        &self,
+        dirty_rects: Vec<Rect>,
+        move_rects: Vec<MoveRect>,
+        placement: Option<&Placement>,
+    ) -> (Vec<Rect>, Vec<MoveRect>) {
+        let surface_width = self.desktop_size.width as usize;
+        let surface_height = self.desktop_size.height as usize;
+
+        let rotation = match placement {
+            Some(placement) => placement.rotation,
+            None if self.auto_rotate() => self.rotation(),
+            None => Rotation::Identity,
+        };
+
+        let to_frame = |rect: Rect| {
+            let (left, top, right, bottom) = rect.to_tuple();
+
+            //clamped to the surface so rotating cannot underflow
+            let rect = rotation.rotate_rect(
+                (
+                    left.min(surface_width),
+                    top.min(surface_height),
+                    right.min(surface_width),
+                    bottom.min(surface_height),
+                ),
+                surface_width,
+                surface_height,
+            );
+
+            match placement {
+                //scaling blends the neighbouring pixels, so the change can reach a pixel further
+                Some(placement) => {
+                    let (left, top, right, bottom) = placement.map_rect(rect);
+
+                    Rect::from_tuple((
+                        left.saturating_sub(1),
+                        top.saturating_sub(1),
+                        (right + 1).min(placement.width),
+                        (bottom + 1).min(placement.height),
+                    ))
+                }
+                None => Rect::from_tuple(rect),
+            }
+        };
+
+        let mapped_moves = match placement {
+            Some(_) => Vec::new(),
+            None => move_rects
+                .iter()
+                .map(|move_rect| {
+                    let destination = move_rect.destination;
+
+                    let source = to_frame(Rect {
+                        left: move_rect.source_x,
+                        top: move_rect.source_y,
+                        right: move_rect.source_x + destination.width(),
+                        bottom: move_rect.source_y + destination.height(),
+                    });
+
+                    MoveRect {
+                        source_x: source.left,
+                        source_y: source.top,
+                        destination: to_frame(destination),
+                    }
+                })
+                .collect(),
+        };
+
+        let dirty_rects = dirty_rects
+            .into_iter()
+            .chain(move_rects.iter().map(|move_rect| move_rect.destination))
+            .map(to_frame)
+            .filter(|rect| !rect.is_empty())
+            .collect();
+
+        (dirty_rects, mapped_moves)
+    }
+
+    // paints over the rectangles of excluded windows, dropping windows that have been closed.
+    //
+    // the placement is given when the frame was scaled on the GPU.
+    fn mask_excluded_windows(&self, data: &mut [u8], placement: Option<&Placement>) {
+        let mut excluded = self.excluded_windows.write().unwrap();
+
+        excluded.retain(|hwnd| unsafe { IsWindow(Some(*hwnd)).as_bool() });
+
+        let surface_width = self.desktop_size.width as usize;
+        let surface_height = self.desktop_size.height as usize;
+        let rotation = self.rotation();
+
+        //the size of the frame being masked
+        let (width, height) = match placement {
+            Some(placement) => (placement.width, placement.height),
+            None => (surface_width, surface_height),
+        };
+
+        //the desktop coordinates are in the rotated orientation
+        let rotated = rotation.rotated_size(&self.desktop_size);
+        let rotated_width = rotated.width as usize;
+        let rotated_height = rotated.height as usize;
+
+        if excluded.is_empty() || height == 0 {
+            return;
+        }
+
+        //frames may be padded, so the stride is taken from the buffer
+        let stride = data.len() / height;
+        let format = self.pixel_format();
+        let bytes_per_pixel = format.bytes_per_pixel();
+
+        let fill = match format {
+            PixelFormat::Bgra8 => self.exclusion_color.read().unwrap().to_vec(),
+            _ => vec![0u8; bytes_per_pixel],
+        };
+
+        //window rects are in desktop coordinates, which may be scaled for DPI unaware processes
+        let coords = &self.desktop_coordinates;
+        let scale_x = rotated_width as f32 / (coords.right - coords.left).max(1) as f32;
+        let scale_y = rotated_height as f32 / (coords.bottom - coords.top).max(1) as f32;
+
+        for hwnd in excluded.iter() {
+            let mut rect = RECT::default();
+
+            unsafe {
+                if IsIconic(*hwnd).as_bool() || GetWindowRect(*hwnd, &mut rect).is_err() {
+                    continue;
+                }
+            }
+
+            //map the window onto the frame, clamped to the monitor
+            let to_x = |x: i32| {
+                (((x - coords.left) as f32 * scale_x).max(0.0) as usize).min(rotated_width)
+            };
+            let to_y = |y: i32| {
+                (((y - coords.top) as f32 * scale_y).max(0.0) as usize).min(rotated_height)
+            };
+
+            let rect = (
+                to_x(rect.left),
+                to_y(rect.top),
+                to_x(rect.right),
+                to_y(rect.bottom),
+            );
+
+            let (left, top, right, bottom) = match placement {
+                //masking happens before the frame is rotated, so map back onto the surface
+                None => rotation.unrotate_rect(rect, surface_width, surface_height),
+                //scaled frames were rotated on the GPU
+                Some(placement) if placement.rotation == rotation => placement.map_rect(rect),
+                //scaled without rotating, so map onto the surface first
+                Some(placement) => {
+                    placement.map_rect(rotation.unrotate_rect(rect, surface_width, surface_height))
+                }
+            };
+
+            if left >= right || top >= bottom {
+                continue;
+            }
+
+            for y in top..bottom {
+                let row = &mut data
+                    [y * stride + left * bytes_per_pixel..y * stride + right * bytes_per_pixel];
+
+                for pixel in row.chunks_exact_mut(bytes_per_pixel) {
+                    pixel.copy_from_slice(&fill);
+                }
+            }
+        }
+    }
+
+    /// # Set Padded Frames
+    ///
+    /// Delivers the raw buffer of the mapped texture, where every row may be followed by padding (Frame::stride is then larger than width * bytes per pixel).
+    ///
+    /// Frames are tightly packed by default. Frames that are rotated or tone mapped on the CPU are always tightly packed.
+    pub fn set_padded_frames(&self, padded: bool) {
+        *self.padded_frames.write().unwrap() = padded;
+    }
+
+    /// # Padded Frames
+    ///
+    /// If frames are delivered with the row padding of the mapped texture.
+    pub fn padded_frames(&self) -> bool {
+        *self.padded_frames.read().unwrap()
+    }
+
+    /// # Dimensions
+    ///
+    /// The size of the delivered frames, same as get_dimensions without the Result.
+    ///
+    /// The size is kept with the monitor and only changes with the output size or when the display is rotated (sent as CaptureEvent::Resized), so this can be called every frame.
+    pub fn dimensions(&self) -> Dimensions {
+        self.get_frame_size()
+    }
+
+    // the size of the delivered frames
+    fn get_frame_size(&self) -> Dimensions {
+        self.output_size().unwrap_or_else(|| self.physical_size())
+    }
+
+    /// # Set Click Overlay
+    ///
+    /// Draws a fading ring around the cursor in the delivered frames every time a mouse button is pressed, useful for tutorial recordings.
+    ///
+    /// Clicks are detected with a low level mouse hook that runs while the overlay is on. Rings are only drawn on BGRA frames.
+    ///
+    /// Frames are only sent when the desktop changes unless constant frame rate mode is on, so use set_constant_fps for rings that fade smoothly.
+    pub fn set_click_overlay(&self, overlay: ClickOverlay) -> Result<(), CaptureError> {
+        let mut click_overlay = self.click_overlay.write().unwrap();
+
+        match click_overlay.as_mut() {
+            Some((current, _)) => *current = overlay,
+            None => *click_overlay = Some((overlay, ClickHook::install()?)),
+        }
+
+        Ok(())
+    }
+
+    /// # Disable Click Overlay
+    ///
+    /// Stops drawing clicks (default) and removes the mouse hook.
+    pub fn disable_click_overlay(&self) {
+        *self.click_overlay.write().unwrap() = None;
+    }
+
+    /// # Click Overlay
+    ///
+    /// The overlay drawn on clicks, None when it is off.
+    pub fn click_overlay(&self) -> Option<ClickOverlay> {
+        self.click_overlay
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|(overlay, _)| *overlay)
+    }
+
+    /// # Cursor Info
+    ///
+    /// The latest position and shape of the mouse cursor on this monitor, None until the first mouse update while capturing.
+    ///
+    /// Frames never contain the cursor, draw the shape at the position to show it (see CursorInfo).
+    pub fn cursor_info(&self) -> Option<CursorInfo> {
+        self.cursor.read().unwrap().clone()
+    }
+
+    /// # Set Cursor Events
+    ///
+    /// Sends a CaptureEvent::CursorChanged on the event receiver for every mouse update, so an overlay can follow the cursor faster than the desktop changes. Off by default.
+    ///
+    /// Cursor events fill up the event receiver quickly, read it continuously while they are on.
+    pub fn set_cursor_events(&self, enabled: bool) {
+        *self.cursor_events.write().unwrap() = enabled;
+    }
+
+    /// # Cursor Events
+    ///
+    /// If mouse updates are sent as events.
+    pub fn cursor_events(&self) -> bool {
+        *self.cursor_events.read().unwrap()
+    }
+
+    // takes the pointer position and, when it changed, the shape from an acquired frame
+    unsafe fn update_cursor(
+        &self,
+        duplication: &IDXGIOutputDuplication,
+        frame_info: &DXGI_OUTDUPL_FRAME_INFO,
+    ) -> Result<(), windows::core::Error> {
+        //the position is only valid when the mouse was updated
+        if frame_info.LastMouseUpdateTime == 0 {
+            return Ok(());
+        }
+
+        let mut cursor = self.cursor_info().unwrap_or_default();
+        let position = frame_info.PointerPosition;
+
+        cursor.position = (position.Position.x, position.Position.y);
+        cursor.visible = position.Visible.as_bool();
+
+        if frame_info.PointerShapeBufferSize > 0 {
+            let mut shape = vec![0u8; frame_info.PointerShapeBufferSize as usize];
+            let mut required = 0;
+            let mut shape_info = DXGI_OUTDUPL_POINTER_SHAPE_INFO::default();
+
+            unsafe {
+                duplication.GetFramePointerShape(
+                    shape.len() as u32,
+                    shape.as_mut_ptr() as *mut _,
+                    &mut required,
+                    &mut shape_info,
+                )?;
+            }
+
+            shape.truncate(required as usize);
+            cursor.set_shape(shape, &shape_info);
+        }
+
+        *self.cursor.write().unwrap() = Some(cursor.clone());
+
+        if self.cursor_events() {
+            self.send_event(CaptureEvent::CursorChanged(cursor));
+        }
+
+        Ok(())
+    }
+
+    // maps a point in desktop coordinates onto the delivered frame
+    fn desktop_point_to_frame(&self, x: i32, y: i32) -> (f32, f32) {
+        let coords = &self.desktop_coordinates;
+        let rotation = self.rotation();
+        let rotated = rotation.rotated_size(&self.desktop_size);
+
+        //desktop coordinates are in the rotated orientation and may be scaled for DPI unaware processes
+        let mut point = (
+            (x - coords.left) as f32 * rotated.width as f32
+                / (coords.right - coords.left).max(1) as f32,
+            (y - coords.top) as f32 * rotated.height as f32
+                / (coords.bottom - coords.top).max(1) as f32,
+        );
+
+        if !self.auto_rotate() {
+            point = rotation.unrotate_point(
+                point,
+                self.desktop_size.width as f32,
+                self.desktop_size.height as f32,
+            );
+        }
+
+        //scaled frames were fit into the output size
+        if self.output_size().is_some()
+            && let Some(scaler) = self.scaler.read().unwrap().as_ref()
+        {
+            point = scaler.placement().map_point(point);
+        }
+
+        point
+    }
+
+    // draws the rings of recent clicks on this monitor onto a delivered frame
+    fn draw_click_overlay(&self, data: &mut [u8]) {
+        let Some(overlay) = self.click_overlay() else {
+            return;
+        };
+
+        if self.pixel_format() != PixelFormat::Bgra8 {
+            return;
+        }
+
+        let coords = self.desktop_coordinates;
+        let Dimensions { width, height } = self.get_frame_size();
+
+        for click in recent_clicks(overlay.duration) {
+            let POINT { x, y } = click.position;
+
+            if x < coords.left || x >= coords.right || y < coords.top || y >= coords.bottom {
+                continue;
+            }
+
+            let strength = 1.0
+                - click.at.elapsed().as_secs_f32()
+                    / overlay.duration.as_secs_f32().max(f32::EPSILON);
+
+            overlay.draw_ring(
+                data,
+                width as usize,
+                height as usize,
+                self.desktop_point_to_frame(x, y),
+                strength,
+            );
+        }
+    }
+
+    /// # Set Constant FPS
+    ///
+    /// Delivers exactly fps frames per second on a steady clock instead of a frame every time the desktop changes.
+    ///
+    /// The last frame is sent again when nothing changed and extra desktop updates are dropped, frame timestamps are the frame index divided by fps.
+    ///
+    /// An fps of 0 turns constant frame rate mode off, this takes effect the next time capturing starts.
+    pub fn set_constant_fps(&self, fps: u32) {
+        *self.constant_fps.write().unwrap() = (fps > 0).then_some(fps);
+    }
+
+    /// # Disable Constant FPS
+    ///
+    /// Sends a frame every time the desktop changes (default).
+    pub fn disable_constant_fps(&self) {
+        *self.constant_fps.write().unwrap() = None;
+    }
+
+    /// # Constant FPS
+    ///
+    /// The frame rate of constant frame rate mode, None if frames are sent when the desktop changes.
+    pub fn constant_fps(&self) -> Option<u32> {
+        *self.constant_fps.read().unwrap()
+    }
+
+    /// # Set Change Detection
+    ///
+    /// Compares the samples of every frame with the previous frame, flagging frames that did not change with Frame::is_duplicate or skipping them (see ChangeDetection).
+    ///
+    /// Only frames Desktop Duplication has no change information for (no dirty or move rects and a new present) are sampled, frames it delivered
+    /// for a cursor change only are flagged without it. Skipping duplicates in constant frame rate mode drops the repeated frames.
+    pub fn set_change_detection(&self, detection: ChangeDetection) {
+        *self.change_detection.write().unwrap() = Some(detection);
+    }
+
+    /// # Disable Change Detection
+    ///
+    /// Frames are not compared (default), only the duplicates Desktop Duplication reports are flagged.
+    pub fn disable_change_detection(&self) {
+        *self.change_detection.write().unwrap() = None;
+    }
+
+    /// # Change Detection
+    ///
+    /// How frames are compared with the previous frame, None if they are not.
+    pub fn change_detection(&self) -> Option<ChangeDetection> {
+        *self.change_detection.read().unwrap()
+    }
+
+    /// # Set HDR Mode
+    ///
+    /// Sets what is delivered when the desktop surface is HDR, tone mapped 8 bit BGRA (default) or the raw 10/16 bit data.
+    pub fn set_hdr_mode(&self, hdr_mode: HdrMode) {
+        *self.hdr_mode.write().unwrap() = hdr_mode;
+    }
+
+    /// # HDR Mode
+    ///
+    /// What is delivered when the desktop surface is HDR.
+    pub fn hdr_mode(&self) -> HdrMode {
+        *self.hdr_mode.read().unwrap()
+    }
+
+    /// # Surface Format
+    ///
+    /// The format of the duplicated desktop surface, 10 or 16 bit on HDR displays.
+    pub fn surface_format(&self) -> PixelFormat {
+        *self.surface_format.read().unwrap()
+    }
+
+    /// # Supported Outputs
+    ///
+    /// The outputs the monitor can deliver, RGB32 and on an HDR display the raw format of the surface (see HdrMode::Raw).
+    ///
+    /// HDR can be toggled in the display settings, which changes the list.
+    pub fn supported_outputs(&self) -> Vec<Output> {
+        let surface_format = self.surface_format();
+
+        if surface_format.is_hdr() {
+            vec![Output::RGB32, surface_format.into()]
+        } else {
+            vec![Output::RGB32]
+        }
+    }
+
+    /// # Set Channel Capacity
+    ///
+    /// How many frames are buffered for the receiver, 1 by default (0 is taken as 1).
+    ///
+    /// A small buffer has the lowest latency, which suits a live preview. The monitor waits for the consumer when the buffer is full, desktop updates meanwhile are counted in skipped_since_last of the next frame.
+    /// A recorder can use a larger buffer, so a slow write does not skip frames, at the cost of that many frames of latency.
+    ///
+    /// Must be called before capturing starts and while no task waits on the receiver, else CaptureError::Other is returned. Frames still in the buffer are dropped.
+    /// Textures of the GPU receiver are always buffered one at a time.
+    pub fn set_channel_capacity(&self, capacity: usize) -> Result<(), CaptureError> {
+        replace_channel(&self.sender, &self.receiver, &self.state, capacity)
+    }
+
+    /// # Channel Capacity
+    ///
+    /// How many frames are buffered for the receiver.
+    pub fn channel_capacity(&self) -> usize {
+        self.sender.read().unwrap().max_capacity()
+    }
+
+    /// # Set Backpressure
+    ///
+    /// What happens to a new frame when the buffer of the receiver is full, Backpressure::Block by default.
+    ///
+    /// With Block the monitor waits for the consumer, a stalled consumer stalls the capture loop (and stop_capturing takes effect only once a frame is taken).
+    /// DropNewest and DropOldest never wait, a dropped frame is traced but not counted in skipped_since_last, which only counts desktop updates.
+    pub fn set_backpressure(&self, backpressure: Backpressure) {
+        *self.backpressure.write().unwrap() = backpressure;
+    }
+
+    /// # Backpressure
+    ///
+    /// What happens to a new frame when the buffer of the receiver is full.
+    pub fn backpressure(&self) -> Backpressure {
+        *self.backpressure.read().unwrap()
+    }
+
+    /// # Actual FPS
+    ///
+    /// The rate frames were really delivered at, measured over the latest frames (see set_fps_window) and reset when capturing starts.
+    ///
+    /// Monitors only deliver when the desktop changes (unless the frame rate is constant), so a still desktop has a low rate. Frames dropped by backpressure are counted, frames skipped by change detection are not.
+    pub fn actual_fps(&self) -> FrameRate {
+        self.frame_rate.get()
+    }
+
+    /// # Set FPS Window
+    ///
+    /// Sets the number of latest frames actual_fps is measured over, 60 by default (at least 2). A short window shows brief dips, a long one smooths them out.
+    pub fn set_fps_window(&self, frames: usize) {
+        self.frame_rate.set_window(frames);
+    }
+
+    /// # FPS Window
+    ///
+    /// The number of latest frames actual_fps is measured over.
+    pub fn fps_window(&self) -> usize {
+        self.frame_rate.window()
+    }
+
+    /// # Pixel Format
+    ///
+    /// The format of the frames sent to the receiver.
+    ///
+    /// This is always Bgra8 unless the surface is HDR and the HDR mode is Raw.
+    pub fn pixel_format(&self) -> PixelFormat {
+        match self.hdr_mode() {
+            HdrMode::Raw => self.surface_format(),
+            HdrMode::ToneMap => PixelFormat::Bgra8,
+        }
+    }
+
+    /// # Set SDR White Level
+    ///
+    /// Overrides the brightness (in nits) that becomes white when tone mapping, by default this is read from the display settings.
+    pub fn set_sdr_white_level(&self, nits: f32) {
+        *self.sdr_white_level.write().unwrap() = nits;
+    }
+
+    /// # SDR White Level
+    ///
+    /// The brightness (in nits) that becomes white when tone mapping.
+    pub fn sdr_white_level(&self) -> f32 {
+        *self.sdr_white_level.read().unwrap()
+    }
+
+    /// # Tone Map
+    ///
+    /// Converts a frame of this monitor to tightly packed 8 bit BGRA with the SDR white level of the monitor, see convert::hdr_to_bgra.
+    ///
+    /// With HdrMode::Raw this gives an SDR preview next to the full range frame, frames that are already BGRA are copied unchanged.
+    /// The frame is read in the current pixel format, frames captured before HDR was toggled fail or convert wrongly.
+    pub fn tone_map(&self, frame: &Frame) -> Result<Vec<u8>, CaptureError> {
+        let size = self
+            .get_dimensions()
+            .map_err(|e| CaptureError::Other(e.to_string()))?;
+
+        crate::convert::hdr_to_bgra(
+            &frame.data,
+            size.width,
+            size.height,
+            frame.stride,
+            self.pixel_format(),
+            self.sdr_white_level(),
+        )
+    }
+
+    /// # Set Retry Policy
+    ///
+    /// Sets how transient duplication failures are retried while capturing.
+    pub fn set_retry_policy(&self, retry_policy: RetryPolicy) {
+        *self.retry_policy.write().unwrap() = retry_policy;
+    }
+
+    /// # Retry Policy
+    ///
+    /// The policy used to retry transient duplication failures.
+    pub fn retry_policy(&self) -> RetryPolicy {
+        *self.retry_policy.read().unwrap()
+    }
+
+    // attempts to duplicate the output once.
+    //
+    // Ok(None) means the failure was transient and the attempt may be retried, unless it was the last attempt.
+    unsafe fn try_duplicate(
+        output: &IDXGIOutput1,
+        device: &ID3D11Device,
+        _attempt: u32,
+        last_attempt: bool,
+    ) -> Result<Option<IDXGIOutputDuplication>, CaptureError> {
+        let e = match unsafe { Self::duplicate_once(output, device) } {
+            Ok(dup) => return Ok(Some(dup)),
+            Err(e) => e,
+        };
 
-    //texture that is used to copy from the GPU to CPU, expensive, so made on init
-    staging_texture: ID3D11Texture2D,
+        //the max number of duplications has been reached, retrying will not help.
+        if e.code() == DXGI_ERROR_NOT_CURRENTLY_AVAILABLE {
+            trace_event!(error, hresult = %e.code(), "desktop duplication unavailable");
+            return Err(CaptureError::DuplicationUnavailable);
+        }
 
-    pub desktop_size: Dimensions,
+        //access denied and access lost occur while the secure desktop (UAC, lock screen) is shown
+        let transient = e.code() == E_ACCESSDENIED
+            || e.code() == DXGI_ERROR_ACCESS_LOST
+            || e.code() == DXGI_ERROR_SESSION_DISCONNECTED;
 
-    pub name: String,
-}
+        if !transient || last_attempt {
+            trace_event!(error, hresult = %e.code(), attempt = _attempt, "DuplicateOutput failed");
+            return Err(e.into());
+        }
 
-impl Monitor {
-    /// # From Monitor Info
-    ///
-    /// Create device information for a given monitor of your system.
-    ///
-    /// Provides a Monitor struct that has the ability to duplicate the data and do other manipulation.
-    pub unsafe fn from_monitor_info(
-        monitor_info: MonitorInfo,
-    ) -> Result<Arc<Self>, Box<dyn std::error::Error>> {
-        let index = monitor_info.index;
+        trace_event!(warn, hresult = %e.code(), attempt = _attempt, "DuplicateOutput failed, retrying");
 
-        unsafe { Self::from_monitor(index) }
+        Ok(None)
     }
 
-    /// ## From Monitor
-    ///
-    /// Create device information for a given monitor of your system.
-    ///
-    /// Provides a Monitor struct that has the ability to duplicate the data and do other manipulation.
-    pub unsafe fn from_monitor(monitor: u32) -> Result<Arc<Self>, Box<dyn std::error::Error>> {
+    // duplicates the output allowing HDR surfaces when the output supports it (IDXGIOutput5)
+    unsafe fn duplicate_once(
+        output: &IDXGIOutput1,
+        device: &ID3D11Device,
+    ) -> Result<IDXGIOutputDuplication, windows::core::Error> {
         unsafe {
-            let max_monitors = get_monitor_count() as u32;
+            let Ok(output5) = output.cast::<IDXGIOutput5>() else {
+                return output.DuplicateOutput(device);
+            };
 
-            if monitor > max_monitors {
-                return Err(format!(
-                    "monitor index ({monitor}) fell outside of the max range of {max_monitors}"
-                )
-                .into());
+            //ordered by preference, the desktop format is used if it is in the list.
+            let supported_formats = [
+                DXGI_FORMAT_R16G16B16A16_FLOAT,
+                DXGI_FORMAT_R10G10B10A2_UNORM,
+                DXGI_FORMAT_B8G8R8A8_UNORM,
+            ];
+
+            match output5.DuplicateOutput1(device, 0, &supported_formats) {
+                //DuplicateOutput1 is refused for processes that are not per monitor DPI aware
+                Err(e) if e.code() == E_INVALIDARG || e.code() == DXGI_ERROR_UNSUPPORTED => {
+                    output.DuplicateOutput(device)
+                }
+                result => result,
             }
+        }
+    }
 
-            //choose default adapater
-            let adapter = None;
-
-            //A hardware driver, which implements Direct3D features in hardware.
-            //This is the primary driver that you should use in your Direct3D applications because it provides the best performance.
-            let driver_type = D3D_DRIVER_TYPE_HARDWARE;
+    // the format of the duplicated surface, BGRA if it is not a known format
+    unsafe fn surface_format_of(duplication: &IDXGIOutputDuplication) -> PixelFormat {
+        let desc = unsafe { duplication.GetDesc() };
 
-            //add support for duplication
-            let flags = D3D11_CREATE_DEVICE_BGRA_SUPPORT;
+        PixelFormat::from_dxgi(desc.ModeDesc.Format).unwrap_or(PixelFormat::Bgra8)
+    }
 
-            //use default
-            let p_feature_levels = None;
+    // duplicates the output, blocking the thread between retries
+    unsafe fn duplicate_output(
+        output: &IDXGIOutput1,
+        device: &ID3D11Device,
+        retry_policy: &RetryPolicy,
+    ) -> Result<IDXGIOutputDuplication, CaptureError> {
+        retry_policy.retry(
+            |attempt, last_attempt| unsafe {
+                Self::try_duplicate(output, device, attempt, last_attempt)
+            },
+            std::thread::sleep,
+        )
+    }
 
-            //device should be unwrappable after the following function call:
-            let mut device = None;
+    // releases the lost duplication and duplicates the output again, waiting between retries.
+    //
+    // retries for the max duration of the retry policy (or its attempts), sending Recovering and Recovered events.
+    // a capture stopped while it waits ends the retries, the duplication is made again when capturing starts.
+    async unsafe fn reduplicate(&self) -> Result<(), CaptureError> {
+        let retry_policy = self.retry_policy();
+        let started = Instant::now();
+
+        //the old duplication must be released before a new one can be made, the lock is not held while waiting.
+        *self.duplication_output.lock().await = None;
+
+        self.send_event(CaptureEvent::Recovering);
+
+        let size = self.get_frame_size();
+        let mut attempt = 0;
+
+        loop {
+            if !self.is_capturing() {
+                trace_event!(
+                    info,
+                    attempt,
+                    "capture stopped while recovering the duplication"
+                );
+                return Ok(());
+            }
 
-            //this is okay to be null since we are using a NON-Software type for the driver_type
-            //A handle to a DLL that implements a software rasterizer. If DriverType is D3D_DRIVER_TYPE_SOFTWARE, Software must not be NULL.
-            let module_handle = HMODULE(std::ptr::null_mut());
+            let last_attempt = match retry_policy.max_duration {
+                Some(max_duration) => started.elapsed() >= max_duration,
+                None => retry_policy.is_last_attempt(attempt),
+            };
 
-            let mut device_context: Option<ID3D11DeviceContext> = None;
+            if let Some(dup) =
+                unsafe { Self::try_duplicate(&self.output, &self.device, attempt, last_attempt)? }
+            {
+                //HDR may have been toggled, which changes the surface format.
+                let surface_format = unsafe { Self::surface_format_of(&dup) };
 
-            D3D11CreateDevice(
-                adapter,
-                driver_type,
-                module_handle,
-                flags,
-                p_feature_levels,
-                D3D11_SDK_VERSION,
-                Some(&mut device),
-                None, //we do not need to determine the feature level at this time
-                Some(&mut device_context), //we do no tneed the device context at this time
-            )?;
+                //the staging texture follows the copied frame, so it is remade on the next copy
+                *self.surface_format.write().unwrap() = surface_format;
 
-            let device: ID3D11Device = device.unwrap();
+                //the display may have been rotated
+                *self.rotation.write().unwrap() =
+                    Rotation::from_dxgi(unsafe { dup.GetDesc() }.Rotation);
 
-            let dxgi_device: IDXGIDevice = device.cast()?;
-            let adapter: IDXGIAdapter = dxgi_device.GetAdapter()?;
+                if let Some(nits) = unsafe { get_sdr_white_level(&self.name) } {
+                    self.set_sdr_white_level(nits);
+                }
 
-            let monitor_output: windows::Win32::Graphics::Dxgi::IDXGIOutput =
-                adapter.EnumOutputs(monitor)?;
+                *self.duplication_output.lock().await = Some(dup);
 
-            let monitor_output1: IDXGIOutput1 = monitor_output.cast()?;
+                self.send_event(CaptureEvent::Recovered {
+                    downtime: started.elapsed(),
+                });
 
-            let desc = monitor_output1.GetDesc()?;
+                let resized = self.get_frame_size();
 
-            //get the size of the monitor
-            let coordinates = &desc.DesktopCoordinates;
-            let device_size = Dimensions {
-                width: (coordinates.right - coordinates.left) as u32,
-                height: (coordinates.bottom - coordinates.top) as u32,
-            };
+                if resized != size {
+                    self.send_event(CaptureEvent::Resized {
+                        width: resized.width,
+                        height: resized.height,
+                    });
+                }
 
-            let dup_output = monitor_output1.DuplicateOutput(&device)?;
+                return Ok(());
+            }
 
-            let (tx, rx) = mpsc::channel(1);
+            let mut delay = retry_policy.delay_for(attempt).min(MAX_RECOVERY_DELAY);
 
-            let staging_texture = Self::create_staging_texture(&device, &device_size)?;
+            //the last attempt is made right when the max duration runs out
+            if let Some(max_duration) = retry_policy.max_duration {
+                delay = delay.min(max_duration.saturating_sub(started.elapsed()));
+            }
 
-            Ok(Arc::new(Self {
-                duplication_output: dup_output,
-                sender: tx,
-                receiver: Arc::new(Mutex::new(rx)),
-                is_sending: Arc::new(Mutex::new(false)),
-                frame: Arc::new(Mutex::new(MonitorFrame::default())),
-                device_context: device_context.unwrap(),
-                staging_texture,
-                desktop_size: device_size,
-                name: String::from_utf16_lossy(&desc.DeviceName),
-            }))
+            tokio::time::sleep(delay).await;
+            attempt += 1;
         }
     }
 
+    /// # Clone Event Receiver
+    ///
+    /// The receiver of the events of the monitor, such as CaptureEvent::Recovering and CaptureEvent::Recovered when the duplication is lost to a UAC prompt, the lock screen or a fullscreen transition.
+    ///
+    /// Events are dropped once 16 are waiting to be read.
+    pub fn clone_event_receiver(&self) -> Arc<Mutex<Receiver<CaptureEvent>>> {
+        self.event_receiver.clone()
+    }
+
+    // sends an event without waiting, dropping it when the receiver is full
+    fn send_event(&self, event: CaptureEvent) {
+        let _ = self.event_sender.try_send(event);
+    }
+
+    // clones the current duplication out of the lock
+    async fn duplication(&self) -> Result<IDXGIOutputDuplication, windows::core::Error> {
+        self.duplication_output
+            .lock()
+            .await
+            .clone()
+            .ok_or_else(|| DXGI_ERROR_ACCESS_LOST.into())
+    }
+
     /// creates a texture that can be used to copy GPU based monitor data to the CPU
     fn create_staging_texture(
         device: &ID3D11Device,
         device_size: &Dimensions,
+        format: PixelFormat,
     ) -> Result<ID3D11Texture2D, windows::core::Error> {
         let desc = D3D11_TEXTURE2D_DESC {
             Width: device_size.width,
             Height: device_size.height,
             MipLevels: 1,
             ArraySize: 1,
-            Format: DXGI_FORMAT_B8G8R8A8_UNORM, // Standard for Desktop Duplication
+            Format: format.to_dxgi(), // Must match the duplicated surface
             SampleDesc: DXGI_SAMPLE_DESC {
                 Count: 1,
                 Quality: 0,
@@ -185,14 +1571,25 @@ impl Monitor {
 
     /// Using the device's context map the staging texture to contain the monitor frame data
     ///
-    /// Once mapped copy from the raw frame data into a Vec<u8>
-    fn map_resource(&self) -> Result<Vec<u8>, windows::core::Error> {
+    /// Once mapped copy from the raw frame data into a Vec<u8>, HDR surfaces are tone mapped to tightly packed BGRA unless the HDR mode is Raw.
+    fn map_resource(
+        &self,
+        staging_texture: &ID3D11Texture2D,
+    ) -> Result<Vec<u8>, windows::core::Error> {
         //we now have access to the data
         let mut mapped_resource = D3D11_MAPPED_SUBRESOURCE::default();
+        let surface_format = self.surface_format();
+        let tone_map = surface_format.is_hdr() && self.hdr_mode() == HdrMode::ToneMap;
+
+        //the staging texture is either the size of the surface or the output size
+        let mut desc = D3D11_TEXTURE2D_DESC::default();
+        unsafe { staging_texture.GetDesc(&mut desc) };
+        let width = desc.Width as usize;
+        let height = desc.Height as usize;
 
         unsafe {
             self.device_context.Map(
-                &self.staging_texture,
+                staging_texture,
                 0,
                 D3D11_MAP_READ,
                 0,
@@ -201,18 +1598,36 @@ impl Monitor {
         }
 
         let row_pitch = mapped_resource.RowPitch as usize;
-        let total_size_bytes = row_pitch * self.desktop_size.height as usize;
+        let total_size_bytes = row_pitch * height;
 
         let data: Option<Vec<u8>>;
 
         unsafe {
-            data = Some(
-                std::slice::from_raw_parts(mapped_resource.pData as *const u8, total_size_bytes)
-                    .to_vec(),
-            );
+            let raw =
+                std::slice::from_raw_parts(mapped_resource.pData as *const u8, total_size_bytes);
+
+            data = Some(if tone_map {
+                tone_map_to_bgra(
+                    raw,
+                    row_pitch,
+                    width,
+                    height,
+                    surface_format,
+                    self.sdr_white_level(),
+                )
+            } else if self.padded_frames() {
+                raw.to_vec()
+            } else {
+                strip_padding(
+                    raw,
+                    row_pitch,
+                    width * surface_format.bytes_per_pixel(),
+                    height,
+                )
+            });
 
             //release all data.
-            self.device_context.Unmap(&self.staging_texture, 0);
+            self.device_context.Unmap(staging_texture, 0);
         }
 
         if data.is_none() {
@@ -222,28 +1637,335 @@ impl Monitor {
         Ok(data.unwrap())
     }
 
+    // a shared texture that no delivered GPU frame is using, a new texture is made when all of them are held
+    fn free_shared_texture(&self) -> Result<Arc<SharedTexture>, windows::core::Error> {
+        let format = self.surface_format();
+        let mut textures = self.gpu_textures.write().unwrap();
+
+        //the surface format changes when HDR is toggled
+        textures.retain(|texture| texture.format() == format);
+
+        if let Some(texture) = textures
+            .iter()
+            .find(|texture| Arc::strong_count(texture) == 1)
+        {
+            return Ok(texture.clone());
+        }
+
+        let texture = Arc::new(SharedTexture::new(
+            &self.device,
+            &self.desktop_size,
+            format,
+        )?);
+        textures.push(texture.clone());
+
+        Ok(texture)
+    }
+
+    // a staging texture the size and format of the source, the current one is replaced when it does not match
+    fn staging_texture_for(
+        &self,
+        source: &ID3D11Texture2D,
+    ) -> Result<ID3D11Texture2D, windows::core::Error> {
+        let mut source_desc = D3D11_TEXTURE2D_DESC::default();
+        let mut staging_desc = D3D11_TEXTURE2D_DESC::default();
+        let mut staging_texture = self.staging_texture.write().unwrap();
+
+        unsafe {
+            source.GetDesc(&mut source_desc);
+            staging_texture.GetDesc(&mut staging_desc);
+        }
+
+        if (source_desc.Width, source_desc.Height, source_desc.Format)
+            != (staging_desc.Width, staging_desc.Height, staging_desc.Format)
+        {
+            let size = Dimensions {
+                width: source_desc.Width,
+                height: source_desc.Height,
+            };
+            let format = PixelFormat::from_dxgi(source_desc.Format).unwrap_or(PixelFormat::Bgra8);
+
+            *staging_texture = Self::create_staging_texture(&self.device, &size, format)?;
+        }
+
+        Ok(staging_texture.clone())
+    }
+
+    // copies the acquired image (scaled when an output size is set) into the staging texture
+    unsafe fn copy_to_staging(
+        &self,
+        source: &ID3D11Texture2D,
+    ) -> Result<(ID3D11Texture2D, Option<Placement>), windows::core::Error> {
+        let (copy_source, placement) = match unsafe { self.scale(source)? } {
+            Some((scaled, placement)) => (scaled, Some(placement)),
+            None => (source.clone(), None),
+        };
+
+        let staging_texture = self.staging_texture_for(&copy_source)?;
+
+        unsafe {
+            self.device_context
+                .CopyResource(&staging_texture, &copy_source);
+
+            //flush the context of the copied resource.
+            self.device_context.Flush();
+        }
+
+        Ok((staging_texture, placement))
+    }
+
     // releases the frames and readies the monitor for another batch of duplication
     async unsafe fn release_frames(&self) -> Result<(), windows::core::Error> {
+        let duplication = self.duplication().await?;
+
         unsafe {
             //release the frames
-            self.duplication_output.ReleaseFrame()?;
+            duplication.ReleaseFrame()?;
         }
         self.frame.lock().await.acquired_image = None;
         Ok(())
     }
 
+    // acquires the next desktop frame, copies it to the CPU (scaled on the GPU when an output size is set)
+    // and applies the exclusion mask and rotation.
+    //
+    // returns None if the desktop did not change within timeout_ms or the duplication had to be recovered.
+    async unsafe fn capture_frame(&self, timeout_ms: u32) -> Result<Option<Frame>, CaptureError> {
+        let monitor_frame = match unsafe { self.acquire_data(timeout_ms).await } {
+            Ok(monitor_frame) => monitor_frame,
+            //this is forgiveable, just no new data was accquired within the specified window time.
+            Err(e) if e.code() == DXGI_ERROR_WAIT_TIMEOUT.into() => return Ok(None),
+            //the duplication was lost (secure desktop, mode change...), duplicate the output again.
+            Err(e) if e.code() == DXGI_ERROR_ACCESS_LOST => {
+                trace_event!(warn, hresult = %e.code(), "duplication lost, recovering");
+                unsafe { self.reduplicate().await? };
+                trace_event!(info, "duplication recovered");
+                return Ok(None);
+            }
+            // this is another error.
+            Err(e) => {
+                trace_event!(error, hresult = %e.code(), "capture loop stopped");
+                return Err(e.into());
+            }
+        };
+
+        //desktop updates that were accumulated into this frame were never delivered
+        let skipped_since_last =
+            (monitor_frame.frame_info.AccumulatedFrames as u64).saturating_sub(1);
+        let presentation_time = presentation_time(&monitor_frame);
+
+        //without a new present only the cursor changed, the desktop image is the same
+        let is_duplicate = monitor_frame.frame_info.LastPresentTime == 0;
+
+        //in the unrotated surface, mapped onto the delivered frame once it is known how it was scaled
+        let dirty_rects: Vec<Rect> = monitor_frame.dirty_buffer
+            [..monitor_frame.dirty_count as usize]
+            .iter()
+            .map(Rect::from_dxgi)
+            .collect();
+        let move_rects: Vec<MoveRect> = monitor_frame.moved_buffer
+            [..monitor_frame.moved_count as usize]
+            .iter()
+            .map(MoveRect::from_dxgi)
+            .collect();
+
+        // update our current monitor frame with the newly acquired one
+        let mut frame_lock = self.frame.lock().await;
+        *frame_lock = monitor_frame;
+
+        let copied = unsafe { self.copy_to_staging(frame_lock.acquired_image.as_ref().unwrap()) };
+        drop(frame_lock);
+
+        let mapped = copied.and_then(|(staging_texture, placement)| {
+            Ok((self.map_resource(&staging_texture)?, placement))
+        });
+
+        //the data has been copied, the frame can be given back to the desktop
+        unsafe { self.release_frames().await? };
+
+        let (mut data, placement) = mapped?;
+
+        self.mask_excluded_windows(&mut data, placement.as_ref());
+
+        //scaled frames were already rotated on the GPU
+        let data = match placement {
+            Some(_) => data,
+            None => self.rotate_frame(data),
+        };
+
+        let (dirty_rects, move_rects) =
+            self.map_changed_rects(dirty_rects, move_rects, placement.as_ref());
+
+        //every row of the frame has the same length, padded or not
+        let height = self.get_frame_size().height.max(1) as usize;
+
+        Ok(Some(Frame {
+            stride: data.len() / height,
+            data,
+            skipped_since_last,
+            timestamp: Duration::ZERO,
+            presentation_time,
+            dirty_rects,
+            move_rects,
+            is_duplicate,
+        }))
+    }
+
+    // sends a frame every time the desktop changes
+    async unsafe fn send_frames(&self) -> Result<(), CaptureError> {
+        let started = Instant::now();
+        let mut dropped = None;
+
+        while self.is_capturing() {
+            let Some(mut frame) = (unsafe { self.capture_frame(ACQUIRE_TIMEOUT_MS).await? }) else {
+                continue;
+            };
+
+            trace_event!(trace, bytes = frame.len(), "sending monitor frame");
+
+            frame.timestamp = started.elapsed();
+
+            self.send_frame(frame, &mut dropped).await?;
+        }
+
+        Ok(())
+    }
+
+    // sends a frame every 1/fps seconds, the last frame is sent again when the desktop did not change
+    // and updates between two ticks are dropped.
+    async unsafe fn send_constant_frames(&self, fps: u32) -> Result<(), CaptureError> {
+        let mut interval = tokio::time::interval(Duration::from_secs_f64(1.0 / fps as f64));
+        let mut latest: Option<Frame> = None;
+        let mut sent: u64 = 0;
+        let mut dropped = None;
+
+        while self.is_capturing() {
+            interval.tick().await;
+
+            //wait for the first frame, after that only take what changed since the last tick
+            let timeout_ms = if latest.is_none() {
+                ACQUIRE_TIMEOUT_MS
+            } else {
+                0
+            };
+
+            let mut skipped_since_last = 0;
+            let mut is_duplicate = true;
+
+            if let Some(frame) = unsafe { self.capture_frame(timeout_ms).await? } {
+                skipped_since_last = frame.skipped_since_last;
+                is_duplicate = frame.is_duplicate;
+                latest = Some(frame);
+            }
+
+            let Some(latest) = &latest else {
+                continue;
+            };
+
+            //repeated frames are shown at the tick
+            let frame = Frame {
+                skipped_since_last,
+                is_duplicate,
+                timestamp: Duration::from_nanos(sent * 1_000_000_000 / fps as u64),
+                presentation_time: clock(),
+                ..latest.clone()
+            };
+
+            self.send_frame(frame, &mut dropped).await?;
+            sent += 1;
+        }
+
+        Ok(())
+    }
+
+    // sends a frame to the receiver, drawing the click overlay first. dropped is the last frame the backpressure dropped, see send_with_backpressure
+    async fn send_frame(
+        &self,
+        mut frame: Frame,
+        dropped: &mut Option<Frame>,
+    ) -> Result<(), CaptureError> {
+        //the rings are drawn outside of the changed rects, so the whole frame is changed
+        if self.click_overlay().is_some() {
+            frame.dirty_rects.clear();
+            frame.move_rects.clear();
+            frame.is_duplicate = false;
+        }
+
+        self.draw_click_overlay(&mut frame.data);
+
+        let detection = self.change_detection();
+
+        if let Some(detection) = detection {
+            let mut detector = self.change_detector.lock().await;
+
+            //the samples are only compared when desktop duplication did not report the frame as changed or unchanged,
+            //a duplicate it reported keeps the previous samples valid while a changed frame makes them stale
+            if !frame.is_duplicate {
+                if frame.dirty_rects.is_empty() && frame.move_rects.is_empty() {
+                    frame.is_duplicate = detector.is_duplicate(&detection, &frame.data);
+                } else {
+                    detector.reset();
+                }
+            }
+
+            drop(detector);
+
+            //a duplicate of a dropped frame is the first the consumer sees of its changes
+            if frame.is_duplicate && detection.skip_duplicates && dropped.is_none() {
+                trace_event!(trace, "skipping duplicate monitor frame");
+                return Ok(());
+            }
+        }
+
+        let sender = self.sender.read().unwrap().clone();
+        let backpressure = self.backpressure();
+
+        self.frame_rate.record(Instant::now());
+
+        let sent = send_with_backpressure(&sender, &self.receiver, backpressure, dropped, frame)
+            .await
+            .map_err(|e| {
+                trace_event!(warn, "receiver dropped, stopping monitor capture");
+                CaptureError::Other(format!("Failed to send frame: {}", e))
+            })?;
+
+        if !sent {
+            trace_event!(
+                debug,
+                ?backpressure,
+                "receiver full, dropped a monitor frame"
+            );
+        }
+
+        Ok(())
+    }
+
     /// acquires a monitory frame based on previous monitor frames
-    async unsafe fn acquire_data(&self) -> Result<MonitorFrame, windows::core::Error> {
-        let timeout_ms = 500;
+    async unsafe fn acquire_data(
+        &self,
+        timeout_ms: u32,
+    ) -> Result<MonitorFrame, windows::core::Error> {
         let mut desktop_resource = None;
         let mut frame_info = DXGI_OUTDUPL_FRAME_INFO::default();
+        let duplication = self.duplication().await?;
 
         unsafe {
-            self.duplication_output.AcquireNextFrame(
-                timeout_ms,
-                &mut frame_info,
-                &mut desktop_resource,
-            )?;
+            duplication
+                .AcquireNextFrame(timeout_ms, &mut frame_info, &mut desktop_resource)
+                .inspect_err(|_e| {
+                    trace_event!(trace, hresult = %_e.code(), "AcquireNextFrame returned an error");
+                })?;
+        }
+
+        trace_event!(
+            trace,
+            accumulated_frames = frame_info.AccumulatedFrames,
+            "AcquireNextFrame acquired a frame"
+        );
+
+        //a lost cursor shape does not stop the frame
+        if let Err(_e) = unsafe { self.update_cursor(&duplication, &frame_info) } {
+            trace_event!(warn, hresult = %_e.code(), "GetFramePointerShape failed");
         }
 
         let desktop_resource = desktop_resource.unwrap();
@@ -275,18 +1997,30 @@ impl Monitor {
         let mut move_bytes_returned = 0;
         let mut dirty_bytes_returned = 0;
 
-        unsafe {
-            self.duplication_output.GetFrameMoveRects(
-                metadata_size,
-                moved_buffer.as_mut_ptr(),
-                &mut move_bytes_returned,
-            )?;
+        //the sizes are those of the buffers themselves, never more than they hold
+        let rects = unsafe {
+            duplication
+                .GetFrameMoveRects(
+                    std::mem::size_of_val(moved_buffer.as_slice()) as u32,
+                    moved_buffer.as_mut_ptr(),
+                    &mut move_bytes_returned,
+                )
+                .and_then(|()| {
+                    duplication.GetFrameDirtyRects(
+                        std::mem::size_of_val(dirty_buffer.as_slice()) as u32,
+                        dirty_buffer.as_mut_ptr(),
+                        &mut dirty_bytes_returned,
+                    )
+                })
+        };
 
-            self.duplication_output.GetFrameDirtyRects(
-                metadata_size,
-                dirty_buffer.as_mut_ptr(),
-                &mut dirty_bytes_returned,
-            )?;
+        //a lost duplication is recreated and acquired again, the buffers go back so metadata_size still matches them
+        if let Err(e) = rects {
+            let mut frame_lock = self.frame.lock().await;
+            frame_lock.moved_buffer = moved_buffer;
+            frame_lock.dirty_buffer = dirty_buffer;
+
+            return Err(e);
         }
 
         let moved_count =
@@ -306,13 +2040,20 @@ impl Monitor {
 }
 
 impl ICapture for Monitor {
-    type CaptureOutput = Vec<u8>;
+    type CaptureOutput = Frame;
 
     /// # Get Dimensions
     ///
-    /// Clones the demisions of the monitor
+    /// Clones the demisions of the monitor, after rotation, or the output size when frames are scaled.
     fn get_dimensions(&self) -> Result<Dimensions, Box<dyn std::error::Error>> {
-        Ok(self.desktop_size.clone())
+        Ok(self.get_frame_size())
+    }
+
+    /// # Get Output Format
+    ///
+    /// RGB32 (BGRA) unless the surface is HDR and the HDR mode is Raw.
+    fn get_output_format(&self) -> Output {
+        self.pixel_format().into()
     }
 
     /// # Stop Cloning
@@ -323,13 +2064,11 @@ impl ICapture for Monitor {
     ) -> std::pin::Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>> + Send>>
     {
         Box::pin(async move {
-            let mut is_sending = self.is_sending.lock().await;
-
-            if !*is_sending {
+            //the loop ends once it sees the state is not capturing anymore
+            if !self.state.stop() {
                 return Err("Not sending any data".into());
             }
 
-            *is_sending = false;
             Ok(())
         })
     }
@@ -352,61 +2091,154 @@ impl ICapture for Monitor {
     ) -> std::pin::Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>> + Send>>
     {
         Box::pin(async move {
-            {
-                let mut sending_lock = self.is_sending.lock().await;
+            //the state is stopped again when the loop ends, also with an error
+            let _running = self.state.start().await?;
 
-                if *sending_lock {
-                    return Err("you are already cloning data".into());
-                }
+            //the first frame is compared with nothing
+            self.change_detector.lock().await.reset();
+            self.frame_rate.reset();
 
-                *sending_lock = true;
-            }
+            let sent = match self.constant_fps() {
+                Some(fps) => unsafe { self.send_constant_frames(fps).await },
+                None => unsafe { self.send_frames().await },
+            };
 
-            loop {
-                //take the lock, the value, and drop
-                let is_sending_currently = { *self.is_sending.lock().await };
-                if !is_sending_currently {
-                    break;
-                }
+            sent?;
 
-                unsafe {
-                    //retrieve the monitor frame currently, using the previous monitor frame on the self
-                    let monitor_frame = self.acquire_data().await;
+            Ok(())
+        })
+    }
+
+    fn clone_receiver(&self) -> Arc<Mutex<Receiver<Self::CaptureOutput>>> {
+        self.receiver.clone()
+    }
+
+    fn set_frame_timeout(&self, duration: Duration) {
+        *self.frame_timeout.write().unwrap() = Some(duration);
+    }
+
+    fn clear_frame_timeout(&self) {
+        *self.frame_timeout.write().unwrap() = None;
+    }
+
+    fn get_frame_timeout(&self) -> Option<Duration> {
+        *self.frame_timeout.read().unwrap()
+    }
+
+    fn frame_callback(&self) -> &FrameCallback<Frame> {
+        &self.frame_callback
+    }
+
+    fn state_cell(&self) -> &StateCell {
+        &self.state
+    }
+}
+
+impl ISyncCapture for Monitor {
+    fn start_capturing(&self) -> Result<(), CaptureError> {
+        self.sync.start(&self.this)
+    }
+
+    fn stop_capturing(&self) -> Result<(), CaptureError> {
+        self.sync.stop()
+    }
+
+    fn recv_frame(&self, timeout: Option<Duration>) -> Result<Option<Vec<u8>>, CaptureError> {
+        self.sync.recv(timeout)
+    }
+
+    fn get_dimensions(&self) -> Result<Dimensions, CaptureError> {
+        Ok(self.get_frame_size())
+    }
+}
+
+impl IGpuCapture for Monitor {
+    /// # Get Texture Dimensions
+    ///
+    /// The unrotated size of the duplicated surface, textures are not rotated.
+    fn get_texture_dimensions(&self) -> Dimensions {
+        self.desktop_size.clone()
+    }
 
-                    if let Err(e) = monitor_frame {
-                        //this is forgiveable, just no new data was accquired within the specified window time.
-                        if e.code() == DXGI_ERROR_WAIT_TIMEOUT.into() {
+    fn gpu_device(&self) -> ID3D11Device {
+        self.device.clone()
+    }
+
+    /// # Stop GPU Capturing
+    ///
+    /// Safely stops sending textures, this is the same as stop_capturing.
+    fn stop_gpu_capturing(
+        self: Arc<Self>,
+    ) -> std::pin::Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>> + Send>>
+    {
+        ICapture::stop_capturing(self)
+    }
+
+    /// # Start GPU Capturing
+    ///
+    /// Starts duplicating the monitor, sending every frame as a texture to the GPU receiver without copying it to system memory.
+    ///
+    /// Textures are in the surface format (HDR surfaces are not tone mapped) and are not rotated or scaled, excluded windows are not masked.
+    fn start_gpu_capturing(
+        self: Arc<Self>,
+    ) -> std::pin::Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>> + Send>>
+    {
+        Box::pin(async move {
+            //the state is stopped again when the loop ends, also with an error
+            let _running = self.state.start().await?;
+
+            while self.is_capturing() {
+                unsafe {
+                    let monitor_frame = match self.acquire_data(ACQUIRE_TIMEOUT_MS).await {
+                        Ok(monitor_frame) => monitor_frame,
+                        //no new data was accquired within the specified window time.
+                        Err(e) if e.code() == DXGI_ERROR_WAIT_TIMEOUT.into() => continue,
+                        Err(e) if e.code() == DXGI_ERROR_ACCESS_LOST => {
+                            trace_event!(warn, hresult = %e.code(), "duplication lost, recovering");
+                            self.reduplicate().await?;
                             continue;
                         }
+                        Err(e) => {
+                            trace_event!(error, hresult = %e.code(), "gpu capture loop stopped");
+                            return Err(e.into());
+                        }
+                    };
 
-                        // this is another error.
-                        return Err(e.into());
-                    }
+                    let skipped_since_last =
+                        (monitor_frame.frame_info.AccumulatedFrames as u64).saturating_sub(1);
+                    let presentation_time = presentation_time(&monitor_frame);
 
-                    let monitor_frame = monitor_frame.unwrap();
+                    let shared = self.free_shared_texture()?;
 
-                    // update our current monitor frame with the newly acquired one
                     let mut frame_lock = self.frame.lock().await;
                     *frame_lock = monitor_frame;
 
-                    self.device_context.CopyResource(
-                        &self.staging_texture,
+                    shared.copy_from(
+                        &self.device_context,
                         frame_lock.acquired_image.as_ref().unwrap(),
-                    );
+                    )?;
 
                     drop(frame_lock);
 
-                    //flush the context of the copied resource.
                     self.device_context.Flush();
 
-                    let data = self.map_resource()?;
+                    let gpu_frame = GpuFrame::new(
+                        shared,
+                        self.device.clone(),
+                        self.device_context.clone(),
+                        self.desktop_size.clone(),
+                        self.rotation(),
+                        skipped_since_last,
+                        presentation_time,
+                    );
 
-                    let send_res = self.sender.send(data).await;
+                    let send_res = self.gpu_sender.send(gpu_frame).await;
 
                     self.release_frames().await?;
 
-                    if let Err(e) = send_res {
-                        return Err(format!("Failed to send frame: {}", e).into());
+                    if send_res.is_err() {
+                        trace_event!(warn, "gpu receiver dropped, stopping monitor capture");
+                        return Err("Failed to send texture, the GPU receiver was dropped".into());
                     }
                 }
             }
@@ -415,9 +2247,32 @@ impl ICapture for Monitor {
         })
     }
 
-    fn clone_receiver(&self) -> Arc<Mutex<Receiver<Self::CaptureOutput>>> {
-        self.receiver.clone()
+    fn clone_gpu_receiver(&self) -> Arc<Mutex<Receiver<GpuFrame>>> {
+        self.gpu_receiver.clone()
+    }
+}
+
+// when the desktop image of a frame was presented on the clock, the current time when only the cursor changed
+fn presentation_time(monitor_frame: &MonitorFrame) -> i64 {
+    match monitor_frame.frame_info.LastPresentTime {
+        0 => clock(),
+        ticks => qpc_to_clock(ticks),
+    }
+}
+
+// copies the rows of a padded buffer next to each other
+fn strip_padding(raw: &[u8], row_pitch: usize, row_bytes: usize, height: usize) -> Vec<u8> {
+    if row_pitch == row_bytes {
+        return raw.to_vec();
     }
+
+    let mut data = Vec::with_capacity(row_bytes * height);
+
+    for row in raw.chunks(row_pitch).take(height) {
+        data.extend_from_slice(&row[..row_bytes]);
+    }
+
+    data
 }
 
 unsafe impl Send for Monitor {}
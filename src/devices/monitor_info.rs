@@ -12,11 +12,39 @@ pub struct MonitorInfo {
     /// The monitor index. Based on all of your monitors.
     /// 
     /// For example if you have two monitors this may be 0 or 1 and so on
-    pub index: u32
+    pub index: u32,
+
+    /// The left edge of the display on the virtual desktop, the top left of the primary display is 0, 0.
+    /// 
+    /// Displays left of or above the primary display have negative coordinates, 0 when the display is not part of the desktop.
+    pub left: i32,
+
+    /// The top edge of the display on the virtual desktop.
+    pub top: i32,
+
+    /// The width of the display mode in pixels, 0 when the display is not part of the desktop.
+    pub width: u32,
+
+    /// The height of the display mode in pixels.
+    pub height: u32,
+
+    /// The refresh rate of the display mode in Hz, 0 when it is unknown.
+    pub refresh_rate: u32,
+
+    /// If the display is part of the desktop, only these can be captured.
+    pub attached: bool,
+
+    /// The effective DPI of the display from the display settings, 96 at 100% scaling and when the display is not part of the desktop.
+    pub dpi: u32,
+
+    /// The DPI scaling of the display, 1.0 at 100% and 1.5 at 150%.
+    /// 
+    /// Positions of applications that are not DPI aware are in logical pixels, multiply them by this to get the pixels of a captured frame.
+    pub scale_factor: f32
 }
 
 impl MonitorInfo {
     pub fn new(name: String, desc: String, index: u32) -> Self {
-        return MonitorInfo { name, description: desc, index };
+        return MonitorInfo { name, description: desc, index, left: 0, top: 0, width: 0, height: 0, refresh_rate: 0, attached: false, dpi: 96, scale_factor: 1.0 };
     }
 }
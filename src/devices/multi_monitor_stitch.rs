@@ -0,0 +1,376 @@
+use std::{
+    pin::Pin,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use tokio::{
+    sync::{
+        Mutex,
+        mpsc::{self, Receiver, Sender},
+    },
+    time::MissedTickBehavior,
+};
+
+use crate::{
+    capture_error::CaptureError,
+    capture_session::CaptureSession,
+    capture_state::StateCell,
+    devices::{
+        Backpressure, DEFAULT_CHANNEL_CAPACITY, Dimensions, Frame, Monitor, camera::Output,
+        monitor_info::MonitorInfo,
+    },
+    frame_callback::FrameCallback,
+    i_capture::ICapture,
+};
+
+// the output rate when no display of the layout reports its refresh rate
+const DEFAULT_FPS: u32 = 60;
+
+// a monitor of the stitch and where its frames go in the stitched frame
+struct Placed {
+    monitor: Arc<Monitor>,
+    x: usize,
+    y: usize,
+}
+
+/// # Multi Monitor Stitch
+///
+/// Captures several monitors at once and composites their frames into a single BGRA frame of the virtual desktop, laid out as in the display settings.
+///
+/// The stitched frame is sized to the bounding rectangle of the monitors, areas no monitor covers (displays of different sizes) stay black.
+/// Frames are sent at the refresh rate of the slowest monitor, every frame shows the latest image of each monitor.
+///
+/// The stitch implements ICapture, so it can be used wherever a single Monitor is, for example with JpegCapture or an encoder.
+pub struct MultiMonitorStitch {
+    monitors: Vec<Placed>,
+
+    // the size of the stitched frames
+    size: Dimensions,
+
+    // the rate stitched frames are sent at
+    fps: u32,
+
+    /// The receiver, can be used to grab the stitched frames directly.
+    pub receiver: Arc<Mutex<Receiver<Frame>>>,
+
+    sender: Sender<Frame>,
+
+    // how long recv_frame waits for a frame
+    frame_timeout: RwLock<Option<Duration>>,
+
+    // the callback registered with on_frame
+    frame_callback: FrameCallback<Frame>,
+
+    // if the capture loop runs, see ICapture::state
+    state: StateCell,
+}
+
+impl MultiMonitorStitch {
+    /// # From Layout
+    ///
+    /// Creates a Monitor for every display of the layout (see get_all_monitor_info) and places it at its position on the virtual desktop.
+    ///
+    /// Displays that are not part of the desktop are skipped, CaptureError::MonitorNotFound is returned when none is left.
+    pub fn from_layout(layout: &[MonitorInfo]) -> Result<Arc<Self>, CaptureError> {
+        let attached: Vec<&MonitorInfo> = layout
+            .iter()
+            .filter(|info| info.attached && info.width > 0 && info.height > 0)
+            .collect();
+
+        if attached.is_empty() {
+            return Err(CaptureError::MonitorNotFound);
+        }
+
+        let left = attached.iter().map(|info| info.left).min().unwrap_or(0);
+        let top = attached.iter().map(|info| info.top).min().unwrap_or(0);
+        let right = attached
+            .iter()
+            .map(|info| info.left + info.width as i32)
+            .max()
+            .unwrap_or(0);
+        let bottom = attached
+            .iter()
+            .map(|info| info.top + info.height as i32)
+            .max()
+            .unwrap_or(0);
+
+        //a refresh rate of 0 or 1 means the default of the hardware, which is not known
+        let fps = attached
+            .iter()
+            .map(|info| info.refresh_rate)
+            .filter(|rate| *rate > 1)
+            .min()
+            .unwrap_or(DEFAULT_FPS);
+
+        let mut monitors = Vec::with_capacity(attached.len());
+
+        for info in attached {
+            let monitor = unsafe { Monitor::from_point(info.left, info.top) }?;
+
+            //the stitch only wants the latest image, a monitor never waits for it
+            monitor.set_backpressure(Backpressure::DropOldest);
+
+            monitors.push(Placed {
+                monitor,
+                x: (info.left - left) as usize,
+                y: (info.top - top) as usize,
+            });
+        }
+
+        trace_event!(
+            debug,
+            monitors = monitors.len(),
+            width = right - left,
+            height = bottom - top,
+            fps,
+            "monitor stitch created"
+        );
+
+        let (tx, rx) = mpsc::channel(DEFAULT_CHANNEL_CAPACITY);
+
+        Ok(Arc::new(Self {
+            monitors,
+            size: Dimensions {
+                width: (right - left) as u32,
+                height: (bottom - top) as u32,
+            },
+            fps,
+            receiver: Arc::new(Mutex::new(rx)),
+            sender: tx,
+            frame_timeout: RwLock::new(None),
+            frame_callback: FrameCallback::default(),
+            state: StateCell::default(),
+        }))
+    }
+
+    /// # Monitors
+    ///
+    /// The monitors of the stitch, for changing their settings (such as the HDR mode) before capturing.
+    ///
+    /// Do not start or stop them yourself and keep their output size unset, the stitch places their frames at the size of the display.
+    pub fn monitors(&self) -> Vec<Arc<Monitor>> {
+        self.monitors
+            .iter()
+            .map(|placed| placed.monitor.clone())
+            .collect()
+    }
+
+    /// # FPS
+    ///
+    /// The rate stitched frames are sent at, the refresh rate of the slowest monitor.
+    pub fn fps(&self) -> u32 {
+        self.fps
+    }
+
+    // composites the latest frames of the monitors until stopped or one of the monitors stops
+    async fn send_frames(&self, sessions: &[CaptureSession<Monitor>]) -> Result<(), CaptureError> {
+        let width = self.size.width as usize;
+        let stride = width * 4;
+
+        let mut canvas = vec![0; stride * self.size.height as usize];
+        let mut interval = tokio::time::interval(Duration::from_secs(1) / self.fps);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        let mut sent: u64 = 0;
+
+        while self.is_capturing() {
+            interval.tick().await;
+
+            //the error of a monitor that stopped is returned once its session is stopped
+            if sessions.iter().any(CaptureSession::is_finished) {
+                break;
+            }
+
+            let mut changed = false;
+
+            for placed in &self.monitors {
+                let Some(frame) = latest_frame(&placed.monitor) else {
+                    continue;
+                };
+
+                let Ok(size) = placed.monitor.get_dimensions() else {
+                    continue;
+                };
+
+                //a monitor with HdrMode::Raw whose display is switched to HDR while capturing delivers frames that are not BGRA, its area keeps the last image
+                if placed.monitor.get_output_format() != Output::RGB32 {
+                    trace_event!(warn, "skipping a monitor frame that is not BGRA");
+                    continue;
+                }
+
+                draw_frame(&mut canvas, stride, &frame, size, placed.x, placed.y);
+                changed = true;
+            }
+
+            let mut frame = Frame::new(
+                canvas.clone(),
+                Duration::from_nanos(sent * 1_000_000_000 / self.fps as u64),
+            );
+            frame.stride = stride;
+
+            //no monitor delivered a new image since the previous tick
+            frame.is_duplicate = !changed && sent > 0;
+
+            self.sender.send(frame).await.map_err(|e| {
+                trace_event!(warn, "receiver dropped, stopping monitor stitch");
+                CaptureError::Other(format!("Failed to send frame: {}", e))
+            })?;
+
+            sent += 1;
+        }
+
+        Ok(())
+    }
+}
+
+// the newest frame waiting in the receiver of a monitor, older ones are dropped
+fn latest_frame(monitor: &Monitor) -> Option<Frame> {
+    let receiver = monitor.clone_receiver();
+    let mut receiver = receiver.try_lock().ok()?;
+
+    let mut latest = None;
+
+    while let Ok(frame) = receiver.try_recv() {
+        latest = Some(frame);
+    }
+
+    latest
+}
+
+// copies the rows of a BGRA frame into the canvas at x, y, cutting off what lies outside of it
+fn draw_frame(
+    canvas: &mut [u8],
+    canvas_stride: usize,
+    frame: &Frame,
+    size: Dimensions,
+    x: usize,
+    y: usize,
+) {
+    let row_len = size.width as usize * 4;
+    let frame_stride = if frame.stride > 0 {
+        frame.stride
+    } else {
+        row_len
+    };
+
+    let canvas_height = canvas.len() / canvas_stride.max(1);
+    let copy_len = row_len.min(canvas_stride.saturating_sub(x * 4));
+
+    for row in 0..(size.height as usize).min(canvas_height.saturating_sub(y)) {
+        let Some(source) = frame
+            .data
+            .get(row * frame_stride..row * frame_stride + copy_len)
+        else {
+            break;
+        };
+
+        let start = (y + row) * canvas_stride + x * 4;
+        canvas[start..start + copy_len].copy_from_slice(source);
+    }
+}
+
+impl ICapture for MultiMonitorStitch {
+    type CaptureOutput = Frame;
+
+    /// # Get Dimensions
+    ///
+    /// The size of the bounding rectangle of the monitors.
+    fn get_dimensions(&self) -> Result<Dimensions, Box<dyn std::error::Error>> {
+        Ok(self.size.clone())
+    }
+
+    /// # Get Output Format
+    ///
+    /// Always RGB32 (BGRA).
+    fn get_output_format(&self) -> Output {
+        Output::RGB32
+    }
+
+    /// # Stop Capturing
+    ///
+    /// Stops sending stitched frames and stops the monitors.
+    fn stop_capturing(
+        self: Arc<Self>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>> + Send>> {
+        Box::pin(async move {
+            //the loop ends once it sees the state is not capturing anymore
+            if !self.state.stop() {
+                return Err("already stopped.".into());
+            }
+
+            Ok(())
+        })
+    }
+
+    /// # Start Capturing
+    ///
+    /// Starts every monitor and sends stitched frames until stop_capturing is called or one of the monitors fails, whose error is returned.
+    ///
+    /// Returns CaptureError::UnsupportedFormat when a monitor does not deliver BGRA frames, such as an HDR display with HdrMode::Raw.
+    fn start_capturing(
+        self: Arc<Self>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>> + Send>> {
+        Box::pin(async move {
+            //frames are copied into the canvas as they are, so every monitor must deliver BGRA
+            if let Some(placed) = self
+                .monitors
+                .iter()
+                .find(|placed| placed.monitor.get_output_format() != Output::RGB32)
+            {
+                return Err(CaptureError::UnsupportedFormat(format!(
+                    "stitching {:?} frames",
+                    placed.monitor.get_output_format()
+                ))
+                .into());
+            }
+
+            //the state is stopped again when the loop ends, also with an error
+            let _running = self.state.start().await?;
+
+            let sessions: Vec<_> = self
+                .monitors
+                .iter()
+                .map(|placed| placed.monitor.clone().start_session())
+                .collect();
+
+            let sent = self.send_frames(&sessions).await;
+
+            let mut stopped = Ok(());
+
+            for session in sessions {
+                let result = session.stop().await;
+                stopped = stopped.and(result);
+            }
+
+            sent?;
+            stopped?;
+
+            Ok(())
+        })
+    }
+
+    fn clone_receiver(&self) -> Arc<Mutex<Receiver<Self::CaptureOutput>>> {
+        self.receiver.clone()
+    }
+
+    fn set_frame_timeout(&self, duration: Duration) {
+        *self.frame_timeout.write().unwrap() = Some(duration);
+    }
+
+    fn clear_frame_timeout(&self) {
+        *self.frame_timeout.write().unwrap() = None;
+    }
+
+    fn get_frame_timeout(&self) -> Option<Duration> {
+        *self.frame_timeout.read().unwrap()
+    }
+
+    fn frame_callback(&self) -> &FrameCallback<Frame> {
+        &self.frame_callback
+    }
+
+    fn state_cell(&self) -> &StateCell {
+        &self.state
+    }
+}
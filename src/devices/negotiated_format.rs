@@ -0,0 +1,68 @@
+use windows::core::GUID;
+
+use crate::devices::{Dimensions, camera::Output};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// # Negotiated Format
+///
+/// The media type the source reader of a Camera really delivers, returned by Camera::negotiated_format.
+///
+/// Media foundation may pick another frame size than requested or insert converters (decoders, the video processor) between the device and the reader,
+/// this tells what the frames of the receiver hold.
+pub struct NegotiatedFormat {
+    /// The media foundation subtype of the delivered frames, such as MFVideoFormat_RGB32.
+    pub subtype: GUID,
+
+    /// The Output of the subtype, None when the subtype has no matching Output.
+    pub output: Option<Output>,
+
+    /// Width of the frames in pixels.
+    pub width: u32,
+
+    /// Height of the frames in pixels.
+    pub height: u32,
+
+    /// Numerator of the frame rate, frames per second is fps_numerator / fps_denominator.
+    pub fps_numerator: u32,
+
+    /// Denominator of the frame rate.
+    pub fps_denominator: u32,
+
+    /// The bytes between the start of two rows (of the luma plane for NV12), 0 for compressed frames such as MJPEG.
+    ///
+    /// A negative stride means the image is bottom-up: the first row of the data is the bottom row, so the frames look vertically flipped unless the rows are reversed.
+    pub stride: i32,
+
+    /// If media foundation inserted a transform (a decoder or the video processor) between the device and the reader to produce the subtype.
+    pub has_converter: bool,
+}
+
+impl NegotiatedFormat {
+    /// # Frames Per Second
+    ///
+    /// The frame rate as a float, for example 29.97.
+    pub fn fps(&self) -> f32 {
+        if self.fps_denominator == 0 {
+            return 0.0;
+        }
+
+        self.fps_numerator as f32 / self.fps_denominator as f32
+    }
+
+    /// # Is Bottom Up
+    ///
+    /// If the rows of the frames are stored from the bottom to the top, see stride.
+    pub fn is_bottom_up(&self) -> bool {
+        self.stride < 0
+    }
+
+    /// # Dimensions
+    ///
+    /// The width and height of the frames.
+    pub fn dimensions(&self) -> Dimensions {
+        Dimensions {
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
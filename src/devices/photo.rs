@@ -0,0 +1,60 @@
+use windows::core::GUID;
+
+use crate::devices::{Dimensions, Frame, camera::Output};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// # Photo Source
+///
+/// Where the image of a Photo came from.
+pub enum PhotoSource {
+    /// The photo stream of the camera (an image or still pin), which usually has a higher resolution than the video stream.
+    PhotoStream,
+
+    /// The camera has no photo stream, the photo is the next frame of the video stream.
+    VideoFrame,
+}
+
+#[derive(Clone, Debug)]
+/// # Photo
+///
+/// A single image taken with Camera::take_photo.
+pub struct Photo {
+    /// The image, data holds a single image laid out as described by output.
+    ///
+    /// The timestamp is 0 for images of the photo stream, the presentation time is the time of the sample.
+    pub frame: Frame,
+
+    /// Width of the image in pixels.
+    pub width: u32,
+
+    /// Height of the image in pixels.
+    pub height: u32,
+
+    /// The media foundation subtype of the image, photo streams often deliver JPEG (MFImageFormat_JPEG) or MJPEG.
+    pub subtype: GUID,
+
+    /// The Output of the subtype, None when the subtype has no matching Output (such as a JPEG still image).
+    pub output: Option<Output>,
+
+    /// If the image came from the photo stream or the video stream.
+    pub source: PhotoSource,
+}
+
+impl Photo {
+    /// # Is Fallback
+    ///
+    /// If the camera had no photo stream and the image is a frame of the video stream.
+    pub fn is_fallback(&self) -> bool {
+        self.source == PhotoSource::VideoFrame
+    }
+
+    /// # Dimensions
+    ///
+    /// The width and height of the image.
+    pub fn dimensions(&self) -> Dimensions {
+        Dimensions {
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use crate::devices::camera::Output;
+use windows::Win32::Graphics::Dxgi::Common::{
+    DXGI_FORMAT, DXGI_FORMAT_B8G8R8A8_UNORM, DXGI_FORMAT_R10G10B10A2_UNORM,
+    DXGI_FORMAT_R16G16B16A16_FLOAT,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// # Pixel Format
+///
+/// The layout of a single pixel of a monitor frame.
+pub enum PixelFormat {
+    /// 8 bits per channel, ordered blue, green, red, alpha. This is the standard SDR desktop format.
+    Bgra8,
+
+    /// 10 bits per channel red, green, blue and 2 bits of alpha packed into a little endian u32.
+    ///
+    /// On an HDR display this is normally ST.2084 (PQ) encoded with BT.2020 primaries.
+    Rgb10A2,
+
+    /// 16 bit float per channel, ordered red, green, blue, alpha.
+    ///
+    /// This is linear scRGB, where 1.0 is 80 nits.
+    Rgba16Float,
+}
+
+impl PixelFormat {
+    /// # Bytes Per Pixel
+    ///
+    /// The number of bytes a single pixel takes up.
+    pub fn bytes_per_pixel(&self) -> usize {
+        match self {
+            PixelFormat::Bgra8 | PixelFormat::Rgb10A2 => 4,
+            PixelFormat::Rgba16Float => 8,
+        }
+    }
+
+    /// # From DXGI
+    ///
+    /// Maps a DXGI format to a pixel format, None if the format is not supported.
+    pub fn from_dxgi(format: DXGI_FORMAT) -> Option<Self> {
+        match format {
+            DXGI_FORMAT_B8G8R8A8_UNORM => Some(PixelFormat::Bgra8),
+            DXGI_FORMAT_R10G10B10A2_UNORM => Some(PixelFormat::Rgb10A2),
+            DXGI_FORMAT_R16G16B16A16_FLOAT => Some(PixelFormat::Rgba16Float),
+            _ => None,
+        }
+    }
+
+    /// # To DXGI
+    ///
+    /// The DXGI format that matches the pixel format.
+    pub fn to_dxgi(&self) -> DXGI_FORMAT {
+        match self {
+            PixelFormat::Bgra8 => DXGI_FORMAT_B8G8R8A8_UNORM,
+            PixelFormat::Rgb10A2 => DXGI_FORMAT_R10G10B10A2_UNORM,
+            PixelFormat::Rgba16Float => DXGI_FORMAT_R16G16B16A16_FLOAT,
+        }
+    }
+
+    /// # Is HDR
+    ///
+    /// If the format carries more than 8 bits per channel.
+    pub fn is_hdr(&self) -> bool {
+        !matches!(self, PixelFormat::Bgra8)
+    }
+}
+
+impl From<PixelFormat> for Output {
+    fn from(value: PixelFormat) -> Self {
+        match value {
+            PixelFormat::Bgra8 => Output::RGB32,
+            PixelFormat::Rgb10A2 => Output::RGB10A2,
+            PixelFormat::Rgba16Float => Output::RGBA16F,
+        }
+    }
+}
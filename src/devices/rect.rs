@@ -0,0 +1,91 @@
+use windows::Win32::{Foundation::RECT, Graphics::Dxgi::DXGI_OUTDUPL_MOVE_RECT};
+
+/// # Rect
+///
+/// A rectangle of a frame in pixels, right and bottom are exclusive.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rect {
+    pub left: u32,
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+}
+
+impl Rect {
+    /// # Width
+    ///
+    /// The number of columns in the rectangle.
+    pub fn width(&self) -> u32 {
+        self.right.saturating_sub(self.left)
+    }
+
+    /// # Height
+    ///
+    /// The number of rows in the rectangle.
+    pub fn height(&self) -> u32 {
+        self.bottom.saturating_sub(self.top)
+    }
+
+    /// # Is Empty
+    ///
+    /// If the rectangle covers no pixels.
+    pub fn is_empty(&self) -> bool {
+        self.width() == 0 || self.height() == 0
+    }
+
+    // as (left, top, right, bottom), the form the rotation and scaling helpers work with
+    pub(crate) fn to_tuple(self) -> (usize, usize, usize, usize) {
+        (
+            self.left as usize,
+            self.top as usize,
+            self.right as usize,
+            self.bottom as usize,
+        )
+    }
+
+    pub(crate) fn from_tuple((left, top, right, bottom): (usize, usize, usize, usize)) -> Self {
+        Self {
+            left: left as u32,
+            top: top as u32,
+            right: right as u32,
+            bottom: bottom as u32,
+        }
+    }
+
+    // a rect of desktop duplication, negative coordinates are clamped to the surface
+    pub(crate) fn from_dxgi(rect: &RECT) -> Self {
+        Self {
+            left: rect.left.max(0) as u32,
+            top: rect.top.max(0) as u32,
+            right: rect.right.max(0) as u32,
+            bottom: rect.bottom.max(0) as u32,
+        }
+    }
+}
+
+/// # Move Rect
+///
+/// A region of the previous frame that was moved (for example a dragged window or a scrolled page), copying source to destination updates the previous frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MoveRect {
+    /// The left of the region in the previous frame.
+    pub source_x: u32,
+
+    /// The top of the region in the previous frame.
+    pub source_y: u32,
+
+    /// Where the region is in this frame, the size is the same in both frames.
+    pub destination: Rect,
+}
+
+impl MoveRect {
+    pub(crate) fn from_dxgi(move_rect: &DXGI_OUTDUPL_MOVE_RECT) -> Self {
+        Self {
+            source_x: move_rect.SourcePoint.x.max(0) as u32,
+            source_y: move_rect.SourcePoint.y.max(0) as u32,
+            destination: Rect::from_dxgi(&move_rect.DestinationRect),
+        }
+    }
+}
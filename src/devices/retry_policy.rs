@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug)]
+/// # Retry Policy
+///
+/// Controls how many times a transient failure is retried and how long to wait between each attempt.
+///
+/// The delay doubles after every failed attempt (backoff).
+pub struct RetryPolicy {
+    /// The max number of attempts before giving up, the first attempt included. 0 still makes a single attempt.
+    pub attempts: u32,
+
+    /// The delay before the first retry.
+    pub delay: Duration,
+
+    /// How long a source lost while capturing (such as a monitor duplication lost to a UAC prompt or the lock screen) is retried before giving up.
+    ///
+    /// None retries it for the number of attempts instead, the first connection always uses the attempts.
+    pub max_duration: Option<Duration>,
+}
+
+impl RetryPolicy {
+    pub fn new(attempts: u32, delay: Duration) -> Self {
+        RetryPolicy {
+            attempts,
+            delay,
+            max_duration: None,
+        }
+    }
+
+    /// # With Max Duration
+    ///
+    /// Retries a source lost while capturing until the duration has passed, see max_duration.
+    pub fn with_max_duration(mut self, max_duration: Duration) -> Self {
+        self.max_duration = Some(max_duration);
+        self
+    }
+
+    /// # Delay For
+    ///
+    /// The amount of time to wait before the given (zero based) retry attempt.
+    pub fn delay_for(&self, attempt: u32) -> Duration {
+        self.delay.saturating_mul(2u32.saturating_pow(attempt))
+    }
+
+    /// # Is Last Attempt
+    ///
+    /// If the given (zero based) attempt is the last one of attempts.
+    pub fn is_last_attempt(&self, attempt: u32) -> bool {
+        attempt.saturating_add(1) >= self.attempts
+    }
+
+    // calls attempt with its zero based number and if it is the last one until it returns a value or an error,
+    // waiting delay_for between the attempts. None asks for another attempt, which the last one must not do.
+    pub(crate) fn retry<T, E>(
+        &self,
+        mut attempt: impl FnMut(u32, bool) -> Result<Option<T>, E>,
+        mut wait: impl FnMut(Duration),
+    ) -> Result<T, E> {
+        let mut number = 0;
+
+        loop {
+            if let Some(value) = attempt(number, self.is_last_attempt(number))? {
+                return Ok(value);
+            }
+
+            wait(self.delay_for(number));
+            number += 1;
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    /// 5 attempts starting at 100ms, a source lost while capturing is retried for a minute.
+    fn default() -> Self {
+        RetryPolicy {
+            attempts: 5,
+            delay: Duration::from_millis(100),
+            max_duration: Some(Duration::from_secs(60)),
+        }
+    }
+}
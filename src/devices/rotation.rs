@@ -0,0 +1,164 @@
+use crate::devices::Dimensions;
+use windows::Win32::Graphics::Dxgi::Common::{
+    DXGI_MODE_ROTATION, DXGI_MODE_ROTATION_ROTATE90, DXGI_MODE_ROTATION_ROTATE180,
+    DXGI_MODE_ROTATION_ROTATE270,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+/// # Rotation
+///
+/// How a display is rotated in the display settings, clockwise.
+///
+/// The duplicated desktop surface is always in the unrotated orientation of the display.
+pub enum Rotation {
+    #[default]
+    Identity,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+}
+
+impl Rotation {
+    /// # From DXGI
+    ///
+    /// Maps a DXGI rotation, unspecified rotations are treated as no rotation.
+    pub fn from_dxgi(rotation: DXGI_MODE_ROTATION) -> Self {
+        match rotation {
+            DXGI_MODE_ROTATION_ROTATE90 => Rotation::Rotate90,
+            DXGI_MODE_ROTATION_ROTATE180 => Rotation::Rotate180,
+            DXGI_MODE_ROTATION_ROTATE270 => Rotation::Rotate270,
+            _ => Rotation::Identity,
+        }
+    }
+
+    /// # Degrees
+    ///
+    /// The clockwise rotation in degrees.
+    pub fn degrees(&self) -> u32 {
+        match self {
+            Rotation::Identity => 0,
+            Rotation::Rotate90 => 90,
+            Rotation::Rotate180 => 180,
+            Rotation::Rotate270 => 270,
+        }
+    }
+
+    /// # Swaps Dimensions
+    ///
+    /// If the width and height of the surface are swapped once rotated.
+    pub fn swaps_dimensions(&self) -> bool {
+        matches!(self, Rotation::Rotate90 | Rotation::Rotate270)
+    }
+
+    /// # Rotated Size
+    ///
+    /// The size of a surface once the rotation is applied.
+    pub fn rotated_size(&self, size: &Dimensions) -> Dimensions {
+        if self.swaps_dimensions() {
+            Dimensions {
+                width: size.height,
+                height: size.width,
+            }
+        } else {
+            size.clone()
+        }
+    }
+
+    // maps a rect (left, top, right, bottom) of the unrotated surface onto the rotated image, the inverse of unrotate_rect
+    pub(crate) fn rotate_rect(
+        &self,
+        (left, top, right, bottom): (usize, usize, usize, usize),
+        surface_width: usize,
+        surface_height: usize,
+    ) -> (usize, usize, usize, usize) {
+        match self {
+            Rotation::Identity => (left, top, right, bottom),
+            Rotation::Rotate90 => (surface_height - bottom, left, surface_height - top, right),
+            Rotation::Rotate180 => (
+                surface_width - right,
+                surface_height - bottom,
+                surface_width - left,
+                surface_height - top,
+            ),
+            Rotation::Rotate270 => (top, surface_width - right, bottom, surface_width - left),
+        }
+    }
+
+    // maps a rect (left, top, right, bottom) of the rotated image back onto the unrotated surface
+    pub(crate) fn unrotate_rect(
+        &self,
+        (left, top, right, bottom): (usize, usize, usize, usize),
+        surface_width: usize,
+        surface_height: usize,
+    ) -> (usize, usize, usize, usize) {
+        match self {
+            Rotation::Identity => (left, top, right, bottom),
+            Rotation::Rotate90 => (top, surface_height - right, bottom, surface_height - left),
+            Rotation::Rotate180 => (
+                surface_width - right,
+                surface_height - bottom,
+                surface_width - left,
+                surface_height - top,
+            ),
+            Rotation::Rotate270 => (surface_width - bottom, left, surface_width - top, right),
+        }
+    }
+
+    // maps a point of the rotated image back onto the unrotated surface, the same as unrotate_rect
+    pub(crate) fn unrotate_point(
+        &self,
+        (x, y): (f32, f32),
+        surface_width: f32,
+        surface_height: f32,
+    ) -> (f32, f32) {
+        match self {
+            Rotation::Identity => (x, y),
+            Rotation::Rotate90 => (y, surface_height - x),
+            Rotation::Rotate180 => (surface_width - x, surface_height - y),
+            Rotation::Rotate270 => (surface_width - y, x),
+        }
+    }
+
+    // rotates the pixels of a (possibly padded) surface, the result is tightly packed.
+    //
+    // returns None when there is no rotation so the buffer does not need to be copied.
+    pub(crate) fn rotate_pixels(
+        &self,
+        data: &[u8],
+        width: usize,
+        height: usize,
+        bytes_per_pixel: usize,
+    ) -> Option<Vec<u8>> {
+        if *self == Rotation::Identity || height == 0 {
+            return None;
+        }
+
+        let stride = data.len() / height;
+        let mut out = vec![0u8; width * height * bytes_per_pixel];
+
+        //width of a row of the rotated image
+        let out_width = if self.swaps_dimensions() {
+            height
+        } else {
+            width
+        };
+
+        for y in 0..height {
+            let row = &data[y * stride..y * stride + width * bytes_per_pixel];
+
+            for (x, pixel) in row.chunks_exact(bytes_per_pixel).enumerate() {
+                let (out_x, out_y) = match self {
+                    Rotation::Rotate90 => (height - 1 - y, x),
+                    Rotation::Rotate180 => (width - 1 - x, height - 1 - y),
+                    Rotation::Rotate270 => (y, width - 1 - x),
+                    Rotation::Identity => (x, y),
+                };
+
+                let offset = (out_y * out_width + out_x) * bytes_per_pixel;
+                out[offset..offset + bytes_per_pixel].copy_from_slice(pixel);
+            }
+        }
+
+        Some(out)
+    }
+}
@@ -0,0 +1,104 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    path::Path,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    capture_error::CaptureError,
+    devices::{Dimensions, Frame, camera::Output},
+};
+
+/// # Saved Frame
+///
+/// A frame together with its size and format, for saving frames to files and loading them again (such as golden images compared in tests).
+///
+/// Files are written with bincode, compact but only readable by the same version of the crate.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedFrame {
+    /// The frame data, laid out as described by format.
+    pub data: Vec<u8>,
+
+    pub width: u32,
+    pub height: u32,
+
+    /// The format of the data.
+    pub format: Output,
+
+    /// The number of bytes between the start of two rows, see Frame::stride.
+    pub stride: usize,
+
+    /// The position of the frame in its capture, chosen by whoever saved it.
+    pub sequence: u64,
+
+    /// Frame::timestamp in nanoseconds.
+    pub timestamp_ns: u64,
+
+    /// Frame::presentation_time.
+    pub presentation_time: i64,
+}
+
+impl SavedFrame {
+    /// # New
+    ///
+    /// Copies the frame, dimensions and format describe the data as reported by the capture (get_dimensions and get_output_format).
+    pub fn new(frame: &Frame, dimensions: Dimensions, format: Output, sequence: u64) -> Self {
+        Self {
+            data: frame.data.clone(),
+            width: dimensions.width,
+            height: dimensions.height,
+            format,
+            stride: frame.stride,
+            sequence,
+            timestamp_ns: frame.timestamp.as_nanos() as u64,
+            presentation_time: frame.presentation_time,
+        }
+    }
+
+    /// # Dimensions
+    ///
+    /// The size of the frame.
+    pub fn dimensions(&self) -> Dimensions {
+        Dimensions {
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    /// # To Frame
+    ///
+    /// A frame with the data, stride and times of the saved frame, without change information.
+    pub fn to_frame(&self) -> Frame {
+        let mut frame = Frame::new(self.data.clone(), Duration::from_nanos(self.timestamp_ns));
+        frame.stride = self.stride;
+        frame.presentation_time = self.presentation_time;
+
+        frame
+    }
+
+    /// # Save To File
+    ///
+    /// Writes the frame to the file, replacing it if it exists.
+    pub fn save_to_file(&self, path: &Path) -> Result<(), CaptureError> {
+        let mut file = BufWriter::new(File::create(path)?);
+
+        bincode::serialize_into(&mut file, self).map_err(|e| CaptureError::Other(e.to_string()))?;
+        file.flush()?;
+
+        Ok(())
+    }
+
+    /// # Load From File
+    ///
+    /// Reads a frame written by save_to_file.
+    pub fn load_from_file(path: &Path) -> Result<Self, CaptureError> {
+        let file = BufReader::new(File::open(path)?);
+
+        bincode::deserialize_from(file).map_err(|e| {
+            CaptureError::Other(format!("{} is not a saved frame: {e}", path.display()))
+        })
+    }
+}
@@ -0,0 +1,46 @@
+use std::sync::Arc;
+
+use crate::{
+    devices::{Dimensions, Frame, SharedFrame, camera::Output},
+    i_capture::ICapture,
+    map_capture::{FrameMap, MapCapture},
+};
+
+/// # Shared Capture
+///
+/// Wraps any capture and delivers its frames as SharedFrame, whose data is an `Arc<[u8]>` that every consumer can hold without a copy.
+///
+/// The data of each frame is moved into the shared allocation once, after that handing it to a preview and an encoder only clones the Arc:
+///
+/// ```rs
+/// let shared = SharedCapture::new(monitor);
+///
+/// while let Some(frame) = shared.recv_frame().await? {
+///     let _ = preview.try_send(frame.clone());
+///     encoder.send(frame).await?;
+/// }
+/// ```
+pub type SharedCapture<S> = MapCapture<S, ShareFrames>;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+/// # Share Frames
+///
+/// The FrameMap of SharedCapture, moves the data of every frame into a SharedFrame.
+pub struct ShareFrames;
+
+impl FrameMap for ShareFrames {
+    type Output = SharedFrame;
+
+    fn map(&self, frame: Frame, _size: &Dimensions, _format: Output) -> Option<SharedFrame> {
+        Some(frame.into())
+    }
+}
+
+impl<S: ICapture<CaptureOutput = Frame> + ?Sized + 'static> MapCapture<S, ShareFrames> {
+    /// # New
+    ///
+    /// Wraps the source, capturing is started and stopped through the wrapper, do not start the source yourself.
+    pub fn new(source: Arc<S>) -> Arc<Self> {
+        Self::with_map(source, ShareFrames)
+    }
+}
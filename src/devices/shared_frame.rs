@@ -0,0 +1,84 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::devices::{Frame, MoveRect, Rect};
+
+#[derive(Clone, Debug)]
+/// # Shared Frame
+///
+/// A Frame whose data is shared, cloning it only counts a reference instead of copying the data.
+///
+/// For handing one frame to several consumers (such as a preview and an encoder), see SharedCapture or Frame::into_shared.
+/// The data is immutable, `&frame.data[..]` is a &[u8] of the frame without a copy.
+pub struct SharedFrame {
+    /// The frame data, see Frame::data.
+    pub data: Arc<[u8]>,
+
+    /// The bytes between the start of two rows, see Frame::stride.
+    pub stride: usize,
+
+    /// See Frame::skipped_since_last.
+    pub skipped_since_last: u64,
+
+    /// When the frame was captured, relative to when capturing started.
+    pub timestamp: Duration,
+
+    /// When the frame was captured on the shared clock, see Frame::presentation_time.
+    pub presentation_time: i64,
+
+    /// The regions that changed since the previous frame, see Frame::dirty_rects.
+    pub dirty_rects: Arc<[Rect]>,
+
+    /// The regions that were moved, see Frame::move_rects.
+    pub move_rects: Arc<[MoveRect]>,
+
+    /// If the frame shows the same image as the previous frame.
+    pub is_duplicate: bool,
+}
+
+impl SharedFrame {
+    /// # Len
+    ///
+    /// The number of bytes of data in the frame.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// # Is Empty
+    ///
+    /// If the frame holds no data.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// # To Frame
+    ///
+    /// Copies the frame into a Frame that owns its data, for consumers that change it.
+    pub fn to_frame(&self) -> Frame {
+        Frame {
+            data: self.data.to_vec(),
+            stride: self.stride,
+            skipped_since_last: self.skipped_since_last,
+            timestamp: self.timestamp,
+            presentation_time: self.presentation_time,
+            dirty_rects: self.dirty_rects.to_vec(),
+            move_rects: self.move_rects.to_vec(),
+            is_duplicate: self.is_duplicate,
+        }
+    }
+}
+
+impl From<Frame> for SharedFrame {
+    /// Moves the data into a shared allocation, which copies it once.
+    fn from(frame: Frame) -> Self {
+        Self {
+            data: frame.data.into(),
+            stride: frame.stride,
+            skipped_since_last: frame.skipped_since_last,
+            timestamp: frame.timestamp,
+            presentation_time: frame.presentation_time,
+            dirty_rects: frame.dirty_rects.into(),
+            move_rects: frame.move_rects.into(),
+            is_duplicate: frame.is_duplicate,
+        }
+    }
+}
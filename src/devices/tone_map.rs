@@ -0,0 +1,112 @@
+use crate::devices::PixelFormat;
+
+// nits of scRGB 1.0
+const SCRGB_WHITE_NITS: f32 = 80.0;
+
+// BT.2020 to BT.709 primaries (linear)
+const BT2020_TO_BT709: [[f32; 3]; 3] = [
+    [1.6605, -0.5876, -0.0728],
+    [-0.1246, 1.1329, -0.0083],
+    [-0.0182, -0.1006, 1.1187],
+];
+
+/// # Tone Map To BGRA
+///
+/// Converts a mapped HDR surface into tightly packed 8 bit BGRA (width * 4 bytes per row).
+///
+/// sdr_white_nits is the brightness that should become pure white, this is normally the SDR white level from the display settings.
+pub(crate) fn tone_map_to_bgra(
+    data: &[u8],
+    row_pitch: usize,
+    width: usize,
+    height: usize,
+    format: PixelFormat,
+    sdr_white_nits: f32,
+) -> Vec<u8> {
+    let mut out = vec![0u8; width * height * 4];
+    let bytes_per_pixel = format.bytes_per_pixel();
+
+    for y in 0..height {
+        let row = &data[y * row_pitch..y * row_pitch + width * bytes_per_pixel];
+        let out_row = &mut out[y * width * 4..(y + 1) * width * 4];
+
+        for x in 0..width {
+            let pixel = &row[x * bytes_per_pixel..(x + 1) * bytes_per_pixel];
+
+            //linear BT.709 where 1.0 is SDR white
+            let [r, g, b] = match format {
+                PixelFormat::Bgra8 => {
+                    out_row[x * 4..x * 4 + 4].copy_from_slice(pixel);
+                    continue;
+                }
+                PixelFormat::Rgba16Float => {
+                    let scale = SCRGB_WHITE_NITS / sdr_white_nits;
+                    [0, 2, 4]
+                        .map(|i| half_to_f32(u16::from_le_bytes([pixel[i], pixel[i + 1]])) * scale)
+                }
+                PixelFormat::Rgb10A2 => {
+                    let packed = u32::from_le_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]);
+                    let rgb = [0, 10, 20].map(|shift| {
+                        pq_to_nits(((packed >> shift) & 0x3FF) as f32 / 1023.0) / sdr_white_nits
+                    });
+
+                    BT2020_TO_BT709.map(|m| m[0] * rgb[0] + m[1] * rgb[1] + m[2] * rgb[2])
+                }
+            };
+
+            out_row[x * 4] = srgb_encode(b);
+            out_row[x * 4 + 1] = srgb_encode(g);
+            out_row[x * 4 + 2] = srgb_encode(r);
+            out_row[x * 4 + 3] = 255;
+        }
+    }
+
+    out
+}
+
+// converts an IEEE 754 half precision float into a f32
+pub(crate) fn half_to_f32(half: u16) -> f32 {
+    let sign = if half & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((half >> 10) & 0x1F) as i32;
+    let mantissa = (half & 0x3FF) as f32;
+
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-24),
+        0x1F => {
+            if mantissa == 0.0 {
+                sign * f32::INFINITY
+            } else {
+                f32::NAN
+            }
+        }
+        _ => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+// ST.2084 (PQ) EOTF, returns nits
+fn pq_to_nits(value: f32) -> f32 {
+    const M1: f32 = 2610.0 / 16384.0;
+    const M2: f32 = 2523.0 / 4096.0 * 128.0;
+    const C1: f32 = 3424.0 / 4096.0;
+    const C2: f32 = 2413.0 / 4096.0 * 32.0;
+    const C3: f32 = 2392.0 / 4096.0 * 32.0;
+
+    let p = value.max(0.0).powf(1.0 / M2);
+    let l = ((p - C1).max(0.0) / (C2 - C3 * p)).powf(1.0 / M1);
+
+    l * 10000.0
+}
+
+// clamps a linear value and applies the sRGB transfer function
+fn srgb_encode(linear: f32) -> u8 {
+    //NaN falls through to 0
+    let linear = if linear > 0.0 { linear.min(1.0) } else { 0.0 };
+
+    let encoded = if linear <= 0.0031308 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    };
+
+    (encoded * 255.0 + 0.5) as u8
+}
@@ -0,0 +1,23 @@
+use crate::{devices::Frame, i_capture::ICapture};
+
+/// # Dyn Capture
+///
+/// An ICapture that delivers Frame, usable as a trait object without naming the output type.
+///
+/// Every capture delivering frames (Monitor, Camera and the wrappers around them) is a DynCapture, so different sources can be stored together:
+///
+/// ```rs
+/// let captures: Vec<Arc<dyn DynCapture>> = vec![monitor, camera];
+///
+/// for capture in &captures {
+///     let capture = capture.clone();
+///     tokio::spawn(async move {
+///         let _ = capture.start_capturing().await;
+///     });
+/// }
+/// ```
+///
+/// The methods are the ones of ICapture (which must be in scope to call them), only start_session needs the concrete type.
+pub trait DynCapture: ICapture<CaptureOutput = Frame> {}
+
+impl<T: ICapture<CaptureOutput = Frame> + ?Sized> DynCapture for T {}
@@ -0,0 +1,38 @@
+pub mod encoded_packet;
+pub mod encoder_output;
+mod encoder_task;
+#[cfg(feature = "gif")]
+pub mod gif_recorder;
+pub mod h264_encoder;
+pub mod hardware_encoder;
+pub mod hevc_encoder;
+#[cfg(feature = "jpeg")]
+pub mod jpeg_capture;
+mod mft_encoder;
+pub mod mp4_muxer;
+mod nal;
+mod nv12_converter;
+pub mod replay_buffer;
+pub mod rtsp_sink;
+pub mod webm_muxer;
+#[cfg(feature = "websocket")]
+pub mod ws_frame_server;
+pub mod y4m_writer;
+pub(crate) mod yuv;
+
+pub use crate::encoding::encoded_packet::EncodedPacket;
+pub use crate::encoding::encoder_output::{EncoderOutput, VideoCodec};
+#[cfg(feature = "gif")]
+pub use crate::encoding::gif_recorder::{GifDithering, GifRecorder};
+pub use crate::encoding::h264_encoder::{H264Config, H264Encoder, H264Profile};
+pub use crate::encoding::hardware_encoder::{EncoderBackend, HardwareEncoder};
+pub use crate::encoding::hevc_encoder::{HevcConfig, HevcEncoder, HevcLevel, HevcTier};
+#[cfg(feature = "jpeg")]
+pub use crate::encoding::jpeg_capture::JpegCapture;
+pub use crate::encoding::mp4_muxer::Mp4Muxer;
+pub use crate::encoding::replay_buffer::ReplayBuffer;
+pub use crate::encoding::rtsp_sink::RtspSink;
+pub use crate::encoding::webm_muxer::WebmMuxer;
+#[cfg(feature = "websocket")]
+pub use crate::encoding::ws_frame_server::WsFrameServer;
+pub use crate::encoding::y4m_writer::Y4MWriter;
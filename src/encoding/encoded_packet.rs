@@ -0,0 +1,16 @@
+use std::time::Duration;
+
+/// # Encoded Packet
+///
+/// A single compressed frame produced by one of the video encoders.
+#[derive(Clone, Debug, Default)]
+pub struct EncodedPacket {
+    /// The compressed frame, an Annex-B byte stream (NAL units prefixed with start codes) for H.264 and HEVC or a single frame for VP8 and VP9.
+    pub data: Vec<u8>,
+
+    /// The presentation time of the frame, taken from the timestamp of the source frame.
+    pub timestamp: Duration,
+
+    /// If the frame is a keyframe (IDR), decoding can start at a keyframe.
+    pub keyframe: bool,
+}
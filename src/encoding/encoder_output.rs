@@ -0,0 +1,65 @@
+use std::{pin::Pin, sync::Arc};
+
+use tokio::sync::{Mutex, mpsc::Receiver};
+
+use crate::{capture_error::CaptureError, devices::Dimensions, encoding::EncodedPacket};
+
+/// # Video Codec
+///
+/// The compression of the packets of an encoder.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum VideoCodec {
+    /// H.264/AVC.
+    H264,
+
+    /// H.265/HEVC.
+    Hevc,
+
+    /// VP8, for WebM files.
+    Vp8,
+
+    /// VP9, for WebM files.
+    Vp9,
+}
+
+/// # Encoder Output
+///
+/// An encoder whose packets can be written to a container, such as with the Mp4Muxer.
+///
+/// Implemented by the H264Encoder, HevcEncoder and HardwareEncoder, can be implemented for other encoders (such as a VP8 or VP9 encoder) to use the muxers.
+pub trait EncoderOutput: Send + Sync {
+    /// # Codec
+    ///
+    /// The codec the packets are compressed with.
+    fn codec(&self) -> VideoCodec;
+
+    /// # Get Encoded Dimensions
+    ///
+    /// The size of the encoded frames, codecs need an even size so an odd source loses its last column or row.
+    fn get_encoded_dimensions(&self) -> Result<Dimensions, CaptureError>;
+
+    /// # Frame Rate
+    ///
+    /// The frame rate the encoder was configured with.
+    fn frame_rate(&self) -> u32;
+
+    /// # Clone Packet Receiver
+    ///
+    /// Get the receiver reference the encoded packets are sent to.
+    fn clone_packet_receiver(&self) -> Arc<Mutex<Receiver<EncodedPacket>>>;
+
+    /// # Finished
+    ///
+    /// Completes once the encoder has stopped and sent its last packet to the receiver, the receiver stays open so the encoder can be started again.
+    ///
+    /// Before the encoder is started this waits for it to start and finish.
+    fn finished(&self) -> Pin<Box<dyn Future<Output = ()> + Send + '_>>;
+}
+
+// the encoded size of a source, shared by the encoder implementations
+pub(crate) fn encoded_dimensions(source: Dimensions) -> Dimensions {
+    Dimensions {
+        width: source.width & !1,
+        height: source.height & !1,
+    }
+}
@@ -0,0 +1,267 @@
+use std::{
+    pin::Pin,
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::{SyncSender, TrySendError},
+    },
+    time::Instant,
+};
+
+use tokio::{
+    sync::{
+        Mutex,
+        mpsc::{self, Receiver, Sender},
+        watch,
+    },
+    task::JoinHandle,
+};
+
+use crate::{
+    capture_error::CaptureError,
+    devices::{Frame, camera::Output},
+    encoding::{
+        EncodedPacket, EncoderBackend,
+        mft_encoder::{self, EncoderFrame, EncoderSettings},
+    },
+    i_capture::ICapture,
+    i_gpu_capture::IGpuCapture,
+};
+
+// starts or stops the source, ICapture::stop_capturing or IGpuCapture::stop_gpu_capturing
+type SourceControl<S> =
+    fn(Arc<S>) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>> + Send>>;
+
+// where the encoder is in its run, for waiting on the last packet
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum EncoderState {
+    Idle,
+    Running,
+    Finished,
+}
+
+// how many encoded packets can wait for the consumer before frames are dropped
+const PACKET_CAPACITY: usize = 8;
+
+// runs a capture and hands its frames to a media foundation encoder, shared by every encoder type.
+//
+// the encoder runs on the blocking thread pool, when the consumer (or the encoder) falls behind,
+// source frames are dropped instead of slowing down the source.
+pub(crate) struct EncoderTask<S: ICapture<CaptureOutput = Frame>> {
+    // the capture frames are taken from
+    pub(crate) source: Arc<S>,
+
+    // the name of the encoder in errors
+    name: &'static str,
+
+    // to send encoded packets
+    sender: Sender<EncodedPacket>,
+
+    // determines if the encoder is running
+    is_encoding: AtomicBool,
+
+    // finished once the encoder has sent its last packet
+    state: watch::Sender<EncoderState>,
+
+    // source frames that were dropped because the consumer or the encoder was behind
+    dropped_frames: AtomicU64,
+
+    // the encoder in use, set once the encoder is made
+    backend: Arc<RwLock<Option<EncoderBackend>>>,
+
+    // stops the source the way it was started
+    stop_source: RwLock<SourceControl<S>>,
+}
+
+impl<S: ICapture<CaptureOutput = Frame> + 'static> EncoderTask<S> {
+    // the task and the receiver of its encoded packets
+    pub(crate) fn new(source: Arc<S>, name: &'static str) -> (Arc<Self>, Receiver<EncodedPacket>) {
+        let (tx, rx) = mpsc::channel(PACKET_CAPACITY);
+
+        let task = Arc::new(Self {
+            source,
+            name,
+            sender: tx,
+            is_encoding: AtomicBool::new(false),
+            state: watch::Sender::new(EncoderState::Idle),
+            dropped_frames: AtomicU64::new(0),
+            backend: Arc::new(RwLock::new(None)),
+            stop_source: RwLock::new(S::stop_capturing as SourceControl<S>),
+        });
+
+        (task, rx)
+    }
+
+    pub(crate) fn dropped_frames(&self) -> u64 {
+        self.dropped_frames.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn backend(&self) -> Option<EncoderBackend> {
+        *self.backend.read().unwrap()
+    }
+
+    // starts the source and spawns the task encoding its frames with the settings
+    pub(crate) fn start(
+        self: &Arc<Self>,
+        settings: EncoderSettings,
+    ) -> Result<JoinHandle<Result<(), CaptureError>>, CaptureError> {
+        if !matches!(
+            self.source.get_output_format(),
+            Output::RGB32 | Output::NV12
+        ) {
+            return Err(CaptureError::Encoding(format!(
+                "{} requires a source that delivers RGB32 or NV12 frames",
+                self.name
+            )));
+        }
+
+        self.spawn(
+            settings,
+            S::start_capturing,
+            S::stop_capturing,
+            self.source.clone_receiver(),
+            EncoderFrame::Memory,
+        )
+    }
+
+    // spawns the task running the source and the encoder, wrap turns the frames of the receiver into encoder frames
+    fn spawn<T: Send + 'static>(
+        self: &Arc<Self>,
+        settings: EncoderSettings,
+        start_source: SourceControl<S>,
+        stop_source: SourceControl<S>,
+        receiver: Arc<Mutex<Receiver<T>>>,
+        wrap: impl FnMut(T) -> EncoderFrame + Send + 'static,
+    ) -> Result<JoinHandle<Result<(), CaptureError>>, CaptureError> {
+        if self.is_encoding.swap(true, Ordering::AcqRel) {
+            return Err(CaptureError::Other("the encoder is already running".into()));
+        }
+
+        *self.stop_source.write().unwrap() = stop_source;
+        *self.backend.write().unwrap() = None;
+        self.state.send_replace(EncoderState::Running);
+
+        let task = self.clone();
+
+        Ok(tokio::spawn(async move {
+            let result = task.run(settings, start_source, receiver, wrap).await;
+            task.is_encoding.store(false, Ordering::Release);
+            task.state.send_replace(EncoderState::Finished);
+            result
+        }))
+    }
+
+    pub(crate) async fn stop(&self) -> Result<(), CaptureError> {
+        if !self.is_encoding.load(Ordering::Acquire) {
+            return Err(CaptureError::Other("the encoder is not running".into()));
+        }
+
+        self.stop_source().await
+    }
+
+    // completes once a started encoder has finished and sent its last packet, waits for a start when the encoder never ran
+    pub(crate) async fn finished(&self) {
+        let mut state = self.state.subscribe();
+        let _ = state
+            .wait_for(|state| *state == EncoderState::Finished)
+            .await;
+    }
+
+    async fn stop_source(&self) -> Result<(), CaptureError> {
+        let stop_source = *self.stop_source.read().unwrap();
+
+        stop_source(self.source.clone())
+            .await
+            .map_err(CaptureError::from)
+    }
+
+    async fn run<T>(
+        &self,
+        settings: EncoderSettings,
+        start_source: SourceControl<S>,
+        receiver: Arc<Mutex<Receiver<T>>>,
+        wrap: impl FnMut(T) -> EncoderFrame,
+    ) -> Result<(), CaptureError> {
+        //a single frame can wait for the encoder, anything more is dropped
+        let (frame_tx, frame_rx) = std::sync::mpsc::sync_channel(1);
+        let packets = self.sender.clone();
+        let backend = self.backend.clone();
+
+        let encoder = tokio::task::spawn_blocking(move || {
+            mft_encoder::encode_stream(settings, frame_rx, packets, &backend)
+        });
+
+        //the forwarding loop only ends with the source channel, so the source finishing ends the encoding
+        let source_result = tokio::select! {
+            result = start_source(self.source.clone()) => result.map_err(CaptureError::from),
+            _ = self.forward_frames(receiver, frame_tx, wrap) => Ok(()),
+        };
+
+        //the frame sender was dropped with the forwarding loop, so the encoder flushes and finishes
+        let encoded = encoder
+            .await
+            .map_err(|e| CaptureError::Other(e.to_string()))?;
+
+        encoded.and(source_result)
+    }
+
+    // hands source frames to the encoder thread, dropping them while the encoder or the consumer is behind.
+    //
+    // the source is always drained so it never blocks on a full channel.
+    async fn forward_frames<T>(
+        &self,
+        source_receiver: Arc<Mutex<Receiver<T>>>,
+        frames: SyncSender<EncoderFrame>,
+        mut wrap: impl FnMut(T) -> EncoderFrame,
+    ) {
+        let mut source_receiver = source_receiver.lock().await;
+        let mut encoder_running = true;
+
+        while let Some(frame) = source_receiver.recv().await {
+            if !encoder_running {
+                continue;
+            }
+
+            if self.sender.capacity() == 0 {
+                self.dropped_frames.fetch_add(1, Ordering::Relaxed);
+                continue;
+            }
+
+            match frames.try_send(wrap(frame)) {
+                Ok(()) => {}
+                Err(TrySendError::Full(_)) => {
+                    self.dropped_frames.fetch_add(1, Ordering::Relaxed);
+                }
+                //the encoder failed, its error is returned once the source stops
+                Err(TrySendError::Disconnected(_)) => {
+                    trace_event!(error, encoder = self.name, "encoder stopped");
+
+                    encoder_running = false;
+                    let _ = self.stop_source().await;
+                }
+            }
+        }
+    }
+}
+
+impl<S: ICapture<CaptureOutput = Frame> + IGpuCapture + 'static> EncoderTask<S> {
+    // starts the source as a GPU capture and spawns the task encoding its textures, the settings must have the device of the source
+    pub(crate) fn start_gpu(
+        self: &Arc<Self>,
+        settings: EncoderSettings,
+    ) -> Result<JoinHandle<Result<(), CaptureError>>, CaptureError> {
+        //textures do not carry a timestamp, they are stamped when they arrive
+        let mut started = None;
+
+        self.spawn(
+            settings,
+            S::start_gpu_capturing,
+            S::stop_gpu_capturing,
+            self.source.clone_gpu_receiver(),
+            move |frame| {
+                let started = *started.get_or_insert_with(Instant::now);
+                EncoderFrame::Texture(frame, started.elapsed())
+            },
+        )
+    }
+}
@@ -0,0 +1,363 @@
+use std::{borrow::Cow, fs::File, io::BufWriter, path::Path, sync::Arc, time::Duration};
+
+use color_quant::NeuQuant;
+use tokio::time::Instant;
+
+use crate::{
+    capture_error::CaptureError,
+    devices::{Dimensions, Frame, camera::Output},
+    i_capture::ICapture,
+};
+
+// gif delays are in hundredths of a second and browsers slow down frames shorter than 2/100 s
+const MAX_FPS: u8 = 50;
+
+// the frame rate and colors of from_capture
+const DEFAULT_FPS: u8 = 15;
+const DEFAULT_COLORS: u16 = 256;
+
+// how many pixels NeuQuant skips while learning the palette, 1 is the best and slowest, 30 the fastest
+const QUANTIZER_SPEED: i32 = 10;
+
+type GifEncoder = gif::Encoder<BufWriter<File>>;
+
+/// # Gif Dithering
+///
+/// How a GifRecorder maps the colors of a frame onto the palette of at most 256 colors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum GifDithering {
+    /// Every pixel gets the closest palette color, keeps text and flat areas sharp.
+    #[default]
+    None,
+
+    /// The error of every pixel is spread to its neighbours (Floyd-Steinberg), smoother gradients with a grainy look.
+    FloydSteinberg,
+}
+
+/// # Gif Recorder
+///
+/// Writes RGBA frames to an animated GIF that loops forever, every frame with its own palette.
+///
+/// The frame rate is clamped to 50 fps, the fastest rate browsers play at. Around 15 fps is a good fit for screen recordings.
+///
+/// The file is complete after finish, dropping the recorder without it may leave the file without its trailer.
+pub struct GifRecorder {
+    // taken while a frame is encoded on the blocking thread pool
+    encoder: Option<GifEncoder>,
+
+    width: u32,
+    height: u32,
+    fps: u8,
+    max_colors: u16,
+    dithering: GifDithering,
+
+    frames_written: u64,
+}
+
+impl GifRecorder {
+    /// # New
+    ///
+    /// Creates (or truncates) the file for frames of the given size, max_colors is the palette size of every frame from 2 to 256.
+    ///
+    /// GIF images are at most 65535 pixels wide and high.
+    pub fn new(
+        path: &Path,
+        width: u32,
+        height: u32,
+        fps: u8,
+        max_colors: u16,
+    ) -> Result<Self, CaptureError> {
+        if width == 0 || height == 0 || width > u16::MAX as u32 || height > u16::MAX as u32 {
+            return Err(CaptureError::UnsupportedFormat(format!(
+                "a gif can not be {width}x{height}, the width and height must be from 1 to 65535"
+            )));
+        }
+
+        let file = BufWriter::new(File::create(path)?);
+
+        let mut encoder =
+            gif::Encoder::new(file, width as u16, height as u16, &[]).map_err(encoding_error)?;
+        encoder
+            .set_repeat(gif::Repeat::Infinite)
+            .map_err(encoding_error)?;
+
+        Ok(Self {
+            encoder: Some(encoder),
+            width,
+            height,
+            fps: fps.clamp(1, MAX_FPS),
+            max_colors: max_colors.clamp(2, 256),
+            dithering: GifDithering::None,
+            frames_written: 0,
+        })
+    }
+
+    /// # From Capture
+    ///
+    /// Records the capture for the duration at 15 fps and writes the GIF, the capture is started and stopped by the recorder.
+    ///
+    /// Frames keep the timing of the capture, a monitor that only sends frames when the desktop changes shows each frame until the next one.
+    ///
+    /// Returns CaptureError::UnsupportedFormat unless the capture delivers RGB32 frames.
+    pub async fn from_capture<S: ICapture<CaptureOutput = Frame> + 'static>(
+        source: Arc<S>,
+        duration: Duration,
+        path: &Path,
+    ) -> Result<(), CaptureError> {
+        let input = source.get_output_format();
+
+        if input != Output::RGB32 {
+            return Err(CaptureError::UnsupportedFormat(format!(
+                "a gif can only be recorded from RGB32 frames, the capture delivers {input:?}"
+            )));
+        }
+
+        let Dimensions { width, height } = source.get_dimensions().map_err(CaptureError::from)?;
+
+        let mut recorder = Self::new(path, width, height, DEFAULT_FPS, DEFAULT_COLORS)?;
+        let frame_duration = Duration::from_secs_f64(1.0 / recorder.fps as f64);
+
+        let receiver = source.clone_receiver();
+        let session = source.start_session();
+        let deadline = Instant::now() + duration;
+
+        //a frame is written once the next one arrives, when it is known how long it is shown
+        let mut pending: Option<(Vec<u8>, Duration)> = None;
+
+        let recorded = async {
+            let mut receiver = receiver.lock().await;
+
+            while let Ok(Some(frame)) = tokio::time::timeout_at(deadline, receiver.recv()).await {
+                //frames faster than the frame rate are dropped
+                if let Some((_, shown_at)) = &pending
+                    && frame.timestamp.saturating_sub(*shown_at) < frame_duration
+                {
+                    continue;
+                }
+
+                let rgba = bgra_to_rgba(&frame, width as usize, height as usize);
+
+                if let Some((previous, shown_at)) = pending.replace((rgba, frame.timestamp)) {
+                    recorder
+                        .write_frame(previous, frame.timestamp.saturating_sub(shown_at))
+                        .await?;
+                }
+            }
+
+            if let Some((last, _)) = pending.take() {
+                recorder.write_frame(last, frame_duration).await?;
+            }
+
+            Ok::<(), CaptureError>(())
+        }
+        .await;
+
+        session.stop().await?;
+        recorded?;
+
+        recorder.finish().await
+    }
+
+    /// # Set Dithering
+    ///
+    /// How the colors of the next frames are mapped onto their palette, GifDithering::None by default.
+    pub fn set_dithering(&mut self, dithering: GifDithering) {
+        self.dithering = dithering;
+    }
+
+    /// # Dithering
+    ///
+    /// How the colors of a frame are mapped onto its palette.
+    pub fn dithering(&self) -> GifDithering {
+        self.dithering
+    }
+
+    /// # Dimensions
+    ///
+    /// The size of every frame of the file.
+    pub fn dimensions(&self) -> Dimensions {
+        Dimensions {
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    /// # Fps
+    ///
+    /// The frame rate frames are shown at, after clamping.
+    pub fn fps(&self) -> u8 {
+        self.fps
+    }
+
+    /// # Frames Written
+    ///
+    /// How many frames have been written to the file.
+    pub fn frames_written(&self) -> u64 {
+        self.frames_written
+    }
+
+    /// # Add Frame
+    ///
+    /// Quantizes a tightly packed RGBA frame to its own palette and writes it, shown for 1/fps seconds.
+    ///
+    /// Returns CaptureError::Encoding when the frame does not have width * height * 4 bytes.
+    pub async fn add_frame(&mut self, rgba: &[u8]) -> Result<(), CaptureError> {
+        let delay = Duration::from_secs_f64(1.0 / self.fps as f64);
+
+        self.write_frame(rgba.to_vec(), delay).await
+    }
+
+    /// # Finish
+    ///
+    /// Writes the trailer of the GIF and flushes the file.
+    pub async fn finish(mut self) -> Result<(), CaptureError> {
+        let Some(encoder) = self.encoder.take() else {
+            return Err(CaptureError::Encoding(
+                "an earlier frame failed, the gif is incomplete".into(),
+            ));
+        };
+
+        tokio::task::spawn_blocking(move || -> Result<(), CaptureError> {
+            let mut file = encoder.into_inner()?;
+            std::io::Write::flush(&mut file)?;
+
+            Ok(())
+        })
+        .await
+        .map_err(|e| CaptureError::Other(e.to_string()))?
+    }
+
+    // encodes the frame on the blocking thread pool, shown for delay (rounded to hundredths of a second)
+    async fn write_frame(&mut self, rgba: Vec<u8>, delay: Duration) -> Result<(), CaptureError> {
+        let width = self.width as usize;
+        let height = self.height as usize;
+
+        if rgba.len() != width * height * 4 {
+            return Err(CaptureError::Encoding(format!(
+                "a {width}x{height} gif frame needs {} bytes of RGBA, got {}",
+                width * height * 4,
+                rgba.len()
+            )));
+        }
+
+        //a failed write leaves the encoder taken, the file cannot be continued
+        let Some(mut encoder) = self.encoder.take() else {
+            return Err(CaptureError::Encoding(
+                "an earlier frame failed, the gif is incomplete".into(),
+            ));
+        };
+
+        let max_colors = self.max_colors as usize;
+        let dithering = self.dithering;
+        let delay = ((delay.as_secs_f64() * 100.0).round() as u16).max(100 / MAX_FPS as u16);
+
+        let encoder = tokio::task::spawn_blocking(move || -> Result<GifEncoder, CaptureError> {
+            let (palette, indices) = quantize(&rgba, width, max_colors, dithering);
+
+            let frame = gif::Frame {
+                width: width as u16,
+                height: height as u16,
+                buffer: Cow::Owned(indices),
+                palette: Some(palette),
+                delay,
+                ..Default::default()
+            };
+
+            encoder.write_frame(&frame).map_err(encoding_error)?;
+
+            Ok(encoder)
+        })
+        .await
+        .map_err(|e| CaptureError::Other(e.to_string()))??;
+
+        self.encoder = Some(encoder);
+        self.frames_written += 1;
+
+        Ok(())
+    }
+}
+
+fn encoding_error(e: gif::EncodingError) -> CaptureError {
+    CaptureError::Encoding(e.to_string())
+}
+
+// a palette for the frame (RGB) and the palette index of every pixel
+fn quantize(
+    rgba: &[u8],
+    width: usize,
+    max_colors: usize,
+    dithering: GifDithering,
+) -> (Vec<u8>, Vec<u8>) {
+    let quantizer = NeuQuant::new(QUANTIZER_SPEED, max_colors, rgba);
+    let palette = quantizer.color_map_rgb();
+
+    let indices = match dithering {
+        GifDithering::None => rgba
+            .chunks_exact(4)
+            .map(|pixel| quantizer.index_of(pixel) as u8)
+            .collect(),
+        GifDithering::FloydSteinberg => dither(rgba, width, &quantizer, &palette),
+    };
+
+    (palette, indices)
+}
+
+// maps every pixel onto the palette and spreads the difference to the pixels right and below
+fn dither(rgba: &[u8], width: usize, quantizer: &NeuQuant, palette: &[u8]) -> Vec<u8> {
+    let mut colors: Vec<[f32; 3]> = rgba
+        .chunks_exact(4)
+        .map(|pixel| [pixel[0] as f32, pixel[1] as f32, pixel[2] as f32])
+        .collect();
+
+    let mut indices = Vec::with_capacity(colors.len());
+
+    for i in 0..colors.len() {
+        let color = colors[i].map(|channel| channel.clamp(0.0, 255.0));
+        let index = quantizer.index_of(&[color[0] as u8, color[1] as u8, color[2] as u8, 255]);
+        indices.push(index as u8);
+
+        let chosen = &palette[index * 3..index * 3 + 3];
+        let error = [0, 1, 2].map(|c| color[c] - chosen[c] as f32);
+
+        let x = i % width;
+        let mut spread = |target: usize, weight: f32| {
+            if let Some(neighbour) = colors.get_mut(target) {
+                for c in 0..3 {
+                    neighbour[c] += error[c] * weight;
+                }
+            }
+        };
+
+        if x + 1 < width {
+            spread(i + 1, 7.0 / 16.0);
+            spread(i + width + 1, 1.0 / 16.0);
+        }
+
+        if x > 0 {
+            spread(i + width - 1, 3.0 / 16.0);
+        }
+
+        spread(i + width, 5.0 / 16.0);
+    }
+
+    indices
+}
+
+// a (possibly padded) BGRA capture frame as tightly packed RGBA
+fn bgra_to_rgba(frame: &Frame, width: usize, height: usize) -> Vec<u8> {
+    let stride = if frame.stride > 0 {
+        frame.stride
+    } else {
+        width * 4
+    };
+
+    let mut rgba = Vec::with_capacity(width * height * 4);
+
+    for row in frame.data.chunks(stride).take(height) {
+        for pixel in row[..(width * 4).min(row.len())].chunks_exact(4) {
+            rgba.extend_from_slice(&[pixel[2], pixel[1], pixel[0], 255]);
+        }
+    }
+
+    rgba
+}
@@ -0,0 +1,194 @@
+use std::{pin::Pin, sync::Arc};
+
+use tokio::{
+    sync::{Mutex, mpsc::Receiver},
+    task::JoinHandle,
+};
+use windows::Win32::{
+    Media::MediaFoundation::{
+        CLSID_MSH264EncoderMFT, IMFTransform, MFVideoFormat_H264, eAVEncH264VProfile,
+        eAVEncH264VProfile_Base, eAVEncH264VProfile_High, eAVEncH264VProfile_Main,
+    },
+    System::Com::{CLSCTX_INPROC_SERVER, CoCreateInstance},
+};
+
+use crate::{
+    capture_error::CaptureError,
+    devices::{Dimensions, Frame},
+    encoding::{
+        EncodedPacket,
+        encoder_output::{EncoderOutput, VideoCodec, encoded_dimensions},
+        encoder_task::EncoderTask,
+        mft_encoder::EncoderSettings,
+    },
+    i_capture::ICapture,
+};
+
+/// # H264 Profile
+///
+/// The H.264 profile the encoder produces, higher profiles compress better but need a more capable decoder.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum H264Profile {
+    /// Constrained baseline, decodable everywhere.
+    Baseline,
+    #[default]
+    Main,
+    High,
+}
+
+impl H264Profile {
+    pub(crate) fn to_eav(self) -> eAVEncH264VProfile {
+        match self {
+            H264Profile::Baseline => eAVEncH264VProfile_Base,
+            H264Profile::Main => eAVEncH264VProfile_Main,
+            H264Profile::High => eAVEncH264VProfile_High,
+        }
+    }
+}
+
+/// # H264 Config
+///
+/// How an H264Encoder encodes its frames.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct H264Config {
+    /// The average bitrate in kilobits per second.
+    pub bitrate_kbps: u32,
+
+    /// The time between two keyframes in seconds.
+    pub keyframe_interval_secs: f32,
+
+    pub profile: H264Profile,
+
+    /// The expected frame rate of the source, used for rate control and the keyframe interval.
+    ///
+    /// The timestamps of the encoded packets are always taken from the source frames.
+    pub frame_rate: u32,
+}
+
+impl Default for H264Config {
+    /// 8 Mbps main profile at 30 fps with a keyframe every 2 seconds.
+    fn default() -> Self {
+        Self {
+            bitrate_kbps: 8000,
+            keyframe_interval_secs: 2.0,
+            profile: H264Profile::Main,
+            frame_rate: 30,
+        }
+    }
+}
+
+/// # H264 Encoder
+///
+/// Encodes the frames of any capture that delivers RGB32 (BGRA) or NV12 frames to H.264 with the Media Foundation software encoder.
+///
+/// Encoded packets are sent to the receiver as an Annex-B byte stream, the sequence and picture parameter sets are sent in band with every keyframe.
+///
+/// The encoder runs in its own task, when the consumer (or the encoder) falls behind, source frames are dropped instead of slowing down the source.
+pub struct H264Encoder<S: ICapture<CaptureOutput = Frame>> {
+    // runs the source and the encoder
+    task: Arc<EncoderTask<S>>,
+
+    config: H264Config,
+
+    /// The receiver, can be used to grab the encoded packets.
+    pub receiver: Arc<Mutex<Receiver<EncodedPacket>>>,
+}
+
+impl<S: ICapture<CaptureOutput = Frame> + 'static> H264Encoder<S> {
+    /// # New
+    ///
+    /// Wraps the source, capturing is started and stopped through the encoder, do not start the source yourself.
+    pub fn new(source: Arc<S>, config: H264Config) -> Arc<Self> {
+        let (task, rx) = EncoderTask::new(source, "H264Encoder");
+
+        Arc::new(Self {
+            task,
+            config,
+            receiver: Arc::new(Mutex::new(rx)),
+        })
+    }
+
+    /// # Config
+    ///
+    /// The settings the frames are encoded with.
+    pub fn config(&self) -> H264Config {
+        self.config
+    }
+
+    /// # Source
+    ///
+    /// The capture the frames are taken from.
+    pub fn source(&self) -> Arc<S> {
+        self.task.source.clone()
+    }
+
+    /// # Dropped Frames
+    ///
+    /// How many source frames were not encoded because the consumer or the encoder fell behind.
+    pub fn dropped_frames(&self) -> u64 {
+        self.task.dropped_frames()
+    }
+
+    /// # Start
+    ///
+    /// Starts the source and spawns the task that encodes its frames.
+    ///
+    /// The returned handle completes once the source has stopped and every frame inside of the encoder has been sent to the receiver.
+    pub fn start(self: &Arc<Self>) -> Result<JoinHandle<Result<(), CaptureError>>, CaptureError> {
+        let source = &self.task.source;
+
+        self.task.start(EncoderSettings {
+            create_transform: create_software_encoder,
+            subtype: MFVideoFormat_H264,
+            profile: self.config.profile.to_eav().0 as u32,
+            level: None,
+            hardware: false,
+            backend: None,
+            device: None,
+            bitrate_kbps: self.config.bitrate_kbps,
+            keyframe_interval_secs: self.config.keyframe_interval_secs,
+            frame_rate: self.config.frame_rate,
+            dimensions: source.get_dimensions().map_err(CaptureError::from)?,
+            input: source.get_output_format(),
+        })
+    }
+
+    /// # Stop
+    ///
+    /// Stops the source, the task started by start finishes once the remaining frames are encoded.
+    pub async fn stop(&self) -> Result<(), CaptureError> {
+        self.task.stop().await
+    }
+}
+
+impl<S: ICapture<CaptureOutput = Frame> + 'static> EncoderOutput for H264Encoder<S> {
+    fn codec(&self) -> VideoCodec {
+        VideoCodec::H264
+    }
+
+    fn get_encoded_dimensions(&self) -> Result<Dimensions, CaptureError> {
+        let dimensions = self
+            .task
+            .source
+            .get_dimensions()
+            .map_err(CaptureError::from)?;
+        Ok(encoded_dimensions(dimensions))
+    }
+
+    fn frame_rate(&self) -> u32 {
+        self.config.frame_rate
+    }
+
+    fn clone_packet_receiver(&self) -> Arc<Mutex<Receiver<EncodedPacket>>> {
+        self.receiver.clone()
+    }
+
+    fn finished(&self) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
+        Box::pin(self.task.finished())
+    }
+}
+
+// the Media Foundation software H.264 encoder
+pub(crate) fn create_software_encoder() -> Result<IMFTransform, windows::core::Error> {
+    unsafe { CoCreateInstance(&CLSID_MSH264EncoderMFT, None, CLSCTX_INPROC_SERVER) }
+}
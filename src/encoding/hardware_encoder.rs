@@ -0,0 +1,244 @@
+use std::{pin::Pin, sync::Arc};
+
+use tokio::{
+    sync::{Mutex, mpsc::Receiver},
+    task::JoinHandle,
+};
+use windows::Win32::{
+    Graphics::Direct3D11::ID3D11Device, Media::MediaFoundation::MFVideoFormat_H264,
+};
+
+use crate::{
+    capture_error::CaptureError,
+    devices::{Dimensions, Frame, Monitor},
+    encoding::{
+        EncodedPacket, H264Config,
+        encoder_output::{EncoderOutput, VideoCodec, encoded_dimensions},
+        encoder_task::EncoderTask,
+        h264_encoder::create_software_encoder,
+        mft_encoder::{self, EncoderSettings},
+    },
+    i_capture::ICapture,
+    i_gpu_capture::IGpuCapture,
+};
+
+/// # Encoder Backend
+///
+/// The encoder a HardwareEncoder encodes with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EncoderBackend {
+    /// NVIDIA NVENC.
+    Nvenc,
+
+    /// Intel Quick Sync Video.
+    QuickSync,
+
+    /// AMD Advanced Media Framework.
+    Amf,
+
+    /// A hardware encoder of another vendor, such as Qualcomm.
+    Other,
+
+    /// The Media Foundation software encoder, running on the CPU.
+    Software,
+}
+
+impl EncoderBackend {
+    /// # Is Hardware
+    ///
+    /// If the backend encodes on the GPU.
+    pub fn is_hardware(&self) -> bool {
+        *self != EncoderBackend::Software
+    }
+
+    // the backend of a hardware encoder from its PCI vendor id, such as VEN_10DE
+    pub(crate) fn from_vendor_id(vendor_id: &str) -> Self {
+        let vendor = vendor_id.trim_start_matches("VEN_");
+
+        if vendor.eq_ignore_ascii_case("10DE") {
+            EncoderBackend::Nvenc
+        } else if vendor.eq_ignore_ascii_case("8086") {
+            EncoderBackend::QuickSync
+        } else if vendor.eq_ignore_ascii_case("1002") {
+            EncoderBackend::Amf
+        } else {
+            EncoderBackend::Other
+        }
+    }
+}
+
+/// # Hardware Encoder
+///
+/// Encodes the frames of a capture to H.264 on the hardware encoder of the GPU (NVENC, Quick Sync or AMF) with Media Foundation.
+///
+/// When no hardware encoder is installed, or the chosen backend cannot encode the frames, the software encoder is used instead, backend tells which one is encoding.
+///
+/// Frames in system memory (RGB32 or NV12) are encoded with start, captures that also implement IGpuCapture (such as a Monitor) can be encoded without leaving the GPU with start_gpu.
+///
+/// Encoded packets are sent to the receiver as an Annex-B byte stream, like the H264Encoder.
+pub struct HardwareEncoder<S: ICapture<CaptureOutput = Frame>> {
+    // runs the source and the encoder
+    task: Arc<EncoderTask<S>>,
+
+    config: H264Config,
+
+    // the hardware encoder to use, any when none is set
+    preferred_backend: Option<EncoderBackend>,
+
+    /// The receiver, can be used to grab the encoded packets.
+    pub receiver: Arc<Mutex<Receiver<EncodedPacket>>>,
+}
+
+//not generic, so it can be called as HardwareEncoder::supported_backends()
+impl HardwareEncoder<Monitor> {
+    /// # Supported Backends
+    ///
+    /// The hardware H.264 encoders installed on this system, best first, followed by EncoderBackend::Software which is always available.
+    pub fn supported_backends() -> Vec<EncoderBackend> {
+        let mut backends = mft_encoder::hardware_backends(&MFVideoFormat_H264).unwrap_or_default();
+        backends.push(EncoderBackend::Software);
+
+        backends
+    }
+}
+
+impl<S: ICapture<CaptureOutput = Frame> + 'static> HardwareEncoder<S> {
+    /// # New
+    ///
+    /// Wraps the source, capturing is started and stopped through the encoder, do not start the source yourself.
+    ///
+    /// The best hardware encoder that accepts the frames is used.
+    pub fn new(source: Arc<S>, config: H264Config) -> Arc<Self> {
+        Self::create(source, config, None)
+    }
+
+    /// # With Backend
+    ///
+    /// Same as new, encoding on the given backend, the software encoder is used when the backend is not available.
+    pub fn with_backend(source: Arc<S>, config: H264Config, backend: EncoderBackend) -> Arc<Self> {
+        Self::create(source, config, Some(backend))
+    }
+
+    fn create(
+        source: Arc<S>,
+        config: H264Config,
+        preferred_backend: Option<EncoderBackend>,
+    ) -> Arc<Self> {
+        let (task, rx) = EncoderTask::new(source, "HardwareEncoder");
+
+        Arc::new(Self {
+            task,
+            config,
+            preferred_backend,
+            receiver: Arc::new(Mutex::new(rx)),
+        })
+    }
+
+    /// # Config
+    ///
+    /// The settings the frames are encoded with.
+    pub fn config(&self) -> H264Config {
+        self.config
+    }
+
+    /// # Source
+    ///
+    /// The capture the frames are taken from.
+    pub fn source(&self) -> Arc<S> {
+        self.task.source.clone()
+    }
+
+    /// # Backend
+    ///
+    /// The encoder the frames are encoded with, None until the encoder has been made after start.
+    pub fn backend(&self) -> Option<EncoderBackend> {
+        self.task.backend()
+    }
+
+    /// # Dropped Frames
+    ///
+    /// How many source frames were not encoded because the consumer or the encoder fell behind.
+    pub fn dropped_frames(&self) -> u64 {
+        self.task.dropped_frames()
+    }
+
+    /// # Start
+    ///
+    /// Starts the source and spawns the task that encodes its frames, which are copied to the encoder from system memory.
+    ///
+    /// The returned handle completes once the source has stopped and every frame inside of the encoder has been sent to the receiver.
+    pub fn start(self: &Arc<Self>) -> Result<JoinHandle<Result<(), CaptureError>>, CaptureError> {
+        let settings = self.settings(None)?;
+        self.task.start(settings)
+    }
+
+    /// # Stop
+    ///
+    /// Stops the source, the task started by start or start_gpu finishes once the remaining frames are encoded.
+    pub async fn stop(&self) -> Result<(), CaptureError> {
+        self.task.stop().await
+    }
+
+    fn settings(&self, device: Option<ID3D11Device>) -> Result<EncoderSettings, CaptureError> {
+        let source = &self.task.source;
+
+        Ok(EncoderSettings {
+            create_transform: create_software_encoder,
+            subtype: MFVideoFormat_H264,
+            profile: self.config.profile.to_eav().0 as u32,
+            level: None,
+            hardware: true,
+            backend: self.preferred_backend,
+            device,
+            bitrate_kbps: self.config.bitrate_kbps,
+            keyframe_interval_secs: self.config.keyframe_interval_secs,
+            frame_rate: self.config.frame_rate,
+            dimensions: source.get_dimensions().map_err(CaptureError::from)?,
+            input: source.get_output_format(),
+        })
+    }
+}
+
+impl<S: ICapture<CaptureOutput = Frame> + 'static> EncoderOutput for HardwareEncoder<S> {
+    fn codec(&self) -> VideoCodec {
+        VideoCodec::H264
+    }
+
+    fn get_encoded_dimensions(&self) -> Result<Dimensions, CaptureError> {
+        let dimensions = self
+            .task
+            .source
+            .get_dimensions()
+            .map_err(CaptureError::from)?;
+        Ok(encoded_dimensions(dimensions))
+    }
+
+    fn frame_rate(&self) -> u32 {
+        self.config.frame_rate
+    }
+
+    fn clone_packet_receiver(&self) -> Arc<Mutex<Receiver<EncodedPacket>>> {
+        self.receiver.clone()
+    }
+
+    fn finished(&self) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
+        Box::pin(self.task.finished())
+    }
+}
+
+impl<S: ICapture<CaptureOutput = Frame> + IGpuCapture + 'static> HardwareEncoder<S> {
+    /// # Start GPU
+    ///
+    /// Starts the source as a GPU capture and spawns the task that encodes its textures, without copying them to system memory.
+    ///
+    /// Textures are converted to NV12 on the GPU, rotated upright and letterboxed into the size of get_dimensions, a hardware encoder reads them directly.
+    /// The software encoder reads them back from the GPU.
+    ///
+    /// Only Bgra8 textures can be encoded, an HDR capture ends with CaptureError::UnsupportedFormat.
+    pub fn start_gpu(
+        self: &Arc<Self>,
+    ) -> Result<JoinHandle<Result<(), CaptureError>>, CaptureError> {
+        let settings = self.settings(Some(self.task.source.gpu_device()))?;
+        self.task.start_gpu(settings)
+    }
+}
@@ -0,0 +1,259 @@
+use std::{pin::Pin, sync::Arc};
+
+use tokio::{
+    sync::{Mutex, mpsc::Receiver},
+    task::JoinHandle,
+};
+use windows::Win32::{
+    Media::MediaFoundation::{
+        CMSH265EncoderMFT, IMFTransform, MFT_ENUM_FLAG_LOCALMFT, MFT_ENUM_FLAG_SYNCMFT,
+        MFVideoFormat_HEVC, eAVEncH265VLevel, eAVEncH265VLevel3, eAVEncH265VLevel3_1,
+        eAVEncH265VLevel4, eAVEncH265VLevel4_1, eAVEncH265VLevel5, eAVEncH265VLevel5_1,
+        eAVEncH265VLevel5_2, eAVEncH265VLevel6, eAVEncH265VLevel6_1, eAVEncH265VLevel6_2,
+        eAVEncH265VProfile_Main_420_8,
+    },
+    System::Com::{CLSCTX_INPROC_SERVER, CoCreateInstance},
+};
+
+use crate::{
+    capture_error::CaptureError,
+    devices::{Dimensions, Frame},
+    encoding::{
+        EncodedPacket,
+        encoder_output::{EncoderOutput, VideoCodec, encoded_dimensions},
+        encoder_task::EncoderTask,
+        mft_encoder::{self, EncoderSettings},
+    },
+    i_capture::ICapture,
+};
+
+// media foundation marks the high tier in the level attribute
+const HIGH_TIER_FLAG: u32 = 0x100;
+
+/// # Hevc Tier
+///
+/// The HEVC tier, the high tier allows higher bitrates at the same level for professional use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum HevcTier {
+    #[default]
+    Main,
+    High,
+}
+
+/// # Hevc Level
+///
+/// The HEVC level, limiting the resolution, frame rate and bitrate a decoder has to handle.
+///
+/// Level 4.1 covers 1080p60, 5.1 covers 4K60 and 6.1 covers 8K60.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HevcLevel {
+    Level3,
+    Level3_1,
+    Level4,
+    Level4_1,
+    Level5,
+    Level5_1,
+    Level5_2,
+    Level6,
+    Level6_1,
+    Level6_2,
+}
+
+impl HevcLevel {
+    fn to_eav(self) -> eAVEncH265VLevel {
+        match self {
+            HevcLevel::Level3 => eAVEncH265VLevel3,
+            HevcLevel::Level3_1 => eAVEncH265VLevel3_1,
+            HevcLevel::Level4 => eAVEncH265VLevel4,
+            HevcLevel::Level4_1 => eAVEncH265VLevel4_1,
+            HevcLevel::Level5 => eAVEncH265VLevel5,
+            HevcLevel::Level5_1 => eAVEncH265VLevel5_1,
+            HevcLevel::Level5_2 => eAVEncH265VLevel5_2,
+            HevcLevel::Level6 => eAVEncH265VLevel6,
+            HevcLevel::Level6_1 => eAVEncH265VLevel6_1,
+            HevcLevel::Level6_2 => eAVEncH265VLevel6_2,
+        }
+    }
+}
+
+/// # Hevc Config
+///
+/// How a HevcEncoder encodes its frames, always with the 8 bit Main profile.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HevcConfig {
+    /// The average bitrate in kilobits per second.
+    pub bitrate_kbps: u32,
+
+    /// The time between two keyframes in seconds.
+    pub keyframe_interval_secs: f32,
+
+    /// The tier, only used when a level is set.
+    pub tier: HevcTier,
+
+    /// The level, the encoder picks one from the frame size and rate when none is set.
+    pub level: Option<HevcLevel>,
+
+    /// The expected frame rate of the source, used for rate control and the keyframe interval.
+    ///
+    /// The timestamps of the encoded packets are always taken from the source frames.
+    pub frame_rate: u32,
+
+    /// If a hardware encoder (Intel, AMD, NVIDIA) is tried before the software encoder.
+    pub prefer_hardware: bool,
+}
+
+impl Default for HevcConfig {
+    /// 5 Mbps at 30 fps with a keyframe every 2 seconds, on the hardware encoder if there is one.
+    fn default() -> Self {
+        Self {
+            bitrate_kbps: 5000,
+            keyframe_interval_secs: 2.0,
+            tier: HevcTier::Main,
+            level: None,
+            frame_rate: 30,
+            prefer_hardware: true,
+        }
+    }
+}
+
+impl HevcConfig {
+    // the MF_MT_VIDEO_LEVEL value of the tier and level
+    fn video_level(&self) -> Option<u32> {
+        let level = self.level?.to_eav().0 as u32;
+
+        Some(match self.tier {
+            HevcTier::Main => level,
+            HevcTier::High => level | HIGH_TIER_FLAG,
+        })
+    }
+}
+
+/// # Hevc Encoder
+///
+/// Encodes the frames of any capture that delivers RGB32 (BGRA) or NV12 frames to H.265/HEVC with Media Foundation.
+///
+/// The hardware encoder of the GPU is used when there is one (and prefer_hardware is set), otherwise the software encoder, which needs Windows 10 1709 or later.
+///
+/// Encoded packets are sent to the receiver as an Annex-B byte stream, the parameter sets are sent in band with every keyframe.
+///
+/// Like the H264Encoder, source frames are dropped instead of slowing down the source when the consumer (or the encoder) falls behind.
+pub struct HevcEncoder<S: ICapture<CaptureOutput = Frame>> {
+    // runs the source and the encoder
+    task: Arc<EncoderTask<S>>,
+
+    config: HevcConfig,
+
+    /// The receiver, can be used to grab the encoded packets.
+    pub receiver: Arc<Mutex<Receiver<EncodedPacket>>>,
+}
+
+impl<S: ICapture<CaptureOutput = Frame> + 'static> HevcEncoder<S> {
+    /// # New
+    ///
+    /// Wraps the source, capturing is started and stopped through the encoder, do not start the source yourself.
+    ///
+    /// Returns CaptureError::UnsupportedFormat when no HEVC encoder is installed.
+    pub fn new(source: Arc<S>, config: HevcConfig) -> Result<Arc<Self>, CaptureError> {
+        if !mft_encoder::encoder_available(&MFVideoFormat_HEVC)? {
+            return Err(CaptureError::UnsupportedFormat(
+                "no HEVC encoder is installed, HEVC encoding needs a GPU with an HEVC encoder or Windows 10 1709 or later with the HEVC Video Extensions".into(),
+            ));
+        }
+
+        let (task, rx) = EncoderTask::new(source, "HevcEncoder");
+
+        Ok(Arc::new(Self {
+            task,
+            config,
+            receiver: Arc::new(Mutex::new(rx)),
+        }))
+    }
+
+    /// # Config
+    ///
+    /// The settings the frames are encoded with.
+    pub fn config(&self) -> HevcConfig {
+        self.config
+    }
+
+    /// # Source
+    ///
+    /// The capture the frames are taken from.
+    pub fn source(&self) -> Arc<S> {
+        self.task.source.clone()
+    }
+
+    /// # Dropped Frames
+    ///
+    /// How many source frames were not encoded because the consumer or the encoder fell behind.
+    pub fn dropped_frames(&self) -> u64 {
+        self.task.dropped_frames()
+    }
+
+    /// # Start
+    ///
+    /// Starts the source and spawns the task that encodes its frames.
+    ///
+    /// The returned handle completes once the source has stopped and every frame inside of the encoder has been sent to the receiver.
+    pub fn start(self: &Arc<Self>) -> Result<JoinHandle<Result<(), CaptureError>>, CaptureError> {
+        let source = &self.task.source;
+
+        self.task.start(EncoderSettings {
+            create_transform: create_software_encoder,
+            subtype: MFVideoFormat_HEVC,
+            profile: eAVEncH265VProfile_Main_420_8.0 as u32,
+            level: self.config.video_level(),
+            hardware: self.config.prefer_hardware,
+            backend: None,
+            device: None,
+            bitrate_kbps: self.config.bitrate_kbps,
+            keyframe_interval_secs: self.config.keyframe_interval_secs,
+            frame_rate: self.config.frame_rate,
+            dimensions: source.get_dimensions().map_err(CaptureError::from)?,
+            input: source.get_output_format(),
+        })
+    }
+
+    /// # Stop
+    ///
+    /// Stops the source, the task started by start finishes once the remaining frames are encoded.
+    pub async fn stop(&self) -> Result<(), CaptureError> {
+        self.task.stop().await
+    }
+}
+
+impl<S: ICapture<CaptureOutput = Frame> + 'static> EncoderOutput for HevcEncoder<S> {
+    fn codec(&self) -> VideoCodec {
+        VideoCodec::Hevc
+    }
+
+    fn get_encoded_dimensions(&self) -> Result<Dimensions, CaptureError> {
+        let dimensions = self
+            .task
+            .source
+            .get_dimensions()
+            .map_err(CaptureError::from)?;
+        Ok(encoded_dimensions(dimensions))
+    }
+
+    fn frame_rate(&self) -> u32 {
+        self.config.frame_rate
+    }
+
+    fn clone_packet_receiver(&self) -> Arc<Mutex<Receiver<EncodedPacket>>> {
+        self.receiver.clone()
+    }
+
+    fn finished(&self) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
+        Box::pin(self.task.finished())
+    }
+}
+
+// the Media Foundation software HEVC encoder, registered by the HEVC Video Extensions on current windows versions
+fn create_software_encoder() -> Result<IMFTransform, windows::core::Error> {
+    mft_encoder::find_encoder(
+        &MFVideoFormat_HEVC,
+        MFT_ENUM_FLAG_SYNCMFT | MFT_ENUM_FLAG_LOCALMFT,
+    )
+    .or_else(|_| unsafe { CoCreateInstance(&CMSH265EncoderMFT, None, CLSCTX_INPROC_SERVER) })
+}
@@ -0,0 +1,239 @@
+use std::{
+    error::Error,
+    pin::Pin,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use tokio::sync::{
+    Mutex,
+    mpsc::{self, Receiver, Sender},
+};
+
+use crate::{
+    capture_state::StateCell,
+    devices::{Dimensions, Frame, camera::Output},
+    frame_callback::FrameCallback,
+    i_capture::ICapture,
+};
+
+// an encoding failure, kept until the source has stopped
+type Failure = std::sync::Mutex<Option<Box<dyn Error + Send + Sync>>>;
+
+/// # JPEG Capture
+///
+/// Wraps any capture that delivers RGB32 (BGRA) frames and encodes every frame as JPEG before sending it to its own receiver.
+///
+/// The receiver gets frames holding complete JPEG images, so a consumer does not need to know if the source is a monitor or a camera.
+pub struct JpegCapture<S: ICapture<CaptureOutput = Frame>> {
+    // the capture frames are taken from
+    source: Arc<S>,
+
+    // JPEG quality from 1 to 100
+    quality: u8,
+
+    /// The receiver, can be used to grab the JPEG encoded frames.
+    pub receiver: Arc<Mutex<Receiver<Frame>>>,
+
+    // to send encoded frames
+    sender: Sender<Frame>,
+
+    // how long recv_frame waits for a frame
+    frame_timeout: RwLock<Option<Duration>>,
+
+    // the callback registered with on_frame
+    frame_callback: FrameCallback<Frame>,
+
+    // if the wrapper captures, see ICapture::state
+    state: StateCell,
+}
+
+impl<S: ICapture<CaptureOutput = Frame> + 'static> JpegCapture<S> {
+    /// # New
+    ///
+    /// Wraps the source, quality is the JPEG quality from 1 (smallest) to 100 (best) and is clamped to that range.
+    ///
+    /// Capturing is started and stopped through the wrapper, do not start the source yourself.
+    pub fn new(source: Arc<S>, quality: u8) -> Arc<Self> {
+        let (tx, rx) = mpsc::channel(1);
+
+        Arc::new(Self {
+            source,
+            quality: quality.clamp(1, 100),
+            receiver: Arc::new(Mutex::new(rx)),
+            sender: tx,
+            frame_timeout: RwLock::new(None),
+            frame_callback: FrameCallback::default(),
+            state: StateCell::default(),
+        })
+    }
+
+    /// # Quality
+    ///
+    /// The JPEG quality frames are encoded with.
+    pub fn quality(&self) -> u8 {
+        self.quality
+    }
+
+    /// # Source
+    ///
+    /// The capture the frames are taken from.
+    pub fn source(&self) -> Arc<S> {
+        self.source.clone()
+    }
+
+    // encodes frames from the source until the source channel closes.
+    //
+    // the source is always drained, even after a failure, so it never blocks on a full channel while it stops.
+    async fn encode_frames(&self, failure: &Failure) {
+        let source_receiver = self.source.clone_receiver();
+        let mut source_receiver = source_receiver.lock().await;
+
+        while let Some(frame) = source_receiver.recv().await {
+            if !self.is_capturing() || failure.lock().unwrap().is_some() {
+                continue;
+            }
+
+            let sent = match self.encode(frame).await {
+                Ok(jpeg) => self
+                    .sender
+                    .send(jpeg)
+                    .await
+                    .map_err(|_| "JpegCapture receiver was dropped".into()),
+                Err(e) => Err(e),
+            };
+
+            if let Err(e) = sent {
+                trace_event!(error, error = %e, "jpeg capture stopped");
+
+                *failure.lock().unwrap() = Some(e);
+                let _ = self.source.clone().stop_capturing().await;
+            }
+        }
+    }
+
+    // encodes a single frame on the blocking thread pool
+    async fn encode(&self, mut frame: Frame) -> Result<Frame, Box<dyn Error + Send + Sync>> {
+        let Dimensions { width, height } =
+            self.source.get_dimensions().map_err(|e| e.to_string())?;
+        let quality = self.quality;
+
+        //the rest of the frame information is kept as is
+        let data = std::mem::take(&mut frame.data);
+
+        frame.data =
+            tokio::task::spawn_blocking(move || encode_jpeg(&data, width, height, quality))
+                .await??;
+
+        Ok(frame)
+    }
+}
+
+// compresses a BGRA frame, the frame may be padded so the pitch is taken from the buffer
+pub(crate) fn encode_jpeg(
+    frame: &[u8],
+    width: u32,
+    height: u32,
+    quality: u8,
+) -> Result<Vec<u8>, turbojpeg::Error> {
+    let image = turbojpeg::Image {
+        pixels: frame,
+        width: width as usize,
+        pitch: frame.len() / height.max(1) as usize,
+        height: height as usize,
+        format: turbojpeg::PixelFormat::BGRA,
+    };
+
+    let jpeg = turbojpeg::compress(image, quality as i32, turbojpeg::Subsamp::Sub2x2)?;
+
+    Ok(jpeg.to_vec())
+}
+
+impl<S: ICapture<CaptureOutput = Frame> + 'static> ICapture for JpegCapture<S> {
+    type CaptureOutput = Frame;
+
+    /// # Get Dimensions
+    ///
+    /// The dimensions of the source, which are also the dimensions of the JPEG images.
+    fn get_dimensions(&self) -> Result<Dimensions, Box<dyn std::error::Error>> {
+        self.source.get_dimensions()
+    }
+
+    /// # Get Output Format
+    ///
+    /// Always MJPEG, every frame is a complete JPEG image.
+    fn get_output_format(&self) -> Output {
+        Output::MJPEG
+    }
+
+    /// # Stop Capturing
+    ///
+    /// Stops sending encoded frames and stops the source.
+    fn stop_capturing(
+        self: Arc<Self>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>> + Send>> {
+        Box::pin(async move {
+            if !self.state.stop() {
+                return Err("Not capturing any data".into());
+            }
+
+            self.source.clone().stop_capturing().await
+        })
+    }
+
+    /// # Start Capturing
+    ///
+    /// Starts the source and encodes each of its frames, the future completes once the source stops.
+    ///
+    /// The source must deliver RGB32 frames.
+    fn start_capturing(
+        self: Arc<Self>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>> + Send>> {
+        Box::pin(async move {
+            if self.source.get_output_format() != Output::RGB32 {
+                return Err("JpegCapture requires a source that delivers RGB32 frames".into());
+            }
+
+            //the state is stopped again when the source stops, also with an error
+            let _running = self.state.start().await?;
+
+            let failure = Failure::default();
+
+            //the encode loop only ends with the source channel, so the source finishing ends the capture
+            let source_result = tokio::select! {
+                result = self.source.clone().start_capturing() => result,
+                _ = self.encode_frames(&failure) => Ok(()),
+            };
+
+            if let Some(e) = failure.into_inner().unwrap() {
+                return Err(e as Box<dyn Error>);
+            }
+
+            source_result
+        })
+    }
+
+    fn clone_receiver(&self) -> Arc<Mutex<Receiver<Self::CaptureOutput>>> {
+        self.receiver.clone()
+    }
+
+    fn set_frame_timeout(&self, duration: Duration) {
+        *self.frame_timeout.write().unwrap() = Some(duration);
+    }
+
+    fn clear_frame_timeout(&self) {
+        *self.frame_timeout.write().unwrap() = None;
+    }
+
+    fn get_frame_timeout(&self) -> Option<Duration> {
+        *self.frame_timeout.read().unwrap()
+    }
+
+    fn frame_callback(&self) -> &FrameCallback<Frame> {
+        &self.frame_callback
+    }
+
+    fn state_cell(&self) -> &StateCell {
+        &self.state
+    }
+}
@@ -0,0 +1,679 @@
+use std::{
+    mem::ManuallyDrop,
+    sync::{RwLock, mpsc},
+    time::Duration,
+};
+
+use tokio::sync::mpsc::Sender;
+use windows::{
+    Win32::{
+        Foundation::E_NOTIMPL,
+        Graphics::Direct3D11::{ID3D11Device, ID3D11Texture2D},
+        Media::MediaFoundation::{
+            IMF2DBuffer, IMFActivate, IMFDXGIDeviceManager, IMFMediaBuffer, IMFMediaEventGenerator,
+            IMFSample, IMFTransform, METransformDrainComplete, METransformHaveOutput,
+            METransformNeedInput, MF_E_NOTACCEPTING, MF_E_TRANSFORM_NEED_MORE_INPUT,
+            MF_E_TRANSFORM_STREAM_CHANGE, MF_EVENT_FLAG_NONE, MF_EVENT_TYPE, MF_MT_AVG_BITRATE,
+            MF_MT_FRAME_RATE, MF_MT_FRAME_SIZE, MF_MT_INTERLACE_MODE, MF_MT_MAJOR_TYPE,
+            MF_MT_MAX_KEYFRAME_SPACING, MF_MT_MPEG2_PROFILE, MF_MT_PIXEL_ASPECT_RATIO,
+            MF_MT_SUBTYPE, MF_MT_VIDEO_LEVEL, MF_SA_D3D11_AWARE, MF_TRANSFORM_ASYNC,
+            MF_TRANSFORM_ASYNC_UNLOCK, MFCreateDXGIDeviceManager, MFCreateDXGISurfaceBuffer,
+            MFCreateMediaType, MFCreateMemoryBuffer, MFCreateSample, MFMediaType_Video,
+            MFSampleExtension_CleanPoint, MFT_CATEGORY_VIDEO_ENCODER, MFT_ENUM_FLAG,
+            MFT_ENUM_FLAG_ALL, MFT_ENUM_FLAG_HARDWARE, MFT_ENUM_FLAG_SORTANDFILTER,
+            MFT_ENUM_HARDWARE_VENDOR_ID_Attribute, MFT_MESSAGE_COMMAND_DRAIN,
+            MFT_MESSAGE_NOTIFY_BEGIN_STREAMING, MFT_MESSAGE_NOTIFY_END_OF_STREAM,
+            MFT_MESSAGE_NOTIFY_START_OF_STREAM, MFT_MESSAGE_SET_D3D_MANAGER,
+            MFT_OUTPUT_DATA_BUFFER, MFT_OUTPUT_STREAM_PROVIDES_SAMPLES, MFT_REGISTER_TYPE_INFO,
+            MFTEnumEx, MFVideoFormat_NV12, MFVideoInterlace_Progressive,
+        },
+        System::Com::CoTaskMemFree,
+    },
+    core::{GUID, Interface, PWSTR},
+};
+
+use crate::{
+    capture_error::CaptureError,
+    com::ComGuard,
+    devices::{Camera, Dimensions, Frame, GpuFrame, camera::Output},
+    encoding::{EncodedPacket, EncoderBackend, nv12_converter::Nv12Converter, yuv::bgra_to_nv12},
+    init::MediaFoundation,
+};
+
+// media foundation times are in 100 nanosecond units
+const TICKS_PER_SECOND: u64 = 10_000_000;
+
+// what a media foundation encoder transform is set up with
+pub(crate) struct EncoderSettings {
+    // makes the encoder transform, called on the encoding thread
+    pub(crate) create_transform: fn() -> Result<IMFTransform, windows::core::Error>,
+
+    // the subtype of the compressed output, such as MFVideoFormat_H264
+    pub(crate) subtype: GUID,
+
+    // the eAVEnc profile of the codec
+    pub(crate) profile: u32,
+
+    // the MF_MT_VIDEO_LEVEL of the codec, the encoder picks one when none is set
+    pub(crate) level: Option<u32>,
+
+    // if a hardware encoder for the subtype is tried before create_transform
+    pub(crate) hardware: bool,
+
+    // the hardware encoder to use, any hardware encoder is tried when none is set
+    pub(crate) backend: Option<EncoderBackend>,
+
+    // the device of the textures, set when the frames are textures instead of system memory
+    pub(crate) device: Option<ID3D11Device>,
+
+    pub(crate) bitrate_kbps: u32,
+    pub(crate) keyframe_interval_secs: f32,
+    pub(crate) frame_rate: u32,
+
+    // the size and format of the source frames
+    pub(crate) dimensions: Dimensions,
+    pub(crate) input: Output,
+}
+
+// a frame handed to the encoder thread
+pub(crate) enum EncoderFrame {
+    // a frame in system memory, in the input format of the settings
+    Memory(Frame),
+
+    // a texture on the device of the settings and the time it was captured at
+    Texture(GpuFrame, Duration),
+}
+
+// encodes frames until the frame channel closes, then flushes the encoder.
+//
+// media foundation objects cannot leave the thread they are used on, so this owns the whole encoder
+// and is run on the blocking thread pool. the backend that ends up encoding is written to backend.
+pub(crate) fn encode_stream(
+    settings: EncoderSettings,
+    frames: mpsc::Receiver<EncoderFrame>,
+    packets: Sender<EncodedPacket>,
+    backend: &RwLock<Option<EncoderBackend>>,
+) -> Result<(), CaptureError> {
+    with_media_foundation(|| unsafe {
+        let textures = match &settings.device {
+            Some(device) => Some(TextureInput::new(device, &settings.dimensions)?),
+            None => None,
+        };
+
+        let mut encoder = MftEncoder::new(&settings, textures)?;
+        *backend.write().unwrap() = Some(encoder.backend);
+
+        encoder.run(&settings, frames, &packets)
+    })
+}
+
+// the hardware encoders for the subtype that are installed, sorted by merit
+pub(crate) fn hardware_backends(subtype: &GUID) -> Result<Vec<EncoderBackend>, CaptureError> {
+    with_media_foundation(|| unsafe {
+        let activates = enumerate_encoders(
+            subtype,
+            MFT_ENUM_FLAG_HARDWARE | MFT_ENUM_FLAG_SORTANDFILTER,
+        )?;
+
+        let mut backends = Vec::new();
+
+        for backend in activates.iter().map(|activate| vendor_backend(activate)) {
+            if !backends.contains(&backend) {
+                backends.push(backend);
+            }
+        }
+
+        Ok(backends)
+    })
+}
+
+// if any encoder (hardware or software) can produce the subtype on this system
+pub(crate) fn encoder_available(subtype: &GUID) -> Result<bool, CaptureError> {
+    with_media_foundation(|| unsafe {
+        Ok(!enumerate_encoders(subtype, MFT_ENUM_FLAG_ALL)?.is_empty())
+    })
+}
+
+// the first encoder the enumeration flags find for the subtype, sorted by merit
+pub(crate) fn find_encoder(
+    subtype: &GUID,
+    flags: MFT_ENUM_FLAG,
+) -> Result<IMFTransform, windows::core::Error> {
+    unsafe {
+        let activates = enumerate_encoders(subtype, flags | MFT_ENUM_FLAG_SORTANDFILTER)?;
+
+        let Some(activate) = activates.first() else {
+            return Err(E_NOTIMPL.into());
+        };
+
+        activate.ActivateObject()
+    }
+}
+
+// every registered video encoder producing the subtype that matches the enumeration flags
+unsafe fn enumerate_encoders(
+    subtype: &GUID,
+    flags: MFT_ENUM_FLAG,
+) -> Result<Vec<IMFActivate>, windows::core::Error> {
+    let output_type = MFT_REGISTER_TYPE_INFO {
+        guidMajorType: MFMediaType_Video,
+        guidSubtype: *subtype,
+    };
+
+    let mut activates: *mut Option<IMFActivate> = std::ptr::null_mut();
+    let mut count = 0;
+
+    unsafe {
+        MFTEnumEx(
+            MFT_CATEGORY_VIDEO_ENCODER,
+            flags,
+            None,
+            Some(&output_type),
+            &mut activates,
+            &mut count,
+        )?;
+
+        if activates.is_null() {
+            return Ok(Vec::new());
+        }
+
+        //the array owns a reference to every activate, take them before the array is freed
+        let encoders = std::slice::from_raw_parts_mut(activates, count as usize)
+            .iter_mut()
+            .filter_map(Option::take)
+            .collect();
+
+        CoTaskMemFree(Some(activates as *const _));
+
+        Ok(encoders)
+    }
+}
+
+// the backend of a hardware encoder from the vendor it is registered for
+unsafe fn vendor_backend(activate: &IMFActivate) -> EncoderBackend {
+    let mut vendor = PWSTR::null();
+    let mut length = 0;
+
+    unsafe {
+        if activate
+            .GetAllocatedString(
+                &MFT_ENUM_HARDWARE_VENDOR_ID_Attribute,
+                &mut vendor,
+                &mut length,
+            )
+            .is_err()
+        {
+            return EncoderBackend::Other;
+        }
+
+        let vendor_id = vendor.to_string().unwrap_or_default();
+        CoTaskMemFree(Some(vendor.0 as *const _));
+
+        EncoderBackend::from_vendor_id(&vendor_id)
+    }
+}
+
+// runs f with COM and media foundation started on the current thread
+fn with_media_foundation<T>(
+    f: impl FnOnce() -> Result<T, CaptureError>,
+) -> Result<T, CaptureError> {
+    unsafe {
+        //a thread that cannot join the multithreaded apartment still runs f, as media foundation may work in its apartment
+        let _com = ComGuard::new().ok();
+
+        let _media_foundation = MediaFoundation::start()?;
+
+        f()
+    }
+}
+
+// what textures are encoded with, the device manager for encoders that read textures and the conversion to NV12
+struct TextureInput {
+    manager: IMFDXGIDeviceManager,
+    converter: Nv12Converter,
+}
+
+impl TextureInput {
+    unsafe fn new(device: &ID3D11Device, dimensions: &Dimensions) -> Result<Self, CaptureError> {
+        //the textures are converted to the encoded size, which must be even
+        let size = Dimensions {
+            width: dimensions.width & !1,
+            height: dimensions.height & !1,
+        };
+
+        unsafe {
+            let mut token = 0;
+            let mut manager = None;
+            MFCreateDXGIDeviceManager(&mut token, &mut manager)?;
+
+            let manager = manager.unwrap();
+            manager.ResetDevice(device, token)?;
+
+            Ok(Self {
+                manager,
+                converter: Nv12Converter::new(device, size)?,
+            })
+        }
+    }
+}
+
+struct MftEncoder {
+    transform: IMFTransform,
+
+    // the encoder in use
+    backend: EncoderBackend,
+
+    // set when the frames are textures
+    textures: Option<TextureInput>,
+
+    // the events of an asynchronous (hardware) encoder, which asks for input and announces output through them
+    events: Option<IMFMediaEventGenerator>,
+
+    // the encoded size, codecs need an even width and height so odd sizes lose their last column or row
+    width: u32,
+    height: u32,
+
+    // the output buffer size when the encoder does not allocate its own samples
+    output_buffer_size: Option<u32>,
+}
+
+impl MftEncoder {
+    // the first hardware encoder (of the backend) that accepts the settings, otherwise the software encoder
+    unsafe fn new(
+        settings: &EncoderSettings,
+        textures: Option<TextureInput>,
+    ) -> Result<Self, CaptureError> {
+        let manager = textures.as_ref().map(|textures| &textures.manager);
+
+        let hardware = if settings.hardware {
+            unsafe {
+                enumerate_encoders(
+                    &settings.subtype,
+                    MFT_ENUM_FLAG_HARDWARE | MFT_ENUM_FLAG_SORTANDFILTER,
+                )
+                .unwrap_or_default()
+            }
+        } else {
+            Vec::new()
+        };
+
+        for activate in hardware {
+            let backend = unsafe { vendor_backend(&activate) };
+
+            if settings.backend.is_some_and(|wanted| wanted != backend) {
+                continue;
+            }
+
+            let encoder = unsafe { activate.ActivateObject::<IMFTransform>() }
+                .map_err(CaptureError::from)
+                .and_then(|transform| unsafe {
+                    Self::configure(transform, settings, manager, backend)
+                });
+
+            match encoder {
+                Ok(mut encoder) => {
+                    encoder.textures = textures;
+                    return Ok(encoder);
+                }
+                Err(_e) => {
+                    trace_event!(debug, backend = ?backend, error = %_e, "hardware encoder failed");
+                }
+            }
+        }
+
+        if settings.hardware {
+            trace_event!(debug, "no hardware encoder, using the software encoder");
+        }
+
+        //the software encoder reads the textures through system memory
+        let mut encoder = unsafe {
+            Self::configure(
+                (settings.create_transform)()?,
+                settings,
+                manager,
+                EncoderBackend::Software,
+            )?
+        };
+        encoder.textures = textures;
+
+        Ok(encoder)
+    }
+
+    unsafe fn configure(
+        transform: IMFTransform,
+        settings: &EncoderSettings,
+        manager: Option<&IMFDXGIDeviceManager>,
+        backend: EncoderBackend,
+    ) -> Result<Self, CaptureError> {
+        let width = settings.dimensions.width & !1;
+        let height = settings.dimensions.height & !1;
+
+        if width == 0 || height == 0 {
+            return Err(CaptureError::Encoding(
+                "frames are too small to be encoded".into(),
+            ));
+        }
+
+        let frame_size = ((width as u64) << 32) | height as u64;
+        let frame_rate = ((settings.frame_rate.max(1) as u64) << 32) | 1;
+        let square_pixels = (1u64 << 32) | 1;
+
+        let keyframe_spacing =
+            (settings.keyframe_interval_secs.max(0.0) * settings.frame_rate as f32).round() as u32;
+
+        unsafe {
+            let attributes = transform.GetAttributes().ok();
+            let attribute = |key| {
+                attributes
+                    .as_ref()
+                    .is_some_and(|attributes| attributes.GetUINT32(key).unwrap_or(0) != 0)
+            };
+
+            //asynchronous transforms must be unlocked before they can be used
+            let events = match &attributes {
+                Some(attributes) if attribute(&MF_TRANSFORM_ASYNC) => {
+                    attributes.SetUINT32(&MF_TRANSFORM_ASYNC_UNLOCK, 1)?;
+                    Some(transform.cast::<IMFMediaEventGenerator>()?)
+                }
+                _ => None,
+            };
+
+            //encoders that read textures on the GPU get the device before the media types
+            if let Some(manager) = manager
+                && attribute(&MF_SA_D3D11_AWARE)
+            {
+                transform.ProcessMessage(MFT_MESSAGE_SET_D3D_MANAGER, manager.as_raw() as usize)?;
+            }
+
+            //encoders need the output type before the input type
+            let output_type = MFCreateMediaType()?;
+            output_type.SetGUID(&MF_MT_MAJOR_TYPE, &MFMediaType_Video)?;
+            output_type.SetGUID(&MF_MT_SUBTYPE, &settings.subtype)?;
+            output_type.SetUINT32(
+                &MF_MT_AVG_BITRATE,
+                settings.bitrate_kbps.saturating_mul(1000),
+            )?;
+            output_type.SetUINT64(&MF_MT_FRAME_SIZE, frame_size)?;
+            output_type.SetUINT64(&MF_MT_FRAME_RATE, frame_rate)?;
+            output_type.SetUINT64(&MF_MT_PIXEL_ASPECT_RATIO, square_pixels)?;
+            output_type.SetUINT32(&MF_MT_INTERLACE_MODE, MFVideoInterlace_Progressive.0 as u32)?;
+            output_type.SetUINT32(&MF_MT_MPEG2_PROFILE, settings.profile)?;
+            output_type.SetUINT32(&MF_MT_MAX_KEYFRAME_SPACING, keyframe_spacing.max(1))?;
+
+            if let Some(level) = settings.level {
+                output_type.SetUINT32(&MF_MT_VIDEO_LEVEL, level)?;
+            }
+
+            transform.SetOutputType(0, &output_type, 0)?;
+
+            //RGB32 frames are converted to NV12 before they are handed to the encoder
+            let input_type = MFCreateMediaType()?;
+            input_type.SetGUID(&MF_MT_MAJOR_TYPE, &MFMediaType_Video)?;
+            input_type.SetGUID(&MF_MT_SUBTYPE, &MFVideoFormat_NV12)?;
+            input_type.SetUINT64(&MF_MT_FRAME_SIZE, frame_size)?;
+            input_type.SetUINT64(&MF_MT_FRAME_RATE, frame_rate)?;
+            input_type.SetUINT64(&MF_MT_PIXEL_ASPECT_RATIO, square_pixels)?;
+            input_type.SetUINT32(&MF_MT_INTERLACE_MODE, MFVideoInterlace_Progressive.0 as u32)?;
+            transform.SetInputType(0, &input_type, 0)?;
+
+            let info = transform.GetOutputStreamInfo(0)?;
+            let provides_samples = info.dwFlags & MFT_OUTPUT_STREAM_PROVIDES_SAMPLES.0 as u32 != 0;
+
+            transform.ProcessMessage(MFT_MESSAGE_NOTIFY_BEGIN_STREAMING, 0)?;
+            transform.ProcessMessage(MFT_MESSAGE_NOTIFY_START_OF_STREAM, 0)?;
+
+            Ok(Self {
+                transform,
+                backend,
+                textures: None,
+                events,
+                width,
+                height,
+                output_buffer_size: (!provides_samples).then_some(info.cbSize.max(1)),
+            })
+        }
+    }
+
+    unsafe fn run(
+        &mut self,
+        settings: &EncoderSettings,
+        frames: mpsc::Receiver<Frame>,
+        packets: &Sender<EncodedPacket>,
+    ) -> Result<(), CaptureError> {
+        let frame_duration = (TICKS_PER_SECOND / settings.frame_rate.max(1) as u64) as i64;
+
+        while let Ok(frame) = frames.recv() {
+            let sample = match frame {
+                EncoderFrame::Memory(frame) => {
+                    let nv12 = match settings.input {
+                        Output::RGB32 => bgra_to_nv12(
+                            &frame.data,
+                            settings.dimensions.height as usize,
+                            self.width as usize,
+                            self.height as usize,
+                        ),
+                        _ => frame.data,
+                    };
+
+                    unsafe { Self::create_input_sample(&nv12, frame.timestamp, frame_duration)? }
+                }
+                EncoderFrame::Texture(frame, timestamp) => {
+                    let Some(textures) = self.textures.as_mut() else {
+                        return Err(CaptureError::Encoding(
+                            "the encoder was not set up for textures".into(),
+                        ));
+                    };
+
+                    unsafe {
+                        let texture = textures.converter.convert(&frame)?;
+                        Self::create_texture_sample(&texture, timestamp, frame_duration)?
+                    }
+                }
+            };
+
+            unsafe {
+                self.process_input(&sample, packets)?;
+            }
+        }
+
+        //the source stopped, flush the frames still inside of the encoder
+        unsafe {
+            self.transform
+                .ProcessMessage(MFT_MESSAGE_NOTIFY_END_OF_STREAM, 0)?;
+            self.transform
+                .ProcessMessage(MFT_MESSAGE_COMMAND_DRAIN, 0)?;
+        }
+
+        let Some(events) = self.events.clone() else {
+            return unsafe { self.drain(packets) };
+        };
+
+        //asynchronous encoders announce every remaining packet and then the end of the drain
+        loop {
+            let event = unsafe { events.GetEvent(MF_EVENT_FLAG_NONE)? };
+
+            match MF_EVENT_TYPE(unsafe { event.GetType()? } as i32) {
+                METransformHaveOutput => unsafe {
+                    self.process_output(packets)?;
+                },
+                METransformDrainComplete => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+
+    unsafe fn create_input_sample(
+        data: &[u8],
+        timestamp: Duration,
+        duration: i64,
+    ) -> Result<IMFSample, windows::core::Error> {
+        unsafe {
+            let buffer = MFCreateMemoryBuffer(data.len() as u32)?;
+
+            let mut ptr: *mut u8 = std::ptr::null_mut();
+            buffer.Lock(&mut ptr, None, None)?;
+            std::ptr::copy_nonoverlapping(data.as_ptr(), ptr, data.len());
+            buffer.Unlock()?;
+            buffer.SetCurrentLength(data.len() as u32)?;
+
+            Self::create_sample(&buffer, timestamp, duration)
+        }
+    }
+
+    // wraps an NV12 texture without copying it
+    unsafe fn create_texture_sample(
+        texture: &ID3D11Texture2D,
+        timestamp: Duration,
+        duration: i64,
+    ) -> Result<IMFSample, windows::core::Error> {
+        unsafe {
+            let buffer = MFCreateDXGISurfaceBuffer(&ID3D11Texture2D::IID, texture, 0, false)?;
+            let length = buffer.cast::<IMF2DBuffer>()?.GetContiguousLength()?;
+            buffer.SetCurrentLength(length)?;
+
+            Self::create_sample(&buffer, timestamp, duration)
+        }
+    }
+
+    unsafe fn create_sample(
+        buffer: &IMFMediaBuffer,
+        timestamp: Duration,
+        duration: i64,
+    ) -> Result<IMFSample, windows::core::Error> {
+        unsafe {
+            let sample = MFCreateSample()?;
+            sample.AddBuffer(buffer)?;
+            sample.SetSampleTime((timestamp.as_nanos() / 100) as i64)?;
+            sample.SetSampleDuration(duration)?;
+
+            Ok(sample)
+        }
+    }
+
+    // hands a sample to the encoder, taking the encoded output first when the encoder is full
+    unsafe fn process_input(
+        &mut self,
+        sample: &IMFSample,
+        packets: &Sender<EncodedPacket>,
+    ) -> Result<(), CaptureError> {
+        if let Some(events) = self.events.clone() {
+            //asynchronous encoders only take input after asking for it, sending the output ready until then
+            loop {
+                let event = unsafe { events.GetEvent(MF_EVENT_FLAG_NONE)? };
+
+                match MF_EVENT_TYPE(unsafe { event.GetType()? } as i32) {
+                    METransformNeedInput => break,
+                    METransformHaveOutput => unsafe {
+                        self.process_output(packets)?;
+                    },
+                    _ => {}
+                }
+            }
+
+            return unsafe { self.transform.ProcessInput(0, sample, 0) }
+                .map_err(CaptureError::from);
+        }
+
+        unsafe {
+            match self.transform.ProcessInput(0, sample, 0) {
+                Err(e) if e.code() == MF_E_NOTACCEPTING => {
+                    self.drain(packets)?;
+                    self.transform.ProcessInput(0, sample, 0)?;
+                }
+                result => result?,
+            }
+
+            self.drain(packets)
+        }
+    }
+
+    // sends every packet the encoder has ready
+    unsafe fn drain(&mut self, packets: &Sender<EncodedPacket>) -> Result<(), CaptureError> {
+        while unsafe { self.process_output(packets)? } {}
+
+        Ok(())
+    }
+
+    // sends a single packet if the encoder has one ready, false once the encoder needs more input
+    unsafe fn process_output(
+        &mut self,
+        packets: &Sender<EncodedPacket>,
+    ) -> Result<bool, CaptureError> {
+        loop {
+            let sample = match self.output_buffer_size {
+                Some(size) => unsafe {
+                    let sample = MFCreateSample()?;
+                    sample.AddBuffer(&MFCreateMemoryBuffer(size)?)?;
+                    Some(sample)
+                },
+                None => None,
+            };
+
+            let mut output = [MFT_OUTPUT_DATA_BUFFER {
+                dwStreamID: 0,
+                pSample: ManuallyDrop::new(sample),
+                dwStatus: 0,
+                pEvents: ManuallyDrop::new(None),
+            }];
+            let mut status = 0;
+
+            let result = unsafe { self.transform.ProcessOutput(0, &mut output, &mut status) };
+
+            //the buffer owns a reference to the sample and events that is not released on its own
+            let [output] = output;
+            let sample = ManuallyDrop::into_inner(output.pSample);
+            drop(ManuallyDrop::into_inner(output.pEvents));
+
+            match result {
+                Err(e) if e.code() == MF_E_TRANSFORM_NEED_MORE_INPUT => return Ok(false),
+                //the encoder changed its output type, accept the new one
+                Err(e) if e.code() == MF_E_TRANSFORM_STREAM_CHANGE => unsafe {
+                    let output_type = self.transform.GetOutputAvailableType(0, 0)?;
+                    self.transform.SetOutputType(0, &output_type, 0)?;
+                    continue;
+                },
+                result => result?,
+            }
+
+            let Some(sample) = sample else {
+                return Ok(true);
+            };
+
+            let packet = unsafe { Self::read_packet(&sample)? };
+
+            trace_event!(
+                trace,
+                bytes = packet.data.len(),
+                keyframe = packet.keyframe,
+                "sending encoded packet"
+            );
+
+            if packets.blocking_send(packet).is_err() {
+                return Err(CaptureError::Other(
+                    "the encoded packet receiver was dropped".into(),
+                ));
+            }
+
+            return Ok(true);
+        }
+    }
+
+    unsafe fn read_packet(sample: &IMFSample) -> Result<EncodedPacket, windows::core::Error> {
+        unsafe {
+            let buffer = sample.ConvertToContiguousBuffer()?;
+            let data = Camera::get_frame_data(&buffer)?;
+
+            let ticks = sample.GetSampleTime().unwrap_or_default().max(0) as u64;
+            let keyframe = sample
+                .GetUINT32(&MFSampleExtension_CleanPoint)
+                .unwrap_or_default()
+                != 0;
+
+            Ok(EncodedPacket {
+                data,
+                timestamp: Duration::from_nanos(ticks * 100),
+                keyframe,
+            })
+        }
+    }
+}
@@ -0,0 +1,518 @@
+use std::{io::SeekFrom, path::Path, sync::Arc, time::Duration};
+
+use tokio::{
+    fs::{File, OpenOptions},
+    io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
+};
+
+use crate::{
+    capture_error::CaptureError,
+    devices::Dimensions,
+    encoding::{
+        EncodedPacket,
+        encoder_output::{EncoderOutput, VideoCodec},
+        nal::{NalKind, ParameterSets, nal_units},
+    },
+};
+
+// the time units per second of the video track
+const TIMESCALE: u32 = 90_000;
+
+// the time units per second of the movie header
+const MOVIE_TIMESCALE: u32 = 1000;
+
+// the size of the ftyp box written by ftyp_box
+const FTYP_SIZE: u64 = 32;
+
+// an mdat header with a 64 bit size, so recordings can grow past 4 GB
+const MDAT_HEADER_SIZE: u64 = 16;
+
+// the size of the blocks the sample data is moved in when the moov box is put in front of it
+const RELOCATE_BLOCK_SIZE: u64 = 1 << 20;
+
+// a sample written to the mdat box
+struct Sample {
+    size: u32,
+    timestamp: Duration,
+    keyframe: bool,
+}
+
+/// # Mp4 Muxer
+///
+/// Writes the packets of an H.264 or HEVC encoder to an MP4 (ISO Base Media) file.
+///
+/// Samples are written to the file as they arrive, finalize writes the index (the moov box) and moves it in front of the samples,
+/// so the file can be played while it is still downloading (fast start).
+///
+/// The file is not playable until finalize has completed.
+pub struct Mp4Muxer {
+    output: Arc<dyn EncoderOutput>,
+    file: File,
+
+    codec: VideoCodec,
+    dimensions: Dimensions,
+    frame_rate: u32,
+
+    // taken from the first keyframe, packets before it are skipped
+    parameter_sets: Option<ParameterSets>,
+
+    samples: Vec<Sample>,
+
+    // the bytes of sample data written after the mdat header
+    data_size: u64,
+}
+
+impl Mp4Muxer {
+    /// # Create
+    ///
+    /// Creates (or truncates) the file for the packets of the encoder, the encoder can be started before or after.
+    ///
+    /// Returns CaptureError::UnsupportedFormat for VP8 and VP9 encoders, use the WebmMuxer for those.
+    pub async fn create(output: Arc<dyn EncoderOutput>, path: &Path) -> Result<Self, CaptureError> {
+        let codec = output.codec();
+
+        let brand = match codec {
+            VideoCodec::H264 => b"avc1",
+            VideoCodec::Hevc => b"hvc1",
+            VideoCodec::Vp8 | VideoCodec::Vp9 => {
+                return Err(CaptureError::UnsupportedFormat(format!(
+                    "{codec:?} cannot be written to an MP4 file, use the WebmMuxer"
+                )));
+            }
+        };
+
+        let dimensions = output.get_encoded_dimensions()?;
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+            .await?;
+
+        let mut header = ftyp_box(brand);
+        //the size of the mdat box is written by finalize
+        header.extend_from_slice(&1u32.to_be_bytes());
+        header.extend_from_slice(b"mdat");
+        header.extend_from_slice(&0u64.to_be_bytes());
+
+        file.write_all(&header).await?;
+
+        Ok(Self {
+            codec,
+            frame_rate: output.frame_rate().max(1),
+            output,
+            file,
+            dimensions,
+            parameter_sets: None,
+            samples: Vec::new(),
+            data_size: 0,
+        })
+    }
+
+    /// # Samples Written
+    ///
+    /// How many packets have been written to the file.
+    pub fn samples_written(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// # Run
+    ///
+    /// Writes every packet of the encoder until the encoder has stopped and sent its last packet, then call finalize.
+    pub async fn run(&mut self) -> Result<(), CaptureError> {
+        let output = self.output.clone();
+        let receiver = output.clone_packet_receiver();
+        let mut receiver = receiver.lock().await;
+        let mut finished = output.finished();
+
+        loop {
+            tokio::select! {
+                packet = receiver.recv() => match packet {
+                    Some(packet) => self.write_packet(&packet).await?,
+                    None => return Ok(()),
+                },
+                _ = &mut finished => break,
+            }
+        }
+
+        //every packet was sent before the encoder finished
+        while let Ok(packet) = receiver.try_recv() {
+            self.write_packet(&packet).await?;
+        }
+
+        Ok(())
+    }
+
+    /// # Write Packet
+    ///
+    /// Writes a single packet of the encoder to the file, for reading the packets yourself instead of using run.
+    ///
+    /// Packets before the first keyframe are skipped, the file has to start with a keyframe.
+    pub async fn write_packet(&mut self, packet: &EncodedPacket) -> Result<(), CaptureError> {
+        if self.parameter_sets.is_none() && !packet.keyframe {
+            return Ok(());
+        }
+
+        let parameter_sets = self.parameter_sets.get_or_insert_default();
+
+        //MP4 samples hold length prefixed NAL units, the parameter sets go to the sample description
+        let mut sample = Vec::with_capacity(packet.data.len());
+
+        for nal in nal_units(&packet.data) {
+            match NalKind::of(self.codec, nal) {
+                kind if kind.is_parameter_set() => parameter_sets.push(kind, nal),
+                NalKind::AccessUnitDelimiter => {}
+                _ => {
+                    sample.extend_from_slice(&(nal.len() as u32).to_be_bytes());
+                    sample.extend_from_slice(nal);
+                }
+            }
+        }
+
+        if sample.is_empty() {
+            return Ok(());
+        }
+
+        self.file.write_all(&sample).await?;
+
+        self.data_size += sample.len() as u64;
+        self.samples.push(Sample {
+            size: sample.len() as u32,
+            timestamp: packet.timestamp,
+            keyframe: packet.keyframe,
+        });
+
+        Ok(())
+    }
+
+    /// # Finalize
+    ///
+    /// Writes the index of the samples in front of them and flushes the file, after this the file is complete.
+    ///
+    /// Returns CaptureError::Encoding when not a single keyframe was written.
+    pub async fn finalize(mut self) -> Result<(), CaptureError> {
+        let Some(parameter_sets) = self
+            .parameter_sets
+            .take()
+            .filter(|_| !self.samples.is_empty())
+        else {
+            return Err(CaptureError::Encoding(
+                "no keyframe was written, the file would not be playable".into(),
+            ));
+        };
+
+        let entry = match self.codec {
+            VideoCodec::H264 => sample_entry(
+                b"avc1",
+                b"avcC",
+                &parameter_sets.avc_decoder_configuration()?,
+                &self.dimensions,
+            ),
+            VideoCodec::Hevc => sample_entry(
+                b"hvc1",
+                b"hvcC",
+                &parameter_sets.hevc_decoder_configuration()?,
+                &self.dimensions,
+            ),
+            //rejected by create
+            VideoCodec::Vp8 | VideoCodec::Vp9 => {
+                return Err(CaptureError::UnsupportedFormat(format!(
+                    "{:?} cannot be written to an MP4 file",
+                    self.codec
+                )));
+            }
+        };
+
+        let durations = self.sample_durations();
+
+        //the samples must be on disk before they are read back
+        self.file.flush().await?;
+
+        //the chunk offsets depend on the size of the moov box, which does not depend on the offsets
+        let moov_size = self.moov_box(&entry, &durations, 0).len() as u64;
+        let moov = self.moov_box(&entry, &durations, moov_size);
+
+        let mdat_size = MDAT_HEADER_SIZE + self.data_size;
+        self.file.seek(SeekFrom::Start(FTYP_SIZE + 8)).await?;
+        self.file.write_all(&mdat_size.to_be_bytes()).await?;
+
+        self.relocate_mdat(moov_size, mdat_size).await?;
+
+        self.file.seek(SeekFrom::Start(FTYP_SIZE)).await?;
+        self.file.write_all(&moov).await?;
+
+        self.file.flush().await?;
+        self.file.sync_all().await?;
+
+        Ok(())
+    }
+
+    // moves the mdat box towards the end of the file by the size of the moov box, starting at the end so nothing is overwritten before it is moved
+    async fn relocate_mdat(&mut self, moov_size: u64, mdat_size: u64) -> Result<(), CaptureError> {
+        let mut block = vec![0u8; RELOCATE_BLOCK_SIZE.min(mdat_size) as usize];
+        let mut remaining = mdat_size;
+
+        while remaining > 0 {
+            let length = RELOCATE_BLOCK_SIZE.min(remaining);
+            let position = FTYP_SIZE + remaining - length;
+            let block = &mut block[..length as usize];
+
+            self.file.seek(SeekFrom::Start(position)).await?;
+            self.file.read_exact(block).await?;
+
+            self.file
+                .seek(SeekFrom::Start(position + moov_size))
+                .await?;
+            self.file.write_all(block).await?;
+
+            remaining -= length;
+        }
+
+        Ok(())
+    }
+
+    // the duration of every sample in track time units, the last sample lasts a frame
+    fn sample_durations(&self) -> Vec<u32> {
+        let first = self.samples[0].timestamp;
+        let mut times: Vec<u64> = Vec::with_capacity(self.samples.len());
+
+        for sample in &self.samples {
+            let time = (sample.timestamp.saturating_sub(first).as_nanos() * TIMESCALE as u128
+                / 1_000_000_000) as u64;
+
+            //timestamps must increase, a repeated timestamp is moved by a single unit
+            let time = match times.last() {
+                Some(&last) if time <= last => last + 1,
+                _ => time,
+            };
+
+            times.push(time);
+        }
+
+        let frame_duration = TIMESCALE / self.frame_rate;
+
+        times
+            .windows(2)
+            .map(|pair| (pair[1] - pair[0]) as u32)
+            .chain(std::iter::once(frame_duration))
+            .collect()
+    }
+
+    fn moov_box(&self, sample_entry: &[u8], durations: &[u32], moov_size: u64) -> Vec<u8> {
+        let duration: u64 = durations.iter().map(|&duration| duration as u64).sum();
+        let movie_duration = duration * MOVIE_TIMESCALE as u64 / TIMESCALE as u64;
+
+        let stbl = mp4_box(
+            b"stbl",
+            &[
+                full_box(
+                    b"stsd",
+                    0,
+                    &[&1u32.to_be_bytes()[..], sample_entry].concat(),
+                ),
+                self.stts_box(durations),
+                self.stss_box(),
+                //one sample per chunk
+                full_box(b"stsc", 0, &[1u32, 1, 1, 1].map(u32::to_be_bytes).concat()),
+                self.stsz_box(),
+                self.co64_box(FTYP_SIZE + moov_size + MDAT_HEADER_SIZE),
+            ]
+            .concat(),
+        );
+
+        let dinf = mp4_box(
+            b"dinf",
+            &full_box(
+                b"dref",
+                0,
+                &[&1u32.to_be_bytes()[..], &full_box(b"url ", 1, &[])[..]].concat(),
+            ),
+        );
+
+        let minf = mp4_box(
+            b"minf",
+            &[full_box(b"vmhd", 1, &[0u8; 8]), dinf, stbl].concat(),
+        );
+
+        let mdia = mp4_box(b"mdia", &[mdhd_box(duration), hdlr_box(), minf].concat());
+
+        let trak = mp4_box(
+            b"trak",
+            &[tkhd_box(movie_duration, &self.dimensions), mdia].concat(),
+        );
+
+        mp4_box(b"moov", &[mvhd_box(movie_duration), trak].concat())
+    }
+
+    // the durations, with runs of equal durations in a single entry
+    fn stts_box(&self, durations: &[u32]) -> Vec<u8> {
+        let mut entries: Vec<(u32, u32)> = Vec::new();
+
+        for &duration in durations {
+            match entries.last_mut() {
+                Some((count, last)) if *last == duration => *count += 1,
+                _ => entries.push((1, duration)),
+            }
+        }
+
+        let mut content = (entries.len() as u32).to_be_bytes().to_vec();
+
+        for (count, duration) in entries {
+            content.extend_from_slice(&count.to_be_bytes());
+            content.extend_from_slice(&duration.to_be_bytes());
+        }
+
+        full_box(b"stts", 0, &content)
+    }
+
+    // the numbers (starting at 1) of the keyframes
+    fn stss_box(&self) -> Vec<u8> {
+        let keyframes: Vec<u32> = (1..)
+            .zip(&self.samples)
+            .filter(|(_, sample)| sample.keyframe)
+            .map(|(number, _)| number)
+            .collect();
+
+        let mut content = (keyframes.len() as u32).to_be_bytes().to_vec();
+        content.extend(keyframes.iter().flat_map(|number| number.to_be_bytes()));
+
+        full_box(b"stss", 0, &content)
+    }
+
+    fn stsz_box(&self) -> Vec<u8> {
+        let mut content = 0u32.to_be_bytes().to_vec();
+        content.extend_from_slice(&(self.samples.len() as u32).to_be_bytes());
+        content.extend(
+            self.samples
+                .iter()
+                .flat_map(|sample| sample.size.to_be_bytes()),
+        );
+
+        full_box(b"stsz", 0, &content)
+    }
+
+    // the offset of every sample in the file, the samples start at data_start
+    fn co64_box(&self, data_start: u64) -> Vec<u8> {
+        let mut content = (self.samples.len() as u32).to_be_bytes().to_vec();
+        let mut offset = data_start;
+
+        for sample in &self.samples {
+            content.extend_from_slice(&offset.to_be_bytes());
+            offset += sample.size as u64;
+        }
+
+        full_box(b"co64", 0, &content)
+    }
+}
+
+fn mp4_box(kind: &[u8; 4], content: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(content.len() + 8);
+    data.extend_from_slice(&(content.len() as u32 + 8).to_be_bytes());
+    data.extend_from_slice(kind);
+    data.extend_from_slice(content);
+    data
+}
+
+// a box with a version (always 0) and flags
+fn full_box(kind: &[u8; 4], flags: u32, content: &[u8]) -> Vec<u8> {
+    mp4_box(
+        kind,
+        &[&(flags & 0xFF_FFFF).to_be_bytes()[..], content].concat(),
+    )
+}
+
+// the brands of the file, followed by the brand of the codec
+fn ftyp_box(codec_brand: &[u8; 4]) -> Vec<u8> {
+    let mut content = b"isom".to_vec();
+    content.extend_from_slice(&0x200u32.to_be_bytes());
+    content.extend_from_slice(b"isomiso2mp41");
+    content.extend_from_slice(codec_brand);
+
+    mp4_box(b"ftyp", &content)
+}
+
+// the unity matrix of the movie and track headers
+fn unity_matrix() -> Vec<u8> {
+    [0x0001_0000u32, 0, 0, 0, 0x0001_0000, 0, 0, 0, 0x4000_0000]
+        .map(u32::to_be_bytes)
+        .concat()
+}
+
+fn mvhd_box(duration: u64) -> Vec<u8> {
+    let mut content = Vec::with_capacity(96);
+    content.extend_from_slice(&[0u8; 8]); //creation and modification time
+    content.extend_from_slice(&MOVIE_TIMESCALE.to_be_bytes());
+    content.extend_from_slice(&(duration as u32).to_be_bytes());
+    content.extend_from_slice(&0x0001_0000u32.to_be_bytes()); //rate 1.0
+    content.extend_from_slice(&0x0100u16.to_be_bytes()); //volume 1.0
+    content.extend_from_slice(&[0u8; 10]);
+    content.extend_from_slice(&unity_matrix());
+    content.extend_from_slice(&[0u8; 24]);
+    content.extend_from_slice(&2u32.to_be_bytes()); //next track id
+
+    full_box(b"mvhd", 0, &content)
+}
+
+fn tkhd_box(duration: u64, dimensions: &Dimensions) -> Vec<u8> {
+    let mut content = Vec::with_capacity(80);
+    content.extend_from_slice(&[0u8; 8]); //creation and modification time
+    content.extend_from_slice(&1u32.to_be_bytes()); //track id
+    content.extend_from_slice(&[0u8; 4]);
+    content.extend_from_slice(&(duration as u32).to_be_bytes());
+    content.extend_from_slice(&[0u8; 8]);
+    content.extend_from_slice(&[0u8; 8]); //layer, alternate group, volume (0 for video)
+    content.extend_from_slice(&unity_matrix());
+    //16.16 fixed point
+    content.extend_from_slice(&(dimensions.width << 16).to_be_bytes());
+    content.extend_from_slice(&(dimensions.height << 16).to_be_bytes());
+
+    //enabled and in the movie
+    full_box(b"tkhd", 3, &content)
+}
+
+fn mdhd_box(duration: u64) -> Vec<u8> {
+    let mut content = Vec::with_capacity(20);
+    content.extend_from_slice(&[0u8; 8]); //creation and modification time
+    content.extend_from_slice(&TIMESCALE.to_be_bytes());
+    content.extend_from_slice(&(duration.min(u32::MAX as u64) as u32).to_be_bytes());
+    content.extend_from_slice(&0x55C4u16.to_be_bytes()); //"und" language
+    content.extend_from_slice(&[0u8; 2]);
+
+    full_box(b"mdhd", 0, &content)
+}
+
+fn hdlr_box() -> Vec<u8> {
+    let mut content = vec![0u8; 4];
+    content.extend_from_slice(b"vide");
+    content.extend_from_slice(&[0u8; 12]);
+    content.extend_from_slice(b"VideoHandler\0");
+
+    full_box(b"hdlr", 0, &content)
+}
+
+// the visual sample entry of the codec with its decoder configuration
+fn sample_entry(
+    kind: &[u8; 4],
+    configuration_kind: &[u8; 4],
+    configuration: &[u8],
+    dimensions: &Dimensions,
+) -> Vec<u8> {
+    let mut content = Vec::with_capacity(78 + configuration.len() + 8);
+    content.extend_from_slice(&[0u8; 6]);
+    content.extend_from_slice(&1u16.to_be_bytes()); //data reference index
+    content.extend_from_slice(&[0u8; 16]);
+    content.extend_from_slice(&(dimensions.width as u16).to_be_bytes());
+    content.extend_from_slice(&(dimensions.height as u16).to_be_bytes());
+    content.extend_from_slice(&0x0048_0000u32.to_be_bytes()); //72 dpi
+    content.extend_from_slice(&0x0048_0000u32.to_be_bytes());
+    content.extend_from_slice(&[0u8; 4]);
+    content.extend_from_slice(&1u16.to_be_bytes()); //frame count
+    content.extend_from_slice(&[0u8; 32]); //compressor name
+    content.extend_from_slice(&0x0018u16.to_be_bytes()); //depth
+    content.extend_from_slice(&(-1i16).to_be_bytes());
+    content.extend_from_slice(&mp4_box(configuration_kind, configuration));
+
+    mp4_box(kind, &content)
+}
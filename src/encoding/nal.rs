@@ -0,0 +1,213 @@
+// helpers for the Annex-B byte streams of the encoders, shared by the container writers.
+
+use crate::{capture_error::CaptureError, encoding::encoder_output::VideoCodec};
+
+// what a NAL unit is to a container
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum NalKind {
+    // HEVC only
+    VideoParameterSet,
+    SequenceParameterSet,
+    PictureParameterSet,
+    AccessUnitDelimiter,
+    // a slice or anything else that belongs to the sample
+    Other,
+}
+
+impl NalKind {
+    pub(crate) fn of(codec: VideoCodec, nal: &[u8]) -> Self {
+        let Some(&header) = nal.first() else {
+            return NalKind::Other;
+        };
+
+        match codec {
+            VideoCodec::H264 => match header & 0x1F {
+                7 => NalKind::SequenceParameterSet,
+                8 => NalKind::PictureParameterSet,
+                9 => NalKind::AccessUnitDelimiter,
+                _ => NalKind::Other,
+            },
+            VideoCodec::Hevc => match (header >> 1) & 0x3F {
+                32 => NalKind::VideoParameterSet,
+                33 => NalKind::SequenceParameterSet,
+                34 => NalKind::PictureParameterSet,
+                35 => NalKind::AccessUnitDelimiter,
+                _ => NalKind::Other,
+            },
+            //not NAL based
+            VideoCodec::Vp8 | VideoCodec::Vp9 => NalKind::Other,
+        }
+    }
+
+    pub(crate) fn is_parameter_set(&self) -> bool {
+        matches!(
+            self,
+            NalKind::VideoParameterSet
+                | NalKind::SequenceParameterSet
+                | NalKind::PictureParameterSet
+        )
+    }
+}
+
+// the NAL units of an Annex-B byte stream, without their start codes
+pub(crate) fn nal_units(data: &[u8]) -> Vec<&[u8]> {
+    let mut units = Vec::new();
+    let mut start = None;
+    let mut i = 0;
+
+    while i + 2 < data.len() {
+        if data[i] == 0 && data[i + 1] == 0 && data[i + 2] == 1 {
+            if let Some(start) = start {
+                units.push(trim_trailing_zeros(&data[start..i]));
+            }
+
+            i += 3;
+            start = Some(i);
+        } else {
+            i += 1;
+        }
+    }
+
+    if let Some(start) = start {
+        units.push(&data[start..]);
+    }
+
+    units.retain(|unit| !unit.is_empty());
+    units
+}
+
+// a NAL unit never ends with a zero byte, any zeros belong to the 4 byte start code of the next unit
+fn trim_trailing_zeros(unit: &[u8]) -> &[u8] {
+    let end = unit
+        .iter()
+        .rposition(|&byte| byte != 0)
+        .map_or(0, |last| last + 1);
+    &unit[..end]
+}
+
+// the parameter sets of a stream, taken from its first keyframe
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ParameterSets {
+    pub(crate) vps: Vec<Vec<u8>>,
+    pub(crate) sps: Vec<Vec<u8>>,
+    pub(crate) pps: Vec<Vec<u8>>,
+}
+
+impl ParameterSets {
+    pub(crate) fn push(&mut self, kind: NalKind, nal: &[u8]) {
+        let sets = match kind {
+            NalKind::VideoParameterSet => &mut self.vps,
+            NalKind::SequenceParameterSet => &mut self.sps,
+            NalKind::PictureParameterSet => &mut self.pps,
+            _ => return,
+        };
+
+        if !sets.iter().any(|set| set == nal) {
+            sets.push(nal.to_vec());
+        }
+    }
+
+    // the AVCDecoderConfigurationRecord (avcC) of ISO/IEC 14496-15
+    pub(crate) fn avc_decoder_configuration(&self) -> Result<Vec<u8>, CaptureError> {
+        let sps = self.first_sps(4)?;
+        let profile = sps[1];
+
+        let mut record = vec![1, profile, sps[2], sps[3], 0xFF];
+
+        record.push(0xE0 | self.sps.len() as u8);
+        for sps in &self.sps {
+            push_nal(&mut record, sps);
+        }
+
+        record.push(self.pps.len() as u8);
+        for pps in &self.pps {
+            push_nal(&mut record, pps);
+        }
+
+        //the high profiles carry the chroma format and bit depth, the encoders always produce 8 bit 4:2:0
+        if matches!(profile, 100 | 110 | 122 | 144) {
+            record.extend_from_slice(&[0xFC | 1, 0xF8, 0xF8, 0]);
+        }
+
+        Ok(record)
+    }
+
+    // the HEVCDecoderConfigurationRecord (hvcC) of ISO/IEC 14496-15
+    pub(crate) fn hevc_decoder_configuration(&self) -> Result<Vec<u8>, CaptureError> {
+        let sps = self.first_sps(3)?;
+
+        //the profile, tier and level follow the 2 byte NAL header and the layer byte of the sequence parameter set
+        let rbsp = remove_emulation_prevention(&sps[2..]);
+
+        if rbsp.len() < 13 {
+            return Err(CaptureError::Encoding(
+                "the sequence parameter set is too short".into(),
+            ));
+        }
+
+        let temporal_id_nested = rbsp[0] & 1;
+        let profile_tier_level = &rbsp[1..13];
+
+        let mut record = vec![1];
+        record.extend_from_slice(profile_tier_level);
+        record.extend_from_slice(&[
+            0xF0,
+            0x00,     //no minimum spatial segmentation
+            0xFC,     //unknown parallelism
+            0xFC | 1, //4:2:0
+            0xF8,     //8 bit luma
+            0xF8,     //8 bit chroma
+            0x00,
+            0x00, //unknown average frame rate
+            //one temporal layer and 4 byte NAL lengths
+            (1 << 3) | (temporal_id_nested << 2) | 3,
+        ]);
+
+        let arrays = [(32, &self.vps), (33, &self.sps), (34, &self.pps)];
+        record.push(arrays.len() as u8);
+
+        for (nal_type, sets) in arrays {
+            //every parameter set is in the array, none are sent in band
+            record.push(0x80 | nal_type);
+            record.extend_from_slice(&(sets.len() as u16).to_be_bytes());
+
+            for set in sets.iter() {
+                push_nal(&mut record, set);
+            }
+        }
+
+        Ok(record)
+    }
+
+    fn first_sps(&self, min_len: usize) -> Result<&[u8], CaptureError> {
+        match self.sps.first() {
+            Some(sps) if sps.len() >= min_len => Ok(sps),
+            _ => Err(CaptureError::Encoding(
+                "the stream has no sequence parameter set".into(),
+            )),
+        }
+    }
+}
+
+fn push_nal(record: &mut Vec<u8>, nal: &[u8]) {
+    record.extend_from_slice(&(nal.len() as u16).to_be_bytes());
+    record.extend_from_slice(nal);
+}
+
+// removes the 0x03 bytes that keep a NAL unit from containing a start code
+fn remove_emulation_prevention(data: &[u8]) -> Vec<u8> {
+    let mut rbsp = Vec::with_capacity(data.len());
+    let mut zeros = 0;
+
+    for &byte in data {
+        if zeros >= 2 && byte == 3 {
+            zeros = 0;
+            continue;
+        }
+
+        zeros = if byte == 0 { zeros + 1 } else { 0 };
+        rbsp.push(byte);
+    }
+
+    rbsp
+}
@@ -0,0 +1,108 @@
+use windows::Win32::Graphics::{
+    Direct3D11::{
+        D3D11_TEXTURE2D_DESC, D3D11_USAGE_DEFAULT, ID3D11Device, ID3D11DeviceContext,
+        ID3D11Texture2D,
+    },
+    Dxgi::Common::{DXGI_FORMAT_NV12, DXGI_SAMPLE_DESC},
+};
+
+use crate::{
+    capture_error::CaptureError,
+    devices::{AspectMode, Dimensions, GpuFrame, PixelFormat, gpu_scaler::GpuScaler},
+};
+
+// converts BGRA textures to upright NV12 textures of a fixed size on the GPU, so frames are encoded without leaving the GPU.
+//
+// textures of another size or rotation are letterboxed into the size.
+pub(crate) struct Nv12Converter {
+    device: ID3D11Device,
+    context: ID3D11DeviceContext,
+
+    // the size of the converted textures
+    size: Dimensions,
+
+    // made for the first texture and made again when the texture size or rotation changes
+    scaler: Option<GpuScaler>,
+}
+
+impl Nv12Converter {
+    pub(crate) fn new(device: &ID3D11Device, size: Dimensions) -> Result<Self, CaptureError> {
+        let context = unsafe { device.GetImmediateContext()? };
+
+        Ok(Self {
+            device: device.clone(),
+            context,
+            size,
+            scaler: None,
+        })
+    }
+
+    // the frame as a new NV12 texture, a new texture is made every frame because the encoder may still be reading the last one
+    pub(crate) unsafe fn convert(
+        &mut self,
+        frame: &GpuFrame,
+    ) -> Result<ID3D11Texture2D, CaptureError> {
+        let format = frame.format();
+
+        if format != PixelFormat::Bgra8 {
+            return Err(CaptureError::UnsupportedFormat(format!(
+                "only Bgra8 textures can be encoded, the capture delivers {format:?}"
+            )));
+        }
+
+        let outdated = !self.scaler.as_ref().is_some_and(|scaler| {
+            scaler.matches(
+                &frame.dimensions,
+                format,
+                frame.rotation,
+                &self.size,
+                AspectMode::Letterbox,
+            )
+        });
+
+        if outdated {
+            self.scaler = Some(GpuScaler::new(
+                &self.device,
+                &self.context,
+                &frame.dimensions,
+                format,
+                DXGI_FORMAT_NV12,
+                frame.rotation,
+                &self.size,
+                AspectMode::Letterbox,
+            )?);
+        }
+
+        let desc = D3D11_TEXTURE2D_DESC {
+            Width: self.size.width,
+            Height: self.size.height,
+            MipLevels: 1,
+            ArraySize: 1,
+            Format: DXGI_FORMAT_NV12,
+            SampleDesc: DXGI_SAMPLE_DESC {
+                Count: 1,
+                Quality: 0,
+            },
+            Usage: D3D11_USAGE_DEFAULT,
+            BindFlags: 0,
+            CPUAccessFlags: 0,
+            MiscFlags: 0,
+        };
+
+        let scaler = self.scaler.as_mut().unwrap();
+
+        unsafe {
+            let mut texture = None;
+            self.device
+                .CreateTexture2D(&desc, None, Some(&mut texture))?;
+            let texture = texture.unwrap();
+
+            //the capture writes to the texture again once the lock is released
+            let _lock = frame.lock()?;
+            let converted = scaler.scale(frame.texture())?;
+            self.context.CopyResource(&texture, converted);
+
+            Ok(texture)
+        }
+    }
+}
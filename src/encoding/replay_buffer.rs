@@ -0,0 +1,444 @@
+use std::{
+    collections::VecDeque,
+    path::Path,
+    pin::Pin,
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use tokio::{
+    sync::{
+        Mutex,
+        mpsc::{self, Receiver},
+    },
+    task::JoinHandle,
+};
+use windows::Win32::Media::MediaFoundation::MFVideoFormat_H264;
+
+use crate::{
+    capture_error::CaptureError,
+    devices::{Dimensions, Frame, camera::Output},
+    encoding::{
+        EncodedPacket, H264Config, Mp4Muxer,
+        encoder_output::{EncoderOutput, VideoCodec, encoded_dimensions},
+        h264_encoder::create_software_encoder,
+        mft_encoder::{self, EncoderFrame, EncoderSettings},
+    },
+    i_capture::ICapture,
+};
+
+// how many encoded packets can wait for the muxer, the encoder waits once they are not written
+const PACKET_CAPACITY: usize = 8;
+
+// the frames kept by a replay buffer, oldest first
+#[derive(Default)]
+pub(crate) struct Retained {
+    frames: VecDeque<Frame>,
+
+    // the memory held by the data of the frames
+    bytes: usize,
+
+    // frames dropped to stay within the limits
+    evicted: u64,
+}
+
+impl Retained {
+    // adds the newest frame and evicts the oldest frames until the frames span at most max_duration and hold at most max_bytes.
+    //
+    // a frame that alone is larger than max_bytes is not kept.
+    pub(crate) fn push(&mut self, frame: Frame, max_duration: Duration, max_bytes: usize) {
+        //a capture that was started again counts its timestamps from 0, the frames of the previous run cannot be ordered with it
+        if self
+            .frames
+            .back()
+            .is_some_and(|newest| frame.timestamp < newest.timestamp)
+        {
+            self.evicted += self.frames.len() as u64;
+            self.clear();
+        }
+
+        self.bytes += frame.data.capacity();
+        self.frames.push_back(frame);
+
+        let newest = self
+            .frames
+            .back()
+            .map_or(Duration::ZERO, |frame| frame.timestamp);
+
+        while let Some(oldest) = self.frames.front() {
+            if self.bytes <= max_bytes && newest - oldest.timestamp <= max_duration {
+                break;
+            }
+
+            self.bytes -= oldest.data.capacity();
+            self.frames.pop_front();
+            self.evicted += 1;
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.frames.clear();
+        self.bytes = 0;
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub(crate) fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    pub(crate) fn evicted(&self) -> u64 {
+        self.evicted
+    }
+
+    // the time between the oldest and the newest frame
+    pub(crate) fn span(&self) -> Duration {
+        match (self.frames.front(), self.frames.back()) {
+            (Some(oldest), Some(newest)) => newest.timestamp - oldest.timestamp,
+            _ => Duration::ZERO,
+        }
+    }
+
+    // the timestamps of the first and the last frame of the last duration, None when no frame is kept
+    pub(crate) fn window(&self, duration: Duration) -> Option<(Duration, Duration)> {
+        let newest = self.frames.back()?.timestamp;
+        let from = newest.saturating_sub(duration);
+
+        let first =
+            self.frames[self.frames.partition_point(|frame| frame.timestamp < from)].timestamp;
+
+        Some((first, newest))
+    }
+
+    // a copy of the first frame at or after from and not after until, frames are copied one at a time so saving never doubles the memory
+    pub(crate) fn next_frame(&self, from: Duration, until: Duration) -> Option<Frame> {
+        let index = self.frames.partition_point(|frame| frame.timestamp < from);
+
+        self.frames
+            .get(index)
+            .filter(|frame| frame.timestamp <= until)
+            .cloned()
+    }
+}
+
+/// # Replay Buffer
+///
+/// Keeps the last frames of a capture in memory, for saving the last seconds on demand (instant replay or game clips).
+///
+/// Frames are kept uncompressed up to a duration and a byte budget, the oldest frames are evicted first.
+/// A 1080p RGB32 frame holds 8 MB (NV12 3 MB), so 30 seconds at 30 fps need about 7.5 GB (2.8 GB), the byte budget caps what is actually kept.
+///
+/// save_last encodes the kept frames to an H.264 MP4 file while the capture keeps running.
+pub struct ReplayBuffer<S: ICapture<CaptureOutput = Frame>> {
+    source: Arc<S>,
+
+    retained: Arc<std::sync::Mutex<Retained>>,
+
+    max_duration: RwLock<Duration>,
+    max_bytes: RwLock<usize>,
+
+    // how saved clips are encoded
+    config: RwLock<H264Config>,
+
+    // determines if the frames of the source are kept
+    is_running: AtomicBool,
+}
+
+impl<S: ICapture<CaptureOutput = Frame> + 'static> ReplayBuffer<S> {
+    /// # New
+    ///
+    /// Wraps the source, keeping at most max_duration of frames that hold at most max_bytes.
+    ///
+    /// Capturing is started and stopped through the buffer, do not start the source yourself.
+    pub fn new(source: Arc<S>, max_duration: Duration, max_bytes: usize) -> Arc<Self> {
+        Arc::new(Self {
+            source,
+            retained: Arc::new(std::sync::Mutex::new(Retained::default())),
+            max_duration: RwLock::new(max_duration),
+            max_bytes: RwLock::new(max_bytes),
+            config: RwLock::new(H264Config::default()),
+            is_running: AtomicBool::new(false),
+        })
+    }
+
+    /// # Source
+    ///
+    /// The capture the frames are taken from.
+    pub fn source(&self) -> Arc<S> {
+        self.source.clone()
+    }
+
+    /// # Set Max Duration
+    ///
+    /// The longest time between the oldest and the newest kept frame, applied with the next frame.
+    pub fn set_max_duration(&self, max_duration: Duration) {
+        *self.max_duration.write().unwrap() = max_duration;
+    }
+
+    /// # Max Duration
+    ///
+    /// The longest time between the oldest and the newest kept frame.
+    pub fn max_duration(&self) -> Duration {
+        *self.max_duration.read().unwrap()
+    }
+
+    /// # Set Max Bytes
+    ///
+    /// The most memory the data of the kept frames can hold, applied with the next frame.
+    pub fn set_max_bytes(&self, max_bytes: usize) {
+        *self.max_bytes.write().unwrap() = max_bytes;
+    }
+
+    /// # Max Bytes
+    ///
+    /// The most memory the data of the kept frames can hold.
+    pub fn max_bytes(&self) -> usize {
+        *self.max_bytes.read().unwrap()
+    }
+
+    /// # Set Encoder Config
+    ///
+    /// How saved clips are encoded, H264Config::default by default.
+    pub fn set_encoder_config(&self, config: H264Config) {
+        *self.config.write().unwrap() = config;
+    }
+
+    /// # Encoder Config
+    ///
+    /// How saved clips are encoded.
+    pub fn encoder_config(&self) -> H264Config {
+        *self.config.read().unwrap()
+    }
+
+    /// # Retained Frames
+    ///
+    /// How many frames are kept.
+    pub fn retained_frames(&self) -> usize {
+        self.retained.lock().unwrap().len()
+    }
+
+    /// # Retained Bytes
+    ///
+    /// The memory held by the data of the kept frames, never more than max_bytes.
+    pub fn retained_bytes(&self) -> usize {
+        self.retained.lock().unwrap().bytes()
+    }
+
+    /// # Retained Duration
+    ///
+    /// The time between the oldest and the newest kept frame, never more than max_duration.
+    pub fn retained_duration(&self) -> Duration {
+        self.retained.lock().unwrap().span()
+    }
+
+    /// # Evicted Frames
+    ///
+    /// How many frames were dropped to stay within the limits.
+    pub fn evicted_frames(&self) -> u64 {
+        self.retained.lock().unwrap().evicted()
+    }
+
+    /// # Start
+    ///
+    /// Starts the source and spawns the task that keeps its frames, the frames of an earlier run are dropped.
+    ///
+    /// The returned handle completes once the source has stopped (with stop) or failed.
+    pub fn start(self: &Arc<Self>) -> Result<JoinHandle<Result<(), CaptureError>>, CaptureError> {
+        if self.is_running.swap(true, Ordering::AcqRel) {
+            return Err(CaptureError::Other(
+                "the replay buffer is already running".into(),
+            ));
+        }
+
+        self.retained.lock().unwrap().clear();
+
+        let buffer = self.clone();
+
+        Ok(tokio::spawn(async move {
+            let result = buffer.retain().await;
+            buffer.is_running.store(false, Ordering::Release);
+            result
+        }))
+    }
+
+    /// # Stop
+    ///
+    /// Stops the source, the kept frames can still be saved.
+    pub async fn stop(&self) -> Result<(), CaptureError> {
+        self.source
+            .clone()
+            .stop_capturing()
+            .await
+            .map_err(CaptureError::from)
+    }
+
+    // runs the source and keeps its frames until it stops
+    async fn retain(&self) -> Result<(), CaptureError> {
+        let capturing = self.source.clone();
+
+        let mut capture = tokio::spawn(async move {
+            capturing
+                .start_capturing()
+                .await
+                .map_err(CaptureError::from)
+        });
+
+        let receiver = self.source.clone_receiver();
+        let mut receiver = receiver.lock().await;
+
+        loop {
+            tokio::select! {
+                ended = &mut capture => {
+                    return ended.map_err(|e| CaptureError::Other(e.to_string()))?;
+                }
+                Some(frame) = receiver.recv() => {
+                    let (max_duration, max_bytes) = (self.max_duration(), self.max_bytes());
+
+                    self.retained.lock().unwrap().push(frame, max_duration, max_bytes);
+                }
+            }
+        }
+    }
+
+    /// # Save Last
+    ///
+    /// Encodes the kept frames of the last duration (up to the newest frame) to an H.264 MP4 file, created or truncated.
+    ///
+    /// The source keeps running and new frames are kept meanwhile. The saved clip starts at 0 and its frames are copied one at a time,
+    /// so saving needs little more memory than the buffer itself.
+    ///
+    /// Returns CaptureError::Encoding when no frame is kept or the source does not deliver RGB32 or NV12 frames.
+    pub async fn save_last(&self, duration: Duration, path: &Path) -> Result<(), CaptureError> {
+        let format = self.source.get_output_format();
+
+        if !matches!(format, Output::RGB32 | Output::NV12) {
+            return Err(CaptureError::Encoding(
+                "ReplayBuffer requires a source that delivers RGB32 or NV12 frames".into(),
+            ));
+        }
+
+        let window = self.retained.lock().unwrap().window(duration);
+
+        let Some((from, until)) = window else {
+            return Err(CaptureError::Encoding(
+                "no frames are kept, start the replay buffer first".into(),
+            ));
+        };
+
+        let config = self.encoder_config();
+        let dimensions = self.source.get_dimensions().map_err(CaptureError::from)?;
+
+        trace_event!(
+            info,
+            from = ?from,
+            until = ?until,
+            path = %path.display(),
+            "saving replay"
+        );
+
+        let settings = EncoderSettings {
+            create_transform: create_software_encoder,
+            subtype: MFVideoFormat_H264,
+            profile: config.profile.to_eav().0 as u32,
+            level: None,
+            hardware: false,
+            backend: None,
+            device: None,
+            bitrate_kbps: config.bitrate_kbps,
+            keyframe_interval_secs: config.keyframe_interval_secs,
+            frame_rate: config.frame_rate,
+            dimensions: dimensions.clone(),
+            input: format,
+        };
+
+        //every frame is encoded, the encoder waits for the muxer instead of dropping frames
+        let (frame_tx, frame_rx) = std::sync::mpsc::sync_channel(1);
+        let (packet_tx, packet_rx) = mpsc::channel(PACKET_CAPACITY);
+
+        let encoder = tokio::task::spawn_blocking(move || {
+            mft_encoder::encode_stream(settings, frame_rx, packet_tx, &RwLock::new(None))
+        });
+
+        let retained = self.retained.clone();
+
+        let feeder = tokio::task::spawn_blocking(move || {
+            let mut next = from;
+
+            loop {
+                //frames evicted meanwhile are skipped, the next kept one is used
+                let frame = retained.lock().unwrap().next_frame(next, until);
+
+                let Some(mut frame) = frame else {
+                    break;
+                };
+
+                next = frame.timestamp + Duration::from_nanos(1);
+                frame.timestamp -= from;
+
+                if frame_tx.send(EncoderFrame::Memory(frame)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let output = Arc::new(ClipOutput {
+            dimensions: encoded_dimensions(dimensions),
+            frame_rate: config.frame_rate.max(1),
+            packets: Arc::new(Mutex::new(packet_rx)),
+        });
+
+        let muxed = async {
+            let mut muxer = Mp4Muxer::create(output, path).await?;
+            muxer.run().await?;
+
+            Ok::<_, CaptureError>(muxer)
+        }
+        .await;
+
+        let _ = feeder.await;
+
+        //the encoder fails when the muxer stopped reading, the muxer error tells why
+        let encoded = encoder
+            .await
+            .map_err(|e| CaptureError::Other(e.to_string()))?;
+
+        let muxer = muxed?;
+        encoded?;
+
+        muxer.finalize().await
+    }
+}
+
+// the packets of a clip encoded by save_last, the muxer runs until the encoder drops its sender
+struct ClipOutput {
+    dimensions: Dimensions,
+    frame_rate: u32,
+    packets: Arc<Mutex<Receiver<EncodedPacket>>>,
+}
+
+impl EncoderOutput for ClipOutput {
+    fn codec(&self) -> VideoCodec {
+        VideoCodec::H264
+    }
+
+    fn get_encoded_dimensions(&self) -> Result<Dimensions, CaptureError> {
+        Ok(self.dimensions.clone())
+    }
+
+    fn frame_rate(&self) -> u32 {
+        self.frame_rate
+    }
+
+    fn clone_packet_receiver(&self) -> Arc<Mutex<Receiver<EncodedPacket>>> {
+        self.packets.clone()
+    }
+
+    fn finished(&self) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
+        Box::pin(std::future::pending())
+    }
+}
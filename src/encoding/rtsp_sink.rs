@@ -0,0 +1,518 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::{
+        Arc, Weak,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream, UdpSocket},
+    sync::Mutex,
+    task::JoinHandle,
+};
+
+use crate::{
+    capture_error::CaptureError,
+    clock::clock,
+    devices::Frame,
+    encoding::{EncodedPacket, EncoderOutput, H264Config, H264Encoder, nal::nal_units},
+    i_capture::ICapture,
+};
+
+// the clock rate of H.264 RTP timestamps
+const RTP_CLOCK_RATE: u64 = 90_000;
+
+// the dynamic payload type the stream is described with in the SDP
+const PAYLOAD_TYPE: u8 = 96;
+
+// the largest RTP payload, keeps the packets below the usual 1500 byte MTU
+const MAX_PAYLOAD: usize = 1400;
+
+// the NAL unit type of a fragmentation unit (FU-A) of RFC 6184
+const FU_A: u8 = 28;
+
+// how long a session may stay idle, announced to the clients
+const SESSION_TIMEOUT_SECS: u32 = 60;
+
+// the longest request or header line that is read, a client sending more without a line break is disconnected
+const MAX_LINE: u64 = 4096;
+
+// a client that set up the stream, packets are sent once it plays and a keyframe arrived
+struct Session {
+    id: String,
+    rtp_address: SocketAddr,
+    playing: bool,
+    started: bool,
+}
+
+// a request of a client, only the parts the sink answers to
+struct Request {
+    method: String,
+    url: String,
+    cseq: String,
+    transport: Option<String>,
+    session: Option<String>,
+}
+
+/// # Rtsp Sink
+///
+/// Serves the frames of a capture as a live H.264 stream over RTSP, for example to VLC or FFmpeg on the local network (rtsp://host:port/).
+///
+/// Clients are sent RTP over UDP unicast (RFC 6184, packetization mode 1), described by a static SDP. The parameter sets are sent in band with every keyframe,
+/// a client that starts playing receives the stream from the next keyframe on.
+///
+/// Interleaved RTP over the RTSP connection (TCP transport) is not supported, clients must be told to use UDP (such as ffmpeg -rtsp_transport udp).
+pub struct RtspSink {
+    port: u16,
+    config: H264Config,
+
+    // sends the RTP packets of every session
+    socket: UdpSocket,
+
+    sessions: Mutex<Vec<Session>>,
+
+    // the synchronization source of the stream, the same for every session
+    ssrc: u32,
+
+    next_session: AtomicU64,
+    packets_sent: AtomicU64,
+
+    // accepts the RTSP connections, aborted when the sink is dropped
+    accept_task: JoinHandle<()>,
+}
+
+impl RtspSink {
+    /// # New
+    ///
+    /// Starts an RTSP server on the port of every interface, frames are encoded with the config once a source is connected.
+    ///
+    /// Must be called from within a Tokio runtime, the server runs until the sink is dropped.
+    pub fn new(port: u16, encoder_config: H264Config) -> Result<Arc<RtspSink>, CaptureError> {
+        let listener = std::net::TcpListener::bind(("0.0.0.0", port))?;
+        listener.set_nonblocking(true)?;
+        let listener = TcpListener::from_std(listener)?;
+
+        let socket = std::net::UdpSocket::bind(("0.0.0.0", 0))?;
+        socket.set_nonblocking(true)?;
+        let socket = UdpSocket::from_std(socket)?;
+
+        let port = listener.local_addr()?.port();
+
+        trace_event!(info, port, "rtsp server listening");
+
+        Ok(Arc::new_cyclic(|sink: &Weak<RtspSink>| RtspSink {
+            port,
+            config: encoder_config,
+            socket,
+            sessions: Mutex::new(vec![]),
+            ssrc: clock() as u32,
+            next_session: AtomicU64::new(1),
+            packets_sent: AtomicU64::new(0),
+            accept_task: tokio::spawn(accept(listener, sink.clone())),
+        }))
+    }
+
+    /// # Port
+    ///
+    /// The port the RTSP server listens on, the bound port when the sink was created with port 0.
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// # Config
+    ///
+    /// The settings the frames are encoded with.
+    pub fn config(&self) -> H264Config {
+        self.config
+    }
+
+    /// # Packets Sent
+    ///
+    /// How many RTP packets have been sent, counted once per session.
+    pub fn packets_sent(&self) -> u64 {
+        self.packets_sent.load(Ordering::Relaxed)
+    }
+
+    /// # Session Count
+    ///
+    /// How many clients have set up the stream.
+    pub async fn session_count(&self) -> usize {
+        self.sessions.lock().await.len()
+    }
+
+    /// # Connect Source
+    ///
+    /// Encodes the frames of the source with an H264Encoder and sends them to the playing clients, the source is started and stopped through the encoder.
+    ///
+    /// The returned handle completes once the source has stopped.
+    pub fn connect_source<S: ICapture<CaptureOutput = Frame> + 'static>(
+        self: &Arc<Self>,
+        source: Arc<S>,
+    ) -> Result<JoinHandle<Result<(), CaptureError>>, CaptureError> {
+        let encoder = H264Encoder::new(source, self.config);
+        let encoding = encoder.start()?;
+        let sink = self.clone();
+
+        Ok(tokio::spawn(async move {
+            let receiver = encoder.receiver.clone();
+            let mut receiver = receiver.lock().await;
+            let mut sequence: u16 = 0;
+
+            //the receiver stays open when the encoder finishes
+            let finished = encoder.finished();
+            tokio::pin!(finished);
+
+            loop {
+                tokio::select! {
+                    Some(packet) = receiver.recv() => {
+                        sink.send_packet(&packet, &mut sequence).await;
+                    }
+                    _ = &mut finished => {
+                        //send what the encoder flushed before finishing
+                        while let Ok(packet) = receiver.try_recv() {
+                            sink.send_packet(&packet, &mut sequence).await;
+                        }
+
+                        break;
+                    }
+                }
+            }
+
+            encoding
+                .await
+                .map_err(|e| CaptureError::Other(e.to_string()))?
+        }))
+    }
+
+    // packetizes an access unit and sends it to every playing session
+    async fn send_packet(&self, packet: &EncodedPacket, sequence: &mut u16) {
+        //RTP timestamps wrap around
+        let timestamp =
+            (packet.timestamp.as_nanos() * RTP_CLOCK_RATE as u128 / 1_000_000_000) as u32;
+        let rtp_packets = packetize(&packet.data, timestamp, self.ssrc, sequence);
+
+        let mut sessions = self.sessions.lock().await;
+
+        for session in sessions.iter_mut().filter(|session| session.playing) {
+            //decoding can only start at a keyframe
+            if !session.started && !packet.keyframe {
+                continue;
+            }
+
+            session.started = true;
+
+            for rtp_packet in &rtp_packets {
+                if let Err(_e) = self.socket.send_to(rtp_packet, session.rtp_address).await {
+                    trace_event!(warn, error = %_e, "could not send an RTP packet");
+                    break;
+                }
+
+                self.packets_sent.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    // answers one request, the session of the connection is updated by SETUP and TEARDOWN
+    async fn respond(
+        &self,
+        request: &Request,
+        peer: IpAddr,
+        session_id: &mut Option<String>,
+    ) -> String {
+        let cseq = &request.cseq;
+
+        match request.method.as_str() {
+            "OPTIONS" => format!(
+                "RTSP/1.0 200 OK\r\nCSeq: {cseq}\r\nPublic: OPTIONS, DESCRIBE, SETUP, PLAY, TEARDOWN\r\n\r\n"
+            ),
+            "DESCRIBE" => {
+                let sdp = self.sdp();
+                let base = request.url.trim_end_matches('/');
+
+                format!(
+                    "RTSP/1.0 200 OK\r\nCSeq: {cseq}\r\nContent-Base: {base}/\r\nContent-Type: application/sdp\r\nContent-Length: {}\r\n\r\n{sdp}",
+                    sdp.len()
+                )
+            }
+            "SETUP" => {
+                let Some(client_port) = request.transport.as_deref().and_then(client_port) else {
+                    return format!("RTSP/1.0 461 Unsupported Transport\r\nCSeq: {cseq}\r\n\r\n");
+                };
+
+                //the RTCP port follows the RTP port, there is none after the last port
+                let Some(client_rtcp_port) = client_port.checked_add(1) else {
+                    return format!("RTSP/1.0 400 Bad Request\r\nCSeq: {cseq}\r\n\r\n");
+                };
+
+                let server_port = self.socket.local_addr().map_or(0, |address| address.port());
+
+                let Some(server_rtcp_port) = server_port.checked_add(1) else {
+                    return format!("RTSP/1.0 500 Internal Server Error\r\nCSeq: {cseq}\r\n\r\n");
+                };
+
+                let id = format!(
+                    "{:08X}",
+                    self.next_session.fetch_add(1, Ordering::Relaxed) ^ self.ssrc as u64
+                );
+
+                self.sessions.lock().await.push(Session {
+                    id: id.clone(),
+                    rtp_address: SocketAddr::new(peer, client_port),
+                    playing: false,
+                    started: false,
+                });
+
+                trace_event!(debug, session = %id, client_port, "rtsp session set up");
+
+                let transport = format!(
+                    "RTP/AVP;unicast;client_port={client_port}-{client_rtcp_port};server_port={server_port}-{server_rtcp_port};ssrc={:08X}",
+                    self.ssrc
+                );
+
+                *session_id = Some(id.clone());
+
+                format!(
+                    "RTSP/1.0 200 OK\r\nCSeq: {cseq}\r\nTransport: {transport}\r\nSession: {id};timeout={SESSION_TIMEOUT_SECS}\r\n\r\n"
+                )
+            }
+            "PLAY" => {
+                let id = request.session.clone().or_else(|| session_id.clone());
+                let mut sessions = self.sessions.lock().await;
+
+                let Some(session) = sessions
+                    .iter_mut()
+                    .find(|session| Some(&session.id) == id.as_ref())
+                else {
+                    return format!("RTSP/1.0 454 Session Not Found\r\nCSeq: {cseq}\r\n\r\n");
+                };
+
+                session.playing = true;
+
+                format!(
+                    "RTSP/1.0 200 OK\r\nCSeq: {cseq}\r\nSession: {}\r\nRange: npt=0.000-\r\n\r\n",
+                    session.id
+                )
+            }
+            "TEARDOWN" => {
+                if let Some(id) = request.session.clone().or_else(|| session_id.take()) {
+                    self.remove_session(&id).await;
+                }
+
+                format!("RTSP/1.0 200 OK\r\nCSeq: {cseq}\r\n\r\n")
+            }
+            _ => format!(
+                "RTSP/1.0 405 Method Not Allowed\r\nCSeq: {cseq}\r\nAllow: OPTIONS, DESCRIBE, SETUP, PLAY, TEARDOWN\r\n\r\n"
+            ),
+        }
+    }
+
+    async fn remove_session(&self, id: &str) {
+        self.sessions
+            .lock()
+            .await
+            .retain(|session| session.id != id);
+    }
+
+    // the static description of the stream, the parameter sets are sent in band
+    fn sdp(&self) -> String {
+        format!(
+            "v=0\r\no=- {ssrc} 1 IN IP4 0.0.0.0\r\ns=win-video\r\nc=IN IP4 0.0.0.0\r\nt=0 0\r\nm=video 0 RTP/AVP {PAYLOAD_TYPE}\r\na=rtpmap:{PAYLOAD_TYPE} H264/{RTP_CLOCK_RATE}\r\na=fmtp:{PAYLOAD_TYPE} packetization-mode=1\r\na=framerate:{}\r\na=control:trackID=0\r\n",
+            self.config.frame_rate,
+            ssrc = self.ssrc,
+        )
+    }
+}
+
+impl Drop for RtspSink {
+    fn drop(&mut self) {
+        self.accept_task.abort();
+    }
+}
+
+// accepts connections until the sink is dropped
+async fn accept(listener: TcpListener, sink: Weak<RtspSink>) {
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(_e) => {
+                trace_event!(warn, error = %_e, "could not accept an rtsp connection");
+                continue;
+            }
+        };
+
+        trace_event!(debug, peer = %peer, "rtsp client connected");
+
+        tokio::spawn(serve(stream, peer.ip(), sink.clone()));
+    }
+}
+
+// answers the requests of one connection, its session ends with the connection
+async fn serve(stream: TcpStream, peer: IpAddr, sink: Weak<RtspSink>) {
+    let (read, mut write) = stream.into_split();
+    let mut read = BufReader::new(read);
+    let mut session_id = None;
+
+    while let Ok(Some(request)) = read_request(&mut read).await {
+        let Some(sink) = sink.upgrade() else {
+            return;
+        };
+
+        let response = sink.respond(&request, peer, &mut session_id).await;
+
+        if write.write_all(response.as_bytes()).await.is_err() {
+            break;
+        }
+    }
+
+    if let (Some(sink), Some(id)) = (sink.upgrade(), session_id) {
+        sink.remove_session(&id).await;
+    }
+}
+
+// reads the request line and headers of a request, None once the connection is closed
+async fn read_request<R: AsyncBufReadExt + Unpin>(
+    read: &mut R,
+) -> Result<Option<Request>, std::io::Error> {
+    let mut line = String::new();
+
+    //skip the empty lines between requests
+    loop {
+        line.clear();
+
+        if read_line(read, &mut line).await? == 0 {
+            return Ok(None);
+        }
+
+        if !line.trim().is_empty() {
+            break;
+        }
+    }
+
+    let mut parts = line.split_whitespace();
+
+    let mut request = Request {
+        method: parts.next().unwrap_or_default().to_ascii_uppercase(),
+        url: parts.next().unwrap_or_default().to_string(),
+        cseq: "0".into(),
+        transport: None,
+        session: None,
+    };
+
+    loop {
+        line.clear();
+
+        if read_line(read, &mut line).await? == 0 || line.trim().is_empty() {
+            break;
+        }
+
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+
+        let value = value.trim().to_string();
+
+        match name.trim().to_ascii_lowercase().as_str() {
+            "cseq" => request.cseq = value,
+            "transport" => request.transport = Some(value),
+            //the timeout is not part of the id
+            "session" => request.session = value.split(';').next().map(str::to_string),
+            _ => {}
+        }
+    }
+
+    Ok(Some(request))
+}
+
+// reads one line of at most MAX_LINE bytes, an error for a longer line
+async fn read_line<R: AsyncBufReadExt + Unpin>(
+    read: &mut R,
+    line: &mut String,
+) -> Result<usize, std::io::Error> {
+    let read_bytes = (&mut *read).take(MAX_LINE).read_line(line).await?;
+
+    if read_bytes as u64 == MAX_LINE && !line.ends_with('\n') {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "the rtsp request line is too long",
+        ));
+    }
+
+    Ok(read_bytes)
+}
+
+// the RTP port of a UDP unicast transport, None for other transports such as interleaved TCP
+fn client_port(transport: &str) -> Option<u16> {
+    transport
+        .split(',')
+        .filter(|spec| {
+            let profile = spec.split(';').next().unwrap_or_default();
+            profile == "RTP/AVP" || profile == "RTP/AVP/UDP"
+        })
+        .flat_map(|spec| spec.split(';'))
+        .find_map(|parameter| parameter.strip_prefix("client_port="))
+        .and_then(|ports| ports.split('-').next())
+        .and_then(|port| port.parse().ok())
+}
+
+// the RTP packets of an Annex-B access unit (RFC 6184), small NAL units are sent whole and large ones as FU-A fragments
+fn packetize(data: &[u8], timestamp: u32, ssrc: u32, sequence: &mut u16) -> Vec<Vec<u8>> {
+    let units = nal_units(data);
+    let mut packets = vec![];
+
+    for (index, nal) in units.iter().enumerate() {
+        let last_unit = index + 1 == units.len();
+
+        if nal.len() <= MAX_PAYLOAD {
+            packets.push(rtp_packet(nal, &[], last_unit, timestamp, ssrc, sequence));
+            continue;
+        }
+
+        let indicator = (nal[0] & 0xE0) | FU_A;
+        let nal_type = nal[0] & 0x1F;
+        let fragments: Vec<&[u8]> = nal[1..].chunks(MAX_PAYLOAD - 2).collect();
+
+        for (fragment_index, fragment) in fragments.iter().enumerate() {
+            let first = fragment_index == 0;
+            let last = fragment_index + 1 == fragments.len();
+
+            let header = nal_type | if first { 0x80 } else { 0 } | if last { 0x40 } else { 0 };
+
+            packets.push(rtp_packet(
+                &[indicator, header],
+                fragment,
+                last_unit && last,
+                timestamp,
+                ssrc,
+                sequence,
+            ));
+        }
+    }
+
+    packets
+}
+
+// an RTP packet with the payload of the two parts, the marker ends the access unit
+fn rtp_packet(
+    head: &[u8],
+    tail: &[u8],
+    marker: bool,
+    timestamp: u32,
+    ssrc: u32,
+    sequence: &mut u16,
+) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(12 + head.len() + tail.len());
+
+    packet.push(0x80);
+    packet.push(PAYLOAD_TYPE | if marker { 0x80 } else { 0 });
+    packet.extend_from_slice(&sequence.to_be_bytes());
+    packet.extend_from_slice(&timestamp.to_be_bytes());
+    packet.extend_from_slice(&ssrc.to_be_bytes());
+    packet.extend_from_slice(head);
+    packet.extend_from_slice(tail);
+
+    *sequence = sequence.wrapping_add(1);
+
+    packet
+}
@@ -0,0 +1,511 @@
+use std::{io::SeekFrom, path::Path, sync::Arc, time::Duration};
+
+use tokio::{
+    fs::{File, OpenOptions},
+    io::{AsyncSeekExt, AsyncWriteExt},
+};
+
+use crate::{
+    capture_error::CaptureError,
+    devices::Dimensions,
+    encoding::{
+        EncodedPacket,
+        encoder_output::{EncoderOutput, VideoCodec},
+    },
+};
+
+// the element ids of the Matroska specification used by WebM
+const EBML: u32 = 0x1A45_DFA3;
+const EBML_VERSION: u32 = 0x4286;
+const EBML_READ_VERSION: u32 = 0x42F7;
+const EBML_MAX_ID_LENGTH: u32 = 0x42F2;
+const EBML_MAX_SIZE_LENGTH: u32 = 0x42F3;
+const DOC_TYPE: u32 = 0x4282;
+const DOC_TYPE_VERSION: u32 = 0x4287;
+const DOC_TYPE_READ_VERSION: u32 = 0x4285;
+const SEGMENT: u32 = 0x1853_8067;
+const SEEK_HEAD: u32 = 0x114D_9B74;
+const SEEK: u32 = 0x4DBB;
+const SEEK_ID: u32 = 0x53AB;
+const SEEK_POSITION: u32 = 0x53AC;
+const INFO: u32 = 0x1549_A966;
+const TIMESTAMP_SCALE: u32 = 0x2AD7B1;
+const MUXING_APP: u32 = 0x4D80;
+const WRITING_APP: u32 = 0x5741;
+const DURATION: u32 = 0x4489;
+const TRACKS: u32 = 0x1654_AE6B;
+const TRACK_ENTRY: u32 = 0xAE;
+const TRACK_NUMBER: u32 = 0xD7;
+const TRACK_UID: u32 = 0x73C5;
+const TRACK_TYPE: u32 = 0x83;
+const FLAG_LACING: u32 = 0x9C;
+const DEFAULT_DURATION: u32 = 0x23E383;
+const CODEC_ID: u32 = 0x86;
+const VIDEO: u32 = 0xE0;
+const PIXEL_WIDTH: u32 = 0xB0;
+const PIXEL_HEIGHT: u32 = 0xBA;
+const CLUSTER: u32 = 0x1F43_B675;
+const TIMESTAMP: u32 = 0xE7;
+const SIMPLE_BLOCK: u32 = 0xA3;
+const CUES: u32 = 0x1C53_BB6B;
+const CUE_POINT: u32 = 0xBB;
+const CUE_TIME: u32 = 0xB3;
+const CUE_TRACK_POSITIONS: u32 = 0xB7;
+const CUE_TRACK: u32 = 0xF7;
+const CUE_CLUSTER_POSITION: u32 = 0xF1;
+
+// a timestamp tick in nanoseconds, timestamps are in milliseconds
+const TIMESTAMP_SCALE_NS: u64 = 1_000_000;
+
+// blocks hold a signed 16 bit offset to the timestamp of their cluster
+const MAX_CLUSTER_DURATION_MS: u64 = i16::MAX as u64;
+
+// the first byte of an 8 byte element size, followed by the 7 bytes of the value
+const SIZE_MARKER_8: u8 = 0x01;
+
+// the only track of the file
+const TRACK: u64 = 1;
+
+// a cluster kept in memory until it is complete, so its size is known when it is written
+struct Cluster {
+    timestamp_ms: u64,
+    keyframe: bool,
+    blocks: Vec<u8>,
+}
+
+// a cluster starting with a keyframe, where players can start decoding when seeking
+struct CuePoint {
+    timestamp_ms: u64,
+    position: u64,
+}
+
+/// # Webm Muxer
+///
+/// Writes VP8 or VP9 frames to a WebM (Matroska) file, the container for video on the web.
+///
+/// Frames are grouped into a cluster per keyframe, which is written once the next keyframe arrives.
+/// finalize writes the seek index (cues) and the duration of the recording.
+///
+/// The file is not playable until finalize has completed.
+pub struct WebmMuxer {
+    // the encoder the packets are read from by run
+    output: Option<Arc<dyn EncoderOutput>>,
+    file: File,
+
+    frame_rate: u32,
+
+    // where the data of the segment starts, positions inside of the file are relative to it
+    segment_start: u64,
+
+    // where the values written by finalize are
+    duration_position: u64,
+    cues_seek_position: u64,
+
+    // the end of the file
+    position: u64,
+
+    // taken from the first keyframe, packets before it are skipped
+    first_timestamp: Option<Duration>,
+    last_timestamp_ms: u64,
+
+    cluster: Option<Cluster>,
+    cue_points: Vec<CuePoint>,
+    frames: usize,
+}
+
+impl WebmMuxer {
+    /// # Create
+    ///
+    /// Creates (or truncates) the file for the packets of a VP8 or VP9 encoder, the encoder can be started before or after.
+    ///
+    /// Returns CaptureError::UnsupportedFormat for H.264 and HEVC encoders, use the Mp4Muxer for those.
+    pub async fn create(output: Arc<dyn EncoderOutput>, path: &Path) -> Result<Self, CaptureError> {
+        let dimensions = output.get_encoded_dimensions()?;
+
+        let mut muxer =
+            Self::create_with_codec(path, output.codec(), dimensions, output.frame_rate()).await?;
+        muxer.output = Some(output);
+
+        Ok(muxer)
+    }
+
+    /// # Create With Codec
+    ///
+    /// Creates (or truncates) the file for VP8 or VP9 frames of the given size that are written with write_packet, such as the frames of an encoder outside of this crate.
+    ///
+    /// Returns CaptureError::UnsupportedFormat for H.264 and HEVC.
+    pub async fn create_with_codec(
+        path: &Path,
+        codec: VideoCodec,
+        dimensions: Dimensions,
+        frame_rate: u32,
+    ) -> Result<Self, CaptureError> {
+        let codec_id = match codec {
+            VideoCodec::Vp8 => "V_VP8",
+            VideoCodec::Vp9 => "V_VP9",
+            VideoCodec::H264 | VideoCodec::Hevc => {
+                return Err(CaptureError::UnsupportedFormat(format!(
+                    "{codec:?} cannot be written to a WebM file, use the Mp4Muxer"
+                )));
+            }
+        };
+
+        let frame_rate = frame_rate.max(1);
+
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+            .await?;
+
+        let header = ebml_header();
+
+        //the size of the segment is written by finalize
+        let mut segment = id_bytes(SEGMENT);
+        segment.push(SIZE_MARKER_8);
+        segment.extend_from_slice(&[0xFF; 7]);
+
+        let segment_start = (header.len() + segment.len()) as u64;
+
+        let info = info_element();
+        let tracks = tracks_element(codec_id, &dimensions, frame_rate);
+
+        //the seek head has a fixed size, so the positions behind it are known before it is made
+        let seek_head_size = seek_head_element(0, 0, 0).len() as u64;
+        let info_position = seek_head_size;
+        let tracks_position = info_position + info.len() as u64;
+        let seek_head = seek_head_element(info_position, tracks_position, 0);
+
+        //the duration and the cues position are the last 8 bytes of their elements
+        let duration_position = segment_start + tracks_position - 8;
+        let cues_seek_position = segment_start + seek_head_size - 8;
+
+        let data = [header, segment, seek_head, info, tracks].concat();
+        file.write_all(&data).await?;
+
+        Ok(Self {
+            output: None,
+            file,
+            frame_rate,
+            segment_start,
+            duration_position,
+            cues_seek_position,
+            position: data.len() as u64,
+            first_timestamp: None,
+            last_timestamp_ms: 0,
+            cluster: None,
+            cue_points: Vec::new(),
+            frames: 0,
+        })
+    }
+
+    /// # Frames Written
+    ///
+    /// How many frames have been written to the file.
+    pub fn frames_written(&self) -> usize {
+        self.frames
+    }
+
+    /// # Duration
+    ///
+    /// The length of the recording so far, from the first keyframe to the end of the last frame.
+    pub fn duration(&self) -> Duration {
+        if self.frames == 0 {
+            return Duration::ZERO;
+        }
+
+        //the last frame is shown for a frame
+        Duration::from_millis(self.last_timestamp_ms)
+            + Duration::from_secs_f64(1.0 / self.frame_rate as f64)
+    }
+
+    /// # Run
+    ///
+    /// Writes every packet of the encoder until the encoder has stopped and sent its last packet, then call finalize.
+    ///
+    /// Returns CaptureError::Other when the muxer was made with create_with_codec, which has no encoder to read from.
+    pub async fn run(&mut self) -> Result<(), CaptureError> {
+        let Some(output) = self.output.clone() else {
+            return Err(CaptureError::Other(
+                "the muxer has no encoder, write the packets with write_packet".into(),
+            ));
+        };
+
+        let receiver = output.clone_packet_receiver();
+        let mut receiver = receiver.lock().await;
+        let mut finished = output.finished();
+
+        loop {
+            tokio::select! {
+                packet = receiver.recv() => match packet {
+                    Some(packet) => self.write_packet(&packet).await?,
+                    None => return Ok(()),
+                },
+                _ = &mut finished => break,
+            }
+        }
+
+        //every packet was sent before the encoder finished
+        while let Ok(packet) = receiver.try_recv() {
+            self.write_packet(&packet).await?;
+        }
+
+        Ok(())
+    }
+
+    /// # Write Packet
+    ///
+    /// Writes a single VP8 or VP9 frame to the file, for reading the packets yourself instead of using run.
+    ///
+    /// Packets before the first keyframe are skipped, the file has to start with a keyframe.
+    pub async fn write_packet(&mut self, packet: &EncodedPacket) -> Result<(), CaptureError> {
+        if self.first_timestamp.is_none() && !packet.keyframe {
+            return Ok(());
+        }
+
+        let first = *self.first_timestamp.get_or_insert(packet.timestamp);
+
+        //blocks of a track must not go back in time
+        let timestamp_ms =
+            (packet.timestamp.saturating_sub(first).as_millis() as u64).max(self.last_timestamp_ms);
+
+        let starts_cluster = self.cluster.as_ref().is_none_or(|cluster| {
+            packet.keyframe || timestamp_ms - cluster.timestamp_ms > MAX_CLUSTER_DURATION_MS
+        });
+
+        if starts_cluster {
+            self.write_cluster().await?;
+
+            self.cluster = Some(Cluster {
+                timestamp_ms,
+                keyframe: packet.keyframe,
+                blocks: Vec::new(),
+            });
+        }
+
+        let cluster = self.cluster.as_mut().unwrap();
+        let offset = (timestamp_ms - cluster.timestamp_ms) as i16;
+
+        let mut block = vint(TRACK);
+        block.extend_from_slice(&offset.to_be_bytes());
+        block.push(if packet.keyframe { 0x80 } else { 0 });
+        block.extend_from_slice(&packet.data);
+
+        cluster.blocks.extend(element(SIMPLE_BLOCK, &block));
+
+        self.last_timestamp_ms = timestamp_ms;
+        self.frames += 1;
+
+        Ok(())
+    }
+
+    /// # Finalize
+    ///
+    /// Writes the last cluster, the seek index and the duration, after this the file is complete.
+    ///
+    /// Returns CaptureError::Encoding when not a single keyframe was written.
+    pub async fn finalize(mut self) -> Result<(), CaptureError> {
+        if self.frames == 0 {
+            return Err(CaptureError::Encoding(
+                "no keyframe was written, the file would not be playable".into(),
+            ));
+        }
+
+        self.write_cluster().await?;
+
+        let cues_position = self.position - self.segment_start;
+        let cues = self.cues_element();
+        self.file.write_all(&cues).await?;
+        self.position += cues.len() as u64;
+
+        let duration_ms = self.duration().as_secs_f64() * 1000.0;
+        let segment_size = self.position - self.segment_start;
+
+        let mut size = vec![SIZE_MARKER_8];
+        size.extend_from_slice(&segment_size.to_be_bytes()[1..]);
+
+        self.file.flush().await?;
+
+        self.write_at(self.segment_start - 8, &size).await?;
+        self.write_at(self.duration_position, &duration_ms.to_be_bytes())
+            .await?;
+        self.write_at(self.cues_seek_position, &cues_position.to_be_bytes())
+            .await?;
+
+        self.file.flush().await?;
+        self.file.sync_all().await?;
+
+        Ok(())
+    }
+
+    // writes the cluster being filled to the end of the file
+    async fn write_cluster(&mut self) -> Result<(), CaptureError> {
+        let Some(cluster) = self.cluster.take() else {
+            return Ok(());
+        };
+
+        if cluster.keyframe {
+            self.cue_points.push(CuePoint {
+                timestamp_ms: cluster.timestamp_ms,
+                position: self.position - self.segment_start,
+            });
+        }
+
+        let data = element(
+            CLUSTER,
+            &[
+                uint_element(TIMESTAMP, cluster.timestamp_ms),
+                cluster.blocks,
+            ]
+            .concat(),
+        );
+
+        self.file.write_all(&data).await?;
+        self.position += data.len() as u64;
+
+        Ok(())
+    }
+
+    async fn write_at(&mut self, position: u64, data: &[u8]) -> Result<(), CaptureError> {
+        self.file.seek(SeekFrom::Start(position)).await?;
+        self.file.write_all(data).await?;
+
+        Ok(())
+    }
+
+    fn cues_element(&self) -> Vec<u8> {
+        let points: Vec<u8> = self
+            .cue_points
+            .iter()
+            .flat_map(|point| {
+                let positions = element(
+                    CUE_TRACK_POSITIONS,
+                    &[
+                        uint_element(CUE_TRACK, TRACK),
+                        uint_element(CUE_CLUSTER_POSITION, point.position),
+                    ]
+                    .concat(),
+                );
+
+                element(
+                    CUE_POINT,
+                    &[uint_element(CUE_TIME, point.timestamp_ms), positions].concat(),
+                )
+            })
+            .collect();
+
+        element(CUES, &points)
+    }
+}
+
+fn ebml_header() -> Vec<u8> {
+    element(
+        EBML,
+        &[
+            uint_element(EBML_VERSION, 1),
+            uint_element(EBML_READ_VERSION, 1),
+            uint_element(EBML_MAX_ID_LENGTH, 4),
+            uint_element(EBML_MAX_SIZE_LENGTH, 8),
+            element(DOC_TYPE, b"webm"),
+            uint_element(DOC_TYPE_VERSION, 4),
+            uint_element(DOC_TYPE_READ_VERSION, 2),
+        ]
+        .concat(),
+    )
+}
+
+// the seek head pointing at the info, tracks and cues, every position is 8 bytes so its size never changes
+fn seek_head_element(info: u64, tracks: u64, cues: u64) -> Vec<u8> {
+    let seek = |id: u32, position: u64| {
+        element(
+            SEEK,
+            &[
+                element(SEEK_ID, &id_bytes(id)),
+                element(SEEK_POSITION, &position.to_be_bytes()),
+            ]
+            .concat(),
+        )
+    };
+
+    element(
+        SEEK_HEAD,
+        &[seek(INFO, info), seek(TRACKS, tracks), seek(CUES, cues)].concat(),
+    )
+}
+
+// the segment info, the duration is last so finalize can find it
+fn info_element() -> Vec<u8> {
+    element(
+        INFO,
+        &[
+            uint_element(TIMESTAMP_SCALE, TIMESTAMP_SCALE_NS),
+            element(MUXING_APP, b"win-video"),
+            element(WRITING_APP, b"win-video"),
+            element(DURATION, &0f64.to_be_bytes()),
+        ]
+        .concat(),
+    )
+}
+
+fn tracks_element(codec_id: &str, dimensions: &Dimensions, frame_rate: u32) -> Vec<u8> {
+    let video = element(
+        VIDEO,
+        &[
+            uint_element(PIXEL_WIDTH, dimensions.width as u64),
+            uint_element(PIXEL_HEIGHT, dimensions.height as u64),
+        ]
+        .concat(),
+    );
+
+    let entry = element(
+        TRACK_ENTRY,
+        &[
+            uint_element(TRACK_NUMBER, TRACK),
+            uint_element(TRACK_UID, TRACK),
+            //video
+            uint_element(TRACK_TYPE, 1),
+            uint_element(FLAG_LACING, 0),
+            uint_element(DEFAULT_DURATION, 1_000_000_000 / frame_rate as u64),
+            element(CODEC_ID, codec_id.as_bytes()),
+            video,
+        ]
+        .concat(),
+    );
+
+    element(TRACKS, &entry)
+}
+
+fn element(id: u32, content: &[u8]) -> Vec<u8> {
+    let mut data = id_bytes(id);
+    data.extend(vint(content.len() as u64));
+    data.extend_from_slice(content);
+    data
+}
+
+// an unsigned integer in as few bytes as possible
+fn uint_element(id: u32, value: u64) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let zeros = bytes.iter().take_while(|&&byte| byte == 0).count().min(7);
+
+    element(id, &bytes[zeros..])
+}
+
+// element ids keep their length marker, so they are written without leading zeros
+fn id_bytes(id: u32) -> Vec<u8> {
+    let bytes = id.to_be_bytes();
+    let zeros = bytes.iter().take_while(|&&byte| byte == 0).count();
+
+    bytes[zeros..].to_vec()
+}
+
+// a variable length integer, the number of leading zeros of the first byte tells the length
+fn vint(value: u64) -> Vec<u8> {
+    //all ones is reserved for an unknown size
+    let length = (1..8)
+        .find(|&length| value < (1u64 << (7 * length)) - 1)
+        .unwrap_or(8);
+
+    let marked = value | (1u64 << (7 * length));
+    marked.to_be_bytes()[8 - length..].to_vec()
+}
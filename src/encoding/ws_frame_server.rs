@@ -0,0 +1,273 @@
+use std::sync::{
+    Arc, Weak,
+    atomic::{AtomicU64, AtomicUsize, Ordering},
+};
+
+use futures_util::{SinkExt, StreamExt};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::broadcast::{self, error::RecvError},
+    task::JoinHandle,
+};
+use tokio_tungstenite::tungstenite::{Bytes, Message};
+
+use crate::{
+    capture_error::CaptureError,
+    devices::{Dimensions, Frame, camera::Output},
+    encoding::jpeg_capture::encode_jpeg,
+    i_capture::ICapture,
+};
+
+// frames kept for a client that is still sending the previous one, older ones are skipped
+const CLIENT_BACKLOG: usize = 2;
+
+/// # Ws Frame Server
+///
+/// Serves the frames of a capture as JPEG images over WebSocket, for previews in a browser (ws://host:port/).
+///
+/// Every frame is one binary message holding a complete JPEG image, which a page can show with a blob URL or createImageBitmap.
+/// Clients that cannot keep up skip frames instead of slowing down the capture, and frames are not encoded while no client is connected.
+pub struct WsFrameServer {
+    port: u16,
+    quality: u8,
+
+    // the encoded frames, every client has its own receiver
+    frames: broadcast::Sender<Bytes>,
+
+    clients: AtomicUsize,
+    frames_sent: AtomicU64,
+
+    // accepts the connections, aborted when the server is dropped
+    accept_task: JoinHandle<()>,
+}
+
+impl WsFrameServer {
+    /// # New
+    ///
+    /// Starts a WebSocket server on the port of every interface, jpeg_quality is from 1 (smallest) to 100 (best) and is clamped to that range.
+    ///
+    /// Must be called from within a Tokio runtime, the server runs until it is dropped.
+    pub fn new(port: u16, jpeg_quality: u8) -> Result<Arc<WsFrameServer>, CaptureError> {
+        let listener = std::net::TcpListener::bind(("0.0.0.0", port))?;
+        listener.set_nonblocking(true)?;
+        let listener = TcpListener::from_std(listener)?;
+
+        let port = listener.local_addr()?.port();
+        let (frames, _) = broadcast::channel(CLIENT_BACKLOG);
+
+        trace_event!(info, port, "websocket server listening");
+
+        Ok(Arc::new_cyclic(|server: &Weak<WsFrameServer>| {
+            WsFrameServer {
+                port,
+                quality: jpeg_quality.clamp(1, 100),
+                frames,
+                clients: AtomicUsize::new(0),
+                frames_sent: AtomicU64::new(0),
+                accept_task: tokio::spawn(accept(listener, server.clone())),
+            }
+        }))
+    }
+
+    /// # Port
+    ///
+    /// The port the server listens on, the bound port when the server was created with port 0.
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// # Quality
+    ///
+    /// The JPEG quality frames are encoded with.
+    pub fn quality(&self) -> u8 {
+        self.quality
+    }
+
+    /// # Client Count
+    ///
+    /// How many clients are connected.
+    pub fn client_count(&self) -> usize {
+        self.clients.load(Ordering::Relaxed)
+    }
+
+    /// # Frames Sent
+    ///
+    /// How many frames have been sent, counted once per client.
+    pub fn frames_sent(&self) -> u64 {
+        self.frames_sent.load(Ordering::Relaxed)
+    }
+
+    /// # Connect Source
+    ///
+    /// Starts the source and sends its frames to every connected client, do not start the source yourself.
+    ///
+    /// RGB32 frames are encoded as JPEG, MJPEG frames of cameras are sent as they are. Other formats return CaptureError::UnsupportedFormat.
+    ///
+    /// The returned handle completes once the source has stopped (with stop_capturing) or failed.
+    pub fn connect_source<S: ICapture<CaptureOutput = Frame> + 'static>(
+        self: &Arc<Self>,
+        source: Arc<S>,
+    ) -> Result<JoinHandle<Result<(), CaptureError>>, CaptureError> {
+        let format = source.get_output_format();
+
+        if !matches!(format, Output::RGB32 | Output::MJPEG) {
+            return Err(CaptureError::UnsupportedFormat(format!(
+                "{format:?} frames cannot be sent as JPEG, use RGB32 or MJPEG"
+            )));
+        }
+
+        let server = self.clone();
+
+        Ok(tokio::spawn(async move {
+            let capturing = source.clone();
+
+            let mut capture = tokio::spawn(async move {
+                capturing
+                    .start_capturing()
+                    .await
+                    .map_err(CaptureError::from)
+            });
+
+            let receiver = source.clone_receiver();
+            let mut receiver = receiver.lock().await;
+
+            loop {
+                tokio::select! {
+                    ended = &mut capture => {
+                        return ended.map_err(|e| CaptureError::Other(e.to_string()))?;
+                    }
+                    Some(frame) = receiver.recv() => {
+                        //the frame is still taken so the capture never waits on a full channel
+                        if server.frames.receiver_count() == 0 {
+                            continue;
+                        }
+
+                        let dimensions = source.get_dimensions().map_err(CaptureError::from);
+
+                        let published = match dimensions {
+                            Ok(dimensions) => server.publish(frame, dimensions, format).await,
+                            Err(e) => Err(e),
+                        };
+
+                        if let Err(e) = published {
+                            trace_event!(error, error = %e, "websocket server stopped the capture");
+
+                            let _ = source.clone().stop_capturing().await;
+
+                            //the capture loop may be waiting on a full channel, frames are dropped until it has ended
+                            loop {
+                                tokio::select! {
+                                    _ = &mut capture => break,
+                                    Some(_) = receiver.recv() => {}
+                                }
+                            }
+
+                            return Err(e);
+                        }
+                    }
+                }
+            }
+        }))
+    }
+
+    // encodes the frame when needed and hands it to every client
+    async fn publish(
+        &self,
+        frame: Frame,
+        dimensions: Dimensions,
+        format: Output,
+    ) -> Result<(), CaptureError> {
+        let jpeg = match format {
+            Output::MJPEG => frame.data,
+            _ => {
+                let quality = self.quality;
+                let Dimensions { width, height } = dimensions;
+
+                tokio::task::spawn_blocking(move || {
+                    encode_jpeg(&frame.data, width, height, quality)
+                })
+                .await
+                .map_err(|e| CaptureError::Other(e.to_string()))?
+                .map_err(|e| CaptureError::Other(e.to_string()))?
+            }
+        };
+
+        //fails only when every client disconnected meanwhile
+        let _ = self.frames.send(Bytes::from(jpeg));
+
+        Ok(())
+    }
+}
+
+impl Drop for WsFrameServer {
+    fn drop(&mut self) {
+        self.accept_task.abort();
+    }
+}
+
+// accepts connections until the server is dropped
+async fn accept(listener: TcpListener, server: Weak<WsFrameServer>) {
+    loop {
+        let (stream, _peer) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(_e) => {
+                trace_event!(warn, error = %_e, "could not accept a websocket connection");
+                continue;
+            }
+        };
+
+        trace_event!(debug, peer = %_peer, "websocket client connected");
+
+        tokio::spawn(serve(stream, server.clone()));
+    }
+}
+
+// sends the frames to one client until it disconnects or the server is dropped
+async fn serve(stream: TcpStream, server: Weak<WsFrameServer>) {
+    let Ok(socket) = tokio_tungstenite::accept_async(stream).await else {
+        trace_event!(debug, "websocket handshake failed");
+        return;
+    };
+
+    let Some(mut frames) = server.upgrade().map(|server| {
+        server.clients.fetch_add(1, Ordering::Relaxed);
+        server.frames.subscribe()
+    }) else {
+        return;
+    };
+
+    let (mut write, mut read) = socket.split();
+
+    loop {
+        tokio::select! {
+            frame = frames.recv() => match frame {
+                Ok(frame) => {
+                    if write.send(Message::Binary(frame)).await.is_err() {
+                        break;
+                    }
+
+                    if let Some(server) = server.upgrade() {
+                        server.frames_sent.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+                //a slow client only gets the newest frames
+                Err(RecvError::Lagged(_)) => continue,
+                //the server was dropped
+                Err(RecvError::Closed) => break,
+            },
+            //pings are answered while reading, anything else from the client is ignored
+            message = read.next() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+
+    let _ = write.close().await;
+
+    if let Some(server) = server.upgrade() {
+        server.clients.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    trace_event!(debug, "websocket client disconnected");
+}
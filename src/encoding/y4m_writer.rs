@@ -0,0 +1,197 @@
+use std::path::Path;
+
+use tokio::{fs::File, io::AsyncWriteExt};
+
+use crate::{
+    capture_error::CaptureError,
+    devices::{Dimensions, Frame, camera::Output},
+    encoding::yuv::{bgra_to_i420, nv12_to_i420, yuy2_to_i420},
+    i_capture::ICapture,
+};
+
+// the frame rate written by from_capture, captures do not report one
+const DEFAULT_FPS: u32 = 30;
+
+/// # Y4M Writer
+///
+/// Writes uncompressed 4:2:0 video to a YUV4MPEG2 (.y4m) file, for lossless recordings or piping to external encoders such as FFmpeg.
+///
+/// Every frame is written with the frame rate of the header, frames should be written at a steady rate (for example from a monitor in constant fps mode).
+///
+/// The file is complete after every frame, dropping the writer finishes the last write in the background, use finish to know it succeeded.
+pub struct Y4MWriter {
+    file: File,
+
+    width: u32,
+    height: u32,
+    fps: u32,
+
+    // the format of the capture frames for write_capture_frame, None when made with new
+    input: Option<Output>,
+
+    frames_written: u64,
+}
+
+impl Y4MWriter {
+    /// # New
+    ///
+    /// Creates (or truncates) the file and writes the header, frames are then written as I420 with write_frame.
+    pub fn new(path: &Path, width: u32, height: u32, fps: u32) -> Result<Self, CaptureError> {
+        if width == 0 || height == 0 || fps == 0 {
+            return Err(CaptureError::Other(
+                "the width, height and frame rate of a y4m file must not be 0".into(),
+            ));
+        }
+
+        //the header is written synchronously so a writer that never gets a frame is still a valid file
+        let mut file = std::fs::File::create(path)?;
+
+        std::io::Write::write_all(
+            &mut file,
+            format!("YUV4MPEG2 W{width} H{height} F{fps}:1 Ip A1:1 C420jpeg XCOLORRANGE=LIMITED\n")
+                .as_bytes(),
+        )?;
+
+        Ok(Self {
+            file: File::from_std(file),
+            width,
+            height,
+            fps,
+            input: None,
+            frames_written: 0,
+        })
+    }
+
+    /// # From Capture
+    ///
+    /// Creates a writer with the size of the capture at 30 fps, frames of the capture are converted by write_capture_frame.
+    ///
+    /// Returns CaptureError::UnsupportedFormat unless the capture delivers RGB32, NV12 or YUY2 frames.
+    pub fn from_capture<S: ICapture<CaptureOutput = Frame>>(
+        source: &S,
+        path: &Path,
+    ) -> Result<Self, CaptureError> {
+        Self::from_capture_with_fps(source, path, DEFAULT_FPS)
+    }
+
+    /// # From Capture With Fps
+    ///
+    /// Same as from_capture, with the frame rate written to the header.
+    pub fn from_capture_with_fps<S: ICapture<CaptureOutput = Frame>>(
+        source: &S,
+        path: &Path,
+        fps: u32,
+    ) -> Result<Self, CaptureError> {
+        let input = source.get_output_format();
+
+        if !matches!(input, Output::RGB32 | Output::NV12 | Output::YUY2) {
+            return Err(CaptureError::UnsupportedFormat(format!(
+                "y4m files can only be written from RGB32, NV12 or YUY2 frames, the capture delivers {input:?}"
+            )));
+        }
+
+        let Dimensions { width, height } = source.get_dimensions().map_err(CaptureError::from)?;
+
+        let mut writer = Self::new(path, width, height, fps)?;
+        writer.input = Some(input);
+
+        Ok(writer)
+    }
+
+    /// # Dimensions
+    ///
+    /// The size of every frame of the file.
+    pub fn dimensions(&self) -> Dimensions {
+        Dimensions {
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    /// # Fps
+    ///
+    /// The frame rate written to the header.
+    pub fn fps(&self) -> u32 {
+        self.fps
+    }
+
+    /// # Frames Written
+    ///
+    /// How many frames have been written to the file.
+    pub fn frames_written(&self) -> u64 {
+        self.frames_written
+    }
+
+    /// # Write Frame
+    ///
+    /// Writes a tightly packed I420 frame, the Y plane followed by the U and V planes (with a width and height of half the frame, rounded up).
+    pub async fn write_frame(&mut self, yuv: &[u8]) -> Result<(), CaptureError> {
+        let width = self.width as usize;
+        let height = self.height as usize;
+        let expected = width * height + width.div_ceil(2) * height.div_ceil(2) * 2;
+
+        if yuv.len() != expected {
+            return Err(CaptureError::Other(format!(
+                "an I420 frame of {width}x{height} is {expected} bytes, got {}",
+                yuv.len()
+            )));
+        }
+
+        //a single write so a frame is never left half written by a dropped writer
+        let mut buffer = Vec::with_capacity(yuv.len() + 6);
+        buffer.extend_from_slice(b"FRAME\n");
+        buffer.extend_from_slice(yuv);
+
+        self.file.write_all(&buffer).await?;
+        self.frames_written += 1;
+
+        Ok(())
+    }
+
+    /// # Write Capture Frame
+    ///
+    /// Converts a frame of the capture given to from_capture to I420 and writes it.
+    pub async fn write_capture_frame(&mut self, frame: &Frame) -> Result<(), CaptureError> {
+        let width = self.width as usize;
+        let height = self.height as usize;
+        let chroma_size = width.div_ceil(2) * height.div_ceil(2) * 2;
+
+        let Some(input) = self.input else {
+            return Err(CaptureError::Other(
+                "write_capture_frame needs a writer made with from_capture".into(),
+            ));
+        };
+
+        let required = match input {
+            Output::NV12 => width * height + chroma_size,
+            Output::YUY2 => width.div_ceil(2) * 4 * height,
+            _ => width * 4 * height,
+        };
+
+        //the capture may have changed its size since the writer was made
+        if frame.len() < required {
+            return Err(CaptureError::Other(format!(
+                "the frame is {} bytes, a {input:?} frame of {width}x{height} needs {required}",
+                frame.len()
+            )));
+        }
+
+        let yuv = match input {
+            Output::NV12 => nv12_to_i420(&frame.data, width, height),
+            Output::YUY2 => yuy2_to_i420(&frame.data, width, height),
+            _ => bgra_to_i420(&frame.data, height, width, height),
+        };
+
+        self.write_frame(&yuv).await
+    }
+
+    /// # Finish
+    ///
+    /// Waits for every frame to be written to the file.
+    pub async fn finish(mut self) -> Result<(), CaptureError> {
+        self.file.flush().await?;
+        self.file.sync_all().await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,184 @@
+// conversions from the capture formats to 4:2:0 YUV with BT.601 limited range, used by the encoders and writers,
+// and back from the YUV outputs to BGRA.
+//
+// the color math is the one of colorspace, this only lays the planes out. odd sizes are allowed, the last chroma column
+// and row then cover a single pixel.
+
+use crate::colorspace::{self, ColorMatrix};
+
+// converts a (possibly padded) BGRA frame to NV12 (a Y plane followed by an interleaved UV plane), cropped to width x height.
+//
+// the stride of the source is taken from the buffer.
+pub(crate) fn bgra_to_nv12(
+    data: &[u8],
+    source_height: usize,
+    width: usize,
+    height: usize,
+) -> Vec<u8> {
+    bgra_to_yuv420(data, source_height, width, height, true)
+}
+
+// converts a (possibly padded) BGRA frame to I420 (a Y, U and V plane), cropped to width x height.
+pub(crate) fn bgra_to_i420(
+    data: &[u8],
+    source_height: usize,
+    width: usize,
+    height: usize,
+) -> Vec<u8> {
+    bgra_to_yuv420(data, source_height, width, height, false)
+}
+
+// separates the interleaved UV plane of a tightly packed NV12 frame into a U and a V plane
+pub(crate) fn nv12_to_i420(data: &[u8], width: usize, height: usize) -> Vec<u8> {
+    let luma_size = width * height;
+    let chroma_size = width.div_ceil(2) * height.div_ceil(2);
+
+    let mut i420 = Vec::with_capacity(luma_size + chroma_size * 2);
+    i420.extend_from_slice(&data[..luma_size]);
+
+    let chroma = &data[luma_size..luma_size + chroma_size * 2];
+    i420.extend(chroma.iter().step_by(2));
+    i420.extend(chroma.iter().skip(1).step_by(2));
+
+    i420
+}
+
+// converts a tightly packed YUY2 (4:2:2) frame to I420, averaging the chroma of every two rows
+pub(crate) fn yuy2_to_i420(data: &[u8], width: usize, height: usize) -> Vec<u8> {
+    let chroma_width = width.div_ceil(2);
+    let chroma_height = height.div_ceil(2);
+    let row_bytes = chroma_width * 4;
+
+    let mut i420 = vec![0u8; width * height + chroma_width * chroma_height * 2];
+    let (luma, chroma) = i420.split_at_mut(width * height);
+    let (u, v) = chroma.split_at_mut(chroma_width * chroma_height);
+
+    for y in 0..height {
+        let row = &data[y * row_bytes..(y + 1) * row_bytes];
+
+        for x in 0..width {
+            luma[y * width + x] = row[(x / 2) * 4 + (x % 2) * 2];
+        }
+    }
+
+    for y in 0..chroma_height {
+        let top = &data[y * 2 * row_bytes..];
+        let bottom = &data[(y * 2 + 1).min(height - 1) * row_bytes..];
+
+        for x in 0..chroma_width {
+            let offset = x * 4;
+            u[y * chroma_width + x] =
+                ((top[offset + 1] as u16 + bottom[offset + 1] as u16) / 2) as u8;
+            v[y * chroma_width + x] =
+                ((top[offset + 3] as u16 + bottom[offset + 3] as u16) / 2) as u8;
+        }
+    }
+
+    i420
+}
+
+// converts a tightly packed NV12 frame to tightly packed BGRA, every 2x2 block shares the chroma of the block
+pub(crate) fn nv12_to_bgra(data: &[u8], width: usize, height: usize) -> Vec<u8> {
+    let chroma_width = width.div_ceil(2);
+    let (luma, chroma) = data.split_at(width * height);
+
+    let mut bgra = vec![0u8; width * height * 4];
+
+    if width == 0 {
+        return bgra;
+    }
+
+    let (mut u, mut v) = (vec![0u8; chroma_width], vec![0u8; chroma_width]);
+
+    for (row, (y_row, bgra_row)) in luma
+        .chunks_exact(width)
+        .zip(bgra.chunks_exact_mut(width * 4))
+        .enumerate()
+    {
+        //the interleaved samples are split once for the two rows sharing them
+        if row % 2 == 0 {
+            let uv = &chroma[(row / 2) * chroma_width * 2..][..chroma_width * 2];
+
+            for ((u, v), pair) in u.iter_mut().zip(&mut v).zip(uv.chunks_exact(2)) {
+                *u = pair[0];
+                *v = pair[1];
+            }
+        }
+
+        colorspace::yuv_row_to_bgra(y_row, &u, &v, bgra_row, ColorMatrix::Bt601);
+    }
+
+    bgra
+}
+
+// converts a tightly packed YUY2 frame to tightly packed BGRA, every two pixels of a row share their chroma
+pub(crate) fn yuy2_to_bgra(data: &[u8], width: usize, height: usize) -> Vec<u8> {
+    let chroma_width = width.div_ceil(2);
+    let row_bytes = chroma_width * 4;
+
+    let mut bgra = vec![0u8; width * height * 4];
+
+    if width == 0 {
+        return bgra;
+    }
+
+    let mut y = vec![0u8; width];
+    let (mut u, mut v) = (vec![0u8; chroma_width], vec![0u8; chroma_width]);
+
+    for (row, bgra_row) in data[..row_bytes * height]
+        .chunks_exact(row_bytes)
+        .zip(bgra.chunks_exact_mut(width * 4))
+    {
+        for (x, luma) in y.iter_mut().enumerate() {
+            *luma = row[(x / 2) * 4 + (x % 2) * 2];
+        }
+
+        for ((u, v), pair) in u.iter_mut().zip(&mut v).zip(row.chunks_exact(4)) {
+            *u = pair[1];
+            *v = pair[3];
+        }
+
+        colorspace::yuv_row_to_bgra(&y, &u, &v, bgra_row, ColorMatrix::Bt601);
+    }
+
+    bgra
+}
+
+fn bgra_to_yuv420(
+    data: &[u8],
+    source_height: usize,
+    width: usize,
+    height: usize,
+    interleaved: bool,
+) -> Vec<u8> {
+    let stride = data.len() / source_height.max(1);
+    let chroma_size = width.div_ceil(2) * height.div_ceil(2);
+
+    let mut yuv = vec![0u8; width * height + chroma_size * 2];
+
+    if width == 0 || height == 0 {
+        return yuv;
+    }
+
+    let (luma, chroma) = yuv.split_at_mut(width * height);
+
+    colorspace::bgra_to_yuv420_rows(
+        data,
+        stride,
+        width,
+        height,
+        luma,
+        ColorMatrix::Bt601,
+        |index, u, v| {
+            if interleaved {
+                chroma[index * 2] = u;
+                chroma[index * 2 + 1] = v;
+            } else {
+                chroma[index] = u;
+                chroma[chroma_size + index] = v;
+            }
+        },
+    );
+
+    yuv
+}
@@ -0,0 +1,138 @@
+use std::{
+    any::Any,
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use tokio::{
+    sync::{self, mpsc::Receiver},
+    task::JoinHandle,
+};
+
+use crate::devices::{AudioFrame, Frame, SharedFrame};
+
+/// # Frame Callback
+///
+/// Holds the callback registered with ICapture::on_frame, every capture keeps one of these.
+///
+/// The callback runs on a task of its own that reads the receiver of the capture, dropping this removes the callback.
+pub struct FrameCallback<T> {
+    // the task calling the registered callback, None when no callback is registered
+    task: Mutex<Option<JoinHandle<()>>>,
+
+    _output: PhantomData<fn(T)>,
+}
+
+impl<T> Default for FrameCallback<T> {
+    fn default() -> Self {
+        Self {
+            task: Mutex::new(None),
+            _output: PhantomData,
+        }
+    }
+}
+
+impl<T> FrameCallback<T> {
+    /// # New
+    ///
+    /// A slot without a callback.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// # Is Registered
+    ///
+    /// If a callback is registered.
+    pub fn is_registered(&self) -> bool {
+        self.task
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|task| !task.is_finished())
+    }
+
+    // stops the task of the callback, the receiver is free again once it ended
+    pub(crate) fn remove(&self) {
+        if let Some(task) = self.task.lock().unwrap().take() {
+            task.abort();
+        }
+    }
+}
+
+impl<T: Send + 'static> FrameCallback<T> {
+    // replaces the callback, the new task waits for the receiver until the old task let go of it
+    pub(crate) fn set<F: Fn(T) + Send + 'static>(
+        &self,
+        receiver: Arc<sync::Mutex<Receiver<T>>>,
+        callback: F,
+    ) {
+        let task = tokio::spawn(async move {
+            let mut receiver = receiver.lock().await;
+            let mut last_timestamp: Option<Duration> = None;
+            let mut last_arrival: Option<Instant> = None;
+
+            while let Some(output) = receiver.recv().await {
+                let arrival = Instant::now();
+
+                //frames queued up behind a slow callback arrive at once, so the period is taken from their timestamps,
+                //the time between two arrivals is only used for outputs without one
+                let period = match timestamp_of(&output) {
+                    Some(timestamp) => {
+                        let period = last_timestamp
+                            .and_then(|last| timestamp.checked_sub(last))
+                            .filter(|period| !period.is_zero());
+
+                        last_timestamp = Some(timestamp);
+                        period
+                    }
+                    None => last_arrival.map(|last| arrival - last),
+                };
+
+                last_arrival = Some(arrival);
+
+                callback(output);
+
+                let _took = arrival.elapsed();
+
+                if let Some(_period) = period
+                    && _took > _period
+                {
+                    trace_event!(
+                        warn,
+                        took = ?_took,
+                        period = ?_period,
+                        "the frame callback took longer than a frame"
+                    );
+                }
+            }
+        });
+
+        if let Some(previous) = self.task.lock().unwrap().replace(task) {
+            previous.abort();
+        }
+    }
+}
+
+// the capture timestamp of the frame types of this crate
+fn timestamp_of<T: 'static>(output: &T) -> Option<Duration> {
+    let output: &dyn Any = output;
+
+    if let Some(frame) = output.downcast_ref::<Frame>() {
+        return Some(frame.timestamp);
+    }
+
+    if let Some(frame) = output.downcast_ref::<SharedFrame>() {
+        return Some(frame.timestamp);
+    }
+
+    output
+        .downcast_ref::<AudioFrame>()
+        .map(|frame| frame.timestamp)
+}
+
+impl<T> Drop for FrameCallback<T> {
+    fn drop(&mut self) {
+        self.remove();
+    }
+}
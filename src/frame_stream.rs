@@ -0,0 +1,63 @@
+use std::{
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, ready},
+};
+
+use futures_core::Stream;
+use tokio::sync::{Mutex, OwnedMutexGuard, mpsc::Receiver};
+
+use crate::devices::Frame;
+
+// waiting for the lock of the receiver, then reading it
+enum State<T> {
+    Locking(Pin<Box<dyn Future<Output = OwnedMutexGuard<Receiver<T>>> + Send>>),
+    Receiving(OwnedMutexGuard<Receiver<T>>),
+}
+
+/// # Frame Stream
+///
+/// The receiver of a capture as a Stream, returned by ICapture::frame_stream.
+///
+/// The stream locks the receiver on the first poll and keeps it locked until it is dropped, so meanwhile recv_frame and the receiver get no frames.
+/// It ends once the capture stops sending data (the channel is closed), the frame timeout does not apply, tokio_stream::StreamExt::timeout can be used instead.
+///
+/// ```rs
+/// use tokio_stream::StreamExt;
+///
+/// let mut frames = monitor.frame_stream().filter(|frame| !frame.is_duplicate).take(10);
+///
+/// while let Some(frame) = frames.next().await {
+///     println!("{} bytes at {:?}", frame.data.len(), frame.timestamp);
+/// }
+/// ```
+pub struct FrameStream<T = Frame> {
+    state: State<T>,
+}
+
+impl<T: Send + 'static> FrameStream<T> {
+    /// # New
+    ///
+    /// A stream over the given receiver, the lock is taken on the first poll.
+    pub fn new(receiver: Arc<Mutex<Receiver<T>>>) -> Self {
+        Self {
+            state: State::Locking(Box::pin(receiver.lock_owned())),
+        }
+    }
+}
+
+impl<T> Stream for FrameStream<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        loop {
+            match &mut self.state {
+                State::Locking(lock) => {
+                    let guard = ready!(lock.as_mut().poll(cx));
+                    self.state = State::Receiving(guard);
+                }
+                State::Receiving(receiver) => return receiver.poll_recv(cx),
+            }
+        }
+    }
+}
@@ -1,8 +1,15 @@
-use std::{pin::Pin, sync::Arc};
+use std::{pin::Pin, sync::Arc, time::Duration};
 
-use tokio::sync::{Mutex, mpsc::Receiver};
+use tokio::sync::{Mutex, mpsc::{self, Receiver}};
 
-use crate::devices::Dimensions;
+use crate::{
+    capture_error::CaptureError,
+    capture_session::{self, CaptureSession},
+    capture_state::{CaptureState, StateCell},
+    devices::{Dimensions, camera::Output},
+    frame_callback::FrameCallback,
+    frame_stream::FrameStream,
+};
 
 /// # I Capture
 /// 
@@ -10,7 +17,7 @@ use crate::devices::Dimensions;
 
 pub trait ICapture: Send + Sync {
 
-    type CaptureOutput;
+    type CaptureOutput: Send;
     
     /// # Get Dimensions
     /// 
@@ -19,6 +26,13 @@ pub trait ICapture: Send + Sync {
     /// This could be used to capture the size of a monitor for example (1920x1080)
     fn get_dimensions(&self) -> Result<Dimensions, Box<dyn std::error::Error>>;
 
+    /// # Get Output Format
+    /// 
+    /// The format of the data actually sent to the receiver.
+    /// 
+    /// This may differ from what was requested, for example when None is passed to activate_device or a device interprets a format differently.
+    fn get_output_format(&self) -> Output;
+
     /// # Stop Capturing
     /// 
     /// Indicates that the device should stop sending some sort of data
@@ -27,10 +41,181 @@ pub trait ICapture: Send + Sync {
     /// # Start Capturing
     /// 
     /// Indicates the device should start sending some sort of data
+    /// 
+    /// Returns CaptureError::AlreadyCapturing while the capture runs, a capture that is still stopping is waited for first,
+    /// so stop_capturing followed by start_capturing never runs two capture loops at once.
     fn start_capturing(self: Arc<Self>) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>> + Send>>;
 
+    /// # State Cell
+    /// 
+    /// The cell the state of the capture is kept in, implementors keep one StateCell per capture.
+    fn state_cell(&self) -> &StateCell;
+
+    /// # State
+    /// 
+    /// If the capture is stopped, capturing or stopping (stop_capturing was called and the capture loop has not ended yet).
+    /// 
+    /// start_capturing returns CaptureError::AlreadyCapturing while the state is Capturing and waits for the loop to end while it is Stopping.
+    fn state(&self) -> CaptureState {
+        self.state_cell().get()
+    }
+
+    /// # Is Capturing
+    /// 
+    /// If the capture loop runs and stop_capturing was not called yet.
+    fn is_capturing(&self) -> bool {
+        self.state() == CaptureState::Capturing
+    }
+
+    /// # Start Session
+    /// 
+    /// Starts capturing in a new task and returns a CaptureSession that stops the capture when it is dropped.
+    /// 
+    /// This must be called from within a Tokio runtime, stop_capturing can still be used for manual control.
+    fn start_session(self: Arc<Self>) -> CaptureSession<Self>
+    where
+        Self: Sized + 'static,
+    {
+        CaptureSession::start(self)
+    }
+
+    /// # Capture N Frames
+    /// 
+    /// Starts capturing, receives n frames, stops capturing and returns the frames, for analysis or tests that need a small number of frames.
+    /// 
+    /// Every frame is awaited with the frame timeout (see set_frame_timeout), set one so a slow or stalled source returns CaptureError::Timeout instead of hanging.
+    /// An error of the capture loop is returned as is, a capture that ends before n frames returns CaptureError::Other.
+    /// 
+    /// The capture must be stopped and no callback may be registered with on_frame, frames still in the receiver are returned first.
+    /// This must be called from within a Tokio runtime.
+    fn capture_n_frames(
+        self: Arc<Self>,
+        n: usize,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<Self::CaptureOutput>, CaptureError>> + Send>>
+    where
+        Self: Sized + 'static,
+    {
+        Box::pin(capture_session::capture_n_frames(self, n))
+    }
+
     /// # Get Receiver
     /// 
     /// Get the receiver reference associated with sending data.
     fn clone_receiver(&self) -> Arc<Mutex<Receiver<Self::CaptureOutput>>>;
+
+    /// # Take Receiver
+    /// 
+    /// Moves the receiver out of the capture, for a single consumer that reads every frame itself without locking the shared receiver for each one.
+    /// 
+    /// The shared receiver of clone_receiver is left closed, so recv_frame, frame_stream and on_frame get no frames afterwards. A taken receiver
+    /// cannot be put back, the channel is made again by set_channel_capacity (where a capture has it), which sends the frames to clone_receiver again.
+    /// With Backpressure::DropOldest the capture cannot reach the taken receiver to drop its oldest frame, a full channel drops the new frame instead.
+    /// 
+    /// Returns CaptureError::Other when the receiver is locked (a callback of on_frame, a FrameStream or another consumer holds it) or was taken before.
+    /// 
+    /// ```rs
+    /// let mut receiver = camera.take_receiver()?;
+    /// 
+    /// while let Some(frame) = receiver.recv().await {
+    ///     //...
+    /// }
+    /// ```
+    fn take_receiver(&self) -> Result<Receiver<Self::CaptureOutput>, CaptureError> {
+        let receiver = self.clone_receiver();
+
+        let Ok(mut guard) = receiver.try_lock() else {
+            return Err(CaptureError::Other("the receiver cannot be taken while it is locked".into()));
+        };
+
+        //only a taken receiver is closed, the capture keeps its sender as long as it lives
+        if guard.is_closed() {
+            return Err(CaptureError::Other("the receiver was already taken".into()));
+        }
+
+        let (_, closed) = mpsc::channel(1);
+
+        Ok(std::mem::replace(&mut *guard, closed))
+    }
+
+    /// # Set Frame Timeout
+    /// 
+    /// The max amount of time recv_frame waits for a frame before returning CaptureError::Timeout.
+    /// 
+    /// This can be used to detect a stalled device. By default there is no timeout.
+    fn set_frame_timeout(&self, duration: Duration);
+
+    /// # Clear Frame Timeout
+    /// 
+    /// Removes the frame timeout, recv_frame waits until a frame arrives.
+    fn clear_frame_timeout(&self);
+
+    /// # Get Frame Timeout
+    /// 
+    /// The current frame timeout, None if recv_frame waits forever.
+    fn get_frame_timeout(&self) -> Option<Duration>;
+
+    /// # Frame Callback
+    /// 
+    /// The slot the callback of on_frame is kept in, implementors keep one FrameCallback per capture.
+    fn frame_callback(&self) -> &FrameCallback<Self::CaptureOutput>;
+
+    /// # On Frame
+    /// 
+    /// Registers a callback that is called with every captured frame, replacing the callback registered before.
+    /// 
+    /// The callback runs on an internal task that reads the receiver, so while it is registered recv_frame and the receiver get no frames.
+    /// A callback that takes longer than a frame period (the time between the timestamps of two frames) holds up the capture once the channel is full and is reported by a warning (with the "tracing" feature),
+    /// hand slow work to another task or thread.
+    /// 
+    /// This must be called from within a Tokio runtime.
+    fn on_frame<F: Fn(Self::CaptureOutput) + Send + 'static>(&self, callback: F)
+    where
+        Self: Sized,
+        Self::CaptureOutput: 'static,
+    {
+        self.frame_callback().set(self.clone_receiver(), callback);
+    }
+
+    /// # Remove On Frame
+    /// 
+    /// Unregisters the callback of on_frame, frames are sent to the receiver again.
+    fn remove_on_frame(&self) {
+        self.frame_callback().remove();
+    }
+
+    /// # Frame Stream
+    /// 
+    /// The receiver as a Stream of frames, for next, map, filter and the other combinators of tokio_stream::StreamExt or futures::StreamExt.
+    /// 
+    /// The stream keeps the receiver locked until it is dropped and ends when the capture stops sending data.
+    fn frame_stream(&self) -> FrameStream<Self::CaptureOutput>
+    where
+        Self::CaptureOutput: 'static,
+    {
+        FrameStream::new(self.clone_receiver())
+    }
+
+    /// # Receive Frame
+    /// 
+    /// Locks the receiver and waits for the next frame, respecting the frame timeout.
+    /// 
+    /// Returns None once the device stops sending data (the channel is closed).
+    fn recv_frame(
+        &self,
+    ) -> Pin<Box<dyn Future<Output = Result<Option<Self::CaptureOutput>, CaptureError>> + Send + '_>>
+    {
+        let receiver = self.clone_receiver();
+        let timeout = self.get_frame_timeout();
+
+        Box::pin(async move {
+            let mut guard = receiver.lock().await;
+
+            match timeout {
+                Some(duration) => tokio::time::timeout(duration, guard.recv())
+                    .await
+                    .map_err(|_| CaptureError::Timeout(duration)),
+                None => Ok(guard.recv().await),
+            }
+        })
+    }
 }
@@ -0,0 +1,41 @@
+use std::{pin::Pin, sync::Arc};
+
+use tokio::sync::{Mutex, mpsc::Receiver};
+use windows::Win32::Graphics::Direct3D11::ID3D11Device;
+
+use crate::devices::{Dimensions, GpuFrame};
+
+/// # I GPU Capture
+/// 
+/// Trait for captures that can deliver frames as D3D11 textures instead of copying them to system memory.
+/// 
+/// This runs in place of ICapture::start_capturing, only one of the two can be capturing at a time.
+pub trait IGpuCapture: Send + Sync {
+
+    /// # Get Texture Dimensions
+    /// 
+    /// The size of the delivered textures.
+    fn get_texture_dimensions(&self) -> Dimensions;
+
+    /// # GPU Device
+    /// 
+    /// The device the textures are created on, textures can be used directly with this device.
+    /// 
+    /// Other devices must open the texture with GpuFrame::shared_handle.
+    fn gpu_device(&self) -> ID3D11Device;
+
+    /// # Stop GPU Capturing
+    /// 
+    /// Indicates that the device should stop sending textures
+    fn stop_gpu_capturing(self: Arc<Self>) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>> + Send>>;
+
+    /// # Start GPU Capturing
+    /// 
+    /// Indicates the device should start sending textures to the GPU receiver
+    fn start_gpu_capturing(self: Arc<Self>) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>> + Send>>;
+
+    /// # Clone GPU Receiver
+    /// 
+    /// Get the receiver reference associated with sending textures.
+    fn clone_gpu_receiver(&self) -> Arc<Mutex<Receiver<GpuFrame>>>;
+}
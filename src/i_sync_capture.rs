@@ -0,0 +1,112 @@
+use std::{
+    sync::{Mutex, Weak, mpsc},
+    time::Duration,
+};
+
+use crate::{
+    blocking::BlockingCapture,
+    capture_error::CaptureError,
+    devices::{Dimensions, Frame},
+    i_capture::ICapture,
+};
+
+/// # I Sync Capture
+/// 
+/// Trait for capturing from threads without an async runtime, such as game engines, GUI frameworks and plugins.
+/// 
+/// Frames are delivered through a std channel read with recv_frame, the capture runs on a thread of its own (see BlockingCapture).
+/// While capturing this way the frames are not sent to the async receiver of the capture.
+/// 
+/// Monitor and Camera implement both traits, call the methods as ISyncCapture::start_capturing(&*capture) when ICapture is in scope as well.
+pub trait ISyncCapture: Send + Sync {
+
+    /// # Start Capturing
+    /// 
+    /// Starts capturing on a background thread and returns right away.
+    /// 
+    /// The capture keeps itself alive until stop_capturing is called.
+    fn start_capturing(&self) -> Result<(), CaptureError>;
+
+    /// # Stop Capturing
+    /// 
+    /// Stops the capture and waits for its thread to end, returning the error that ended the capture if it failed on its own.
+    /// 
+    /// The wait is bounded, a capture that does not stop in time is aborted and CaptureError::Timeout is returned.
+    fn stop_capturing(&self) -> Result<(), CaptureError>;
+
+    /// # Recv Frame
+    /// 
+    /// Blocks until the next frame, or up to the timeout when one is given (CaptureError::Timeout when no frame arrived in time).
+    /// 
+    /// None once the capture has ended or when it was not started.
+    fn recv_frame(&self, timeout: Option<Duration>) -> Result<Option<Vec<u8>>, CaptureError>;
+
+    /// # Get Dimensions
+    /// 
+    /// The size of the delivered frames.
+    fn get_dimensions(&self) -> Result<Dimensions, CaptureError>;
+}
+
+// the running synchronous capture of a monitor or camera.
+//
+// the frames are kept apart from the capture so recv can wait on them without blocking stop.
+#[derive(Default)]
+pub(crate) struct SyncChannel {
+    capture: Mutex<Option<BlockingCapture>>,
+    frames: Mutex<Option<mpsc::Receiver<Frame>>>,
+}
+
+impl SyncChannel {
+    pub(crate) fn start<S: ICapture<CaptureOutput = Frame> + 'static>(
+        &self,
+        source: &Weak<S>,
+    ) -> Result<(), CaptureError> {
+        let mut capture = self.capture.lock().unwrap();
+
+        if capture.is_some() {
+            return Err(CaptureError::AlreadyCapturing);
+        }
+
+        let source = source
+            .upgrade()
+            .ok_or_else(|| CaptureError::Other("the capture was dropped".into()))?;
+
+        let mut started = BlockingCapture::start(source)?;
+
+        *self.frames.lock().unwrap() = started.take_frames();
+        *capture = Some(started);
+
+        Ok(())
+    }
+
+    pub(crate) fn stop(&self) -> Result<(), CaptureError> {
+        let Some(mut capture) = self.capture.lock().unwrap().take() else {
+            return Err(CaptureError::Other("already stopped.".into()));
+        };
+
+        capture.signal_stop();
+
+        //a pending recv ends once the capture has stopped, dropping the frames then unblocks a pending send.
+        //the loop is aborted when it does not stop in time, so neither this nor the join below waits without a bound
+        *self.frames.lock().unwrap() = None;
+
+        capture.stop()
+    }
+
+    pub(crate) fn recv(&self, timeout: Option<Duration>) -> Result<Option<Vec<u8>>, CaptureError> {
+        let frames = self.frames.lock().unwrap();
+
+        let Some(frames) = frames.as_ref() else {
+            return Ok(None);
+        };
+
+        match timeout {
+            Some(timeout) => match frames.recv_timeout(timeout) {
+                Ok(frame) => Ok(Some(frame.data)),
+                Err(mpsc::RecvTimeoutError::Timeout) => Err(CaptureError::Timeout(timeout)),
+                Err(mpsc::RecvTimeoutError::Disconnected) => Ok(None),
+            },
+            None => Ok(frames.recv().ok().map(|frame| frame.data)),
+        }
+    }
+}
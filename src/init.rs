@@ -0,0 +1,81 @@
+use std::{
+    cell::RefCell,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use windows::Win32::{
+    Foundation::E_FAIL,
+    Media::MediaFoundation::{MF_VERSION, MFSTARTUP_LITE, MFShutdown, MFStartup},
+};
+
+use crate::{
+    capture_error::CaptureError,
+    com::{Apartment, ComGuard, current_apartment, require_com},
+};
+
+// set by assume_com_initialized, the crate then leaves the apartments of the threads it is called from alone
+static ASSUME_COM_INITIALIZED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    // COM initialized by the crate on a thread of the app, uninitialized when the thread exits
+    static THREAD_COM: RefCell<Option<ComGuard>> = const { RefCell::new(None) };
+}
+
+/// # Assume Com Initialized
+///
+/// Stops the crate from initializing COM on the threads of the app, for apps that manage their COM apartments themselves
+/// (for example a UI thread that must be a single threaded apartment, initialized after the crate was first used).
+///
+/// By default Cameras::new joins a thread without COM to the multithreaded apartment until the thread exits. After this call it returns
+/// CO_E_NOTINITIALIZED on such a thread instead. Threads the crate starts itself (encoders, the device watcher) always initialize COM.
+pub fn assume_com_initialized() {
+    ASSUME_COM_INITIALIZED.store(true, Ordering::Relaxed);
+}
+
+/// # Is Com Assumed Initialized
+///
+/// If assume_com_initialized was called.
+pub fn is_com_assumed_initialized() -> bool {
+    ASSUME_COM_INITIALIZED.load(Ordering::Relaxed)
+}
+
+// joins a thread without COM to the multithreaded apartment until it exits, unless the app manages COM itself.
+//
+// threads that already are in an apartment (either one, or the implicit multithreaded apartment) are left as they are.
+pub(crate) fn ensure_com(what: &str) -> Result<(), windows::core::Error> {
+    if !is_com_assumed_initialized() && current_apartment() == Apartment::Uninitialized {
+        THREAD_COM.with(|com| -> Result<(), windows::core::Error> {
+            let mut com = com.borrow_mut();
+
+            if com.is_none() {
+                *com = Some(ComGuard::new().map_err(|e| match e {
+                    CaptureError::Windows(e) => e,
+                    e => windows::core::Error::new(E_FAIL, e.to_string()),
+                })?);
+
+                trace_event!(debug, "com initialized for the thread");
+            }
+
+            Ok(())
+        })?;
+    }
+
+    require_com(what)
+}
+
+// keeps media foundation started, media foundation counts the startups itself and shuts down with the last one
+pub(crate) struct MediaFoundation(());
+
+impl MediaFoundation {
+    pub(crate) fn start() -> Result<Self, windows::core::Error> {
+        unsafe { MFStartup(MF_VERSION, MFSTARTUP_LITE)? };
+
+        Ok(Self(()))
+    }
+}
+
+impl Drop for MediaFoundation {
+    fn drop(&mut self) {
+        let _ = unsafe { MFShutdown() };
+    }
+}
@@ -1,11 +1,42 @@
+#[macro_use]
+mod trace;
+
+pub mod analysis;
+pub mod blocking;
+pub mod capture_error;
+pub mod capture_event;
+pub mod capture_session;
+pub mod capture_state;
+pub mod clock;
+pub mod colorspace;
+pub mod com;
+pub mod compat;
+pub mod convert;
 pub mod devices;
+pub mod dyn_capture;
+pub mod encoding;
+pub mod frame_callback;
+pub mod frame_stream;
 pub mod i_capture;
+pub mod i_gpu_capture;
+pub mod i_sync_capture;
+pub mod init;
+pub mod map_capture;
+pub mod named_pipe;
+pub mod shared_memory;
+pub mod transform;
+
+pub use crate::clock::clock;
+
+#[cfg(feature = "png")]
+pub mod screenshot;
+
+#[cfg(feature = "png")]
+pub use crate::screenshot::capture_screenshot;
 
 #[cfg(test)]
 mod tests {
 
-    use windows::Win32::System::Com::{COINIT_MULTITHREADED, CoInitializeEx};
-
     use crate::{devices::{Cameras, Monitor, get_device_name}, i_capture::ICapture};
 
     use windows::Win32::{
@@ -46,7 +77,7 @@ mod tests {
                     let data = data.unwrap();
 
                     let mut had_data = false;
-                    for d in &data {
+                    for d in &data.data {
                         if *d != 0 {
                             had_data = true;
                             break;
@@ -68,6 +99,54 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_monitor_dpi_dimensions() {
+        unsafe {
+            let monitor = Monitor::from_monitor(0);
+
+            assert!(monitor.is_ok(), "{:?}", monitor.err());
+
+            let monitor = monitor.unwrap();
+
+            let physical = monitor.physical_size();
+            let logical = monitor.logical_size();
+            let scale = monitor.scale_factor();
+
+            assert!(scale >= 1.0, "Scale factor was below 100%: {scale}");
+
+            //on a scaled display the logical size scaled back up must land on the physical size (within rounding)
+            let scaled_width = (logical.width as f32 * scale).round() as i64;
+            let scaled_height = (logical.height as f32 * scale).round() as i64;
+
+            assert!((scaled_width - physical.width as i64).abs() <= 1);
+            assert!((scaled_height - physical.height as i64).abs() <= 1);
+
+            //get_dimensions and the frame buffer are always in physical pixels
+            let dimensions = monitor.get_dimensions().unwrap();
+
+            assert_eq!(dimensions.width, physical.width);
+            assert_eq!(dimensions.height, physical.height);
+
+            let monitor_clone = monitor.clone();
+            let recv = monitor.clone_receiver();
+            let min_len = (physical.width * physical.height * 4) as usize;
+
+            tokio::spawn(async move {
+                let data = recv.lock().await.recv().await;
+
+                assert!(data.is_some());
+                assert!(data.unwrap().data.len() >= min_len);
+
+                let stopped = monitor_clone.stop_capturing().await;
+                assert!(stopped.is_ok());
+            });
+
+            let captured = monitor.start_capturing().await;
+
+            assert!(captured.is_ok(), "{captured:?}");
+        }
+    }
+
     #[test]
     fn find_video_devices() -> () {
         unsafe {
@@ -126,9 +205,10 @@ mod tests {
             let video_devices = devices.unwrap();
 
             assert!(!video_devices.devices.is_empty());
+            assert_eq!(video_devices.infos().len(), video_devices.devices.len());
 
             for device in &video_devices.devices {
-                let name = get_device_name(*device);
+                let name = get_device_name(device);
 
                 assert!(name.is_ok());
 
@@ -136,21 +216,12 @@ mod tests {
 
                 println!("Name of device: '{name}'");
             }
-
-            video_devices.free_devices();
         }
     }
 
     #[test]
     fn test_activation() {
         unsafe {
-            let init = CoInitializeEx(None, COINIT_MULTITHREADED);
-
-            assert!(
-                init == windows::Win32::Foundation::S_OK,
-                "Co Initialize was not OK: {init}"
-            );
-
             let devices = Cameras::new();
 
             assert!(devices.is_ok());
@@ -159,7 +230,7 @@ mod tests {
 
             assert!(!devices.devices.is_empty());
 
-            let activated_device = devices.activate_device(devices.devices[0], None);
+            let activated_device = devices.activate_device(&devices.devices[0], None);
 
             assert!(activated_device.is_ok(), "{:?}", activated_device.err());
         }
@@ -168,13 +239,6 @@ mod tests {
     #[tokio::test]
     async fn capture_image() {
         unsafe {
-            let init = CoInitializeEx(None, COINIT_MULTITHREADED);
-
-            assert!(
-                init == windows::Win32::Foundation::S_OK,
-                "Co Initialize was not OK: {init}"
-            );
-
             let devices = Cameras::new();
 
             assert!(devices.is_ok());
@@ -183,7 +247,7 @@ mod tests {
 
             assert!(!devices.devices.is_empty());
 
-            let activated_device = devices.activate_device(devices.devices[0], None);
+            let activated_device = devices.activate_device(&devices.devices[0], None);
 
             assert!(activated_device.is_ok(), "{:?}", activated_device.err());
 
@@ -203,7 +267,7 @@ mod tests {
 
                     let data = data.unwrap();
 
-                    if data.len() > 0 {
+                    if !data.is_empty() {
                         let stopped = activated_device_clone.stop_capturing().await;
 
                         assert!(stopped.is_ok());
@@ -224,8 +288,1106 @@ mod tests {
             .await;
 
             assert!(spawned.is_ok(), "Failed to Join Task: {spawned:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn take_photo() {
+        unsafe {
+            let devices = Cameras::new().unwrap();
+
+            assert!(!devices.devices.is_empty());
+
+            let camera = devices.activate_device(&devices.devices[0], None).unwrap();
+
+            let photo = camera.take_photo(std::time::Duration::from_secs(5)).await;
+
+            assert!(photo.is_ok(), "{:?}", photo.err());
+
+            let photo = photo.unwrap();
+
+            assert!(!photo.frame.data.is_empty());
+            println!("{}x{} photo from the {:?}", photo.width, photo.height, photo.source);
+        }
+    }
+
+    #[tokio::test]
+    async fn audio_capture() {
+        use crate::devices::AudioDevices;
+
+        let devices = AudioDevices::new().unwrap();
+
+        if devices.is_empty() {
+            println!("No audio capture device found");
+            return;
+        }
+
+        let microphone = devices.activate(0).unwrap();
+        let format = microphone.format();
+
+        microphone.set_frame_timeout(std::time::Duration::from_secs(2));
+
+        let session = microphone.clone().start_session();
+        let packet = microphone.recv_frame().await;
+
+        assert!(session.stop().await.is_ok());
+
+        let packet = packet.unwrap().unwrap();
+
+        assert_eq!(packet.format, format);
+        assert_eq!(packet.len(), packet.frames * format.block_align());
+    }
+
+    #[test]
+    fn activate_index_out_of_range() {
+        use crate::{capture_error::CaptureError, devices::DeviceConfig};
+
+        let devices = unsafe { Cameras::new().unwrap() };
+        let len = devices.len();
+
+        assert!(devices.get(len).is_none());
+
+        match devices.activate(len, DeviceConfig::default()) {
+            Err(CaptureError::DeviceIndexOutOfRange { index, len: found }) => assert_eq!((index, found), (len, len)),
+            other => panic!("expected DeviceIndexOutOfRange, got {:?}", other.err()),
+        }
+
+        assert_eq!(devices.get(0).map(|handle| handle.index()), Some(0));
+    }
+
+    #[test]
+    fn retry_attempts() {
+        use crate::devices::RetryPolicy;
+        use std::time::Duration;
+
+        let policy = RetryPolicy::new(3, Duration::from_millis(100));
+
+        let mut calls = 0;
+        let mut waits = vec![];
+
+        //every attempt asks for another one, the last one gives up
+        let result: Result<(), u32> = policy.retry(|attempt, last_attempt| {
+            calls += 1;
+            if last_attempt { Err(attempt) } else { Ok(None) }
+        }, |delay| waits.push(delay));
+
+        assert_eq!(result, Err(2));
+        assert_eq!(calls, 3);
+        assert_eq!(waits, vec![Duration::from_millis(100), Duration::from_millis(200)]);
+
+        //a single attempt is made without attempts
+        let mut calls = 0;
+        let result: Result<(), ()> = RetryPolicy::new(0, Duration::ZERO).retry(|_, last_attempt| {
+            calls += 1;
+            assert!(last_attempt);
+            Err(())
+        }, |_| panic!("waited without another attempt"));
+
+        assert_eq!((result, calls), (Err(()), 1));
+
+        let result = policy.retry(|attempt, _| Ok::<_, ()>((attempt == 1).then_some(attempt)), |_| {});
+        assert_eq!(result, Ok(1));
+    }
+
+    #[test]
+    fn device_in_use() {
+        use crate::{capture_error::CaptureError, devices::camera::Camera};
+        use windows::Win32::Foundation::{E_ACCESSDENIED, ERROR_BUSY, ERROR_DEVICE_REMOVED};
+
+        let busy = Camera::in_use_named(CaptureError::Windows(ERROR_BUSY.to_hresult().into()), || "Front Camera".into());
+
+        match &busy {
+            CaptureError::DeviceInUse { name, error } => assert_eq!((name.as_str(), error.code()), ("Front Camera", ERROR_BUSY.to_hresult())),
+            other => panic!("expected DeviceInUse, got {other:?}"),
+        }
+
+        assert!(busy.to_string().starts_with("Front Camera is in use by another application, close it and try again ("));
+
+        let denied = Camera::in_use_named(CaptureError::Windows(E_ACCESSDENIED.into()), || "Front Camera".into());
+        assert_eq!(denied.to_string(), "access to Front Camera was denied, another application may be using it or camera access is turned off in the privacy settings");
+
+        //other errors are kept and the name is not read
+        let removed = Camera::in_use_named(CaptureError::Windows(ERROR_DEVICE_REMOVED.to_hresult().into()), || panic!("the name was read"));
+        assert!(matches!(removed, CaptureError::Windows(error) if error.code() == ERROR_DEVICE_REMOVED.to_hresult()));
+    }
+
+    #[test]
+    fn refresh_diff() {
+        use crate::devices::cameras::diff_symbolic_links;
+
+        //b was unplugged, d plugged in and reported twice, a and c moved
+        let (removed, added) = diff_symbolic_links(&["a", "b", "c"], &["c", "d", "a", "d"]);
+        assert_eq!(removed, vec![1]);
+        assert_eq!(added, vec![1]);
+
+        let (removed, added) = diff_symbolic_links(&["a"], &[]);
+        assert_eq!((removed, added), (vec![0], vec![]));
+
+        let (removed, added) = diff_symbolic_links(&[], &["a"]);
+        assert_eq!((removed, added), (vec![], vec![0]));
+    }
+
+    #[tokio::test]
+    async fn cameras_are_independent() {
+        let (first, second) = unsafe {
+            let devices = Cameras::new().unwrap();
+
+            if devices.devices.len() < 2 {
+                println!("Two cameras are needed, found {}", devices.devices.len());
+                return;
+            }
+
+            let first = devices.activate_device(&devices.devices[0], None);
+            let second = devices.activate_device(&devices.devices[1], None);
+
+            assert!(first.is_ok(), "{:?}", first.err());
+            assert!(second.is_ok(), "{:?}", second.err());
+
+            (first.unwrap(), second.unwrap())
+        };
+
+        assert!(!std::sync::Arc::ptr_eq(&first.clone_receiver(), &second.clone_receiver()));
+
+        let sessions = [first.clone().start_session(), second.clone().start_session()];
+        let receivers = [first.clone_receiver(), second.clone_receiver()];
+        let mut last: [Option<(usize, std::time::Duration)>; 2] = [None, None];
+
+        //both cameras capture at once and are read in turns, a frame of the other camera would break the size or the order of the timestamps
+        for _ in 0..10 {
+            for (index, receiver) in receivers.iter().enumerate() {
+                let mut receiver = receiver.lock().await;
+                let frame = tokio::time::timeout(std::time::Duration::from_secs(5), receiver.recv()).await;
+
+                assert!(matches!(frame, Ok(Some(_))), "no frame from camera {index}");
+
+                let frame = frame.unwrap().unwrap();
+
+                if let Some((len, timestamp)) = last[index] {
+                    assert_eq!(frame.len(), len, "camera {index} received a frame of another size");
+                    assert!(frame.timestamp >= timestamp, "camera {index} received a frame out of order");
+                }
+
+                last[index] = Some((frame.len(), frame.timestamp));
+            }
+        }
+
+        for session in sessions {
+            let stopped = session.stop().await;
+            assert!(stopped.is_ok(), "{:?}", stopped.err());
+        }
+    }
+
+    #[tokio::test]
+    async fn record_mp4() {
+        use crate::encoding::{H264Config, H264Encoder, Mp4Muxer};
+
+        let monitor = unsafe { Monitor::from_monitor(0) };
+
+        assert!(monitor.is_ok(), "{:?}", monitor.err());
+
+        let encoder = H264Encoder::new(monitor.unwrap(), H264Config::default());
+        let path = std::env::temp_dir().join("win_video_record.mp4");
+
+        let muxer = Mp4Muxer::create(encoder.clone(), &path).await;
+
+        assert!(muxer.is_ok(), "{:?}", muxer.err());
+
+        let mut muxer = muxer.unwrap();
+
+        let encoding = encoder.start();
+
+        assert!(encoding.is_ok(), "{:?}", encoding.err());
+
+        let stopping = encoder.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+            let _ = stopping.stop().await;
+        });
+
+        let written = muxer.run().await;
+
+        assert!(written.is_ok(), "{:?}", written.err());
+        assert!(muxer.samples_written() > 0);
+
+        let finalized = muxer.finalize().await;
+
+        assert!(finalized.is_ok(), "{:?}", finalized.err());
+
+        //only checked when ffprobe is installed
+        if let Ok(probe) = std::process::Command::new("ffprobe").arg("-v").arg("error").arg(&path).output() {
+            assert!(probe.status.success(), "ffprobe rejected the file: {}", String::from_utf8_lossy(&probe.stderr));
+        }
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn write_webm() {
+        use crate::{devices::Dimensions, encoding::{EncodedPacket, VideoCodec, WebmMuxer}};
+
+        let path = std::env::temp_dir().join("win_video_write.webm");
+
+        let muxer = WebmMuxer::create_with_codec(&path, VideoCodec::Vp8, Dimensions { width: 64, height: 64 }, 30).await;
+
+        assert!(muxer.is_ok(), "{:?}", muxer.err());
+
+        let mut muxer = muxer.unwrap();
+
+        //the frames are not decoded, only the container is checked
+        for index in 0..60u64 {
+            let packet = EncodedPacket {
+                data: vec![0x9D, 0x01, 0x2A, index as u8],
+                timestamp: std::time::Duration::from_millis(index * 1000 / 30),
+                keyframe: index % 30 == 0,
+            };
+
+            let written = muxer.write_packet(&packet).await;
+
+            assert!(written.is_ok(), "{:?}", written.err());
+        }
+
+        assert_eq!(muxer.frames_written(), 60);
+
+        let duration = muxer.duration();
+
+        assert!((duration.as_secs_f64() - 2.0).abs() < 0.05, "{duration:?}");
+        assert!(muxer.finalize().await.is_ok());
+
+        //only checked when ffprobe is installed
+        if let Ok(probe) = std::process::Command::new("ffprobe").args(["-v", "quiet", "-show_entries", "format=duration", "-of", "csv=p=0"]).arg(&path).output() {
+            assert!(probe.status.success(), "ffprobe rejected the file");
+
+            let reported: f64 = String::from_utf8_lossy(&probe.stdout).trim().parse().unwrap_or_default();
+
+            assert!((reported - duration.as_secs_f64()).abs() < 0.05, "ffprobe reported {reported}s");
+        }
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn convert_nv12() {
+        //white on the left, black on the right, no color
+        let nv12 = [235, 235, 16, 16, 235, 235, 16, 16, 128, 128, 128, 128];
+
+        let bgra = crate::convert::nv12_to_bgra(&nv12, 4, 2);
+
+        assert!(bgra.is_ok(), "{:?}", bgra.err());
+
+        let bgra = bgra.unwrap();
+
+        assert_eq!(bgra.len(), 4 * 2 * 4);
+        assert_eq!(&bgra[0..4], &[255, 255, 255, 255]);
+        assert_eq!(&bgra[8..12], &[0, 0, 0, 255]);
+        assert_eq!(&bgra[16..20], &[255, 255, 255, 255]);
+
+        assert!(crate::convert::nv12_to_bgra(&nv12[..8], 4, 2).is_err());
+    }
+
+    #[test]
+    fn convert_yuy2() {
+        //a white and a black pixel sharing no color, then pure red
+        let yuy2 = [235, 128, 16, 128, 81, 90, 81, 240];
+
+        let bgra = crate::convert::yuy2_to_bgra(&yuy2, 2, 2);
+
+        assert!(bgra.is_ok(), "{:?}", bgra.err());
+
+        let bgra = bgra.unwrap();
+
+        assert_eq!(&bgra[0..8], &[255, 255, 255, 255, 0, 0, 0, 255]);
+        assert!(bgra[8] < 8 && bgra[9] < 8 && bgra[10] > 247, "{:?}", &bgra[8..12]);
+    }
+
+    #[test]
+    fn replay_buffer_eviction() {
+        use std::time::Duration;
+        use crate::{devices::Frame, encoding::replay_buffer::Retained};
+
+        let frame = |millis| Frame::new(vec![0; 100], Duration::from_millis(millis));
+
+        let mut retained = Retained::default();
+
+        //one second at 10 fps is 11 frames including both ends
+        for i in 0..30 {
+            retained.push(frame(i * 100), Duration::from_secs(1), 10_000);
+        }
+
+        assert_eq!(retained.len(), 11);
+        assert_eq!(retained.span(), Duration::from_secs(1));
+        assert_eq!(retained.bytes(), 1100);
+        assert_eq!(retained.evicted(), 19);
+
+        //the byte budget evicts the oldest frames as well
+        retained.push(frame(3000), Duration::from_secs(1), 500);
+
+        assert_eq!(retained.len(), 5);
+        assert_eq!(retained.bytes(), 500);
+        assert_eq!(retained.window(Duration::from_secs(10)), Some((Duration::from_millis(2600), Duration::from_millis(3000))));
+        assert_eq!(retained.window(Duration::from_millis(250)), Some((Duration::from_millis(2800), Duration::from_millis(3000))));
+
+        assert_eq!(retained.next_frame(Duration::from_millis(2650), Duration::from_millis(3000)).map(|frame| frame.timestamp), Some(Duration::from_millis(2700)));
+        assert!(retained.next_frame(Duration::from_millis(2650), Duration::from_millis(2650)).is_none());
+
+        //a frame larger than the budget is not kept at all
+        retained.push(Frame::new(vec![0; 1000], Duration::from_millis(3100)), Duration::from_secs(1), 500);
+
+        assert_eq!(retained.len(), 0);
+        assert_eq!(retained.bytes(), 0);
+
+        //a restarted capture drops the frames of the previous run
+        retained.push(frame(3200), Duration::from_secs(1), 10_000);
+        retained.push(frame(0), Duration::from_secs(1), 10_000);
+
+        assert_eq!(retained.len(), 1);
+        assert_eq!(retained.span(), Duration::ZERO);
+    }
+
+    #[test]
+    fn change_detection() {
+        use crate::devices::{ChangeDetection, change_detection::ChangeDetector};
+
+        let mut detector = ChangeDetector::default();
+        let exact = ChangeDetection::new(1, 0);
+
+        //the first frame has nothing to be compared with
+        assert!(!detector.is_duplicate(&exact, &[10; 16]));
+        assert!(detector.is_duplicate(&exact, &[10; 16]));
+        assert!(!detector.is_duplicate(&exact, &[11; 16]));
+
+        //noise within the tolerance is not a change
+        let noisy = ChangeDetection::new(1, 2);
+
+        assert!(detector.is_duplicate(&noisy, &[13; 16]));
+        assert!(!detector.is_duplicate(&noisy, &[20; 16]));
+
+        //another size always changed
+        assert!(!detector.is_duplicate(&noisy, &[20; 8]));
+
+        detector.reset();
+        assert!(!detector.is_duplicate(&noisy, &[20; 8]));
+    }
+
+    #[test]
+    fn frame_delta() {
+        use crate::{analysis::{frame_delta, frame_delta_with_image, frame_delta_with_tolerance}, devices::Rect};
+
+        let prev = vec![0u8; 4 * 4 * 3];
+        let mut curr = prev.clone();
+
+        assert!(frame_delta(&prev, &curr, 4, 3).is_unchanged());
+
+        //pixels (1, 0) and (2, 2), alpha is not a change
+        curr[4 + 2] = 200;
+        curr[(2 * 4 + 2) * 4] = 10;
+        curr[3] = 255;
+
+        let delta = frame_delta_with_image(&prev, &curr, 4, 3);
+
+        assert_eq!(delta.changed_pixels, 2);
+        assert_eq!(delta.dirty_region, Some(Rect { left: 1, top: 0, right: 3, bottom: 3 }));
+
+        let image = delta.diff_image.unwrap();
+        assert_eq!(image.len(), prev.len());
+        assert_eq!(image[4 + 2], 200);
+        assert_eq!(image[(2 * 4 + 2) * 4], 10);
+
+        assert_eq!(frame_delta(&prev, &curr, 4, 3).diff_image, None);
+
+        //only the first pixel changed by more than 10
+        assert_eq!(frame_delta_with_tolerance(&prev, &curr, 4, 3, 10).changed_pixels, 1);
+    }
+
+    #[tokio::test]
+    async fn motion_detector() {
+        use crate::{analysis::{MotionDetector, MotionEvent}, capture_state::StateCell, devices::{Dimensions, Frame, Rect, camera::Output}, frame_callback::FrameCallback};
+        use std::{pin::Pin, sync::{Arc, RwLock}, time::Duration};
+        use tokio::sync::{Mutex, mpsc::{self, Receiver, Sender}};
+
+        //sends its frames once started and waits to be stopped
+        struct FakeCapture {
+            frames: Vec<Frame>,
+            sender: Sender<Frame>,
+            receiver: Arc<Mutex<Receiver<Frame>>>,
+            frame_timeout: RwLock<Option<Duration>>,
+            frame_callback: FrameCallback<Frame>,
+            state: StateCell,
+        }
+
+        impl ICapture for FakeCapture {
+            type CaptureOutput = Frame;
+
+            fn get_dimensions(&self) -> Result<Dimensions, Box<dyn std::error::Error>> {
+                Ok(Dimensions { width: 4, height: 2 })
+            }
 
-            devices.free_devices();
+            fn get_output_format(&self) -> Output {
+                Output::RGB32
+            }
+
+            fn stop_capturing(self: Arc<Self>) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>> + Send>> {
+                Box::pin(async move {
+                    self.state.stop();
+                    Ok(())
+                })
+            }
+
+            fn start_capturing(self: Arc<Self>) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>> + Send>> {
+                Box::pin(async move {
+                    let _running = self.state.start().await?;
+
+                    for frame in &self.frames {
+                        self.sender.send(frame.clone()).await?;
+                    }
+
+                    while self.is_capturing() {
+                        tokio::time::sleep(Duration::from_millis(5)).await;
+                    }
+
+                    Ok(())
+                })
+            }
+
+            fn clone_receiver(&self) -> Arc<Mutex<Receiver<Frame>>> {
+                self.receiver.clone()
+            }
+
+            fn set_frame_timeout(&self, duration: Duration) {
+                *self.frame_timeout.write().unwrap() = Some(duration);
+            }
+
+            fn clear_frame_timeout(&self) {
+                *self.frame_timeout.write().unwrap() = None;
+            }
+
+            fn get_frame_timeout(&self) -> Option<Duration> {
+                *self.frame_timeout.read().unwrap()
+            }
+
+            fn frame_callback(&self) -> &FrameCallback<Frame> {
+                &self.frame_callback
+            }
+
+            fn state_cell(&self) -> &StateCell {
+                &self.state
+            }
+        }
+
+        //4x2 pixels, the pixels at the indices are set to 200
+        let image = |changed: &[usize]| -> Vec<u8> {
+            (0..8).flat_map(|pixel| if changed.contains(&pixel) { [200, 200, 200, 255] } else { [0, 0, 0, 255] }).collect()
+        };
+
+        let frame = |n: u64, data: Vec<u8>, is_duplicate: bool| Frame { stride: 16, is_duplicate, ..Frame::new(data, Duration::from_millis(n * 10)) };
+
+        let frames = vec![
+            frame(0, image(&[]), false),
+            //still
+            frame(1, image(&[]), false),
+            //2 of 8 pixels moved, above the threshold
+            frame(2, image(&[1, 6]), false),
+            //1 of 8 pixels moved, not above the threshold
+            frame(3, image(&[1, 6, 7]), false),
+            //a duplicate is not compared and does not replace the previous frame
+            frame(4, image(&[0, 1, 2, 3, 4, 5, 6, 7]), true),
+            frame(5, image(&[1, 6, 7]), false),
+        ];
+
+        let (tx, rx) = mpsc::channel(1);
+
+        let source = Arc::new(FakeCapture {
+            frames: frames.clone(),
+            sender: tx,
+            receiver: Arc::new(Mutex::new(rx)),
+            frame_timeout: RwLock::new(None),
+            frame_callback: FrameCallback::default(),
+            state: StateCell::default(),
+        });
+
+        let detector = MotionDetector::new(source, 20.0);
+        detector.set_frame_timeout(Duration::from_secs(5));
+
+        let session = detector.clone().start_session();
+
+        //every frame is passed on as it is
+        for expected in &frames {
+            let frame = detector.recv_frame().await;
+            assert!(matches!(frame, Ok(Some(_))), "{:?}", frame.err());
+
+            let frame = frame.unwrap().unwrap();
+            assert_eq!((frame.data, frame.timestamp, frame.is_duplicate), (expected.data.clone(), expected.timestamp, expected.is_duplicate));
+        }
+
+        let motion = detector.clone_motion_receiver();
+        let mut motion = motion.lock().await;
+
+        assert_eq!(motion.try_recv().ok(), Some(MotionEvent { timestamp: Duration::from_millis(20), intensity: 25.0, region: Rect { left: 1, top: 0, right: 3, bottom: 2 } }));
+        assert!(motion.try_recv().is_err());
+
+        let stopped = session.stop().await;
+        assert!(stopped.is_ok(), "{:?}", stopped.err());
+    }
+
+    #[test]
+    fn solid_red_histogram() {
+        use crate::{analysis::{HistogramChannel, compute_histogram}, devices::camera::Output};
+
+        let frame = [0, 0, 255, 255].repeat(6);
+
+        let red = compute_histogram(&frame, Output::RGB32, HistogramChannel::Red).unwrap();
+        assert_eq!(red.len(), 256);
+        assert_eq!(red[255], 6);
+        assert_eq!(red.iter().sum::<u32>(), 6);
+
+        let blue = compute_histogram(&frame, Output::RGB32, HistogramChannel::Blue).unwrap();
+        assert_eq!(blue[0], 6);
+
+        //0.299 of full red
+        let luma = compute_histogram(&frame, Output::RGB32, HistogramChannel::Luma).unwrap();
+        assert_eq!(luma[76], 6);
+
+        let all = compute_histogram(&frame, Output::RGB32, HistogramChannel::All).unwrap();
+        assert_eq!(all.len(), 768);
+        assert_eq!((all[255], all[256], all[512]), (6, 6, 6));
+
+        assert!(compute_histogram(&frame, Output::NV12, HistogramChannel::Red).is_err());
+    }
+
+    #[test]
+    fn shared_frame() {
+        use crate::devices::Frame;
+        use std::sync::Arc;
+
+        let frame = Frame::new(vec![1, 2, 3, 4], std::time::Duration::from_millis(5)).into_shared();
+        let preview = frame.clone();
+
+        //both consumers read the same allocation
+        assert!(Arc::ptr_eq(&frame.data, &preview.data));
+        assert_eq!(&preview.data[..], &[1, 2, 3, 4]);
+        assert_eq!(preview.to_frame().data, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn frame_map() {
+        use crate::{devices::{Dimensions, Frame, ShareFrames, camera::Output}, map_capture::FrameMap};
+
+        let size = Dimensions { width: 1, height: 1 };
+        let frame = || Frame::new(vec![1, 2, 3, 4], std::time::Duration::ZERO);
+
+        //closures are frame maps, None skips the frame
+        let length = |frame: Frame| Some(frame.len());
+        let skip = |_frame: Frame| None::<Frame>;
+
+        assert_eq!(length.map(frame(), &size, Output::RGB32), Some(4));
+        assert!(skip.map(frame(), &size, Output::RGB32).is_none());
+        assert!(length.dimensions(Dimensions { width: 2, height: 3 }) == Dimensions { width: 2, height: 3 });
+        assert!(length.check(Output::NV12).is_ok());
+
+        let shared = ShareFrames.map(frame(), &size, Output::RGB32).unwrap();
+        assert_eq!(&shared.data[..], &[1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn scale_frame() {
+        use crate::{devices::camera::Output, transform::{ScaleFilter, scale_frame}};
+
+        //a black and a white column, shrunk to a single pixel and enlarged to 4x1
+        let frame = [0, 0, 0, 255, 255, 255, 255, 255];
+
+        let average = scale_frame(&frame, 2, 1, 1, 1, Output::RGB32, ScaleFilter::Bilinear).unwrap();
+        assert_eq!(average, vec![128, 128, 128, 255]);
+
+        for filter in [ScaleFilter::Nearest, ScaleFilter::Bilinear, ScaleFilter::Lanczos3] {
+            let scaled = scale_frame(&frame, 2, 1, 4, 1, Output::RGB32, filter).unwrap();
+            assert_eq!(scaled.len(), 16);
+            assert_eq!(&scaled[..4], &[0, 0, 0, 255], "{filter:?}");
+            assert_eq!(&scaled[12..], &[255, 255, 255, 255], "{filter:?}");
+        }
+
+        //4x2 NV12 is 8 luma and 4 chroma bytes, 2x2 has 4 and 2
+        let nv12 = scale_frame(&[16; 12], 4, 2, 2, 2, Output::NV12, ScaleFilter::Bilinear).unwrap();
+        assert_eq!(nv12, vec![16; 6]);
+
+        assert!(scale_frame(&frame, 2, 1, 1, 1, Output::YUY2, ScaleFilter::Nearest).is_err());
+    }
+
+    //run with cargo test --release scale_frame_timing -- --ignored, the targets are only checked in release builds
+    #[test]
+    #[ignore]
+    fn scale_frame_timing() {
+        use std::time::{Duration, Instant};
+        use crate::{devices::camera::Output, transform::{ScaleFilter, scale_frame}};
+
+        //a 4K BGRA gradient scaled to 1080p, the fastest of a few runs is the least disturbed by the machine
+        let frame: Vec<u8> = (0..3840 * 2160 * 4).map(|i| (i % 251) as u8).collect();
+
+        for (filter, target) in [(ScaleFilter::Nearest, Duration::from_millis(2)), (ScaleFilter::Bilinear, Duration::from_millis(10))] {
+            let fastest = (0..10).map(|_| {
+                let start = Instant::now();
+                let scaled = scale_frame(&frame, 3840, 2160, 1920, 1080, Output::RGB32, filter).unwrap();
+                assert_eq!(scaled.len(), 1920 * 1080 * 4);
+                start.elapsed()
+            }).min().unwrap();
+
+            println!("{filter:?}: {fastest:?}");
+
+            if !cfg!(debug_assertions) {
+                assert!(fastest < target, "{filter:?} took {fastest:?}, more than {target:?}");
+            }
         }
     }
+
+    #[test]
+    fn generate_thumbnail() {
+        use crate::{devices::camera::Output, transform::generate_thumbnail};
+
+        let bgra = [[10u8, 20, 30, 255]; 16].concat();
+        assert_eq!(generate_thumbnail(&bgra, 4, 4, Output::RGB32, 2, 2).unwrap(), [[10u8, 20, 30, 255]; 4].concat());
+
+        //a gray NV12 frame is scaled first, then converted to gray BGRA
+        let nv12 = [[126u8; 16].as_slice(), &[128; 8]].concat();
+        let thumbnail = generate_thumbnail(&nv12, 4, 4, Output::NV12, 2, 2).unwrap();
+        assert_eq!(thumbnail, [[128u8, 128, 128, 255]; 4].concat());
+
+        assert!(generate_thumbnail(&bgra, 4, 4, Output::PCM, 2, 2).is_err());
+        assert!(generate_thumbnail(&bgra, 4, 4, Output::RGB32, 0, 2).is_err());
+    }
+
+    #[test]
+    fn rotate_frame() {
+        use crate::{devices::camera::Output, transform::{FrameRotation, rotate_frame}};
+
+        //a 5x1 row, long enough for four pixels to be mirrored at once
+        let frame: Vec<u8> = (1..=5).flat_map(|v| [v, v, v, 255]).collect();
+
+        let (mirrored, width, height) = rotate_frame(&frame, 5, 1, Output::RGB32, FrameRotation::FlipHorizontal).unwrap();
+        let expected: Vec<u8> = (1..=5).rev().flat_map(|v| [v, v, v, 255]).collect();
+        assert_eq!((mirrored, width, height), (expected, 5, 1));
+
+        //a quarter turn counterclockwise puts the last pixel of the row on top
+        let (turned, width, height) = rotate_frame(&frame, 5, 1, Output::RGB32, FrameRotation::Rotate90CCW).unwrap();
+        assert_eq!((width, height), (1, 5));
+        assert_eq!(&turned[..4], &[5, 5, 5, 255]);
+
+        assert!(rotate_frame(&frame, 5, 1, Output::NV12, FrameRotation::Rotate180).is_err());
+    }
+
+    #[test]
+    fn composite_overlay() {
+        use crate::{devices::camera::Output, transform::composite_overlay};
+
+        let black = [[0u8, 0, 0, 255]; 6].concat();
+        let white = [[255u8; 4]; 4].concat();
+
+        //a 2x2 overlay at the bottom right corner of a 3x2 frame is cut down to a single pixel
+        let mut frame = black.clone();
+        composite_overlay(&mut frame, 3, 2, &white, 2, 2, 2, 1, 1.0, Output::RGB32).unwrap();
+        assert_eq!(&frame[..20], &black[..20]);
+        assert_eq!(&frame[20..], &[255, 255, 255, 255]);
+
+        //half of the overlay, the alpha of the frame is kept
+        let mut frame = black.clone();
+        composite_overlay(&mut frame, 3, 2, &white, 2, 2, 0, 0, 0.5, Output::RGB32).unwrap();
+        assert_eq!(&frame[..4], &[128, 128, 128, 255]);
+
+        //an overlay outside of the frame leaves it as it is
+        let mut frame = black.clone();
+        composite_overlay(&mut frame, 3, 2, &white, 2, 2, 3, 0, 1.0, Output::RGB32).unwrap();
+        assert_eq!(frame, black);
+
+        assert!(composite_overlay(&mut frame, 3, 2, &white, 2, 2, 0, 0, 1.0, Output::PCM).is_err());
+        assert!(composite_overlay(&mut frame, 3, 2, &white, 4, 4, 0, 0, 1.0, Output::RGB32).is_err());
+    }
+
+    #[test]
+    fn frame_transform() {
+        use crate::devices::{Dimensions, FrameTransform, Rotation, camera::Output};
+
+        let pixel = |v: u8| [v, v, v, 255];
+        let frame: Vec<u8> = [1, 2, 3, 4].into_iter().flat_map(pixel).collect();
+
+        let transform = FrameTransform { mirror: true, flip: false, rotation: Rotation::Rotate90 };
+
+        //mirrored to 2 1 / 4 3, then rotated clockwise
+        let transformed = transform.apply(&frame, 2, 2, Output::RGB32).unwrap();
+        let expected: Vec<u8> = [4, 2, 3, 1].into_iter().flat_map(pixel).collect();
+        assert_eq!(transformed, expected);
+
+        assert!(transform.transformed_size(&Dimensions { width: 3, height: 2 }) == Dimensions { width: 2, height: 3 });
+        assert!(FrameTransform::default().apply(&frame, 2, 2, Output::RGB32).is_none());
+        assert!(transform.apply(&frame, 2, 2, Output::NV12).is_none());
+    }
+
+    #[test]
+    fn fps_meter() {
+        use crate::devices::frame_rate::FpsMeter;
+        use std::time::{Duration, Instant};
+
+        let meter = FpsMeter::default();
+        meter.set_window(3);
+
+        assert_eq!(meter.get().frames, 0);
+
+        let start = Instant::now();
+        for ms in [0, 100, 200, 250] {
+            meter.record(start + Duration::from_millis(ms));
+        }
+
+        //the first frame fell out of the window
+        let rate = meter.get();
+        assert_eq!(rate.frames, 3);
+        assert!((rate.instantaneous - 20.0).abs() < 0.01, "{rate:?}");
+        assert!((rate.average - 2.0 / 0.15).abs() < 0.01, "{rate:?}");
+    }
+
+    #[test]
+    fn colorspace() {
+        use crate::colorspace::{ColorMatrix, bgra_to_rgb32, bgra_to_yuv420_with, yuv420_to_bgra_with};
+
+        let mut pixels = [0u32; 5];
+        bgra_to_rgb32(&[[0x10, 0x20, 0x30, 0xFF]; 5].concat(), &mut pixels).unwrap();
+        assert_eq!(pixels, [0x0030_2010; 5]);
+        assert!(bgra_to_rgb32(&[0; 7], &mut [0; 2]).is_err());
+
+        assert_eq!(ColorMatrix::for_height(480), ColorMatrix::Bt601);
+        assert_eq!(ColorMatrix::for_height(1080), ColorMatrix::Bt709);
+
+        //odd sizes and a width past the simd blocks, a flat color survives the round trip
+        let (width, height) = (13, 3);
+        let bgra = [[30u8, 120, 200, 255]; 13 * 3].concat();
+
+        for matrix in [ColorMatrix::Bt601, ColorMatrix::Bt709] {
+            let (mut y, mut u, mut v) = (vec![0; 13 * 3], vec![0; 7 * 2], vec![0; 7 * 2]);
+            bgra_to_yuv420_with(&bgra, &mut y, &mut u, &mut v, width, height, matrix).unwrap();
+
+            let mut back = vec![0; bgra.len()];
+            yuv420_to_bgra_with(&y, &u, &v, &mut back, width, height, matrix).unwrap();
+
+            assert!(back.iter().zip(&bgra).all(|(a, b)| a.abs_diff(*b) <= 3), "{matrix:?} {back:?}");
+            assert!(yuv420_to_bgra_with(&y, &u[..13], &v, &mut back, width, height, matrix).is_err());
+        }
+    }
+
+    #[test]
+    fn bgra_to_0rgb() {
+        use crate::convert::{bgra_to_0rgb, bgra_to_0rgb_into};
+
+        //an odd width of 5 pixels, the rows padded to 24 bytes with bytes that must not show up
+        let row: Vec<u8> = (1..=5).flat_map(|v| [v, v * 2, v * 3, 0x80]).chain([0xEE; 4]).collect();
+        let padded = [row.as_slice(), &row, &row[..20]].concat();
+        let expected: Vec<u32> = (1..=5u32).map(|v| (v * 3) << 16 | (v * 2) << 8 | v).collect();
+
+        assert_eq!(bgra_to_0rgb(&padded, 5, 3, 24).unwrap(), expected.repeat(3));
+        assert_eq!(bgra_to_0rgb(&row[..20], 5, 1, 0).unwrap(), expected);
+
+        //the values past the frame are left as they are
+        let mut framebuffer = [7u32; 6];
+        bgra_to_0rgb_into(&row[..20], &mut framebuffer, 5, 1, 0).unwrap();
+        assert_eq!(&framebuffer[..5], expected.as_slice());
+        assert_eq!(framebuffer[5], 7);
+
+        assert!(bgra_to_0rgb(&padded, 5, 3, 16).is_err());
+        assert!(bgra_to_0rgb(&padded[..60], 5, 3, 24).is_err());
+        assert!(bgra_to_0rgb_into(&padded, &mut [0; 14], 5, 3, 24).is_err());
+    }
+
+    #[test]
+    fn frame_limiter() {
+        use crate::devices::frame_rate::FrameLimiter;
+        use std::time::Duration;
+
+        //ten seconds of a 60 fps camera with a little jitter
+        let kept = |max_fps: u32| {
+            let mut limiter = FrameLimiter::default();
+            (0..600u64).filter(|frame| limiter.admit(Duration::from_micros(frame * 16_667 + frame % 2 * 2_000), max_fps, Some(60.0))).count()
+        };
+
+        assert_eq!(kept(15), 150);
+        assert_eq!(kept(30), 300);
+        assert_eq!(kept(60), 600);
+        assert_eq!(kept(120), 600);
+    }
+
+    #[test]
+    fn hdr_to_bgra() {
+        use crate::{convert::hdr_to_bgra, devices::PixelFormat};
+
+        //scRGB 1.0 (half float 0x3C00) is 80 nits, white at an SDR white level of 80, the row is padded to 16 bytes
+        let mut data = vec![0u8; 16 + 8];
+        for i in [0, 2, 4, 6, 16, 18, 20, 22] {
+            data[i..i + 2].copy_from_slice(&0x3C00u16.to_le_bytes());
+        }
+
+        let bgra = hdr_to_bgra(&data, 1, 2, 16, PixelFormat::Rgba16Float, 80.0).unwrap();
+        assert_eq!(bgra, vec![255; 8]);
+
+        assert!(hdr_to_bgra(&data, 2, 2, 16, PixelFormat::Rgba16Float, 80.0).is_err());
+    }
+
+    #[tokio::test]
+    async fn capture_state() {
+        use crate::{capture_error::CaptureError, capture_state::{CaptureState, StateCell}};
+        use std::time::Duration;
+
+        let state = StateCell::new();
+        assert_eq!(state.get(), CaptureState::Stopped);
+        assert!(!state.stop());
+
+        let running = state.start().await.unwrap();
+        assert_eq!(state.get(), CaptureState::Capturing);
+
+        //a second start is rejected while the loop runs
+        assert!(matches!(state.start().await.err(), Some(CaptureError::AlreadyCapturing)));
+
+        assert!(state.stop());
+        assert_eq!(state.get(), CaptureState::Stopping);
+
+        //a start while stopping waits for the loop to end
+        let restart = state.start();
+        tokio::pin!(restart);
+
+        assert!(tokio::time::timeout(Duration::from_millis(20), &mut restart).await.is_err());
+
+        drop(running);
+
+        let restarted = restart.await;
+        assert!(restarted.is_ok());
+        assert_eq!(state.get(), CaptureState::Capturing);
+
+        drop(restarted);
+        assert_eq!(state.get(), CaptureState::Stopped);
+    }
+
+    #[tokio::test]
+    async fn backpressure() {
+        use crate::devices::{Backpressure, Frame, Rect, send_with_backpressure};
+        use std::time::Duration;
+        use tokio::sync::{Mutex, mpsc};
+
+        let rect = |left: u32| Rect { left, top: 0, right: left + 1, bottom: 1 };
+        let frame = |n: u8, rects: Vec<Rect>| Frame { dirty_rects: rects, ..Frame::new(vec![n], Duration::ZERO) };
+
+        //Block waits for the consumer
+        let (tx, rx) = mpsc::channel(1);
+        let rx = Mutex::new(rx);
+        let mut dropped = None;
+
+        assert_eq!(send_with_backpressure(&tx, &rx, Backpressure::Block, &mut dropped, frame(0, vec![])).await.ok(), Some(true));
+        assert!(tokio::time::timeout(Duration::from_millis(50), send_with_backpressure(&tx, &rx, Backpressure::Block, &mut dropped, frame(1, vec![]))).await.is_err());
+        assert_eq!(rx.lock().await.recv().await.unwrap().data, vec![0]);
+
+        //DropNewest keeps the buffered frame, the changes of the dropped one go with the next frame sent
+        let (tx, rx) = mpsc::channel(1);
+        let rx = Mutex::new(rx);
+        let mut dropped = None;
+
+        for (n, sent) in [(0, true), (1, false)] {
+            assert_eq!(send_with_backpressure(&tx, &rx, Backpressure::DropNewest, &mut dropped, frame(n, vec![rect(n as u32)])).await.ok(), Some(sent));
+        }
+
+        assert_eq!(rx.lock().await.recv().await.unwrap().data, vec![0]);
+        assert_eq!(send_with_backpressure(&tx, &rx, Backpressure::DropNewest, &mut dropped, frame(2, vec![rect(2)])).await.ok(), Some(true));
+
+        let next = rx.lock().await.recv().await.unwrap();
+        assert_eq!((next.data, next.dirty_rects, next.skipped_since_last), (vec![2], vec![rect(2), rect(1)], 1));
+        assert!(dropped.is_none());
+
+        //DropOldest merges the oldest frame into the one after it
+        let (tx, rx) = mpsc::channel(2);
+        let rx = Mutex::new(rx);
+        let mut dropped = None;
+
+        for n in 0..3 {
+            assert_eq!(send_with_backpressure(&tx, &rx, Backpressure::DropOldest, &mut dropped, frame(n, vec![rect(n as u32)])).await.ok(), Some(true));
+        }
+
+        let next = rx.lock().await.recv().await.unwrap();
+        assert_eq!((next.data, next.dirty_rects, next.skipped_since_last), (vec![1], vec![rect(1), rect(0)], 1));
+
+        let next = rx.lock().await.recv().await.unwrap();
+        assert_eq!((next.data, next.dirty_rects, next.skipped_since_last), (vec![2], vec![rect(2)], 0));
+
+        //a consumer holding the receiver makes DropOldest drop the new frame
+        let (tx, rx) = mpsc::channel(1);
+        let rx = Mutex::new(rx);
+        let mut dropped = None;
+
+        assert_eq!(send_with_backpressure(&tx, &rx, Backpressure::DropOldest, &mut dropped, frame(0, vec![rect(0)])).await.ok(), Some(true));
+
+        let mut consumer = rx.lock().await;
+        assert_eq!(send_with_backpressure(&tx, &rx, Backpressure::DropOldest, &mut dropped, frame(1, vec![rect(1)])).await.ok(), Some(false));
+        assert_eq!(dropped.as_ref().map(|frame| frame.data.len()), Some(0));
+        assert_eq!(consumer.recv().await.unwrap().data, vec![0]);
+        drop(consumer);
+
+        //Coalesce merges every buffered frame into the new one, a frame without rects changed as a whole
+        let (tx, rx) = mpsc::channel(2);
+        let rx = Mutex::new(rx);
+        let mut dropped = None;
+
+        for (n, rects) in [(0, vec![rect(0)]), (1, vec![rect(1)]), (2, vec![rect(2)])] {
+            assert_eq!(send_with_backpressure(&tx, &rx, Backpressure::Coalesce, &mut dropped, frame(n, rects)).await.ok(), Some(true));
+        }
+
+        let next = rx.lock().await.try_recv().unwrap();
+        assert_eq!((next.data, next.dirty_rects, next.skipped_since_last), (vec![2], vec![rect(2), rect(0), rect(1)], 2));
+        assert!(rx.lock().await.try_recv().is_err());
+
+        for (n, rects) in [(3, vec![rect(3)]), (4, vec![]), (5, vec![rect(5)])] {
+            assert_eq!(send_with_backpressure(&tx, &rx, Backpressure::Coalesce, &mut dropped, frame(n, rects)).await.ok(), Some(true));
+        }
+
+        let next = rx.lock().await.try_recv().unwrap();
+        assert_eq!((next.data, next.dirty_rects, next.is_duplicate), (vec![5], vec![], false));
+    }
+
+    #[tokio::test]
+    async fn restart_monitor() {
+        use crate::{capture_error::CaptureError, capture_state::CaptureState, devices::Backpressure};
+        use std::time::Duration;
+
+        let monitor = unsafe { Monitor::from_monitor(0) };
+
+        assert!(monitor.is_ok(), "{:?}", monitor.err());
+
+        let monitor = monitor.unwrap();
+        monitor.set_constant_fps(30);
+        monitor.set_frame_timeout(Duration::from_secs(5));
+
+        //the frames nobody reads are dropped, so the loop never waits on the receiver while it stops
+        monitor.set_backpressure(Backpressure::DropOldest);
+
+        for _ in 0..5 {
+            let session = monitor.clone().start_session();
+
+            let frame = monitor.recv_frame().await;
+            assert!(matches!(frame, Ok(Some(_))), "{:?}", frame.err());
+
+            //a second start is rejected while the first one runs
+            let second = monitor.clone().start_capturing().await.map_err(CaptureError::from);
+            assert!(matches!(second, Err(CaptureError::AlreadyCapturing)), "{:?}", second);
+
+            let stopped = session.stop().await;
+            assert!(stopped.is_ok(), "{:?}", stopped.err());
+            assert_eq!(monitor.state(), CaptureState::Stopped);
+        }
+    }
+
+    #[tokio::test]
+    async fn multi_monitor_stitch() {
+        use crate::devices::{MultiMonitorStitch, get_all_monitor_info};
+
+        let layout = unsafe { get_all_monitor_info() };
+        let stitch = MultiMonitorStitch::from_layout(&layout);
+
+        assert!(stitch.is_ok(), "{:?}", stitch.err());
+
+        let stitch = stitch.unwrap();
+        let dimensions = stitch.get_dimensions().unwrap();
+
+        //every monitor fits into the stitched frame
+        for info in layout.iter().filter(|info| info.attached) {
+            assert!(info.width <= dimensions.width && info.height <= dimensions.height);
+        }
+
+        stitch.set_frame_timeout(std::time::Duration::from_secs(5));
+
+        let frames = stitch.clone().capture_n_frames(2).await;
+
+        assert!(frames.is_ok(), "{:?}", frames.err());
+
+        for frame in frames.unwrap() {
+            assert_eq!(frame.data.len(), dimensions.width as usize * dimensions.height as usize * 4);
+        }
+    }
+
+    #[tokio::test]
+    async fn capture_n_frames() {
+        use crate::capture_state::CaptureState;
+
+        let monitor = unsafe { Monitor::from_monitor(0) };
+
+        assert!(monitor.is_ok(), "{:?}", monitor.err());
+
+        let monitor = monitor.unwrap();
+
+        //a still desktop sends no new frames, repeated frames keep them coming
+        monitor.set_constant_fps(30);
+        monitor.set_frame_timeout(std::time::Duration::from_secs(5));
+
+        let frames = monitor.clone().capture_n_frames(5).await;
+
+        assert!(frames.is_ok(), "{:?}", frames.err());
+        assert_eq!(frames.unwrap().len(), 5);
+        assert_eq!(monitor.state(), CaptureState::Stopped);
+    }
+
+    #[tokio::test]
+    async fn take_receiver() {
+        let monitor = unsafe { Monitor::from_monitor(0) };
+
+        assert!(monitor.is_ok(), "{:?}", monitor.err());
+
+        let monitor = monitor.unwrap();
+        monitor.set_constant_fps(30);
+
+        let receiver = monitor.take_receiver();
+        assert!(receiver.is_ok(), "{:?}", receiver.err());
+
+        let mut receiver = receiver.unwrap();
+
+        //the receiver can only be taken once, the shared one is closed
+        assert!(monitor.take_receiver().is_err());
+        assert!(matches!(monitor.recv_frame().await, Ok(None)));
+
+        let session = monitor.clone().start_session();
+
+        let frame = tokio::time::timeout(std::time::Duration::from_secs(5), receiver.recv()).await;
+        assert!(matches!(frame, Ok(Some(_))), "no frame on the taken receiver");
+
+        let stopped = session.stop().await;
+        assert!(stopped.is_ok(), "{:?}", stopped.err());
+    }
+
+    #[test]
+    fn gdi_dib_rows() {
+        use crate::devices::gdi_monitor::dib_to_frame;
+
+        //2x3 pixels, every pixel holds its row in the blue channel and GDI leaves alpha at 0
+        let rows: Vec<u8> = (0..3u8).flat_map(|row| [row, 0, 0, 0, row, 1, 0, 0]).collect();
+
+        let top_down = dib_to_frame(rows.clone(), 2, -3);
+        assert_eq!(top_down, vec![0, 0, 0, 255, 0, 1, 0, 255, 1, 0, 0, 255, 1, 1, 0, 255, 2, 0, 0, 255, 2, 1, 0, 255]);
+
+        //a bottom up bitmap has its last row first
+        let bottom_up = dib_to_frame(rows, 2, 3);
+        assert_eq!(bottom_up, vec![2, 0, 0, 255, 2, 1, 0, 255, 1, 0, 0, 255, 1, 1, 0, 255, 0, 0, 0, 255, 0, 1, 0, 255]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn saved_frame_round_trip() {
+        use crate::devices::{camera::Output, Dimensions, Frame, SavedFrame};
+
+        let mut frame = Frame::new(vec![1, 2, 3, 4, 5, 6, 7, 8], std::time::Duration::from_millis(40));
+        frame.stride = 8;
+
+        let saved = SavedFrame::new(&frame, Dimensions { width: 2, height: 1 }, Output::RGB32, 7);
+
+        let path = std::env::temp_dir().join("win_video_saved_frame.bin");
+
+        let written = saved.save_to_file(&path);
+        assert!(written.is_ok(), "{:?}", written.err());
+
+        let loaded = SavedFrame::load_from_file(&path);
+        let _ = std::fs::remove_file(&path);
+
+        assert!(loaded.is_ok(), "{:?}", loaded.err());
+
+        let loaded = loaded.unwrap();
+
+        assert_eq!(loaded, saved);
+        assert_eq!(loaded.to_frame().data, frame.data);
+        assert_eq!(loaded.to_frame().timestamp, frame.timestamp);
+    }
 }
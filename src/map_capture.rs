@@ -0,0 +1,253 @@
+use std::{
+    pin::Pin,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use tokio::sync::{
+    Mutex,
+    mpsc::{self, Receiver, Sender},
+};
+
+use crate::{
+    capture_error::CaptureError,
+    capture_state::StateCell,
+    devices::{DEFAULT_CHANNEL_CAPACITY, Dimensions, Frame, camera::Output},
+    frame_callback::FrameCallback,
+    i_capture::ICapture,
+};
+
+/// # Frame Map
+///
+/// What a MapCapture does with every frame of its source.
+///
+/// Closures taking a Frame and returning an `Option` are frame maps, SharedCapture, ScaledCapture, RotatedCapture and WatermarkCapture
+/// are MapCaptures with their own frame maps.
+pub trait FrameMap: Send + Sync + 'static {
+    /// What frames are mapped to.
+    type Output: Send + 'static;
+
+    /// # Map
+    ///
+    /// Maps a frame of the source, which has the size and format from the arguments. None skips the frame.
+    fn map(&self, frame: Frame, size: &Dimensions, format: Output) -> Option<Self::Output>;
+
+    /// # Dimensions
+    ///
+    /// The dimensions of the mapped frames for a source of the dimensions, the same as the source by default.
+    fn dimensions(&self, source: Dimensions) -> Dimensions {
+        source
+    }
+
+    /// # Check
+    ///
+    /// Called before the source starts, an error when frames of the format can not be mapped.
+    fn check(&self, _format: Output) -> Result<(), CaptureError> {
+        Ok(())
+    }
+}
+
+impl<T, F> FrameMap for F
+where
+    T: Send + 'static,
+    F: Fn(Frame) -> Option<T> + Send + Sync + 'static,
+{
+    type Output = T;
+
+    fn map(&self, frame: Frame, _size: &Dimensions, _format: Output) -> Option<T> {
+        self(frame)
+    }
+}
+
+/// # Map Capture
+///
+/// Wraps any capture and passes every frame of it through a FrameMap.
+///
+/// ```rs
+/// let marked = MapCapture::with_map(monitor, |mut frame: Frame| {
+///     frame.data[..4].copy_from_slice(&[0, 0, 255, 255]);
+///     Some(frame)
+/// });
+///
+/// tokio::spawn(marked.clone().start_capturing());
+///
+/// while let Some(frame) = marked.recv_frame().await? {
+///     encoder.send(frame).await?;
+/// }
+/// ```
+///
+/// Capturing is started and stopped through the wrapper, do not start the source yourself.
+pub struct MapCapture<S: ICapture<CaptureOutput = Frame> + ?Sized, M: FrameMap> {
+    // the capture frames are taken from
+    source: Arc<S>,
+
+    // what is done with every frame
+    map: M,
+
+    /// The receiver, can be used to grab the mapped frames.
+    pub receiver: Arc<Mutex<Receiver<M::Output>>>,
+
+    // to pass mapped frames on
+    sender: Sender<M::Output>,
+
+    // how long recv_frame waits for a frame
+    frame_timeout: RwLock<Option<Duration>>,
+
+    // the callback registered with on_frame
+    frame_callback: FrameCallback<M::Output>,
+
+    // if the wrapper captures, see ICapture::state
+    state: StateCell,
+}
+
+impl<S: ICapture<CaptureOutput = Frame> + ?Sized + 'static, M: FrameMap> MapCapture<S, M> {
+    /// # With Map
+    ///
+    /// Wraps the source, every frame is passed through the map.
+    pub fn with_map(source: Arc<S>, map: M) -> Arc<Self> {
+        let (tx, rx) = mpsc::channel(DEFAULT_CHANNEL_CAPACITY);
+
+        Arc::new(Self {
+            source,
+            map,
+            receiver: Arc::new(Mutex::new(rx)),
+            sender: tx,
+            frame_timeout: RwLock::new(None),
+            frame_callback: FrameCallback::default(),
+            state: StateCell::default(),
+        })
+    }
+
+    /// # Frame Map
+    ///
+    /// What is done with every frame.
+    pub fn frame_map(&self) -> &M {
+        &self.map
+    }
+
+    /// # Source
+    ///
+    /// The capture the frames are taken from.
+    pub fn source(&self) -> Arc<S> {
+        self.source.clone()
+    }
+
+    // passes the mapped frames of the source on until the source channel closes.
+    //
+    // the source is always drained, even after the receiver was dropped, so it never blocks on a full channel while it stops.
+    async fn map_frames(&self, format: Output) {
+        let source_receiver = self.source.clone_receiver();
+        let mut source_receiver = source_receiver.lock().await;
+
+        let mut dropped = false;
+
+        while let Some(frame) = source_receiver.recv().await {
+            if dropped || !self.is_capturing() {
+                continue;
+            }
+
+            //read for every frame, the size of a source can change while it captures
+            let size = match self.source.get_dimensions() {
+                Ok(size) => size,
+                Err(_e) => {
+                    trace_event!(warn, error = %_e, "skipping a frame of a source without dimensions");
+                    continue;
+                }
+            };
+
+            let Some(frame) = self.map.map(frame, &size, format) else {
+                continue;
+            };
+
+            if self.sender.send(frame).await.is_err() {
+                trace_event!(warn, "receiver dropped, stopping map capture");
+
+                dropped = true;
+                let _ = self.source.clone().stop_capturing().await;
+            }
+        }
+    }
+}
+
+impl<S: ICapture<CaptureOutput = Frame> + ?Sized + 'static, M: FrameMap> ICapture
+    for MapCapture<S, M>
+{
+    type CaptureOutput = M::Output;
+
+    /// # Get Dimensions
+    ///
+    /// The dimensions of the source as the frame map changes them, see FrameMap::dimensions.
+    fn get_dimensions(&self) -> Result<Dimensions, Box<dyn std::error::Error>> {
+        Ok(self.map.dimensions(self.source.get_dimensions()?))
+    }
+
+    /// # Get Output Format
+    ///
+    /// The output of the source, a frame map keeps the format.
+    fn get_output_format(&self) -> Output {
+        self.source.get_output_format()
+    }
+
+    /// # Stop Capturing
+    ///
+    /// Stops passing frames on and stops the source.
+    fn stop_capturing(
+        self: Arc<Self>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>> + Send>> {
+        Box::pin(async move {
+            if !self.state.stop() {
+                return Err("Not capturing any data".into());
+            }
+
+            self.source.clone().stop_capturing().await
+        })
+    }
+
+    /// # Start Capturing
+    ///
+    /// Starts the source and maps each of its frames, the future completes once the source stops.
+    ///
+    /// Returns the error of FrameMap::check when the frames of the source can not be mapped.
+    fn start_capturing(
+        self: Arc<Self>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>> + Send>> {
+        Box::pin(async move {
+            let format = self.source.get_output_format();
+
+            self.map.check(format)?;
+
+            //the state is stopped again when the source stops, also with an error
+            let _running = self.state.start().await?;
+
+            //the map loop only ends with the source channel, so the source finishing ends the capture
+            tokio::select! {
+                result = self.source.clone().start_capturing() => result,
+                _ = self.map_frames(format) => Ok(()),
+            }
+        })
+    }
+
+    fn clone_receiver(&self) -> Arc<Mutex<Receiver<Self::CaptureOutput>>> {
+        self.receiver.clone()
+    }
+
+    fn set_frame_timeout(&self, duration: Duration) {
+        *self.frame_timeout.write().unwrap() = Some(duration);
+    }
+
+    fn clear_frame_timeout(&self) {
+        *self.frame_timeout.write().unwrap() = None;
+    }
+
+    fn get_frame_timeout(&self) -> Option<Duration> {
+        *self.frame_timeout.read().unwrap()
+    }
+
+    fn frame_callback(&self) -> &FrameCallback<M::Output> {
+        &self.frame_callback
+    }
+
+    fn state_cell(&self) -> &StateCell {
+        &self.state
+    }
+}
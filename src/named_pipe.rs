@@ -0,0 +1,362 @@
+use std::{
+    pin::Pin,
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::windows::named_pipe::{ClientOptions, NamedPipeClient, NamedPipeServer, ServerOptions},
+    sync::{
+        Mutex,
+        mpsc::{self, Receiver, Sender},
+    },
+};
+
+use crate::{
+    capture_error::CaptureError,
+    capture_state::StateCell,
+    devices::{DEFAULT_CHANNEL_CAPACITY, Dimensions, Frame, camera::Output},
+    frame_callback::FrameCallback,
+    i_capture::ICapture,
+    shared_memory::{format_code, format_from_code},
+};
+
+// the bytes of a message before the frame data
+const HEADER_LEN: usize = 32;
+
+// larger messages are taken as a broken stream rather than allocated, 8K RGBA16F is about 265 MB
+const MAX_MESSAGE_LEN: usize = 512 * 1024 * 1024;
+
+// names without a path are created in the local pipe namespace
+fn pipe_path(pipe_name: &str) -> String {
+    if pipe_name.starts_with(r"\\") {
+        pipe_name.to_string()
+    } else {
+        format!(r"\\.\pipe\{pipe_name}")
+    }
+}
+
+/// # Pipe Frame Sink
+///
+/// Writes frames into a named pipe, so a process (such as one with other privileges) can receive them with a PipeFrameSource or by reading the pipe itself.
+///
+/// Every frame is one message: the length of the rest of the message as a 4 byte little endian integer, a 32 byte header and the frame data.
+/// The header holds the width, height, format and stride as little endian u32s, then the timestamp in nanoseconds (u64) and the presentation time (i64).
+/// The formats are numbered NV12, RGB32, RGB10A2, RGBA16F, YUY2 and MJPEG from 0.
+///
+/// Unlike shared memory every frame is delivered, a slow reader slows down the writes once the pipe buffer is full.
+pub struct PipeFrameSink {
+    pipe: Mutex<NamedPipeServer>,
+    name: String,
+
+    frames_written: AtomicU64,
+}
+
+impl PipeFrameSink {
+    /// # New
+    ///
+    /// Creates the named pipe server and waits for one client to connect, the name is the part after \\.\pipe\ (or a full pipe path).
+    ///
+    /// The pipe is outbound only, the default security of named pipes lets any local user connect to read it.
+    ///
+    /// Returns CaptureError::Io when a pipe with the name already exists.
+    pub async fn new(pipe_name: &str) -> Result<Self, CaptureError> {
+        let name = pipe_path(pipe_name);
+
+        let pipe = ServerOptions::new()
+            .first_pipe_instance(true)
+            .access_inbound(false)
+            .access_outbound(true)
+            .create(&name)?;
+
+        trace_event!(debug, pipe = %name, "waiting for a pipe client");
+
+        pipe.connect().await?;
+
+        trace_event!(debug, pipe = %name, "pipe client connected");
+
+        Ok(Self {
+            pipe: Mutex::new(pipe),
+            name,
+            frames_written: AtomicU64::new(0),
+        })
+    }
+
+    /// # Name
+    ///
+    /// The full path of the pipe.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// # Frames Written
+    ///
+    /// How many frames have been written to the pipe.
+    pub fn frames_written(&self) -> u64 {
+        self.frames_written.load(Ordering::Relaxed)
+    }
+
+    /// # Write Frame
+    ///
+    /// Writes the frame as one message, dimensions and format describe the data for the reader.
+    ///
+    /// Waits while the pipe buffer is full, returns CaptureError::Io once the client disconnected.
+    pub async fn write_frame(
+        &self,
+        frame: &Frame,
+        dimensions: Dimensions,
+        format: Output,
+    ) -> Result<(), CaptureError> {
+        let len = HEADER_LEN + frame.data.len();
+
+        if len > MAX_MESSAGE_LEN {
+            return Err(CaptureError::Other(format!(
+                "a frame of {} bytes is too large for the pipe",
+                frame.data.len()
+            )));
+        }
+
+        let mut header = Vec::with_capacity(4 + HEADER_LEN);
+        header.extend_from_slice(&(len as u32).to_le_bytes());
+        header.extend_from_slice(&dimensions.width.to_le_bytes());
+        header.extend_from_slice(&dimensions.height.to_le_bytes());
+        header.extend_from_slice(&format_code(format).to_le_bytes());
+        header.extend_from_slice(&(frame.stride as u32).to_le_bytes());
+        header.extend_from_slice(&(frame.timestamp.as_nanos() as u64).to_le_bytes());
+        header.extend_from_slice(&frame.presentation_time.to_le_bytes());
+
+        let mut pipe = self.pipe.lock().await;
+
+        pipe.write_all(&header).await?;
+        pipe.write_all(&frame.data).await?;
+
+        self.frames_written.fetch_add(1, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// # Run
+    ///
+    /// Writes every frame of the capture until its channel closes or the client disconnects, start the capture yourself (for example with start_session).
+    ///
+    /// The size and format are read from the capture for every frame.
+    pub async fn run<S: ICapture<CaptureOutput = Frame>>(
+        &self,
+        source: &S,
+    ) -> Result<(), CaptureError> {
+        let receiver = source.clone_receiver();
+        let mut receiver = receiver.lock().await;
+
+        while let Some(frame) = receiver.recv().await {
+            let dimensions = source.get_dimensions().map_err(CaptureError::from)?;
+
+            self.write_frame(&frame, dimensions, source.get_output_format())
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// # Pipe Frame Source
+///
+/// Receives the frames a PipeFrameSink writes, usually in another process, as a capture.
+///
+/// get_dimensions and get_output_format describe the newest frame read from the pipe.
+/// The capture ends without an error when the sink closes the pipe.
+pub struct PipeFrameSource {
+    pipe: Mutex<NamedPipeClient>,
+
+    // the first frame, read by connect to learn the size and format and sent once capturing starts
+    first_frame: Mutex<Option<Frame>>,
+
+    dimensions: RwLock<Dimensions>,
+    format: RwLock<Output>,
+
+    /// The receiver, can be used to grab frames directly from the pipe.
+    pub receiver: Arc<Mutex<Receiver<Frame>>>,
+
+    sender: Sender<Frame>,
+
+    // how long recv_frame waits for a frame
+    frame_timeout: RwLock<Option<Duration>>,
+
+    // the callback registered with on_frame
+    frame_callback: FrameCallback<Frame>,
+
+    // if the capture loop runs, see ICapture::state
+    state: StateCell,
+}
+
+impl PipeFrameSource {
+    /// # Connect
+    ///
+    /// Connects to the pipe of a PipeFrameSink (the name as passed to PipeFrameSink::new) and reads its first frame, which describes the size and format.
+    ///
+    /// Returns CaptureError::Io when there is no such pipe or another client is connected to it.
+    pub async fn connect(pipe_name: &str) -> Result<Arc<Self>, CaptureError> {
+        let name = pipe_path(pipe_name);
+
+        //read only, so the default security of the pipe lets the client connect
+        let mut pipe = ClientOptions::new().write(false).open(&name)?;
+
+        let Some((frame, dimensions, format)) = read_message(&mut pipe).await? else {
+            return Err(CaptureError::Other(format!(
+                "{name} was closed before a frame was written"
+            )));
+        };
+
+        let (tx, rx) = mpsc::channel(DEFAULT_CHANNEL_CAPACITY);
+
+        Ok(Arc::new(Self {
+            pipe: Mutex::new(pipe),
+            first_frame: Mutex::new(Some(frame)),
+            dimensions: RwLock::new(dimensions),
+            format: RwLock::new(format),
+            receiver: Arc::new(Mutex::new(rx)),
+            sender: tx,
+            frame_timeout: RwLock::new(None),
+            frame_callback: FrameCallback::default(),
+            state: StateCell::default(),
+        }))
+    }
+}
+
+// reads one message of a sink, None when the pipe was closed between messages
+async fn read_message(
+    pipe: &mut NamedPipeClient,
+) -> Result<Option<(Frame, Dimensions, Output)>, CaptureError> {
+    let len = match pipe.read_u32_le().await {
+        Ok(len) => len as usize,
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+
+    if !(HEADER_LEN..=MAX_MESSAGE_LEN).contains(&len) {
+        return Err(CaptureError::Other(format!(
+            "the pipe sent a message of {len} bytes, it was not written by a PipeFrameSink"
+        )));
+    }
+
+    let mut header = [0u8; HEADER_LEN];
+    pipe.read_exact(&mut header).await?;
+
+    let mut data = vec![0u8; len - HEADER_LEN];
+    pipe.read_exact(&mut data).await?;
+
+    let u32_at = |at: usize| u32::from_le_bytes(header[at..at + 4].try_into().unwrap());
+    let u64_at = |at: usize| u64::from_le_bytes(header[at..at + 8].try_into().unwrap());
+
+    let mut frame = Frame::new(data, Duration::from_nanos(u64_at(16)));
+    frame.stride = u32_at(12) as usize;
+    frame.presentation_time = u64_at(24) as i64;
+
+    let dimensions = Dimensions {
+        width: u32_at(0),
+        height: u32_at(4),
+    };
+
+    Ok(Some((frame, dimensions, format_from_code(u32_at(8)))))
+}
+
+impl ICapture for PipeFrameSource {
+    type CaptureOutput = Frame;
+
+    /// # Get Dimensions
+    ///
+    /// The size of the newest frame read from the pipe.
+    fn get_dimensions(&self) -> Result<Dimensions, Box<dyn std::error::Error>> {
+        Ok(self.dimensions.read().unwrap().clone())
+    }
+
+    /// # Get Output Format
+    ///
+    /// The format of the newest frame read from the pipe.
+    fn get_output_format(&self) -> Output {
+        *self.format.read().unwrap()
+    }
+
+    /// # Stop Capturing
+    ///
+    /// Stops sending frames once the next frame arrives, frames stay in the pipe until capturing starts again.
+    fn stop_capturing(
+        self: Arc<Self>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>> + Send>> {
+        Box::pin(async move {
+            //the loop ends once it sees the state is not capturing anymore
+            if !self.state.stop() {
+                return Err("already stopped.".into());
+            }
+
+            Ok(())
+        })
+    }
+
+    /// # Start Capturing
+    ///
+    /// Reads frames from the pipe and sends them to the receiver until stop_capturing is called or the sink closes the pipe.
+    fn start_capturing(
+        self: Arc<Self>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>> + Send>> {
+        Box::pin(async move {
+            //the state is stopped again when the loop ends, also with an error
+            let _running = self.state.start().await?;
+
+            if let Some(frame) = self.first_frame.lock().await.take() {
+                self.sender.send(frame).await?;
+            }
+
+            let mut pipe = self.pipe.lock().await;
+
+            loop {
+                if !self.is_capturing() {
+                    break;
+                }
+
+                let Some((frame, dimensions, format)) = read_message(&mut pipe).await? else {
+                    trace_event!(debug, "the pipe was closed by the sink");
+                    break;
+                };
+
+                *self.dimensions.write().unwrap() = dimensions;
+                *self.format.write().unwrap() = format;
+
+                self.sender.send(frame).await?;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// # Clone Receiver
+    ///
+    /// Clones the receiver the frames of the pipe are sent to.
+    fn clone_receiver(&self) -> Arc<Mutex<Receiver<Self::CaptureOutput>>> {
+        self.receiver.clone()
+    }
+
+    fn set_frame_timeout(&self, duration: Duration) {
+        *self.frame_timeout.write().unwrap() = Some(duration);
+    }
+
+    fn clear_frame_timeout(&self) {
+        *self.frame_timeout.write().unwrap() = None;
+    }
+
+    fn get_frame_timeout(&self) -> Option<Duration> {
+        *self.frame_timeout.read().unwrap()
+    }
+
+    fn frame_callback(&self) -> &FrameCallback<Frame> {
+        &self.frame_callback
+    }
+
+    fn state_cell(&self) -> &StateCell {
+        &self.state
+    }
+}
@@ -0,0 +1,106 @@
+use std::path::Path;
+
+use tokio::runtime::{Builder, Handle, RuntimeFlavor};
+
+use crate::{
+    capture_error::CaptureError,
+    devices::{Monitor, PixelFormat},
+    i_capture::ICapture,
+};
+
+/// # Capture Screenshot
+///
+/// Captures a single frame of a monitor and encodes it as a PNG image, the PNG bytes are returned and written to path if one is given.
+///
+/// This blocks until the frame is captured and can be called with or without a tokio runtime, HDR monitors are tone mapped.
+pub fn capture_screenshot(monitor_id: u32, path: Option<&Path>) -> Result<Vec<u8>, CaptureError> {
+    let png = match Handle::try_current() {
+        //the worker is allowed to block while the capture runs on the runtime
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(|| handle.block_on(capture_png(monitor_id)))
+        }
+        //a current thread runtime cannot be blocked, capture on a runtime of another thread
+        Ok(_) => std::thread::scope(|scope| {
+            scope
+                .spawn(|| block_on_new_runtime(monitor_id))
+                .join()
+                .unwrap_or_else(|_| {
+                    Err(CaptureError::Other("the screenshot thread panicked".into()))
+                })
+        }),
+        Err(_) => block_on_new_runtime(monitor_id),
+    }?;
+
+    if let Some(path) = path {
+        std::fs::write(path, &png)?;
+    }
+
+    Ok(png)
+}
+
+// creates a runtime for the current thread and captures the screenshot on it
+fn block_on_new_runtime(monitor_id: u32) -> Result<Vec<u8>, CaptureError> {
+    let runtime = Builder::new_current_thread().enable_all().build()?;
+
+    runtime.block_on(capture_png(monitor_id))
+}
+
+// captures the first frame of the monitor and encodes it
+async fn capture_png(monitor_id: u32) -> Result<Vec<u8>, CaptureError> {
+    let monitor = unsafe { Monitor::from_monitor(monitor_id)? };
+
+    let (captured, frame) = tokio::join!(monitor.clone().start_capturing(), async {
+        let frame = monitor.recv_frame().await;
+        let _ = monitor.clone().stop_capturing().await;
+
+        frame
+    });
+
+    captured?;
+
+    let frame = frame?.ok_or_else(|| {
+        CaptureError::Other("the monitor stopped before a frame was captured".into())
+    })?;
+
+    if monitor.pixel_format() != PixelFormat::Bgra8 {
+        return Err(CaptureError::Encoding(
+            "screenshots can only be taken of 8 bit frames".into(),
+        ));
+    }
+
+    let dimensions = monitor.get_dimensions()?;
+
+    encode_png(&frame.data, dimensions.width, dimensions.height)
+}
+
+// encodes a BGRA frame as an RGB PNG, the frame may be padded so the stride is taken from the buffer
+fn encode_png(data: &[u8], width: u32, height: u32) -> Result<Vec<u8>, CaptureError> {
+    let stride = data.len() / height.max(1) as usize;
+
+    if stride < width as usize * 4 {
+        return Err(CaptureError::Encoding(
+            "the frame is smaller than its dimensions".into(),
+        ));
+    }
+    let mut rgb = Vec::with_capacity(width as usize * height as usize * 3);
+
+    for row in data.chunks_exact(stride).take(height as usize) {
+        for pixel in row[..width as usize * 4].chunks_exact(4) {
+            rgb.extend_from_slice(&[pixel[2], pixel[1], pixel[0]]);
+        }
+    }
+
+    let mut png = vec![];
+    let encoding_error = |e: png::EncodingError| CaptureError::Encoding(e.to_string());
+
+    {
+        let mut encoder = png::Encoder::new(&mut png, width, height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header().map_err(encoding_error)?;
+        writer.write_image_data(&rgb).map_err(encoding_error)?;
+    }
+
+    Ok(png)
+}
@@ -0,0 +1,493 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use windows::{
+    Win32::{
+        Foundation::{
+            CloseHandle, ERROR_ALREADY_EXISTS, GetLastError, HANDLE, INVALID_HANDLE_VALUE,
+            WAIT_ABANDONED, WAIT_OBJECT_0,
+        },
+        System::{
+            Memory::{
+                CreateFileMappingW, FILE_MAP_ALL_ACCESS, FILE_MAP_READ, MEMORY_BASIC_INFORMATION,
+                MEMORY_MAPPED_VIEW_ADDRESS, MapViewOfFile, OpenFileMappingW, PAGE_READWRITE,
+                UnmapViewOfFile, VirtualQuery,
+            },
+            Threading::{
+                CreateMutexW, MUTEX_MODIFY_STATE, OpenMutexW, ReleaseMutex,
+                SYNCHRONIZATION_SYNCHRONIZE, WaitForSingleObject,
+            },
+        },
+    },
+    core::HSTRING,
+};
+
+use crate::{
+    capture_error::CaptureError,
+    devices::{Dimensions, Frame, camera::Output},
+    i_capture::ICapture,
+};
+
+// marks a mapping written by a SharedMemoryOutput, "WVSM"
+const MAGIC: u32 = 0x4D53_5657;
+
+// the layout of the header, raised when it changes
+const VERSION: u32 = 1;
+
+// the frame data starts here, after the header
+const DATA_OFFSET: usize = 128;
+
+// how long a write or read waits for the other side to release the mutex
+const LOCK_TIMEOUT: Duration = Duration::from_secs(1);
+
+// the formats in the order of their code in the header
+const FORMATS: [Output; 6] = [
+    Output::NV12,
+    Output::RGB32,
+    Output::RGB10A2,
+    Output::RGBA16F,
+    Output::YUY2,
+    Output::MJPEG,
+];
+
+// the code of the format in a header, also used by the pipe transport
+pub(crate) fn format_code(format: Output) -> u32 {
+    FORMATS.iter().position(|f| *f == format).unwrap_or(0) as u32
+}
+
+// the format of a code in a header, unknown codes are taken as the last format
+pub(crate) fn format_from_code(code: u32) -> Output {
+    FORMATS[(code as usize).min(FORMATS.len() - 1)]
+}
+
+// the start of the mapping, only read or written while the mutex is held
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Header {
+    magic: u32,
+    version: u32,
+
+    // the bytes available for frame data
+    capacity: u64,
+
+    // raised for every written frame, 0 while no frame was written
+    sequence: u64,
+
+    width: u32,
+    height: u32,
+    format: u32,
+    reserved: u32,
+    stride: u64,
+    len: u64,
+
+    timestamp_nanos: u64,
+    presentation_time: i64,
+}
+
+/// # Mapped Frame
+///
+/// A frame read from shared memory, with the size and format it was written with.
+#[derive(Clone)]
+pub struct MappedFrame {
+    /// The frame, its data, stride and timestamps, change information (dirty rects) is not shared.
+    pub frame: Frame,
+
+    /// The size of the frame.
+    pub dimensions: Dimensions,
+
+    /// How the data of the frame is laid out.
+    pub format: Output,
+
+    /// The number of the frame, starting at 1, a gap means the reader missed frames.
+    pub sequence: u64,
+}
+
+// the mapping and the mutex guarding it, shared by the output and input
+struct Mapping {
+    mapping: HANDLE,
+    mutex: HANDLE,
+    view: MEMORY_MAPPED_VIEW_ADDRESS,
+}
+
+// holds the named mutex, released when dropped
+struct MappingLock<'m> {
+    mapping: &'m Mapping,
+}
+
+impl Mapping {
+    // waits for the named mutex, a mutex abandoned by a crashed process is taken over
+    fn lock(&self) -> Result<MappingLock<'_>, CaptureError> {
+        let wait = unsafe { WaitForSingleObject(self.mutex, LOCK_TIMEOUT.as_millis() as u32) };
+
+        if wait != WAIT_OBJECT_0 && wait != WAIT_ABANDONED {
+            return Err(CaptureError::Timeout(LOCK_TIMEOUT));
+        }
+
+        Ok(MappingLock { mapping: self })
+    }
+
+    fn header(&self) -> *mut Header {
+        self.view.Value as *mut Header
+    }
+
+    fn data(&self) -> *mut u8 {
+        unsafe { (self.view.Value as *mut u8).add(DATA_OFFSET) }
+    }
+}
+
+impl Drop for MappingLock<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = ReleaseMutex(self.mapping.mutex);
+        }
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = UnmapViewOfFile(self.view);
+            let _ = CloseHandle(self.mapping);
+            let _ = CloseHandle(self.mutex);
+        }
+    }
+}
+
+// the name of the mutex of a mapping, mappings and mutexes share a namespace so they cannot have the same name
+fn mutex_name(name: &str) -> HSTRING {
+    HSTRING::from(format!("{name}_mutex"))
+}
+
+/// # Shared Memory Output
+///
+/// Writes frames into a named shared memory object, so another process (such as OBS or a viewer in another language) can read them with a SharedMemoryInput or by opening the mapping itself.
+///
+/// The mapping holds a 128 byte header followed by the newest frame, a named mutex (the name followed by _mutex) is held while either side touches it.
+/// Readers that are slower than the capture only see the newest frame, sequence tells how many were missed.
+///
+/// Names starting with Local\ are visible to the session, Global\ to every session (which needs the SeCreateGlobalPrivilege).
+pub struct SharedMemoryOutput {
+    mapping: Mapping,
+    name: String,
+    capacity: usize,
+
+    frames_written: AtomicU64,
+}
+
+impl SharedMemoryOutput {
+    /// # Create
+    ///
+    /// Creates the shared memory object with room for frames of up to capacity bytes, for example width * height * 4 for RGB32.
+    ///
+    /// Returns CaptureError::Other when an object with the name already exists, such as one of another output that is still open.
+    pub fn create(name: &str, capacity: usize) -> Result<Self, CaptureError> {
+        let size = (DATA_OFFSET + capacity) as u64;
+
+        let mapping = unsafe {
+            let mapping = CreateFileMappingW(
+                INVALID_HANDLE_VALUE,
+                None,
+                PAGE_READWRITE,
+                (size >> 32) as u32,
+                size as u32,
+                &HSTRING::from(name),
+            )?;
+
+            //an existing object is opened with its own size, which may be smaller than the capacity
+            if GetLastError() == ERROR_ALREADY_EXISTS {
+                let _ = CloseHandle(mapping);
+
+                return Err(CaptureError::Other(format!(
+                    "a shared memory object named {name} already exists"
+                )));
+            }
+
+            let mutex = match CreateMutexW(None, false, &mutex_name(name)) {
+                Ok(mutex) => mutex,
+                Err(e) => {
+                    let _ = CloseHandle(mapping);
+                    return Err(e.into());
+                }
+            };
+
+            let view = MapViewOfFile(mapping, FILE_MAP_ALL_ACCESS, 0, 0, 0);
+
+            //the handles are closed on drop from here on
+            let mapping = Mapping {
+                mapping,
+                mutex,
+                view,
+            };
+
+            if view.Value.is_null() {
+                return Err(windows::core::Error::from_thread().into());
+            }
+
+            mapping
+        };
+
+        {
+            let _lock = mapping.lock()?;
+
+            unsafe {
+                mapping.header().write(Header {
+                    magic: MAGIC,
+                    version: VERSION,
+                    capacity: capacity as u64,
+                    sequence: 0,
+                    width: 0,
+                    height: 0,
+                    format: 0,
+                    reserved: 0,
+                    stride: 0,
+                    len: 0,
+                    timestamp_nanos: 0,
+                    presentation_time: 0,
+                });
+            }
+        }
+
+        Ok(Self {
+            mapping,
+            name: name.to_string(),
+            capacity,
+            frames_written: AtomicU64::new(0),
+        })
+    }
+
+    /// # Name
+    ///
+    /// The name another process opens the shared memory with.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// # Capacity
+    ///
+    /// The largest frame in bytes that fits the shared memory.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// # Frames Written
+    ///
+    /// How many frames have been written to the shared memory.
+    pub fn frames_written(&self) -> u64 {
+        self.frames_written.load(Ordering::Relaxed)
+    }
+
+    /// # Write Frame
+    ///
+    /// Replaces the frame in the shared memory, dimensions and format describe the data for the reader.
+    ///
+    /// Returns CaptureError::Other when the frame is larger than the capacity and CaptureError::Timeout when a reader holds the mutex for more than a second.
+    pub fn write_frame(
+        &self,
+        frame: &Frame,
+        dimensions: Dimensions,
+        format: Output,
+    ) -> Result<(), CaptureError> {
+        if frame.data.len() > self.capacity {
+            return Err(CaptureError::Other(format!(
+                "a frame of {} bytes does not fit the {} bytes of the shared memory",
+                frame.data.len(),
+                self.capacity
+            )));
+        }
+
+        let _lock = self.mapping.lock()?;
+
+        unsafe {
+            let header = &mut *self.mapping.header();
+
+            std::ptr::copy_nonoverlapping(
+                frame.data.as_ptr(),
+                self.mapping.data(),
+                frame.data.len(),
+            );
+
+            header.sequence += 1;
+            header.width = dimensions.width;
+            header.height = dimensions.height;
+            header.format = format_code(format);
+            header.stride = frame.stride as u64;
+            header.len = frame.data.len() as u64;
+            header.timestamp_nanos = frame.timestamp.as_nanos() as u64;
+            header.presentation_time = frame.presentation_time;
+        }
+
+        self.frames_written.fetch_add(1, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// # Run
+    ///
+    /// Writes every frame of the capture until its channel closes, start the capture yourself (for example with start_session).
+    ///
+    /// The size and format are read from the capture for every frame, so a monitor that is resized keeps being described correctly.
+    pub async fn run<S: ICapture<CaptureOutput = Frame>>(
+        &self,
+        source: &S,
+    ) -> Result<(), CaptureError> {
+        let receiver = source.clone_receiver();
+        let mut receiver = receiver.lock().await;
+
+        while let Some(frame) = receiver.recv().await {
+            let dimensions = source.get_dimensions().map_err(CaptureError::from)?;
+
+            self.write_frame(&frame, dimensions, source.get_output_format())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// # Shared Memory Input
+///
+/// Reads the frames a SharedMemoryOutput writes, usually from another process.
+pub struct SharedMemoryInput {
+    mapping: Mapping,
+
+    // the bytes of the view, every read stays within them whatever the header claims
+    view_size: usize,
+
+    // the sequence of the last frame returned by read_frame
+    last_sequence: AtomicU64,
+}
+
+impl SharedMemoryInput {
+    /// # Open
+    ///
+    /// Opens the shared memory of a SharedMemoryOutput by its name.
+    ///
+    /// Returns CaptureError::UnsupportedFormat when the shared memory was not written by a SharedMemoryOutput of this version.
+    pub fn open(name: &str) -> Result<Self, CaptureError> {
+        let mapping = unsafe {
+            let mapping = OpenFileMappingW(FILE_MAP_READ.0, false, &HSTRING::from(name))?;
+
+            let mutex = match OpenMutexW(
+                SYNCHRONIZATION_SYNCHRONIZE | MUTEX_MODIFY_STATE,
+                false,
+                &mutex_name(name),
+            ) {
+                Ok(mutex) => mutex,
+                Err(e) => {
+                    let _ = CloseHandle(mapping);
+                    return Err(e.into());
+                }
+            };
+
+            let view = MapViewOfFile(mapping, FILE_MAP_READ, 0, 0, 0);
+
+            let mapping = Mapping {
+                mapping,
+                mutex,
+                view,
+            };
+
+            if view.Value.is_null() {
+                return Err(windows::core::Error::from_thread().into());
+            }
+
+            mapping
+        };
+
+        let view_size = unsafe {
+            let mut info = MEMORY_BASIC_INFORMATION::default();
+
+            let queried = VirtualQuery(
+                Some(mapping.view.Value as *const _),
+                &mut info,
+                size_of::<MEMORY_BASIC_INFORMATION>(),
+            );
+
+            if queried == 0 {
+                return Err(windows::core::Error::from_thread().into());
+            }
+
+            info.RegionSize
+        };
+
+        if view_size < DATA_OFFSET {
+            return Err(CaptureError::UnsupportedFormat(format!(
+                "{name} is too small for a SharedMemoryOutput"
+            )));
+        }
+
+        let header = {
+            let _lock = mapping.lock()?;
+            unsafe { mapping.header().read() }
+        };
+
+        if header.magic != MAGIC || header.version != VERSION {
+            return Err(CaptureError::UnsupportedFormat(format!(
+                "{name} was not written by a version {VERSION} SharedMemoryOutput"
+            )));
+        }
+
+        Ok(Self {
+            mapping,
+            view_size,
+            last_sequence: AtomicU64::new(0),
+        })
+    }
+
+    /// # Read Frame
+    ///
+    /// Copies the newest frame out of the shared memory, None when no frame was written since the last call.
+    ///
+    /// This does not wait for a frame, poll it at the rate frames are needed (such as once per rendered frame).
+    ///
+    /// Returns CaptureError::Other when the header claims a frame larger than the shared memory, nothing is read then.
+    pub fn read_frame(&self) -> Result<Option<MappedFrame>, CaptureError> {
+        let _lock = self.mapping.lock()?;
+
+        let header = unsafe { self.mapping.header().read() };
+
+        if header.sequence == self.last_sequence.load(Ordering::Relaxed) {
+            return Ok(None);
+        }
+
+        //the header is written by another process, a length beyond the view would read out of bounds
+        let available = (self.view_size - DATA_OFFSET) as u64;
+
+        if header.len > header.capacity.min(available) {
+            return Err(CaptureError::Other(format!(
+                "the shared memory claims a frame of {} bytes, it only holds {}",
+                header.len,
+                header.capacity.min(available)
+            )));
+        }
+
+        let len = header.len as usize;
+        let data =
+            unsafe { std::slice::from_raw_parts(self.mapping.data() as *const u8, len).to_vec() };
+
+        self.last_sequence.store(header.sequence, Ordering::Relaxed);
+
+        let mut frame = Frame::new(data, Duration::from_nanos(header.timestamp_nanos));
+        frame.stride = header.stride as usize;
+        frame.presentation_time = header.presentation_time;
+
+        Ok(Some(MappedFrame {
+            frame,
+            dimensions: Dimensions {
+                width: header.width,
+                height: header.height,
+            },
+            format: format_from_code(header.format),
+            sequence: header.sequence,
+        }))
+    }
+}
+
+unsafe impl Send for SharedMemoryOutput {}
+
+unsafe impl Sync for SharedMemoryOutput {}
+
+unsafe impl Send for SharedMemoryInput {}
+
+unsafe impl Sync for SharedMemoryInput {}
@@ -0,0 +1,22 @@
+//! Internal instrumentation macros.
+//!
+//! These forward to the tracing crate when the "tracing" feature is enabled and expand to nothing otherwise,
+//! so there is zero overhead when the feature is off.
+
+// emits a tracing event at the given level, for example trace_event!(debug, hresult = %code, "message")
+macro_rules! trace_event {
+    ($level:ident, $($arg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($arg)+);
+    };
+}
+
+// enters a span that lasts until the end of the current block.
+//
+// The span guard is not Send, only use this in synchronous code.
+macro_rules! trace_span {
+    ($level:ident, $($arg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::span!(tracing::Level::$level, $($arg)+).entered();
+    };
+}
@@ -0,0 +1,362 @@
+pub mod overlay;
+pub mod rotate;
+pub mod rotated_capture;
+pub mod scaled_capture;
+pub mod thumbnail;
+pub mod watermark_capture;
+
+pub use crate::transform::overlay::composite_overlay;
+pub use crate::transform::rotate::{FrameRotation, rotate_frame};
+pub use crate::transform::rotated_capture::RotatedCapture;
+pub use crate::transform::scaled_capture::{Scale, ScaledCapture};
+pub use crate::transform::thumbnail::{Thumbnail, generate_thumbnail};
+pub use crate::transform::watermark_capture::{Watermark, WatermarkCapture};
+
+use crate::{capture_error::CaptureError, convert::check_size, devices::camera::Output};
+
+// the fractional bits of the fixed point filter weights
+const PRECISION_BITS: u32 = 14;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+/// # Scale Filter
+///
+/// How scale_frame computes the pixels of the scaled frame.
+pub enum ScaleFilter {
+    /// The nearest source pixel, the fastest but blocky when enlarging and aliased when shrinking.
+    Nearest,
+
+    /// Linear interpolation of the two neighbouring pixels in each direction, fast at any scale.
+    ///
+    /// When shrinking by more than half, source pixels between the two neighbours are left out, Lanczos3 takes every one of them.
+    #[default]
+    Bilinear,
+
+    /// A windowed sinc over three pixels on each side, the sharpest and the slowest.
+    Lanczos3,
+}
+
+impl ScaleFilter {
+    // how many source pixels the filter reaches on each side, at a scale of 1, nearest takes a single pixel
+    fn support(&self) -> f64 {
+        match self {
+            ScaleFilter::Nearest => 0.0,
+            ScaleFilter::Bilinear => 1.0,
+            ScaleFilter::Lanczos3 => 3.0,
+        }
+    }
+
+    fn weight(&self, x: f64) -> f64 {
+        let x = x.abs();
+
+        match self {
+            ScaleFilter::Nearest => 1.0,
+            ScaleFilter::Bilinear => (1.0 - x).max(0.0),
+            ScaleFilter::Lanczos3 if x < 3.0 => sinc(x) * sinc(x / 3.0),
+            ScaleFilter::Lanczos3 => 0.0,
+        }
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    } else {
+        let x = x * std::f64::consts::PI;
+        x.sin() / x
+    }
+}
+
+/// # Scale Frame
+///
+/// Resizes a tightly packed frame to the new size, for encoders or previews that need another resolution than the capture delivers.
+///
+/// RGB32 (BGRA) scales all four channels, NV12 scales the Y plane and the interleaved UV plane separately, so the result is NV12 again.
+/// Bilinear and Lanczos3 scale the height and then the width with fixed point weights computed once per frame, shrinking widens Lanczos3
+/// so every source pixel counts.
+///
+/// Returns CaptureError::UnsupportedFormat for the other formats and CaptureError::Other when a size is 0 or the data is too short for the source size.
+pub fn scale_frame(
+    src: &[u8],
+    src_width: u32,
+    src_height: u32,
+    dst_width: u32,
+    dst_height: u32,
+    format: Output,
+    filter: ScaleFilter,
+) -> Result<Vec<u8>, CaptureError> {
+    if !matches!(format, Output::RGB32 | Output::NV12) {
+        return Err(CaptureError::UnsupportedFormat(format!(
+            "scaling {format:?} frames"
+        )));
+    }
+
+    if src_width == 0 || src_height == 0 || dst_width == 0 || dst_height == 0 {
+        return Err(CaptureError::Other(format!(
+            "cannot scale a {src_width}x{src_height} frame to {dst_width}x{dst_height}"
+        )));
+    }
+
+    check_size(format, src, src_width, src_height)?;
+
+    let (src_width, src_height) = (src_width as usize, src_height as usize);
+    let (dst_width, dst_height) = (dst_width as usize, dst_height as usize);
+
+    if format == Output::RGB32 {
+        return Ok(resample::<4>(
+            &src[..src_width * src_height * 4],
+            (src_width, src_height),
+            (dst_width, dst_height),
+            filter,
+        ));
+    }
+
+    //the UV plane holds a pair of chroma samples for every 2x2 block, odd sizes round up
+    let luma = src_width * src_height;
+    let chroma_size = |width: usize, height: usize| (width.div_ceil(2), height.div_ceil(2));
+
+    let mut scaled = resample::<1>(
+        &src[..luma],
+        (src_width, src_height),
+        (dst_width, dst_height),
+        filter,
+    );
+
+    let (chroma_width, chroma_height) = chroma_size(src_width, src_height);
+
+    scaled.extend(resample::<2>(
+        &src[luma..luma + chroma_width * chroma_height * 2],
+        (chroma_width, chroma_height),
+        chroma_size(dst_width, dst_height),
+        filter,
+    ));
+
+    Ok(scaled)
+}
+
+// the first source pixel and the fixed point weights of the source pixels from it for every pixel of the destination,
+// every pixel has the same number of taps so the weights are a single slice
+struct Taps {
+    starts: Vec<usize>,
+    weights: Vec<i32>,
+    len: usize,
+
+    // the weights in 1/256 for filters of two taps that are never negative (bilinear), summed in 16 bits which vectorizes a lot better
+    linear: Vec<u16>,
+}
+
+impl Taps {
+    fn new(src_len: usize, dst_len: usize, filter: ScaleFilter) -> Self {
+        let ratio = src_len as f64 / dst_len as f64;
+
+        //when shrinking lanczos covers every source pixel under a destination pixel, bilinear keeps its two taps
+        let scale = match filter {
+            ScaleFilter::Lanczos3 => ratio.max(1.0),
+            _ => 1.0,
+        };
+        let support = filter.support() * scale;
+
+        //the weights of every pixel without the taps at the edges of the filter that get none
+        let pixels: Vec<(usize, Vec<f64>)> = (0..dst_len)
+            .map(|i| {
+                let center = (i as f64 + 0.5) * ratio;
+
+                let first = ((center - support).floor().max(0.0) as usize).min(src_len - 1);
+                let last = ((center + support).ceil() as usize).clamp(first + 1, src_len);
+
+                let mut taps: Vec<f64> = (first..last)
+                    .map(|j| filter.weight((j as f64 + 0.5 - center) / scale))
+                    .collect();
+
+                let skipped = taps
+                    .iter()
+                    .take_while(|tap| **tap == 0.0)
+                    .count()
+                    .min(taps.len() - 1);
+                taps.drain(..skipped);
+
+                while taps.len() > 1 && taps.last() == Some(&0.0) {
+                    taps.pop();
+                }
+
+                (first + skipped, taps)
+            })
+            .collect();
+
+        //nearest only reaches the pixel under the center
+        let len = pixels
+            .iter()
+            .map(|(_, taps)| taps.len())
+            .max()
+            .unwrap_or(1)
+            .min(src_len);
+
+        let mut starts = Vec::with_capacity(dst_len);
+        let mut weights = Vec::with_capacity(dst_len * len);
+
+        for (first, taps) in pixels {
+            //the taps are moved back at the end of the source, the pixels past the filter get no weight
+            let start = first.min(src_len - len);
+            let total: f64 = taps.iter().sum();
+
+            starts.push(start);
+            weights.extend((start..start + len).map(|j| {
+                match j.checked_sub(first).and_then(|tap| taps.get(tap)) {
+                    Some(tap) => (tap / total * (1 << PRECISION_BITS) as f64).round() as i32,
+                    None => 0,
+                }
+            }));
+        }
+
+        //the weights of a pixel still add up to 256
+        let linear = if len == 2 && weights.iter().all(|weight| *weight >= 0) {
+            weights
+                .chunks_exact(2)
+                .map(|pair| {
+                    ((pair[0] + (1 << (PRECISION_BITS - 9))) >> (PRECISION_BITS - 8)).min(256)
+                        as u16
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        Self {
+            starts,
+            weights,
+            len,
+            linear,
+        }
+    }
+
+    // the first source pixel and the weights of a destination pixel
+    fn get(&self, i: usize) -> (usize, &[i32]) {
+        (
+            self.starts[i],
+            &self.weights[i * self.len..(i + 1) * self.len],
+        )
+    }
+}
+
+// the rows of a frame without the padding of the stride, the planes of NV12 share the stride
+pub(crate) fn unpad(data: Vec<u8>, stride: usize, row_len: usize) -> Vec<u8> {
+    if stride <= row_len || row_len == 0 {
+        return data;
+    }
+
+    data.chunks(stride)
+        .flat_map(|row| &row[..row_len.min(row.len())])
+        .copied()
+        .collect()
+}
+
+// rounds a fixed point sum back to a channel
+fn to_channel(sum: i32) -> u8 {
+    ((sum + (1 << (PRECISION_BITS - 1))) >> PRECISION_BITS).clamp(0, 255) as u8
+}
+
+// scales a plane of C interleaved 8 bit channels, first the rows, then the columns
+fn resample<const C: usize>(
+    src: &[u8],
+    (src_width, src_height): (usize, usize),
+    (dst_width, dst_height): (usize, usize),
+    filter: ScaleFilter,
+) -> Vec<u8> {
+    let columns = Taps::new(src_width, dst_width, filter);
+    let rows = Taps::new(src_height, dst_height, filter);
+
+    let src_row_len = src_width * C;
+    let row_len = dst_width * C;
+
+    let mut scaled = vec![0u8; row_len * dst_height];
+
+    //nearest copies the single pixel of each tap
+    if filter == ScaleFilter::Nearest {
+        for (scaled_row, &y) in scaled.chunks_exact_mut(row_len).zip(&rows.starts) {
+            let (src_row, _) = src[y * src_row_len..(y + 1) * src_row_len].as_chunks::<C>();
+            let (scaled_row, _) = scaled_row.as_chunks_mut::<C>();
+
+            for (pixel, &x) in scaled_row.iter_mut().zip(&columns.starts) {
+                *pixel = src_row[x];
+            }
+        }
+
+        return scaled;
+    }
+
+    //every row is scaled in height first, summing whole rows vectorizes well and leaves the narrower horizontal pass for the end
+    let mut tall = vec![0u8; src_row_len];
+    let mut sum = vec![0i32; src_row_len];
+
+    for (y, scaled_row) in scaled.chunks_exact_mut(row_len).enumerate() {
+        let (start, weights) = rows.get(y);
+        let src_row =
+            |row: usize| &src[(start + row) * src_row_len..(start + row + 1) * src_row_len];
+
+        if let Some(&first) = rows.linear.get(y) {
+            let second = 256 - first;
+            for ((value, a), b) in tall.iter_mut().zip(src_row(0)).zip(src_row(1)) {
+                *value = linear(*a, *b, first, second);
+            }
+        } else {
+            sum.fill(0);
+
+            for (row, &weight) in weights.iter().enumerate() {
+                if weight == 0 {
+                    continue;
+                }
+
+                for (value, source) in sum.iter_mut().zip(src_row(row)) {
+                    *value += *source as i32 * weight;
+                }
+            }
+
+            for (value, sum) in tall.iter_mut().zip(&sum) {
+                *value = to_channel(*sum);
+            }
+        }
+
+        let (tall_row, _) = tall.as_chunks::<C>();
+        let (scaled_row, _) = scaled_row.as_chunks_mut::<C>();
+
+        if !columns.linear.is_empty() {
+            for ((pixel, &x), &first) in scaled_row
+                .iter_mut()
+                .zip(&columns.starts)
+                .zip(&columns.linear)
+            {
+                let taps = &tall_row[x..x + 2];
+                let second = 256 - first;
+
+                for c in 0..C {
+                    pixel[c] = linear(taps[0][c], taps[1][c], first, second);
+                }
+            }
+
+            continue;
+        }
+
+        for (x, pixel) in scaled_row.iter_mut().enumerate() {
+            let (start, weights) = columns.get(x);
+
+            let mut sum = [0i32; C];
+
+            for (source, &weight) in tall_row[start..start + weights.len()].iter().zip(weights) {
+                for c in 0..C {
+                    sum[c] += source[c] as i32 * weight;
+                }
+            }
+
+            for c in 0..C {
+                pixel[c] = to_channel(sum[c]);
+            }
+        }
+    }
+
+    scaled
+}
+
+// blends two channels with weights in 1/256 that add up to 256
+#[inline]
+fn linear(a: u8, b: u8, first: u16, second: u16) -> u8 {
+    ((a as u16 * first + b as u16 * second + 128) >> 8) as u8
+}
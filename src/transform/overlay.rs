@@ -0,0 +1,136 @@
+use crate::{capture_error::CaptureError, convert::check_size, devices::camera::Output};
+
+/// # Composite Overlay
+///
+/// Alpha blends a tightly packed overlay onto a tightly packed frame of the same format with the top left corner of the overlay at x, y,
+/// for a logo or a timestamp stamped onto captured frames.
+///
+/// RGB32 (BGRA) overlays are blended with their own alpha channel times alpha, the alpha channel of the frame is kept. NV12 has no alpha channel,
+/// the overlay is blended with alpha alone, and as a 2x2 block of pixels shares its chroma x and y are rounded down to even positions.
+///
+/// alpha is clamped to 0.0..=1.0. The parts of the overlay beyond the frame are cut off, an overlay placed outside of the frame leaves it as it is.
+///
+/// Returns CaptureError::UnsupportedFormat for the other formats and CaptureError::Other when the frame or the overlay is too short for its size.
+#[allow(clippy::too_many_arguments)]
+pub fn composite_overlay(
+    base: &mut [u8],
+    base_width: u32,
+    base_height: u32,
+    overlay: &[u8],
+    overlay_width: u32,
+    overlay_height: u32,
+    x: u32,
+    y: u32,
+    alpha: f32,
+    format: Output,
+) -> Result<(), CaptureError> {
+    if !matches!(format, Output::RGB32 | Output::NV12) {
+        return Err(CaptureError::UnsupportedFormat(format!(
+            "compositing {format:?} frames"
+        )));
+    }
+
+    check_size(format, base, base_width, base_height)?;
+    check_size(format, overlay, overlay_width, overlay_height)?;
+
+    //the weight of the overlay in 1/256, 256 covers the frame
+    let weight = (alpha.clamp(0.0, 1.0) * 256.0).round() as u32;
+
+    if weight == 0 {
+        return Ok(());
+    }
+
+    let base_size = (base_width as usize, base_height as usize);
+    let overlay_size = (overlay_width as usize, overlay_height as usize);
+
+    if format == Output::RGB32 {
+        blend_plane::<4>(
+            base,
+            base_size,
+            overlay,
+            overlay_size,
+            (x as usize, y as usize),
+            weight,
+            true,
+        );
+
+        return Ok(());
+    }
+
+    let (x, y) = ((x & !1) as usize, (y & !1) as usize);
+
+    let base_luma = base_size.0 * base_size.1;
+    let overlay_luma = overlay_size.0 * overlay_size.1;
+
+    //the UV plane holds a pair of chroma samples for every 2x2 block, odd sizes round up
+    let chroma_size = |(width, height): (usize, usize)| (width.div_ceil(2), height.div_ceil(2));
+
+    let (base_luma_plane, base_chroma_plane) = base.split_at_mut(base_luma);
+
+    blend_plane::<1>(
+        base_luma_plane,
+        base_size,
+        &overlay[..overlay_luma],
+        overlay_size,
+        (x, y),
+        weight,
+        false,
+    );
+
+    blend_plane::<2>(
+        base_chroma_plane,
+        chroma_size(base_size),
+        &overlay[overlay_luma..],
+        chroma_size(overlay_size),
+        (x / 2, y / 2),
+        weight,
+        false,
+    );
+
+    Ok(())
+}
+
+// blends the part of an overlay plane that fits onto a plane of the frame, the sizes are in pixels of C bytes.
+//
+// with alpha_channel the last byte of a pixel weighs the pixel and is left as it is in the frame.
+fn blend_plane<const C: usize>(
+    base: &mut [u8],
+    (base_width, base_height): (usize, usize),
+    overlay: &[u8],
+    (overlay_width, overlay_height): (usize, usize),
+    (x, y): (usize, usize),
+    weight: u32,
+    alpha_channel: bool,
+) {
+    let width = overlay_width.min(base_width.saturating_sub(x));
+    let height = overlay_height.min(base_height.saturating_sub(y));
+    let channels = if alpha_channel { C - 1 } else { C };
+
+    if width == 0 || height == 0 {
+        return;
+    }
+
+    for row in 0..height {
+        let from = row * overlay_width * C;
+        let to = ((y + row) * base_width + x) * C;
+
+        let overlay_row = &overlay[from..from + width * C];
+        let base_row = &mut base[to..to + width * C];
+
+        for (pixel, over) in base_row
+            .chunks_exact_mut(C)
+            .zip(overlay_row.chunks_exact(C))
+        {
+            let weight = if alpha_channel {
+                (over[C - 1] as u32 * weight + 127) / 255
+            } else {
+                weight
+            };
+
+            for (value, over) in pixel[..channels].iter_mut().zip(&over[..channels]) {
+                *value =
+                    ((*over as u32 * weight + *value as u32 * (256 - weight) + 128) >> 8) as u8;
+            }
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use crate::{
+    capture_error::CaptureError,
+    convert::check_size,
+    devices::{FrameTransform, Rect, Rotation, camera::Output},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// # Frame Rotation
+///
+/// How rotate_frame turns or flips a frame.
+pub enum FrameRotation {
+    /// A quarter turn clockwise, the width and height are swapped.
+    Rotate90CW,
+
+    /// A quarter turn counterclockwise, the width and height are swapped.
+    Rotate90CCW,
+
+    Rotate180,
+
+    /// Mirrors the frame, the left column becomes the right column.
+    FlipHorizontal,
+
+    /// Turns the frame upside down, the top row becomes the bottom row.
+    FlipVertical,
+}
+
+impl FrameRotation {
+    /// # Swaps Dimensions
+    ///
+    /// If the width and height of the frame are swapped.
+    pub fn swaps_dimensions(&self) -> bool {
+        matches!(self, FrameRotation::Rotate90CW | FrameRotation::Rotate90CCW)
+    }
+
+    // the clockwise rotation of the turns, nothing for the flips
+    fn rotation(&self) -> Rotation {
+        match self {
+            FrameRotation::Rotate90CW => Rotation::Rotate90,
+            FrameRotation::Rotate90CCW => Rotation::Rotate270,
+            FrameRotation::Rotate180 => Rotation::Rotate180,
+            FrameRotation::FlipHorizontal | FrameRotation::FlipVertical => Rotation::Identity,
+        }
+    }
+
+    // maps a rect of the frame onto the transformed frame
+    pub(crate) fn map_rect(&self, rect: Rect, width: u32, height: u32) -> Rect {
+        match self {
+            FrameRotation::FlipHorizontal => Rect {
+                left: width.saturating_sub(rect.right),
+                right: width.saturating_sub(rect.left),
+                ..rect
+            },
+            FrameRotation::FlipVertical => Rect {
+                top: height.saturating_sub(rect.bottom),
+                bottom: height.saturating_sub(rect.top),
+                ..rect
+            },
+            _ => Rect::from_tuple(self.rotation().rotate_rect(
+                rect.to_tuple(),
+                width as usize,
+                height as usize,
+            )),
+        }
+    }
+}
+
+/// # Rotate Frame
+///
+/// Turns or flips a tightly packed frame, returned with its new width and height (swapped by the quarter turns).
+///
+/// Only formats with whole pixels can be turned, RGB32, RGB10A2 and RGBA16F. The flips are bound by memory bandwidth, on x86_64
+/// 4 byte pixels are mirrored with SSE2, four at a time.
+///
+/// Returns CaptureError::UnsupportedFormat for the other formats and CaptureError::Other when the data is too short for the size.
+pub fn rotate_frame(
+    src: &[u8],
+    width: u32,
+    height: u32,
+    format: Output,
+    rotation: FrameRotation,
+) -> Result<(Vec<u8>, u32, u32), CaptureError> {
+    if !FrameTransform::supports(format) {
+        return Err(CaptureError::UnsupportedFormat(format!(
+            "turning {format:?} frames"
+        )));
+    }
+
+    check_size(format, src, width, height)?;
+
+    let bytes_per_pixel = format.stride(1);
+    let data = &src[..format.frame_size(width, height)];
+    let (w, h) = (width as usize, height as usize);
+
+    let rotated = match rotation {
+        FrameRotation::FlipHorizontal => reflect(data, w, h, bytes_per_pixel, true, false),
+        FrameRotation::FlipVertical => reflect(data, w, h, bytes_per_pixel, false, true),
+        _ => rotation
+            .rotation()
+            .rotate_pixels(data, w, h, bytes_per_pixel)
+            .unwrap_or_else(|| data.to_vec()),
+    };
+
+    if rotation.swaps_dimensions() {
+        Ok((rotated, height, width))
+    } else {
+        Ok((rotated, width, height))
+    }
+}
+
+// mirrors and flips a tightly packed surface in a single copy
+pub(crate) fn reflect(
+    data: &[u8],
+    width: usize,
+    height: usize,
+    bytes_per_pixel: usize,
+    mirror: bool,
+    flip: bool,
+) -> Vec<u8> {
+    let row_len = width * bytes_per_pixel;
+    let mut out = vec![0u8; row_len * height];
+
+    for (y, out_row) in out.chunks_exact_mut(row_len.max(1)).enumerate() {
+        let source_y = if flip { height - 1 - y } else { y };
+        let row = &data[source_y * row_len..(source_y + 1) * row_len];
+
+        if !mirror {
+            out_row.copy_from_slice(row);
+        } else if bytes_per_pixel == 4 {
+            mirror_row_32(row, out_row);
+        } else {
+            for (out_pixel, pixel) in out_row
+                .chunks_exact_mut(bytes_per_pixel)
+                .zip(row.chunks_exact(bytes_per_pixel).rev())
+            {
+                out_pixel.copy_from_slice(pixel);
+            }
+        }
+    }
+
+    out
+}
+
+// reverses the 4 byte pixels of a row, both rows are the same length
+fn mirror_row_32(row: &[u8], out: &mut [u8]) {
+    let pixels = row.len() / 4;
+
+    #[cfg(target_arch = "x86_64")]
+    let done = {
+        use std::arch::x86_64::{__m128i, _mm_loadu_si128, _mm_shuffle_epi32, _mm_storeu_si128};
+
+        let blocks = pixels / 4;
+
+        for block in 0..blocks {
+            //four pixels from the front of the row are stored reversed at the back
+            let from = &row[block * 16..block * 16 + 16];
+            let to = (pixels - (block + 1) * 4) * 4;
+            let to = &mut out[to..to + 16];
+
+            //SSE2 is part of every x86_64 cpu, both slices are 16 bytes long
+            unsafe {
+                let four = _mm_loadu_si128(from.as_ptr() as *const __m128i);
+                _mm_storeu_si128(
+                    to.as_mut_ptr() as *mut __m128i,
+                    _mm_shuffle_epi32(four, 0x1B),
+                );
+            }
+        }
+
+        blocks * 4
+    };
+
+    #[cfg(not(target_arch = "x86_64"))]
+    let done = 0;
+
+    for x in done..pixels {
+        let to = (pixels - 1 - x) * 4;
+        out[to..to + 4].copy_from_slice(&row[x * 4..x * 4 + 4]);
+    }
+}
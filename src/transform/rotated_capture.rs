@@ -0,0 +1,93 @@
+use std::sync::Arc;
+
+use crate::{
+    capture_error::CaptureError,
+    devices::{Dimensions, Frame, FrameTransform, camera::Output},
+    i_capture::ICapture,
+    map_capture::{FrameMap, MapCapture},
+    transform::{FrameRotation, rotate_frame},
+};
+
+/// # Rotated Capture
+///
+/// Wraps any capture that delivers RGB32, RGB10A2 or RGBA16F frames and turns or flips every frame with rotate_frame.
+///
+/// ```rs
+/// let rotated = RotatedCapture::new(camera, FrameRotation::Rotate90CW);
+///
+/// tokio::spawn(rotated.clone().start_capturing());
+///
+/// while let Some(frame) = rotated.recv_frame().await? {
+///     encoder.send(frame).await?;
+/// }
+/// ```
+///
+/// Cameras transform their frames in the capture loop with Camera::set_transform, which saves the extra channel.
+/// Dirty rects are mapped onto the turned frame, move rects are dropped.
+pub type RotatedCapture<S> = MapCapture<S, FrameRotation>;
+
+impl FrameMap for FrameRotation {
+    type Output = Frame;
+
+    // turns a frame of the source, None when it does not match the size of the source
+    fn map(&self, mut frame: Frame, source: &Dimensions, format: Output) -> Option<Frame> {
+        let Dimensions { width, height } = *source;
+
+        let (data, rotated_width, _) = rotate_frame(&frame.data, width, height, format, *self)
+            .inspect_err(|_e| {
+                trace_event!(warn, error = %_e, "skipping a frame that could not be turned");
+            })
+            .ok()?;
+
+        frame.data = data;
+        frame.stride = format.stride(rotated_width);
+        frame.move_rects.clear();
+
+        for rect in &mut frame.dirty_rects {
+            *rect = self.map_rect(*rect, width, height);
+        }
+
+        Some(frame)
+    }
+
+    // the dimensions of the source, with the width and height swapped by the quarter turns
+    fn dimensions(&self, source: Dimensions) -> Dimensions {
+        if self.swaps_dimensions() {
+            Dimensions {
+                width: source.height,
+                height: source.width,
+            }
+        } else {
+            source
+        }
+    }
+
+    // the source must deliver tightly packed RGB32, RGB10A2 or RGBA16F frames
+    fn check(&self, format: Output) -> Result<(), CaptureError> {
+        if !FrameTransform::supports(format) {
+            return Err(CaptureError::UnsupportedFormat(format!(
+                "RotatedCapture cannot turn {format:?} frames"
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+impl<S: ICapture<CaptureOutput = Frame> + ?Sized + 'static> MapCapture<S, FrameRotation> {
+    /// # New
+    ///
+    /// Wraps the source, frames are turned or flipped as the rotation says.
+    ///
+    /// Capturing is started and stopped through the wrapper, do not start the source yourself.
+    pub fn new(source: Arc<S>, rotation: FrameRotation) -> Arc<Self> {
+        Self::with_map(source, rotation)
+    }
+
+    /// # Rotation
+    ///
+    /// How the frames are turned or flipped.
+    pub fn rotation(&self) -> FrameRotation {
+        *self.frame_map()
+    }
+}
@@ -0,0 +1,134 @@
+use std::sync::Arc;
+
+use crate::{
+    capture_error::CaptureError,
+    devices::{Dimensions, Frame, Rect, camera::Output},
+    i_capture::ICapture,
+    map_capture::{FrameMap, MapCapture},
+    transform::{ScaleFilter, scale_frame},
+};
+
+/// # Scaled Capture
+///
+/// Wraps any capture that delivers RGB32 or NV12 frames and scales every frame to a fixed size with scale_frame.
+///
+/// ```rs
+/// let scaled = ScaledCapture::new(monitor, 1280, 720, ScaleFilter::Bilinear);
+///
+/// tokio::spawn(scaled.clone().start_capturing());
+///
+/// while let Some(frame) = scaled.recv_frame().await? {
+///     encoder.send(frame).await?;
+/// }
+/// ```
+///
+/// The frames are scaled on the CPU, monitors scale on the GPU with Monitor::set_output_size which is much cheaper.
+/// Dirty rects are scaled with the frame, move rects are dropped since they no longer line up with pixels.
+pub type ScaledCapture<S> = MapCapture<S, Scale>;
+
+#[derive(Clone, PartialEq)]
+/// # Scale
+///
+/// The FrameMap of ScaledCapture, scales every frame to a fixed size.
+pub struct Scale {
+    /// The size of the scaled frames.
+    pub size: Dimensions,
+
+    /// How the scaled pixels are computed.
+    pub filter: ScaleFilter,
+}
+
+impl FrameMap for Scale {
+    type Output = Frame;
+
+    // scales a frame of the source, None when it does not match the size of the source
+    fn map(&self, mut frame: Frame, source: &Dimensions, format: Output) -> Option<Frame> {
+        let data = scale_frame(
+            &frame.data,
+            source.width,
+            source.height,
+            self.size.width,
+            self.size.height,
+            format,
+            self.filter,
+        )
+        .inspect_err(|_e| {
+            trace_event!(warn, error = %_e, "skipping a frame that could not be scaled");
+        })
+        .ok()?;
+
+        //rounded outwards so the scaled rects still cover every changed pixel
+        let scale = |value: u32, to: u32, from: u32, round_up: bool| {
+            let scaled = value as u64 * to as u64;
+
+            let scaled = if round_up {
+                scaled.div_ceil(from as u64)
+            } else {
+                scaled / from as u64
+            };
+
+            (scaled as u32).min(to)
+        };
+
+        frame.data = data;
+        frame.stride = format.stride(self.size.width);
+        frame.move_rects.clear();
+
+        for rect in &mut frame.dirty_rects {
+            *rect = Rect {
+                left: scale(rect.left, self.size.width, source.width, false),
+                top: scale(rect.top, self.size.height, source.height, false),
+                right: scale(rect.right, self.size.width, source.width, true),
+                bottom: scale(rect.bottom, self.size.height, source.height, true),
+            };
+        }
+
+        Some(frame)
+    }
+
+    // the size frames are scaled to
+    fn dimensions(&self, _source: Dimensions) -> Dimensions {
+        self.size.clone()
+    }
+
+    // the source must deliver tightly packed RGB32 or NV12 frames
+    fn check(&self, format: Output) -> Result<(), CaptureError> {
+        if !matches!(format, Output::RGB32 | Output::NV12) {
+            return Err(CaptureError::UnsupportedFormat(format!(
+                "ScaledCapture cannot scale {format:?} frames"
+            )));
+        }
+
+        if self.size.width == 0 || self.size.height == 0 {
+            return Err(CaptureError::Other(
+                "ScaledCapture needs a width and height above 0".into(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl<S: ICapture<CaptureOutput = Frame> + ?Sized + 'static> MapCapture<S, Scale> {
+    /// # New
+    ///
+    /// Wraps the source, frames are scaled to width by height with the filter.
+    ///
+    /// Capturing is started and stopped through the wrapper, do not start the source yourself.
+    pub fn new(source: Arc<S>, width: u32, height: u32, filter: ScaleFilter) -> Arc<Self> {
+        Self::with_map(
+            source,
+            Scale {
+                size: Dimensions { width, height },
+                filter,
+            },
+        )
+    }
+
+    /// # Filter
+    ///
+    /// How the scaled pixels are computed.
+    pub fn filter(&self) -> ScaleFilter {
+        self.frame_map().filter
+    }
+}
@@ -0,0 +1,145 @@
+use std::sync::Arc;
+
+use crate::{
+    capture_error::CaptureError,
+    convert::{nv12_to_bgra, yuy2_to_bgra},
+    devices::{Frame, camera::Output},
+    i_capture::ICapture,
+    transform::{ScaleFilter, scale_frame, unpad},
+};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// # Thumbnail
+///
+/// A small BGRA image of a frame, made with generate_thumbnail or Thumbnail::from_capture.
+pub struct Thumbnail {
+    /// The tightly packed BGRA pixels, width * height * 4 bytes.
+    pub data: Vec<u8>,
+
+    pub width: u32,
+
+    pub height: u32,
+}
+
+impl Thumbnail {
+    /// # From Capture
+    ///
+    /// Starts the capture, takes its first frame and stops it again, returning the thumbnail of the frame.
+    ///
+    /// The capture must be stopped. The frame is awaited with the frame timeout of the capture (see ICapture::set_frame_timeout),
+    /// an error of the capture loop is returned as is and a capture that ends before it delivers a frame returns CaptureError::Other.
+    ///
+    /// ```rs
+    /// let thumbnail = Thumbnail::from_capture(camera, 320, 240).await?;
+    /// ```
+    pub async fn from_capture<S: ICapture<CaptureOutput = Frame> + ?Sized + 'static>(
+        source: Arc<S>,
+        thumb_width: u32,
+        thumb_height: u32,
+    ) -> Result<Thumbnail, CaptureError> {
+        let format = source.get_output_format();
+        let size = source.get_dimensions()?;
+
+        let capture = source.clone().start_capturing();
+        tokio::pin!(capture);
+
+        //a capture that fails to start or ends on its own never delivers the frame
+        let frame = tokio::select! {
+            result = &mut capture => {
+                result?;
+
+                return Err(CaptureError::Other(
+                    "the capture stopped before a frame was captured".into(),
+                ));
+            }
+            frame = source.recv_frame() => frame,
+        };
+
+        let _ = source.clone().stop_capturing().await;
+        capture.await?;
+
+        let frame = frame?.ok_or_else(|| {
+            CaptureError::Other("the capture stopped before a frame was captured".into())
+        })?;
+
+        //padded rows are packed first, MJPEG has no rows
+        let data = unpad(frame.data, frame.stride, format.stride(size.width));
+
+        Ok(Thumbnail {
+            data: generate_thumbnail(
+                &data,
+                size.width,
+                size.height,
+                format,
+                thumb_width,
+                thumb_height,
+            )?,
+            width: thumb_width,
+            height: thumb_height,
+        })
+    }
+}
+
+/// # Generate Thumbnail
+///
+/// Downsamples a tightly packed frame to thumb_width by thumb_height with bilinear interpolation, returning the BGRA pixels of the thumbnail.
+///
+/// The frame is stretched to the size, pick a size with the aspect ratio of the frame to keep it. NV12 frames are scaled before they are converted,
+/// so only the pixels of the thumbnail are converted, YUY2 frames are converted first. MJPEG frames are decoded with the jpeg feature, their own size is used.
+///
+/// Returns CaptureError::UnsupportedFormat for HDR and audio frames (convert::hdr_to_bgra makes an SDR frame of HDR frames)
+/// and CaptureError::Other when a size is 0 or the data is too short for the size.
+pub fn generate_thumbnail(
+    frame: &[u8],
+    width: u32,
+    height: u32,
+    format: Output,
+    thumb_width: u32,
+    thumb_height: u32,
+) -> Result<Vec<u8>, CaptureError> {
+    let scale = |bgra: &[u8], width: u32, height: u32| {
+        scale_frame(
+            bgra,
+            width,
+            height,
+            thumb_width,
+            thumb_height,
+            Output::RGB32,
+            ScaleFilter::Bilinear,
+        )
+    };
+
+    match format {
+        Output::RGB32 => scale(frame, width, height),
+        Output::NV12 => {
+            let thumb = scale_frame(
+                frame,
+                width,
+                height,
+                thumb_width,
+                thumb_height,
+                Output::NV12,
+                ScaleFilter::Bilinear,
+            )?;
+
+            nv12_to_bgra(&thumb, thumb_width, thumb_height)
+        }
+        Output::YUY2 => scale(&yuy2_to_bgra(frame, width, height)?, width, height),
+        #[cfg(feature = "jpeg")]
+        Output::MJPEG => {
+            let image =
+                turbojpeg::decompress(frame, turbojpeg::PixelFormat::BGRA).map_err(|e| {
+                    CaptureError::Other(format!("the MJPEG frame could not be decoded: {e}"))
+                })?;
+
+            scale(&image.pixels, image.width as u32, image.height as u32)
+        }
+        #[cfg(not(feature = "jpeg"))]
+        Output::MJPEG => Err(CaptureError::UnsupportedFormat(
+            "thumbnails of MJPEG frames need the jpeg feature".into(),
+        )),
+        Output::RGB10A2 | Output::RGBA16F | Output::PCM => Err(CaptureError::UnsupportedFormat(
+            format!("thumbnails of {format:?} frames"),
+        )),
+    }
+}
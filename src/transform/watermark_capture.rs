@@ -0,0 +1,169 @@
+use std::sync::{Arc, RwLock};
+
+use crate::{
+    capture_error::CaptureError,
+    convert::check_size,
+    devices::{Dimensions, Frame, camera::Output},
+    i_capture::ICapture,
+    map_capture::{FrameMap, MapCapture},
+    transform::{composite_overlay, unpad},
+};
+
+#[derive(Clone, Debug, PartialEq)]
+/// # Watermark
+///
+/// The image a WatermarkCapture blends onto every frame, in the format of the frames (BGRA for RGB32 sources, NV12 for NV12 sources).
+///
+/// ```rs
+/// let logo = Watermark::new(bgra, 128, 64).at(16, 16).alpha(0.8);
+/// ```
+pub struct Watermark {
+    /// The tightly packed pixels of the image.
+    pub data: Vec<u8>,
+
+    pub width: u32,
+
+    pub height: u32,
+
+    /// The left edge of the image on the frame.
+    pub x: u32,
+
+    /// The top edge of the image on the frame.
+    pub y: u32,
+
+    /// How opaque the image is, from 0.0 to 1.0, see composite_overlay.
+    pub alpha: f32,
+}
+
+impl Watermark {
+    /// # New
+    ///
+    /// The image at the top left corner of the frame, fully opaque.
+    pub fn new(data: Vec<u8>, width: u32, height: u32) -> Self {
+        Watermark {
+            data,
+            width,
+            height,
+            x: 0,
+            y: 0,
+            alpha: 1.0,
+        }
+    }
+
+    /// # At
+    ///
+    /// Places the top left corner of the image at x, y.
+    pub fn at(mut self, x: u32, y: u32) -> Self {
+        self.x = x;
+        self.y = y;
+        self
+    }
+
+    /// # Alpha
+    ///
+    /// Sets how opaque the image is, see alpha.
+    pub fn alpha(mut self, alpha: f32) -> Self {
+        self.alpha = alpha;
+        self
+    }
+}
+
+/// # Watermark Capture
+///
+/// Wraps any capture that delivers RGB32 or NV12 frames and blends a Watermark onto every frame with composite_overlay.
+///
+/// ```rs
+/// let watermarked = WatermarkCapture::new(monitor, Watermark::new(logo, 128, 64).at(16, 16));
+///
+/// tokio::spawn(watermarked.clone().start_capturing());
+///
+/// while let Some(frame) = watermarked.recv_frame().await? {
+///     encoder.send(frame).await?;
+/// }
+/// ```
+///
+/// Padded frames are packed before the watermark is blended, the frames passed on are tightly packed. A watermark beyond the edges
+/// of the frame is cut off.
+pub type WatermarkCapture<S> = MapCapture<S, RwLock<Watermark>>;
+
+// blends the watermark onto a frame of the source, None when the frame does not match the size of the source
+fn stamp(
+    watermark: &Watermark,
+    mut frame: Frame,
+    &Dimensions { width, height }: &Dimensions,
+    format: Output,
+) -> Option<Frame> {
+    let row_len = format.stride(width);
+    frame.data = unpad(std::mem::take(&mut frame.data), frame.stride, row_len);
+    frame.stride = row_len;
+
+    check_size(format, &frame.data, width, height)
+        .inspect_err(|_e| {
+            trace_event!(warn, error = %_e, "skipping a frame that could not be watermarked");
+        })
+        .ok()?;
+
+    //a watermark that does not fit its size leaves the frames as they are
+    if let Err(_e) = composite_overlay(
+        &mut frame.data,
+        width,
+        height,
+        &watermark.data,
+        watermark.width,
+        watermark.height,
+        watermark.x,
+        watermark.y,
+        watermark.alpha,
+        format,
+    ) {
+        trace_event!(warn, error = %_e, "passing a frame on without the watermark");
+    }
+
+    Some(frame)
+}
+
+impl FrameMap for RwLock<Watermark> {
+    type Output = Frame;
+
+    fn map(&self, frame: Frame, size: &Dimensions, format: Output) -> Option<Frame> {
+        stamp(&self.read().unwrap(), frame, size, format)
+    }
+
+    // the source must deliver RGB32 or NV12 frames
+    fn check(&self, format: Output) -> Result<(), CaptureError> {
+        if !matches!(format, Output::RGB32 | Output::NV12) {
+            return Err(CaptureError::UnsupportedFormat(format!(
+                "watermarking {format:?} frames"
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+impl<S: ICapture<CaptureOutput = Frame> + ?Sized + 'static> MapCapture<S, RwLock<Watermark>> {
+    /// # New
+    ///
+    /// Wraps the source, the watermark is blended onto every frame.
+    ///
+    /// Capturing is started and stopped through the wrapper, do not start the source yourself.
+    pub fn new(source: Arc<S>, watermark: Watermark) -> Arc<Self> {
+        Self::with_map(source, RwLock::new(watermark))
+    }
+
+    /// # Set Watermark
+    ///
+    /// Replaces the watermark, from the next frame on. A new image every second can stamp a timestamp onto the frames.
+    ///
+    /// The image must be in the format of the frames of the source, frames are passed on without a watermark when it does not fit its size.
+    pub fn set_watermark(&self, watermark: Watermark) {
+        *self.frame_map().write().unwrap() = watermark;
+    }
+
+    /// # Watermark
+    ///
+    /// The image blended onto every frame.
+    pub fn watermark(&self) -> Watermark {
+        self.frame_map().read().unwrap().clone()
+    }
+}